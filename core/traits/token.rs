@@ -1,4 +1,4 @@
-use crate::types::{Address, Result, Error};
+use crate::types::{decode_lock_event, Address, ChainType, Result, Error};
 
 /// 统一的代币操作接口
 /// 所有链上实现都必须实现这个 trait
@@ -27,6 +27,19 @@ pub trait CrossChainOperations {
     /// 从跨链桥接收资产
     fn receive_from_bridge(&self, to: Address, amount: u64, source_chain: u32) -> Result<()>;
     
-    /// 验证跨链消息
-    fn verify_cross_chain_message(&self, message: &[u8], signature: &[u8]) -> Result<bool>;
+    /// 本实现所在的链
+    fn chain(&self) -> ChainType;
+    
+    /// 验证中继者对消息字节的签名
+    fn verify_signature(&self, message: &[u8], signature: &[u8]) -> Result<bool>;
+    
+    /// 验证跨链消息: `message` 须为 `encode_lock_event` 编码的锁定事件, 目标为本链, 且签名有效。
+    /// 消息格式不正确时返回错误, 目标是其他链时返回 `false`
+    fn verify_cross_chain_message(&self, message: &[u8], signature: &[u8]) -> Result<bool> {
+        let event = decode_lock_event(message)?;
+        if event.target_chain != self.chain().chain_id() {
+            return Ok(false);
+        }
+        self.verify_signature(message, signature)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::fmt;
+use super::{Address, ChainType, Error, Result};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+impl Address {
+    /// 由原始字节构造地址, 长度必须等于 `chain.address_byte_len()`
+    pub fn from_bytes(chain: ChainType, bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != chain.address_byte_len() {
+            return Err(Error::InvalidAddress);
+        }
+        let mut array = [0u8; 32];
+        array.copy_from_slice(bytes);
+        Ok(match chain {
+            ChainType::Solana => Address::Solana(array),
+            ChainType::Aptos => Address::Aptos(array),
+            ChainType::Sui => Address::Sui(array),
+        })
+    }
+
+    /// 按链的格式解析地址并规范化
+    ///
+    /// Solana 为 base58 编码的 32 字节公钥; Aptos 与 Sui 为十六进制 (`0x` 前缀可省略, 不区分大小写),
+    /// 不足 64 位的短地址 (如 Aptos 的 `0x1`) 左侧补零。同一账户的不同写法解析为相同的字节,
+    /// 因此可以直接比较或作为跨链映射的键。
+    pub fn parse(chain: ChainType, s: &str) -> Result<Self> {
+        let bytes = match chain {
+            ChainType::Solana => base58_decode(s)?,
+            ChainType::Aptos | ChainType::Sui => parse_hex(s, chain.address_byte_len())?,
+        };
+        Self::from_bytes(chain, &bytes)
+    }
+}
+
+/// 各链的规范写法: Solana 为 base58; Aptos 按 AIP-40, `0x0`-`0xf` 的特殊地址用短格式,
+/// 其余为完整的 64 位十六进制; Sui 总是完整的 64 位十六进制
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Address::Solana(bytes) => write!(f, "{}", base58_encode(bytes)),
+            Address::Aptos(bytes) if bytes[..31].iter().all(|b| *b == 0) && bytes[31] < 16 => {
+                write!(f, "0x{:x}", bytes[31])
+            }
+            Address::Aptos(bytes) | Address::Sui(bytes) => write!(f, "0x{}", to_hex(bytes)),
+        }
+    }
+}
+
+fn parse_hex(s: &str, len: usize) -> Result<Vec<u8>> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    if digits.is_empty() || digits.len() > len * 2 || !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::InvalidAddress);
+    }
+    let padded = format!("{:0>width$}", digits, width = len * 2);
+    (0..len)
+        .map(|i| u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).map_err(|_| Error::InvalidAddress))
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn base58_decode(s: &str) -> Result<Vec<u8>> {
+    if s.is_empty() {
+        return Err(Error::InvalidAddress);
+    }
+    // 大端序字节, 逐位乘 58 累加
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c).ok_or(Error::InvalidAddress)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    // 每个前导 '1' 表示一个前导零字节
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    let mut decoded = vec![0u8; zeros];
+    decoded.extend(bytes.into_iter().skip_while(|b| *b == 0));
+    Ok(decoded)
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in bytes {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut().rev() {
+            carry += *digit as u32 * 256;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.insert(0, (carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    std::iter::repeat_n('1', zeros)
+        .chain(digits.into_iter().map(|d| BASE58_ALPHABET[d as usize] as char))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one() -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[31] = 1;
+        bytes
+    }
+
+    #[test]
+    fn test_short_aptos_addresses_are_left_padded() {
+        let framework = Address::parse(ChainType::Aptos, "0x1").unwrap();
+        assert_eq!(framework, Address::Aptos(one()));
+        assert_eq!(Address::parse(ChainType::Aptos, &format!("0x{}1", "0".repeat(63))).unwrap(), framework);
+        assert_eq!(framework.to_string(), "0x1");
+
+        // 奇数位的短地址同样补零, 非特殊地址输出完整格式
+        let short = Address::parse(ChainType::Aptos, "0xABC").unwrap();
+        assert_eq!(short.to_bytes()[30..], [0x0a, 0xbc]);
+        assert_eq!(short.to_string(), format!("0x{}abc", "0".repeat(61)));
+        assert_eq!(Address::parse(ChainType::Aptos, &short.to_string()).unwrap(), short);
+    }
+
+    #[test]
+    fn test_sui_addresses_use_the_long_form() {
+        let sui = Address::parse(ChainType::Sui, "0x2").unwrap();
+        assert_eq!(sui.to_string(), format!("0x{}2", "0".repeat(63)));
+        assert_ne!(sui, Address::parse(ChainType::Aptos, "0x2").unwrap());
+    }
+
+    #[test]
+    fn test_invalid_addresses_are_rejected() {
+        for s in ["", "0x", "0xg1", &format!("0x1{}", "0".repeat(64))] {
+            assert!(Address::parse(ChainType::Aptos, s).is_err(), "{}", s);
+        }
+        assert!(Address::parse(ChainType::Solana, "0OIl").is_err());
+        // 解码后不是 32 字节
+        assert!(Address::parse(ChainType::Solana, "3mJr7AoUXx2Wqd").is_err());
+        assert!(Address::from_bytes(ChainType::Sui, &[1u8; 20]).is_err());
+    }
+
+    #[test]
+    fn test_solana_base58_round_trips() {
+        let system = Address::parse(ChainType::Solana, "11111111111111111111111111111111").unwrap();
+        assert_eq!(system, Address::Solana([0u8; 32]));
+        assert_eq!(system.to_string(), "11111111111111111111111111111111");
+
+        let token_program = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+        let address = Address::parse(ChainType::Solana, token_program).unwrap();
+        assert_eq!(address.to_bytes()[0], 0x06);
+        assert_eq!(address.to_string(), token_program);
+    }
+}
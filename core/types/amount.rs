@@ -0,0 +1,112 @@
+use std::fmt;
+use super::{Error, Result};
+
+/// u64 能表示的最大精度 (10^19 < 2^64 < 10^20)
+pub const MAX_DECIMALS: u8 = 19;
+
+/// 人类可读数量转换为链上最小单位, 例如 `to_base_units(1.5, 9)` 为 `1_500_000_000`
+///
+/// 超出精度的小数部分四舍五入; 负数、NaN、无穷大和超出 u64 范围的结果返回 `Error::InvalidAmount`。
+/// f64 只有 53 位有效数字, 需要精确到最小单位的大额数量应直接使用 `TokenAmount::from_base_units`。
+pub fn to_base_units(amount: f64, decimals: u8) -> Result<u64> {
+    let scale = scale(decimals)?;
+    if !amount.is_finite() || amount < 0.0 {
+        return Err(Error::InvalidAmount(format!("{} is not a valid token amount", amount)));
+    }
+    let base = (amount * scale as f64).round();
+    // u64::MAX as f64 向上取整为 2^64, 因此用 >= 判断
+    if base >= u64::MAX as f64 {
+        return Err(Error::InvalidAmount(format!(
+            "{} with {} decimals overflows u64", amount, decimals
+        )));
+    }
+    Ok(base as u64)
+}
+
+/// 链上最小单位转换为人类可读数量, 例如 `from_base_units(1_500_000_000, 9)` 为 `1.5`
+///
+/// 结果仅用于展示; 超过 2^53 的数量会损失精度。
+pub fn from_base_units(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+/// 带精度的代币数量, 内部以最小单位保存
+///
+/// 构造指令时使用 `base_units()`, 展示时使用 `Display` (按精度输出, 不经过浮点数)。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenAmount {
+    base_units: u64,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub fn from_base_units(base_units: u64, decimals: u8) -> Result<Self> {
+        scale(decimals)?;
+        Ok(Self { base_units, decimals })
+    }
+
+    pub fn from_ui(amount: f64, decimals: u8) -> Result<Self> {
+        Ok(Self { base_units: to_base_units(amount, decimals)?, decimals })
+    }
+
+    pub fn base_units(&self) -> u64 {
+        self.base_units
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    pub fn to_ui(&self) -> f64 {
+        from_base_units(self.base_units, self.decimals)
+    }
+
+    /// 精度不同的数量不能相加
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        self.same_decimals(&other)?;
+        let base_units = self.base_units.checked_add(other.base_units)
+            .ok_or_else(|| Error::InvalidAmount("token amount overflows u64".to_string()))?;
+        Ok(Self { base_units, ..self })
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self> {
+        self.same_decimals(&other)?;
+        let base_units = self.base_units.checked_sub(other.base_units)
+            .ok_or(Error::InsufficientBalance)?;
+        Ok(Self { base_units, ..self })
+    }
+
+    fn same_decimals(&self, other: &Self) -> Result<()> {
+        if self.decimals != other.decimals {
+            return Err(Error::InvalidAmount(format!(
+                "cannot combine amounts with {} and {} decimals", self.decimals, other.decimals
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.decimals == 0 {
+            return write!(f, "{}", self.base_units);
+        }
+        let scale = 10u64.pow(self.decimals as u32);
+        let fraction = format!("{:0width$}", self.base_units % scale, width = self.decimals as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}", self.base_units / scale)
+        } else {
+            write!(f, "{}.{}", self.base_units / scale, fraction)
+        }
+    }
+}
+
+fn scale(decimals: u8) -> Result<u64> {
+    if decimals > MAX_DECIMALS {
+        return Err(Error::InvalidAmount(format!(
+            "{} decimals exceeds the maximum of {}", decimals, MAX_DECIMALS
+        )));
+    }
+    Ok(10u64.pow(decimals as u32))
+}
@@ -0,0 +1,119 @@
+use super::{Address, ChainType, Error, Result};
+
+/// 锁定事件编码的版本号, 布局变化时递增
+pub const LOCK_EVENT_VERSION: u8 = 1;
+
+/// 编码后的锁定事件长度
+pub const LOCK_EVENT_LEN: usize = 57;
+
+/// 跨链桥锁定资产时发出的事件 (`CrossChainLockEvent`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockEvent {
+    pub from: Address,
+    pub amount: u64,
+    pub target_chain: u32,
+    pub nonce: u64,
+}
+
+/// 锁定事件的规范编码, Solana、Aptos、Sui 上产生的字节完全相同, 目标链据此验证锁定内容
+///
+/// 布局 (整数均为大端序, 共 57 字节):
+///
+/// | 偏移 | 长度 | 字段 |
+/// |------|------|------|
+/// | 0    | 1    | 版本号 `LOCK_EVENT_VERSION` |
+/// | 1    | 4    | 来源链 id (`from` 所在链的 `ChainType::chain_id`) |
+/// | 5    | 32   | `from` 地址的字节 |
+/// | 37   | 8    | `amount` |
+/// | 45   | 4    | `target_chain` |
+/// | 49   | 8    | `nonce` |
+pub fn encode_lock_event(from: Address, amount: u64, target_chain: u32, nonce: u64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(LOCK_EVENT_LEN);
+    bytes.push(LOCK_EVENT_VERSION);
+    bytes.extend_from_slice(&from.chain_type().chain_id().to_be_bytes());
+    bytes.extend_from_slice(&from.to_bytes());
+    bytes.extend_from_slice(&amount.to_be_bytes());
+    bytes.extend_from_slice(&target_chain.to_be_bytes());
+    bytes.extend_from_slice(&nonce.to_be_bytes());
+    bytes
+}
+
+/// `encode_lock_event` 的逆过程; 长度、版本号或来源链不正确时返回 `Error::CrossChainError`
+pub fn decode_lock_event(bytes: &[u8]) -> Result<LockEvent> {
+    if bytes.len() != LOCK_EVENT_LEN {
+        return Err(Error::CrossChainError(format!(
+            "Lock event must be {} bytes, got {}", LOCK_EVENT_LEN, bytes.len()
+        )));
+    }
+    if bytes[0] != LOCK_EVENT_VERSION {
+        return Err(Error::CrossChainError(format!("Unsupported lock event version {}", bytes[0])));
+    }
+    let source_chain = u32::from_be_bytes(bytes[1..5].try_into().unwrap());
+    let chain = ChainType::from_chain_id(source_chain)
+        .ok_or_else(|| Error::CrossChainError(format!("Unknown source chain {}", source_chain)))?;
+    Ok(LockEvent {
+        from: Address::from_bytes(chain, &bytes[5..37])?,
+        amount: u64::from_be_bytes(bytes[37..45].try_into().unwrap()),
+        target_chain: u32::from_be_bytes(bytes[45..49].try_into().unwrap()),
+        nonce: u64::from_be_bytes(bytes[49..57].try_into().unwrap()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // 测试向量: 改变这些字节意味着各链之间的编码不再兼容
+    #[test]
+    fn test_lock_event_vectors() {
+        let from = Address::parse(ChainType::Aptos, "0x1").unwrap();
+        let bytes = encode_lock_event(from, 1_000_000, 3, 7);
+        assert_eq!(
+            hex(&bytes),
+            "01\
+             00000002\
+             0000000000000000000000000000000000000000000000000000000000000001\
+             00000000000f4240\
+             00000003\
+             0000000000000007"
+        );
+
+        let from = Address::Solana([0xab; 32]);
+        let bytes = encode_lock_event(from, u64::MAX, 2, 0x0102030405060708);
+        assert_eq!(
+            hex(&bytes),
+            "01\
+             00000001\
+             abababababababababababababababababababababababababababababababab\
+             ffffffffffffffff\
+             00000002\
+             0102030405060708"
+        );
+    }
+
+    #[test]
+    fn test_lock_event_round_trips() {
+        let from = Address::parse(ChainType::Sui, "0x2").unwrap();
+        let bytes = encode_lock_event(from.clone(), 42, ChainType::Solana.chain_id(), 9);
+        assert_eq!(bytes.len(), LOCK_EVENT_LEN);
+        let event = decode_lock_event(&bytes).unwrap();
+        assert_eq!(event, LockEvent { from, amount: 42, target_chain: 1, nonce: 9 });
+    }
+
+    #[test]
+    fn test_malformed_lock_events_are_rejected() {
+        let bytes = encode_lock_event(Address::Aptos([1; 32]), 1, 1, 1);
+        assert!(decode_lock_event(&bytes[..56]).is_err());
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 2;
+        assert!(decode_lock_event(&wrong_version).is_err());
+        // 来源链 id 为 9
+        let mut unknown_chain = bytes;
+        unknown_chain[4] = 9;
+        assert!(decode_lock_event(&unknown_chain).is_err());
+    }
+}
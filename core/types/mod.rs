@@ -1,8 +1,17 @@
 use std::fmt;
 
+mod address;
+mod amount;
+mod message;
+pub use amount::{to_base_units, from_base_units, TokenAmount, MAX_DECIMALS};
+pub use message::{encode_lock_event, decode_lock_event, LockEvent, LOCK_EVENT_LEN, LOCK_EVENT_VERSION};
+
 /// 统一的地址类型
 /// 可以表示 Solana、Aptos、Sui 的地址
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// 从字节或字符串构造时使用 `Address::from_bytes` / `Address::parse`, 按各链的规则校验并规范化,
+/// 同一账户总是得到相同的字节
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Address {
     Solana([u8; 32]),
     Aptos([u8; 32]),
@@ -41,6 +50,23 @@ impl ChainType {
             ChainType::Sui => 3,
         }
     }
+
+    /// `chain_id` 的逆过程
+    pub fn from_chain_id(id: u32) -> Option<ChainType> {
+        match id {
+            1 => Some(ChainType::Solana),
+            2 => Some(ChainType::Aptos),
+            3 => Some(ChainType::Sui),
+            _ => None,
+        }
+    }
+
+    /// 地址的字节数
+    pub fn address_byte_len(&self) -> usize {
+        match self {
+            ChainType::Solana | ChainType::Aptos | ChainType::Sui => 32,
+        }
+    }
 }
 
 /// 统一的错误类型
@@ -49,6 +75,7 @@ pub enum Error {
     InvalidAddress,
     InsufficientBalance,
     Unauthorized,
+    InvalidAmount(String),
     CrossChainError(String),
     ChainSpecific(String),
 }
@@ -59,6 +86,7 @@ impl fmt::Display for Error {
             Error::InvalidAddress => write!(f, "Invalid address"),
             Error::InsufficientBalance => write!(f, "Insufficient balance"),
             Error::Unauthorized => write!(f, "Unauthorized"),
+            Error::InvalidAmount(msg) => write!(f, "Invalid amount: {}", msg),
             Error::CrossChainError(msg) => write!(f, "Cross-chain error: {}", msg),
             Error::ChainSpecific(msg) => write!(f, "Chain-specific error: {}", msg),
         }
@@ -1,2 +1,649 @@
 pub mod solana;
-pub mod move_gen;
\ No newline at end of file
+pub mod move_gen;
+pub mod sui_gen;
+mod writer;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use crate::{Bridge, Contract, Function, Parameter, StateVariable, Statement, Expression, LValue, BinaryOp, Type, UnaryOp};
+
+/// How generated code handles integer `+`, `-`, `*` and `/` that leave the type's range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArithmeticMode {
+    /// Fail the transaction with an `Overflow`, `Underflow` or `DivisionByZero`
+    /// error (Move aborts natively)
+    #[default]
+    Checked,
+    /// Wrap around modulo 2^bits; Move has no wrapping arithmetic, so only Solana supports it
+    Wrapping,
+    /// Emit the plain operator and keep the target's default behaviour
+    Unchecked,
+}
+
+impl FromStr for ArithmeticMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "checked" => Ok(ArithmeticMode::Checked),
+            "wrapping" => Ok(ArithmeticMode::Wrapping),
+            "unchecked" => Ok(ArithmeticMode::Unchecked),
+            _ => Err(format!("Unknown arithmetic mode '{}', expected checked, wrapping or unchecked", s)),
+        }
+    }
+}
+
+impl fmt::Display for ArithmeticMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArithmeticMode::Checked => write!(f, "checked"),
+            ArithmeticMode::Wrapping => write!(f, "wrapping"),
+            ArithmeticMode::Unchecked => write!(f, "unchecked"),
+        }
+    }
+}
+
+/// Calls `f` on every statement in `statements`, including those in nested blocks
+pub(crate) fn visit_statements(statements: &[Statement], f: &mut dyn FnMut(&Statement)) {
+    for stmt in statements {
+        f(stmt);
+        match stmt {
+            Statement::If { then_block, else_block, .. } => {
+                visit_statements(then_block, f);
+                if let Some(else_stmts) = else_block {
+                    visit_statements(else_stmts, f);
+                }
+            }
+            Statement::For { init, update, body, .. } => {
+                visit_statements(std::slice::from_ref(init.as_ref()), f);
+                visit_statements(body, f);
+                visit_statements(std::slice::from_ref(update.as_ref()), f);
+            }
+            Statement::While { body, .. } | Statement::ForEach { body, .. } | Statement::Block(body) => {
+                visit_statements(body, f)
+            }
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    visit_statements(&arm.body, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Calls `f` on every expression in `statements`, including nested subexpressions
+pub(crate) fn visit_expressions(statements: &[Statement], f: &mut dyn FnMut(&Expression)) {
+    for stmt in statements {
+        match stmt {
+            Statement::Let { value, .. } | Statement::LetTuple { value, .. } => visit_expression(value, f),
+            Statement::Assign { target, value } => {
+                visit_lvalue(target, f);
+                visit_expression(value, f);
+            }
+            Statement::If { condition, then_block, else_block } => {
+                visit_expression(condition, f);
+                visit_expressions(then_block, f);
+                if let Some(else_stmts) = else_block {
+                    visit_expressions(else_stmts, f);
+                }
+            }
+            Statement::While { condition, body } => {
+                visit_expression(condition, f);
+                visit_expressions(body, f);
+            }
+            Statement::For { init, condition, update, body } => {
+                visit_expressions(std::slice::from_ref(init.as_ref()), f);
+                visit_expression(condition, f);
+                visit_expressions(std::slice::from_ref(update.as_ref()), f);
+                visit_expressions(body, f);
+            }
+            Statement::ForEach { iterable, body, .. } => {
+                visit_expression(iterable, f);
+                visit_expressions(body, f);
+            }
+            Statement::Require { condition, .. } | Statement::Assert { condition, .. } => {
+                visit_expression(condition, f);
+            }
+            Statement::Emit { args, .. } => {
+                for arg in args {
+                    visit_expression(arg, f);
+                }
+            }
+            Statement::Return { value: Some(value) } | Statement::Expression(value) => visit_expression(value, f),
+            Statement::Match { value, arms } => {
+                visit_expression(value, f);
+                for arm in arms {
+                    visit_expressions(&arm.body, f);
+                }
+            }
+            Statement::Block(body) => visit_expressions(body, f),
+            Statement::Return { value: None } | Statement::Break | Statement::Continue | Statement::Placeholder => {}
+        }
+    }
+}
+
+/// The expression that reads what `lvalue` writes
+pub(crate) fn lvalue_to_expression(lvalue: &LValue) -> Expression {
+    match lvalue {
+        LValue::Identifier(name) => Expression::Identifier(name.clone()),
+        LValue::Index { array, index } => Expression::Index {
+            array: Box::new(lvalue_to_expression(array)),
+            index: index.clone(),
+        },
+        LValue::Field { object, field } => Expression::Field {
+            object: Box::new(lvalue_to_expression(object)),
+            field: field.clone(),
+        },
+    }
+}
+
+fn visit_lvalue(lvalue: &LValue, f: &mut dyn FnMut(&Expression)) {
+    match lvalue {
+        LValue::Identifier(_) => {}
+        LValue::Index { array, index } => {
+            visit_lvalue(array, f);
+            visit_expression(index, f);
+        }
+        LValue::Field { object, .. } => visit_lvalue(object, f),
+    }
+}
+
+pub(crate) fn visit_expression(expr: &Expression, f: &mut dyn FnMut(&Expression)) {
+    f(expr);
+    match expr {
+        Expression::Binary { left, right, .. } => {
+            visit_expression(left, f);
+            visit_expression(right, f);
+        }
+        Expression::Unary { expr, .. } | Expression::Cast { expr, .. } | Expression::OptionSome(expr) => visit_expression(expr, f),
+        Expression::Ternary { condition, then_expr, else_expr } => {
+            visit_expression(condition, f);
+            visit_expression(then_expr, f);
+            visit_expression(else_expr, f);
+        }
+        Expression::Call { func, args } => {
+            visit_expression(func, f);
+            for arg in args {
+                visit_expression(arg, f);
+            }
+        }
+        Expression::MethodCall { object, args, .. } => {
+            visit_expression(object, f);
+            for arg in args {
+                visit_expression(arg, f);
+            }
+        }
+        Expression::Index { array, index } => {
+            visit_expression(array, f);
+            visit_expression(index, f);
+        }
+        Expression::Field { object, .. } => visit_expression(object, f),
+        Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => {
+            for item in items {
+                visit_expression(item, f);
+            }
+        }
+        Expression::StructLiteral { fields, .. } => {
+            for value in fields.values() {
+                visit_expression(value, f);
+            }
+        }
+        Expression::Lambda { body, .. } => visit_expression(body, f),
+        _ => {}
+    }
+}
+
+/// Divisors a statement always evaluates that aren't literals, so generated code can
+/// check them for zero first. Nested blocks are left to their own statements, and
+/// operands that only sometimes run (ternary branches, the right side of `&&`/`||`)
+/// are skipped, as are divisors with calls, which the check would run twice.
+pub(crate) fn runtime_divisors(stmt: &Statement) -> Vec<&Expression> {
+    let mut divisors = Vec::new();
+    match stmt {
+        Statement::Let { value, .. } | Statement::LetTuple { value, .. } | Statement::Assign { value, .. } => {
+            collect_divisors(value, &mut divisors)
+        }
+        Statement::If { condition, .. } | Statement::Require { condition, .. } | Statement::Assert { condition, .. } => {
+            collect_divisors(condition, &mut divisors)
+        }
+        Statement::Emit { args, .. } => {
+            for arg in args {
+                collect_divisors(arg, &mut divisors);
+            }
+        }
+        Statement::Return { value: Some(value) } | Statement::Expression(value) | Statement::Match { value, .. } => {
+            collect_divisors(value, &mut divisors)
+        }
+        _ => {}
+    }
+    divisors
+}
+
+fn collect_divisors<'a>(expr: &'a Expression, divisors: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::Binary { op: BinaryOp::And | BinaryOp::Or, left, .. } => collect_divisors(left, divisors),
+        Expression::Binary { op, left, right } => {
+            collect_divisors(left, divisors);
+            collect_divisors(right, divisors);
+            if matches!(op, BinaryOp::Div | BinaryOp::Mod)
+                && !matches!(**right, Expression::Number(_) | Expression::TypedNumber { .. })
+                && !contains_call(right)
+                && !divisors.contains(&&**right)
+            {
+                divisors.push(right);
+            }
+        }
+        Expression::Ternary { condition, .. } => collect_divisors(condition, divisors),
+        Expression::Unary { expr, .. } | Expression::Cast { expr, .. } | Expression::OptionSome(expr) => collect_divisors(expr, divisors),
+        Expression::Index { array, index } => {
+            collect_divisors(array, divisors);
+            collect_divisors(index, divisors);
+        }
+        Expression::Field { object, .. } => collect_divisors(object, divisors),
+        Expression::Call { args, .. } | Expression::MethodCall { args, .. } => {
+            for arg in args {
+                collect_divisors(arg, divisors);
+            }
+        }
+        Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => {
+            for item in items {
+                collect_divisors(item, divisors);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn contains_call(expr: &Expression) -> bool {
+    let mut found = false;
+    visit_expression(expr, &mut |e| {
+        found |= matches!(e, Expression::Call { .. } | Expression::MethodCall { .. });
+    });
+    found
+}
+
+/// Whether `func` is the contract's constructor, see [`Contract::constructor`]
+pub(crate) fn is_constructor(contract: &Contract, func: &Function) -> bool {
+    contract.constructor().is_some_and(|constructor| constructor.name == func.name)
+}
+
+/// Names of the other contracts a function body calls into, in a stable order
+pub(crate) fn called_contracts(statements: &[Statement]) -> Vec<String> {
+    let mut contracts = Vec::new();
+    visit_expressions(statements, &mut |expr| {
+        if let Expression::MethodCall { object, .. } = expr {
+            if expr.is_value_method() {
+                return;
+            }
+            if let Expression::Identifier(name) = object.as_ref() {
+                if !contracts.contains(name) {
+                    contracts.push(name.clone());
+                }
+            }
+        }
+    });
+    contracts
+}
+
+/// Arguments of every call to the bridge builtin `name` (`Bridge::LOCK` or `Bridge::RECEIVE`) in a function body
+pub(crate) fn bridge_calls(statements: &[Statement], name: &str) -> Vec<Vec<Expression>> {
+    let mut calls = Vec::new();
+    visit_expressions(statements, &mut |expr| {
+        if let Expression::Call { func, args } = expr {
+            if matches!(func.as_ref(), Expression::Identifier(f) if f == name) {
+                calls.push(args.clone());
+            }
+        }
+    });
+    calls
+}
+
+/// The error a `require` with `message` (or without one, for `None`) raises
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RequireError {
+    pub message: Option<String>,
+    pub name: String,
+    /// The name belongs to an error the generator always emits
+    pub builtin: bool,
+}
+
+/// One error per distinct `require` (or `assert`) message, in order of first use.
+///
+/// `name` builds an identifier from a message's words. A message identical to one
+/// of the `builtins` (name, message) reuses that error; other names that clash
+/// with a builtin or an earlier message get a numeric suffix. Requires without a
+/// message share `generic`.
+pub(crate) fn require_errors(
+    contract: &Contract,
+    builtins: &[(&str, &str)],
+    generic: &str,
+    name: impl Fn(&[&str]) -> String,
+) -> Vec<RequireError> {
+    let mut messages: Vec<Option<String>> = Vec::new();
+    for func in &contract.functions {
+        visit_statements(&func.body, &mut |stmt| {
+            if let Statement::Require { message, .. } | Statement::Assert { message, .. } = stmt {
+                if !messages.contains(message) {
+                    messages.push(message.clone());
+                }
+            }
+        });
+    }
+
+    let mut errors: Vec<RequireError> = Vec::new();
+    for message in messages {
+        let Some(text) = &message else {
+            errors.push(RequireError { message, name: generic.to_string(), builtin: false });
+            continue;
+        };
+        if let Some((builtin, _)) = builtins.iter().find(|(_, msg)| msg == text) {
+            errors.push(RequireError { message, name: builtin.to_string(), builtin: true });
+            continue;
+        }
+
+        let words: Vec<&str> = text.split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let base = name(&words);
+        let taken = |candidate: &str| builtins.iter().any(|(b, _)| *b == candidate)
+            || candidate == generic
+            || errors.iter().any(|e| e.name == candidate);
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while taken(&candidate) {
+            candidate = format!("{}{}", base, n);
+            n += 1;
+        }
+        errors.push(RequireError { message, name: candidate, builtin: false });
+    }
+    errors
+}
+
+/// How a generated failing-case test makes a `require` fail
+pub(crate) enum Violation {
+    /// Pass the value for the parameter at the index
+    Argument(usize, BreakingValue),
+    /// Sign with another account than the other tests
+    OtherSigner,
+}
+
+/// An argument that breaks a condition, for each test generator to write in its language
+pub(crate) enum BreakingValue {
+    Bool(bool),
+    Number(u128),
+    /// The largest value of the parameter's type
+    Max,
+}
+
+/// Arguments that break `condition`, for the shapes this can decide without
+/// knowing the state: a parameter (or `msg_value()`, the payable amount argument)
+/// compared with anything, a bool parameter, or `msg_sender()` required to equal some address
+pub(crate) fn require_violation(condition: &Expression, params: &[Parameter]) -> Option<Violation> {
+    let param = |expr: &Expression| match expr {
+        Expression::Identifier(name) => params.iter().position(|p| p.name == *name),
+        Expression::MsgValue => params.iter().position(|p| p.name == solana::MSG_VALUE),
+        _ => None,
+    };
+    let literal = |expr: &Expression| match expr {
+        Expression::Number(n) => Some(*n as u128),
+        Expression::TypedNumber { value, .. } => Some(*value),
+        _ => None,
+    };
+
+    match condition {
+        Expression::Identifier(_) => {
+            let index = param(condition)?;
+            (params[index].ty == Type::Bool).then_some(Violation::Argument(index, BreakingValue::Bool(false)))
+        }
+        Expression::Unary { op: UnaryOp::Not, expr } => {
+            let index = param(expr)?;
+            (params[index].ty == Type::Bool).then_some(Violation::Argument(index, BreakingValue::Bool(true)))
+        }
+        Expression::Binary { op: BinaryOp::And, left, right } => {
+            require_violation(left, params).or_else(|| require_violation(right, params))
+        }
+        Expression::Binary { op: BinaryOp::Eq, left, right }
+            if matches!(**left, Expression::MsgSender) || matches!(**right, Expression::MsgSender) =>
+        {
+            Some(Violation::OtherSigner)
+        }
+        Expression::Binary { op, left, right } => {
+            // With the parameter on the left
+            let (index, op, other) = match (param(left), param(right)) {
+                (Some(index), _) => (index, op.clone(), right),
+                (None, Some(index)) => (index, flip(op)?, left),
+                (None, None) => return None,
+            };
+            let ty = &params[index].ty;
+            if !is_unsigned(ty) {
+                return None;
+            }
+            let value = match (op, literal(other)) {
+                // Unsigned values are never below 0
+                (BinaryOp::Gt, _) => BreakingValue::Number(0),
+                (BinaryOp::Ge, Some(n)) if n > 0 => BreakingValue::Number(0),
+                (BinaryOp::Lt, _) => BreakingValue::Max,
+                (BinaryOp::Le, n) if n.is_none_or(|n| !is_max(n, ty)) => BreakingValue::Max,
+                (BinaryOp::Ne, Some(n)) => BreakingValue::Number(n),
+                (BinaryOp::Eq, Some(n)) => BreakingValue::Number(if n == 0 { 1 } else { 0 }),
+                _ => return None,
+            };
+            Some(Violation::Argument(index, value))
+        }
+        _ => None,
+    }
+}
+
+/// The comparison with its operands swapped: `a < b` is `b > a`
+fn flip(op: &BinaryOp) -> Option<BinaryOp> {
+    match op {
+        BinaryOp::Lt => Some(BinaryOp::Gt),
+        BinaryOp::Le => Some(BinaryOp::Ge),
+        BinaryOp::Gt => Some(BinaryOp::Lt),
+        BinaryOp::Ge => Some(BinaryOp::Le),
+        BinaryOp::Eq | BinaryOp::Ne => Some(op.clone()),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_unsigned(ty: &Type) -> bool {
+    matches!(ty, Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::U256)
+}
+
+/// The width of an integer type in bytes
+pub(crate) fn integer_bytes(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::U8 | Type::I8 => Some(1),
+        Type::U16 | Type::I16 => Some(2),
+        Type::U32 | Type::I32 => Some(4),
+        Type::U64 | Type::I64 => Some(8),
+        Type::U128 | Type::I128 => Some(16),
+        Type::U256 => Some(32),
+        _ => None,
+    }
+}
+
+/// Whether `n` is the largest value of `ty`
+fn is_max(n: u128, ty: &Type) -> bool {
+    match integer_bytes(ty).unwrap_or(8) * 8 {
+        128 => n == u128::MAX,
+        256 => false,
+        bits => n == (1 << bits) - 1,
+    }
+}
+
+/// How a function uses the contract's state, including through the helpers it calls
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct StateAccess {
+    pub reads: bool,
+    pub writes: bool,
+}
+
+/// Access of every function in `contract` to the state variables `tracked` selects,
+/// keyed by function name
+pub(crate) fn state_access(
+    contract: &Contract,
+    tracked: impl Fn(&StateVariable) -> bool,
+) -> HashMap<String, StateAccess> {
+    let state: HashSet<&str> = contract.state.iter()
+        .filter(|v| tracked(v))
+        .map(|v| v.name.as_str())
+        .collect();
+    
+    let mut access = HashMap::new();
+    let mut callees = HashMap::new();
+    for func in &contract.functions {
+        access.insert(func.name.clone(), direct_state_access(func, &state));
+        
+        let mut called = HashSet::new();
+        visit_expressions(&func.body, &mut |expr| {
+            if let Expression::Call { func, .. } = expr {
+                if let Expression::Identifier(name) = func.as_ref() {
+                    called.insert(name.clone());
+                }
+            }
+        });
+        callees.insert(func.name.clone(), called);
+    }
+    
+    // Propagate through internal calls until nothing changes
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (caller, called) in &callees {
+            let inherited = called.iter()
+                .filter_map(|name| access.get(name).copied())
+                .fold(access[caller], |acc, callee| StateAccess {
+                    reads: acc.reads || callee.reads,
+                    writes: acc.writes || callee.writes,
+                });
+            if inherited != access[caller] {
+                access.insert(caller.clone(), inherited);
+                changed = true;
+            }
+        }
+    }
+    
+    access
+}
+
+/// Access of `func`'s own statements to the state variables named in `state`,
+/// without the functions it calls
+pub(crate) fn direct_state_access(func: &Function, state: &HashSet<&str>) -> StateAccess {
+    let mut access = StateAccess::default();
+    let mut shadowed = func.params.iter().map(|p| p.name.clone()).collect();
+    collect_state_access(&func.body, state, &mut shadowed, &mut access);
+    access
+}
+
+/// Checks that `view` functions return a value and have no side effects: no state
+/// writes, directly or through the helpers they call, and no events
+pub(crate) fn check_view_functions(contract: &Contract) -> anyhow::Result<()> {
+    let access = state_access(contract, |_| true);
+    for func in contract.functions.iter().filter(|f| f.is_view) {
+        if func.return_type.is_none() {
+            anyhow::bail!("View function '{}' must return a value", func.name);
+        }
+        if access[&func.name].writes {
+            anyhow::bail!("View function '{}' modifies state", func.name);
+        }
+        let mut emits = false;
+        visit_statements(&func.body, &mut |stmt| emits |= matches!(stmt, Statement::Emit { .. }));
+        if emits {
+            anyhow::bail!("View function '{}' emits an event", func.name);
+        }
+        if [Bridge::LOCK, Bridge::RECEIVE].iter().any(|name| !bridge_calls(&func.body, name).is_empty()) {
+            anyhow::bail!("View function '{}' moves tokens through the bridge", func.name);
+        }
+    }
+    Ok(())
+}
+
+/// Walks statements in order so that locals shadow state variables from their `let` on
+fn collect_state_access(
+    statements: &[Statement],
+    state: &HashSet<&str>,
+    shadowed: &mut HashSet<String>,
+    access: &mut StateAccess,
+) {
+    let read = |expr: &Expression, shadowed: &HashSet<String>, access: &mut StateAccess| {
+        visit_expression(expr, &mut |e| {
+            if let Expression::Identifier(name) = e {
+                access.reads |= state.contains(name.as_str()) && !shadowed.contains(name);
+            }
+        });
+    };
+    
+    for stmt in statements {
+        match stmt {
+            Statement::Let { name, value, .. } => {
+                read(value, shadowed, access);
+                shadowed.insert(name.clone());
+            }
+            Statement::LetTuple { names, value } => {
+                read(value, shadowed, access);
+                shadowed.extend(names.iter().cloned());
+            }
+            Statement::Assign { target, value } => {
+                read(value, shadowed, access);
+                let mut root = target;
+                loop {
+                    match root {
+                        LValue::Identifier(name) => {
+                            access.writes |= state.contains(name.as_str()) && !shadowed.contains(name);
+                            break;
+                        }
+                        LValue::Index { array, index } => {
+                            read(index, shadowed, access);
+                            root = array;
+                        }
+                        LValue::Field { object, .. } => root = object,
+                    }
+                }
+            }
+            Statement::If { condition, then_block, else_block } => {
+                read(condition, shadowed, access);
+                collect_state_access(then_block, state, &mut shadowed.clone(), access);
+                if let Some(else_stmts) = else_block {
+                    collect_state_access(else_stmts, state, &mut shadowed.clone(), access);
+                }
+            }
+            Statement::While { condition, body } => {
+                read(condition, shadowed, access);
+                collect_state_access(body, state, &mut shadowed.clone(), access);
+            }
+            Statement::For { init, condition, update, body } => {
+                let mut scope = shadowed.clone();
+                collect_state_access(std::slice::from_ref(init.as_ref()), state, &mut scope, access);
+                read(condition, &scope, access);
+                collect_state_access(body, state, &mut scope.clone(), access);
+                collect_state_access(std::slice::from_ref(update.as_ref()), state, &mut scope, access);
+            }
+            Statement::ForEach { variable, iterable, body } => {
+                read(iterable, shadowed, access);
+                let mut scope = shadowed.clone();
+                scope.insert(variable.clone());
+                collect_state_access(body, state, &mut scope, access);
+            }
+            Statement::Require { condition, .. } | Statement::Assert { condition, .. } => {
+                read(condition, shadowed, access);
+            }
+            Statement::Emit { args, .. } => {
+                for arg in args {
+                    read(arg, shadowed, access);
+                }
+            }
+            Statement::Return { value: Some(value) } | Statement::Expression(value) => read(value, shadowed, access),
+            Statement::Match { value, arms } => {
+                read(value, shadowed, access);
+                for arm in arms {
+                    collect_state_access(&arm.body, state, &mut shadowed.clone(), access);
+                }
+            }
+            Statement::Block(body) => collect_state_access(body, state, &mut shadowed.clone(), access),
+            Statement::Return { value: None } | Statement::Break | Statement::Continue | Statement::Placeholder => {}
+        }
+    }
+}
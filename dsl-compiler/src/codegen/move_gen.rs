@@ -1,33 +1,684 @@
-use super::super::{Contract, Type, Function, Statement, Expression, Visibility, BinaryOp, LValue};
-use anyhow::Result;
+use super::super::{Contract, EventDefinition, ExternalContract, StructDefinition, Type, Function, Statement, Expression, Visibility, BinaryOp, UnaryOp, LValue, MatchArm, Pattern};
+use crate::idl::{MoveAbortCodes, MoveIdl, MoveIdlError, MoveIdlField, MoveIdlFunction, MoveIdlStruct};
+use crate::interface::{Argument, Entrypoint};
+use crate::modifiers::expand_modifiers;
+use crate::printer::range_loop;
+use super::writer::{indent, CodeWriter};
+use super::{called_contracts, check_view_functions, direct_state_access, is_constructor, lvalue_to_expression, visit_expression, visit_expressions, visit_statements, runtime_divisors, require_errors, ArithmeticMode, StateAccess};
+use anyhow::{Result, anyhow};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
-pub struct MoveCodeGenerator;
+mod unit_tests;
+
+// 溢出时 Move 会自动 abort
+const POW_HELPER: &str = "    fun pow(base: u64, exponent: u64): u64 {
+        let result = 1;
+        while (exponent > 0) {
+            result = result * base;
+            exponent = exponent - 1;
+        };
+        result
+    }
+";
+
+// Sui 没有比较字节序列的框架函数, 字符串的大小比较调用它; 按字典序返回 0 (小于), 1 (相等) 或 2 (大于)
+const COMPARE_BYTES_HELPER: &str = "    fun compare_bytes(a: &vector<u8>, b: &vector<u8>): u8 {
+        let a_len = std::vector::length(a);
+        let b_len = std::vector::length(b);
+        let i = 0;
+        while (i < a_len && i < b_len) {
+            let x = *std::vector::borrow(a, i);
+            let y = *std::vector::borrow(b, i);
+            if (x < y) return 0;
+            if (x > y) return 2;
+            i = i + 1;
+        };
+        if (a_len < b_len) 0 else if (a_len > b_len) 2 else 1
+    }
+";
+
+// 除零检查的 abort 码为 1, 重复调用构造函数为 2, Aptos 上由模块地址以外的账户调用构造函数为 3,
+// require 的 abort 码由消息的哈希得到
+const E_DIVISION_BY_ZERO: (&str, &str) = ("E_DIVISION_BY_ZERO", "Division by zero");
+const E_ALREADY_INITIALIZED: (&str, &str) = ("E_ALREADY_INITIALIZED", "Already initialized");
+const E_NOT_MODULE_ADDRESS: (&str, &str) = ("E_NOT_MODULE_ADDRESS", "Only the module's account can initialize");
+const BUILTIN_ERRORS: &[(&str, &str)] = &[E_DIVISION_BY_ZERO, E_ALREADY_INITIALIZED, E_NOT_MODULE_ADDRESS];
+
+// 没有消息的 require 共用的 abort 码
+const E_REQUIREMENT_FAILED: &str = "E_REQUIREMENT_FAILED";
+
+// Sui 的 State 在发布时创建, 构造函数运行后置为 true
+pub(super) const IS_INITIALIZED: &str = "is_initialized";
+
+// 所有者检查可以改写为持有能力资源的铸币函数, 及该资源的名字
+const MINT_FUNCTION: &str = "mint";
+const MINT_CAPABILITY: &str = "MintCapability";
+
+// 模块与 State 资源默认所在的命名地址
+const DEFAULT_ADDRESS: &str = "cross_chain";
+
+// 命名地址在测试与开发构建中的值
+const DEV_ADDRESS: &str = "0xcafe";
+
+// 引入 #[view] 与模块事件 (event::emit) 的框架版本
+const VIEW_FUNCTIONS: (u32, u32) = (1, 3);
+const MODULE_EVENTS: (u32, u32) = (1, 8);
+
+// 结构体可以声明的能力 (key 只用于 State), 按声明中的顺序
+const ABILITIES: [&str; 3] = ["copy", "drop", "store"];
+
+/// Aptos framework revision the module is compiled against, as in the `rev` of the
+/// `AptosFramework` dependency. Release tags (`aptos-node-v1.8.0`, `v1.8`) select the
+/// APIs of that release; branches such as `mainnet` and commit hashes are taken as recent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AptosFramework {
+    rev: String,
+    // 从 rev 中解析出的 (major, minor), 无法解析时为 None
+    release: Option<(u32, u32)>,
+}
+
+impl AptosFramework {
+    pub fn rev(&self) -> &str {
+        &self.rev
+    }
+
+    /// Whether the framework has what `release` introduced
+    fn has(&self, release: (u32, u32)) -> bool {
+        self.release.is_none_or(|r| r >= release)
+    }
+}
+
+impl Default for AptosFramework {
+    fn default() -> Self {
+        Self { rev: "mainnet".to_string(), release: None }
+    }
+}
+
+impl FromStr for AptosFramework {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("The Aptos framework revision cannot be empty".to_string());
+        }
+        let version = ["aptos-node-v", "aptos-framework-v", "v"].iter()
+            .find_map(|prefix| s.strip_prefix(prefix))
+            .unwrap_or(s);
+        let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+        let release = match (parts.next().flatten(), parts.next().flatten()) {
+            (Some(major), Some(minor)) => Some((major, minor)),
+            _ => None,
+        };
+        Ok(Self { rev: s.to_string(), release })
+    }
+}
+
+/// Checks that `name` can be a Move named address: an identifier such as `cross_chain`
+pub fn validate_named_address(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(anyhow!("Named address '{}' is not a Move identifier", name));
+    }
+    Ok(())
+}
+
+/// The Move chain a module is generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveChain {
+    Aptos,
+    Sui,
+}
+
+impl fmt::Display for AptosFramework {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rev)
+    }
+}
+
+/// Arguments a function takes besides its declared parameters. On Sui these are the
+/// shared `State` object and the `TxContext`; on Aptos, `State` borrowed by the caller
+/// of a private helper and the sender's `&signer`, which `ctx` stands for.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct ObjectArgs {
+    pub state: bool,
+    pub ctx: bool,
+}
+
+pub struct MoveCodeGenerator {
+    arithmetic: ArithmeticMode,
+    // 生成的目标链; Sui 的模块骨架由 SuiCodeGenerator 生成, 这里只影响函数体的写法
+    chain: MoveChain,
+    // 各函数额外接收的参数 (State 与 TxContext / signer), 调用时传入
+    object_args: RefCell<HashMap<String, ObjectArgs>>,
+    // require 消息对应的 abort 码常量
+    require_codes: RefCell<HashMap<Option<String>, String>>,
+    // 程序声明的外部合约, 调用时导入对应的模块
+    externals: Vec<ExternalContract>,
+    // 目标框架版本, 决定能否使用 #[view] 与模块事件
+    framework: AptosFramework,
+    // 各事件按声明顺序的字段名, emit 时按位置填入参数
+    events: RefCell<HashMap<String, Vec<String>>>,
+    // 合约声明的结构体, 用于生成零值
+    structs: RefCell<Vec<StructDefinition>>,
+    // 模块发布到的命名地址, State 资源也保存在这里
+    address: String,
+    // 当前函数中仍指向状态变量的名字及其类型 (被参数或局部变量遮蔽的除外), 生成为 state.<name>
+    state_vars: RefCell<HashMap<String, Type>>,
+    // 当前作用域中保存字符串的参数与局部变量, 比较大小时要按字节比较
+    string_locals: RefCell<HashSet<String>>,
+    // 字符串常量; Move 的常量不能是 String, 声明为 vector<u8>, 使用时再转换
+    string_constants: RefCell<HashSet<String>>,
+    // 函数体中是否比较了字符串的大小, Sui 需要为此生成 compare_bytes
+    compares_strings: Cell<bool>,
+    // 是否把受所有者检查保护的 mint 改写为 MintCapability 资源 (仅 Aptos)
+    capabilities: bool,
+}
 
 impl MoveCodeGenerator {
     pub fn new() -> Self {
-        Self
+        Self {
+            arithmetic: ArithmeticMode::default(),
+            chain: MoveChain::Aptos,
+            object_args: RefCell::new(HashMap::new()),
+            require_codes: RefCell::new(HashMap::new()),
+            externals: Vec::new(),
+            framework: AptosFramework::default(),
+            events: RefCell::new(HashMap::new()),
+            structs: RefCell::new(Vec::new()),
+            address: DEFAULT_ADDRESS.to_string(),
+            state_vars: RefCell::new(HashMap::new()),
+            string_locals: RefCell::new(HashSet::new()),
+            string_constants: RefCell::new(HashSet::new()),
+            compares_strings: Cell::new(false),
+            capabilities: false,
+        }
+    }
+
+    /// Selects how integer arithmetic is lowered; Move already aborts on overflow
+    pub fn with_arithmetic(mut self, mode: ArithmeticMode) -> Self {
+        self.arithmetic = mode;
+        self
+    }
+
+    /// Declares the external contracts the module can call, see [`crate::ExternalContract`]
+    pub fn with_externals(mut self, externals: Vec<ExternalContract>) -> Self {
+        self.externals = externals;
+        self
+    }
+
+    /// Targets an Aptos framework revision; see [`AptosFramework`]
+    pub fn with_framework(mut self, framework: AptosFramework) -> Self {
+        self.framework = framework;
+        self
+    }
+
+    /// Lowers the owner check of a guarded `mint` to a `MintCapability` resource that
+    /// the deployer receives with `State`, see [`MoveCodeGenerator::capability_guarded`];
+    /// other checks stay address comparisons
+    pub fn with_capabilities(mut self, enabled: bool) -> Self {
+        self.capabilities = enabled;
+        self
+    }
+
+    /// Lowers function bodies with the APIs of `chain`
+    pub(super) fn with_chain(mut self, chain: MoveChain) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Records the extra arguments each function takes, which calls to it pass along
+    pub(super) fn set_object_args(&self, args: HashMap<String, ObjectArgs>) {
+        self.object_args.replace(args);
+    }
+
+    pub(super) fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Publishes the module under the named address `name` instead of `cross_chain`; the
+    /// `State` resource lives there too, so publishing binds it to the deploying account
+    pub fn with_named_address(mut self, name: impl Into<String>) -> Self {
+        self.address = name.into();
+        self
+    }
+
+    /// Whether `func` is generated as a `#[view]` function; older frameworks have
+    /// no view functions, and there it is a plain public function
+    fn is_view(&self, func: &Function) -> bool {
+        func.is_view && self.framework.has(VIEW_FUNCTIONS)
+    }
+
+    /// The public `mint` whose owner check becomes holding a `MintCapability`, when
+    /// capabilities are enabled for Aptos. Its body, with modifiers expanded, must open with
+    /// `require(msg_sender() == owner)` on an address state variable that starts as the
+    /// deployer, through its initial value or the constructor, and is never set again: only
+    /// then is the account holding the capability the owner. No function may call `mint`,
+    /// since it would have to hold the capability too.
+    fn capability_guarded<'c>(&self, contract: &'c Contract) -> Option<&'c Function> {
+        if !self.capabilities || self.chain != MoveChain::Aptos || contract.structs.iter().any(|s| s.name == MINT_CAPABILITY) {
+            return None;
+        }
+        let mint = contract.functions.iter()
+            .find(|f| f.name == MINT_FUNCTION && matches!(f.visibility, Visibility::Public | Visibility::External))?;
+        let Some(Statement::Require { condition: Expression::Binary { op: BinaryOp::Eq, left, right }, .. }) = mint.body.first() else {
+            return None;
+        };
+        let owner = match (left.as_ref(), right.as_ref()) {
+            (Expression::MsgSender, Expression::Identifier(name)) | (Expression::Identifier(name), Expression::MsgSender) => name,
+            _ => return None,
+        };
+        let var = contract.state.iter().find(|v| v.name == *owner && v.ty == Type::Address)?;
+        if mint.params.iter().any(|p| p.name == *owner) {
+            return None;
+        }
+
+        // owner 只能由初始值或构造函数顶层的 owner = msg_sender() 设为部署者
+        let sets_owner = |stmt: &Statement| matches!(stmt, Statement::Assign { target: LValue::Identifier(name), .. } if name == owner);
+        let deployer = Statement::Assign { target: LValue::Identifier(owner.clone()), value: Expression::MsgSender };
+        let mut assignments = 0;
+        let mut calls_mint = false;
+        for func in &contract.functions {
+            visit_statements(&func.body, &mut |stmt| assignments += sets_owner(stmt) as usize);
+            calls_mint |= internal_calls(func).contains(MINT_FUNCTION);
+        }
+        let starts_as_deployer = match &var.initial_value {
+            Some(Expression::MsgSender) => assignments == 0,
+            _ => assignments == 1 && contract.constructor().is_some_and(|f| f.body.contains(&deployer)),
+        };
+        (starts_as_deployer && !calls_mint).then_some(mint)
+    }
+
+    fn external(&self, name: &str) -> Option<&ExternalContract> {
+        self.externals.iter().find(|e| e.name == name)
     }
 
     pub fn generate(&self, contract: &Contract) -> Result<String> {
-        let move_code = self.transform_contract(contract);
+        let contract = self.prepare(contract)?;
+        
+        // 旧框架只有 EventHandle, 需要在资源中保存句柄
+        let mut emits = false;
+        for func in &contract.functions {
+            visit_statements(&func.body, &mut |stmt| emits |= matches!(stmt, Statement::Emit { .. }));
+        }
+        if emits && !self.framework.has(MODULE_EVENTS) {
+            return Err(anyhow!(
+                "Aptos framework '{}' predates module events (v{}.{}), which the generated `event::emit` needs; \
+                 target a newer revision",
+                self.framework, MODULE_EVENTS.0, MODULE_EVENTS.1
+            ));
+        }
+        
+        let move_code = self.transform_contract(&contract);
         Ok(move_code)
     }
 
+    /// The `Move.toml` of the package holding the module under `sources/` and its tests
+    /// under `tests/`. The named address is left as `_` for `aptos move publish
+    /// --named-addresses` to fill in; `aptos move test` uses its dev address.
+    pub fn move_toml(&self, contract: &Contract) -> String {
+        let mut code = String::new();
+        code.push_str("[package]\n");
+        code.push_str(&format!("name = \"{}\"\n", contract.name));
+        code.push_str("version = \"0.1.0\"\n\n");
+        code.push_str("[addresses]\n");
+        code.push_str(&format!("{} = \"_\"\n\n", self.address));
+        // aptos move test 时使用的地址, 与测试中发布 State 的账户一致
+        code.push_str("[dev-addresses]\n");
+        code.push_str(&format!("{} = \"{}\"\n\n", self.address, DEV_ADDRESS));
+        code.push_str("[dependencies.AptosFramework]\n");
+        code.push_str("git = \"https://github.com/aptos-labs/aptos-core.git\"\n");
+        code.push_str(&format!("rev = \"{}\"\n", self.framework));
+        code.push_str("subdir = \"aptos-move/framework/aptos-framework\"\n");
+        code
+    }
+
+    /// `contract` with its modifiers expanded, once it is known to fit the Move backends
+    pub(super) fn prepare(&self, contract: &Contract) -> Result<Contract> {
+        // 先把修饰器展开到函数体中
+        let contract = expand_modifiers(contract)?;
+        check_view_functions(&contract)?;
+        
+        // Move 的调用不附带原生代币, 需要价值的函数应显式接收 Coin 参数
+        if let Some(func) = contract.functions.iter().find(|f| f.is_payable) {
+            return Err(anyhow!(
+                "Payable function '{}' cannot be compiled to Move: Move calls carry no value; take the payment as a parameter instead",
+                func.name
+            ));
+        }
+        
+        // Move 没有有符号整数, 有符号类型按无符号生成, 无法取负
+        for func in &contract.functions {
+            let mut negates = false;
+            visit_expressions(&func.body, &mut |expr| negates |= matches!(expr, Expression::Unary { op: UnaryOp::Neg, .. }));
+            if negates {
+                return Err(anyhow!(
+                    "Function '{}' negates a value, which cannot be compiled to Move: Move has no signed integers",
+                    func.name
+                ));
+            }
+        }
+        
+        // 构造函数由部署者的交易调用来创建 State
+        if let Some(func) = state_constructor(&contract) {
+            if !matches!(func.visibility, Visibility::Public) || func.is_view {
+                return Err(anyhow!(
+                    "Constructor '{}' must be a public, non-view function: the deployer calls it to create State",
+                    func.name
+                ));
+            }
+            // Sui 的 State 用这个字段记录构造函数是否已运行
+            if self.chain == MoveChain::Sui && contract.state.iter().any(|v| v.name == IS_INITIALIZED) {
+                return Err(anyhow!(
+                    "State variable '{}' clashes with the field the Sui State object uses to run the constructor '{}' only once",
+                    IS_INITIALIZED, func.name
+                ));
+            }
+        }
+        
+        // Aptos 的 #[view] 函数不经过交易调用, 没有签名者
+        if self.chain == MoveChain::Aptos {
+            let args = aptos_args(&contract);
+            if let Some(func) = contract.functions.iter().find(|f| self.is_view(f) && args[&f.name].ctx) {
+                return Err(anyhow!(
+                    "View function '{}' needs the caller's address, but Aptos view functions are called without a signer",
+                    func.name
+                ));
+            }
+        }
+        
+        check_struct_abilities(&contract, self.chain)?;
+        
+        // Sui 的字符串是 std::ascii::String, 非 ASCII 的字面量在运行时会 abort
+        if self.chain == MoveChain::Sui {
+            let mut literals = Vec::new();
+            for func in &contract.functions {
+                visit_expressions(&func.body, &mut |expr| {
+                    if let Expression::String(s) = expr {
+                        literals.push(s.clone());
+                    }
+                });
+            }
+            literals.extend(contract.constants.iter().filter_map(|c| match &c.value {
+                Expression::String(s) => Some(s.clone()),
+                _ => None,
+            }));
+            if let Some(literal) = literals.iter().find(|s| !s.is_ascii()) {
+                return Err(anyhow!("String literal \"{}\" is not ASCII, which Sui strings (std::ascii::String) must be", literal));
+            }
+        }
+        
+        // 金库与跨链事件目前只有 Solana 后端生成
+        if contract.bridge.is_some() {
+            return Err(anyhow!("Contract '{}' declares a bridge, which only the Solana backend supports", contract.name));
+        }
+        
+        // Move 的整数运算溢出时总是 abort, 无法生成回绕语义
+        if self.arithmetic == ArithmeticMode::Wrapping {
+            let mut wraps = false;
+            for func in &contract.functions {
+                visit_expressions(&func.body, &mut |expr| {
+                    wraps |= matches!(expr, Expression::Binary { op: BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul, .. });
+                });
+            }
+            if wraps {
+                return Err(anyhow!("Move has no wrapping arithmetic; use --arith checked or unchecked for Move targets"));
+            }
+        }
+        Ok(contract)
+    }
+
+    /// The Move function generated for each DSL function, keyed by function name.
+    /// Public functions are `entry` functions unless they return a value or are `view`
+    /// functions; those that need the caller's address take the sender as a `&signer`.
+    pub fn entrypoints(&self, contract: &Contract) -> BTreeMap<String, Entrypoint> {
+        let module = contract.name.to_lowercase();
+        let address = &self.address;
+        let contract = expand_modifiers(contract).unwrap_or_else(|_| contract.clone());
+        let args = aptos_args(&contract);
+        contract.functions.iter()
+            .map(|func| {
+                let entry = is_entry(func);
+                (func.name.clone(), Entrypoint {
+                    name: format!("{}::{}::{}", address, module, func.name),
+                    entry,
+                    args: func.params.iter()
+                        .map(|p| Argument { name: p.name.clone(), ty: self.type_to_move(&p.ty) })
+                        .collect(),
+                    returns: func.return_type.as_ref().map(|ty| self.type_to_move(ty)),
+                    signers: if args[&func.name].ctx { vec!["account".to_string()] } else { Vec::new() },
+                    accounts: Vec::new(),
+                })
+            })
+            .collect()
+    }
+
+    /// The abort codes of the module `generate` emits and their messages, for explorers
+    /// and clients decoding failed transactions
+    pub fn abort_codes(&self, contract: &Contract) -> Result<MoveAbortCodes> {
+        let contract = self.prepare(contract)?;
+        Ok(MoveAbortCodes {
+            module: format!("{}::{}", self.address, contract.name.to_lowercase()),
+            errors: abort_codes(&contract, self.chain).into_iter()
+                .map(|(name, code, msg)| MoveIdlError { code, name, msg })
+                .collect(),
+        })
+    }
+
+    /// A description of the module `generate` emits, for frontends and relayers
+    pub fn idl(&self, contract: &Contract) -> Result<MoveIdl> {
+        let contract = expand_modifiers(contract)?;
+        let fields = |fields: Vec<(&str, &Type)>| fields.into_iter()
+            .map(|(name, ty)| MoveIdlField { name: name.to_string(), ty: self.type_to_move(ty) })
+            .collect::<Vec<_>>();
+        
+        let args = aptos_args(&contract);
+        let functions = contract.functions.iter()
+            .map(|func| MoveIdlFunction {
+                name: func.name.clone(),
+                entry: is_entry(func),
+                signer: args[&func.name].ctx,
+                view: self.is_view(func),
+                params: fields(func.params.iter().map(|p| (p.name.as_str(), &p.ty)).collect()),
+                returns: func.return_type.as_ref().map(|ty| self.type_to_move(ty)),
+            })
+            .collect();
+        
+        let abilities = struct_abilities(&contract, self.chain);
+        let mut structs: Vec<MoveIdlStruct> = contract.structs.iter()
+            .map(|def| MoveIdlStruct {
+                name: def.name.clone(),
+                abilities: abilities[&def.name].iter().map(|a| a.to_string()).collect(),
+                fields: fields(def.fields.iter().map(|f| (f.name.as_str(), &f.ty)).collect()),
+            })
+            .collect();
+        if !contract.state.is_empty() {
+            structs.push(MoveIdlStruct {
+                name: "State".to_string(),
+                abilities: vec!["key".to_string()],
+                fields: fields(contract.state.iter().map(|v| (v.name.as_str(), &v.ty)).collect()),
+            });
+        }
+        if self.capability_guarded(&contract).is_some() {
+            structs.push(MoveIdlStruct { name: MINT_CAPABILITY.to_string(), abilities: vec!["key".to_string()], fields: Vec::new() });
+        }
+        
+        // 事件需要 drop 和 store 才能发射
+        let events = contract.events.iter()
+            .map(|event| MoveIdlStruct {
+                name: event.name.clone(),
+                abilities: vec!["drop".to_string(), "store".to_string()],
+                fields: fields(event.params.iter().map(|p| (p.name.as_str(), &p.ty)).collect()),
+            })
+            .collect();
+        
+        Ok(MoveIdl {
+            address: self.address.clone(),
+            name: contract.name.to_lowercase(),
+            functions,
+            structs,
+            events,
+            errors: abort_codes(&contract, self.chain).into_iter()
+                .map(|(name, code, msg)| MoveIdlError { code, name, msg })
+                .collect(),
+        })
+    }
+
     fn transform_contract(&self, contract: &Contract) -> String {
         let mut code = String::new();
         
         // 模块声明
-        code.push_str(&format!("module cross_chain::{} {{\n", contract.name.to_lowercase()));
+        code.push_str(&format!("module {}::{} {{\n", self.address, contract.name.to_lowercase()));
         
         // 导入
-        code.push_str("    use std::signer;\n");
+        if uses_expression(contract, |expr| matches!(expr, Expression::MsgSender)) || state_constructor(contract).is_some() {
+            code.push_str("    use std::signer;\n");
+        }
+        if uses_strings(contract) {
+            code.push_str("    use std::string::{Self, String};\n");
+        }
+        if uses_vectors(contract) {
+            code.push_str("    use std::vector;\n");
+        }
+        if uses_tables(contract) {
+            code.push_str("    use aptos_std::table::{Self, Table};\n");
+        }
         code.push_str("    use aptos_framework::event;\n");
-        code.push_str("    use aptos_framework::timestamp;\n\n");
+        code.push_str("    use aptos_framework::timestamp;\n");
+        code.push_str(&self.external_imports(contract));
+        code.push('\n');
+        code.push_str(&self.declarations(contract));
+        
+        // 生成函数; 借用 State 的函数 (包括通过调用的函数) 要声明 acquires State
+        let args = aptos_args(contract);
+        self.set_object_args(args.clone());
+        let access = helper_state_access(contract);
+        let acquires = acquires_state(contract, &args, &access);
+        let guarded = self.capability_guarded(contract);
+        if guarded.is_some() {
+            code.push_str("    /// Held by the account allowed to mint, the deployer\n");
+            code.push_str(&format!("    struct {} has key {{}}\n\n", MINT_CAPABILITY));
+        }
+        for func in &contract.functions {
+            let mut acquires: Vec<&str> = if acquires.contains(&func.name) { vec!["State"] } else { vec![] };
+            let mut prologue = self.state_prologue(contract, func, args[&func.name], access[&func.name]);
+            let mut func = func;
+            let unguarded;
+            if guarded.is_some() && is_constructor(contract, func) {
+                // 在 State 的 move_to 之后
+                prologue.insert(3, format!("move_to(account, {} {{}});", MINT_CAPABILITY));
+            }
+            // 所有者检查改为检查调用者持有 MintCapability, abort 码不变
+            if let Some(mint) = guarded.filter(|mint| mint.name == func.name) {
+                let Statement::Require { message, .. } = &mint.body[0] else { unreachable!() };
+                let abort_code = self.require_codes.borrow()[message].clone();
+                prologue.splice(0..0, [
+                    format!("assert!(exists<{}>(signer::address_of(account)), {});", MINT_CAPABILITY, abort_code),
+                    format!("let _cap = borrow_global<{}>(signer::address_of(account));", MINT_CAPABILITY),
+                ]);
+                acquires.push(MINT_CAPABILITY);
+                unguarded = Function { body: mint.body[1..].to_vec(), ..mint.clone() };
+                func = &unguarded;
+            }
+            code.push_str(&self.generate_function(contract, func, args[&func.name], access[&func.name], &acquires, &prologue));
+            code.push('\n');
+        }
+        
+        // 没有构造函数时, 发布模块时由 init_module 用默认值创建 State
+        if !contract.state.is_empty() && contract.constructor().is_none() {
+            code.push_str("    fun init_module(account: &signer) {\n");
+            code.push_str(&format!("        move_to(account, {});\n", self.initial_state(contract)));
+            if guarded.is_some() {
+                code.push_str(&format!("        move_to(account, {} {{}});\n", MINT_CAPABILITY));
+            }
+            code.push_str("    }\n\n");
+            // 测试模块无法调用私有的 init_module, 由它代为发布 State
+            code.push_str("    #[test_only]\n");
+            code.push_str("    public fun init_module_for_test(account: &signer) {\n");
+            code.push_str("        init_module(account);\n");
+            code.push_str("    }\n\n");
+        }
+        
+        code.push_str(&self.helpers(contract));
+        code.push_str("}\n");
+        code
+    }
+
+    /// A `use` of the module of every external contract the contract calls: `0x`
+    /// addresses are used as is, other contracts by the named address of the same name
+    pub(super) fn external_imports(&self, contract: &Contract) -> String {
+        let mut called = Vec::new();
+        for func in &contract.functions {
+            for name in called_contracts(&func.body) {
+                if !called.contains(&name) {
+                    called.push(name);
+                }
+            }
+        }
+        let mut code = String::new();
+        for external in self.externals.iter().filter(|e| called.contains(&e.name)) {
+            let module = external.name.to_lowercase();
+            let address = if external.address.starts_with("0x") { external.address.clone() } else { module.clone() };
+            code.push_str(&format!("    use {}::{};\n", address, module));
+        }
+        code
+    }
+
+    /// The constants, the declared structs, the `State` struct and the event structs, in that order. Also
+    /// records the abort codes, events and structs that function bodies refer to.
+    pub(super) fn declarations(&self, contract: &Contract) -> String {
+        let mut code = String::new();
+        
+        // 生成常量
+        let mut constants: Vec<String> = contract.constants.iter()
+            .map(|constant| match (&constant.ty, &constant.value) {
+                (Type::String(_), Expression::String(s)) => format!("    const {}: vector<u8> = b\"{}\";\n", constant.name, s),
+                (ty, value) => format!("    const {}: {} = {};\n", constant.name, self.type_to_move(ty), self.expression_to_move(value)),
+            })
+            .collect();
+        self.string_constants.replace(contract.constants.iter()
+            .filter(|c| matches!(c.ty, Type::String(_)))
+            .map(|c| c.name.clone())
+            .collect());
+        self.compares_strings.set(false);
+        for (name, code, _) in abort_codes(contract, self.chain) {
+            constants.push(format!("    const {}: u64 = {};\n", name, code));
+        }
+        let errors = require_errors(contract, BUILTIN_ERRORS, E_REQUIREMENT_FAILED, abort_code_name);
+        self.require_codes.replace(errors.into_iter().map(|e| (e.message, e.name)).collect());
+        self.events.replace(contract.events.iter()
+            .map(|e| (e.name.clone(), e.params.iter().map(|p| p.name.clone()).collect()))
+            .collect());
+        self.structs.replace(contract.structs.clone());
+        if !constants.is_empty() {
+            code.push_str(&constants.concat());
+            code.push('\n');
+        }
+        
+        // 合约声明的结构体, 能力由用法推断
+        let abilities = struct_abilities(contract, self.chain);
+        for def in &contract.structs {
+            match abilities[&def.name].as_slice() {
+                [] => code.push_str(&format!("    struct {} {{\n", def.name)),
+                abilities => code.push_str(&format!("    struct {} has {} {{\n", def.name, abilities.join(", "))),
+            }
+            for field in &def.fields {
+                code.push_str(&format!("        {}: {},\n", field.name, self.type_to_move(&field.ty)));
+            }
+            code.push_str("    }\n\n");
+        }
         
-        // 生成资源结构
+        // 生成资源结构; Sui 的对象以 UID 作为第一个字段
         if !contract.state.is_empty() {
             code.push_str("    /// Main state resource\n");
             code.push_str("    struct State has key {\n");
+            if self.chain == MoveChain::Sui {
+                code.push_str("        id: UID,\n");
+            }
             for var in &contract.state {
                 code.push_str("        ");
                 code.push_str(&var.name);
@@ -35,21 +686,137 @@ impl MoveCodeGenerator {
                 code.push_str(&self.type_to_move(&var.ty));
                 code.push_str(",\n");
             }
+            if self.chain == MoveChain::Sui && state_constructor(contract).is_some() {
+                code.push_str(&format!("        {}: bool,\n", IS_INITIALIZED));
+            }
             code.push_str("    }\n\n");
         }
         
-        // 生成函数
-        for func in &contract.functions {
-            code.push_str(&self.generate_function(func));
-            code.push_str("\n");
+        // 事件结构体
+        code.push_str(&self.event_structs(&contract.events, self.chain));
+        code
+    }
+
+    /// Helper functions the bodies call: `pow` when the contract uses `**`, and on Sui
+    /// `compare_bytes` when the bodies lowered so far compare strings by order
+    pub(super) fn helpers(&self, contract: &Contract) -> String {
+        let mut code = String::new();
+        if uses_expression(contract, |expr| matches!(expr, Expression::Binary { op: BinaryOp::Pow, .. })) {
+            code.push_str(POW_HELPER);
+        }
+        if self.chain == MoveChain::Sui && self.compares_strings.get() {
+            if !code.is_empty() {
+                code.push('\n');
+            }
+            code.push_str(COMPARE_BYTES_HELPER);
         }
-        
-        code.push_str("}\n");
         code
     }
 
-    fn generate_function(&self, func: &Function) -> String {
+    /// A struct per event, with the fields `emit` fills from its arguments. Aptos module
+    /// events are `#[event]` structs with `drop, store`; Sui events need `copy, drop`.
+    pub fn event_structs(&self, events: &[EventDefinition], chain: MoveChain) -> String {
         let mut code = String::new();
+        for event in events {
+            let abilities = match chain {
+                // 旧框架没有 #[event] 属性, 那里不会发射事件 (见 generate)
+                MoveChain::Aptos if self.framework.has(MODULE_EVENTS) => {
+                    code.push_str("    #[event]\n");
+                    "drop, store"
+                }
+                MoveChain::Aptos => "drop, store",
+                MoveChain::Sui => "copy, drop",
+            };
+            code.push_str(&format!("    struct {} has {} {{\n", event.name, abilities));
+            for param in &event.params {
+                code.push_str(&format!("        {}: {},\n", param.name, self.type_to_move(&param.ty)));
+            }
+            code.push_str("    }\n\n");
+        }
+        code
+    }
+
+    /// The statements that give a function's body its `state` reference: the constructor
+    /// first checks it is called by the module's account, which the other functions borrow
+    /// `State` from, and that `State` doesn't exist yet, then moves a zeroed one there; other
+    /// functions that use state borrow it from the module's address, mutably only if they
+    /// write to it
+    fn state_prologue(&self, contract: &Contract, func: &Function, args: ObjectArgs, access: StateAccess) -> Vec<String> {
+        let mut lines = Vec::new();
+        if state_constructor(contract).is_some_and(|c| c.name == func.name) {
+            lines.push(format!("assert!(signer::address_of(account) == @{}, {});", self.address, E_NOT_MODULE_ADDRESS.0));
+            lines.push(format!("assert!(!exists<State>(@{}), {});", self.address, E_ALREADY_INITIALIZED.0));
+            lines.push(format!("move_to(account, {});", self.initial_state(contract)));
+        }
+        // 私有辅助函数使用调用者借用的 State; 其他函数自己借用, 调用的私有函数用到的部分也算在内
+        if args.state {
+            return lines;
+        }
+        if access.writes {
+            lines.push(format!("let state = borrow_global_mut<State>(@{});", self.address));
+        } else if access.reads {
+            lines.push(format!("let state = borrow_global<State>(@{});", self.address));
+        }
+        lines
+    }
+
+    /// `State { .. }` with every state variable at its declared initial value, or else its
+    /// type's zero value; a Sui object also gets a fresh UID, and a cleared `is_initialized`
+    /// flag if there is a constructor to run. It is built in the function that creates the
+    /// state, where `msg_sender()` is the deployer.
+    pub(super) fn initial_state(&self, contract: &Contract) -> String {
+        let mut fields: Vec<String> = contract.state.iter()
+            .map(|var| match &var.initial_value {
+                Some(value) => format!("{}: {}", var.name, self.expression_to_move(value)),
+                None => format!("{}: {}", var.name, self.zero_value(&var.ty)),
+            })
+            .collect();
+        if self.chain == MoveChain::Sui {
+            fields.insert(0, "id: object::new(ctx)".to_string());
+            if state_constructor(contract).is_some() {
+                fields.push(format!("{}: false", IS_INITIALIZED));
+            }
+        }
+        format!("State {{ {} }}", fields.join(", "))
+    }
+
+    fn zero_value(&self, ty: &Type) -> String {
+        match ty {
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::U256
+            | Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128 => "0".to_string(),
+            Type::Bool => "false".to_string(),
+            Type::Address => "@0x0".to_string(),
+            Type::String(_) => self.string_literal(""),
+            Type::Bytes(_) | Type::Vec(_) | Type::Array(..) => "vector[]".to_string(),
+            Type::Map(..) => match self.chain {
+                MoveChain::Aptos => "table::new()".to_string(),
+                MoveChain::Sui => "table::new(ctx)".to_string(),
+            },
+            Type::Option(_) => "std::option::none()".to_string(),
+            Type::Struct(name) => {
+                let structs = self.structs.borrow();
+                let fields: Vec<String> = structs.iter()
+                    .find(|s| s.name == *name)
+                    .map(|s| s.fields.iter()
+                        .map(|field| format!("{}: {}", field.name, self.zero_value(&field.ty)))
+                        .collect())
+                    .unwrap_or_default();
+                format!("{} {{ {} }}", name, fields.join(", "))
+            },
+            Type::Tuple(_) | Type::Result(..) => "/* default */".to_string(),
+        }
+    }
+
+    /// A Move function; `args` are the arguments it takes besides its parameters, `access`
+    /// its use of `State` with that of the private helpers it calls, `acquires` the resources
+    /// it borrows, directly or through its callees, and `prologue` the statements before the body
+    fn generate_function(&self, contract: &Contract, func: &Function, args: ObjectArgs, access: StateAccess, acquires: &[&str], prologue: &[String]) -> String {
+        let mut code = String::new();
+        
+        // 只读查询, 客户端无需交易即可调用
+        if self.is_view(func) {
+            code.push_str("    #[view]\n");
+        }
         
         // 函数可见性
         match func.visibility {
@@ -59,34 +826,23 @@ impl MoveCodeGenerator {
             Visibility::External => code.push_str("    public "),
         }
         
-        // entry 修饰符（如果是 public）
-        if matches!(func.visibility, Visibility::Public) {
+        // entry 修饰符 (public 且没有返回值; entry 函数不能返回值)
+        if is_entry(func) {
             code.push_str("entry ");
         }
         
-        code.push_str("fun ");
-        code.push_str(&func.name);
-        code.push_str("(");
-        
-        // 第一个参数通常是 signer
-        let mut first = true;
-        if matches!(func.visibility, Visibility::Public) {
-            code.push_str("account: &signer");
-            first = false;
+        // 需要调用者地址或创建 State 的函数第一个参数是 signer, 私有辅助函数接着接收调用者借用的 State
+        let mut params = Vec::new();
+        if args.ctx {
+            params.push("account: &signer".to_string());
+        }
+        if args.state {
+            params.push(if access.writes { "state: &mut State".to_string() } else { "state: &State".to_string() });
         }
-        
-        // 其他参数
         for param in &func.params {
-            if !first {
-                code.push_str(", ");
-            }
-            code.push_str(&param.name);
-            code.push_str(": ");
-            code.push_str(&self.type_to_move(&param.ty));
-            first = false;
+            params.push(format!("{}: {}", param.name, self.type_to_move(&param.ty)));
         }
-        
-        code.push_str(")");
+        code.push_str(&format!("fun {}({})", func.name, params.join(", ")));
         
         // 返回类型
         if let Some(ret_ty) = &func.return_type {
@@ -94,23 +850,46 @@ impl MoveCodeGenerator {
             code.push_str(&self.type_to_move(ret_ty));
         }
         
-        // acquires 声明
-        code.push_str(" acquires State");
-        
-        code.push_str(" {\n");
-        
-        // 函数体
-        for stmt in &func.body {
-            code.push_str("        ");
-            code.push_str(&self.statement_to_move(stmt));
-            code.push_str("\n");
+        // 声明了却没有借用的资源无法通过编译
+        if !acquires.is_empty() {
+            code.push_str(&format!(" acquires {}", acquires.join(", ")));
         }
         
+        code.push_str(" {\n");
+        code.push_str(&self.function_body(contract, func, prologue, &[]));
         code.push_str("    }\n");
         code
     }
 
-    fn type_to_move(&self, ty: &Type) -> String {
+    /// The statements of `func` between `prologue` and `epilogue`, one per line at the
+    /// function body's indentation
+    pub(super) fn function_body(&self, contract: &Contract, func: &Function, prologue: &[String], epilogue: &[String]) -> String {
+        let mut code = CodeWriter::new(2);
+        for line in prologue {
+            code.line(line);
+        }
+        
+        // 没有被参数遮蔽的状态变量通过 state 访问
+        self.state_vars.replace(contract.state.iter()
+            .filter(|var| !func.params.iter().any(|p| p.name == var.name))
+            .map(|var| (var.name.clone(), var.ty.clone()))
+            .collect());
+        self.string_locals.replace(func.params.iter()
+            .filter(|p| matches!(p.ty, Type::String(_)))
+            .map(|p| p.name.clone())
+            .collect());
+        // 函数体后还有语句时, 最后一条语句不是函数的值
+        self.write_sequence(&mut code, &func.body, epilogue.is_empty());
+        if !epilogue.is_empty() && matches!(func.body.last(), Some(Statement::If { .. } | Statement::Match { .. })) {
+            code.append(";");
+        }
+        for line in epilogue {
+            code.line(line);
+        }
+        code.finish()
+    }
+
+    pub(super) fn type_to_move(&self, ty: &Type) -> String {
         match ty {
             Type::U8 => "u8".to_string(),
             Type::U16 => "u16".to_string(),
@@ -125,13 +904,10 @@ impl MoveCodeGenerator {
             Type::I128 => "u128".to_string(),
             Type::Bool => "bool".to_string(),
             Type::Address => "address".to_string(),
-            Type::String => "vector<u8>".to_string(),
-            Type::Bytes => "vector<u8>".to_string(),
-            Type::Map(k, v) => {
-                // Move 使用 Table 或 SimpleMap
-                format!("aptos_std::simple_map::SimpleMap<{}, {}>", 
-                    self.type_to_move(k), self.type_to_move(v))
-            },
+            // Aptos 为 std::string::String (UTF-8), Sui 为 std::ascii::String, 均以 String 导入
+            Type::String(_) => "String".to_string(),
+            Type::Bytes(_) => "vector<u8>".to_string(),
+            Type::Map(k, v) => format!("Table<{}, {}>", self.type_to_move(k), self.type_to_move(v)),
             Type::Vec(t) => format!("vector<{}>", self.type_to_move(t)),
             Type::Array(t, _size) => format!("vector<{}>", self.type_to_move(t)),
             Type::Tuple(types) => {
@@ -142,86 +918,499 @@ impl MoveCodeGenerator {
                 format!("({})", types_str)
             },
             Type::Struct(name) => name.clone(),
-            Type::Option(t) => format!("Option<{}>", self.type_to_move(t)),
+            Type::Option(t) => format!("std::option::Option<{}>", self.type_to_move(t)),
             Type::Result(ok, err) => format!("Result<{}, {}>", self.type_to_move(ok), self.type_to_move(err)),
         }
     }
 
-    fn statement_to_move(&self, stmt: &Statement) -> String {
+    /// `stmt` at `level`; `tail` if it is the last statement the function runs, whose
+    /// `return` is written as the function's value
+    fn statement_to_move(&self, stmt: &Statement, level: usize, tail: bool) -> String {
+        // 除数不是常量时先检查, 除零时以明确的错误码 abort
+        let mut code = String::new();
+        for divisor in runtime_divisors(stmt) {
+            code.push_str(&format!("assert!({} != 0, E_DIVISION_BY_ZERO);\n{}",
+                self.expression_to_move(divisor), indent(level)));
+        }
+        code.push_str(&self.lower_statement(stmt, level, tail));
+        code
+    }
+
+    /// Writes the statements one per line at the writer's level. Move separates the
+    /// expressions of a sequence with `;`, so an `if` or an early `return` followed by more
+    /// statements needs one too; only the last statement can be in tail position.
+    fn write_sequence(&self, code: &mut CodeWriter, stmts: &[Statement], tail: bool) {
+        for (i, stmt) in stmts.iter().enumerate() {
+            let last = i + 1 == stmts.len();
+            code.line(&self.statement_to_move(stmt, code.level(), tail && last));
+            if !last && matches!(stmt, Statement::If { .. } | Statement::Match { .. } | Statement::Return { .. }) {
+                code.append(";");
+            }
+        }
+    }
+
+    /// `{ ... }` with the statements one level deeper than `level`, the last in tail
+    /// position if the block is
+    fn block_to_move(&self, stmts: &[Statement], level: usize, tail: bool) -> String {
+        self.scoped(|| {
+            let mut code = CodeWriter::new(level + 1);
+            self.write_sequence(&mut code, stmts, tail);
+            format!("{{\n{}{}}}", code.finish(), indent(level))
+        })
+    }
+
+    /// Runs `lower` in a new scope: locals it declares stop shadowing state variables afterwards
+    fn scoped<T>(&self, lower: impl FnOnce() -> T) -> T {
+        let outer = self.state_vars.borrow().clone();
+        let strings = self.string_locals.borrow().clone();
+        let result = lower();
+        self.state_vars.replace(outer);
+        self.string_locals.replace(strings);
+        result
+    }
+
+    fn lower_statement(&self, stmt: &Statement, level: usize, tail: bool) -> String {
         match stmt {
-            Statement::Let { name, value, .. } => {
-                format!("let {} = {};", name, self.expression_to_move(value))
+            // 局部变量从声明处起遮蔽同名的状态变量
+            Statement::Let { name, ty, value, .. } => {
+                let is_string = match ty {
+                    Some(ty) => matches!(ty, Type::String(_)),
+                    None => self.is_string(value),
+                };
+                let value = self.expression_to_move(value);
+                self.state_vars.borrow_mut().remove(name);
+                if is_string {
+                    self.string_locals.borrow_mut().insert(name.clone());
+                } else {
+                    self.string_locals.borrow_mut().remove(name);
+                }
+                format!("let {} = {};", name, value)
             },
-            Statement::Assign { target, value } => {
-                // Move 中赋值需要处理可变引用
-                let target_str = self.lvalue_to_move(target);
-                format!("*{} = {};", target_str, self.expression_to_move(value))
+            Statement::LetTuple { names, value } => {
+                let value = self.expression_to_move(value);
+                for name in names {
+                    self.state_vars.borrow_mut().remove(name);
+                    self.string_locals.borrow_mut().remove(name);
+                }
+                format!("let ({}) = {};", names.join(", "), value)
             },
-            Statement::Require { condition, message } => {
-                if let Some(msg) = message {
-                    format!("assert!({}, 1);", self.expression_to_move(condition))
-                } else {
-                    format!("assert!({}, 1);", self.expression_to_move(condition))
+            // 局部变量直接赋值, 状态变量通过 state 引用的字段赋值
+            Statement::Assign { target, value } => {
+                let entry = lvalue_to_expression(target);
+                match self.map_entry(&entry) {
+                    Some((map, keys, _)) => self.map_write(&map, &keys, value, level),
+                    None => match self.lvalue_to_move(target) {
+                        (place, true) => format!("*{} = {};", place, self.expression_to_move(value)),
+                        (place, false) => format!("{} = {};", place, self.expression_to_move(value)),
+                    },
                 }
             },
+            Statement::Require { condition, message } | Statement::Assert { condition, message } => {
+                let code = self.require_codes.borrow()[message].clone();
+                format!("assert!({}, {});", self.expression_to_move(condition), code)
+            },
             Statement::If { condition, then_block, else_block } => {
-                let mut code = format!("if ({}) {{\n", self.expression_to_move(condition));
-                for s in then_block {
-                    code.push_str("            ");
-                    code.push_str(&self.statement_to_move(s));
-                    code.push_str("\n");
+                let mut code = format!("if ({}) {}", self.expression_to_move(condition), self.block_to_move(then_block, level, tail));
+                if let Some(else_b) = else_block {
+                    code.push_str(" else ");
+                    code.push_str(&self.block_to_move(else_b, level, tail));
                 }
-                code.push_str("        }");
+                code
+            },
+            Statement::While { condition, body } => {
+                format!("while ({}) {};", self.expression_to_move(condition), self.block_to_move(body, level, false))
+            },
+            // 计数循环展开成 while; 更新语句放在循环体末尾, continue 之前也要先执行
+            Statement::For { init, condition, update, body } => self.scoped(|| {
+                let mut body = run_before_continue(body, update);
+                body.push(*update.clone());
+                let mut code = self.statement_to_move(init, level, false);
+                // 与 Solana 的 `for i in a..b` 一样, 区间的终点只求值一次
+                let condition = match range_loop(init, condition, update) {
+                    Some((variable, _, _, end)) if !matches!(end, Expression::Number(_) | Expression::TypedNumber { .. }) => {
+                        let bound = format!("{}_end", variable);
+                        code.push_str(&format!("\n{}let {} = {};", indent(level), bound, self.expression_to_move(end)));
+                        self.state_vars.borrow_mut().remove(&bound);
+                        self.string_locals.borrow_mut().remove(&bound);
+                        let Expression::Binary { op, left, .. } = condition else { unreachable!() };
+                        Expression::Binary { op: op.clone(), left: left.clone(), right: Box::new(Expression::Identifier(bound)) }
+                    }
+                    _ => condition.clone(),
+                };
+                format!("{}\n{}while ({}) {};",
+                    code,
+                    indent(level),
+                    self.expression_to_move(&condition),
+                    self.block_to_move(&body, level, false))
+            }),
+            // 按下标遍历 vector; 取出元素后立即递增下标, continue 不会跳过
+            Statement::ForEach { variable, iterable, body } => self.scoped(|| {
+                let vector = self.expression_to_move(iterable);
+                self.state_vars.borrow_mut().remove(variable);
+                self.string_locals.borrow_mut().remove(variable);
+                let index = format!("{}_index", variable);
+                let len = format!("{}_len", variable);
+                let mut code = CodeWriter::new(level);
+                code.line(&format!("let {} = vector::length(&{});", len, vector));
+                code.open(&format!("while ({} < {})", index, len));
+                code.line(&format!("let {} = *vector::borrow(&{}, {});", variable, vector, index));
+                code.line(&format!("{} = {} + 1;", index, index));
+                self.write_sequence(&mut code, body, false);
+                code.close(";");
+                // 第一行由调用方缩进
+                format!("let {} = 0;\n{}", index, code.finish().trim_end())
+            }),
+            Statement::Match { value, arms } => {
+                // Move 没有 match, 降级为 if/else 链
+                let mut code = String::new();
+                let subject = match value {
+                    Expression::Identifier(id) => id.clone(),
+                    _ => {
+                        code.push_str(&format!("let match_value = {};\n{}", self.expression_to_move(value), indent(level)));
+                        "match_value".to_string()
+                    }
+                };
                 
-                if let Some(else_b) = else_block {
-                    code.push_str(" else {\n");
-                    for s in else_b {
-                        code.push_str("            ");
-                        code.push_str(&self.statement_to_move(s));
-                        code.push_str("\n");
+                for (i, arm) in arms.iter().enumerate() {
+                    match self.pattern_to_move(&arm.pattern, &subject) {
+                        Some(cond) if i == 0 => code.push_str(&format!("if ({}) ", cond)),
+                        Some(cond) => code.push_str(&format!(" else if ({}) ", cond)),
+                        None if i == 0 => {}
+                        None => code.push_str(" else "),
+                    }
+                    code.push_str(&self.block_to_move(&arm.body, level, tail));
+                    
+                    // 通配符之后的分支不可达
+                    if arm.pattern == Pattern::Wildcard {
+                        break;
                     }
-                    code.push_str("        }");
                 }
                 code
             },
+            // 按位置把参数对应到事件声明的字段
             Statement::Emit { event, args } => {
-                format!("event::emit({} {{ /* fields */ }});", event)
+                let fields = self.events.borrow().get(event).cloned().unwrap_or_default();
+                let fields: Vec<String> = fields.iter().zip(args)
+                    .map(|(name, arg)| {
+                        let value = self.expression_to_move(arg);
+                        if value == *name { value } else { format!("{}: {}", name, value) }
+                    })
+                    .collect();
+                format!("event::emit({} {{ {} }});", event, fields.join(", "))
             },
-            Statement::Return { value } => {
-                if let Some(v) = value {
-                    self.expression_to_move(v)
-                } else {
-                    "".to_string()
-                }
+            // 函数末尾的 return 写成函数的值, 其他位置的提前返回用 return
+            Statement::Return { value } => match (value, tail) {
+                (Some(v), true) => self.expression_to_move(v),
+                (Some(v), false) => format!("return {}", self.expression_to_move(v)),
+                (None, true) => "".to_string(),
+                (None, false) => "return".to_string(),
             },
+            Statement::Break => "break;".to_string(),
+            Statement::Continue => "continue;".to_string(),
+            Statement::Expression(expr) => format!("{};", self.expression_to_move(expr)),
+            Statement::Block(body) => format!("{};", self.block_to_move(body, level, false)),
             _ => "// TODO".to_string(),
         }
     }
 
+    /// Condition testing `subject` against a pattern, `None` for the wildcard
+    fn pattern_to_move(&self, pattern: &Pattern, subject: &str) -> Option<String> {
+        match pattern {
+            Pattern::Literal(expr) => Some(format!("{} == {}", subject, self.expression_to_move(expr))),
+            Pattern::Range { start, end, inclusive } => Some(format!(
+                "{} >= {} && {} {} {}",
+                subject, start, subject, if *inclusive { "<=" } else { "<" }, end
+            )),
+            Pattern::Wildcard => None,
+        }
+    }
+
     fn expression_to_move(&self, expr: &Expression) -> String {
         match expr {
             Expression::Number(n) => n.to_string(),
+            Expression::TypedNumber { value, ty } => format!("{}{}", value, self.type_to_move(ty)),
             Expression::Bool(b) => b.to_string(),
-            Expression::String(s) => format!("b\"{}\"", s),
+            Expression::String(s) => self.string_literal(s),
+            Expression::Bytes(bytes) => format!("x\"{}\"", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+            Expression::Identifier(id) if self.state_vars.borrow().contains_key(id) => format!("state.{}", id),
+            Expression::Identifier(id) if self.string_constants.borrow().contains(id) && !self.string_locals.borrow().contains(id) => {
+                match self.chain {
+                    MoveChain::Aptos => format!("string::utf8({})", id),
+                    MoveChain::Sui => format!("ascii::string({})", id),
+                }
+            },
+            // 不存在的条目读作零值; 外层的 map 缺少键时同样得到零值.
+            // Sui 的 Table 没有 borrow_with_default, 最内层的键也先检查
+            Expression::Index { .. } if self.map_entry(expr).is_some() => {
+                let (map, keys, ty) = self.map_entry(expr).unwrap();
+                let (last, outer) = keys.split_last().unwrap();
+                let zero = self.zero_value(&ty);
+                let table = self.table_ref(&map, outer, false);
+                let (value, checked) = match self.chain {
+                    MoveChain::Aptos => (format!("*table::borrow_with_default({}, {}, &{})", table, self.expression_to_move(last), zero), outer.len()),
+                    MoveChain::Sui => (format!("*table::borrow({}, {})", table, self.expression_to_move(last)), keys.len()),
+                };
+                if checked == 0 {
+                    return value;
+                }
+                let present: Vec<String> = (0..checked)
+                    .map(|depth| format!("table::contains({}, {})", self.table_ref(&map, &keys[..depth], false), self.expression_to_move(keys[depth])))
+                    .collect();
+                format!("(if ({}) {} else {})", present.join(" && "), value, zero)
+            },
+            // 其他下标都是 vector 的元素, 越界时 abort
+            Expression::Index { array, index } => format!("*{}", self.borrow_element(array, index)),
+            // 结构体值的字段直接从 Table 或 vector 中借用
+            Expression::Field { object, field } => match (self.map_entry(object), object.as_ref()) {
+                (Some((map, keys, _)), _) => {
+                    let (last, outer) = keys.split_last().unwrap();
+                    format!("table::borrow({}, {}).{}", self.table_ref(&map, outer, false), self.expression_to_move(last), field)
+                }
+                (None, Expression::Index { array, index }) => format!("{}.{}", self.borrow_element(array, index), field),
+                (None, _) => format!("{}.{}", self.expression_to_move(object), field),
+            },
             Expression::Identifier(id) => id.clone(),
+            Expression::Binary { op: BinaryOp::Pow, left, right } => {
+                format!("pow({}, {})", self.expression_to_move(left), self.expression_to_move(right))
+            },
+            // Move 的移位数必须是 u8: 未标注类型的字面量直接推断为 u8, 其他值先转换 (超过 255 时 abort)
+            Expression::Binary { op: op @ (BinaryOp::Shl | BinaryOp::Shr), left, right } => {
+                let amount = match right.as_ref() {
+                    Expression::Number(_) | Expression::TypedNumber { ty: Type::U8, .. } | Expression::Cast { ty: Type::U8, .. } => {
+                        self.expression_to_move(right)
+                    }
+                    _ => format!("({} as u8)", self.expression_to_move(right)),
+                };
+                format!("({} {} {})", self.expression_to_move(left), self.binary_op_to_move(op), amount)
+            },
+            // Move 只能比较整数的大小, 字符串按字节的字典序比较
+            Expression::Binary { op: op @ (BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge), left, right }
+                if self.is_string(left) || self.is_string(right) => self.compare_strings(op, left, right),
             Expression::Binary { op, left, right } => {
                 format!("({} {} {})", 
                     self.expression_to_move(left),
                     self.binary_op_to_move(op),
                     self.expression_to_move(right))
             },
+            // Move 的类型转换在值超出范围时会 abort
+            Expression::Cast { expr, ty } => {
+                format!("({} as {})", self.expression_to_move(expr), self.type_to_move(ty))
+            },
+            // 外层括号防止 else 分支吞掉后面的运算符
+            Expression::Ternary { condition, then_expr, else_expr } => {
+                format!("(if ({}) {} else {})",
+                    self.expression_to_move(condition),
+                    self.expression_to_move(then_expr),
+                    self.expression_to_move(else_expr))
+            },
+            Expression::ArrayLiteral(items) => {
+                format!("vector[{}]", items.iter()
+                    .map(|i| self.expression_to_move(i))
+                    .collect::<Vec<_>>()
+                    .join(", "))
+            },
+            // Move 的元组只用于多返回值
+            Expression::TupleLiteral(items) => {
+                format!("({})", items.iter()
+                    .map(|i| self.expression_to_move(i))
+                    .collect::<Vec<_>>()
+                    .join(", "))
+            },
+            // Sui 上被调用的函数可能还要接收 State 对象与 TxContext
             Expression::Call { func, args } => {
                 let func_str = self.expression_to_move(func);
+                let objects = match func.as_ref() {
+                    Expression::Identifier(name) => self.object_args.borrow().get(name).copied().unwrap_or_default(),
+                    _ => ObjectArgs::default(),
+                };
+                let mut call_args: Vec<String> = args.iter().map(|a| self.expression_to_move(a)).collect();
+                if objects.state {
+                    call_args.insert(0, "state".to_string());
+                }
+                match (objects.ctx, self.chain) {
+                    (true, MoveChain::Aptos) => call_args.insert(0, "account".to_string()),
+                    (true, MoveChain::Sui) => call_args.push("ctx".to_string()),
+                    (false, _) => {}
+                }
+                format!("{}({})", func_str, call_args.join(", "))
+            },
+            Expression::OptionSome(value) => format!("std::option::some({})", self.expression_to_move(value)),
+            Expression::OptionNone => "std::option::none()".to_string(),
+            Expression::MethodCall { .. } if expr.is_value_method() => self.value_method_to_move(expr),
+            // 调用其他合约: 每个合约对应同一命名地址下的一个模块, 外部合约的模块已在开头导入
+            Expression::MethodCall { object, method, args } => {
                 let args_str = args.iter()
                     .map(|a| self.expression_to_move(a))
                     .collect::<Vec<_>>()
                     .join(", ");
-                format!("{}({})", func_str, args_str)
+                let target = self.expression_to_move(object);
+                if self.external(&target).is_some() {
+                    format!("{}::{}({})", target.to_lowercase(), method, args_str)
+                } else {
+                    format!("{}::{}::{}({})", self.address, target.to_lowercase(), method, args_str)
+                }
             },
+            Expression::MsgSender => match self.chain {
+                MoveChain::Aptos => "signer::address_of(account)".to_string(),
+                MoveChain::Sui => "tx_context::sender(ctx)".to_string(),
+            },
+            Expression::BlockTimestamp if self.chain == MoveChain::Sui => "(tx_context::epoch_timestamp_ms(ctx) / 1000)".to_string(),
+            Expression::BlockNumber if self.chain == MoveChain::Sui => "tx_context::epoch(ctx)".to_string(),
+            _ => "/* expr */".to_string(),
+        }
+    }
+
+    /// A string literal: `string::utf8(b"...")` on Aptos, `ascii::string(b"...")` on Sui
+    fn string_literal(&self, s: &str) -> String {
+        match self.chain {
+            MoveChain::Aptos => format!("string::utf8(b\"{}\")", s),
+            MoveChain::Sui => format!("ascii::string(b\"{}\")", s),
+        }
+    }
+
+    /// Whether `expr` is a string, as far as the literals, state variables, constants,
+    /// map values, parameters and locals it consists of tell
+    fn is_string(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::String(_) => true,
+            Expression::Identifier(id) => self.string_locals.borrow().contains(id)
+                || self.state_vars.borrow().get(id).is_some_and(|ty| matches!(ty, Type::String(_)))
+                || self.string_constants.borrow().contains(id),
+            Expression::Index { .. } => self.map_entry(expr).is_some_and(|(_, _, ty)| matches!(ty, Type::String(_))),
+            Expression::Ternary { then_expr, else_expr, .. } => self.is_string(then_expr) || self.is_string(else_expr),
+            _ => false,
+        }
+    }
+
+    /// `left op right` for strings, compared byte by byte: through `aptos_std::comparator`
+    /// on Aptos, and on Sui through the module's `compare_bytes`. `==` and `!=` compare
+    /// the values directly, which both string types allow.
+    fn compare_strings(&self, op: &BinaryOp, left: &Expression, right: &Expression) -> String {
+        let (left, right) = (self.expression_to_move(left), self.expression_to_move(right));
+        match self.chain {
+            MoveChain::Aptos => {
+                let ordering = format!("aptos_std::comparator::compare_u8_vector(*string::bytes(&{}), *string::bytes(&{}))", left, right);
+                match op {
+                    BinaryOp::Lt => format!("aptos_std::comparator::is_smaller_than(&{})", ordering),
+                    BinaryOp::Gt => format!("aptos_std::comparator::is_greater_than(&{})", ordering),
+                    BinaryOp::Le => format!("!aptos_std::comparator::is_greater_than(&{})", ordering),
+                    _ => format!("!aptos_std::comparator::is_smaller_than(&{})", ordering),
+                }
+            }
+            MoveChain::Sui => {
+                self.compares_strings.set(true);
+                let ordering = format!("compare_bytes(ascii::as_bytes(&{}), ascii::as_bytes(&{}))", left, right);
+                match op {
+                    BinaryOp::Lt => format!("({} == 0)", ordering),
+                    BinaryOp::Gt => format!("({} == 2)", ordering),
+                    BinaryOp::Le => format!("({} != 2)", ordering),
+                    _ => format!("({} != 0)", ordering),
+                }
+            }
+        }
+    }
+
+    /// `opt.is_some()`, `opt.is_none()`, `opt.unwrap_or(default)` and `map.get(key)`.
+    /// An option read straight from a map tests the table rather than building the option.
+    fn value_method_to_move(&self, expr: &Expression) -> String {
+        let Expression::MethodCall { object, method, args } = expr else { unreachable!() };
+        if let Some((table, key)) = self.map_lookup(object) {
+            let contains = format!("table::contains({}, {})", table, key);
+            return match method.as_str() {
+                "is_some" => contains,
+                "is_none" => format!("!{}", contains),
+                _ => {
+                    let default = self.expression_to_move(&args[0]);
+                    match self.chain {
+                        MoveChain::Aptos => format!("*table::borrow_with_default({}, {}, &{})", table, key, default),
+                        MoveChain::Sui => format!("(if ({}) *table::borrow({}, {}) else {})", contains, table, key, default),
+                    }
+                }
+            };
+        }
+        if let Some((table, key)) = self.map_lookup(expr) {
+            return format!("(if (table::contains({}, {})) std::option::some(*table::borrow({}, {})) else std::option::none())",
+                table, key, table, key);
+        }
+        
+        let option = self.expression_to_move(object);
+        match method.as_str() {
+            "is_some" => format!("std::option::is_some(&{})", option),
+            "is_none" => format!("std::option::is_none(&{})", option),
+            "unwrap_or" => format!("std::option::get_with_default(&{}, {})", option, self.expression_to_move(&args[0])),
             _ => "/* expr */".to_string(),
         }
     }
 
+    /// The table and the key of a `map.get(key)` on a state map
+    fn map_lookup(&self, expr: &Expression) -> Option<(String, String)> {
+        let Expression::MethodCall { object, method, args } = expr else { return None };
+        let Expression::Identifier(map) = object.as_ref() else { return None };
+        if method != "get" || args.len() != 1 || !matches!(self.state_vars.borrow().get(map), Some(Type::Map(..))) {
+            return None;
+        }
+        Some((self.table_ref(map, &[], false), self.expression_to_move(&args[0])))
+    }
+
+    /// A state map entry `expr` reads: the map, its keys from the outermost in and the type
+    /// of the value they reach. `None` for anything else.
+    fn map_entry<'e>(&self, mut expr: &'e Expression) -> Option<(String, Vec<&'e Expression>, Type)> {
+        let mut keys = Vec::new();
+        while let Expression::Index { array, index } = expr {
+            keys.push(index.as_ref());
+            expr = array;
+        }
+        let Expression::Identifier(map) = expr else { return None };
+        let mut ty = self.state_vars.borrow().get(map)?.clone();
+        if keys.is_empty() {
+            return None;
+        }
+        keys.reverse();
+        for _ in &keys {
+            let Type::Map(_, value) = ty else { return None };
+            ty = *value;
+        }
+        Some((map.clone(), keys, ty))
+    }
+
+    /// A reference to the table of state map `map` that `keys` lead to
+    fn table_ref(&self, map: &str, keys: &[&Expression], mutable: bool) -> String {
+        let (borrow, prefix) = if mutable { ("borrow_mut", "&mut ") } else { ("borrow", "&") };
+        keys.iter().fold(format!("{}state.{}", prefix, map), |table, key| {
+            format!("table::{}({}, {})", borrow, table, self.expression_to_move(key))
+        })
+    }
+
+    /// `map[k1]..[kn] = value`: inner tables are created on first use and the entry is
+    /// inserted or replaced. A value that reads the state maps is computed first, since
+    /// the table stays mutably borrowed while `upsert` runs.
+    fn map_write(&self, map: &str, keys: &[&Expression], value: &Expression, level: usize) -> String {
+        let (last, outer) = keys.split_last().unwrap();
+        let new_table = self.zero_value(&Type::Map(Box::new(Type::Bool), Box::new(Type::Bool)));
+        let mut lines = Vec::new();
+        for depth in 0..outer.len() {
+            let key = self.expression_to_move(outer[depth]);
+            lines.push(format!("if (!table::contains({}, {})) table::add({}, {}, {});",
+                self.table_ref(map, &outer[..depth], false), key, self.table_ref(map, &outer[..depth], true), key, new_table));
+        }
+        let mut reads_maps = false;
+        visit_expression(value, &mut |e| reads_maps |= self.map_entry(e).is_some() || self.map_lookup(e).is_some());
+        let value = if reads_maps {
+            lines.push(format!("let new_{} = {};", map, self.expression_to_move(value)));
+            format!("new_{}", map)
+        } else {
+            self.expression_to_move(value)
+        };
+        let (table, key) = (self.table_ref(map, outer, true), self.expression_to_move(last));
+        lines.push(match self.chain {
+            MoveChain::Aptos => format!("table::upsert({}, {}, {});", table, key, value),
+            // Sui 的 Table 没有 upsert
+            MoveChain::Sui => format!("if (table::contains({}, {})) {{ *table::borrow_mut({}, {}) = {} }} else {{ table::add({}, {}, {}) }};",
+                self.table_ref(map, outer, false), key, table, key, value, table, key, value),
+        });
+        lines.join(&format!("\n{}", indent(level)))
+    }
+
     fn binary_op_to_move(&self, op: &BinaryOp) -> &str {
         match op {
             BinaryOp::Add => "+",
@@ -229,7 +1418,7 @@ impl MoveCodeGenerator {
             BinaryOp::Mul => "*",
             BinaryOp::Div => "/",
             BinaryOp::Mod => "%",
-            BinaryOp::Pow => "^",  // Move doesn't have pow operator
+            BinaryOp::Pow => "**",  // Move 没有幂运算符, 在 expression_to_move 中改写为 pow()
             BinaryOp::Eq => "==",
             BinaryOp::Ne => "!=",
             BinaryOp::Lt => "<",
@@ -246,13 +1435,440 @@ impl MoveCodeGenerator {
         }
     }
     
-    fn lvalue_to_move(&self, lvalue: &LValue) -> String {
+    /// `vector::borrow(&array, index)`; an element of an element borrows from the reference
+    /// to the outer one rather than copying it
+    fn borrow_element(&self, array: &Expression, index: &Expression) -> String {
+        let vector = match array {
+            Expression::Index { array: outer, index: outer_index } if self.map_entry(array).is_none() => {
+                self.borrow_element(outer, outer_index)
+            }
+            _ => format!("&{}", self.expression_to_move(array)),
+        };
+        format!("vector::borrow({}, {})", vector, self.expression_to_move(index))
+    }
+
+    /// The Move place `lvalue` writes to, and whether it is a mutable reference that
+    /// assigning the whole value has to go through with `*`. Locals and state fields are
+    /// assigned directly; map entries and vector elements are borrowed mutably, and
+    /// fields are reached through those borrows without a deref.
+    fn lvalue_to_move(&self, lvalue: &LValue) -> (String, bool) {
         match lvalue {
-            LValue::Identifier(name) => name.clone(),
-            LValue::Index { array, index } => {
-                // array is a String (identifier name)
-                format!("{}[{}]", array, self.expression_to_move(index))
+            LValue::Identifier(name) if self.state_vars.borrow().contains_key(name) => (format!("state.{}", name), false),
+            LValue::Identifier(name) => (name.clone(), false),
+            LValue::Index { array, index } => match self.map_entry(&lvalue_to_expression(lvalue)) {
+                Some((map, keys, _)) => {
+                    let (last, outer) = keys.split_last().unwrap();
+                    (format!("table::borrow_mut({}, {})", self.table_ref(&map, outer, true), self.expression_to_move(last)), true)
+                }
+                None => {
+                    let vector = match self.lvalue_to_move(array) {
+                        (place, true) => place,
+                        (place, false) => format!("&mut {}", place),
+                    };
+                    (format!("vector::borrow_mut({}, {})", vector, self.expression_to_move(index)), true)
+                }
             },
+            LValue::Field { object, field } => (format!("{}.{}", self.lvalue_to_move(object).0, field), false),
+        }
+    }
+}
+
+/// Abort code constants the module declares, as (name, code, message). Division
+/// by zero is 1 when the module can divide by a runtime value, a second call of the
+/// constructor 2, a call of the constructor on Aptos by another account than the
+/// module's 3; a require's code is the hash of its message, so adding or
+/// reordering requires keeps the other codes.
+fn abort_codes(contract: &Contract, chain: MoveChain) -> Vec<(String, u64, String)> {
+    let errors = require_errors(contract, BUILTIN_ERRORS, E_REQUIREMENT_FAILED, abort_code_name);
+    let reuses = |builtin: (&str, &str)| errors.iter().any(|e| e.builtin && e.name == builtin.0);
+    let mut codes = Vec::new();
+    if reuses(E_DIVISION_BY_ZERO) || uses_expression(contract, |expr| matches!(expr,
+        Expression::Binary { op: BinaryOp::Div | BinaryOp::Mod, right, .. }
+            if !matches!(**right, Expression::Number(_) | Expression::TypedNumber { .. })))
+    {
+        codes.push((E_DIVISION_BY_ZERO.0.to_string(), 1, E_DIVISION_BY_ZERO.1.to_string()));
+    }
+    if reuses(E_ALREADY_INITIALIZED) || state_constructor(contract).is_some() {
+        codes.push((E_ALREADY_INITIALIZED.0.to_string(), 2, E_ALREADY_INITIALIZED.1.to_string()));
+    }
+    if reuses(E_NOT_MODULE_ADDRESS) || (chain == MoveChain::Aptos && state_constructor(contract).is_some()) {
+        codes.push((E_NOT_MODULE_ADDRESS.0.to_string(), 3, E_NOT_MODULE_ADDRESS.1.to_string()));
+    }
+    let mut taken: HashSet<u64> = [0, 1, 2, 3].into();
+    for error in errors.into_iter().filter(|e| !e.builtin) {
+        let message = error.message.unwrap_or_else(|| "Requirement failed".to_string());
+        // 哈希冲突时顺延到下一个未使用的码
+        let mut code = message_hash(&message);
+        while !taken.insert(code) {
+            code += 1;
+        }
+        codes.push((error.name, code, message));
+    }
+    codes
+}
+
+/// 32-bit FNV-1a hash of an abort message; fixed by the algorithm, unlike `std`'s hashers
+fn message_hash(message: &str) -> u64 {
+    message.bytes().fold(0x811c_9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) as u64
+}
+
+fn uses_expression(contract: &Contract, predicate: impl Fn(&Expression) -> bool) -> bool {
+    let mut found = false;
+    for func in &contract.functions {
+        visit_expressions(&func.body, &mut |expr| found |= predicate(expr));
+    }
+    for value in contract.state.iter().filter_map(|v| v.initial_value.as_ref()) {
+        visit_expression(value, &mut |expr| found |= predicate(expr));
+    }
+    found
+}
+
+/// The abilities of each declared struct: those its uses require (see `required_abilities`),
+/// and `drop` when its fields allow it, so that values can go out of scope
+fn struct_abilities(contract: &Contract, chain: MoveChain) -> HashMap<String, Vec<&'static str>> {
+    let required = required_abilities(contract, chain);
+    contract.structs.iter()
+        .map(|def| {
+            let required = required.get(&def.name);
+            let abilities = ABILITIES.into_iter()
+                .filter(|ability| {
+                    required.is_some_and(|r| r.contains_key(ability))
+                        || (*ability == "drop" && has_ability(&Type::Struct(def.name.clone()), ability, &contract.structs, &mut Vec::new()))
+                })
+                .collect();
+            (def.name.clone(), abilities)
+        })
+        .collect()
+}
+
+/// Why each struct needs each ability. Values kept in state need `store`; values a function
+/// returns are copied out of state, so they need `copy` and `drop`; event fields need the
+/// abilities of the event struct. A struct's fields need every ability the struct needs.
+fn required_abilities(contract: &Contract, chain: MoveChain) -> HashMap<String, BTreeMap<&'static str, String>> {
+    let mut required = HashMap::new();
+    for var in &contract.state {
+        require_ability(&var.ty, "store", &format!("it is stored in state variable '{}'", var.name), &contract.structs, &mut required);
+    }
+    for func in &contract.functions {
+        if let Some(ty) = &func.return_type {
+            for ability in ["copy", "drop"] {
+                require_ability(ty, ability, &format!("function '{}' returns it", func.name), &contract.structs, &mut required);
+            }
+        }
+    }
+    let event_abilities: &[&'static str] = match chain {
+        MoveChain::Aptos => &["drop", "store"],
+        MoveChain::Sui => &["copy", "drop"],
+    };
+    for event in &contract.events {
+        for param in &event.params {
+            for ability in event_abilities {
+                require_ability(&param.ty, ability, &format!("it is a field of event '{}'", event.name), &contract.structs, &mut required);
+            }
+        }
+    }
+    required
+}
+
+fn require_ability(
+    ty: &Type,
+    ability: &'static str,
+    reason: &str,
+    structs: &[StructDefinition],
+    required: &mut HashMap<String, BTreeMap<&'static str, String>>,
+) {
+    match ty {
+        Type::Struct(name) => {
+            let Some(def) = structs.iter().find(|s| s.name == *name) else { return };
+            let needs = required.entry(name.clone()).or_default();
+            // 已经记录过的要求不再展开, 也避免了自引用的结构体无限递归
+            if needs.contains_key(ability) {
+                return;
+            }
+            needs.insert(ability, reason.to_string());
+            for field in &def.fields {
+                let reason = format!("it is the type of field '{}' of '{}', which needs `{}` because {}", field.name, name, ability, reason);
+                require_ability(&field.ty, ability, &reason, structs, required);
+            }
+        }
+        // Table 的键要能复制和丢弃, 值要能保存
+        Type::Map(key, value) => {
+            for ability in ABILITIES {
+                require_ability(key, ability, "it is a map key", structs, required);
+            }
+            require_ability(value, "store", reason, structs, required);
+        }
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => require_ability(t, ability, reason, structs, required),
+        Type::Tuple(types) => types.iter().for_each(|t| require_ability(t, ability, reason, structs, required)),
+        Type::Result(ok, err) => {
+            require_ability(ok, ability, reason, structs, required);
+            require_ability(err, ability, reason, structs, required);
+        }
+        _ => {}
+    }
+}
+
+/// Whether values of `ty` can have `ability`: a `Table` only has `store`, and a struct has
+/// an ability only if all its fields do. `seen` guards against structs that contain themselves.
+fn has_ability<'a>(ty: &'a Type, ability: &str, structs: &'a [StructDefinition], seen: &mut Vec<&'a str>) -> bool {
+    match ty {
+        Type::Map(..) => ability == "store",
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => has_ability(t, ability, structs, seen),
+        Type::Tuple(types) => types.iter().all(|t| has_ability(t, ability, structs, seen)),
+        Type::Result(ok, err) => has_ability(ok, ability, structs, seen) && has_ability(err, ability, structs, seen),
+        Type::Struct(name) => {
+            if seen.contains(&name.as_str()) {
+                return false;
+            }
+            seen.push(name);
+            let has = structs.iter()
+                .find(|s| s.name == *name)
+                .is_some_and(|def| def.fields.iter().all(|f| has_ability(&f.ty, ability, structs, seen)));
+            seen.pop();
+            has
         }
+        _ => true,
+    }
+}
+
+/// The first ability a struct needs but can't have, because one of its own fields
+/// (not a nested struct's, which is reported for that struct) lacks it
+fn check_struct_abilities(contract: &Contract, chain: MoveChain) -> Result<()> {
+    let required = required_abilities(contract, chain);
+    for def in &contract.structs {
+        let Some(needs) = required.get(&def.name) else { continue };
+        for (ability, reason) in needs {
+            let missing = def.fields.iter()
+                .find(|f| !matches!(f.ty, Type::Struct(_)) && !has_ability(&f.ty, ability, &contract.structs, &mut Vec::new()));
+            if let Some(field) = missing {
+                return Err(anyhow!(
+                    "Struct '{}' needs `{}` because {}, but its field '{}' is a map, and a Table can't be copied or dropped",
+                    def.name, ability, reason, field.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The constructor, when the contract has a `State` for it to create
+pub(super) fn state_constructor(contract: &Contract) -> Option<&Function> {
+    contract.constructor().filter(|_| !contract.state.is_empty())
+}
+
+/// Public functions without a return value can be called by transactions; `view`
+/// functions are queries, never transactions
+pub(super) fn is_entry(func: &Function) -> bool {
+    matches!(func.visibility, Visibility::Public | Visibility::External) && !func.is_view && func.return_type.is_none()
+}
+
+/// The arguments each Aptos function takes besides its declared parameters. A function
+/// takes the sender's `&signer` if it reads `msg_sender()` or creates `State`, directly or
+/// through its callees; a private helper that uses state takes it from its caller, which
+/// could not call a function borrowing `State` again while holding it.
+fn aptos_args(contract: &Contract) -> HashMap<String, ObjectArgs> {
+    let mut needs_signer: HashSet<String> = contract.functions.iter()
+        .filter(|func| {
+            let mut reads_sender = false;
+            visit_expressions(&func.body, &mut |expr| reads_sender |= matches!(expr, Expression::MsgSender));
+            reads_sender || state_constructor(contract).is_some_and(|c| c.name == func.name)
+        })
+        .map(|func| func.name.clone())
+        .collect();
+    
+    // 调用需要 signer 的函数时要把它传下去
+    let callees: HashMap<&str, HashSet<String>> = contract.functions.iter()
+        .map(|func| (func.name.as_str(), internal_calls(func)))
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (caller, called) in &callees {
+            if !needs_signer.contains(*caller) && called.iter().any(|name| needs_signer.contains(name)) {
+                needs_signer.insert(caller.to_string());
+                changed = true;
+            }
+        }
+    }
+    
+    let access = helper_state_access(contract);
+    contract.functions.iter()
+        .map(|func| {
+            let uses_state = access[&func.name].reads || access[&func.name].writes;
+            let args = ObjectArgs {
+                state: uses_state && func.visibility == Visibility::Private && !is_constructor(contract, func),
+                ctx: needs_signer.contains(&func.name),
+            };
+            (func.name.clone(), args)
+        })
+        .collect()
+}
+
+/// Each function's use of `State`, with that of the private helpers it calls, which
+/// use the caller's borrow; other functions borrow `State` themselves
+fn helper_state_access(contract: &Contract) -> HashMap<String, StateAccess> {
+    let state: HashSet<&str> = contract.state.iter().map(|v| v.name.as_str()).collect();
+    let private: HashSet<&str> = contract.functions.iter()
+        .filter(|f| f.visibility == Visibility::Private && !is_constructor(contract, f))
+        .map(|f| f.name.as_str())
+        .collect();
+    let mut access: HashMap<String, StateAccess> = contract.functions.iter()
+        .map(|func| (func.name.clone(), direct_state_access(func, &state)))
+        .collect();
+    let callees: Vec<(&str, Vec<String>)> = contract.functions.iter()
+        .map(|func| (func.name.as_str(), internal_calls(func).into_iter().filter(|name| private.contains(name.as_str())).collect()))
+        .collect();
+    
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (caller, called) in &callees {
+            let inherited = called.iter()
+                .filter_map(|name| access.get(name).copied())
+                .fold(access[*caller], |acc, callee| StateAccess {
+                    reads: acc.reads || callee.reads,
+                    writes: acc.writes || callee.writes,
+                });
+            if inherited != access[*caller] {
+                access.insert(caller.to_string(), inherited);
+                changed = true;
+            }
+        }
+    }
+    access
+}
+
+/// Functions that borrow `State` themselves or call one that does, and so must declare `acquires State`
+fn acquires_state(contract: &Contract, args: &HashMap<String, ObjectArgs>, access: &HashMap<String, StateAccess>) -> HashSet<String> {
+    let mut acquires: HashSet<String> = contract.functions.iter()
+        .filter(|func| !args[&func.name].state && (access[&func.name].reads || access[&func.name].writes))
+        .map(|func| func.name.clone())
+        .collect();
+    let callees: Vec<(&str, HashSet<String>)> = contract.functions.iter()
+        .map(|func| (func.name.as_str(), internal_calls(func)))
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (caller, called) in &callees {
+            if !acquires.contains(*caller) && called.iter().any(|name| acquires.contains(name)) {
+                acquires.insert(caller.to_string());
+                changed = true;
+            }
+        }
+    }
+    acquires
+}
+
+/// Names of the functions of the same contract that `func` calls
+fn internal_calls(func: &Function) -> HashSet<String> {
+    let mut called = HashSet::new();
+    visit_expressions(&func.body, &mut |expr| {
+        if let Expression::Call { func, .. } = expr {
+            if let Expression::Identifier(name) = func.as_ref() {
+                called.insert(name.clone());
+            }
+        }
+    });
+    called
+}
+
+/// Whether the module needs `std::vector`: for-in loops iterate with it, and indexing
+/// anything but a state map borrows a vector element
+pub(super) fn uses_vectors(contract: &Contract) -> bool {
+    let maps: HashSet<&str> = contract.state.iter()
+        .filter(|v| matches!(v.ty, Type::Map(..)))
+        .map(|v| v.name.as_str())
+        .collect();
+    let indexes_vector = |expr: &Expression| {
+        let Expression::Index { array, .. } = expr else { return false };
+        let mut root = array.as_ref();
+        while let Expression::Index { array, .. } = root {
+            root = array;
+        }
+        !matches!(root, Expression::Identifier(name) if maps.contains(name.as_str()))
+    };
+    let mut uses = false;
+    for func in &contract.functions {
+        visit_statements(&func.body, &mut |stmt| match stmt {
+            Statement::ForEach { .. } => uses = true,
+            Statement::Assign { target, .. } => uses |= indexes_vector(&lvalue_to_expression(target)),
+            _ => {}
+        });
+        visit_expressions(&func.body, &mut |expr| uses |= indexes_vector(expr));
+    }
+    uses
+}
+
+/// Whether any declared type is a map, which is generated as a `Table` (nested maps as a `Table` of `Table`s)
+pub(super) fn uses_tables(contract: &Contract) -> bool {
+    let mut declared = contract.state.iter().map(|v| &v.ty)
+        .chain(contract.structs.iter().flat_map(|s| s.fields.iter().map(|f| &f.ty)))
+        .chain(contract.functions.iter().flat_map(|f| f.params.iter().map(|p| &p.ty).chain(&f.return_type)));
+    declared.any(has_map)
+}
+
+/// Whether the module uses strings, which it imports `String` for: in its declarations
+/// or as literals
+pub(super) fn uses_strings(contract: &Contract) -> bool {
+    let mut declared = contract.state.iter().map(|v| &v.ty)
+        .chain(contract.constants.iter().map(|c| &c.ty))
+        .chain(contract.structs.iter().flat_map(|s| s.fields.iter().map(|f| &f.ty)))
+        .chain(contract.events.iter().flat_map(|e| e.params.iter().map(|p| &p.ty)))
+        .chain(contract.functions.iter().flat_map(|f| f.params.iter().map(|p| &p.ty).chain(&f.return_type)));
+    declared.any(has_string) || uses_expression(contract, |expr| matches!(expr, Expression::String(_)))
+}
+
+/// Whether `ty` is or contains a string
+fn has_string(ty: &Type) -> bool {
+    match ty {
+        Type::String(_) => true,
+        Type::Map(k, v) => has_string(k) || has_string(v),
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => has_string(t),
+        Type::Result(ok, err) => has_string(ok) || has_string(err),
+        Type::Tuple(types) => types.iter().any(has_string),
+        _ => false,
+    }
+}
+
+/// Whether `ty` is or contains a map, which the module needs `Table` for
+fn has_map(ty: &Type) -> bool {
+    match ty {
+        Type::Map(..) => true,
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => has_map(t),
+        Type::Result(ok, err) => has_map(ok) || has_map(err),
+        Type::Tuple(types) => types.iter().any(has_map),
+        _ => false,
+    }
+}
+
+/// `body` with `update` run before every `continue` that targets this loop
+/// (nested loops keep their own `continue`s)
+fn run_before_continue(body: &[Statement], update: &Statement) -> Vec<Statement> {
+    body.iter()
+        .flat_map(|stmt| match stmt {
+            Statement::Continue => vec![update.clone(), Statement::Continue],
+            Statement::If { condition, then_block, else_block } => vec![Statement::If {
+                condition: condition.clone(),
+                then_block: run_before_continue(then_block, update),
+                else_block: else_block.as_ref().map(|stmts| run_before_continue(stmts, update)),
+            }],
+            Statement::Block(stmts) => vec![Statement::Block(run_before_continue(stmts, update))],
+            Statement::Match { value, arms } => vec![Statement::Match {
+                value: value.clone(),
+                arms: arms.iter()
+                    .map(|arm| MatchArm { body: run_before_continue(&arm.body, update), ..arm.clone() })
+                    .collect(),
+            }],
+            other => vec![other.clone()],
+        })
+        .collect()
+}
+
+/// SCREAMING_SNAKE abort code constant for a require message, e.g. `Only owner` -> `E_ONLY_OWNER`
+fn abort_code_name(words: &[&str]) -> String {
+    if words.is_empty() {
+        return "E_FAILED".to_string();
     }
-}
\ No newline at end of file
+    format!("E_{}", words.iter().map(|w| w.to_uppercase()).collect::<Vec<_>>().join("_"))
+}
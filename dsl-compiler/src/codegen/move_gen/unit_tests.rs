@@ -0,0 +1,415 @@
+use super::{abort_code_name, abort_codes, aptos_args, state_constructor, MoveChain, MoveCodeGenerator, ObjectArgs, BUILTIN_ERRORS, DEV_ADDRESS, E_ALREADY_INITIALIZED, E_REQUIREMENT_FAILED};
+use crate::codegen::sui_gen::object_args;
+use crate::codegen::{integer_bytes, require_errors, require_violation, state_access, visit_statements, BreakingValue, Violation};
+use crate::{Contract, Expression, Function, LValue, Statement, Type, Visibility};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+// 发布模块的账户, 测试默认由它签名
+const DEPLOYER: &str = "deployer";
+// 访问控制失败的用例由这个账户签名
+const OTHER: (&str, &str) = ("other", "@0xb0b");
+// Sui 测试中当前交易的 TxContext
+const SUI_CTX: &str = "test_scenario::ctx(&mut scenario)";
+
+impl MoveCodeGenerator {
+    /// A `#[test_only]` module testing the module `generate` emits, for the package's
+    /// `tests/` directory and `aptos move test` or `sui move test`.
+    ///
+    /// Each test first publishes `State` from the account of the module's named address,
+    /// the same one Move.toml declares, through the constructor or `init_module`; the
+    /// constructor's own test checks that calling it again aborts. Every public function
+    /// gets a test calling it with default arguments (zeros, empty vectors, `@0x0`)
+    /// signed by that account, which then checks the state variables the function sets
+    /// from its parameters through the getters that return them.
+    /// Every `require` message in its body gets an `#[expected_failure]` test with
+    /// its abort code, for the conditions whose failing arguments can be derived as
+    /// for the Solana tests; the others are left as a TODO.
+    ///
+    /// On Sui the tests run in a `test_scenario`: the deployer's first transaction
+    /// creates the shared `State` through `init_for_testing`, the next one runs the
+    /// constructor on it, if there is one, and each test takes it for the call and
+    /// returns it afterwards.
+    pub fn generate_tests(&self, contract: &Contract) -> Result<String> {
+        let contract = self.prepare(contract)?;
+        let name = contract.name.to_lowercase();
+        let module = match self.chain {
+            MoveChain::Aptos => Module { name: &name, chain: self.chain, objects: aptos_args(&contract), writes: HashMap::new() },
+            MoveChain::Sui => Module {
+                name: &name,
+                chain: self.chain,
+                objects: object_args(&contract),
+                // 构造函数总是写入 is_initialized
+                writes: state_access(&contract, |_| true).into_iter()
+                    .map(|(func, access)| {
+                        let constructs = state_constructor(&contract).is_some_and(|c| c.name == func);
+                        (func, access.writes || constructs)
+                    })
+                    .collect(),
+            },
+        };
+        let errors: HashMap<Option<String>, String> = require_errors(&contract, BUILTIN_ERRORS, E_REQUIREMENT_FAILED, abort_code_name)
+            .into_iter()
+            .map(|e| (e.message, e.name))
+            .collect();
+        let codes: HashMap<String, u64> = abort_codes(&contract, self.chain).into_iter()
+            .map(|(name, code, _)| (name, code))
+            .collect();
+        let constructor = state_constructor(&contract);
+        let getters = getters(&contract);
+
+        let mut tests = String::new();
+        for func in &contract.functions {
+            if !matches!(func.visibility, Visibility::Public | Visibility::External) {
+                continue;
+            }
+            let Some(defaults) = func.params.iter().map(|p| default_value(&p.ty, self.chain)).collect::<Option<Vec<_>>>() else {
+                tests.push_str(&format!("    // TODO: test {}, whose parameters a test module can't construct\n\n", func.name));
+                continue;
+            };
+            let call = module.call(func, defaults);
+
+            if constructor.is_some_and(|c| c.name == func.name) {
+                tests.push_str("    #[test]\n");
+                tests.push_str(&format!("    fun test_{}() {{\n", func.name));
+                tests.push_str(&format!("        // setup() 已调用 {}\n", func.name));
+                match self.chain {
+                    MoveChain::Aptos => tests.push_str("        setup();\n"),
+                    MoveChain::Sui => tests.push_str("        test_scenario::end(setup());\n"),
+                }
+                tests.push_str("    }\n\n");
+
+                // 再次调用时 abort
+                tests.push_str("    #[test]\n");
+                tests.push_str(&format!("    #[expected_failure(abort_code = {}, location = {}::{})]\n",
+                    codes[E_ALREADY_INITIALIZED.0], self.address(), module.name));
+                tests.push_str(&format!("    fun test_{}_only_runs_once() {{\n", func.name));
+                tests.push_str(&call.render(DEPLOYER));
+                tests.push_str(&call.finish());
+                tests.push_str("    }\n\n");
+            } else {
+                tests.push_str("    #[test]\n");
+                tests.push_str(&format!("    fun test_{}() {{\n", func.name));
+                tests.push_str(&call.render(DEPLOYER));
+                for (getter, key, value) in assertions(func, &getters) {
+                    let key = match key {
+                        Some(Expression::MsgSender) => vec![self.deployer()],
+                        Some(Expression::Identifier(name)) => vec![call.arg(name)],
+                        _ => Vec::new(),
+                    };
+                    tests.push_str(&format!("        assert!({} == {}, 0);\n", module.call(getter, key).expression(DEPLOYER), call.arg(value)));
+                }
+                tests.push_str(&call.finish());
+                tests.push_str("    }\n\n");
+            }
+
+            let mut tested = Vec::new();
+            for stmt in &func.body {
+                let Statement::Require { condition, message: message @ Some(_) } = stmt else { continue };
+                let error = &errors[message];
+                if tested.contains(&error) {
+                    continue;
+                }
+                tested.push(error);
+
+                let name = format!("test_{}_fails_with_{}", func.name, error.to_lowercase());
+                // 构造函数已在 setup() 中执行, 不能再调用
+                let violation = if constructor.is_some_and(|c| c.name == func.name) { None } else { require_violation(condition, &func.params) };
+                let Some(violation) = violation else {
+                    tests.push_str(&format!("    // TODO: {}: set up state or arguments that make this require fail\n\n", name));
+                    continue;
+                };
+                let mut call = module.call(func, call.args.clone());
+                let signer = match violation {
+                    Violation::Argument(index, value) => {
+                        call.args[index] = breaking_value(value, &func.params[index].ty);
+                        DEPLOYER
+                    }
+                    Violation::OtherSigner => OTHER.0,
+                };
+
+                tests.push_str("    #[test]\n");
+                tests.push_str(&format!("    #[expected_failure(abort_code = {}, location = {}::{})]\n", codes[error], self.address(), module.name));
+                tests.push_str(&format!("    fun {}() {{\n", name));
+                tests.push_str(&call.render(signer));
+                tests.push_str(&call.finish());
+                tests.push_str("    }\n\n");
+            }
+        }
+
+        let mut code = String::new();
+        code.push_str("#[test_only]\n");
+        code.push_str(&format!("module {}::{}_tests {{\n", self.address(), name));
+        match self.chain {
+            MoveChain::Aptos => {
+                code.push_str("    use aptos_framework::account;\n");
+                code.push_str(&format!("    use {}::{};\n\n", self.address(), name));
+            }
+            MoveChain::Sui => {
+                code.push_str("    use sui::test_scenario::{Self, Scenario};\n");
+                if contract.state.is_empty() {
+                    code.push_str(&format!("    use {}::{};\n\n", self.address(), name));
+                } else {
+                    code.push_str(&format!("    use {}::{}::{{Self, State}};\n\n", self.address(), name));
+                }
+            }
+        }
+
+        // 模块发布在命名地址上, State 也由这个账户持有; Sui 上由部署者的第一笔交易创建共享的 State,
+        // 下一笔交易对它调用构造函数
+        let created = match constructor {
+            Some(func) => {
+                let args = func.params.iter()
+                    .map(|p| default_value(&p.ty, self.chain).ok_or_else(|| anyhow!(
+                        "Constructor '{}' takes a parameter of type {}, which a test module can't construct", func.name, p.ty)))
+                    .collect::<Result<_>>()?;
+                Some(module.call(func, args).expression(DEPLOYER))
+            }
+            None if !contract.state.is_empty() && self.chain == MoveChain::Aptos => {
+                Some(format!("{}::init_module_for_test(&deployer)", name))
+            }
+            None => None,
+        };
+        match self.chain {
+            MoveChain::Aptos => {
+                code.push_str("    fun setup(): signer {\n");
+                code.push_str(&format!("        let deployer = account::create_account_for_test({});\n", self.deployer()));
+                if let Some(created) = created {
+                    code.push_str(&format!("        {};\n", created));
+                }
+                code.push_str("        deployer\n");
+            }
+            MoveChain::Sui => {
+                code.push_str("    fun setup(): Scenario {\n");
+                code.push_str(&format!("        let scenario = test_scenario::begin({});\n", self.deployer()));
+                if !contract.state.is_empty() {
+                    code.push_str(&format!("        {}::init_for_testing({});\n", name, SUI_CTX));
+                    code.push_str(&format!("        test_scenario::next_tx(&mut scenario, {});\n", self.deployer()));
+                }
+                if let Some(created) = created {
+                    code.push_str("        let state = test_scenario::take_shared<State>(&scenario);\n");
+                    code.push_str(&format!("        {};\n", created));
+                    code.push_str("        test_scenario::return_shared(state);\n");
+                    code.push_str(&format!("        test_scenario::next_tx(&mut scenario, {});\n", self.deployer()));
+                }
+                code.push_str("        scenario\n");
+            }
+        }
+        code.push_str("    }\n\n");
+
+        code.push_str(&tests);
+        // 去掉最后一个测试后的空行
+        if code.ends_with("\n\n") {
+            code.pop();
+        }
+        code.push_str("}\n");
+        Ok(code)
+    }
+
+    /// The account that publishes the module: the named address on Aptos, where `State`
+    /// lives; on Sui, any sender, here the dev address
+    fn deployer(&self) -> String {
+        match self.chain {
+            MoveChain::Aptos => format!("@{}", self.address()),
+            MoveChain::Sui => format!("@{}", DEV_ADDRESS),
+        }
+    }
+}
+
+/// The module under test, and the extra arguments its functions take
+struct Module<'a> {
+    name: &'a str,
+    chain: MoveChain,
+    objects: HashMap<String, ObjectArgs>,
+    // Sui 上各函数是否可变借用 State
+    writes: HashMap<String, bool>,
+}
+
+impl<'a> Module<'a> {
+    fn call(&self, func: &'a Function, args: Vec<String>) -> Call<'a> {
+        Call {
+            module: self.name,
+            chain: self.chain,
+            func,
+            objects: self.objects[&func.name],
+            writes: self.writes.get(&func.name).copied().unwrap_or(false),
+            args,
+        }
+    }
+}
+
+/// One call of a test to a function of the module under test
+struct Call<'a> {
+    module: &'a str,
+    chain: MoveChain,
+    func: &'a Function,
+    // 函数是否接收 State 对象 (Sui) 与 &signer 或 TxContext
+    objects: ObjectArgs,
+    writes: bool,
+    args: Vec<String>,
+}
+
+impl Call<'_> {
+    /// The argument passed for the parameter `name`
+    fn arg(&self, name: &str) -> String {
+        let index = self.func.params.iter().position(|p| p.name == name).expect("a parameter of the function");
+        self.args[index].clone()
+    }
+
+    /// The call, signed by the account in the variable `signer` if the function takes one;
+    /// on Sui the sender is the scenario's, and the function takes the `State` the test took
+    fn expression(&self, signer: &str) -> String {
+        let args: Vec<String> = match self.chain {
+            MoveChain::Aptos => self.objects.ctx.then(|| format!("&{}", signer)).into_iter()
+                .chain(self.args.iter().cloned())
+                .collect(),
+            MoveChain::Sui => {
+                let state = self.objects.state.then(|| if self.writes { "&mut state" } else { "&state" }.to_string());
+                let ctx = self.objects.ctx.then(|| SUI_CTX.to_string());
+                state.into_iter().chain(self.args.iter().cloned()).chain(ctx).collect()
+            }
+        };
+        format!("{}::{}({})", self.module, self.func.name, args.join(", "))
+    }
+
+    /// The body of a test: `setup()`, then the call signed by `signer`, the deployer
+    /// or the other account
+    fn render(&self, signer: &str) -> String {
+        let mut code = String::new();
+        match self.chain {
+            MoveChain::Aptos => match (self.objects.ctx, signer) {
+                (true, DEPLOYER) => code.push_str(&format!("        let {} = setup();\n", DEPLOYER)),
+                (true, _) => {
+                    code.push_str("        setup();\n");
+                    code.push_str(&format!("        let {} = account::create_account_for_test({});\n", OTHER.0, OTHER.1));
+                }
+                (false, _) => code.push_str("        setup();\n"),
+            },
+            MoveChain::Sui => {
+                code.push_str("        let scenario = setup();\n");
+                if signer != DEPLOYER {
+                    code.push_str(&format!("        test_scenario::next_tx(&mut scenario, {});\n", OTHER.1));
+                }
+                if self.objects.state {
+                    code.push_str("        let state = test_scenario::take_shared<State>(&scenario);\n");
+                }
+            }
+        }
+        if self.func.return_type.is_some() {
+            code.push_str(&format!("        let _ = {};\n", self.expression(signer)));
+        } else {
+            code.push_str(&format!("        {};\n", self.expression(signer)));
+        }
+        code
+    }
+
+    /// The end of a test after the call and its checks: on Sui, returning `State` and
+    /// ending the scenario, neither of which can be dropped
+    fn finish(&self) -> String {
+        let mut code = String::new();
+        if self.chain == MoveChain::Sui {
+            if self.objects.state {
+                code.push_str("        test_scenario::return_shared(state);\n");
+            }
+            code.push_str("        test_scenario::end(scenario);\n");
+        }
+        code
+    }
+}
+
+/// Public functions that return a state variable, or the entry of a state map at
+/// their only parameter, by the variable's name
+fn getters(contract: &Contract) -> HashMap<String, &Function> {
+    let mut getters = HashMap::new();
+    for func in contract.functions.iter().filter(|f| matches!(f.visibility, Visibility::Public | Visibility::External)) {
+        let [Statement::Return { value: Some(value) }] = func.body.as_slice() else { continue };
+        let var = match (value, func.params.as_slice()) {
+            (Expression::Identifier(var), []) => var,
+            (Expression::Index { array, index }, [param]) if **index == Expression::Identifier(param.name.clone()) => {
+                let Expression::Identifier(var) = array.as_ref() else { continue };
+                var
+            }
+            _ => continue,
+        };
+        if contract.state.iter().any(|v| v.name == *var) && !func.params.iter().any(|p| p.name == *var) {
+            getters.entry(var.clone()).or_insert(func);
+        }
+    }
+    getters
+}
+
+/// The checks after calling `func`: a getter, the key it takes (for a map entry) and the
+/// parameter it returns, for each state variable `func` sets once, to a parameter, at
+/// the top level of its body
+fn assertions<'a>(func: &'a Function, getters: &HashMap<String, &'a Function>) -> Vec<(&'a Function, Option<&'a Expression>, &'a str)> {
+    let root = |target: &LValue| -> Option<String> {
+        match target {
+            LValue::Identifier(name) => Some(name.clone()),
+            LValue::Index { array, .. } => match array.as_ref() {
+                LValue::Identifier(name) => Some(name.clone()),
+                _ => None,
+            },
+            LValue::Field { .. } => None,
+        }
+    };
+    let mut writes: HashMap<String, usize> = HashMap::new();
+    visit_statements(&func.body, &mut |stmt| {
+        if let Statement::Assign { target, .. } = stmt {
+            if let Some(name) = root(target) {
+                *writes.entry(name).or_default() += 1;
+            }
+        }
+    });
+    let is_param = |name: &str| func.params.iter().any(|p| p.name == name);
+
+    let mut checks = Vec::new();
+    for stmt in &func.body {
+        let Statement::Assign { target, value: Expression::Identifier(value) } = stmt else { continue };
+        let Some(var) = root(target) else { continue };
+        if !is_param(value) || is_param(&var) || writes[&var] != 1 {
+            continue;
+        }
+        let Some(getter) = getters.get(&var) else { continue };
+        match target {
+            LValue::Identifier(_) if getter.params.is_empty() => checks.push((*getter, None, value.as_str())),
+            LValue::Index { index, .. } if getter.params.len() == 1 => match index.as_ref() {
+                Expression::MsgSender => checks.push((*getter, Some(index.as_ref()), value.as_str())),
+                Expression::Identifier(key) if is_param(key) => checks.push((*getter, Some(index.as_ref()), value.as_str())),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    checks
+}
+
+/// The value a test passes for a parameter of type `ty`; a test module can't build
+/// the module's structs or tables
+fn default_value(ty: &Type, chain: MoveChain) -> Option<String> {
+    match ty {
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::U256
+        | Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128 => Some("0".to_string()),
+        Type::Bool => Some("false".to_string()),
+        Type::Address => Some("@0x0".to_string()),
+        Type::String(_) => match chain {
+            MoveChain::Aptos => Some("std::string::utf8(b\"\")".to_string()),
+            MoveChain::Sui => Some("std::ascii::string(b\"\")".to_string()),
+        },
+        Type::Bytes(_) => Some("b\"\"".to_string()),
+        Type::Vec(_) | Type::Array(..) => Some("vector[]".to_string()),
+        Type::Option(_) => Some("std::option::none()".to_string()),
+        Type::Map(..) | Type::Struct(_) | Type::Tuple(_) | Type::Result(..) => None,
+    }
+}
+
+fn breaking_value(value: BreakingValue, ty: &Type) -> String {
+    match value {
+        BreakingValue::Bool(b) => b.to_string(),
+        BreakingValue::Number(n) => n.to_string(),
+        BreakingValue::Max => match integer_bytes(ty).unwrap_or(8) * 8 {
+            256 => "115792089237316195423570985008687907853269984665640564039457584007913129639935".to_string(),
+            128 => u128::MAX.to_string(),
+            bits => ((1u128 << bits) - 1).to_string(),
+        },
+    }
+}
@@ -1,62 +1,994 @@
-use super::super::{Contract, Type, Function, Statement, Expression, BinaryOp, LValue};
-use anyhow::Result;
+use super::super::{Bridge, Contract, EventDefinition, ExternalContract, Parameter, StateVariable, Type, Function, ModifierCall, Statement, Expression, BinaryOp, LValue, Pattern, Visibility};
+use super::writer::{check_rust_output, indent, CodeWriter};
+use super::{called_contracts, check_view_functions, is_constructor, lvalue_to_expression, require_errors, runtime_divisors, state_access, visit_expressions, visit_statements, ArithmeticMode, StateAccess};
+use crate::idl::{
+    AnchorIdl, IdlAccount, IdlErrorCode, IdlEvent, IdlEventField, IdlField, IdlInstruction, IdlMetadata,
+    IdlType, IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use crate::interface::{AccountMeta, Argument, Entrypoint, Seed};
+use crate::modifiers::{expand_modifiers, sender_check};
+use anyhow::{Context, Result, anyhow};
 use handlebars::Handlebars;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+mod anchor_tests;
+mod bridge;
+mod spl;
+
+pub use spl::TokenStandard;
+use spl::{SplPlan, MINT};
+use bridge::{BRIDGE_ERRORS, INIT_BRIDGE, VAULT, VAULT_TYPE};
+
+// 始终生成的错误码 (变体名, 消息)
+const BUILTIN_ERRORS: &[(&str, &str)] = &[
+    ("Unauthorized", "Unauthorized"),
+    ("InsufficientBalance", "Insufficient balance"),
+    ("InvalidParameter", "Invalid parameter"),
+    ("Overflow", "Arithmetic overflow"),
+    ("Underflow", "Arithmetic underflow"),
+    ("DivisionByZero", "Division by zero"),
+];
+
+// 状态中有 string/bytes 时额外生成的错误码, 写入超过最大长度的值时报错
+const LENGTH_ERRORS: &[(&str, &str)] = &[("ValueTooLong", "Value exceeds maximum length")];
+
+// 没有消息的 require 共用的错误码
+const REQUIRE_FAILED: (&str, &str) = ("RequirementFailed", "Requirement failed");
+
+// msg_sender() 对应的签名账户在 Accounts 结构中的字段名
+const SIGNER: &str = "user";
+
+// 创建状态账户的指令; 合约没有构造函数时自动生成一个空的
+const INITIALIZE: &str = "initialize";
+
+// payable 函数额外接收的指令参数: 随调用转入状态账户的 lamports 数量, 即 msg_value()
+pub(super) const MSG_VALUE: &str = "msg_value";
+
+/// Clusters `Anchor.toml` can deploy to
+pub const CLUSTERS: &[&str] = &["localnet", "devnet", "testnet", "mainnet"];
+
+/// Program id used when none is configured: the one `anchor init` generates.
+/// It must be replaced before deploying.
+pub const PLACEHOLDER_PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+
+/// Anchor release the program is generated for, `0.minor.patch`. Releases differ in
+/// how bumps are read (`ctx.bumps.mint` from 0.29) and in `Anchor.toml`'s features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AnchorVersion {
+    minor: u32,
+    patch: u32,
+}
+
+impl AnchorVersion {
+    // 支持的最低与最高版本
+    const OLDEST: u32 = 28;
+    const NEWEST: u32 = 31;
+
+    /// How the instruction reads the canonical bump of the PDA `account`
+    fn bump(&self, account: &str) -> String {
+        if self.minor >= 29 {
+            format!("ctx.bumps.{}", account)
+        } else {
+            format!("*ctx.bumps.get(\"{}\").unwrap()", account)
+        }
+    }
+}
+
+/// 0.29.0, the release the generated code assumed before versions could be selected
+impl Default for AnchorVersion {
+    fn default() -> Self {
+        Self { minor: 29, patch: 0 }
+    }
+}
+
+impl FromStr for AnchorVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unsupported = || format!(
+            "Unsupported Anchor version '{}', expected 0.{} to 0.{} (e.g. 0.30 or 0.30.1)",
+            s, Self::OLDEST, Self::NEWEST);
+        let parts: Vec<&str> = s.trim_start_matches('v').split('.').collect();
+        let (minor, patch) = match parts.as_slice() {
+            ["0", minor] => (minor.parse().map_err(|_| unsupported())?, 0),
+            ["0", minor, patch] => (minor.parse().map_err(|_| unsupported())?, patch.parse().map_err(|_| unsupported())?),
+            _ => return Err(unsupported()),
+        };
+        if !(Self::OLDEST..=Self::NEWEST).contains(&minor) {
+            return Err(unsupported());
+        }
+        Ok(Self { minor, patch })
+    }
+}
+
+impl fmt::Display for AnchorVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0.{}.{}", self.minor, self.patch)
+    }
+}
 
 pub struct SolanaCodeGenerator {
     handlebars: Handlebars<'static>,
+    // 未标注长度的 string/bytes/vec 在计算账户空间时使用的长度, 未设置时报错
+    default_max_len: Option<usize>,
+    // 整数运算溢出时的处理方式
+    arithmetic: ArithmeticMode,
+    // declare_id! 中的程序地址, 未设置时使用占位地址并生成 TODO
+    program_id: Option<String>,
+    // balances/total_supply 存放在程序自己的账户中还是 SPL 代币账户与 mint 中
+    token_standard: TokenStandard,
+    // 程序声明的外部合约, 调用时生成 CPI
+    externals: Vec<ExternalContract>,
+    // 目标 Anchor 版本; 显式设置时写入 Anchor.toml 的 [toolchain]
+    anchor_version: Option<AnchorVersion>,
+    // Anchor.toml 中部署到的集群, 默认 localnet
+    cluster: String,
+    // 生成后用 syn 解析一遍, 无法解析时报错而不是输出
+    check_output: bool,
+    // 正在生成的函数
+    scope: RefCell<FunctionScope>,
+}
+
+/// What the generator knows about the function it is currently lowering
+#[derive(Default)]
+struct FunctionScope {
+    // 已知类型的名字 (常量, 状态变量, 参数, 局部变量), 用于选择 U256 运算
+    known_types: HashMap<String, Type>,
+    // 未被参数或局部变量遮蔽的标量状态变量, 通过 ctx.accounts.state 访问
+    state_vars: HashSet<String>,
+    // SPL 模式下从 mint 账户读取的状态变量 (total_supply, decimals)
+    mint_fields: HashSet<String>,
+    // 函数中被赋值的状态变量, 不能作为 seed (账户在指令执行前就已推导)
+    assigned_state: HashSet<String>,
+    params: HashSet<String>,
+    // 局部变量的初始值, 用于把 map 的键还原成 PDA seed
+    aliases: HashMap<String, Expression>,
+    // 函数访问的 map 条目, 每个条目是一个 PDA 账户
+    map_entries: Vec<MapEntry>,
+    // 合约声明的事件, 用于把 emit 的位置参数对应到字段
+    events: Vec<EventDefinition>,
+    // require 消息对应的错误码
+    errors: HashMap<Option<String>, String>,
+    // 为true时, 嵌套在其他表达式中的检查运算先存入临时变量
+    hoist: bool,
+    // 当前语句之前要声明的临时变量
+    temporaries: Vec<String>,
+    next_temporary: usize,
+    // SPL 模式下余额与总量的写入如何变成代币程序的 CPI
+    spl: Option<SplPlan>,
+    // 合约的 bridge, 释放代币的函数要检查 authority
+    bridge: Option<Bridge>,
+    // 为 true 时正在生成 #[program] 之外的内部函数, 状态通过参数 state 访问
+    helper: bool,
+    // 内部函数及其对 State 的访问, 调用时传入状态
+    helpers: HashMap<String, StateAccess>,
+}
+
+/// A `map[key]` entry a function touches, stored in its own PDA account
+#[derive(Clone)]
+struct MapEntry {
+    map: String,
+    // 还原别名后的键, 相同的键对应同一个账户
+    keys: Vec<Expression>,
+    key_types: Vec<Type>,
+    // Accounts 结构中的字段名
+    field: String,
+    seeds: Vec<String>,
+    writes: bool,
+    // 以 `m[k] = m[k] - x` 扣减: 条目必须已经存在, 不按需创建
+    debits: bool,
+    // 只通过 `map.get(key)` 读取: 条目可能不存在, 账户不经 Anchor 反序列化
+    optional: bool,
+}
+
+/// One field of an instruction's Accounts struct
+struct InstructionAccount {
+    name: String,
+    kind: AccountKind,
+    mutable: bool,
+    // 额外的 Anchor 约束, 如签名账户上的 `address = state.owner @ ErrorCode::NotOwner`
+    constraints: Vec<String>,
+}
+
+enum AccountKind {
+    Signer,
+    // init 为 true 时由该指令创建
+    State { init: bool },
+    MapEntry(MapEntry),
+    // 被调用合约的程序账户
+    Program(String),
+    // 外部合约的程序账户, 地址是声明中的程序 id
+    ExternalProgram { contract: String, address: String },
+    System,
+    // SPL 模式下的 mint (seeds = [b"mint"]); 由该指令创建时带有精度
+    Mint { decimals: Option<String> },
+    // bridge 的金库 (seeds = [b"bridge_vault"]); init 为 true 时由该指令创建
+    BridgeVault { init: bool },
+    // SPL 模式下 balances 条目对应的关联代币账户; owner 是 associated_token::authority
+    TokenAccount { entry: MapEntry, owner: String, init: bool },
+    // 创建关联代币账户时需要的持有者账户, 地址必须等于给定的键
+    Wallet(String),
+    TokenProgram,
+    AssociatedTokenProgram,
+}
+
+impl InstructionAccount {
+    fn new(name: &str, kind: AccountKind, mutable: bool) -> Self {
+        Self { name: name.to_string(), kind, mutable, constraints: Vec::new() }
+    }
+}
+
+impl FunctionScope {
+    /// Rewrites a map key in terms of values the Accounts struct can see:
+    /// the signer, instruction arguments, state fields and literals
+    fn resolve_key(&self, key: &Expression) -> Option<Expression> {
+        match key {
+            Expression::MsgSender | Expression::Number(_) | Expression::TypedNumber { .. } => Some(key.clone()),
+            Expression::Identifier(name) => match self.aliases.get(name) {
+                // 自引用的别名 (`let x = x;`) 不再继续展开
+                Some(value) if *value != *key => self.resolve_key(value),
+                Some(_) => None,
+                None if self.params.contains(name) => Some(key.clone()),
+                None if self.state_vars.contains(name) && !self.assigned_state.contains(name) => Some(key.clone()),
+                None => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn entry(&self, map: &str, keys: &[Expression]) -> Option<&MapEntry> {
+        let keys: Vec<Expression> = keys.iter().map(|k| self.resolve_key(k)).collect::<Option<_>>()?;
+        self.map_entries.iter().find(|e| e.map == map && e.keys == keys)
+    }
 }
 
 impl SolanaCodeGenerator {
     pub fn new() -> Self {
         let mut handlebars = Handlebars::new();
         handlebars.register_template_string("program", SOLANA_TEMPLATE).unwrap();
-        Self { handlebars }
+        Self {
+            handlebars,
+            default_max_len: None,
+            arithmetic: ArithmeticMode::default(),
+            program_id: None,
+            token_standard: TokenStandard::default(),
+            externals: Vec::new(),
+            anchor_version: None,
+            cluster: CLUSTERS[0].to_string(),
+            check_output: true,
+            scope: RefCell::new(FunctionScope::default()),
+        }
+    }
+
+    /// Sets the length assumed for strings, bytes and vectors declared without one;
+    /// by default their state variables are an error
+    pub fn with_default_max_len(mut self, len: usize) -> Self {
+        self.default_max_len = Some(len);
+        self
+    }
+
+    /// Selects how integer arithmetic is lowered
+    pub fn with_arithmetic(mut self, mode: ArithmeticMode) -> Self {
+        self.arithmetic = mode;
+        self
+    }
+
+    /// Sets the address written into `declare_id!`
+    pub fn with_program_id(mut self, id: impl Into<String>) -> Self {
+        self.program_id = Some(id.into());
+        self
+    }
+
+    /// Selects where token balances live; see [`TokenStandard`]
+    pub fn with_token_standard(mut self, standard: TokenStandard) -> Self {
+        self.token_standard = standard;
+        self
+    }
+
+    /// Declares the external contracts the program can call, see [`crate::ExternalContract`]
+    pub fn with_externals(mut self, externals: Vec<ExternalContract>) -> Self {
+        self.externals = externals;
+        self
+    }
+
+    /// Targets an Anchor release and pins it in `Anchor.toml`; see [`AnchorVersion`]
+    pub fn with_anchor_version(mut self, version: AnchorVersion) -> Self {
+        self.anchor_version = Some(version);
+        self
+    }
+
+    /// Sets the cluster `Anchor.toml` deploys to, one of [`CLUSTERS`]
+    pub fn with_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.cluster = cluster.into();
+        self
+    }
+
+    /// Whether `generate` parses the program it generates and fails, quoting the
+    /// offending lines, if it is not valid Rust; on by default
+    pub fn with_output_check(mut self, enabled: bool) -> Self {
+        self.check_output = enabled;
+        self
+    }
+
+    fn anchor_version(&self) -> AnchorVersion {
+        self.anchor_version.unwrap_or_default()
     }
 
     pub fn generate(&self, contract: &Contract) -> Result<String> {
-        let solana_code = self.transform_contract(contract);
-        Ok(solana_code)
+        if let Some(id) = &self.program_id {
+            validate_program_id(id)?;
+        }
+        // 先把修饰器展开到函数体中
+        let contract = self.prepare(contract)?;
+        let code = self.transform_contract(&contract)?;
+        if self.check_output {
+            check_rust_output(&code).with_context(|| format!("In the Solana program of '{}'", contract.name))?;
+        }
+        Ok(code)
     }
 
-    fn transform_contract(&self, contract: &Contract) -> String {
+    /// Expands modifiers, checks the contract fits the selected token standard,
+    /// adds the events the bridge emits and checks the generated type names are distinct.
+    ///
+    /// Each function's `modifiers` are left holding the sender checks that become
+    /// constraints on its signer (see [`Self::constrained_modifiers`]); their `require`s
+    /// still open the body, so error codes, state access and tests see them.
+    fn prepare(&self, contract: &Contract) -> Result<Contract> {
+        let constrained: Vec<Vec<ModifierCall>> = contract.functions.iter()
+            .map(|func| self.constrained_modifiers(contract, func))
+            .collect();
+        let mut contract = expand_modifiers(contract)?;
+        for (func, modifiers) in contract.functions.iter_mut().zip(constrained) {
+            func.modifiers = modifiers;
+        }
+        if self.initializes(&contract) {
+            // 生成的 initialize 指令不能与合约中不是构造函数的 initialize 同名
+            if contract.constructor().is_none() && contract.functions.iter().any(|f| f.name == INITIALIZE) {
+                return Err(anyhow!(
+                    "Function '{}' is not the constructor, but the program needs an instruction of that name to create its accounts: \
+                     make it public and non-view, or mark a constructor with #[constructor]",
+                    INITIALIZE
+                ));
+            }
+            set_initial_values(&mut contract);
+        }
+        check_view_functions(&contract)?;
+        if self.is_spl() {
+            spl::check_token_contract(&contract)?;
+        }
+        if let Some(bridge) = &contract.bridge {
+            bridge::check_bridge_contract(&contract, bridge)?;
+            contract.events.extend(bridge::bridge_events());
+            bridge::add_sequence_params(&mut contract)?;
+        }
+        // Solana 的调用不附带价值: payable 函数把转账数量作为参数, 在函数开头转入状态账户
+        for func in contract.functions.iter_mut().filter(|f| f.is_payable) {
+            if func.params.iter().any(|p| p.name == MSG_VALUE) {
+                return Err(anyhow!("Payable function '{}' cannot have a parameter named '{}'", func.name, MSG_VALUE));
+            }
+            func.params.push(Parameter { name: MSG_VALUE.to_string(), ty: Type::U64, is_mutable: false });
+        }
+        check_type_names(&contract)?;
+        self.check_helpers(&contract)?;
+        Ok(contract)
+    }
+
+    /// Private and internal functions become plain Rust functions that get the caller's
+    /// `State` but no accounts, so they can't use the signer, map entries, the mint, the
+    /// bridge or other programs, nor call instructions
+    fn check_helpers(&self, contract: &Contract) -> Result<()> {
+        for func in contract.functions.iter().filter(|f| !is_instruction(f)) {
+            let kind = if func.visibility == Visibility::Internal { "Internal" } else { "Private" };
+            if is_constructor(contract, func) {
+                return Err(anyhow!("{} function '{}' must be public: it is the instruction that creates the program's accounts", kind, func.name));
+            }
+            let scope = self.function_scope(contract, func)?;
+            let mut uses = scope.map_entries.first().map(|entry| format!("the map '{}'", entry.map));
+            if let Some(target) = called_contracts(&func.body).first() {
+                uses.get_or_insert(format!("the contract '{}'", target));
+            }
+            visit_expressions(&func.body, &mut |expr| {
+                let found = match expr {
+                    Expression::MsgSender => Some("msg_sender()".to_string()),
+                    Expression::MsgValue => Some("msg_value()".to_string()),
+                    Expression::Identifier(name) if scope.mint_fields.contains(name) => Some(format!("'{}', which lives in the mint", name)),
+                    Expression::Call { func: callee, .. } => match callee.as_ref() {
+                        Expression::Identifier(name) if name == Bridge::LOCK || name == Bridge::RECEIVE => Some(format!("{}()", name)),
+                        Expression::Identifier(name) if contract.functions.iter().any(|f| f.name == *name && is_instruction(f)) => {
+                            Some(format!("the instruction '{}'", name))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if uses.is_none() {
+                    uses = found;
+                }
+            });
+            if let Some(what) = uses {
+                return Err(anyhow!(
+                    "{} function '{}' uses {}, which needs accounts that only instructions get on Solana; \
+                     make it public or move that part into its callers",
+                    kind, func.name, what
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The leading modifiers of `func` that only check `msg_sender()` against an address in
+    /// the `State` PDA. Anchor checks them as `address` constraints before the instruction runs;
+    /// later modifiers, or checks against anything else, stay inline `require!`s.
+    fn constrained_modifiers(&self, contract: &Contract, func: &Function) -> Vec<ModifierCall> {
+        let is_state_address = |field: &str| !func.params.iter().any(|p| p.name == field)
+            && !self.is_mint_field(field)
+            && contract.state.iter().any(|v| v.name == field && v.ty == Type::Address);
+        func.modifiers.iter()
+            .take_while(|call| contract.modifiers.iter()
+                .find(|m| m.name == call.name)
+                .and_then(sender_check)
+                .is_some_and(|check| is_state_address(check.field)))
+            .cloned()
+            .collect()
+    }
+
+    fn external(&self, name: &str) -> Option<&ExternalContract> {
+        self.externals.iter().find(|e| e.name == name)
+    }
+
+    /// Scalar state variables, stored in the `State` PDA
+    fn state_scalars<'a>(&self, contract: &'a Contract) -> Vec<&'a StateVariable> {
+        contract.state.iter()
+            .filter(|v| !matches!(v.ty, Type::Map(..)) && !self.is_mint_field(&v.name))
+            .collect()
+    }
+
+    /// Whether the contract has a `State` PDA. Contracts with no state at all still get an empty one,
+    /// as do contracts with payable functions, whose lamports it holds.
+    fn has_state_account(&self, contract: &Contract) -> bool {
+        contract.state.is_empty() || !self.state_scalars(contract).is_empty() || contract.functions.iter().any(|f| f.is_payable)
+    }
+
+    /// Whether the constructor creates accounts: the `State` PDA, or the mint in SPL mode
+    fn initializes(&self, contract: &Contract) -> bool {
+        self.has_state_account(contract) || self.is_spl()
+    }
+
+    /// Access of every function to the variables in the `State` PDA
+    fn state_access(&self, contract: &Contract) -> HashMap<String, StateAccess> {
+        state_access(contract, |var| !matches!(var.ty, Type::Map(..)) && !self.is_mint_field(&var.name))
+    }
+
+    /// Accounts of the `initialize` generated for contracts that don't define one
+    fn initialize_accounts(&self, contract: &Contract) -> Vec<InstructionAccount> {
+        let mut accounts = vec![InstructionAccount::new(SIGNER, AccountKind::Signer, true)];
+        if self.has_state_account(contract) {
+            accounts.push(InstructionAccount::new("state", AccountKind::State { init: true }, true));
+        }
+        if self.is_spl() {
+            accounts.push(InstructionAccount::new(MINT, AccountKind::Mint { decimals: Some(self.mint_decimals()) }, true));
+            accounts.push(InstructionAccount::new("token_program", AccountKind::TokenProgram, false));
+        }
+        accounts.push(InstructionAccount::new("system_program", AccountKind::System, false));
+        accounts
+    }
+
+    fn transform_contract(&self, contract: &Contract) -> Result<String> {
         let mut code = String::new();
         
         // 生成 Anchor 程序头
-        code.push_str("use anchor_lang::prelude::*;\n\n");
-        code.push_str("declare_id!(\"11111111111111111111111111111111\");\n\n");
+        code.push_str("use anchor_lang::prelude::*;\n");
+        // Rust 没有原生 u256, 需要 primitive-types 依赖
+        if uses_u256(contract) {
+            code.push_str("use primitive_types::U256;\n");
+        }
+        code.push('\n');
+        match &self.program_id {
+            Some(id) => code.push_str(&format!("declare_id!(\"{}\");\n\n", id)),
+            None => {
+                code.push_str("// TODO: placeholder program id; set --program-id or [solana] program_id in ccdsl.toml before deploying\n");
+                code.push_str(&format!("declare_id!(\"{}\");\n\n", PLACEHOLDER_PROGRAM_ID));
+            }
+        }
+        
+        // 生成常量
+        for constant in &contract.constants {
+            // String 不能用于 const, 使用 &str
+            let ty = match constant.ty {
+                Type::String(_) => "&str".to_string(),
+                _ => self.type_to_rust(&constant.ty),
+            };
+            // U256::from 不是 const fn, 直接写出小端序的 64 位分段
+            let value = match (&constant.ty, &constant.value) {
+                (Type::U256, Expression::Number(n)) => format!("U256([{}, 0, 0, 0])", n),
+                (Type::U256, Expression::TypedNumber { value, .. }) => {
+                    format!("U256([{}, {}, 0, 0])", *value as u64, (*value >> 64) as u64)
+                }
+                _ => self.expression_to_rust(&constant.value),
+            };
+            code.push_str(&format!("pub const {}: {} = {};\n", constant.name, ty, value));
+        }
+        if !contract.constants.is_empty() {
+            code.push('\n');
+        }
         
         // 生成程序模块
         code.push_str(&format!("#[program]\npub mod {} {{\n", contract.name.to_lowercase()));
         code.push_str("    use super::*;\n\n");
         
-        // 生成函数
-        for func in &contract.functions {
-            code.push_str(&self.generate_function(func));
+        // 生成函数, 同时生成各自的账户结构
+        let contexts = context_names(contract);
+        let access = self.state_access(contract);
+        let mut accounts = String::new();
+        if self.initializes(contract) && contract.constructor().is_none() {
+            let context = &contexts[INITIALIZE];
+            code.push_str(&format!("    pub fn {}(ctx: Context<{}>) -> Result<()> {{\n", INITIALIZE, context));
+            code.push_str("        Ok(())\n");
+            code.push_str("    }\n\n");
+            
+            accounts.push_str("#[derive(Accounts)]\n");
+            accounts.push_str(&accounts_struct(context, &self.initialize_accounts(contract)));
+        }
+        if let Some(bridge) = &contract.bridge {
+            let context = &contexts[INIT_BRIDGE];
+            code.push_str(&self.generate_init_bridge(bridge, context));
+            accounts.push_str("#[derive(Accounts)]\n");
+            accounts.push_str(&accounts_struct(context, &bridge::init_bridge_accounts()));
+        }
+        for func in contract.functions.iter().filter(|f| is_instruction(f)) {
+            self.scope.replace(self.function_scope(contract, func)?);
+            
+            code.push_str(&self.generate_function(func, &contexts[&func.name]));
             code.push_str("\n");
+            accounts.push_str(&self.generate_accounts(contract, func, &contexts[&func.name], access[&func.name]));
         }
         
         code.push_str("}\n\n");
         
+        // 私有和 internal 函数不是指令, 生成在 #[program] 之外
+        for func in contract.functions.iter().filter(|f| !is_instruction(f)) {
+            self.scope.replace(self.function_scope(contract, func)?);
+            code.push_str(&self.generate_helper(func, access[&func.name]));
+            code.push('\n');
+        }
+        self.scope.take();
+        
         // 生成账户结构
-        code.push_str(&self.generate_accounts(&contract));
+        code.push_str(&accounts);
         
         // 生成状态结构
-        code.push_str(&self.generate_state(&contract));
+        code.push_str(&self.generate_state(contract)?);
+        
+        // 生成 bridge 的金库账户与链 id
+        if let Some(bridge) = &contract.bridge {
+            code.push_str(&self.generate_bridge(bridge));
+        }
+        
+        // 生成自定义结构体
+        code.push_str(&self.generate_structs(contract));
+        
+        // 生成事件结构
+        code.push_str(&self.generate_events(contract));
         
         // 生成错误码
-        code.push_str(&self.generate_errors());
+        code.push_str(&self.generate_errors(contract));
         
+        // 生成被调用的外部合约的 CPI 模块
+        code.push_str(&self.generate_externals(contract)?);
+        
+        Ok(code)
+    }
+
+    /// An `Anchor.toml` for the generated program, registering it under its program id.
+    /// With [`Self::workspace_cargo_toml`] and [`Self::program_cargo_toml`] it makes the
+    /// output an Anchor workspace, with the program in `programs/<contract>/src/lib.rs`.
+    pub fn anchor_toml(&self, contract: &Contract) -> String {
+        let id = self.program_id.as_deref().unwrap_or(PLACEHOLDER_PROGRAM_ID);
+        let mut code = String::new();
+        // 选择了 Anchor 版本时由 avm 使用对应的 anchor-cli
+        if let Some(version) = self.anchor_version {
+            code.push_str("[toolchain]\n");
+            code.push_str(&format!("anchor_version = \"{}\"\n\n", version));
+        }
+        code.push_str("[features]\n");
+        // 0.30 去掉了 seeds, 改为由 resolution 推导账户
+        if self.anchor_version().minor >= 30 {
+            code.push_str("resolution = true\n");
+        } else {
+            code.push_str("seeds = false\n");
+        }
+        code.push_str("skip-lint = false\n\n");
+        code.push_str(&format!("[programs.{}]\n", self.cluster));
+        code.push_str(&format!("{} = \"{}\"\n\n", contract.name.to_lowercase(), id));
+        code.push_str("[provider]\n");
+        code.push_str(&format!("cluster = \"{}\"\n", capitalize(&self.cluster)));
+        code.push_str("wallet = \"~/.config/solana/id.json\"\n\n");
+        // --with-tests 生成的测试由 anchor test 运行
+        code.push_str("[scripts]\n");
+        code.push_str("test = \"yarn run ts-mocha -p ./tsconfig.json -t 1000000 tests/**/*.ts\"\n");
         code
     }
 
-    fn generate_function(&self, func: &Function) -> String {
+    /// The workspace `Cargo.toml` next to `Anchor.toml`, as `anchor init` writes it
+    pub fn workspace_cargo_toml(&self) -> String {
+        let mut code = String::new();
+        code.push_str("[workspace]\n");
+        code.push_str("members = [\"programs/*\"]\n");
+        code.push_str("resolver = \"2\"\n\n");
+        // 链上程序保留溢出检查
+        code.push_str("[profile.release]\n");
+        code.push_str("overflow-checks = true\n");
+        code.push_str("lto = \"fat\"\n");
+        code.push_str("codegen-units = 1\n\n");
+        code.push_str("[profile.release.build-override]\n");
+        code.push_str("opt-level = 3\n");
+        code.push_str("incremental = false\n");
+        code.push_str("codegen-units = 1\n");
+        code
+    }
+
+    /// `programs/<contract>/Cargo.toml`, with the dependencies the generated code uses
+    /// pinned to the selected Anchor release
+    pub fn program_cargo_toml(&self, contract: &Contract) -> String {
+        let name = contract.name.to_lowercase();
+        let version = self.anchor_version();
+        let mut code = String::new();
+        code.push_str("[package]\n");
+        code.push_str(&format!("name = \"{}\"\n", name));
+        code.push_str("version = \"0.1.0\"\n");
+        code.push_str(&format!("description = \"The {} contract, generated by ccdsl\"\n", contract.name));
+        code.push_str("edition = \"2021\"\n\n");
+        code.push_str("[lib]\n");
+        code.push_str("crate-type = [\"cdylib\", \"lib\"]\n");
+        code.push_str(&format!("name = \"{}\"\n\n", name));
+        code.push_str("[features]\n");
+        code.push_str("default = []\n");
+        code.push_str("cpi = [\"no-entrypoint\"]\n");
+        code.push_str("no-entrypoint = []\n");
+        code.push_str("no-idl = []\n");
+        code.push_str("no-log-ix-name = []\n");
+        // 0.30 起 anchor build 通过 idl-build feature 生成 IDL
+        if version.minor >= 30 {
+            let mut crates = vec!["\"anchor-lang/idl-build\""];
+            if self.is_spl() {
+                crates.push("\"anchor-spl/idl-build\"");
+            }
+            code.push_str(&format!("idl-build = [{}]\n", crates.join(", ")));
+        }
+        code.push('\n');
+        code.push_str("[dependencies]\n");
+        // 写入 map 条目的指令使用 init_if_needed
+        code.push_str(&format!("anchor-lang = {{ version = \"{}\", features = [\"init-if-needed\"] }}\n", version));
+        if self.is_spl() {
+            code.push_str(&format!("anchor-spl = \"{}\"\n", version));
+        }
+        if uses_u256(contract) {
+            code.push_str("primitive-types = \"0.12\"\n");
+        }
+        code
+    }
+
+    /// `programs/<contract>/Xargo.toml`, read by the older `cargo build-bpf` toolchains
+    pub fn xargo_toml(&self) -> String {
+        "[target.bpfel-unknown-unknown.dependencies.std]\nfeatures = []\n".to_string()
+    }
+
+    /// The instruction generated for each function, keyed by function name
+    pub fn entrypoints(&self, contract: &Contract) -> Result<BTreeMap<String, Entrypoint>> {
+        let contract = self.prepare(contract)?;
+        let access = self.state_access(&contract);
+        let mut entrypoints = BTreeMap::new();
+        for func in contract.functions.iter().filter(|f| is_instruction(f)) {
+            self.scope.replace(self.function_scope(&contract, func)?);
+            let accounts = self.instruction_accounts(&contract, func, access[&func.name]);
+            let scope = self.scope.take();
+            
+            let accounts: Vec<AccountMeta> = accounts.into_iter()
+                .map(|account| {
+                    let seeds = match &account.kind {
+                        AccountKind::State { .. } => Some(vec![Seed::Literal("state".to_string())]),
+                        AccountKind::Mint { .. } => Some(vec![Seed::Literal(MINT.to_string())]),
+                        AccountKind::BridgeVault { .. } => Some(vec![Seed::Literal(VAULT.to_string())]),
+                        AccountKind::MapEntry(entry) => Some(std::iter::once(Seed::Literal(entry.map.clone()))
+                            .chain(entry.keys.iter().zip(&entry.key_types).map(|(key, ty)| match key {
+                                Expression::MsgSender => Seed::Signer,
+                                Expression::Identifier(name) if scope.params.contains(name) => Seed::Arg(name.clone()),
+                                Expression::Identifier(name) => Seed::State(name.clone()),
+                                Expression::Number(n) => Seed::Constant(format!("{}{}", n, self.type_to_rust(ty))),
+                                Expression::TypedNumber { value, .. } => Seed::Constant(format!("{}{}", value, self.type_to_rust(ty))),
+                                _ => unreachable!("map keys are resolved before seeds are built"),
+                            }))
+                            .collect()),
+                        _ => None,
+                    };
+                    AccountMeta {
+                        signer: matches!(account.kind, AccountKind::Signer),
+                        writable: account.mutable,
+                        name: account.name,
+                        seeds,
+                    }
+                })
+                .collect();
+            entrypoints.insert(func.name.clone(), Entrypoint {
+                name: func.name.clone(),
+                entry: true,
+                args: func.params.iter()
+                    .map(|p| Argument { name: p.name.clone(), ty: self.type_to_rust(&p.ty) })
+                    .collect(),
+                returns: func.return_type.as_ref().map(|ty| self.type_to_rust(ty)),
+                signers: accounts.iter().filter(|a| a.signer).map(|a| a.name.clone()).collect(),
+                accounts,
+            });
+        }
+        Ok(entrypoints)
+    }
+
+    /// An Anchor IDL for the program `generate` emits
+    pub fn idl(&self, contract: &Contract) -> Result<AnchorIdl> {
+        let contract = self.prepare(contract)?;
+        let access = self.state_access(&contract);
+        let fields = |fields: Vec<(&str, &Type)>| fields.into_iter()
+            .map(|(name, ty)| Ok(IdlField { name: camel_case(name), ty: idl_type(ty)? }))
+            .collect::<Result<Vec<_>>>();
+        let idl_accounts = |accounts: Vec<InstructionAccount>| accounts.into_iter()
+            .map(|account| IdlAccount {
+                is_signer: matches!(account.kind, AccountKind::Signer),
+                is_mut: account.mutable,
+                name: camel_case(&account.name),
+            })
+            .collect();
+        
+        // 与 transform_contract 中指令的顺序一致
+        let mut instructions = Vec::new();
+        if self.initializes(&contract) && contract.constructor().is_none() {
+            instructions.push(IdlInstruction {
+                name: INITIALIZE.to_string(),
+                accounts: idl_accounts(self.initialize_accounts(&contract)),
+                args: Vec::new(),
+                returns: None,
+            });
+        }
+        if let Some(bridge) = &contract.bridge {
+            instructions.push(IdlInstruction {
+                name: camel_case(INIT_BRIDGE),
+                accounts: idl_accounts(bridge::init_bridge_accounts()),
+                args: fields(vec![(bridge.authority.as_str(), &Type::Address)])?,
+                returns: None,
+            });
+        }
+        for func in contract.functions.iter().filter(|f| is_instruction(f)) {
+            self.scope.replace(self.function_scope(&contract, func)?);
+            let accounts = self.instruction_accounts(&contract, func, access[&func.name]);
+            self.scope.take();
+            instructions.push(IdlInstruction {
+                name: camel_case(&func.name),
+                accounts: idl_accounts(accounts),
+                args: fields(func.params.iter().map(|p| (p.name.as_str(), &p.ty)).collect())
+                    .with_context(|| format!("In the parameters of '{}'", func.name))?,
+                returns: func.return_type.as_ref().map(idl_type).transpose()
+                    .with_context(|| format!("In the return type of '{}'", func.name))?,
+            });
+        }
+        
+        // 账户布局与 generate_state 一致
+        let mut accounts = Vec::new();
+        if self.has_state_account(&contract) {
+            let scalars = self.state_scalars(&contract).into_iter()
+                .map(|var| (var.name.as_str(), &var.ty))
+                .collect();
+            accounts.push(IdlTypeDefinition {
+                name: "State".to_string(),
+                ty: IdlTypeDefinitionTy::Struct { fields: fields(scalars)? },
+            });
+        }
+        for var in contract.state.iter().filter(|var| matches!(var.ty, Type::Map(..)) && !self.is_token_map(&var.name)) {
+            let mut value = &var.ty;
+            while let Type::Map(_, inner) = value {
+                value = inner;
+            }
+            accounts.push(IdlTypeDefinition {
+                name: entry_type_name(&var.name),
+                ty: IdlTypeDefinitionTy::Struct { fields: fields(vec![("value", value)])? },
+            });
+        }
+        if let Some(bridge) = &contract.bridge {
+            accounts.push(IdlTypeDefinition {
+                name: VAULT_TYPE.to_string(),
+                ty: IdlTypeDefinitionTy::Struct {
+                    fields: fields(bridge::vault_fields(bridge).iter().map(|(name, ty, _)| (*name, ty)).collect())?,
+                },
+            });
+        }
+        
+        let types = contract.structs.iter()
+            .map(|def| Ok(IdlTypeDefinition {
+                name: def.name.clone(),
+                ty: IdlTypeDefinitionTy::Struct {
+                    fields: fields(def.fields.iter().map(|f| (f.name.as_str(), &f.ty)).collect())?,
+                },
+            }))
+            .collect::<Result<_>>()?;
+        
+        let events = contract.events.iter()
+            .map(|event| Ok(IdlEvent {
+                name: pascal_case(&event.name),
+                fields: event.params.iter()
+                    .map(|p| Ok(IdlEventField { name: camel_case(&p.name), ty: idl_type(&p.ty)?, index: p.indexed }))
+                    .collect::<Result<_>>()?,
+            }))
+            .collect::<Result<_>>()?;
+        
+        // Anchor 的自定义错误码从 6000 开始
+        let errors = error_codes(&contract).into_iter()
+            .zip(6000..)
+            .map(|((name, msg), code)| IdlErrorCode { code, name, msg: Some(msg) })
+            .collect();
+        
+        Ok(AnchorIdl {
+            version: "0.1.0".to_string(),
+            name: contract.name.to_lowercase(),
+            instructions,
+            accounts,
+            types,
+            events,
+            errors,
+            metadata: self.program_id.as_ref().map(|id| IdlMetadata { address: id.clone() }),
+        })
+    }
+
+    /// Collects the names, aliases and map entries a function uses
+    fn function_scope(&self, contract: &Contract, func: &Function) -> Result<FunctionScope> {
+        let mut scope = FunctionScope {
+            known_types: contract.constants.iter()
+                .map(|c| (c.name.clone(), c.ty.clone()))
+                .chain(contract.state.iter().map(|v| (v.name.clone(), v.ty.clone())))
+                .chain(func.params.iter().map(|p| (p.name.clone(), p.ty.clone())))
+                .collect(),
+            params: func.params.iter().map(|p| p.name.clone()).collect(),
+            events: contract.events.clone(),
+            errors: require_errors(contract, &builtin_errors(contract), REQUIRE_FAILED.0, error_variant).into_iter()
+                .map(|e| (e.message, e.name))
+                .collect(),
+            bridge: contract.bridge.clone(),
+            helper: !is_instruction(func),
+            helpers: self.state_access(contract).into_iter()
+                .filter(|(name, _)| contract.functions.iter().any(|f| f.name == *name && !is_instruction(f)))
+                .collect(),
+            ..FunctionScope::default()
+        };
+        
+        // 参数和局部变量遮蔽同名的状态变量
+        let mut locals = scope.params.clone();
+        let mut writes = Vec::new();
+        let mut debits = Vec::new();
+        visit_statements(&func.body, &mut |stmt| match stmt {
+            Statement::Let { name, value, .. } => {
+                locals.insert(name.clone());
+                scope.aliases.insert(name.clone(), value.clone());
+            }
+            Statement::ForEach { variable, .. } => {
+                locals.insert(variable.clone());
+            }
+            Statement::LetTuple { names, .. } => locals.extend(names.iter().cloned()),
+            // 写入结构体字段 (`pools[k].reserve = ...`) 也是写入整个条目
+            Statement::Assign { target, value } => {
+                let target = lvalue_to_expression(target);
+                if matches!(value, Expression::Binary { op: BinaryOp::Sub, left, .. } if **left == target) {
+                    debits.push(field_base(target.clone()));
+                }
+                writes.push(field_base(target));
+            }
+            _ => {}
+        });
+        let (mint_fields, state_vars) = contract.state.iter()
+            .filter(|v| !matches!(v.ty, Type::Map(..)) && !locals.contains(&v.name))
+            .map(|v| v.name.clone())
+            .partition(|name| self.is_mint_field(name));
+        scope.state_vars = state_vars;
+        scope.mint_fields = mint_fields;
+        scope.assigned_state = writes.iter()
+            .filter_map(|target| match target {
+                Expression::Identifier(name) if scope.state_vars.contains(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        
+        // 先登记写入的条目, 再登记只读的条目, 最后是 `map.get(key)` 读取的条目
+        let mut indexed = Vec::new();
+        let mut lookups = Vec::new();
+        visit_expressions(&func.body, &mut |expr| {
+            if matches!(expr, Expression::Index { .. }) {
+                indexed.push(expr.clone());
+            }
+            if let Some(entry) = lookup_entry(expr) {
+                lookups.push(entry);
+            }
+        });
+        let mut partial = Vec::new();
+        let accesses = writes.iter().map(|e| (e, true, false))
+            .chain(indexed.iter().map(|e| (e, false, false)))
+            .chain(lookups.iter().map(|e| (e, false, true)));
+        for (expr, is_write, optional) in accesses {
+            let Some((map, keys)) = index_chain(expr) else { continue };
+            let Some(var) = contract.state.iter().find(|v| v.name == map && !locals.contains(&v.name)) else { continue };
+            let key_types = map_key_types(&var.ty);
+            if key_types.is_empty() {
+                continue;
+            }
+            if optional && self.is_token_map(&map) {
+                return Err(anyhow!(
+                    "'{}.get' in function '{}' is not supported: with SPL token balances its entries are token accounts",
+                    map, func.name
+                ));
+            }
+            if keys.len() < key_types.len() {
+                partial.push((map, keys));
+                continue;
+            }
+            
+            let keys = &keys[..key_types.len()];
+            let resolved: Vec<Expression> = keys.iter()
+                .map(|key| scope.resolve_key(key).ok_or_else(|| anyhow!(
+                    "Cannot derive a PDA seed for '{}' from the key `{}` in function '{}'; \
+                     map keys must be parameters, literals, msg_sender() or state fields the function doesn't assign",
+                    map, self.expression_to_rust(key), func.name)))
+                .collect::<Result<_>>()?;
+            
+            if let Some(entry) = scope.map_entries.iter_mut().find(|e| e.map == map && e.keys == resolved) {
+                // 同一个账户不能既要求存在又允许不存在
+                if entry.optional != optional {
+                    return Err(anyhow!(
+                        "Function '{}' reads an entry of '{}' with get() and also indexes or writes it; use one or the other",
+                        func.name, map
+                    ));
+                }
+                entry.writes |= is_write;
+                entry.debits |= is_write && debits.contains(expr);
+                continue;
+            }
+            
+            let mut field = std::iter::once(map.clone())
+                .chain(keys.iter().map(key_name))
+                .collect::<Vec<_>>()
+                .join("_");
+            if scope.map_entries.iter().any(|e| e.field == field) {
+                field = format!("{}_{}", field, scope.map_entries.len() + 1);
+            }
+            let seeds = std::iter::once(format!("b\"{}\"", map))
+                .chain(resolved.iter().zip(&key_types).map(|(key, ty)| self.seed(key, ty, &scope.params)))
+                .collect();
+            let debits = is_write && debits.contains(expr);
+            scope.map_entries.push(MapEntry { map, keys: resolved, key_types, field, seeds, writes: is_write, debits, optional });
+        }
+        
+        // 嵌套 map 只能整体索引到值
+        for (map, keys) in partial {
+            let covered = scope.map_entries.iter().any(|e| e.map == map
+                && keys.iter().zip(&e.keys).all(|(k, resolved)| scope.resolve_key(k).as_ref() == Some(resolved)));
+            if !covered {
+                return Err(anyhow!(
+                    "Nested map '{}' in function '{}' must be indexed down to its values", map, func.name
+                ));
+            }
+        }
+        
+        if self.is_spl() {
+            scope.spl = Some(spl::plan(func, is_constructor(contract, func), &scope, &locals)?);
+        }
+        Ok(scope)
+    }
+
+    fn generate_function(&self, func: &Function, context: &str) -> String {
         let mut code = String::new();
         
         // 函数签名
         code.push_str("    pub fn ");
         code.push_str(&func.name);
         code.push_str("(ctx: Context<");
-        code.push_str(&capitalize(&func.name));
+        code.push_str(context);
         code.push_str(">");
         
         // 添加参数
@@ -67,84 +999,450 @@ impl SolanaCodeGenerator {
             code.push_str(&self.type_to_rust(&param.ty));
         }
         
-        code.push_str(") -> Result<()> {\n");
+        // 有返回值的指令通过 Anchor 的 return data 返回
+        let returns = func.return_type.as_ref()
+            .map(|ty| self.type_to_rust(ty))
+            .unwrap_or_else(|| "()".to_string());
+        code.push_str(&format!(") -> Result<{}> {{\n", returns));
         
-        // 函数体
-        for stmt in &func.body {
-            code.push_str("        ");
-            code.push_str(&self.statement_to_rust(stmt));
-            code.push_str("\n");
+        let mut body = CodeWriter::new(2);
+        // payable 函数先把 msg_value 个 lamports 从签名者转入状态账户
+        if func.is_payable {
+            body.line(&format!(
+                "anchor_lang::system_program::transfer(CpiContext::new(ctx.accounts.system_program.to_account_info(), \
+                 anchor_lang::system_program::Transfer {{ from: ctx.accounts.{}.to_account_info(), to: ctx.accounts.state.to_account_info() }}), {})?;",
+                SIGNER, MSG_VALUE));
         }
         
-        code.push_str("        Ok(())\n");
+        // 释放 bridge 代币的函数只能由金库记录的 authority 调用
+        if let Some(bridge) = &self.scope.borrow().bridge {
+            if bridge::bridge_use(func).1 {
+                body.line(&self.bridge_authority_check(bridge));
+            }
+        }
+        
+        // 函数体; 已并入代币 CPI 的语句不再生成, 开头已变成账户约束的 require 也不再生成
+        self.write_statements(&mut body, func.body.iter().skip(func.modifiers.len()));
+        
+        // 有返回值时语义分析保证每条路径都已 return
+        if func.return_type.is_none() {
+            body.line("Ok(())");
+        }
+        code.push_str(&body.finish());
         code.push_str("    }\n");
         
         code
     }
 
-    fn generate_accounts(&self, contract: &Contract) -> String {
+    /// A private or internal function, as a plain Rust function outside `#[program]`: it
+    /// has no accounts of its own, and takes the caller's `State` if it uses state
+    fn generate_helper(&self, func: &Function, access: StateAccess) -> String {
+        let mut params = Vec::new();
+        if access.writes {
+            params.push("state: &mut State".to_string());
+        } else if access.reads {
+            params.push("state: &State".to_string());
+        }
+        params.extend(func.params.iter().map(|p| format!("{}: {}", p.name, self.type_to_rust(&p.ty))));
+        let returns = func.return_type.as_ref()
+            .map(|ty| self.type_to_rust(ty))
+            .unwrap_or_else(|| "()".to_string());
+        
+        let mut code = CodeWriter::new(0);
+        code.open(&format!("fn {}({}) -> Result<{}>", func.name, params.join(", "), returns));
+        self.write_statements(&mut code, &func.body);
+        if func.return_type.is_none() {
+            code.line("Ok(())");
+        }
+        code.close("");
+        code.finish()
+    }
+
+    fn generate_accounts(&self, contract: &Contract, func: &Function, context: &str, access: StateAccess) -> String {
         let mut code = String::new();
+        let accounts = self.instruction_accounts(contract, func, access);
+        let scope = self.scope.borrow();
         
-        // 为每个函数生成 Accounts 结构
-        for func in &contract.functions {
-            code.push_str("#[derive(Accounts)]\n");
-            code.push_str(&format!("pub struct {} {{\n", capitalize(&func.name)));
-            
-            // 基本账户
-            code.push_str("    #[account(mut)]\n");
-            code.push_str("    pub user: Signer<'info>,\n");
-            
-            // 状态账户
-            if !contract.state.is_empty() {
-                code.push_str("    #[account(\n");
-                code.push_str("        mut,\n");
-                code.push_str("        seeds = [b\"state\"],\n");
-                code.push_str("        bump\n");
-                code.push_str("    )]\n");
-                code.push_str("    pub state: Account<'info, State>,\n");
-            }
-            
-            code.push_str("    pub system_program: Program<'info, System>,\n");
-            code.push_str("}\n\n");
+        code.push_str("#[derive(Accounts)]\n");
+        // seeds (或 SPL 模式下的代币账户持有者、mint 精度) 用到参数时需要声明指令参数
+        let decimals_param = scope.spl.as_ref().is_some_and(|plan| matches!(plan.decimals, Some(Expression::Identifier(_))));
+        if decimals_param || scope.map_entries.iter().any(|e| e.keys.iter().any(|k| matches!(k, Expression::Identifier(n) if scope.params.contains(n)))) {
+            let params: Vec<String> = func.params.iter()
+                .map(|p| format!("{}: {}", p.name, self.type_to_rust(&p.ty)))
+                .collect();
+            code.push_str(&format!("#[instruction({})]\n", params.join(", ")));
         }
+        code.push_str(&accounts_struct(context, &accounts));
         
         code
     }
 
-    fn generate_state(&self, contract: &Contract) -> String {
+    /// The accounts an instruction takes, in the order of its Accounts struct
+    fn instruction_accounts(&self, contract: &Contract, func: &Function, access: StateAccess) -> Vec<InstructionAccount> {
+        let mut accounts = Vec::new();
+        let scope = self.scope.borrow();
+        // 外部合约的 CPI 由本程序直接构造, 不需要签名账户和 system_program
+        let (externals, calls): (Vec<String>, Vec<String>) = called_contracts(&func.body).into_iter()
+            .partition(|name| self.external(name).is_some());
+        let spl = scope.spl.as_ref();
+        
+        // 写入的 map 条目按需创建; SPL 模式下只有收款的代币账户需要创建
+        let creates = |entry: &MapEntry| match spl {
+            Some(plan) if self.is_token_map(&entry.map) => plan.credited.contains(&entry.keys[0]),
+            _ => entry.writes && !entry.debits,
+        };
+        
+        // 签名账户: 读取 msg_sender() 或付费 (创建新账户, payable 函数的转账) 时才需要, 付费时需要 mut
+        let init = is_constructor(contract, func) && self.initializes(contract);
+        let pays = init || func.is_payable || scope.map_entries.iter().any(creates);
+        // bridge_lock 的事件记录发送者
+        let (locks, receives) = match &contract.bridge {
+            Some(_) => bridge::bridge_use(func),
+            None => (false, false),
+        };
+        let mut uses_sender = !calls.is_empty() || locks;
+        visit_expressions(&func.body, &mut |expr| {
+            uses_sender |= matches!(expr, Expression::MsgSender);
+        });
+        if pays || uses_sender {
+            let mut signer = InstructionAccount::new(SIGNER, AccountKind::Signer, pays);
+            // onlyOwner 之类的修饰器: Anchor 只接受一个 address 约束, 其余写成 constraint
+            let checks = func.modifiers.iter()
+                .filter_map(|call| contract.modifiers.iter().find(|m| m.name == call.name))
+                .filter_map(sender_check);
+            for (i, check) in checks.enumerate() {
+                let error = &scope.errors[check.message];
+                signer.constraints.push(if i == 0 {
+                    format!("address = state.{} @ ErrorCode::{}", check.field, error)
+                } else {
+                    format!("constraint = {}.key() == state.{} @ ErrorCode::{}", SIGNER, check.field, error)
+                });
+            }
+            accounts.push(signer);
+        }
+        
+        // 状态账户: 只读函数不需要 mut, 不访问状态时省略 (seeds 引用状态字段时也需要)
+        // 合约的构造函数负责创建状态账户
+        let StateAccess { reads, writes } = access;
+        let state_seeds = scope.map_entries.iter()
+            .any(|e| e.keys.iter().any(|k| matches!(k, Expression::Identifier(n) if scope.state_vars.contains(n))));
+        if init && self.has_state_account(contract) {
+            accounts.push(InstructionAccount::new("state", AccountKind::State { init: true }, true));
+        } else if reads || writes || state_seeds || func.is_payable {
+            accounts.push(InstructionAccount::new("state", AccountKind::State { init: false }, writes || func.is_payable));
+        }
+        
+        // bridge 的金库: 锁定和释放都会修改; 释放还需要 authority 签名
+        if locks || receives {
+            accounts.push(InstructionAccount::new(VAULT, AccountKind::BridgeVault { init: false }, true));
+        }
+        if let Some(bridge) = contract.bridge.as_ref().filter(|_| receives) {
+            accounts.push(InstructionAccount::new(&bridge.authority, AccountKind::Signer, false));
+        }
+        
+        // SPL 模式的 mint: 构造函数创建, 铸造和销毁时需要 mut, 代币账户的约束也要用到
+        let token_accounts = scope.map_entries.iter().any(|e| self.is_token_map(&e.map));
+        if let Some(plan) = spl {
+            if init {
+                let decimals = self.mint_decimals();
+                accounts.push(InstructionAccount::new(MINT, AccountKind::Mint { decimals: Some(decimals) }, true));
+            } else if plan.reads_mint || plan.mint_mut || token_accounts {
+                accounts.push(InstructionAccount::new(MINT, AccountKind::Mint { decimals: None }, plan.mint_mut));
+            }
+        }
+        
+        // 每个访问到的 map 条目一个 PDA 账户, 写入时按需创建
+        for entry in &scope.map_entries {
+            if !self.is_token_map(&entry.map) {
+                let mut account = InstructionAccount::new(&entry.field, AccountKind::MapEntry(entry.clone()), entry.writes);
+                // 两个条目的键相同时 (如转账给自己) Anchor 反序列化出两份, 后写回的覆盖先写回的
+                let earlier = scope.map_entries.iter().take_while(|e| e.field != entry.field);
+                for other in earlier.filter(|e| may_alias(e, entry)) {
+                    account.constraints.push(format!(
+                        "constraint = {}.key() != {}.key() @ ErrorCode::InvalidParameter", other.field, entry.field));
+                }
+                accounts.push(account);
+                continue;
+            }
+            // SPL 模式下是持有者的关联代币账户; 创建时持有者要作为账户传入
+            let init = creates(entry);
+            let owner = match &entry.keys[0] {
+                Expression::MsgSender => SIGNER.to_string(),
+                key => {
+                    let address = match key {
+                        Expression::Identifier(name) if scope.params.contains(name) => name.clone(),
+                        Expression::Identifier(name) => format!("state.{}", name),
+                        _ => unreachable!("address keys resolve to the signer, parameters or state fields"),
+                    };
+                    if init {
+                        let wallet = format!("{}_owner", entry.field);
+                        accounts.push(InstructionAccount::new(&wallet, AccountKind::Wallet(address), false));
+                        wallet
+                    } else {
+                        address
+                    }
+                }
+            };
+            let kind = AccountKind::TokenAccount { entry: entry.clone(), owner, init };
+            accounts.push(InstructionAccount::new(&entry.field, kind, entry.writes));
+        }
+        
+        // 跨合约调用需要被调用程序的账户
+        for target in &calls {
+            let name = format!("{}_program", target.to_lowercase());
+            accounts.push(InstructionAccount::new(&name, AccountKind::Program(target.clone()), false));
+        }
+        for target in &externals {
+            let name = format!("{}_program", target.to_lowercase());
+            let address = self.external(target).map(|e| e.address.clone()).unwrap_or_default();
+            accounts.push(InstructionAccount::new(&name, AccountKind::ExternalProgram { contract: target.clone(), address }, false));
+        }
+        
+        // 代币 CPI 与创建 mint 需要 token_program, 创建关联代币账户还需要 associated_token_program
+        if let Some(plan) = spl {
+            let creates_token_account = scope.map_entries.iter().any(|e| self.is_token_map(&e.map) && creates(e));
+            if init || plan.has_cpis() || creates_token_account {
+                accounts.push(InstructionAccount::new("token_program", AccountKind::TokenProgram, false));
+            }
+            if creates_token_account {
+                accounts.push(InstructionAccount::new("associated_token_program", AccountKind::AssociatedTokenProgram, false));
+            }
+        }
+        
+        // 创建账户和 CPI 的账户参数会用到 system_program
+        if init || func.is_payable || !calls.is_empty() || scope.map_entries.iter().any(creates) {
+            accounts.push(InstructionAccount::new("system_program", AccountKind::System, false));
+        }
+        
+        accounts
+    }
+
+    fn generate_state(&self, contract: &Contract) -> Result<String> {
+        let mut code = String::new();
+        
+        // map 存放在各自的 PDA 账户中, 不进入 State; SPL 模式下余额、总量和精度在代币账户与 mint 中
+        if self.has_state_account(contract) {
+            let fields = self.state_scalars(contract).iter()
+                .map(|var| Ok((var.name.as_str(), &var.ty, self.field_size(&var.name, &var.ty, contract)?)))
+                .collect::<Result<Vec<_>>>()?;
+            code.push_str(&self.generate_account_struct("State", &fields));
+        }
+        
+        for var in contract.state.iter().filter(|var| matches!(var.ty, Type::Map(..)) && !self.is_token_map(&var.name)) {
+            let mut value = &var.ty;
+            while let Type::Map(_, inner) = value {
+                value = inner;
+            }
+            let size = self.field_size(&var.name, value, contract)?;
+            code.push_str(&self.generate_account_struct(&entry_type_name(&var.name), &[("value", value, size)]));
+        }
+        
+        Ok(code)
+    }
+
+    fn generate_account_struct(&self, name: &str, fields: &[(&str, &Type, usize)]) -> String {
         let mut code = String::new();
         
         code.push_str("#[account]\n");
-        code.push_str("pub struct State {\n");
+        code.push_str(&format!("pub struct {} {{\n", name));
         
-        for var in &contract.state {
+        for (field, ty, _) in fields {
             code.push_str("    pub ");
-            code.push_str(&var.name);
+            code.push_str(field);
             code.push_str(": ");
-            code.push_str(&self.type_to_rust(&var.ty));
+            code.push_str(&self.type_to_rust(ty));
             code.push_str(",\n");
         }
         
         code.push_str("}\n\n");
+        
+        // 账户空间 (不含 8 字节 discriminator)
+        let sizes: Vec<String> = fields.iter().map(|(_, _, size)| size.to_string()).collect();
+        code.push_str(&format!("impl {} {{\n", name));
+        code.push_str(&format!("    pub const LEN: usize = {};\n",
+            if sizes.is_empty() { "0".to_string() } else { sizes.join(" + ") }));
+        code.push_str("}\n\n");
+        code
+    }
+
+    /// Account space for the state variable `name`, which must be bounded
+    fn field_size(&self, name: &str, ty: &Type, contract: &Contract) -> Result<usize> {
+        self.type_size(ty, contract).ok_or_else(|| anyhow!(
+            "Cannot compute the account space of state variable '{}': {} has no maximum length; \
+             annotate it (e.g. `string<32>`) or set a default maximum length",
+            name, self.unbounded_part(ty, contract)))
+    }
+
+    /// Names the part of `ty` without a size, pointing into structs by field
+    fn unbounded_part(&self, ty: &Type, contract: &Contract) -> String {
+        if let Type::Struct(name) = ty {
+            let field = contract.structs.iter()
+                .find(|s| s.name == *name)
+                .and_then(|s| s.fields.iter().find(|f| self.type_size(&f.ty, contract).is_none()));
+            if let Some(field) = field {
+                return format!("{}.{} ({})", name, field.name, self.unbounded_part(&field.ty, contract));
+            }
+        }
+        self.type_to_rust(ty)
+    }
+
+    /// Borsh-serialized size of a type, using `default_max_len` for anything unbounded;
+    /// `None` if something is unbounded and there's no default
+    fn type_size(&self, ty: &Type, contract: &Contract) -> Option<usize> {
+        Some(match ty {
+            Type::U8 | Type::I8 | Type::Bool => 1,
+            Type::U16 | Type::I16 => 2,
+            Type::U32 | Type::I32 => 4,
+            Type::U64 | Type::I64 => 8,
+            Type::U128 | Type::I128 => 16,
+            Type::U256 | Type::Address => 32,
+            Type::String(max_len) | Type::Bytes(max_len) => 4 + max_len.or(self.default_max_len)?,
+            Type::Vec(t) => 4 + self.default_max_len? * self.type_size(t, contract)?,
+            Type::Map(k, v) => {
+                4 + self.default_max_len? * (self.type_size(k, contract)? + self.type_size(v, contract)?)
+            }
+            Type::Array(t, size) => size * self.type_size(t, contract)?,
+            Type::Tuple(types) => types.iter().map(|t| self.type_size(t, contract)).sum::<Option<usize>>()?,
+            Type::Struct(name) => match contract.structs.iter().find(|s| s.name == *name) {
+                Some(s) => s.fields.iter().map(|f| self.type_size(&f.ty, contract)).sum::<Option<usize>>()?,
+                None => 0,
+            },
+            Type::Option(t) => 1 + self.type_size(t, contract)?,
+            Type::Result(ok, err) => 1 + self.type_size(ok, contract)?.max(self.type_size(err, contract)?),
+        })
+    }
+
+    /// Plain Borsh types for `struct` declarations. Map entries and state embed them
+    /// in their own `#[account]` structs, so they never need a discriminator.
+    fn generate_structs(&self, contract: &Contract) -> String {
+        let mut code = String::new();
+        
+        for def in &contract.structs {
+            code.push_str("#[derive(AnchorSerialize, AnchorDeserialize, Clone)]\n");
+            code.push_str(&format!("pub struct {} {{\n", def.name));
+            for field in &def.fields {
+                code.push_str(&format!("    pub {}: {},\n", field.name, self.type_to_rust(&field.ty)));
+            }
+            code.push_str("}\n\n");
+        }
+        
         code
     }
 
-    fn generate_errors(&self) -> String {
+    fn generate_events(&self, contract: &Contract) -> String {
+        let mut code = String::new();
+        
+        for event in &contract.events {
+            code.push_str("#[event]\n");
+            code.push_str(&format!("pub struct {} {{\n", pascal_case(&event.name)));
+            for param in &event.params {
+                // Anchor 的 #[index] 会写入 IDL (`"index": true`), 供链下索引器使用
+                if param.indexed {
+                    code.push_str("    #[index]\n");
+                }
+                code.push_str(&format!("    pub {}: {},\n", param.name, self.type_to_rust(&param.ty)));
+            }
+            code.push_str("}\n\n");
+        }
+        
+        code
+    }
+
+    fn generate_errors(&self, contract: &Contract) -> String {
         let mut code = String::new();
         
         code.push_str("#[error_code]\n");
         code.push_str("pub enum ErrorCode {\n");
-        code.push_str("    #[msg(\"Unauthorized\")]\n");
-        code.push_str("    Unauthorized,\n");
-        code.push_str("    #[msg(\"Insufficient balance\")]\n");
-        code.push_str("    InsufficientBalance,\n");
-        code.push_str("    #[msg(\"Invalid parameter\")]\n");
-        code.push_str("    InvalidParameter,\n");
+        for (name, msg) in error_codes(contract) {
+            code.push_str(&format!("    #[msg(\"{}\")]\n", msg));
+            code.push_str(&format!("    {},\n", name));
+        }
         code.push_str("}\n");
         
         code
     }
 
+    /// A module per external contract the program calls, named like the crate Anchor
+    /// generates for a program: `program::Name` for the `Program` account and one
+    /// function per declared instruction that invokes it
+    fn generate_externals(&self, contract: &Contract) -> Result<String> {
+        let mut called = Vec::new();
+        for func in &contract.functions {
+            for name in called_contracts(&func.body) {
+                if !called.contains(&name) {
+                    called.push(name);
+                }
+            }
+        }
+        
+        let mut code = String::new();
+        for external in self.externals.iter().filter(|e| called.contains(&e.name)) {
+            validate_program_id(&external.address)
+                .with_context(|| format!("External contract '{}' needs a Solana program id", external.name))?;
+            code.push_str(&format!("\n/// {}: 外部程序, 通过 CPI 调用\n", external.name));
+            code.push_str(&format!("pub mod {} {{\n", external.name.to_lowercase()));
+            code.push_str("    use super::*;\n");
+            code.push_str("    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};\n");
+            code.push_str("    use anchor_lang::solana_program::program::{get_return_data, invoke};\n\n");
+            code.push_str(&format!("    declare_id!(\"{}\");\n\n", external.address));
+            code.push_str("    pub mod program {\n");
+            code.push_str("        use super::*;\n\n");
+            code.push_str("        #[derive(Clone)]\n");
+            code.push_str(&format!("        pub struct {};\n\n", external.name));
+            code.push_str(&format!("        impl anchor_lang::Id for {} {{\n", external.name));
+            code.push_str("            fn id() -> Pubkey {\n");
+            code.push_str("                ID\n");
+            code.push_str("            }\n");
+            code.push_str("        }\n");
+            code.push_str("    }\n");
+            
+            for func in &external.functions {
+                let params: Vec<String> = func.params.iter()
+                    .map(|p| format!("{}: {}", p.name, self.type_to_rust(&p.ty)))
+                    .collect();
+                let returns = func.return_type.as_ref().map(|ty| self.type_to_rust(ty));
+                code.push_str(&format!(
+                    "\n    pub fn {}<'info>(program: AccountInfo<'info>, accounts: &[AccountInfo<'info>]{}) -> Result<{}> {{\n",
+                    func.name,
+                    params.iter().map(|p| format!(", {}", p)).collect::<String>(),
+                    returns.as_deref().unwrap_or("()")));
+                // Anchor 指令的判别符: sha256("global:<指令名>") 的前 8 字节, 之后是 Borsh 编码的参数
+                code.push_str(&format!(
+                    "        let mut data = anchor_lang::solana_program::hash::hash(b\"global:{}\").to_bytes()[..8].to_vec();\n",
+                    func.name));
+                for param in &func.params {
+                    code.push_str(&format!(
+                        "        AnchorSerialize::serialize(&{}, &mut data).map_err(|_| anchor_lang::error::ErrorCode::InstructionDidNotSerialize)?;\n",
+                        param.name));
+                }
+                code.push_str("        let metas = accounts.iter()\n");
+                code.push_str("            .map(|a| if a.is_writable { AccountMeta::new(a.key(), a.is_signer) } else { AccountMeta::new_readonly(a.key(), a.is_signer) })\n");
+                code.push_str("            .collect();\n");
+                code.push_str("        let mut infos = accounts.to_vec();\n");
+                code.push_str("        infos.push(program);\n");
+                code.push_str("        invoke(&Instruction { program_id: ID, accounts: metas, data }, &infos)?;\n");
+                match &returns {
+                    // 返回值由被调用程序通过 set_return_data 传回
+                    Some(ty) => {
+                        code.push_str("        let (program_id, data) = get_return_data()\n");
+                        code.push_str("            .ok_or(anchor_lang::error::ErrorCode::InstructionDidNotDeserialize)?;\n");
+                        code.push_str("        require_keys_eq!(program_id, ID);\n");
+                        // Vec<u8>、元组等类型要写成 <T>::try_from_slice
+                        let ty = if ty.contains(['<', '(']) { format!("<{}>", ty) } else { ty.clone() };
+                        code.push_str(&format!(
+                            "        {}::try_from_slice(&data).map_err(|_| anchor_lang::error::ErrorCode::InstructionDidNotDeserialize.into())\n",
+                            ty));
+                    }
+                    None => code.push_str("        Ok(())\n"),
+                }
+                code.push_str("    }\n");
+            }
+            code.push_str("}\n");
+        }
+        
+        Ok(code)
+    }
+
     fn type_to_rust(&self, ty: &Type) -> String {
         match ty {
             Type::U8 => "u8".to_string(),
@@ -152,7 +1450,7 @@ impl SolanaCodeGenerator {
             Type::U32 => "u32".to_string(),
             Type::U64 => "u64".to_string(),
             Type::U128 => "u128".to_string(),
-            Type::U256 => "u256".to_string(),
+            Type::U256 => "U256".to_string(),
             Type::I8 => "i8".to_string(),
             Type::I16 => "i16".to_string(),
             Type::I32 => "i32".to_string(),
@@ -160,8 +1458,8 @@ impl SolanaCodeGenerator {
             Type::I128 => "i128".to_string(),
             Type::Bool => "bool".to_string(),
             Type::Address => "Pubkey".to_string(),
-            Type::String => "String".to_string(),
-            Type::Bytes => "Vec<u8>".to_string(),
+            Type::String(_) => "String".to_string(),
+            Type::Bytes(_) => "Vec<u8>".to_string(),
             Type::Map(k, v) => format!("HashMap<{}, {}>", 
                 self.type_to_rust(k), self.type_to_rust(v)),
             Type::Vec(t) => format!("Vec<{}>", self.type_to_rust(t)),
@@ -175,44 +1473,275 @@ impl SolanaCodeGenerator {
         }
     }
 
-    fn statement_to_rust(&self, stmt: &Statement) -> String {
+    /// Lowers a statement nested `level` indents deep. The first line is left for
+    /// the caller to indent; any further lines carry their own indentation.
+    fn statement_to_rust(&self, stmt: &Statement, level: usize) -> String {
+        let newline = format!("\n{}", indent(level));
+        
+        // 除数不是常量时先检查, 避免运行时除零 panic
+        let mut code = String::new();
+        for divisor in runtime_divisors(stmt) {
+            code.push_str(&format!("require!({} != 0, ErrorCode::DivisionByZero);",
+                self.expression_to_rust(divisor)));
+            code.push_str(&newline);
+        }
+        
+        // 嵌套语句 (如 match 分支) 有各自的临时变量
+        let (hoist, outer) = {
+            let mut scope = self.scope.borrow_mut();
+            (std::mem::replace(&mut scope.hoist, true), std::mem::take(&mut scope.temporaries))
+        };
+        let statement = self.lower_statement(stmt, level);
+        let temporaries = {
+            let mut scope = self.scope.borrow_mut();
+            scope.hoist = hoist;
+            std::mem::replace(&mut scope.temporaries, outer)
+        };
+        
+        for temporary in temporaries {
+            code.push_str(&temporary);
+            code.push_str(&newline);
+        }
+        code.push_str(&statement);
+        code
+    }
+
+    /// Writes the statements one per line at the writer's level, leaving out those
+    /// folded into a token CPI
+    fn write_statements<'s>(&self, code: &mut CodeWriter, stmts: impl IntoIterator<Item = &'s Statement>) {
+        for stmt in stmts.into_iter().filter(|stmt| !self.folded_into_cpi(stmt)) {
+            code.line(&self.statement_to_rust(stmt, code.level()));
+        }
+    }
+
+    /// `{ ... }` with the statements one level deeper than `level`
+    fn block_to_rust(&self, stmts: &[Statement], level: usize) -> String {
+        let mut code = CodeWriter::new(level + 1);
+        self.write_statements(&mut code, stmts);
+        format!("{{\n{}{}}}", code.finish(), indent(level))
+    }
+
+    fn lower_statement(&self, stmt: &Statement, level: usize) -> String {
         match stmt {
-            Statement::Let { name, value, .. } => {
-                format!("let {} = {};", name, self.expression_to_rust(value))
+            Statement::Let { name, value, is_mutable, .. } => {
+                match self.type_of(value) {
+                    Some(ty) => self.scope.borrow_mut().known_types.insert(name.clone(), ty),
+                    None => self.scope.borrow_mut().known_types.remove(name),
+                };
+                let binding = if *is_mutable { "let mut" } else { "let" };
+                format!("{} {} = {};", binding, name, self.root_to_rust(value))
             },
-            Statement::Assign { target, value } => {
-                format!("{} = {};", self.lvalue_to_rust(target), self.expression_to_rust(value))
+            Statement::LetTuple { names, value } => {
+                for name in names {
+                    self.scope.borrow_mut().known_types.remove(name);
+                }
+                format!("let ({}) = {};", names.join(", "), self.root_to_rust(value))
             },
-            Statement::Require { condition, message } => {
-                if let Some(msg) = message {
-                    format!("require!({}, ErrorCode::InvalidParameter);", 
-                        self.expression_to_rust(condition))
-                } else {
-                    format!("require!({});", self.expression_to_rust(condition))
+            Statement::Assign { target, value } => {
+                // SPL 模式下余额与总量由代币程序修改
+                if let Some(cpi) = self.token_cpi(stmt, level) {
+                    return cpi;
+                }
+                let target_rust = self.lvalue_to_rust(target);
+                // 字符串字面量是 &str, 存入账户中的 String 前先转换
+                let value_rust = match value {
+                    Expression::String(s) if self.stored_max_len(target).is_some() => format!("\"{}\".to_string()", s),
+                    _ => self.root_to_rust(value),
+                };
+                // 账户空间按最大长度分配, 写入前检查长度; 字面量的长度已知
+                let literal_len = match value {
+                    Expression::String(s) => Some(s.len()),
+                    Expression::Bytes(b) => Some(b.len()),
+                    _ => None,
+                };
+                match self.stored_max_len(target) {
+                    Some(max) if literal_len.is_none_or(|len| len > max) => {
+                        let checked = if matches!(value, Expression::Identifier(_)) {
+                            value_rust
+                        } else {
+                            let mut scope = self.scope.borrow_mut();
+                            let name = format!("__tmp{}", scope.next_temporary);
+                            scope.next_temporary += 1;
+                            scope.temporaries.push(format!("let {} = {};", name, value_rust));
+                            name
+                        };
+                        format!("require!({}.len() <= {}, ErrorCode::ValueTooLong);\n{}{} = {};",
+                            checked, max, indent(level), target_rust, checked)
+                    }
+                    _ => format!("{} = {};", target_rust, value_rust),
                 }
             },
+            Statement::Require { condition, message } | Statement::Assert { condition, message } => {
+                let error = self.scope.borrow().errors[message].clone();
+                format!("require!({}, ErrorCode::{});", self.expression_to_rust(condition), error)
+            },
             Statement::Emit { event, args } => {
-                format!("emit!({} {{ /* fields */ }});", event)
+                // 语义分析保证事件存在且参数个数一致
+                let params = self.scope.borrow().events.iter()
+                    .find(|e| e.name == *event)
+                    .map(|e| e.params.clone())
+                    .unwrap_or_default();
+                let fields: Vec<String> = params.iter().zip(args)
+                    .map(|(param, arg)| {
+                        let value = self.event_field_to_rust(arg, &param.ty);
+                        if value == param.name { value } else { format!("{}: {}", param.name, value) }
+                    })
+                    .collect();
+                format!("emit!({} {{ {} }});", pascal_case(event), fields.join(", "))
+            },
+            Statement::If { condition, then_block, else_block } => {
+                let mut code = format!("if {} {}", self.expression_to_rust(condition), self.block_to_rust(then_block, level));
+                match else_block.as_deref() {
+                    // `else if` 的条件只在前面的条件不成立时求值, 不能提到整个 if 之前
+                    Some([elif @ Statement::If { .. }]) if runtime_divisors(elif).is_empty() => {
+                        let hoist = std::mem::replace(&mut self.scope.borrow_mut().hoist, false);
+                        code.push_str(" else ");
+                        code.push_str(&self.lower_statement(elif, level));
+                        self.scope.borrow_mut().hoist = hoist;
+                    }
+                    Some(stmts) => {
+                        code.push_str(" else ");
+                        code.push_str(&self.block_to_rust(stmts, level));
+                    }
+                    None => {}
+                }
+                code
+            },
+            Statement::While { condition, body } => {
+                // 条件每次循环都要重新求值
+                format!("while {} {}", self.conditional_to_rust(condition), self.block_to_rust(body, level))
+            },
+            Statement::For { init, condition, update, body } => match range_loop(init, condition, update) {
+                Some((variable, start, end, inclusive)) => {
+                    if let Some(ty) = self.type_of(start).or_else(|| self.type_of(end)) {
+                        self.scope.borrow_mut().known_types.insert(variable.to_string(), ty);
+                    }
+                    let range = if inclusive { "..=" } else { ".." };
+                    format!("for {} in {}{}{} {}", variable, self.expression_to_rust(start), range,
+                        self.expression_to_rust(end), self.block_to_rust(body, level))
+                }
+                // 其他形式的计数循环展开成 while; 循环体中的 continue 会跳过更新语句
+                None => {
+                    let mut body = body.clone();
+                    body.push(*update.clone());
+                    let looped = Statement::While { condition: condition.clone(), body };
+                    self.block_to_rust(&[*init.clone(), looped], level)
+                }
+            },
+            Statement::ForEach { variable, iterable, body } => {
+                if let Some(Type::Vec(elem) | Type::Array(elem, _)) = self.type_of(iterable) {
+                    self.scope.borrow_mut().known_types.insert(variable.clone(), *elem);
+                }
+                // 遍历副本, 循环体仍可修改状态账户
+                format!("for {} in {}.clone() {}", variable, self.expression_to_rust(iterable), self.block_to_rust(body, level))
+            },
+            Statement::Block(stmts) => self.block_to_rust(stmts, level),
+            Statement::Match { value, arms } => {
+                let mut code = CodeWriter::new(level + 1);
+                for arm in arms {
+                    code.line(&format!("{} => {}", self.pattern_to_rust(&arm.pattern), self.block_to_rust(&arm.body, level + 1)));
+                }
+                format!("match {} {{\n{}{}}}", self.expression_to_rust(value), code.finish(), indent(level))
             },
             Statement::Return { value } => {
                 if let Some(v) = value {
-                    format!("return Ok({});", self.expression_to_rust(v))
+                    format!("return Ok({});", self.root_to_rust(v))
                 } else {
                     "return Ok(());".to_string()
                 }
             },
+            Statement::Break => "break;".to_string(),
+            Statement::Continue => "continue;".to_string(),
+            Statement::Expression(expr) => self.bridge_call(expr, level).unwrap_or_else(|| match expr {
+                Expression::Call { .. } => format!("{};", self.expression_to_rust(expr)),
+                _ => "// TODO".to_string(),
+            }),
             _ => "// TODO".to_string(),
         }
     }
 
+    fn pattern_to_rust(&self, pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Literal(expr) => self.expression_to_rust(expr),
+            // 使用闭区间, 排他区间模式在较旧的 Rust 版本中不稳定
+            Pattern::Range { start, end, inclusive: true } => format!("{}..={}", start, end),
+            Pattern::Range { start, end, inclusive: false } => format!("{}..={}", start, end - 1),
+            Pattern::Wildcard => "_".to_string(),
+        }
+    }
+
     fn expression_to_rust(&self, expr: &Expression) -> String {
+        let code = self.root_to_rust(expr);
+        
+        // 嵌套的检查运算存入临时变量, 让每个 `?` 单独成行
+        let mut scope = self.scope.borrow_mut();
+        if scope.hoist && matches!(expr, Expression::Binary { .. }) && code.ends_with('?') {
+            let name = format!("__tmp{}", scope.next_temporary);
+            scope.next_temporary += 1;
+            scope.temporaries.push(format!("let {} = {};", name, code));
+            return name;
+        }
+        code
+    }
+
+    /// Lowers an expression that is evaluated only sometimes, so nothing in it
+    /// may be moved ahead of the statement
+    fn conditional_to_rust(&self, expr: &Expression) -> String {
+        let hoist = std::mem::replace(&mut self.scope.borrow_mut().hoist, false);
+        let code = self.expression_to_rust(expr);
+        self.scope.borrow_mut().hoist = hoist;
+        code
+    }
+
+    /// Lowers the outermost expression of a statement, which stays inline
+    fn root_to_rust(&self, expr: &Expression) -> String {
+        if let Expression::Binary { op, left, right } = expr {
+            if let Some(code) = self.u256_arithmetic(op, left, right).or_else(|| self.arithmetic(op, left, right)) {
+                return code;
+            }
+        }
+        if let Some(code) = self.map_entry_to_rust(expr) {
+            return code;
+        }
+        
         match expr {
             Expression::Number(n) => n.to_string(),
+            Expression::TypedNumber { value, ty: Type::U256 } => format!("U256::from({}u128)", value),
+            Expression::TypedNumber { value, ty } => format!("{}{}", value, self.type_to_rust(ty)),
             Expression::Float(f) => f.to_string(),
             Expression::Bool(b) => b.to_string(),
             Expression::String(s) => format!("\"{}\"", s),
             Expression::Bytes(b) => format!("vec!{:?}", b),
+            // 状态变量通过 Context 中的状态账户读写
+            Expression::Identifier(id) if self.scope.borrow().state_vars.contains(id) => {
+                if self.scope.borrow().helper { format!("state.{}", id) } else { format!("ctx.accounts.state.{}", id) }
+            },
+            // SPL 模式下总量和精度直接读取 mint
+            Expression::Identifier(id) if self.scope.borrow().mint_fields.contains(id) => {
+                let field = if id == spl::TOTAL_SUPPLY { "supply" } else { "decimals" };
+                format!("ctx.accounts.{}.{}", MINT, field)
+            },
             Expression::Identifier(id) => id.clone(),
+            // Rust 没有幂运算符, 用 checked_pow 并在溢出时报错
+            Expression::Binary { op: BinaryOp::Pow, left, right } => {
+                let base = match &**left {
+                    // 字面量需要标明类型才能调用方法
+                    Expression::Number(n) => format!("{}u64", n),
+                    other => self.expression_to_rust(other),
+                };
+                let exponent = match &**right {
+                    Expression::Number(n) => n.to_string(),
+                    other => format!("u32::try_from({}).map_err(|_| ErrorCode::Overflow)?", self.expression_to_rust(other)),
+                };
+                format!("{}.checked_pow({}).ok_or(ErrorCode::Overflow)?", base, exponent)
+            },
+            // 右侧可能被短路, 不能提前求值
+            Expression::Binary { op: op @ (BinaryOp::And | BinaryOp::Or), left, right } => {
+                format!("({} {} {})",
+                    self.expression_to_rust(left),
+                    self.binary_op_to_rust(op),
+                    self.conditional_to_rust(right))
+            },
             Expression::Binary { op, left, right } => {
                 format!("({} {} {})", 
                     self.expression_to_rust(left),
@@ -228,6 +1757,47 @@ impl SolanaCodeGenerator {
                     },
                     self.expression_to_rust(expr))
             },
+            Expression::Cast { expr, ty } => self.cast_to_rust(expr, ty),
+            Expression::Ternary { condition, then_expr, else_expr } => {
+                format!("if {} {{ {} }} else {{ {} }}",
+                    self.expression_to_rust(condition),
+                    self.conditional_to_rust(then_expr),
+                    self.conditional_to_rust(else_expr))
+            },
+            Expression::ArrayLiteral(items) => {
+                format!("vec![{}]", items.iter()
+                    .map(|i| self.expression_to_rust(i))
+                    .collect::<Vec<_>>()
+                    .join(", "))
+            },
+            Expression::TupleLiteral(items) => {
+                let items = items.iter()
+                    .map(|i| self.expression_to_rust(i))
+                    .collect::<Vec<_>>();
+                // 单元素元组需要尾随逗号
+                if items.len() == 1 {
+                    format!("({},)", items[0])
+                } else {
+                    format!("({})", items.join(", "))
+                }
+            },
+            // 内部函数接收调用者的状态, 出错时向上传递
+            Expression::Call { func, args } if matches!(&**func, Expression::Identifier(name) if self.scope.borrow().helpers.contains_key(name)) => {
+                let Expression::Identifier(name) = &**func else { unreachable!() };
+                let (access, helper) = {
+                    let scope = self.scope.borrow();
+                    (scope.helpers[name], scope.helper)
+                };
+                let mut call_args = Vec::new();
+                match (access.writes, access.reads, helper) {
+                    (true, _, false) => call_args.push("&mut ctx.accounts.state".to_string()),
+                    (false, true, false) => call_args.push("&ctx.accounts.state".to_string()),
+                    (false, false, _) => {}
+                    (_, _, true) => call_args.push("state".to_string()),
+                }
+                call_args.extend(args.iter().map(|a| self.expression_to_rust(a)));
+                format!("{}({})?", name, call_args.join(", "))
+            },
             Expression::Call { func, args } => {
                 let func_name = match &**func {
                     Expression::Identifier(name) => name.clone(),
@@ -238,6 +1808,32 @@ impl SolanaCodeGenerator {
                     .collect::<Vec<_>>()
                     .join(", "))
             },
+            Expression::OptionSome(value) => format!("Some({})", self.expression_to_rust(value)),
+            Expression::OptionNone => "None".to_string(),
+            Expression::MethodCall { .. } if expr.is_value_method() => self.value_method_to_rust(expr),
+            // 调用外部合约: 使用生成的 CPI 函数, 被调用程序需要的账户通过 remaining_accounts 传入
+            Expression::MethodCall { object, method, args } if matches!(&**object, Expression::Identifier(name) if self.external(name).is_some()) => {
+                let module = self.expression_to_rust(object).to_lowercase();
+                let mut call_args = vec![
+                    format!("ctx.accounts.{}_program.to_account_info()", module),
+                    "ctx.remaining_accounts".to_string(),
+                ];
+                call_args.extend(args.iter().map(|a| self.expression_to_rust(a)));
+                format!("{}::{}({})?", module, method, call_args.join(", "))
+            },
+            // 调用其他合约: 通过 CPI 调用对应的 Anchor 程序
+            Expression::MethodCall { object, method, args } => {
+                let target = self.expression_to_rust(object);
+                let module = target.to_lowercase();
+                let mut call_args = vec![format!(
+                    "CpiContext::new(ctx.accounts.{}_program.to_account_info(), {}::cpi::accounts::{} {{ \
+                     {signer}: ctx.accounts.{signer}.to_account_info(), \
+                     system_program: ctx.accounts.system_program.to_account_info() }})",
+                    module, module, pascal_case(method), signer = SIGNER
+                )];
+                call_args.extend(args.iter().map(|a| self.expression_to_rust(a)));
+                format!("{}::cpi::{}({})?", module, method, call_args.join(", "))
+            },
             Expression::Index { array, index } => {
                 format!("{}[{}]", 
                     self.expression_to_rust(array),
@@ -246,28 +1842,257 @@ impl SolanaCodeGenerator {
             Expression::Field { object, field } => {
                 format!("{}.{}", self.expression_to_rust(object), field)
             },
-            Expression::MsgSender => "ctx.accounts.user.key()".to_string(),
-            Expression::MsgValue => "ctx.accounts.user.lamports()".to_string(),
+            Expression::StructLiteral { name, fields } => {
+                // 字段存放在 HashMap 中, 排序后输出以保证生成结果稳定
+                let mut fields = fields.iter()
+                    .map(|(field, value)| format!("{}: {}", field, self.expression_to_rust(value)))
+                    .collect::<Vec<_>>();
+                fields.sort();
+                format!("{} {{ {} }}", name, fields.join(", "))
+            },
+            Expression::MsgSender => format!("ctx.accounts.{}.key()", SIGNER),
+            Expression::MsgValue => MSG_VALUE.to_string(),
             Expression::BlockNumber => "Clock::get()?.slot".to_string(),
             Expression::BlockTimestamp => "Clock::get()?.unix_timestamp".to_string(),
             _ => "/* expr */".to_string(),
         }
     }
 
-    fn lvalue_to_rust(&self, lvalue: &LValue) -> String {
-        match lvalue {
-            LValue::Identifier(name) => name.clone(),
-            LValue::Index { array, index } => {
-                format!("{}[{}]", 
-                    self.lvalue_to_rust(array),
-                    self.expression_to_rust(index))
+    /// Lowers an emit argument to the Rust type of the event field it fills
+    fn event_field_to_rust(&self, arg: &Expression, ty: &Type) -> String {
+        match (arg, ty) {
+            (Expression::String(s), Type::String(_)) => format!("\"{}\".to_string()", s),
+            (Expression::Number(n), Type::U256) => format!("U256::from({}u128)", n),
+            (_, Type::U256) if !self.is_u256(arg) => format!("U256::from({})", self.expression_to_rust(arg)),
+            // 事件取得字段的所有权, 非 Copy 类型先 clone, 以免移出账户或后续还会用到的变量
+            (_, Type::String(_) | Type::Bytes(_) | Type::Vec(_) | Type::Struct(_) | Type::Map(..))
+                if !matches!(arg, Expression::Bytes(_) | Expression::ArrayLiteral(_)) => {
+                format!("{}.clone()", self.expression_to_rust(arg))
+            }
+            _ => self.expression_to_rust(arg),
+        }
+    }
+
+    /// `opt.is_some()`, `opt.is_none()`, `opt.unwrap_or(default)` and `map.get(key)`; a state
+    /// map entry read with `get` is `None` while its account holds no data
+    fn value_method_to_rust(&self, expr: &Expression) -> String {
+        let Expression::MethodCall { object, method, args } = expr else { unreachable!() };
+        if let Some((map, keys)) = lookup_entry(expr).as_ref().and_then(index_chain) {
+            let field = self.scope.borrow().entry(&map, &keys).map(|e| e.field.clone());
+            if let Some(field) = field {
+                return format!(
+                    "(if ctx.accounts.{field}.data_is_empty() {{ None }} else {{ \
+                     Some({}::try_deserialize(&mut &ctx.accounts.{field}.try_borrow_data()?[..])?.value) }})",
+                    entry_type_name(&map), field = field
+                );
+            }
+        }
+        
+        let receiver = self.expression_to_rust(object);
+        match method.as_str() {
+            "get" => format!("{}.get(&{}).cloned()", receiver, self.expression_to_rust(&args[0])),
+            // 状态账户中的值不能移出, 先复制一份
+            "unwrap_or" if matches!(&**object, Expression::Identifier(id) if self.scope.borrow().state_vars.contains(id)) => {
+                format!("{}.clone().unwrap_or({})", receiver, self.expression_to_rust(&args[0]))
+            }
+            "unwrap_or" => format!("{}.unwrap_or({})", receiver, self.expression_to_rust(&args[0])),
+            _ => format!("{}.{}()", receiver, method),
+        }
+    }
+
+    /// Lowers `map[key]` to the value field of the entry's PDA account
+    fn map_entry_to_rust(&self, expr: &Expression) -> Option<String> {
+        let (map, keys) = index_chain(expr)?;
+        let scope = self.scope.borrow();
+        let depth = scope.map_entries.iter().find(|e| e.map == map)?.keys.len();
+        if keys.len() < depth {
+            return None;
+        }
+        let field = scope.entry(&map, &keys[..depth])?.field.clone();
+        drop(scope);
+        
+        // SPL 模式下余额是代币账户的 amount
+        if self.is_token_map(&map) {
+            return Some(format!("ctx.accounts.{}.amount", field));
+        }
+        
+        // 值本身是集合时, 剩余的下标作用在值上
+        let mut code = format!("ctx.accounts.{}.value", field);
+        for key in &keys[depth..] {
+            code.push_str(&format!("[{}]", self.expression_to_rust(key)));
+        }
+        Some(code)
+    }
+    
+    /// Lowers `%` and `**` on U256 values to checked calls, since the operators panic on overflow
+    fn u256_arithmetic(&self, op: &BinaryOp, left: &Expression, right: &Expression) -> Option<String> {
+        let (method, error) = match op {
+            BinaryOp::Mod => ("checked_rem", "DivisionByZero"),
+            BinaryOp::Pow => ("checked_pow", "Overflow"),
+            _ => return None,
+        };
+        if !self.is_u256(left) && !self.is_u256(right) {
+            return None;
+        }
+        
+        // 另一侧 (如 pow 的指数) 先转换为 U256
+        let operand = |expr: &Expression| if self.is_u256(expr) {
+            self.expression_to_rust(expr)
+        } else {
+            format!("U256::from({})", self.expression_to_rust(expr))
+        };
+        Some(format!("{}.{}({}).ok_or(ErrorCode::{})?", operand(left), method, operand(right), error))
+    }
+    
+    /// Lowers integer `+`, `-`, `*` and `/` according to the arithmetic mode;
+    /// operands of unknown type are taken to be u64 like untyped literals
+    fn arithmetic(&self, op: &BinaryOp, left: &Expression, right: &Expression) -> Option<String> {
+        let (method, error) = match op {
+            BinaryOp::Add => ("add", "Overflow"),
+            BinaryOp::Sub => ("sub", "Underflow"),
+            BinaryOp::Mul => ("mul", "Overflow"),
+            BinaryOp::Div => ("div", "DivisionByZero"),
+            _ => return None,
+        };
+        let ty = self.type_of(left).or_else(|| self.type_of(right)).unwrap_or(Type::U64);
+        let signed = matches!(ty, Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128);
+        if !signed && !matches!(ty, Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::U256) {
+            return None;
+        }
+        // 有符号数减法越界是溢出而不是下溢
+        let error = if signed && matches!(op, BinaryOp::Sub) { "Overflow" } else { error };
+        
+        let u256 = ty == Type::U256;
+        let operand = |expr: &Expression| match expr {
+            // 字面量需要标明类型才能调用方法
+            Expression::Number(n) if u256 => format!("U256::from({}u128)", n),
+            Expression::Number(n) => format!("{}{}", n, self.type_to_rust(&ty)),
+            _ if u256 && !self.is_u256(expr) => format!("U256::from({})", self.expression_to_rust(expr)),
+            // if 表达式作为方法调用的接收者时需要括号
+            Expression::Ternary { .. } => format!("({})", self.expression_to_rust(expr)),
+            _ => self.expression_to_rust(expr),
+        };
+        let symbol = self.binary_op_to_rust(op);
+        Some(match self.arithmetic {
+            ArithmeticMode::Checked => {
+                format!("{}.checked_{}({}).ok_or(ErrorCode::{})?", operand(left), method, operand(right), error)
+            }
+            // 整数除法不会回绕, 除数已在语句之前检查过
+            ArithmeticMode::Wrapping if matches!(op, BinaryOp::Div) => {
+                format!("({} / {})", operand(left), operand(right))
+            }
+            ArithmeticMode::Wrapping if u256 => format!("{}.overflowing_{}({}).0", operand(left), method, operand(right)),
+            ArithmeticMode::Wrapping => format!("{}.wrapping_{}({})", operand(left), method, operand(right)),
+            ArithmeticMode::Unchecked if u256 => format!("({} {} {})", operand(left), symbol, operand(right)),
+            ArithmeticMode::Unchecked => {
+                format!("({} {} {})", self.expression_to_rust(left), symbol, self.expression_to_rust(right))
+            }
+        })
+    }
+
+    /// Widening casts always succeed; narrowing ones fail with `Overflow` when the
+    /// value doesn't fit, unless the arithmetic mode allows truncation
+    fn cast_to_rust(&self, expr: &Expression, ty: &Type) -> String {
+        let target = self.type_to_rust(ty);
+        // 语义分析已检查字面量在目标类型的范围内
+        if let Expression::Number(n) = expr {
+            return match ty {
+                Type::U256 => format!("U256::from({}u128)", n),
+                _ => format!("{}{}", n, target),
+            };
+        }
+        
+        let from = self.type_of(expr).unwrap_or(Type::U64);
+        let value = match expr {
+            Expression::Ternary { .. } => format!("({})", self.expression_to_rust(expr)),
+            _ => self.expression_to_rust(expr),
+        };
+        if from == *ty {
+            return value;
+        }
+        if integer_bits(&from) < integer_bits(ty) {
+            return match ty {
+                Type::U256 => format!("U256::from({})", value),
+                _ => format!("({} as {})", value, target),
+            };
+        }
+        
+        match (self.arithmetic, &from) {
+            (ArithmeticMode::Checked, _) => format!("{}::try_from({}).map_err(|_| ErrorCode::Overflow)?", target, value),
+            // 截断到低位
+            (_, Type::U256) if *ty == Type::U128 => format!("{}.low_u128()", value),
+            (_, Type::U256) => format!("({}.low_u64() as {})", value, target),
+            _ => format!("({} as {})", value, target),
+        }
+    }
+
+    fn is_u256(&self, expr: &Expression) -> bool {
+        matches!(self.type_of(expr), Some(Type::U256))
+    }
+    
+    /// The type of `expr` where it follows from declared types alone, without full inference
+    fn type_of(&self, expr: &Expression) -> Option<Type> {
+        match expr {
+            Expression::TypedNumber { ty, .. } | Expression::Cast { ty, .. } => Some(ty.clone()),
+            Expression::Identifier(name) => self.scope.borrow().known_types.get(name).cloned(),
+            Expression::Index { array, .. } => match self.type_of(array)? {
+                Type::Map(_, value) => Some(*value),
+                Type::Vec(elem) | Type::Array(elem, _) => Some(*elem),
+                _ => None,
             },
-            LValue::Field { object, field } => {
-                format!("{}.{}", self.lvalue_to_rust(object), field)
+            Expression::Binary { op, left, right } => match op {
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod |
+                BinaryOp::Pow | BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor => {
+                    self.type_of(left).or_else(|| self.type_of(right))
+                }
+                BinaryOp::Shl | BinaryOp::Shr => self.type_of(left),
+                _ => Some(Type::Bool),
             },
+            Expression::Unary { op: crate::UnaryOp::Not, .. } => Some(Type::Bool),
+            Expression::Unary { expr, .. } => self.type_of(expr),
+            Expression::Ternary { then_expr, else_expr, .. } => {
+                self.type_of(then_expr).or_else(|| self.type_of(else_expr))
+            }
+            _ => None,
+        }
+    }
+
+    /// PDA seed for a resolved map key of type `ty`, as written inside `seeds = [...]`
+    fn seed(&self, key: &Expression, ty: &Type, params: &HashSet<String>) -> String {
+        let value = match key {
+            Expression::MsgSender => format!("{}.key()", SIGNER),
+            Expression::Identifier(name) if params.contains(name) => name.clone(),
+            Expression::Identifier(name) => format!("state.{}", name),
+            Expression::Number(n) => format!("{}{}", n, self.type_to_rust(ty)),
+            Expression::TypedNumber { value, .. } => format!("{}{}", value, self.type_to_rust(ty)),
+            _ => unreachable!("map keys are resolved before seeds are built"),
+        };
+        match ty {
+            Type::String(_) => format!("{}.as_bytes()", value),
+            Type::Address | Type::Bytes(_) => format!("{}.as_ref()", value),
+            _ => format!("{}.to_le_bytes().as_ref()", value),
+        }
+    }
+
+    /// Maximum length of the string or bytes a write to `target` stores in an account,
+    /// `None` for locals and other types
+    fn stored_max_len(&self, target: &LValue) -> Option<usize> {
+        let expr = lvalue_to_expression(target);
+        let stored = match &expr {
+            Expression::Identifier(name) => self.scope.borrow().state_vars.contains(name),
+            Expression::Index { array, .. } => matches!(self.type_of(array), Some(Type::Map(..))),
+            _ => false,
+        };
+        match self.type_of(&expr)? {
+            Type::String(max_len) | Type::Bytes(max_len) if stored => max_len.or(self.default_max_len),
+            _ => None,
         }
     }
 
+    fn lvalue_to_rust(&self, lvalue: &LValue) -> String {
+        self.expression_to_rust(&lvalue_to_expression(lvalue))
+    }
+
     fn binary_op_to_rust(&self, op: &BinaryOp) -> &str {
         match op {
             BinaryOp::Add => "+",
@@ -275,7 +2100,7 @@ impl SolanaCodeGenerator {
             BinaryOp::Mul => "*",
             BinaryOp::Div => "/",
             BinaryOp::Mod => "%",
-            BinaryOp::Pow => ".pow",
+            BinaryOp::Pow => "**",  // 在 expression_to_rust 中改写为 checked_pow
             BinaryOp::Eq => "==",
             BinaryOp::Ne => "!=",
             BinaryOp::Lt => "<",
@@ -293,6 +2118,484 @@ impl SolanaCodeGenerator {
     }
 }
 
+/// Whether any declared type or literal in the contract is a U256
+fn uses_u256(contract: &Contract) -> bool {
+    let types = contract.constants.iter().map(|c| &c.ty)
+        .chain(contract.state.iter().map(|v| &v.ty))
+        .chain(contract.structs.iter().flat_map(|s| s.fields.iter().map(|f| &f.ty)))
+        .chain(contract.events.iter().flat_map(|e| e.params.iter().map(|p| &p.ty)))
+        .chain(contract.functions.iter().flat_map(|f| f.params.iter().map(|p| &p.ty).chain(&f.return_type)));
+    if types.into_iter().any(mentions_u256) {
+        return true;
+    }
+    
+    let mut found = false;
+    for func in &contract.functions {
+        visit_expressions(&func.body, &mut |expr| {
+            found |= matches!(expr, Expression::TypedNumber { ty: Type::U256, .. });
+        });
+    }
+    found
+}
+
+fn mentions_u256(ty: &Type) -> bool {
+    match ty {
+        Type::U256 => true,
+        Type::Map(k, v) | Type::Result(k, v) => mentions_u256(k) || mentions_u256(v),
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => mentions_u256(t),
+        Type::Tuple(types) => types.iter().any(mentions_u256),
+        _ => false,
+    }
+}
+
+/// The value whose fields `expr` reads, e.g. `pools[k]` for `pools[k].reserve_a`
+fn field_base(mut expr: Expression) -> Expression {
+    while let Expression::Field { object, .. } = expr {
+        expr = *object;
+    }
+    expr
+}
+
+/// `map[key]` for a `map.get(key)`, which reads the same entry
+fn lookup_entry(expr: &Expression) -> Option<Expression> {
+    match expr {
+        Expression::MethodCall { object, method, args } if method == "get" && args.len() == 1 && expr.is_value_method() => {
+            Some(Expression::Index { array: object.clone(), index: Box::new(args[0].clone()) })
+        }
+        _ => None,
+    }
+}
+
+/// The map variable and keys of a `map[k1][k2]...` chain, outermost key first
+fn index_chain(expr: &Expression) -> Option<(String, Vec<Expression>)> {
+    let mut keys = Vec::new();
+    let mut current = expr;
+    loop {
+        match current {
+            Expression::Index { array, index } => {
+                keys.push((**index).clone());
+                current = array;
+            }
+            Expression::Identifier(name) => {
+                keys.reverse();
+                return Some((name.clone(), keys));
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Checks that `id` is a base58-encoded 32-byte public key
+pub fn validate_program_id(id: &str) -> Result<()> {
+    let bytes = bs58::decode(id).into_vec()
+        .map_err(|e| anyhow!("Program id '{}' is not valid base58: {}", id, e))?;
+    if bytes.len() != 32 {
+        return Err(anyhow!("Program id '{}' decodes to {} bytes; a public key has 32", id, bytes.len()));
+    }
+    Ok(())
+}
+
+/// Variants of the generated `ErrorCode` enum with their messages, in order
+fn error_codes(contract: &Contract) -> Vec<(String, String)> {
+    let builtin = builtin_errors(contract);
+    let required = require_errors(contract, &builtin, REQUIRE_FAILED.0, error_variant);
+    builtin.iter()
+        .map(|(name, msg)| (name.to_string(), msg.to_string()))
+        .chain(required.into_iter()
+            .filter(|e| !e.builtin)
+            .map(|e| (e.name, e.message.unwrap_or_else(|| REQUIRE_FAILED.1.to_string()))))
+        .collect()
+}
+
+/// Error codes generated whatever the `require`s: the builtin ones, the length check's
+/// when state holds strings or bytes and, with a bridge, the bridge's
+fn builtin_errors(contract: &Contract) -> Vec<(&'static str, &'static str)> {
+    let length = if contract.state.iter().any(|v| stores_bytes(&v.ty)) { LENGTH_ERRORS } else { &[] };
+    let bridge = if contract.bridge.is_some() { BRIDGE_ERRORS } else { &[] };
+    BUILTIN_ERRORS.iter().chain(length).chain(bridge).copied().collect()
+}
+
+/// Whether a state variable of type `ty` stores strings or bytes with a maximum length
+fn stores_bytes(ty: &Type) -> bool {
+    match ty {
+        Type::String(_) | Type::Bytes(_) => true,
+        Type::Map(_, value) => stores_bytes(value),
+        _ => false,
+    }
+}
+
+/// A type in Anchor IDL notation; tuples, maps and results have none
+fn idl_type(ty: &Type) -> Result<IdlType> {
+    Ok(match ty {
+        Type::U8 => IdlType::U8,
+        Type::U16 => IdlType::U16,
+        Type::U32 => IdlType::U32,
+        Type::U64 => IdlType::U64,
+        Type::U128 => IdlType::U128,
+        Type::U256 => IdlType::U256,
+        Type::I8 => IdlType::I8,
+        Type::I16 => IdlType::I16,
+        Type::I32 => IdlType::I32,
+        Type::I64 => IdlType::I64,
+        Type::I128 => IdlType::I128,
+        Type::Bool => IdlType::Bool,
+        Type::Address => IdlType::PublicKey,
+        Type::String(_) => IdlType::String,
+        Type::Bytes(_) => IdlType::Bytes,
+        Type::Vec(inner) => IdlType::Vec(Box::new(idl_type(inner)?)),
+        Type::Array(inner, len) => IdlType::Array(Box::new(idl_type(inner)?), *len),
+        Type::Option(inner) => IdlType::Option(Box::new(idl_type(inner)?)),
+        Type::Struct(name) => IdlType::Defined(name.clone()),
+        Type::Map(..) | Type::Tuple(_) | Type::Result(..) => {
+            return Err(anyhow!("{} has no Anchor IDL representation", ty));
+        }
+    })
+}
+
+/// The name Anchor's IDL and TypeScript client use for a snake_case instruction, account or field
+fn camel_case(name: &str) -> String {
+    let mut parts = name.split('_').filter(|p| !p.is_empty());
+    let first = parts.next().unwrap_or_default().to_string();
+    first + &parts.map(capitalize).collect::<String>()
+}
+
+/// An Accounts struct with the given fields
+fn accounts_struct(context: &str, accounts: &[InstructionAccount]) -> String {
+    // 没有任何账户时不能声明未使用的生命周期
+    if accounts.is_empty() {
+        return format!("pub struct {} {{}}\n\n", context);
+    }
+    
+    let mut code = format!("pub struct {}<'info> {{\n", context);
+    for account in accounts {
+        match &account.kind {
+            AccountKind::Signer => {
+                let mut constraints = account.constraints.clone();
+                if account.mutable {
+                    constraints.insert(0, "mut".to_string());
+                }
+                if !constraints.is_empty() {
+                    code.push_str(&format!("    #[account({})]\n", constraints.join(", ")));
+                }
+                code.push_str(&format!("    pub {}: Signer<'info>,\n", account.name));
+            }
+            AccountKind::State { init: true } => {
+                code.push_str("    #[account(\n");
+                code.push_str("        init,\n");
+                code.push_str(&format!("        payer = {},\n", SIGNER));
+                code.push_str("        space = 8 + State::LEN,\n");
+                code.push_str("        seeds = [b\"state\"],\n");
+                code.push_str("        bump\n");
+                code.push_str("    )]\n");
+                code.push_str(&format!("    pub {}: Account<'info, State>,\n", account.name));
+            }
+            AccountKind::State { init: false } => {
+                code.push_str("    #[account(\n");
+                if account.mutable {
+                    code.push_str("        mut,\n");
+                }
+                code.push_str("        seeds = [b\"state\"],\n");
+                code.push_str("        bump\n");
+                code.push_str("    )]\n");
+                code.push_str(&format!("    pub {}: Account<'info, State>,\n", account.name));
+            }
+            // 条目可能不存在, 由函数体检查数据后再反序列化
+            AccountKind::MapEntry(entry) if entry.optional => {
+                code.push_str("    /// CHECK: the entry's PDA, read only if it holds data\n");
+                code.push_str("    #[account(\n");
+                code.push_str(&format!("        seeds = [{}],\n", entry.seeds.join(", ")));
+                code.push_str("        bump\n");
+                code.push_str("    )]\n");
+                code.push_str(&format!("    pub {}: UncheckedAccount<'info>,\n", account.name));
+            }
+            AccountKind::MapEntry(entry) => {
+                let ty = entry_type_name(&entry.map);
+                code.push_str("    #[account(\n");
+                if entry.debits {
+                    code.push_str("        mut,\n");
+                } else if entry.writes {
+                    code.push_str("        init_if_needed,\n");
+                    code.push_str(&format!("        payer = {},\n", SIGNER));
+                    code.push_str(&format!("        space = 8 + {}::LEN,\n", ty));
+                }
+                for constraint in &account.constraints {
+                    code.push_str(&format!("        {},\n", constraint));
+                }
+                code.push_str(&format!("        seeds = [{}],\n", entry.seeds.join(", ")));
+                code.push_str("        bump\n");
+                code.push_str("    )]\n");
+                code.push_str(&format!("    pub {}: Account<'info, {}>,\n", account.name, ty));
+            }
+            AccountKind::Program(target) | AccountKind::ExternalProgram { contract: target, .. } => {
+                let module = target.to_lowercase();
+                code.push_str(&format!("    pub {}: Program<'info, {}::program::{}>,\n", account.name, module, target));
+            }
+            AccountKind::System => {
+                code.push_str(&format!("    pub {}: Program<'info, System>,\n", account.name));
+            }
+            // mint 以自身为铸币权限, 由程序签名铸造
+            AccountKind::Mint { decimals } => {
+                code.push_str("    #[account(\n");
+                if decimals.is_some() {
+                    code.push_str("        init,\n");
+                    code.push_str(&format!("        payer = {},\n", SIGNER));
+                } else if account.mutable {
+                    code.push_str("        mut,\n");
+                }
+                code.push_str(&format!("        seeds = [b\"{}\"],\n", MINT));
+                code.push_str("        bump");
+                if let Some(decimals) = decimals {
+                    code.push_str(",\n");
+                    code.push_str(&format!("        mint::decimals = {},\n", decimals));
+                    code.push_str(&format!("        mint::authority = {}.key()", MINT));
+                }
+                code.push_str("\n    )]\n");
+                code.push_str(&format!("    pub {}: Account<'info, anchor_spl::token::Mint>,\n", account.name));
+            }
+            AccountKind::BridgeVault { init } => {
+                code.push_str("    #[account(\n");
+                if *init {
+                    code.push_str("        init,\n");
+                    code.push_str(&format!("        payer = {},\n", SIGNER));
+                    code.push_str(&format!("        space = 8 + {}::LEN,\n", VAULT_TYPE));
+                } else if account.mutable {
+                    code.push_str("        mut,\n");
+                }
+                code.push_str(&format!("        seeds = [b\"{}\"],\n", VAULT));
+                code.push_str("        bump\n");
+                code.push_str("    )]\n");
+                code.push_str(&format!("    pub {}: Account<'info, {}>,\n", account.name, VAULT_TYPE));
+            }
+            AccountKind::TokenAccount { owner, init, .. } => {
+                code.push_str("    #[account(\n");
+                if *init {
+                    code.push_str("        init_if_needed,\n");
+                    code.push_str(&format!("        payer = {},\n", SIGNER));
+                } else if account.mutable {
+                    code.push_str("        mut,\n");
+                }
+                code.push_str(&format!("        associated_token::mint = {},\n", MINT));
+                code.push_str(&format!("        associated_token::authority = {}\n", owner));
+                code.push_str("    )]\n");
+                code.push_str(&format!("    pub {}: Account<'info, anchor_spl::token::TokenAccount>,\n", account.name));
+            }
+            AccountKind::Wallet(address) => {
+                code.push_str("    /// CHECK: only the owner of the token account created for it\n");
+                code.push_str(&format!("    #[account(address = {})]\n", address));
+                code.push_str(&format!("    pub {}: UncheckedAccount<'info>,\n", account.name));
+            }
+            AccountKind::TokenProgram => {
+                code.push_str(&format!("    pub {}: Program<'info, anchor_spl::token::Token>,\n", account.name));
+            }
+            AccountKind::AssociatedTokenProgram => {
+                code.push_str(&format!("    pub {}: Program<'info, anchor_spl::associated_token::AssociatedToken>,\n", account.name));
+            }
+        }
+    }
+    code.push_str("}\n\n");
+    code
+}
+
+fn integer_bits(ty: &Type) -> u32 {
+    match ty {
+        Type::U8 => 8,
+        Type::U16 => 16,
+        Type::U32 => 32,
+        Type::U128 => 128,
+        Type::U256 => 256,
+        _ => 64,
+    }
+}
+
+/// Recognizes the counter loop the parser builds for `for i in start..end`,
+/// returning the variable, the bounds and whether the end is inclusive
+fn range_loop<'a>(init: &'a Statement, condition: &'a Expression, update: &Statement)
+    -> Option<(&'a str, &'a Expression, &'a Expression, bool)>
+{
+    let Statement::Let { name, value: start, .. } = init else { return None };
+    let Expression::Binary { op, left, right: end } = condition else { return None };
+    let inclusive = match op {
+        BinaryOp::Lt => false,
+        BinaryOp::Le => true,
+        _ => return None,
+    };
+    let counter = Expression::Identifier(name.clone());
+    let step = Expression::Binary {
+        op: BinaryOp::Add,
+        left: Box::new(counter.clone()),
+        right: Box::new(Expression::Number(1)),
+    };
+    match update {
+        Statement::Assign { target: LValue::Identifier(target), value }
+            if target == name && **left == counter && *value == step => Some((name, start, end, inclusive)),
+        _ => None,
+    }
+}
+
+/// Whether two entries of the same map can be the same PDA while one of them is written;
+/// keys that are different literals never are
+fn may_alias(a: &MapEntry, b: &MapEntry) -> bool {
+    let literal = |key: &Expression| matches!(key, Expression::Number(_) | Expression::TypedNumber { .. });
+    a.map == b.map && !a.optional && !b.optional && (a.writes || b.writes)
+        && !a.keys.iter().zip(&b.keys).any(|(x, y)| literal(x) && literal(y) && x != y)
+}
+
+/// Key types of a map, outermost first; empty for anything else
+fn map_key_types(ty: &Type) -> Vec<Type> {
+    let mut keys = Vec::new();
+    let mut current = ty;
+    while let Type::Map(key, value) = current {
+        keys.push((**key).clone());
+        current = value;
+    }
+    keys
+}
+
+fn entry_type_name(map: &str) -> String {
+    format!("{}Entry", pascal_case(map))
+}
+
+/// Part of the account field name derived from a map key
+fn key_name(key: &Expression) -> String {
+    match key {
+        Expression::Identifier(name) => name.clone(),
+        Expression::MsgSender => "sender".to_string(),
+        Expression::Number(n) => n.to_string(),
+        Expression::TypedNumber { value, .. } => value.to_string(),
+        _ => "key".to_string(),
+    }
+}
+
+/// Accounts struct name for each function. Names that would clash with a struct,
+/// an event or a generated type get an `Accounts` suffix; `check_type_names` has
+/// already rejected functions whose names clash with each other.
+fn context_names(contract: &Contract) -> HashMap<String, String> {
+    let mut taken: HashSet<String> = type_names(contract).into_iter().map(|(name, _)| name).collect();
+    let functions = function_names(contract);
+    // 加后缀的名字也不能占用其他函数的名字
+    let bases: HashSet<String> = functions.iter().map(|func| pascal_case(func)).collect();
+    
+    let mut names = HashMap::new();
+    for func in functions {
+        let base = pascal_case(func);
+        let mut name = base.clone();
+        let mut n = 1;
+        while taken.contains(&name) || (name != base && bases.contains(&name)) {
+            name = if n == 1 { format!("{}Accounts", base) } else { format!("{}Accounts{}", base, n) };
+            n += 1;
+        }
+        taken.insert(name.clone());
+        names.insert(func.to_string(), name);
+    }
+    names
+}
+
+/// Functions that get an instruction, including the generated `initialize` and `init_bridge`
+fn function_names(contract: &Contract) -> Vec<&str> {
+    let mut functions: Vec<&str> = contract.functions.iter()
+        .filter(|f| is_instruction(f))
+        .map(|f| f.name.as_str())
+        .collect();
+    if contract.constructor().is_none() {
+        functions.push(INITIALIZE);
+    }
+    if contract.bridge.is_some() {
+        functions.push(INIT_BRIDGE);
+    }
+    functions
+}
+
+/// Whether `func` becomes an instruction; private and internal functions are helpers
+/// the instructions call
+/// Assigns the declared initial values of the state variables at the start of the
+/// constructor, after the checks that became account constraints, adding an `initialize`
+/// if there is none
+fn set_initial_values(contract: &mut Contract) {
+    let assignments: Vec<Statement> = contract.state.iter()
+        .filter_map(|var| var.initial_value.as_ref().map(|value| Statement::Assign {
+            target: LValue::Identifier(var.name.clone()),
+            value: value.clone(),
+        }))
+        .collect();
+    if assignments.is_empty() {
+        return;
+    }
+    let constructor = contract.constructor().map(|f| f.name.clone());
+    match contract.functions.iter_mut().find(|f| Some(&f.name) == constructor.as_ref()) {
+        Some(func) => {
+            let at = func.modifiers.len();
+            func.body.splice(at..at, assignments);
+        }
+        None => contract.functions.insert(0, Function {
+            visibility: Visibility::Public,
+            name: INITIALIZE.to_string(),
+            params: Vec::new(),
+            return_type: None,
+            modifiers: Vec::new(),
+            body: assignments,
+            is_payable: false,
+            is_view: false,
+            is_constructor: true,
+            line: 0,
+        }),
+    }
+}
+
+fn is_instruction(func: &Function) -> bool {
+    matches!(func.visibility, Visibility::Public | Visibility::External)
+}
+
+/// Rust types the program declares, each with what it is generated from
+fn type_names(contract: &Contract) -> Vec<(String, String)> {
+    ["State", "ErrorCode"].iter().map(|name| (name.to_string(), format!("the generated '{}'", name)))
+        .chain(contract.bridge.iter().map(|_| (VAULT_TYPE.to_string(), format!("the generated '{}'", VAULT_TYPE))))
+        .chain(contract.structs.iter().map(|s| (s.name.clone(), format!("struct '{}'", s.name))))
+        .chain(contract.events.iter().map(|e| (pascal_case(&e.name), format!("event '{}'", e.name))))
+        .chain(contract.state.iter()
+            .filter(|v| matches!(v.ty, Type::Map(..)))
+            .map(|v| (entry_type_name(&v.name), format!("map '{}'", v.name))))
+        .collect()
+}
+
+/// Rejects identifiers that become the same Rust name, e.g. functions `newPool` and
+/// `new_pool` (both `NewPool`) or event `pool_created` and struct `PoolCreated`
+fn check_type_names(contract: &Contract) -> Result<()> {
+    let functions = function_names(contract).into_iter()
+        .map(|func| (pascal_case(func), format!("function '{}'", func)))
+        .collect();
+    for names in [type_names(contract), functions] {
+        let mut sources: Vec<(String, Vec<String>)> = Vec::new();
+        for (name, source) in names {
+            match sources.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, list)) => list.push(source),
+                None => sources.push((name, vec![source])),
+            }
+        }
+        if let Some((name, list)) = sources.into_iter().find(|(_, list)| list.len() > 1) {
+            return Err(anyhow!("{} all become '{}' in the Solana program; rename all but one", list.join(", "), name));
+        }
+    }
+    Ok(())
+}
+
+/// CamelCase error variant for a require message, e.g. `Only owner` -> `OnlyOwner`
+fn error_variant(words: &[&str]) -> String {
+    let name = pascal_case(&words.join("_"));
+    // 标识符不能为空或以数字开头
+    if name.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        format!("Error{}", name)
+    } else {
+        name
+    }
+}
+
+/// The CamelCase Rust type name for a DSL identifier: `create_pool` -> `CreatePool`,
+/// `newPool` -> `NewPool`, `_swap_v2` -> `SwapV2`. Letters after the first of each
+/// `_`-separated word keep their case, so `ERC20_vault` becomes `ERC20Vault`.
+fn pascal_case(name: &str) -> String {
+    name.split('_').map(capitalize).collect()
+}
+
 fn capitalize(s: &str) -> String {
     let mut chars = s.chars();
     match chars.next() {
@@ -319,4 +2622,34 @@ pub mod {{contract_name}} {
     }
     {{/each}}
 }
-"#;
\ No newline at end of file
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_pascal_case() {
+        assert_eq!(pascal_case("create_pool"), "CreatePool");
+        assert_eq!(pascal_case("transfer"), "Transfer");
+        // Mixed case keeps the inner capitals, so camelCase and snake_case spellings meet
+        assert_eq!(pascal_case("newPool"), "NewPool");
+        assert_eq!(pascal_case("new_pool"), "NewPool");
+        assert_eq!(pascal_case("getHTTPRate"), "GetHTTPRate");
+        // Digits stay where they are
+        assert_eq!(pascal_case("swap_v2"), "SwapV2");
+        assert_eq!(pascal_case("pool_2_fee"), "Pool2Fee");
+        assert_eq!(pascal_case("ERC20_vault"), "ERC20Vault");
+        // Leading, trailing and repeated underscores are dropped
+        assert_eq!(pascal_case("_internal"), "Internal");
+        assert_eq!(pascal_case("__init__bridge_"), "InitBridge");
+    }
+    
+    #[test]
+    fn test_error_variant() {
+        assert_eq!(error_variant(&["Only", "owner"]), "OnlyOwner");
+        assert_eq!(error_variant(&["max", "supply", "reached"]), "MaxSupplyReached");
+        assert_eq!(error_variant(&["404", "not", "found"]), "Error404NotFound");
+        assert_eq!(error_variant(&[]), "Error");
+    }
+}
@@ -0,0 +1,329 @@
+use super::bridge::{init_bridge_accounts, CHAIN_IDS, INIT_BRIDGE, VAULT};
+use super::{
+    builtin_errors, camel_case, capitalize, error_variant, AccountKind, InstructionAccount,
+    SolanaCodeGenerator, INITIALIZE, MINT, REQUIRE_FAILED,
+};
+use crate::codegen::{bridge_calls, integer_bytes, is_constructor, is_unsigned, require_errors, require_violation, BreakingValue, Violation};
+use crate::{Bridge, Contract, Expression, Parameter, Statement, Type, Visibility};
+use anyhow::Result;
+use std::collections::HashMap;
+
+// 访问控制失败的用例由这个钱包签名
+const OTHER_SIGNER: &str = "other";
+
+/// One `program.methods` call of a test
+struct Call<'a> {
+    method: &'a str,
+    params: &'a [Parameter],
+    args: Vec<String>,
+    accounts: &'a [InstructionAccount],
+    other_signer: bool,
+    // view 函数通过模拟执行读取返回值, 不发送交易
+    view: bool,
+}
+
+impl SolanaCodeGenerator {
+    /// A mocha test file for the program `generate` emits, to run with `anchor test`.
+    ///
+    /// Every public function gets a test calling it with default arguments (zeros,
+    /// empty strings, `PublicKey.default`) that expects it to succeed, and every
+    /// `require` message in its body a test expecting its error code. The failing
+    /// arguments are only derived for simple conditions on a parameter or
+    /// `msg_sender()`; the other failing tests are emitted as `it.skip`.
+    pub fn generate_tests(&self, contract: &Contract) -> Result<String> {
+        let contract = self.prepare(contract)?;
+        let module = contract.name.to_lowercase();
+        let program_type = capitalize(&module);
+        let errors: HashMap<Option<String>, String> = require_errors(&contract, &builtin_errors(&contract), REQUIRE_FAILED.0, error_variant)
+            .into_iter()
+            .map(|e| (e.message, e.name))
+            .collect();
+        let access = self.state_access(&contract);
+        let creates_state = self.initializes(&contract);
+
+        let mut instructions = Vec::new();
+        for func in &contract.functions {
+            self.scope.replace(self.function_scope(&contract, func)?);
+            instructions.push((func, self.instruction_accounts(&contract, func, access[&func.name])));
+        }
+        self.scope.take();
+
+        // 每个函数的测试, 同时记录是否用到另一个钱包
+        let mut tests = String::new();
+        let mut uses_other = false;
+        for (func, accounts) in &instructions {
+            if !matches!(func.visibility, Visibility::Public | Visibility::External) {
+                continue;
+            }
+            let mut args: Vec<String> = func.params.iter().map(|p| default_value(&contract, &p.ty)).collect();
+            // bridge 只接受其他链的 id, 链参数默认使用第一个
+            let calls = bridge_calls(&func.body, Bridge::LOCK).into_iter().chain(bridge_calls(&func.body, Bridge::RECEIVE));
+            for chain in calls.filter_map(|args| args.last().cloned()) {
+                if let Expression::Identifier(name) = chain {
+                    if let Some(index) = func.params.iter().position(|p| p.name == name) {
+                        args[index] = number(CHAIN_IDS[1].1 as u128, &func.params[index].ty);
+                    }
+                }
+            }
+
+            let constructs = creates_state && is_constructor(&contract, func);
+            if constructs {
+                tests.push_str(&format!("  it({:?}, async () => {{\n", func.name));
+                tests.push_str(&format!("    // {} runs once, in before()\n", func.name));
+                if self.has_state_account(&contract) {
+                    tests.push_str("    await program.account.state.fetch(pda(Buffer.from(\"state\")));\n");
+                } else {
+                    tests.push_str(&format!(
+                        "    expect(await provider.connection.getAccountInfo(pda(Buffer.from({:?})))).to.not.be.null;\n", MINT));
+                }
+                tests.push_str("  });\n\n");
+            } else {
+                tests.push_str(&format!("  it({:?}, async () => {{\n", func.name));
+                let call = Call { method: &func.name, params: &func.params, args: args.clone(), accounts, other_signer: false, view: func.is_view };
+                tests.push_str(&call.render(2));
+                tests.push_str("  });\n\n");
+            }
+
+            let mut tested = Vec::new();
+            for stmt in &func.body {
+                let Statement::Require { condition, message: message @ Some(_) } = stmt else { continue };
+                let error = &errors[message];
+                if tested.contains(&error) {
+                    continue;
+                }
+                tested.push(error);
+
+                let name = format!("{} fails with {}", func.name, error);
+                // 构造函数已在 before() 中执行, 不能再调用
+                let violation = if constructs { None } else { require_violation(condition, &func.params) };
+                if let Some(violation) = violation {
+                    let mut call = Call { method: &func.name, params: &func.params, args: args.clone(), accounts, other_signer: false, view: func.is_view };
+                    match violation {
+                        Violation::Argument(index, value) => call.args[index] = breaking_value(value, &func.params[index].ty),
+                        Violation::OtherSigner => call.other_signer = true,
+                    }
+                    uses_other |= call.other_signer;
+
+                    tests.push_str(&format!("  it({:?}, async () => {{\n", name));
+                    tests.push_str("    try {\n");
+                    tests.push_str(&call.render(3));
+                    tests.push_str(&format!("      expect.fail(\"{} should have failed\");\n", func.name));
+                    tests.push_str("    } catch (err) {\n");
+                    tests.push_str("      expect(err).to.be.instanceOf(anchor.AnchorError);\n");
+                    tests.push_str(&format!("      expect((err as anchor.AnchorError).error.errorCode.code).to.equal({:?});\n", error));
+                    tests.push_str("    }\n");
+                    tests.push_str("  });\n\n");
+                } else {
+                    tests.push_str(&format!("  it.skip({:?}, async () => {{\n", name));
+                    tests.push_str("    // TODO: set up accounts or arguments that make this require fail\n");
+                    tests.push_str("  });\n\n");
+                }
+            }
+        }
+
+        let mut code = String::new();
+        code.push_str("import * as anchor from \"@coral-xyz/anchor\";\n");
+        code.push_str("import { BN, Program } from \"@coral-xyz/anchor\";\n");
+        code.push_str("import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from \"@solana/web3.js\";\n");
+        if self.is_spl() {
+            code.push_str("import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync } from \"@solana/spl-token\";\n");
+        }
+        code.push_str("import { expect } from \"chai\";\n");
+        code.push_str(&format!("import {{ {} }} from \"../target/types/{}\";\n\n", program_type, module));
+
+        code.push_str(&format!("describe({:?}, () => {{\n", module));
+        code.push_str("  const provider = anchor.AnchorProvider.env();\n");
+        code.push_str("  anchor.setProvider(provider);\n");
+        code.push_str(&format!("  const program = anchor.workspace.{} as Program<{}>;\n", program_type, program_type));
+        code.push_str("  const pda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, program.programId)[0];\n");
+        if uses_other {
+            code.push_str(&format!("  const {} = Keypair.generate();\n", OTHER_SIGNER));
+        }
+        code.push('\n');
+
+        // 状态账户只能创建一次, 在所有测试之前
+        if creates_state || uses_other || contract.bridge.is_some() {
+            code.push_str("  before(async () => {\n");
+            if creates_state {
+                match instructions.iter().find(|(func, _)| is_constructor(&contract, func)) {
+                    Some((func, accounts)) => {
+                        let args = func.params.iter().map(|p| default_value(&contract, &p.ty)).collect();
+                        let call = Call { method: &func.name, params: &func.params, args, accounts, other_signer: false, view: func.is_view };
+                        code.push_str(&call.render(2));
+                    }
+                    None => {
+                        let accounts = self.initialize_accounts(&contract);
+                        let call = Call { method: INITIALIZE, params: &[], args: Vec::new(), accounts: &accounts, other_signer: false, view: false };
+                        code.push_str(&call.render(2));
+                    }
+                }
+            }
+            // 金库的 authority 是测试钱包, 释放代币的函数由它签名
+            if let Some(bridge) = &contract.bridge {
+                let params = [Parameter { name: bridge.authority.clone(), ty: Type::Address, is_mutable: false }];
+                let accounts = init_bridge_accounts();
+                let args = vec!["provider.wallet.publicKey".to_string()];
+                let call = Call { method: INIT_BRIDGE, params: &params, args, accounts: &accounts, other_signer: false, view: false };
+                code.push_str(&call.render(2));
+            }
+            if uses_other {
+                code.push_str(&format!(
+                    "    const airdrop = await provider.connection.requestAirdrop({}.publicKey, LAMPORTS_PER_SOL);\n",
+                    OTHER_SIGNER));
+                code.push_str("    await provider.connection.confirmTransaction(airdrop);\n");
+            }
+            code.push_str("  });\n\n");
+        }
+
+        code.push_str(&tests);
+        // 去掉最后一个测试后的空行
+        if code.ends_with("\n\n") {
+            code.pop();
+        }
+        code.push_str("});\n");
+
+        Ok(code)
+    }
+}
+
+impl Call<'_> {
+    fn render(&self, level: usize) -> String {
+        let pad = "  ".repeat(level);
+        let signer = if self.other_signer {
+            format!("{}.publicKey", OTHER_SIGNER)
+        } else {
+            "provider.wallet.publicKey".to_string()
+        };
+
+        // map 的键 (已还原为签名者、参数、状态字段或字面量) 在测试中的值
+        let key_value = |key: &Expression, ty: &Type| match key {
+            Expression::MsgSender => signer.clone(),
+            Expression::Number(n) => number(*n as u128, ty),
+            Expression::TypedNumber { value, .. } => number(*value, ty),
+            Expression::Identifier(name) => match self.params.iter().position(|p| p.name == *name) {
+                Some(index) => self.args[index].clone(),
+                None => format!(
+                    "(await program.account.state.fetch(pda(Buffer.from(\"state\")))).{}",
+                    camel_case(name)),
+            },
+            _ => unreachable!("map keys are resolved before seeds are built"),
+        };
+
+        let mut code = String::new();
+        code.push_str(&format!("{}await program.methods\n", pad));
+        code.push_str(&format!("{}  .{}({})\n", pad, camel_case(self.method), self.args.join(", ")));
+        code.push_str(&format!("{}  .accountsStrict({{\n", pad));
+        for account in self.accounts {
+            let value = match &account.kind {
+                AccountKind::Signer => signer.clone(),
+                AccountKind::State { .. } => "pda(Buffer.from(\"state\"))".to_string(),
+                AccountKind::MapEntry(entry) => {
+                    let seeds: Vec<String> = std::iter::once(format!("Buffer.from({:?})", entry.map))
+                        .chain(entry.keys.iter().zip(&entry.key_types).map(|(k, ty)| seed(&key_value(k, ty), ty)))
+                        .collect();
+                    format!("pda({})", seeds.join(", "))
+                }
+                AccountKind::Program(target) => format!("anchor.workspace.{}.programId", capitalize(&target.to_lowercase())),
+                AccountKind::ExternalProgram { address, .. } => format!("new PublicKey({:?})", address),
+                AccountKind::System => "SystemProgram.programId".to_string(),
+                AccountKind::Mint { .. } => format!("pda(Buffer.from({:?}))", MINT),
+                AccountKind::BridgeVault { .. } => format!("pda(Buffer.from({:?}))", VAULT),
+                AccountKind::TokenAccount { entry, .. } => format!(
+                    "getAssociatedTokenAddressSync(pda(Buffer.from({:?})), {})", MINT, key_value(&entry.keys[0], &Type::Address)),
+                AccountKind::Wallet(_) => {
+                    let entry = self.accounts.iter()
+                        .find_map(|a| match &a.kind {
+                            AccountKind::TokenAccount { entry, owner, .. } if *owner == account.name => Some(entry),
+                            _ => None,
+                        })
+                        .expect("a wallet is the owner of a token account");
+                    key_value(&entry.keys[0], &Type::Address)
+                }
+                AccountKind::TokenProgram => "TOKEN_PROGRAM_ID".to_string(),
+                AccountKind::AssociatedTokenProgram => "ASSOCIATED_TOKEN_PROGRAM_ID".to_string(),
+            };
+            code.push_str(&format!("{}    {}: {},\n", pad, camel_case(&account.name), value));
+        }
+        code.push_str(&format!("{}  }})\n", pad));
+        if self.other_signer {
+            code.push_str(&format!("{}  .signers([{}])\n", pad, OTHER_SIGNER));
+        }
+        code.push_str(&format!("{}  .{}();\n", pad, if self.view { "view" } else { "rpc" }));
+
+        code
+    }
+}
+
+/// Anchor's client decodes integers wider than 32 bits as `BN`
+fn is_big(ty: &Type) -> bool {
+    matches!(ty, Type::U64 | Type::U128 | Type::U256 | Type::I64 | Type::I128)
+}
+
+fn number(n: u128, ty: &Type) -> String {
+    match (is_big(ty), n < 1 << 53) {
+        (false, _) => n.to_string(),
+        (true, true) => format!("new BN({})", n),
+        (true, false) => format!("new BN(\"{}\")", n),
+    }
+}
+
+fn breaking_value(value: BreakingValue, ty: &Type) -> String {
+    match value {
+        BreakingValue::Bool(b) => b.to_string(),
+        BreakingValue::Number(n) => number(n, ty),
+        BreakingValue::Max => max_value(ty),
+    }
+}
+
+fn max_value(ty: &Type) -> String {
+    let bits = integer_bytes(ty).unwrap_or(8) * 8;
+    if is_big(ty) {
+        format!("new BN(1).shln({}).subn(1)", bits)
+    } else {
+        ((1u64 << bits) - 1).to_string()
+    }
+}
+
+/// The value the test passes for an argument of type `ty`
+fn default_value(contract: &Contract, ty: &Type) -> String {
+    match ty {
+        _ if is_big(ty) => "new BN(0)".to_string(),
+        _ if integer_bytes(ty).is_some() => "0".to_string(),
+        Type::Bool => "false".to_string(),
+        Type::Address => "PublicKey.default".to_string(),
+        Type::String(_) => "\"\"".to_string(),
+        Type::Bytes(_) => "Buffer.alloc(0)".to_string(),
+        Type::Vec(_) => "[]".to_string(),
+        Type::Array(inner, len) => format!("Array({}).fill({})", len, default_value(contract, inner)),
+        Type::Tuple(types) => format!("[{}]",
+            types.iter().map(|t| default_value(contract, t)).collect::<Vec<_>>().join(", ")),
+        Type::Struct(name) => {
+            let fields: Vec<String> = contract.structs.iter()
+                .find(|s| s.name == *name)
+                .map(|s| s.fields.iter()
+                    .map(|f| format!("{}: {}", camel_case(&f.name), default_value(contract, &f.ty)))
+                    .collect())
+                .unwrap_or_default();
+            if fields.is_empty() { "{}".to_string() } else { format!("{{ {} }}", fields.join(", ")) }
+        }
+        _ => "null".to_string(),
+    }
+}
+
+/// The bytes the program's `seeds` use for a key, matching `to_le_bytes`/`as_ref`
+fn seed(value: &str, ty: &Type) -> String {
+    match ty {
+        Type::Address => format!("{}.toBuffer()", value),
+        Type::String(_) | Type::Bytes(_) => format!("Buffer.from({})", value),
+        Type::Bool => format!("Buffer.from([{} ? 1 : 0])", value),
+        _ => {
+            let bytes = integer_bytes(ty).unwrap_or(8);
+            let value = if is_big(ty) { value.to_string() } else { format!("new BN({})", value) };
+            if is_unsigned(ty) {
+                format!("{}.toArrayLike(Buffer, \"le\", {})", value, bytes)
+            } else {
+                format!("{}.toTwos({}).toArrayLike(Buffer, \"le\", {})", value, bytes * 8, bytes)
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::{indent, pascal_case, AccountKind, InstructionAccount, SolanaCodeGenerator, MINT, SIGNER};
+use crate::codegen::bridge_calls;
+use crate::{Bridge, Contract, EventDefinition, EventParam, Expression, Function, Parameter, Type};
+use anyhow::{Result, anyhow};
+
+// 金库账户在 Accounts 结构中的字段名, 也是它的 PDA seed
+pub(super) const VAULT: &str = "bridge_vault";
+pub(super) const VAULT_TYPE: &str = "BridgeVault";
+
+// 创建金库并设置 authority 的指令
+pub(super) const INIT_BRIDGE: &str = "init_bridge";
+
+// 释放代币的函数额外接收的参数: 中继者传入锁定事件中的序号
+pub(super) const SEQUENCE: &str = "sequence";
+
+// 与 solana-impl 中手写的桥接程序使用相同的事件名
+const LOCK_EVENT: &str = "CrossChainLockEvent";
+const RECEIVE_EVENT: &str = "CrossChainReceiveEvent";
+
+// 合约有 bridge 时额外生成的错误码 (变体名, 消息)
+pub(super) const BRIDGE_ERRORS: &[(&str, &str)] = &[
+    ("InsufficientVaultBalance", "Insufficient vault balance"),
+    ("UnsupportedChain", "Unsupported chain"),
+    ("AlreadyProcessed", "Message already processed"),
+    ("SequenceGap", "Message arrived before an earlier one from the same chain"),
+];
+
+// 各链的 id, 与 core::types::ChainType::chain_id 一致; 第一个是本链
+pub(super) const CHAIN_IDS: &[(&str, u32)] = &[
+    ("SOLANA_CHAIN_ID", 1),
+    ("APTOS_CHAIN_ID", 2),
+    ("SUI_CHAIN_ID", 3),
+];
+
+/// Rejects declarations that would clash with what the bridge generates
+pub(super) fn check_bridge_contract(contract: &Contract, bridge: &Bridge) -> Result<()> {
+    if contract.functions.iter().any(|f| f.name == INIT_BRIDGE) {
+        return Err(anyhow!("Function '{}' clashes with the instruction generated for the bridge", INIT_BRIDGE));
+    }
+    let generated_types = [VAULT_TYPE, LOCK_EVENT, RECEIVE_EVENT];
+    if let Some(name) = contract.events.iter().map(|e| pascal_case(&e.name))
+        .chain(contract.structs.iter().map(|s| s.name.clone()))
+        .find(|name| generated_types.contains(&name.as_str()))
+    {
+        return Err(anyhow!("'{}' clashes with a type generated for the bridge", name));
+    }
+    if let Some(constant) = contract.constants.iter().find(|c| CHAIN_IDS.iter().any(|(id, _)| c.name == *id)) {
+        return Err(anyhow!("Constant '{}' clashes with a chain id generated for the bridge", constant.name));
+    }
+    // authority 是释放指令中的签名账户, 不能与其他账户重名
+    if [SIGNER, "state", VAULT, MINT, "system_program"].contains(&bridge.authority.as_str()) {
+        return Err(anyhow!("Bridge authority cannot be named '{}', a generated account uses that name", bridge.authority));
+    }
+    Ok(())
+}
+
+/// Adds the `sequence` parameter to the functions that release tokens: the relayer passes the
+/// sequence number of the lock event being delivered, so each one is released exactly once
+pub(super) fn add_sequence_params(contract: &mut Contract) -> Result<()> {
+    for func in contract.functions.iter_mut() {
+        match bridge_calls(&func.body, Bridge::RECEIVE).len() {
+            0 => continue,
+            1 => {}
+            _ => return Err(anyhow!("Function '{}' calls {} more than once; each call delivers its own message", func.name, Bridge::RECEIVE)),
+        }
+        if func.params.iter().any(|p| p.name == SEQUENCE) {
+            return Err(anyhow!("Function '{}' cannot have a parameter named '{}', which {} adds", func.name, SEQUENCE, Bridge::RECEIVE));
+        }
+        func.params.push(Parameter { name: SEQUENCE.to_string(), ty: Type::U64, is_mutable: false });
+    }
+    Ok(())
+}
+
+/// Fields of the vault account with their sizes: the authority, the locked amount, the
+/// sequence number of the next lock event and, per remote chain, of the next message to release
+pub(super) fn vault_fields(bridge: &Bridge) -> Vec<(&str, Type, usize)> {
+    let remotes = CHAIN_IDS.len() - 1;
+    vec![
+        (bridge.authority.as_str(), Type::Address, 32),
+        ("locked_amount", Type::U64, 8),
+        ("sequence", Type::U64, 8),
+        ("inbound_sequences", Type::Array(Box::new(Type::U64), remotes), 8 * remotes),
+    ]
+}
+
+/// The events `bridge_lock` and `bridge_receive` emit
+pub(super) fn bridge_events() -> Vec<EventDefinition> {
+    let event = |name: &str, params: &[(&str, Type)]| EventDefinition {
+        name: name.to_string(),
+        params: params.iter()
+            .map(|(param, ty)| EventParam { name: param.to_string(), ty: ty.clone(), indexed: false })
+            .collect(),
+    };
+    vec![
+        event(LOCK_EVENT, &[("from", Type::Address), ("amount", Type::U64), ("target_chain", Type::U32), ("sequence", Type::U64), ("timestamp", Type::I64)]),
+        event(RECEIVE_EVENT, &[("to", Type::Address), ("amount", Type::U64), ("source_chain", Type::U32), ("sequence", Type::U64), ("timestamp", Type::I64)]),
+    ]
+}
+
+/// Whether a function locks tokens and whether it releases them
+pub(super) fn bridge_use(func: &Function) -> (bool, bool) {
+    (!bridge_calls(&func.body, Bridge::LOCK).is_empty(), !bridge_calls(&func.body, Bridge::RECEIVE).is_empty())
+}
+
+/// Accounts of `init_bridge`: the payer, the vault it creates and the system program
+pub(super) fn init_bridge_accounts() -> Vec<InstructionAccount> {
+    vec![
+        InstructionAccount::new(SIGNER, AccountKind::Signer, true),
+        InstructionAccount::new(VAULT, AccountKind::BridgeVault { init: true }, true),
+        InstructionAccount::new("system_program", AccountKind::System, false),
+    ]
+}
+
+impl SolanaCodeGenerator {
+    /// `init_bridge`, which creates the vault and records who may release tokens from it
+    pub(super) fn generate_init_bridge(&self, bridge: &Bridge, context: &str) -> String {
+        let mut code = String::new();
+        code.push_str(&format!("    pub fn {}(ctx: Context<{}>, {}: Pubkey) -> Result<()> {{\n", INIT_BRIDGE, context, bridge.authority));
+        code.push_str(&format!("        ctx.accounts.{}.{} = {};\n", VAULT, bridge.authority, bridge.authority));
+        code.push_str(&format!("        ctx.accounts.{}.locked_amount = 0;\n", VAULT));
+        code.push_str(&format!("        ctx.accounts.{}.sequence = 0;\n", VAULT));
+        code.push_str(&format!("        ctx.accounts.{}.inbound_sequences = [0; {}];\n", VAULT, CHAIN_IDS.len() - 1));
+        code.push_str("        Ok(())\n");
+        code.push_str("    }\n\n");
+        code
+    }
+
+    /// The authority check at the top of a function that releases tokens
+    pub(super) fn bridge_authority_check(&self, bridge: &Bridge) -> String {
+        format!("require!(ctx.accounts.{}.key() == ctx.accounts.{}.{}, ErrorCode::Unauthorized);",
+            bridge.authority, VAULT, bridge.authority)
+    }
+
+    /// Lowers a `bridge_lock`/`bridge_receive` statement: the vault's locked amount
+    /// changes and a cross-chain event records the transfer with its sequence number;
+    /// a release first checks its message wasn't released before. `None` for any other expression.
+    pub(super) fn bridge_call(&self, expr: &Expression, level: usize) -> Option<String> {
+        let Expression::Call { func, args } = expr else { return None };
+        let Expression::Identifier(name) = func.as_ref() else { return None };
+        let vault = format!("ctx.accounts.{}", VAULT);
+        let field = |name: &str, value: String| if value == name { value } else { format!("{}: {}", name, value) };
+        let timestamp = "timestamp: Clock::get()?.unix_timestamp";
+
+        let lines = match (name.as_str(), args.as_slice()) {
+            (Bridge::LOCK, [amount, chain]) => {
+                let amount = self.expression_to_rust(amount);
+                vec![
+                    format!("{v}.locked_amount = {v}.locked_amount.checked_add({}).ok_or(ErrorCode::Overflow)?;", amount, v = vault),
+                    format!("emit!({} {{ from: ctx.accounts.{}.key(), {}, target_chain: remote_chain({})?, sequence: next_outbound(&mut {})?, {} }});",
+                        LOCK_EVENT, SIGNER, field("amount", amount), self.expression_to_rust(chain), vault, timestamp),
+                ]
+            }
+            (Bridge::RECEIVE, [to, amount, chain]) => {
+                let amount = self.expression_to_rust(amount);
+                let chain = self.expression_to_rust(chain);
+                vec![
+                    format!("process_inbound(&mut {}, remote_chain({})?, {})?;", vault, chain, SEQUENCE),
+                    format!("require!({}.locked_amount >= {}, ErrorCode::InsufficientVaultBalance);", vault, amount),
+                    format!("{}.locked_amount -= {};", vault, amount),
+                    format!("emit!({} {{ {}, {}, source_chain: remote_chain({})?, {}, {} }});",
+                        RECEIVE_EVENT, field("to", self.expression_to_rust(to)), field("amount", amount),
+                        chain, SEQUENCE, timestamp),
+                ]
+            }
+            _ => return None,
+        };
+        Some(lines.join(&format!("\n{}", indent(level))))
+    }
+
+    /// The vault account, the chain ids, the check that a chain is one the bridge connects to
+    /// and the sequence numbers that let relayers detect replayed or missing messages
+    pub(super) fn generate_bridge(&self, bridge: &Bridge) -> String {
+        let fields = vault_fields(bridge);
+        let fields: Vec<(&str, &Type, usize)> = fields.iter().map(|(name, ty, size)| (*name, ty, *size)).collect();
+        let mut code = self.generate_account_struct(VAULT_TYPE, &fields);
+
+        code.push_str("// Chain ids, as in core::types::ChainType::chain_id\n");
+        for (name, id) in CHAIN_IDS {
+            code.push_str(&format!("pub const {}: u32 = {};\n", name, id));
+        }
+        code.push('\n');
+
+        let remote: Vec<&str> = CHAIN_IDS[1..].iter().map(|(name, _)| *name).collect();
+        code.push_str("/// Checks that tokens can be bridged between this chain and `chain`\n");
+        code.push_str("fn remote_chain(chain: u64) -> Result<u32> {\n");
+        code.push_str("    match u32::try_from(chain) {\n");
+        code.push_str(&format!("        Ok(id @ ({})) => Ok(id),\n", remote.join(" | ")));
+        code.push_str("        _ => err!(ErrorCode::UnsupportedChain),\n");
+        code.push_str("    }\n");
+        code.push_str("}\n\n");
+
+        // 每条锁定事件带有递增的序号; 每个来源链的消息按序号依次释放, 同一 (链, 序号) 只能释放一次
+        code.push_str("/// The sequence number of the next lock event\n");
+        code.push_str(&format!("fn next_outbound(vault: &mut {}) -> Result<u64> {{\n", VAULT_TYPE));
+        code.push_str("    let sequence = vault.sequence;\n");
+        code.push_str("    vault.sequence = sequence.checked_add(1).ok_or(ErrorCode::Overflow)?;\n");
+        code.push_str("    Ok(sequence)\n");
+        code.push_str("}\n\n");
+        code.push_str("/// Accepts message `sequence` from `chain` once, and only after the ones before it\n");
+        code.push_str(&format!("fn process_inbound(vault: &mut {}, chain: u32, sequence: u64) -> Result<()> {{\n", VAULT_TYPE));
+        code.push_str("    let next = match chain {\n");
+        for (index, name) in remote.iter().enumerate() {
+            code.push_str(&format!("        {} => &mut vault.inbound_sequences[{}],\n", name, index));
+        }
+        code.push_str("        _ => return err!(ErrorCode::UnsupportedChain),\n");
+        code.push_str("    };\n");
+        code.push_str("    require!(sequence >= *next, ErrorCode::AlreadyProcessed);\n");
+        code.push_str("    require!(sequence == *next, ErrorCode::SequenceGap);\n");
+        code.push_str("    *next += 1;\n");
+        code.push_str("    Ok(())\n");
+        code.push_str("}\n\n");
+        code
+    }
+}
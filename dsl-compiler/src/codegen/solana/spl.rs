@@ -0,0 +1,393 @@
+use super::{indent, FunctionScope, SolanaCodeGenerator, SIGNER};
+use crate::codegen::visit_expressions;
+use crate::{BinaryOp, Contract, Expression, Function, LValue, Statement, Type};
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+// SPL 模式下对应代币账户与 mint 的状态变量
+pub(super) const BALANCES: &str = "balances";
+pub(super) const TOTAL_SUPPLY: &str = "total_supply";
+pub(super) const DECIMALS: &str = "decimals";
+
+// mint 账户在 Accounts 结构中的字段名, 也是它的 PDA seed
+pub(super) const MINT: &str = "mint";
+
+// initialize 没有设置 decimals 时 mint 使用的精度
+const DEFAULT_DECIMALS: u8 = 9;
+
+/// How the Solana backend stores a token contract's balances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenStandard {
+    /// `balances` entries in PDAs of the program itself
+    #[default]
+    Native,
+    /// A real SPL mint: `balances[owner]` is the owner's associated token account,
+    /// `total_supply` and `decimals` are read from the mint, and mints, transfers
+    /// and burns are CPIs into the SPL Token program
+    Spl,
+}
+
+impl FromStr for TokenStandard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(TokenStandard::Native),
+            "spl" => Ok(TokenStandard::Spl),
+            _ => Err(format!("Unknown token standard '{}', expected native or spl", s)),
+        }
+    }
+}
+
+impl fmt::Display for TokenStandard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenStandard::Native => write!(f, "native"),
+            TokenStandard::Spl => write!(f, "spl"),
+        }
+    }
+}
+
+/// A change to balances and supply, lowered to one SPL Token instruction
+#[derive(Debug, Clone)]
+enum TokenOp {
+    MintTo { to: Expression, amount: Expression },
+    Transfer { from: Expression, to: Expression, amount: Expression },
+    Burn { from: Expression, amount: Expression },
+}
+
+/// How the token statements of one function are lowered
+#[derive(Default)]
+pub(super) struct SplPlan {
+    // 替换为 CPI 的语句
+    ops: Vec<(Statement, TokenOp)>,
+    // 已并入其他语句的 CPI 或由 mint 账户约束完成的语句, 不再生成
+    merged: Vec<Statement>,
+    // 收款余额的键 (已还原别名), 对应的关联代币账户按需创建
+    pub credited: Vec<Expression>,
+    // 读取总量或精度
+    pub reads_mint: bool,
+    // 铸造或销毁会修改 mint 的 supply
+    pub mint_mut: bool,
+    // 构造函数中设置的精度 (参数或字面量)
+    pub decimals: Option<Expression>,
+    // 函数是构造函数, 它创建 mint
+    constructor: bool,
+}
+
+/// One balance or supply write of a block, before they are paired up
+enum Change {
+    Credit(Expression, Expression),
+    Debit(Expression, Expression),
+    SetBalance(Expression, Expression),
+    SupplyUp(Expression),
+    SupplyDown(Expression),
+    SetSupply(Expression),
+}
+
+/// Checks the contract has the state SPL mode maps onto a mint
+pub(super) fn check_token_contract(contract: &Contract) -> Result<()> {
+    let var = |name: &str| contract.state.iter().find(|v| v.name == name).map(|v| &v.ty);
+    if !matches!(var(BALANCES), Some(Type::Map(key, value)) if **key == Type::Address && **value == Type::U64) {
+        return Err(anyhow!(
+            "SPL token mode needs a `{}: map<address, u64>` state variable for the token accounts", BALANCES));
+    }
+    if var(TOTAL_SUPPLY) != Some(&Type::U64) {
+        return Err(anyhow!("SPL token mode needs a `{}: u64` state variable, read from the mint", TOTAL_SUPPLY));
+    }
+    if var(DECIMALS).is_some_and(|ty| *ty != Type::U8) {
+        return Err(anyhow!("In SPL token mode, '{}' is the mint's decimals and must be a u8", DECIMALS));
+    }
+    Ok(())
+}
+
+/// Pairs up the balance and supply writes of `func` into mints, transfers and burns
+pub(super) fn plan(func: &Function, constructor: bool, scope: &FunctionScope, locals: &HashSet<String>) -> Result<SplPlan> {
+    let token_var = |name: &str| [BALANCES, TOTAL_SUPPLY, DECIMALS].contains(&name) && !locals.contains(name);
+    let mut plan = SplPlan { constructor, ..SplPlan::default() };
+
+    // balances 只能按地址索引, 不能整体使用
+    let (mut indexed, mut bare) = (0, 0);
+    visit_expressions(&func.body, &mut |expr| match expr {
+        Expression::Index { array, .. } if **array == Expression::Identifier(BALANCES.to_string()) => indexed += 1,
+        Expression::Identifier(name) if name == BALANCES => bare += 1,
+        Expression::Identifier(name) if token_var(name) => plan.reads_mint = true,
+        _ => {}
+    });
+    if token_var(BALANCES) && bare > indexed {
+        return Err(anyhow!(
+            "In SPL token mode, '{}' can only be used as `{}[owner]` (in function '{}')", BALANCES, BALANCES, func.name));
+    }
+
+    plan_block(&func.body, func, scope, &token_var, &mut plan, true)?;
+    Ok(plan)
+}
+
+fn plan_block(
+    stmts: &[Statement],
+    func: &Function,
+    scope: &FunctionScope,
+    token_var: &dyn Fn(&str) -> bool,
+    plan: &mut SplPlan,
+    top_level: bool,
+) -> Result<()> {
+    let mut changes: Vec<(usize, Change)> = Vec::new();
+    for (i, stmt) in stmts.iter().enumerate() {
+        match stmt {
+            Statement::Assign { target: LValue::Identifier(name), value } if name == DECIMALS && token_var(name) => {
+                set_decimals(value, func, plan, top_level)?;
+                plan.merged.push(stmt.clone());
+            }
+            Statement::Assign { target, value } => {
+                if let Some(change) = classify(target, value, func, plan.constructor, token_var)? {
+                    changes.push((i, change));
+                }
+            }
+            Statement::If { then_block, else_block, .. } => {
+                plan_block(then_block, func, scope, token_var, plan, false)?;
+                if let Some(stmts) = else_block {
+                    plan_block(stmts, func, scope, token_var, plan, false)?;
+                }
+            }
+            Statement::While { body, .. } | Statement::ForEach { body, .. } | Statement::For { body, .. } => {
+                plan_block(body, func, scope, token_var, plan, false)?;
+            }
+            Statement::Block(stmts) => plan_block(stmts, func, scope, token_var, plan, false)?,
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    plan_block(&arm.body, func, scope, token_var, plan, false)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let unpaired = || anyhow!(
+        "In SPL token mode, every write to '{}' or '{}' in '{}' must be part of a mint (a balance and {} grow \
+         by the same amount), a transfer (one balance shrinks and another grows by the same amount) or a burn, \
+         within one block",
+        BALANCES, TOTAL_SUPPLY, func.name, TOTAL_SUPPLY);
+    let from_signer = |from: &Expression| {
+        if scope.resolve_key(from) == Some(Expression::MsgSender) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "In SPL token mode, '{}' can only move tokens out of msg_sender()'s balance, since the signer \
+                 is the authority of the token account",
+                func.name))
+        }
+    };
+
+    // 先把总量的变化与余额配对成铸造/销毁, 剩下的扣减与增加配对成转账
+    let mut used = vec![false; changes.len()];
+    let mut ops = Vec::new();
+    for s in 0..changes.len() {
+        let (supply, grows, set) = match &changes[s].1 {
+            Change::SupplyUp(amount) => (amount, true, false),
+            Change::SupplyDown(amount) => (amount, false, false),
+            Change::SetSupply(amount) => (amount, true, true),
+            _ => continue,
+        };
+        let partner = (0..changes.len())
+            .find(|&b| !used[b] && match &changes[b].1 {
+                Change::Credit(_, amount) => grows && !set && amount == supply,
+                Change::SetBalance(_, amount) => set && amount == supply,
+                Change::Debit(_, amount) => !grows && amount == supply,
+                _ => false,
+            })
+            .ok_or_else(unpaired)?;
+        let op = match &changes[partner].1 {
+            Change::Credit(to, amount) | Change::SetBalance(to, amount) => {
+                plan.credited.extend(scope.resolve_key(to));
+                TokenOp::MintTo { to: to.clone(), amount: amount.clone() }
+            }
+            Change::Debit(from, amount) => {
+                from_signer(from)?;
+                TokenOp::Burn { from: from.clone(), amount: amount.clone() }
+            }
+            _ => unreachable!("only balance changes are partners"),
+        };
+        used[s] = true;
+        used[partner] = true;
+        plan.mint_mut = true;
+        ops.push((s, partner, op));
+    }
+    for d in 0..changes.len() {
+        let Change::Debit(from, amount) = &changes[d].1 else { continue };
+        if used[d] {
+            continue;
+        }
+        let credit = (0..changes.len())
+            .find(|&c| !used[c] && matches!(&changes[c].1, Change::Credit(_, credited) if credited == amount))
+            .ok_or_else(unpaired)?;
+        let Change::Credit(to, _) = &changes[credit].1 else { unreachable!() };
+        from_signer(from)?;
+        plan.credited.extend(scope.resolve_key(to));
+        used[d] = true;
+        used[credit] = true;
+        ops.push((d, credit, TokenOp::Transfer { from: from.clone(), to: to.clone(), amount: amount.clone() }));
+    }
+    if used.contains(&false) {
+        return Err(unpaired());
+    }
+
+    // CPI 放在两条语句中靠前的位置
+    for (a, b, op) in ops {
+        let (first, second) = (changes[a].0.min(changes[b].0), changes[a].0.max(changes[b].0));
+        plan.ops.push((stmts[first].clone(), op));
+        plan.merged.push(stmts[second].clone());
+    }
+    Ok(())
+}
+
+/// What a write does to the balances or the supply, `None` for writes to anything else
+fn classify(
+    target: &LValue,
+    value: &Expression,
+    func: &Function,
+    constructor: bool,
+    token_var: &dyn Fn(&str) -> bool,
+) -> Result<Option<Change>> {
+    // `x = x + a`, `x = a + x` 或 `x = x - a` 中的 a 及方向
+    let delta = |var: Expression| match value {
+        Expression::Binary { op: BinaryOp::Add, left, right } if **left == var => Some(((**right).clone(), true)),
+        Expression::Binary { op: BinaryOp::Add, left, right } if **right == var => Some(((**left).clone(), true)),
+        Expression::Binary { op: BinaryOp::Sub, left, right } if **left == var => Some(((**right).clone(), false)),
+        _ => None,
+    };
+
+    Ok(match target {
+        LValue::Index { array, index } if matches!(&**array, LValue::Identifier(name) if name == BALANCES && token_var(name)) => {
+            let var = Expression::Index { array: Box::new(Expression::Identifier(BALANCES.to_string())), index: index.clone() };
+            Some(match delta(var) {
+                Some((amount, true)) => Change::Credit((**index).clone(), amount),
+                Some((amount, false)) => Change::Debit((**index).clone(), amount),
+                None => Change::SetBalance((**index).clone(), value.clone()),
+            })
+        }
+        LValue::Identifier(name) if name == TOTAL_SUPPLY && token_var(name) => {
+            Some(match delta(Expression::Identifier(name.clone())) {
+                Some((amount, true)) => Change::SupplyUp(amount),
+                Some((amount, false)) => Change::SupplyDown(amount),
+                // 新建的 mint 供应量为 0, 只有构造函数能直接设置
+                None if constructor => Change::SetSupply(value.clone()),
+                None => return Err(anyhow!(
+                    "In SPL token mode, '{}' can only be assigned in the constructor; '{}' must change it by minting or burning",
+                    TOTAL_SUPPLY, func.name)),
+            })
+        }
+        LValue::Identifier(name) if name == BALANCES && token_var(name) => {
+            return Err(anyhow!("In SPL token mode, '{}' can only be used as `{}[owner]` (in function '{}')",
+                BALANCES, BALANCES, func.name));
+        }
+        _ => None,
+    })
+}
+
+fn set_decimals(value: &Expression, func: &Function, plan: &mut SplPlan, top_level: bool) -> Result<()> {
+    let constant = match value {
+        Expression::Number(_) | Expression::TypedNumber { .. } => true,
+        Expression::Identifier(param) => func.params.iter().any(|p| p.name == *param),
+        _ => false,
+    };
+    if !plan.constructor || !top_level || !constant || plan.decimals.is_some() {
+        return Err(anyhow!(
+            "In SPL token mode, '{}' is fixed when the mint is created: assign it once, at the top level of \
+             the constructor, from a parameter or a literal",
+            DECIMALS));
+    }
+    plan.decimals = Some(value.clone());
+    Ok(())
+}
+
+impl SplPlan {
+    pub fn has_cpis(&self) -> bool {
+        !self.ops.is_empty()
+    }
+}
+
+impl SolanaCodeGenerator {
+    pub(super) fn is_spl(&self) -> bool {
+        self.token_standard == TokenStandard::Spl
+    }
+
+    /// Whether `name` is a state variable SPL mode keeps in the mint instead of `State`
+    pub(super) fn is_mint_field(&self, name: &str) -> bool {
+        self.is_spl() && (name == TOTAL_SUPPLY || name == DECIMALS)
+    }
+
+    /// Whether `name` is the map SPL mode keeps in token accounts
+    pub(super) fn is_token_map(&self, name: &str) -> bool {
+        self.is_spl() && name == BALANCES
+    }
+
+    /// `mint::decimals` of the mint `initialize` creates
+    pub(super) fn mint_decimals(&self) -> String {
+        let scope = self.scope.borrow();
+        match scope.spl.as_ref().and_then(|plan| plan.decimals.as_ref()) {
+            Some(Expression::Number(n)) => n.to_string(),
+            Some(Expression::TypedNumber { value, .. }) => value.to_string(),
+            Some(Expression::Identifier(param)) => param.clone(),
+            _ => DEFAULT_DECIMALS.to_string(),
+        }
+    }
+
+    /// Whether `stmt` was folded into another statement's CPI or into the mint's constraints
+    pub(super) fn folded_into_cpi(&self, stmt: &Statement) -> bool {
+        self.scope.borrow().spl.as_ref().is_some_and(|plan| plan.merged.contains(stmt))
+    }
+
+    /// The SPL Token CPI that replaces `stmt`, if it is the first of a mint, transfer or burn
+    pub(super) fn token_cpi(&self, stmt: &Statement, level: usize) -> Option<String> {
+        let op = self.scope.borrow().spl.as_ref()?
+            .ops.iter()
+            .find(|(s, _)| s == stmt)?
+            .1.clone();
+        let account = |key: &Expression| self.scope.borrow().entry(BALANCES, std::slice::from_ref(key))
+            .map(|e| e.field.clone())
+            .expect("every balance a function touches has a token account");
+
+        // (函数, 指令结构, 账户, 数量, CPI 后需要重新加载的账户)
+        let (call, instruction, accounts, amount, touched) = match &op {
+            TokenOp::MintTo { to, amount } => {
+                let to = account(to);
+                ("mint_to", "MintTo", vec![("mint", MINT.to_string()), ("to", to.clone())], amount, vec![MINT.to_string(), to])
+            }
+            TokenOp::Transfer { from, to, amount } => {
+                let (from, to) = (account(from), account(to));
+                ("transfer", "Transfer", vec![("from", from.clone()), ("to", to.clone())], amount, vec![from, to])
+            }
+            TokenOp::Burn { from, amount } => {
+                let from = account(from);
+                ("burn", "Burn", vec![("mint", MINT.to_string()), ("from", from.clone())], amount, vec![MINT.to_string(), from])
+            }
+        };
+        // mint 是自己的铸币权限, 由程序用 PDA seeds 签名; 转出和销毁由持有者签名
+        let minting = matches!(op, TokenOp::MintTo { .. });
+        let authority = if minting { MINT } else { SIGNER };
+
+        // 使用完整路径, 避免与合约中的 Mint/Token 等名字以及名为 token 的程序模块冲突
+        let mut lines = vec![format!("anchor_spl::token::{}(", call)];
+        lines.push(format!("    CpiContext::{}(", if minting { "new_with_signer" } else { "new" }));
+        lines.push("        ctx.accounts.token_program.to_account_info(),".to_string());
+        lines.push(format!("        anchor_spl::token::{} {{", instruction));
+        for (field, account) in &accounts {
+            lines.push(format!("            {}: ctx.accounts.{}.to_account_info(),", field, account));
+        }
+        lines.push(format!("            authority: ctx.accounts.{}.to_account_info(),", authority));
+        lines.push("        },".to_string());
+        if minting {
+            lines.push(format!("        &[&[b\"{}\", &[{}]]],", MINT, self.anchor_version().bump(MINT)));
+        }
+        lines.push("    ),".to_string());
+        lines.push(format!("    {},", self.expression_to_rust(amount)));
+        lines.push(")?;".to_string());
+        // CPI 修改的是账户数据, 已反序列化的副本需要重新加载
+        for account in touched {
+            lines.push(format!("ctx.accounts.{}.reload()?;", account));
+        }
+        Some(lines.join(&format!("\n{}", indent(level))))
+    }
+}
@@ -0,0 +1,256 @@
+use super::super::{Contract, ExternalContract, Function, Statement, Expression, Type, Visibility};
+use super::move_gen::{is_entry, state_constructor, uses_strings, uses_tables, uses_vectors, MoveChain, MoveCodeGenerator, ObjectArgs, IS_INITIALIZED};
+use crate::idl::MoveAbortCodes;
+use crate::interface::{Argument, Entrypoint};
+use super::{state_access, visit_expressions, visit_statements, ArithmeticMode};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Generates a Sui Move module. Function bodies are lowered as for Aptos; the state lives
+/// in a shared `State` object that `init` creates and shares when the module is published,
+/// and that every function using it takes as its first argument. The constructor, if
+/// any, runs on that object once: its `is_initialized` flag rejects a second call.
+pub struct SuiCodeGenerator {
+    inner: MoveCodeGenerator,
+}
+
+impl Default for SuiCodeGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SuiCodeGenerator {
+    pub fn new() -> Self {
+        Self { inner: MoveCodeGenerator::new().with_chain(MoveChain::Sui) }
+    }
+
+    /// Selects how integer overflow is handled; Move has no wrapping arithmetic
+    pub fn with_arithmetic(mut self, mode: ArithmeticMode) -> Self {
+        self.inner = self.inner.with_arithmetic(mode);
+        self
+    }
+
+    /// Declares the external contracts the module can call, see [`crate::ExternalContract`]
+    pub fn with_externals(mut self, externals: Vec<ExternalContract>) -> Self {
+        self.inner = self.inner.with_externals(externals);
+        self
+    }
+
+    /// Publishes the module under the named address `name` instead of `cross_chain`
+    pub fn with_named_address(mut self, name: impl Into<String>) -> Self {
+        self.inner = self.inner.with_named_address(name);
+        self
+    }
+
+    /// The abort codes of the module, the same as on Aptos
+    pub fn abort_codes(&self, contract: &Contract) -> Result<MoveAbortCodes> {
+        self.inner.abort_codes(contract)
+    }
+
+    /// The Sui function generated for each DSL function, keyed by function name, as for
+    /// Aptos except that a function using state takes the shared `State` object as its first
+    /// argument, and no signer: the `TxContext` gives the transaction's sender
+    pub fn entrypoints(&self, contract: &Contract) -> Result<BTreeMap<String, Entrypoint>> {
+        let contract = self.inner.prepare(contract)?;
+        let module = contract.name.to_lowercase();
+        let objects = object_args(&contract);
+        let access = state_access(&contract, |_| true);
+        Ok(contract.functions.iter()
+            .map(|func| {
+                let mut args = Vec::new();
+                if objects[&func.name].state {
+                    let writes = access[&func.name].writes || state_constructor(&contract).is_some_and(|c| c.name == func.name);
+                    let ty = if writes { "&mut State" } else { "&State" };
+                    args.push(Argument { name: "state".to_string(), ty: ty.to_string() });
+                }
+                args.extend(func.params.iter().map(|p| Argument { name: p.name.clone(), ty: self.inner.type_to_move(&p.ty) }));
+                (func.name.clone(), Entrypoint {
+                    name: format!("{}::{}::{}", self.inner.address(), module, func.name),
+                    entry: is_entry(func),
+                    args,
+                    returns: func.return_type.as_ref().map(|ty| self.inner.type_to_move(ty)),
+                    signers: Vec::new(),
+                    accounts: Vec::new(),
+                })
+            })
+            .collect())
+    }
+
+    /// A `#[test_only]` module testing the module `generate` emits in a `test_scenario`,
+    /// for the package's `tests/` directory and `sui move test`; see
+    /// [`MoveCodeGenerator::generate_tests`]
+    pub fn generate_tests(&self, contract: &Contract) -> Result<String> {
+        self.inner.generate_tests(contract)
+    }
+
+    /// The `Move.toml` of the package holding the module under `sources/`. Sui packages
+    /// are built with their own address as `0x0`; publishing assigns the real one.
+    pub fn move_toml(&self, contract: &Contract) -> String {
+        let mut code = String::new();
+        code.push_str("[package]\n");
+        code.push_str(&format!("name = \"{}\"\n", contract.name));
+        code.push_str("version = \"0.1.0\"\n\n");
+        code.push_str("[dependencies]\n");
+        code.push_str("Sui = { git = \"https://github.com/MystenLabs/sui.git\", subdir = \"crates/sui-framework/packages/sui-framework\", rev = \"framework/mainnet\" }\n\n");
+        code.push_str("[addresses]\n");
+        code.push_str(&format!("{} = \"0x0\"\n", self.inner.address()));
+        code
+    }
+
+    pub fn generate(&self, contract: &Contract) -> Result<String> {
+        let contract = self.inner.prepare(contract)?;
+        let objects = object_args(&contract);
+        self.inner.set_object_args(objects.clone());
+        let access = state_access(&contract, |_| true);
+        let has_state = !contract.state.is_empty();
+
+        let mut code = String::new();
+        code.push_str(&format!("module {}::{} {{\n", self.inner.address(), contract.name.to_lowercase()));
+
+        // 导入
+        if has_state {
+            code.push_str("    use sui::object::{Self, UID};\n");
+            code.push_str("    use sui::transfer;\n");
+        }
+        code.push_str("    use sui::tx_context::{Self, TxContext};\n");
+        let mut emits = false;
+        for func in &contract.functions {
+            visit_statements(&func.body, &mut |stmt| emits |= matches!(stmt, Statement::Emit { .. }));
+        }
+        if emits {
+            code.push_str("    use sui::event;\n");
+        }
+        if uses_tables(&contract) {
+            code.push_str("    use sui::table::{Self, Table};\n");
+        }
+        if uses_strings(&contract) {
+            code.push_str("    use std::ascii::{Self, String};\n");
+        }
+        if uses_vectors(&contract) {
+            code.push_str("    use std::vector;\n");
+        }
+        code.push_str(&self.inner.external_imports(&contract));
+        code.push('\n');
+        code.push_str(&self.inner.declarations(&contract));
+
+        for func in &contract.functions {
+            // 构造函数要写入 is_initialized
+            let writes = access[&func.name].writes || state_constructor(&contract).is_some_and(|c| c.name == func.name);
+            code.push_str(&self.generate_function(&contract, func, objects[&func.name], writes));
+            code.push('\n');
+        }
+
+        // 发布模块时由 init 用默认值创建并共享 State
+        if has_state {
+            code.push_str("    fun init(ctx: &mut TxContext) {\n");
+            code.push_str(&format!("        transfer::share_object({});\n", self.inner.initial_state(&contract)));
+            code.push_str("    }\n\n");
+            // 测试模块无法调用 init, 由它代为创建 State
+            code.push_str("    #[test_only]\n");
+            code.push_str("    public fun init_for_testing(ctx: &mut TxContext) {\n");
+            code.push_str("        init(ctx);\n");
+            code.push_str("    }\n\n");
+        }
+
+        code.push_str(&self.inner.helpers(&contract));
+        code.push_str("}\n");
+        Ok(code)
+    }
+
+    /// A Sui function: the `State` object comes first, mutable only if the function writes
+    /// to it, and the `TxContext` last
+    fn generate_function(&self, contract: &Contract, func: &Function, objects: ObjectArgs, writes: bool) -> String {
+        let mut code = String::new();
+        match func.visibility {
+            Visibility::Public | Visibility::External => code.push_str("    public "),
+            Visibility::Private => code.push_str("    "),
+            Visibility::Internal => code.push_str("    public(friend) "),
+        }
+        if is_entry(func) {
+            code.push_str("entry ");
+        }
+
+        let mut params = Vec::new();
+        if objects.state {
+            params.push(if writes { "state: &mut State".to_string() } else { "state: &State".to_string() });
+        }
+        for param in &func.params {
+            params.push(format!("{}: {}", param.name, self.inner.type_to_move(&param.ty)));
+        }
+        if objects.ctx {
+            params.push("ctx: &mut TxContext".to_string());
+        }
+        code.push_str(&format!("fun {}({})", func.name, params.join(", ")));
+        if let Some(ret_ty) = &func.return_type {
+            code.push_str(&format!(": {}", self.inner.type_to_move(ret_ty)));
+        }
+        code.push_str(" {\n");
+
+        // 构造函数只能对 init 共享的 State 运行一次
+        let prologue = if state_constructor(contract).is_some_and(|c| c.name == func.name) {
+            vec![
+                format!("assert!(!state.{}, E_ALREADY_INITIALIZED);", IS_INITIALIZED),
+                format!("state.{} = true;", IS_INITIALIZED),
+            ]
+        } else {
+            Vec::new()
+        };
+        code.push_str(&self.inner.function_body(contract, func, &prologue, &[]));
+        code.push_str("    }\n");
+        code
+    }
+}
+
+/// The objects each function takes: the `State` object if it uses state, directly or
+/// through its callees, as the constructor always does, and the `TxContext` if it needs
+/// one, directly or through its callees, for a new object or table, the sender or the clock
+pub(super) fn object_args(contract: &Contract) -> HashMap<String, ObjectArgs> {
+    let access = state_access(contract, |_| true);
+    // 写入嵌套 map 时可能要创建内层的 Table, 这需要 TxContext
+    let nested = state_access(contract, |var| matches!(&var.ty, Type::Map(_, value) if matches!(**value, Type::Map(..))));
+
+    let mut needs_ctx = HashSet::new();
+    let mut callees = HashMap::new();
+    for func in &contract.functions {
+        let mut uses_ctx = nested[&func.name].writes;
+        let mut called = HashSet::new();
+        visit_expressions(&func.body, &mut |expr| match expr {
+            Expression::MsgSender | Expression::BlockTimestamp | Expression::BlockNumber => uses_ctx = true,
+            Expression::Call { func, .. } => {
+                if let Expression::Identifier(name) = func.as_ref() {
+                    called.insert(name.clone());
+                }
+            }
+            _ => {}
+        });
+        if uses_ctx {
+            needs_ctx.insert(func.name.clone());
+        }
+        callees.insert(func.name.clone(), called);
+    }
+
+    // 调用需要 TxContext 的函数时要把它传下去
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (caller, called) in &callees {
+            if !needs_ctx.contains(caller) && called.iter().any(|name| needs_ctx.contains(name)) {
+                needs_ctx.insert(caller.clone());
+                changed = true;
+            }
+        }
+    }
+
+    let constructor = state_constructor(contract).map(|c| c.name.as_str());
+    contract.functions.iter()
+        .map(|func| {
+            let uses_state = access[&func.name].reads || access[&func.name].writes;
+            let args = ObjectArgs {
+                state: uses_state || constructor == Some(func.name.as_str()),
+                ctx: needs_ctx.contains(&func.name),
+            };
+            (func.name.clone(), args)
+        })
+        .collect()
+}
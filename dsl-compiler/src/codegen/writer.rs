@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+
+/// The indentation of code nested `level` blocks deep
+pub(crate) fn indent(level: usize) -> String {
+    "    ".repeat(level)
+}
+
+/// Builds generated code a line at a time, indenting each line to the current level.
+///
+/// Lowered statements may span several lines; as everywhere in the generators, their
+/// first line is left for the writer to indent and any further lines carry their own
+/// indentation.
+pub(crate) struct CodeWriter {
+    code: String,
+    level: usize,
+}
+
+impl CodeWriter {
+    /// A writer whose lines start `level` indents deep
+    pub(crate) fn new(level: usize) -> Self {
+        Self { code: String::new(), level }
+    }
+
+    pub(crate) fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Writes `text` as a line at the current level
+    pub(crate) fn line(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.code.push_str(&indent(self.level));
+            self.code.push_str(text);
+        }
+        self.code.push('\n');
+    }
+
+    /// Appends `text` to the last line written
+    pub(crate) fn append(&mut self, text: &str) {
+        let end = self.code.len() - usize::from(self.code.ends_with('\n'));
+        self.code.insert_str(end, text);
+    }
+
+    /// Writes `header {` and moves the following lines one level deeper
+    pub(crate) fn open(&mut self, header: &str) {
+        if header.is_empty() {
+            self.line("{");
+        } else {
+            self.line(&format!("{} {{", header));
+        }
+        self.indent();
+    }
+
+    /// Moves back out of the block [`Self::open`] started and writes its `}`, followed by
+    /// `trailer` (a `;` or an `else ...`)
+    pub(crate) fn close(&mut self, trailer: &str) {
+        self.dedent();
+        self.line(&format!("}}{}", trailer));
+    }
+
+    pub(crate) fn indent(&mut self) {
+        self.level += 1;
+    }
+
+    pub(crate) fn dedent(&mut self) {
+        self.level = self.level.saturating_sub(1);
+    }
+
+    /// The code written so far
+    pub(crate) fn finish(self) -> String {
+        self.code
+    }
+}
+
+/// Parses generated Rust with `syn`, so output that would not compile is reported here
+/// rather than by `anchor build`. The error names the position and quotes the lines
+/// around it.
+pub fn check_rust_output(code: &str) -> Result<()> {
+    let error = match syn::parse_file(code) {
+        Ok(_) => return Ok(()),
+        Err(error) => error,
+    };
+    let start = error.span().start();
+    let lines: Vec<&str> = code.lines().collect();
+    let mut snippet = String::new();
+    // 行号从 1 开始; 列号从 0 开始
+    let first = start.line.saturating_sub(3).max(1);
+    for number in first..=start.line.min(lines.len()) {
+        snippet.push_str(&format!("{:>5} | {}\n", number, lines[number - 1]));
+    }
+    snippet.push_str(&format!("{:>5} | {}^", "", " ".repeat(start.column)));
+    Err(anyhow!("Generated Rust does not parse: {} at line {}, column {}\n{}",
+        error, start.line, start.column + 1, snippet))
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+use serde::Deserialize;
+use crate::codegen::move_gen::{validate_named_address, AptosFramework};
+use crate::codegen::solana::{validate_program_id, AnchorVersion, CLUSTERS};
+
+/// Name of the project config file, looked up next to the compiled file
+pub const CONFIG_FILE: &str = "ccdsl.toml";
+
+/// Project settings from `ccdsl.toml`. Every key is optional; command-line flags
+/// take precedence over the file.
+///
+/// ```toml
+/// [solana]
+/// program_id = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+/// anchor_version = "0.30.1"
+/// cluster = "devnet"
+///
+/// [aptos]
+/// framework_rev = "aptos-node-v1.8.0"
+/// named_address = "my_token"
+///
+/// [sui]
+/// named_address = "my_token"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(default)]
+    pub solana: SolanaConfig,
+    #[serde(default)]
+    pub aptos: AptosConfig,
+    #[serde(default)]
+    pub sui: SuiConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SolanaConfig {
+    /// Base58 address the program is deployed at, written into `declare_id!`
+    pub program_id: Option<String>,
+    /// Anchor release to generate for, see [`AnchorVersion`]
+    pub anchor_version: Option<String>,
+    /// Cluster `Anchor.toml` deploys to, one of [`CLUSTERS`]; localnet by default
+    pub cluster: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AptosConfig {
+    /// Aptos framework revision to generate for, see [`AptosFramework`]
+    pub framework_rev: Option<String>,
+    /// Named address the module and its `State` are published under; `cross_chain` by default
+    pub named_address: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SuiConfig {
+    /// Named address the module is published under; `cross_chain` by default
+    pub named_address: Option<String>,
+}
+
+impl ProjectConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let config: ProjectConfig = toml::from_str(&source)
+            .with_context(|| format!("In {}", path.display()))?;
+        if let Some(id) = &config.solana.program_id {
+            validate_program_id(id)
+                .with_context(|| format!("Invalid [solana] program_id in {}", path.display()))?;
+        }
+        if let Some(version) = &config.solana.anchor_version {
+            version.parse::<AnchorVersion>()
+                .map_err(anyhow::Error::msg)
+                .with_context(|| format!("Invalid [solana] anchor_version in {}", path.display()))?;
+        }
+        if let Some(cluster) = config.solana.cluster.as_deref().filter(|c| !CLUSTERS.contains(c)) {
+            anyhow::bail!("Invalid [solana] cluster '{}' in {}, expected one of {}", cluster, path.display(), CLUSTERS.join(", "));
+        }
+        if let Some(rev) = &config.aptos.framework_rev {
+            rev.parse::<AptosFramework>()
+                .map_err(anyhow::Error::msg)
+                .with_context(|| format!("Invalid [aptos] framework_rev in {}", path.display()))?;
+        }
+        if let Some(name) = &config.aptos.named_address {
+            validate_named_address(name)
+                .with_context(|| format!("Invalid [aptos] named_address in {}", path.display()))?;
+        }
+        if let Some(name) = &config.sui.named_address {
+            validate_named_address(name)
+                .with_context(|| format!("Invalid [sui] named_address in {}", path.display()))?;
+        }
+        Ok(config)
+    }
+
+    /// Loads the `ccdsl.toml` in `input`'s directory, or the defaults if there is none
+    pub fn for_input(input: &Path) -> Result<Self> {
+        let path = match input.parent() {
+            Some(dir) => dir.join(CONFIG_FILE),
+            None => Path::new(CONFIG_FILE).to_path_buf(),
+        };
+        if path.exists() {
+            Self::load(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use crate::{Contract, Function, Statement, Expression, LValue, BinaryOp, Type};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Iterations assumed for loops whose trip count isn't statically known
+const UNKNOWN_LOOP_ITERATIONS: u64 = 10;
+
+/// Rough per-operation costs. Only the relative weights matter: the numbers
+/// are meant to rank functions against each other, not to predict fees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostModel {
+    /// Fixed overhead of any instruction / entry function
+    pub base: Cost,
+    /// Checked arithmetic
+    pub arithmetic: Cost,
+    /// Comparisons, logical and bitwise operators
+    pub comparison: Cost,
+    /// Reading a plain state field
+    pub state_read: Cost,
+    /// Writing a plain state field
+    pub state_write: Cost,
+    /// Reading a map entry (a PDA derivation on Solana, a table lookup on Move)
+    pub map_read: Cost,
+    /// Writing a map entry
+    pub map_write: Cost,
+    /// Calling another function of the same contract
+    pub internal_call: Cost,
+    /// Calling into another program (CPI on Solana)
+    pub external_call: Cost,
+    pub emit: Cost,
+}
+
+/// Cost of one operation on each backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cost {
+    pub compute_units: u64,
+    pub move_gas: u64,
+}
+
+impl Cost {
+    pub const fn new(compute_units: u64, move_gas: u64) -> Self {
+        Self { compute_units, move_gas }
+    }
+
+    fn add(&mut self, other: Cost) {
+        self.compute_units = self.compute_units.saturating_add(other.compute_units);
+        self.move_gas = self.move_gas.saturating_add(other.move_gas);
+    }
+
+    fn times(self, n: u64) -> Cost {
+        Cost::new(self.compute_units.saturating_mul(n), self.move_gas.saturating_mul(n))
+    }
+
+    fn max(self, other: Cost) -> Cost {
+        Cost::new(self.compute_units.max(other.compute_units), self.move_gas.max(other.move_gas))
+    }
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            base: Cost::new(1_000, 10),
+            arithmetic: Cost::new(4, 2),
+            comparison: Cost::new(2, 1),
+            state_read: Cost::new(10, 20),
+            state_write: Cost::new(20, 40),
+            map_read: Cost::new(1_500, 50),
+            map_write: Cost::new(1_600, 60),
+            internal_call: Cost::new(50, 10),
+            external_call: Cost::new(1_000, 100),
+            emit: Cost::new(100, 50),
+        }
+    }
+}
+
+/// Estimated cost of a single function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCost {
+    pub name: String,
+    pub compute_units: u64,
+    pub move_gas: u64,
+    /// False when a loop bound had to be guessed
+    pub statically_bounded: bool,
+}
+
+/// Per-function cost estimates for a contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostReport {
+    pub functions: Vec<FunctionCost>,
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.functions.iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Function".len());
+
+        writeln!(f, "📊 Estimated costs (relative, not exact):")?;
+        writeln!(f, "   {:<width$}  {:>14}  {:>10}", "Function", "Compute units", "Move gas", width = width)?;
+        for cost in &self.functions {
+            // `~` marks estimates that assumed a loop bound
+            let marker = if cost.statically_bounded { " " } else { "~" };
+            writeln!(f, "   {:<width$}  {:>13}{}  {:>10}",
+                cost.name, cost.compute_units, marker, cost.move_gas, width = width)?;
+        }
+        if self.functions.iter().any(|c| !c.statically_bounded) {
+            write!(f, "   ~ assumes {} iterations for loops without a constant bound", UNKNOWN_LOOP_ITERATIONS)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct CostEstimator {
+    model: CostModel,
+    // State variable name -> whether it is a map (stored behind PDAs / tables)
+    state: HashMap<String, bool>,
+    // Parameters and locals of the function being estimated, which shadow state
+    locals: HashSet<String>,
+    statically_bounded: bool,
+}
+
+impl CostEstimator {
+    pub fn new() -> Self {
+        Self::with_model(CostModel::default())
+    }
+
+    pub fn with_model(model: CostModel) -> Self {
+        Self {
+            model,
+            state: HashMap::new(),
+            locals: HashSet::new(),
+            statically_bounded: true,
+        }
+    }
+
+    pub fn estimate(&mut self, contract: &Contract) -> CostReport {
+        self.state = contract.state.iter()
+            .map(|var| (var.name.clone(), matches!(var.ty, Type::Map(_, _))))
+            .collect();
+
+        CostReport {
+            functions: contract.functions.iter()
+                .map(|func| self.estimate_function(func))
+                .collect(),
+        }
+    }
+
+    fn estimate_function(&mut self, func: &Function) -> FunctionCost {
+        self.locals = func.params.iter().map(|p| p.name.clone()).collect();
+        collect_locals(&func.body, &mut self.locals);
+        self.statically_bounded = true;
+
+        let mut cost = self.model.base;
+        cost.add(self.block_cost(&func.body));
+
+        FunctionCost {
+            name: func.name.clone(),
+            compute_units: cost.compute_units,
+            move_gas: cost.move_gas,
+            statically_bounded: self.statically_bounded,
+        }
+    }
+
+    fn block_cost(&mut self, statements: &[Statement]) -> Cost {
+        let mut cost = Cost::default();
+        for stmt in statements {
+            cost.add(self.statement_cost(stmt));
+        }
+        cost
+    }
+
+    fn statement_cost(&mut self, stmt: &Statement) -> Cost {
+        let mut cost = Cost::default();
+
+        match stmt {
+            Statement::Let { value, .. } | Statement::LetTuple { value, .. } => cost.add(self.expression_cost(value)),
+            Statement::Assign { target, value } => {
+                cost.add(self.expression_cost(value));
+                cost.add(self.lvalue_cost(target));
+            }
+            // Worst case: the more expensive branch is taken
+            Statement::If { condition, then_block, else_block } => {
+                cost.add(self.expression_cost(condition));
+                let then_cost = self.block_cost(then_block);
+                let else_cost = else_block.as_ref()
+                    .map(|b| self.block_cost(b))
+                    .unwrap_or_default();
+                cost.add(then_cost.max(else_cost));
+            }
+            Statement::Match { value, arms } => {
+                cost.add(self.expression_cost(value));
+                let mut worst = Cost::default();
+                for arm in arms {
+                    worst = worst.max(self.block_cost(&arm.body));
+                }
+                cost.add(worst);
+            }
+            Statement::While { condition, body } => {
+                self.statically_bounded = false;
+                let mut iteration = self.expression_cost(condition);
+                iteration.add(self.block_cost(body));
+                cost.add(iteration.times(UNKNOWN_LOOP_ITERATIONS));
+            }
+            Statement::For { init, condition, update, body } => {
+                let iterations = trip_count(init, condition, update).unwrap_or_else(|| {
+                    self.statically_bounded = false;
+                    UNKNOWN_LOOP_ITERATIONS
+                });
+                cost.add(self.statement_cost(init));
+                let mut iteration = self.expression_cost(condition);
+                iteration.add(self.statement_cost(update));
+                iteration.add(self.block_cost(body));
+                cost.add(iteration.times(iterations));
+            }
+            Statement::ForEach { iterable, body, .. } => {
+                self.statically_bounded = false;
+                cost.add(self.expression_cost(iterable));
+                cost.add(self.block_cost(body).times(UNKNOWN_LOOP_ITERATIONS));
+            }
+            Statement::Require { condition, .. } | Statement::Assert { condition, .. } => {
+                cost.add(self.expression_cost(condition));
+                cost.add(self.model.comparison);
+            }
+            Statement::Emit { args, .. } => {
+                for arg in args {
+                    cost.add(self.expression_cost(arg));
+                }
+                cost.add(self.model.emit);
+            }
+            Statement::Return { value: Some(value) } | Statement::Expression(value) => {
+                cost.add(self.expression_cost(value));
+            }
+            Statement::Block(body) => cost.add(self.block_cost(body)),
+            Statement::Return { value: None } | Statement::Break | Statement::Continue | Statement::Placeholder => {}
+        }
+
+        cost
+    }
+
+    fn lvalue_cost(&mut self, lvalue: &LValue) -> Cost {
+        match lvalue {
+            LValue::Identifier(name) if self.is_state(name) => self.model.state_write,
+            LValue::Identifier(_) => Cost::default(),
+            LValue::Index { array, index } => {
+                let mut cost = self.expression_cost(index);
+                if self.is_state(lvalue_root(array)) {
+                    cost.add(self.model.map_write);
+                } else {
+                    cost.add(self.lvalue_cost(array));
+                }
+                cost
+            }
+            LValue::Field { object, .. } => self.lvalue_cost(object),
+        }
+    }
+
+    fn expression_cost(&mut self, expr: &Expression) -> Cost {
+        let mut cost = Cost::default();
+
+        match expr {
+            Expression::Identifier(name) => {
+                if let Some(is_map) = self.state.get(name) {
+                    if !self.locals.contains(name) {
+                        // Reading a whole map is rare; charge it like a lookup
+                        cost.add(if *is_map { self.model.map_read } else { self.model.state_read });
+                    }
+                }
+            }
+            Expression::Binary { op, left, right } => {
+                cost.add(self.expression_cost(left));
+                cost.add(self.expression_cost(right));
+                cost.add(match op {
+                    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul |
+                    BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => self.model.arithmetic,
+                    _ => self.model.comparison,
+                });
+            }
+            Expression::Unary { expr, .. } => {
+                cost.add(self.expression_cost(expr));
+                cost.add(self.model.comparison);
+            }
+            // 缩窄转换需要一次范围检查
+            Expression::Cast { expr, .. } => {
+                cost.add(self.expression_cost(expr));
+                cost.add(self.model.comparison);
+            }
+            Expression::OptionSome(value) => cost.add(self.expression_cost(value)),
+            Expression::Ternary { condition, then_expr, else_expr } => {
+                cost.add(self.expression_cost(condition));
+                let then_cost = self.expression_cost(then_expr);
+                let else_cost = self.expression_cost(else_expr);
+                cost.add(then_cost.max(else_cost));
+            }
+            Expression::Call { func, args } => {
+                for arg in args {
+                    cost.add(self.expression_cost(arg));
+                }
+                if let Expression::Identifier(_) = func.as_ref() {
+                    cost.add(self.model.internal_call);
+                } else {
+                    cost.add(self.model.external_call);
+                }
+            }
+            // `balances.get(k)` reads an entry like `balances[k]`; option methods only test the tag
+            Expression::MethodCall { object, method, args } if expr.is_value_method() => {
+                for arg in args {
+                    cost.add(self.expression_cost(arg));
+                }
+                match expression_root(object) {
+                    Some(root) if method == "get" && self.is_state(root) => cost.add(self.model.map_read),
+                    _ => {
+                        cost.add(self.expression_cost(object));
+                        cost.add(self.model.comparison);
+                    }
+                }
+            }
+            // Calls on another object go through another program
+            Expression::MethodCall { object, args, .. } => {
+                cost.add(self.expression_cost(object));
+                for arg in args {
+                    cost.add(self.expression_cost(arg));
+                }
+                cost.add(self.model.external_call);
+            }
+            Expression::Index { array, index } => {
+                cost.add(self.expression_cost(index));
+                match expression_root(array) {
+                    // Nested map lookups (`allowances[a][b]`) resolve to a single entry
+                    Some(root) if self.is_state(root) => cost.add(self.model.map_read),
+                    _ => cost.add(self.expression_cost(array)),
+                }
+            }
+            Expression::Field { object, .. } => cost.add(self.expression_cost(object)),
+            Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => {
+                for item in items {
+                    cost.add(self.expression_cost(item));
+                }
+            }
+            Expression::StructLiteral { fields, .. } => {
+                for value in fields.values() {
+                    cost.add(self.expression_cost(value));
+                }
+            }
+            _ => {}
+        }
+
+        cost
+    }
+
+    fn is_state(&self, name: &str) -> bool {
+        self.state.contains_key(name) && !self.locals.contains(name)
+    }
+}
+
+impl Default for CostEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iteration count of a `for i in a..b` style loop with literal bounds
+fn trip_count(init: &Statement, condition: &Expression, update: &Statement) -> Option<u64> {
+    let (variable, start) = match init {
+        Statement::Let { name, value: Expression::Number(start), .. } => (name, *start),
+        _ => return None,
+    };
+
+    let (inclusive, end) = match condition {
+        Expression::Binary { op, left, right } if **left == Expression::Identifier(variable.clone()) => {
+            match (op, right.as_ref()) {
+                (BinaryOp::Lt, Expression::Number(end)) => (false, *end),
+                (BinaryOp::Le, Expression::Number(end)) => (true, *end),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let step = match update {
+        Statement::Assign {
+            target: LValue::Identifier(target),
+            value: Expression::Binary { op: BinaryOp::Add, right, .. },
+        } if target == variable => match right.as_ref() {
+            Expression::Number(step) if *step > 0 => *step,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let span = if inclusive { end.checked_sub(start)? + 1 } else { end.saturating_sub(start) };
+    Some(span.div_ceil(step))
+}
+
+fn lvalue_root(lvalue: &LValue) -> &str {
+    match lvalue {
+        LValue::Identifier(name) => name,
+        LValue::Index { array, .. } => lvalue_root(array),
+        LValue::Field { object, .. } => lvalue_root(object),
+    }
+}
+
+fn expression_root(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Identifier(name) => Some(name),
+        Expression::Index { array, .. } => expression_root(array),
+        Expression::Field { object, .. } => expression_root(object),
+        _ => None,
+    }
+}
+
+fn collect_locals(statements: &[Statement], locals: &mut HashSet<String>) {
+    for stmt in statements {
+        match stmt {
+            Statement::Let { name, .. } => {
+                locals.insert(name.clone());
+            }
+            Statement::LetTuple { names, .. } => locals.extend(names.iter().cloned()),
+            Statement::If { then_block, else_block, .. } => {
+                collect_locals(then_block, locals);
+                if let Some(else_stmts) = else_block {
+                    collect_locals(else_stmts, locals);
+                }
+            }
+            Statement::While { body, .. } | Statement::Block(body) => collect_locals(body, locals),
+            Statement::ForEach { variable, body, .. } => {
+                locals.insert(variable.clone());
+                collect_locals(body, locals);
+            }
+            Statement::For { init, body, .. } => {
+                collect_locals(std::slice::from_ref(init.as_ref()), locals);
+                collect_locals(body, locals);
+            }
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    collect_locals(&arm.body, locals);
+                }
+            }
+            _ => {}
+        }
+    }
+}
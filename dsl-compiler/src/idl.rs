@@ -0,0 +1,173 @@
+use serde::{Serialize, Deserialize};
+
+/// An Anchor IDL (the pre-0.30 format `anchor build` writes to `target/idl`)
+/// for the program the Solana backend generates.
+///
+/// Names follow Anchor's conventions: instructions, arguments, accounts and
+/// fields in camelCase, types and errors as declared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnchorIdl {
+    pub version: String,
+    pub name: String,
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<IdlTypeDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<IdlTypeDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<IdlEvent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<IdlErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<IdlMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub accounts: Vec<IdlAccount>,
+    pub args: Vec<IdlField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<IdlType>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlAccount {
+    pub name: String,
+    pub is_mut: bool,
+    pub is_signer: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+/// A named struct, used for both account layouts and user-defined types
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlTypeDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefinitionTy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefinitionTy {
+    Struct { fields: Vec<IdlField> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    pub fields: Vec<IdlEventField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlEventField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+    pub index: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlErrorCode {
+    pub code: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlMetadata {
+    pub address: String,
+}
+
+/// A type in Anchor's IDL notation: primitives as strings (`"publicKey"`),
+/// compound types as objects (`{ "vec": "u8" }`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    U256,
+    Bytes,
+    String,
+    PublicKey,
+    Defined(String),
+    Option(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+}
+
+/// The same information for the Aptos Move module, in a schema of our own: Move
+/// has no IDL standard. Types are written in Move syntax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveIdl {
+    /// Named address the module is published under
+    pub address: String,
+    pub name: String,
+    pub functions: Vec<MoveIdlFunction>,
+    /// Resources the module declares
+    pub structs: Vec<MoveIdlStruct>,
+    pub events: Vec<MoveIdlStruct>,
+    /// Abort codes
+    pub errors: Vec<MoveIdlError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveIdlFunction {
+    pub name: String,
+    /// `entry` functions can be called by transactions
+    pub entry: bool,
+    /// The function takes the sender as a `&signer` first
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub signer: bool,
+    /// `#[view]` functions can be called off-chain without a transaction
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub view: bool,
+    pub params: Vec<MoveIdlField>,
+    pub returns: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveIdlStruct {
+    pub name: String,
+    pub abilities: Vec<String>,
+    pub fields: Vec<MoveIdlField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveIdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveIdlError {
+    pub code: u64,
+    pub name: String,
+    pub msg: String,
+}
+
+/// The abort codes of a Move module, written next to it as `<contract>.errors.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveAbortCodes {
+    /// `<address>::<module>`
+    pub module: String,
+    pub errors: Vec<MoveIdlError>,
+}
@@ -0,0 +1,163 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, Context};
+use crate::{Program, Contract, ExternalContract, Import};
+
+/// Loads a DSL file and resolves its `import { ... } from "..."` statements.
+///
+/// Importing a contract's name makes that contract callable as `Name.method(...)`;
+/// importing a function, struct or constant copies it into every contract of the
+/// importing file. Paths are relative to the importing file.
+pub fn load_program(path: &Path) -> Result<Program> {
+    ImportResolver::default().load(path)
+}
+
+/// Loads a program from JSON (as printed by `ccdsl ast`) and resolves its imports,
+/// which may point at DSL files, relative to the JSON file
+pub fn load_json_program(path: &Path) -> Result<Program> {
+    let canonical = path.canonicalize()
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    let json = fs::read_to_string(&canonical)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    let mut program = Program::from_json(&json)
+        .with_context(|| format!("In {}", path.display()))?;
+
+    let mut resolver = ImportResolver::default();
+    resolver.stack.push(canonical.clone());
+    resolver.resolve(&mut program, &canonical, path)?;
+    Ok(program)
+}
+
+#[derive(Default)]
+struct ImportResolver {
+    // Files currently being loaded, outermost first, for cycle detection
+    stack: Vec<PathBuf>,
+}
+
+impl ImportResolver {
+    fn load(&mut self, path: &Path) -> Result<Program> {
+        let canonical = path.canonicalize()
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+
+        if let Some(start) = self.stack.iter().position(|p| *p == canonical) {
+            let cycle: Vec<String> = self.stack[start..].iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(anyhow!("Circular import: {}", cycle.join(" -> ")));
+        }
+
+        let source = fs::read_to_string(&canonical)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let mut program = Program::parse(&source)
+            .with_context(|| format!("In {}", path.display()))?;
+
+        self.stack.push(canonical.clone());
+        self.resolve(&mut program, &canonical, path)?;
+        self.stack.pop();
+
+        Ok(program)
+    }
+
+    fn resolve(&mut self, program: &mut Program, canonical: &Path, path: &Path) -> Result<()> {
+        let dir = canonical.parent().unwrap_or(Path::new("."));
+        for import in program.imports.clone() {
+            let imported = self.load(&dir.join(&import.path))?;
+            resolve_import(program, &import, imported)
+                .with_context(|| format!("In {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+fn resolve_import(program: &mut Program, import: &Import, imported: Program) -> Result<()> {
+    for item in &import.items {
+        if let Some(contract) = imported.contracts.iter()
+            .chain(&imported.dependencies)
+            .find(|c| c.name == *item)
+        {
+            add_dependency(program, contract.clone())?;
+            continue;
+        }
+        if let Some(external) = imported.externals.iter().find(|e| e.name == *item) {
+            add_external(program, external.clone())?;
+            continue;
+        }
+
+        let mut found = false;
+        for source in &imported.contracts {
+            if let Some(func) = source.functions.iter().find(|f| f.name == *item) {
+                for contract in &mut program.contracts {
+                    ensure_undefined(contract, item, &import.path)?;
+                    contract.functions.push(func.clone());
+                }
+            } else if let Some(def) = source.structs.iter().find(|s| s.name == *item) {
+                for contract in &mut program.contracts {
+                    ensure_undefined(contract, item, &import.path)?;
+                    contract.structs.push(def.clone());
+                }
+            } else if let Some(constant) = source.constants.iter().find(|c| c.name == *item) {
+                for contract in &mut program.contracts {
+                    ensure_undefined(contract, item, &import.path)?;
+                    contract.constants.push(constant.clone());
+                }
+            } else {
+                continue;
+            }
+            found = true;
+            break;
+        }
+
+        if !found {
+            return Err(anyhow!("'{}' is not defined in \"{}\"", item, import.path));
+        }
+    }
+
+    // Imported functions may call into contracts their own file imported or declared external
+    for dependency in imported.dependencies {
+        if !program.dependencies.iter().any(|c| c.name == dependency.name) {
+            add_dependency(program, dependency)?;
+        }
+    }
+    for external in imported.externals {
+        if !program.externals.iter().any(|e| e.name == external.name) {
+            add_external(program, external)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn add_dependency(program: &mut Program, contract: Contract) -> Result<()> {
+    if program.contracts.iter().any(|c| c.name == contract.name) {
+        return Err(anyhow!("Imported contract '{}' conflicts with a contract of the same name", contract.name));
+    }
+    if !program.dependencies.iter().any(|c| c.name == contract.name) {
+        program.dependencies.push(contract);
+    }
+    Ok(())
+}
+
+fn add_external(program: &mut Program, external: ExternalContract) -> Result<()> {
+    if program.contracts.iter().chain(&program.dependencies).any(|c| c.name == external.name) {
+        return Err(anyhow!("Imported external contract '{}' conflicts with a contract of the same name", external.name));
+    }
+    if !program.externals.iter().any(|e| e.name == external.name) {
+        program.externals.push(external);
+    }
+    Ok(())
+}
+
+fn ensure_undefined(contract: &Contract, name: &str, path: &str) -> Result<()> {
+    let defined = contract.functions.iter().any(|f| f.name == name)
+        || contract.structs.iter().any(|s| s.name == name)
+        || contract.constants.iter().any(|c| c.name == name);
+
+    if defined {
+        return Err(anyhow!(
+            "'{}' imported from \"{}\" conflicts with an existing definition in '{}'",
+            name, path, contract.name
+        ));
+    }
+    Ok(())
+}
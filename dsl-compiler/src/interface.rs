@@ -0,0 +1,102 @@
+use crate::{Contract, Visibility};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+/// Chain-independent description of how to call a compiled contract, listing for
+/// every public function the entrypoint each backend generated for it.
+///
+/// A relayer or SDK can use it to dispatch one logical call to any chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interface {
+    pub contract: String,
+    pub functions: Vec<FunctionInterface>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionInterface {
+    pub name: String,
+    /// Parameters with their DSL types
+    pub params: Vec<Argument>,
+    pub returns: Option<String>,
+    /// Entrypoint per chain, keyed by chain name
+    pub chains: BTreeMap<String, Entrypoint>,
+}
+
+/// How one function is called on one chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entrypoint {
+    /// The instruction name on Solana, `address::module::function` on Move chains
+    pub name: String,
+    /// Whether a transaction can call it directly
+    pub entry: bool,
+    /// Arguments the caller passes, in order, with the chain's types. Signers and
+    /// accounts are listed separately.
+    pub args: Vec<Argument>,
+    pub returns: Option<String>,
+    /// Who must sign the transaction
+    pub signers: Vec<String>,
+    /// Accounts to pass, in order (Solana only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<AccountMeta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Argument {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// An account a Solana instruction takes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountMeta {
+    pub name: String,
+    pub writable: bool,
+    pub signer: bool,
+    /// Seeds the address is derived from, for PDAs of this program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seeds: Option<Vec<Seed>>,
+}
+
+/// One seed of a PDA, in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum Seed {
+    /// The UTF-8 bytes of a fixed string
+    Literal(String),
+    /// The signer's public key
+    Signer,
+    /// An instruction argument, by name
+    Arg(String),
+    /// A field of the state account, by name
+    State(String),
+    /// A constant integer in little-endian bytes
+    Constant(String),
+}
+
+impl Interface {
+    /// The public and external functions of `contract`, with no entrypoints yet
+    pub fn new(contract: &Contract) -> Self {
+        let functions = contract.functions.iter()
+            .filter(|f| matches!(f.visibility, Visibility::Public | Visibility::External))
+            .map(|f| FunctionInterface {
+                name: f.name.clone(),
+                params: f.params.iter()
+                    .map(|p| Argument { name: p.name.clone(), ty: p.ty.to_string() })
+                    .collect(),
+                returns: f.return_type.as_ref().map(|ty| ty.to_string()),
+                chains: BTreeMap::new(),
+            })
+            .collect();
+        Self { contract: contract.name.clone(), functions }
+    }
+
+    /// Records the entrypoints one backend generated, keyed by function name
+    pub fn add_chain(&mut self, chain: &str, mut entrypoints: BTreeMap<String, Entrypoint>) {
+        for func in &mut self.functions {
+            if let Some(entrypoint) = entrypoints.remove(&func.name) {
+                func.chains.insert(chain.to_string(), entrypoint);
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use pest_derive::Parser;
 use anyhow::{Result, anyhow};
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Parser)]
 #[grammar = "../grammar.pest"]
@@ -13,31 +14,50 @@ pub mod parser;
 pub mod semantic;
 pub mod semantic_analyzer;
 pub mod optimizer;
+pub mod estimator;
+pub mod imports;
+pub mod modifiers;
+pub mod lint;
+pub mod config;
+pub mod interface;
+pub mod idl;
+pub mod printer;
+#[cfg(feature = "verify")]
+pub mod verify;
 
 pub use semantic_analyzer::{SemanticAnalyzer, SymbolTable, TypeInference};
+pub use parser::ParseDiagnostic;
+
+/// Length assumed for `string`/`bytes` declared without a `<N>` bound
+pub const DEFAULT_MAX_STRING_LEN: usize = 64;
 
 // Enhanced AST definitions with more comprehensive node types
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Program {
     pub contracts: Vec<Contract>,
     pub imports: Vec<Import>,
     pub type_definitions: Vec<TypeDefinition>,
+    /// Contracts imported by name; callable from `contracts` but not compiled with them
+    pub dependencies: Vec<Contract>,
+    /// Programs deployed elsewhere, callable as `Name.method(...)`
+    #[serde(default)]
+    pub externals: Vec<ExternalContract>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Import {
     pub path: String,
     pub items: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TypeDefinition {
     pub name: String,
     pub ty: Type,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Contract {
     pub name: String,
     pub state: Vec<StateVariable>,
@@ -46,49 +66,91 @@ pub struct Contract {
     pub events: Vec<EventDefinition>,
     pub modifiers: Vec<Modifier>,
     pub constants: Vec<Constant>,
+    #[serde(default)]
+    pub bridge: Option<Bridge>,
+}
+
+/// `bridge { vault; authority: address; }`: the lock/receive pattern of a token bridge.
+///
+/// `bridge_lock(amount, target_chain)` counts tokens leaving for another chain in
+/// the vault and `bridge_receive(to, amount, source_chain)` releases them again;
+/// functions that receive can only be called by the authority stored in the vault.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bridge {
+    /// Name of the vault field holding the authority's address
+    pub authority: String,
+}
+
+impl Bridge {
+    pub const LOCK: &'static str = "bridge_lock";
+    pub const RECEIVE: &'static str = "bridge_receive";
+}
+
+/// `external contract Oracle at <id> { fn get_price(pair: string) -> u64; }`:
+/// a deployed program, declared by the functions this program calls on it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalContract {
+    pub name: String,
+    /// Program id on Solana; account address (`0x...`) on Move chains
+    pub address: String,
+    pub functions: Vec<ExternalFunction>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalFunction {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Option<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StructDefinition {
     pub name: String,
     pub fields: Vec<StructField>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StructField {
     pub name: String,
     pub ty: Type,
     pub is_public: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EventDefinition {
     pub name: String,
     pub params: Vec<EventParam>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EventParam {
     pub name: String,
     pub ty: Type,
     pub indexed: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Modifier {
     pub name: String,
     pub params: Vec<Parameter>,
     pub body: Vec<Statement>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A modifier applied to a function: `onlyOwner`, or `below(limit)` for one with parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModifierCall {
+    pub name: String,
+    pub args: Vec<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Constant {
     pub name: String,
     pub ty: Type,
     pub value: Expression,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StateVariable {
     pub name: String,
     pub ty: Type,
@@ -97,19 +159,26 @@ pub struct StateVariable {
     pub initial_value: Option<Expression>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Function {
     pub visibility: Visibility,
     pub name: String,
     pub params: Vec<Parameter>,
     pub return_type: Option<Type>,
-    pub modifiers: Vec<String>,
+    pub modifiers: Vec<ModifierCall>,
     pub body: Vec<Statement>,
     pub is_payable: bool,
     pub is_view: bool,
+    /// Marked `#[constructor]`: the function that creates the contract's state;
+    /// see [`Contract::constructor`]
+    #[serde(default)]
+    pub is_constructor: bool,
+    /// 1-based line of the definition in its source file, 0 when unknown
+    #[serde(default)]
+    pub line: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Visibility {
     Public,
     Private,
@@ -117,14 +186,14 @@ pub enum Visibility {
     External,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Parameter {
     pub name: String,
     pub ty: Type,
     pub is_mutable: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Type {
     U8,
     U16,
@@ -139,8 +208,10 @@ pub enum Type {
     I128,
     Bool,
     Address,
-    String,
-    Bytes,
+    /// Optional maximum length in UTF-8 bytes, from `string<32>`
+    String(Option<usize>),
+    /// Optional maximum length, from `bytes<32>`
+    Bytes(Option<usize>),
     Map(Box<Type>, Box<Type>),
     Vec(Box<Type>),
     Array(Box<Type>, usize),
@@ -150,7 +221,42 @@ pub enum Type {
     Result(Box<Type>, Box<Type>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Prints a type as it is written in DSL source, e.g. `map<address, u64>`
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::U128 => write!(f, "u128"),
+            Type::U256 => write!(f, "u256"),
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::I128 => write!(f, "i128"),
+            Type::Bool => write!(f, "bool"),
+            Type::Address => write!(f, "address"),
+            Type::String(None) => write!(f, "string"),
+            Type::String(Some(len)) => write!(f, "string<{}>", len),
+            Type::Bytes(None) => write!(f, "bytes"),
+            Type::Bytes(Some(len)) => write!(f, "bytes<{}>", len),
+            Type::Map(k, v) => write!(f, "map<{}, {}>", k, v),
+            Type::Vec(t) => write!(f, "vec<{}>", t),
+            Type::Array(t, size) => write!(f, "[{}; {}]", t, size),
+            Type::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+                write!(f, "({})", types.join(", "))
+            }
+            Type::Struct(name) => write!(f, "{}", name),
+            Type::Option(t) => write!(f, "option<{}>", t),
+            Type::Result(ok, err) => write!(f, "result<{}, {}>", ok, err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Statement {
     Let { 
         name: String, 
@@ -158,6 +264,11 @@ pub enum Statement {
         value: Expression,
         is_mutable: bool,
     },
+    /// `let (a, b) = value;`, binding each element of a tuple
+    LetTuple {
+        names: Vec<String>,
+        value: Expression,
+    },
     Assign { 
         target: LValue, 
         value: Expression 
@@ -197,13 +308,36 @@ pub enum Statement {
     Return { 
         value: Option<Expression> 
     },
+    Match {
+        value: Expression,
+        arms: Vec<MatchArm>,
+    },
     Break,
     Continue,
+    /// `_;` in a modifier body, replaced by the modified function's body
+    Placeholder,
     Expression(Expression),
     Block(Vec<Statement>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    Literal(Expression),
+    Range {
+        start: u64,
+        end: u64,
+        inclusive: bool,
+    },
+    Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LValue {
     Identifier(String),
     Index { 
@@ -216,9 +350,14 @@ pub enum LValue {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
     Number(u64),
+    /// Integer literal with a type suffix, such as `100u8` or `1_000_000u128`
+    TypedNumber {
+        value: u128,
+        ty: Type,
+    },
     Float(f64),
     Bool(bool),
     String(String),
@@ -255,8 +394,17 @@ pub enum Expression {
         object: Box<Expression>, 
         field: String 
     },
+    /// `expr as ty`, converting between integer types
+    Cast {
+        expr: Box<Expression>,
+        ty: Type,
+    },
     ArrayLiteral(Vec<Expression>),
     TupleLiteral(Vec<Expression>),
+    /// `Some(value)`: an option holding `value`
+    OptionSome(Box<Expression>),
+    /// `None`: an empty option, of the type the context expects
+    OptionNone,
     StructLiteral {
         name: String,
         fields: HashMap<String, Expression>,
@@ -266,12 +414,32 @@ pub enum Expression {
         body: Box<Expression>,
     },
     MsgSender,
+    /// `msg_value()`: the amount sent with a call to a `payable` function. Solana
+    /// instructions carry no value, so there it is a `msg_value: u64` argument the
+    /// function transfers from the signer to the state account, never a balance
     MsgValue,
     BlockNumber,
     BlockTimestamp,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Expression {
+    /// Methods of values rather than of other contracts: options have `is_some()`,
+    /// `is_none()` and `unwrap_or(default)`, and maps `get(key)`, which reads an entry
+    /// as an option
+    pub const VALUE_METHODS: &'static [&'static str] = &["is_some", "is_none", "unwrap_or", "get"];
+
+    /// Whether this calls one of [`Expression::VALUE_METHODS`] rather than another
+    /// contract. Contracts are named like types, so a capitalized receiver is a contract.
+    pub fn is_value_method(&self) -> bool {
+        match self {
+            Expression::MethodCall { object, method, .. } => Self::VALUE_METHODS.contains(&method.as_str())
+                && !matches!(object.as_ref(), Expression::Identifier(name) if name.starts_with(|c: char| c.is_ascii_uppercase())),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOp {
     // Arithmetic
     Add, Sub, Mul, Div, Mod, Pow,
@@ -283,17 +451,61 @@ pub enum BinaryOp {
     BitAnd, BitOr, BitXor, Shl, Shr,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOp {
     Not, Neg, BitNot,
 }
 
+impl Program {
+    /// Parses every contract in a file; imports are recorded but not resolved
+    pub fn parse(input: &str) -> Result<Self> {
+        let pairs = DslParser::parse(Rule::program, input)
+            .map_err(|e| anyhow!("Parse error: {}", parser::syntax_error(e, input)))?;
+        
+        parser::parse_program_from_pairs(pairs)
+    }
+    
+    /// Parses a file and resolves its imports relative to its directory
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        imports::load_program(path)
+    }
+    
+    /// Reads a program from its JSON form, as printed by `ccdsl ast`
+    pub fn from_json(input: &str) -> Result<Self> {
+        serde_json::from_str(input).map_err(|e| anyhow!("Invalid program JSON: {}", e))
+    }
+    
+    /// Reads a JSON program from a file and resolves its imports relative to its directory
+    pub fn load_json(path: &std::path::Path) -> Result<Self> {
+        imports::load_json_program(path)
+    }
+}
+
 impl Contract {
+    /// Name of the constructor when no function is marked `#[constructor]`
+    pub const DEFAULT_CONSTRUCTOR: &'static str = "initialize";
+    
+    /// Parses a file holding exactly one contract
     pub fn parse(input: &str) -> Result<Self> {
         let pairs = DslParser::parse(Rule::program, input)
-            .map_err(|e| anyhow!("Parse error: {}", e))?;
+            .map_err(|e| anyhow!("Parse error: {}", parser::syntax_error(e, input)))?;
         
         // Call the actual parser implementation
         parser::parse_contract_from_pairs(pairs)
     }
+    
+    /// Parses like [`Contract::parse`] but recovers from syntax errors, returning
+    /// all of them; the contract is `None` unless there were none
+    pub fn parse_recovering(input: &str) -> (Option<Self>, Vec<ParseDiagnostic>) {
+        parser::parse_contract_recovering(input)
+    }
+    
+    /// The function that creates the contract's state: the one marked `#[constructor]`,
+    /// or else a public, non-view function named `initialize`
+    pub fn constructor(&self) -> Option<&Function> {
+        self.functions.iter().find(|f| f.is_constructor)
+            .or_else(|| self.functions.iter().find(|f| f.name == Contract::DEFAULT_CONSTRUCTOR
+                && matches!(f.visibility, Visibility::Public | Visibility::External)
+                && !f.is_view))
+    }
 }
\ No newline at end of file
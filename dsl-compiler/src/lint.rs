@@ -0,0 +1,305 @@
+use crate::{Contract, Function, Statement, Expression, LValue, BinaryOp, Visibility};
+use crate::codegen::{visit_expression, visit_expressions, visit_statements};
+use crate::modifiers::expand_modifiers;
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// How serious a lint finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+/// One anti-pattern found in a function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub function: String,
+    /// Line of the function's definition; the AST doesn't track statement lines
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.severity, self.message)
+    }
+}
+
+/// Fast, purely syntactic checks for common smart-contract pitfalls.
+///
+/// Unlike the formal verifier this needs no solver, so it can run on every save.
+/// The checks are heuristics: they can miss real problems and flag safe code.
+pub struct Linter {
+    // State variables of the contract being linted
+    state: HashSet<String>,
+    // Parameters and locals of the function being linted, which shadow state
+    locals: HashSet<String>,
+    findings: Vec<Finding>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self { state: HashSet::new(), locals: HashSet::new(), findings: Vec::new() }
+    }
+
+    /// Lints every function of `contract`, in declaration order
+    pub fn lint(&mut self, contract: &Contract) -> Vec<Finding> {
+        self.state = contract.state.iter().map(|var| var.name.clone()).collect();
+        // Modifier checks count as the function's own access control
+        let expanded = expand_modifiers(contract).unwrap_or_else(|_| contract.clone());
+
+        for func in &expanded.functions {
+            self.locals = func.params.iter().map(|p| p.name.clone()).collect();
+            visit_statements(&func.body, &mut |stmt| match stmt {
+                Statement::Let { name, .. } | Statement::ForEach { variable: name, .. } => {
+                    self.locals.insert(name.clone());
+                }
+                Statement::LetTuple { names, .. } => self.locals.extend(names.iter().cloned()),
+                _ => {}
+            });
+
+            self.check_reentrancy(func);
+            self.check_access_control(func);
+            self.check_unchecked_subtraction(func);
+            self.check_require_messages(func);
+            self.check_timestamp_randomness(func);
+        }
+
+        std::mem::take(&mut self.findings)
+    }
+
+    /// State written after a call into another contract, which could call back in
+    /// while the state is stale
+    fn check_reentrancy(&mut self, func: &Function) {
+        let mut called = None;
+        let mut written = Vec::new();
+        visit_statements(&func.body, &mut |stmt| {
+            // The value is evaluated, and any call in it made, before the write
+            for expr in direct_expressions(stmt) {
+                visit_expression(expr, &mut |e| {
+                    if let Expression::MethodCall { object, method, .. } = e {
+                        if e.is_value_method() {
+                            return;
+                        }
+                        if let Expression::Identifier(target) = object.as_ref() {
+                            called.get_or_insert_with(|| format!("{}.{}", target, method));
+                        }
+                    }
+                });
+            }
+            if let (Some(call), Statement::Assign { target, .. }) = (&called, stmt) {
+                let var = lvalue_root(target);
+                if self.is_state(var) && !written.contains(&var.to_string()) {
+                    written.push(var.to_string());
+                    self.report(func, "reentrancy", Severity::High, format!(
+                        "State variable '{}' is written after the external call {}() in '{}'; \
+                         update state before calling other contracts",
+                        var, call, func.name));
+                }
+            }
+        });
+    }
+
+    /// Public functions that change plain state without checking who the caller is.
+    /// Map writes are skipped: they are usually keyed by the caller already.
+    fn check_access_control(&mut self, func: &Function) {
+        if !matches!(func.visibility, Visibility::Public | Visibility::External) {
+            return;
+        }
+
+        let mut writes = Vec::new();
+        let mut checks_caller = false;
+        visit_statements(&func.body, &mut |stmt| match stmt {
+            Statement::Assign { target: LValue::Identifier(var), .. } if self.is_state(var) && !writes.contains(var) => {
+                writes.push(var.clone());
+            }
+            Statement::Require { condition, .. } | Statement::Assert { condition, .. } |
+            Statement::If { condition, .. } => {
+                visit_expression(condition, &mut |e| checks_caller |= matches!(e, Expression::MsgSender));
+            }
+            _ => {}
+        });
+
+        if !writes.is_empty() && !checks_caller {
+            self.report(func, "missing-access-control", Severity::High, format!(
+                "Public function '{}' writes {} without checking msg_sender()",
+                func.name,
+                writes.iter().map(|w| format!("'{}'", w)).collect::<Vec<_>>().join(", ")));
+        }
+    }
+
+    /// `a - b` with no earlier `require`/`if` comparing `a` and `b`, which aborts
+    /// (or wraps, with `--arith wrapping`) when `b > a`
+    fn check_unchecked_subtraction(&mut self, func: &Function) {
+        let mut guarded: Vec<(Expression, Expression)> = Vec::new();
+        let mut unguarded = Vec::new();
+        visit_statements(&func.body, &mut |stmt| {
+            for expr in direct_expressions(stmt) {
+                visit_expression(expr, &mut |e| {
+                    if let Expression::Binary { op: BinaryOp::Sub, left, right } = e {
+                        let literals = is_literal(left) && is_literal(right);
+                        let checked = guarded.iter().any(|(big, small)| big == left.as_ref() && small == right.as_ref());
+                        if !literals && !checked && !unguarded.contains(e) {
+                            unguarded.push(e.clone());
+                        }
+                    }
+                });
+            }
+            if let Statement::Require { condition, .. } | Statement::Assert { condition, .. } |
+                   Statement::If { condition, .. } = stmt {
+                visit_expression(condition, &mut |e| {
+                    if let Expression::Binary { op, left, right } = e {
+                        match op {
+                            BinaryOp::Ge | BinaryOp::Gt => guarded.push((*left.clone(), *right.clone())),
+                            BinaryOp::Le | BinaryOp::Lt => guarded.push((*right.clone(), *left.clone())),
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+
+        for expr in unguarded {
+            if let Expression::Binary { left, right, .. } = &expr {
+                self.report(func, "unchecked-arithmetic", Severity::Medium, format!(
+                    "Subtraction `{} - {}` in '{}' is not preceded by a check that {} >= {}",
+                    describe(left), describe(right), func.name, describe(left), describe(right)));
+            }
+        }
+    }
+
+    fn check_require_messages(&mut self, func: &Function) {
+        let mut missing = 0;
+        visit_statements(&func.body, &mut |stmt| {
+            if let Statement::Require { message: None, .. } = stmt {
+                missing += 1;
+            }
+        });
+        if missing > 0 {
+            self.report(func, "require-without-message", Severity::Low, format!(
+                "{} require(s) in '{}' have no message, so failures are hard to diagnose",
+                missing, func.name));
+        }
+    }
+
+    /// Block timestamps are chosen by validators, so they make poor randomness
+    fn check_timestamp_randomness(&mut self, func: &Function) {
+        let mut found = false;
+        visit_expressions(&func.body, &mut |expr| {
+            let seeded = match expr {
+                Expression::Binary { op: BinaryOp::Mod, left, .. } => mentions_timestamp(left),
+                Expression::Call { func: callee, args } => matches!(callee.as_ref(),
+                    Expression::Identifier(name) if ["random", "rand", "hash", "keccak", "sha"]
+                        .iter().any(|word| name.to_lowercase().contains(word)))
+                    && args.iter().any(mentions_timestamp),
+                _ => false,
+            };
+            found |= seeded;
+        });
+        if found {
+            self.report(func, "timestamp-randomness", Severity::Medium, format!(
+                "'{}' derives a random-looking value from block_timestamp(), which validators can influence",
+                func.name));
+        }
+    }
+
+    fn is_state(&self, name: &str) -> bool {
+        self.state.contains(name) && !self.locals.contains(name)
+    }
+
+    fn report(&mut self, func: &Function, rule: &str, severity: Severity, message: String) {
+        self.findings.push(Finding {
+            rule: rule.to_string(),
+            severity,
+            function: func.name.clone(),
+            line: func.line,
+            message,
+        });
+    }
+}
+
+/// The expressions a statement evaluates itself, leaving nested blocks to their own statements
+fn direct_expressions(stmt: &Statement) -> Vec<&Expression> {
+    match stmt {
+        Statement::Let { value, .. } | Statement::LetTuple { value, .. } | Statement::Expression(value) |
+        Statement::Return { value: Some(value) } => vec![value],
+        Statement::Assign { target, value } => {
+            let mut exprs = vec![value];
+            collect_indices(target, &mut exprs);
+            exprs
+        }
+        Statement::If { condition, .. } | Statement::While { condition, .. } |
+        Statement::Require { condition, .. } | Statement::Assert { condition, .. } |
+        Statement::For { condition, .. } => vec![condition],
+        Statement::ForEach { iterable, .. } => vec![iterable],
+        Statement::Match { value, .. } => vec![value],
+        Statement::Emit { args, .. } => args.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn collect_indices<'a>(lvalue: &'a LValue, exprs: &mut Vec<&'a Expression>) {
+    match lvalue {
+        LValue::Identifier(_) => {}
+        LValue::Index { array, index } => {
+            collect_indices(array, exprs);
+            exprs.push(index);
+        }
+        LValue::Field { object, .. } => collect_indices(object, exprs),
+    }
+}
+
+fn lvalue_root(lvalue: &LValue) -> &str {
+    match lvalue {
+        LValue::Identifier(name) => name,
+        LValue::Index { array, .. } => lvalue_root(array),
+        LValue::Field { object, .. } => lvalue_root(object),
+    }
+}
+
+fn mentions_timestamp(expr: &Expression) -> bool {
+    let mut found = false;
+    visit_expression(expr, &mut |e| found |= matches!(e, Expression::BlockTimestamp));
+    found
+}
+
+fn is_literal(expr: &Expression) -> bool {
+    matches!(expr, Expression::Number(_) | Expression::TypedNumber { .. })
+}
+
+/// Short source-like rendering of an operand for messages
+fn describe(expr: &Expression) -> String {
+    match expr {
+        Expression::Identifier(name) => name.clone(),
+        Expression::Number(n) => n.to_string(),
+        Expression::TypedNumber { value, .. } => value.to_string(),
+        Expression::Index { array, index } => format!("{}[{}]", describe(array), describe(index)),
+        Expression::Field { object, field } => format!("{}.{}", describe(object), field),
+        Expression::MsgSender => "msg_sender()".to_string(),
+        Expression::MsgValue => "msg_value()".to_string(),
+        Expression::Call { func, .. } => format!("{}(...)", describe(func)),
+        _ => "...".to_string(),
+    }
+}
@@ -1,10 +1,20 @@
-use clap::{Parser as ClapParser, Subcommand};
-use anyhow::Result;
+use clap::{ArgAction, Parser as ClapParser, Subcommand, ValueEnum};
+use anyhow::{Context, Result};
+use notify::RecursiveMode;
+use notify_debouncer_mini::new_debouncer;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
 
-mod codegen;
-use codegen::{solana::SolanaCodeGenerator, move_gen::MoveCodeGenerator};
+use cross_chain_dsl::{Contract, Program, SemanticAnalyzer, Statement, Type};
+use cross_chain_dsl::codegen::{ArithmeticMode, solana::{self, AnchorVersion, SolanaCodeGenerator, TokenStandard}, move_gen::{validate_named_address, AptosFramework, MoveCodeGenerator}, sui_gen::SuiCodeGenerator};
+use cross_chain_dsl::config::{ProjectConfig, CONFIG_FILE};
+use cross_chain_dsl::optimizer::{Optimizer, OptimizerConfig, OptimizationLevel};
+use cross_chain_dsl::estimator::CostEstimator;
+use cross_chain_dsl::interface::Interface;
+use cross_chain_dsl::lint::Linter;
 
 #[derive(ClapParser)]
 #[command(name = "ccdsl")]
@@ -22,6 +32,10 @@ enum Commands {
         #[arg(short, long)]
         input: PathBuf,
         
+        /// Format of the input file
+        #[arg(long, value_enum, default_value = "dsl")]
+        input_format: InputFormat,
+        
         /// Target platform (solana, aptos, sui, all)
         #[arg(short, long, default_value = "all")]
         target: String,
@@ -29,6 +43,98 @@ enum Commands {
         /// Output directory
         #[arg(short, long, default_value = "./output")]
         output: PathBuf,
+        
+        /// Optimization level (0, 1, 2)
+        #[arg(short = 'O', long = "opt-level", default_value = "1")]
+        opt_level: OptimizationLevel,
+        
+        /// Integer arithmetic semantics (checked, wrapping, unchecked)
+        #[arg(long, default_value = "checked")]
+        arith: ArithmeticMode,
+        
+        /// Emit plain arithmetic operators; same as `--arith unchecked`
+        #[arg(long, conflicts_with = "arith")]
+        unchecked_math: bool,
+        
+        /// Account space assumed for state strings, bytes and vecs declared without a maximum length
+        #[arg(long)]
+        max_len: Option<usize>,
+        
+        /// Base58 address of the deployed Solana program; overrides [solana] program_id in ccdsl.toml
+        #[arg(long, value_parser = parse_program_id)]
+        program_id: Option<String>,
+        
+        /// Where the Solana program keeps token balances: native (its own PDAs) or spl
+        /// (an SPL mint and associated token accounts, moved with SPL Token CPIs)
+        #[arg(long, default_value = "native")]
+        solana_token_standard: TokenStandard,
+        
+        /// Anchor release to generate the Solana program for (0.28 to 0.31), pinned in Anchor.toml;
+        /// overrides [solana] anchor_version in ccdsl.toml
+        #[arg(long)]
+        anchor_version: Option<AnchorVersion>,
+        
+        /// Aptos framework revision (e.g. aptos-node-v1.8.0) to generate Move for;
+        /// overrides [aptos] framework_rev in ccdsl.toml
+        #[arg(long)]
+        aptos_framework_rev: Option<AptosFramework>,
+        
+        /// Named address the Move modules are published under (cross_chain by default), declared
+        /// in the generated Move.toml; overrides [aptos] and [sui] named_address in ccdsl.toml
+        #[arg(long, value_parser = parse_named_address)]
+        move_address: Option<String>,
+        
+        /// Lower the owner check of a `mint` guarded by `require(msg_sender() == owner)` to a
+        /// MintCapability resource the deployer receives at init (Aptos); other guards stay address checks
+        #[arg(long)]
+        move_capabilities: bool,
+        
+        /// Parse the generated Solana program as Rust and fail, quoting the offending lines, if it
+        /// does not parse; on by default, `--check-output=false` writes the program unchecked
+        #[arg(long, action = ArgAction::Set, num_args = 0..=1, default_value_t = true, default_missing_value = "true")]
+        check_output: bool,
+        
+        /// Also emit tests: TypeScript tests for the Solana program under solana/tests and
+        /// Move unit tests for the Aptos and Sui modules under aptos/tests and sui/tests
+        #[arg(long, visible_alias = "emit-tests")]
+        with_tests: bool,
+        
+        /// Also write <contract>.interface.json, the entrypoint of every public function on each target chain
+        #[arg(long)]
+        emit_interface: bool,
+        
+        /// Also write <contract>.idl.json next to each target's code (an Anchor IDL for Solana)
+        #[arg(long)]
+        emit_idl: bool,
+        
+        /// Print the optimization report as JSON
+        #[arg(long)]
+        report_json: bool,
+        
+        /// Treat overflowing constant expressions as errors
+        #[arg(long)]
+        strict_overflow: bool,
+        
+        /// Recompile whenever the input file is saved
+        #[arg(short, long)]
+        watch: bool,
+        
+        /// Print estimated compute units / gas per function
+        #[arg(long)]
+        estimate: bool,
+        
+        /// Print the generated code for a single target to stdout instead of writing files
+        #[arg(long, conflicts_with_all = ["dry_run", "watch", "with_tests", "emit_interface", "emit_idl"])]
+        stdout: bool,
+        
+        /// List the files that would be written without touching the filesystem
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Also replace project files (Anchor.toml, Cargo.toml, ...) left by an earlier compile;
+        /// by default only the generated sources are rewritten
+        #[arg(long, conflicts_with = "stdout")]
+        overwrite: bool,
     },
     
     /// Validate DSL syntax
@@ -38,6 +144,38 @@ enum Commands {
         input: PathBuf,
     },
     
+    /// Check for common smart-contract anti-patterns
+    Lint {
+        /// Input DSL file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    
+    /// Run the formal verifier and print its proof certificate; fails if a property is violated
+    Verify {
+        /// Input DSL file
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Print the certificate as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Print the parsed AST as JSON
+    Ast {
+        /// Input DSL file
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Indent the JSON output
+        #[arg(long)]
+        pretty: bool,
+    },
+    
+    /// Read expressions from stdin and print their inferred types
+    Repl,
+    
     /// Generate example DSL file
     Example {
         /// Output file
@@ -46,16 +184,104 @@ enum Commands {
     },
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum InputFormat {
+    /// CrossChain DSL source
+    Dsl,
+    /// A JSON AST, as printed by `ccdsl ast`
+    Json,
+}
+
+/// What `compile` does with the generated files
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Write,
+    /// Print the main code file of the (single) target; everything else goes to stderr
+    Stdout,
+    DryRun,
+}
+
+/// Settings shared by every compile of a `compile` invocation
+struct CompileOptions {
+    input_format: InputFormat,
+    target: String,
+    output: PathBuf,
+    config: OptimizerConfig,
+    arith: ArithmeticMode,
+    max_len: Option<usize>,
+    program_id: Option<String>,
+    solana_token_standard: TokenStandard,
+    anchor_version: Option<AnchorVersion>,
+    aptos_framework_rev: Option<AptosFramework>,
+    move_address: Option<String>,
+    move_capabilities: bool,
+    check_output: bool,
+    with_tests: bool,
+    emit_interface: bool,
+    emit_idl: bool,
+    report_json: bool,
+    estimate: bool,
+    overwrite: bool,
+    output_mode: OutputMode,
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     
     match cli.command {
-        Commands::Compile { input, target, output } => {
-            compile(input, target, output)?;
+        Commands::Compile { input, input_format, target, output, opt_level, arith, unchecked_math, max_len, program_id, solana_token_standard, anchor_version, aptos_framework_rev, move_address, move_capabilities, check_output, with_tests, emit_interface, emit_idl, report_json, strict_overflow, watch, estimate, stdout, dry_run, overwrite } => {
+            let options = CompileOptions {
+                input_format,
+                target,
+                output,
+                config: OptimizerConfig::builder()
+                    .level(opt_level)
+                    .strict_overflow(strict_overflow)
+                    .build(),
+                arith: if unchecked_math { ArithmeticMode::Unchecked } else { arith },
+                max_len,
+                program_id,
+                solana_token_standard,
+                anchor_version,
+                aptos_framework_rev,
+                move_address,
+                move_capabilities,
+                check_output,
+                with_tests,
+                emit_interface,
+                emit_idl,
+                report_json,
+                estimate,
+                overwrite,
+                output_mode: if stdout {
+                    OutputMode::Stdout
+                } else if dry_run {
+                    OutputMode::DryRun
+                } else {
+                    OutputMode::Write
+                },
+            };
+            if watch {
+                watch_and_compile(&input, &options)?;
+            } else {
+                compile(&input, &options)?;
+            }
         }
         Commands::Validate { input } => {
             validate(input)?;
         }
+        Commands::Lint { input } => {
+            lint(input)?;
+        }
+        Commands::Verify { input, json } => {
+            verify(input, json)?;
+        }
+        Commands::Ast { input, pretty } => {
+            print_ast(input, pretty)?;
+        }
+        Commands::Repl => {
+            repl()?;
+        }
         Commands::Example { output } => {
             generate_example(output)?;
         }
@@ -64,79 +290,328 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn compile(input: PathBuf, target: String, output: PathBuf) -> Result<()> {
-    println!("🚀 CrossChain DSL Compiler");
-    println!("==========================");
-    println!("Input: {}", input.display());
-    println!("Target: {}", target);
-    println!("Output: {}", output.display());
-    println!();
+fn compile(input: &Path, options: &CompileOptions) -> Result<()> {
+    let CompileOptions { input_format, ref target, ref output, ref config, arith, max_len, ref program_id, solana_token_standard, anchor_version, ref aptos_framework_rev, ref move_address, move_capabilities, check_output, with_tests, emit_interface, emit_idl, report_json, estimate, overwrite, output_mode } = *options;
+    // 命令行参数优先于 ccdsl.toml (其中的值在加载时已检查)
+    let project = ProjectConfig::for_input(input)?;
+    let program_id = program_id.clone().or(project.solana.program_id);
+    let anchor_version = anchor_version
+        .or_else(|| project.solana.anchor_version.and_then(|v| v.parse().ok()));
+    let aptos_framework_rev = aptos_framework_rev.clone()
+        .or_else(|| project.aptos.framework_rev.and_then(|rev| rev.parse().ok()));
+    let aptos_named_address = move_address.clone().or(project.aptos.named_address);
+    let sui_named_address = move_address.clone().or(project.sui.named_address);
+    let mut warnings = 0;
     
-    // 读取 DSL 文件
-    let dsl_content = fs::read_to_string(&input)?;
+    if output_mode == OutputMode::Stdout && !matches!(target.as_str(), "solana" | "aptos" | "sui") {
+        anyhow::bail!("--stdout needs a single target (solana, aptos or sui), got '{}'", target);
+    }
     
-    // 创建输出目录
-    fs::create_dir_all(&output)?;
+    // --stdout 时标准输出只包含生成的代码, 其余信息写到 stderr
+    let to_stderr = output_mode == OutputMode::Stdout;
+    macro_rules! status {
+        ($($arg:tt)*) => {
+            if to_stderr { eprintln!($($arg)*) } else { println!($($arg)*) }
+        };
+    }
+    
+    status!("🚀 CrossChain DSL Compiler");
+    status!("==========================");
+    status!("Input: {}", input.display());
+    status!("Target: {}", target);
+    status!("Output: {}", output.display());
+    status!("Optimization: {}", config.level);
+    status!("Arithmetic: {}", arith);
+    status!();
+    
+    // 读取 DSL 文件 (或前端生成的 JSON AST) 并解析 import
+    let program = match input_format {
+        InputFormat::Dsl => Program::load(input)?,
+        InputFormat::Json => Program::load_json(input)?,
+    };
+    if program.contracts.len() != 1 {
+        anyhow::bail!("Expected exactly one contract in {}, found {}", input.display(), program.contracts.len());
+    }
+    let mut contract = program.contracts[0].clone();
+    
+    // 语义分析, 有错误时直接失败而不生成代码
+    SemanticAnalyzer::new(contract.name.clone())
+        .with_program(&program)
+        .analyze(&contract)
+        .with_context(|| format!("{} failed semantic analysis", input.display()))?;
+    
+    // 优化
+    let mut optimizer = Optimizer::with_config(config.clone());
+    let report = optimizer.optimize(&mut contract);
+    if report_json {
+        status!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        status!("{}", report);
+    }
+    status!();
+    
+    if !report.errors.is_empty() {
+        anyhow::bail!("Optimization failed with {} error(s)", report.errors.len());
+    }
+    
+    // 基于优化后的 AST 估算每个函数的计算开销
+    if estimate {
+        let costs = CostEstimator::new().estimate(&contract);
+        if report_json {
+            status!("{}", serde_json::to_string_pretty(&costs)?);
+        } else {
+            status!("{}", costs);
+        }
+        status!();
+    }
+    
+    // 先生成全部输出, 再统一写入; 每个目标的主代码文件排在该目标其他文件之前
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    // 工程文件只在不存在时写入 (--overwrite 时总是写入), 保留用户的修改
+    let mut scaffold: Vec<(PathBuf, String)> = Vec::new();
+    let mut interface = Interface::new(&contract);
     
     // 根据目标生成代码
     match target.as_str() {
         "solana" | "all" => {
-            println!("📦 Generating Solana code...");
-            let solana_gen = SolanaCodeGenerator::new();
-            
-            // 简化的示例 - 实际需要先解析 DSL
-            let solana_code = generate_solana_example();
+            status!("📦 Generating Solana code...");
+            let mut generator = SolanaCodeGenerator::new()
+                .with_arithmetic(arith)
+                .with_token_standard(solana_token_standard)
+                .with_externals(program.externals.clone())
+                .with_output_check(check_output);
+            if let Some(len) = max_len {
+                generator = generator.with_default_max_len(len);
+            }
+            if let Some(version) = anchor_version {
+                generator = generator.with_anchor_version(version);
+            }
+            if let Some(cluster) = &project.solana.cluster {
+                generator = generator.with_cluster(cluster);
+            }
+            match &program_id {
+                Some(id) => generator = generator.with_program_id(id),
+                None => {
+                    status!("⚠️  Warning: no program id set; declare_id! uses a placeholder. \
+                              Pass --program-id or set [solana] program_id in {}", CONFIG_FILE);
+                    warnings += 1;
+                }
+            }
+            let solana_code = generator.generate(&contract)?;
             
+            // Anchor 工作区: 程序位于 programs/<合约名>/src/lib.rs
             let solana_output = output.join("solana");
-            fs::create_dir_all(&solana_output)?;
-            fs::write(solana_output.join("lib.rs"), solana_code)?;
-            
-            println!("✅ Solana code generated at: {}", solana_output.display());
+            let program = solana_output.join("programs").join(contract.name.to_lowercase());
+            files.push((program.join("src").join("lib.rs"), solana_code));
+            scaffold.push((solana_output.join("Anchor.toml"), generator.anchor_toml(&contract)));
+            scaffold.push((solana_output.join("Cargo.toml"), generator.workspace_cargo_toml()));
+            scaffold.push((program.join("Cargo.toml"), generator.program_cargo_toml(&contract)));
+            scaffold.push((program.join("Xargo.toml"), generator.xargo_toml()));
+            if emit_interface {
+                interface.add_chain("solana", generator.entrypoints(&contract)?);
+            }
+            if emit_idl {
+                let idl = serde_json::to_string_pretty(&generator.idl(&contract)?)? + "\n";
+                files.push((solana_output.join(format!("{}.idl.json", contract.name.to_lowercase())), idl));
+            }
+            if with_tests {
+                let tests = solana_output.join("tests").join(format!("{}.ts", contract.name.to_lowercase()));
+                files.push((tests, generator.generate_tests(&contract)?));
+            }
         }
         _ => {}
     }
     
     match target.as_str() {
         "aptos" | "all" => {
-            println!("📦 Generating Aptos Move code...");
-            let move_gen = MoveCodeGenerator::new();
-            
-            // 简化的示例 - 实际需要先解析 DSL
-            let move_code = generate_move_example();
+            status!("📦 Generating Aptos Move code...");
+            let mut generator = MoveCodeGenerator::new()
+                .with_arithmetic(arith)
+                .with_externals(program.externals.clone())
+                .with_capabilities(move_capabilities);
+            if let Some(framework) = aptos_framework_rev {
+                generator = generator.with_framework(framework);
+            }
+            if let Some(name) = &aptos_named_address {
+                generator = generator.with_named_address(name);
+            }
+            let move_code = generator.generate(&contract)?;
+            if emit_interface {
+                interface.add_chain("aptos", generator.entrypoints(&contract));
+            }
             
+            // aptos/ 是一个 Move 包, 模块放在 sources/ 下
             let aptos_output = output.join("aptos");
-            fs::create_dir_all(&aptos_output)?;
-            fs::write(aptos_output.join("token.move"), move_code)?;
-            
-            println!("✅ Aptos Move code generated at: {}", aptos_output.display());
+            files.push((aptos_output.join("sources").join(format!("{}.move", contract.name.to_lowercase())), move_code));
+            scaffold.push((aptos_output.join("Move.toml"), generator.move_toml(&contract)));
+            // abort 码与消息的对照表, 供浏览器和客户端解析失败的交易
+            let abort_codes = generator.abort_codes(&contract)?;
+            if !abort_codes.errors.is_empty() {
+                let json = serde_json::to_string_pretty(&abort_codes)? + "\n";
+                files.push((aptos_output.join(format!("{}.errors.json", contract.name.to_lowercase())), json));
+            }
+            if emit_idl {
+                let idl = serde_json::to_string_pretty(&generator.idl(&contract)?)? + "\n";
+                files.push((aptos_output.join(format!("{}.idl.json", contract.name.to_lowercase())), idl));
+            }
+            if with_tests {
+                let tests = aptos_output.join("tests").join(format!("{}_tests.move", contract.name.to_lowercase()));
+                files.push((tests, generator.generate_tests(&contract)?));
+            }
         }
         _ => {}
     }
     
     match target.as_str() {
         "sui" | "all" => {
-            println!("📦 Generating Sui Move code...");
-            
-            let sui_code = generate_sui_example();
-            
+            status!("📦 Generating Sui Move code...");
+            let mut generator = SuiCodeGenerator::new()
+                .with_arithmetic(arith)
+                .with_externals(program.externals.clone());
+            if let Some(name) = &sui_named_address {
+                generator = generator.with_named_address(name);
+            }
+            let sui_code = generator.generate(&contract)?;
+            if emit_interface {
+                interface.add_chain("sui", generator.entrypoints(&contract)?);
+            }
             let sui_output = output.join("sui");
-            fs::create_dir_all(&sui_output)?;
-            fs::write(sui_output.join("token.move"), sui_code)?;
-            
-            println!("✅ Sui Move code generated at: {}", sui_output.display());
+            files.push((sui_output.join("sources").join(format!("{}.move", contract.name.to_lowercase())), sui_code));
+            scaffold.push((sui_output.join("Move.toml"), generator.move_toml(&contract)));
+            let abort_codes = generator.abort_codes(&contract)?;
+            if !abort_codes.errors.is_empty() {
+                let json = serde_json::to_string_pretty(&abort_codes)? + "\n";
+                files.push((sui_output.join(format!("{}.errors.json", contract.name.to_lowercase())), json));
+            }
+            if with_tests {
+                let tests = sui_output.join("tests").join(format!("{}_tests.move", contract.name.to_lowercase()));
+                files.push((tests, generator.generate_tests(&contract)?));
+            }
         }
         _ => {}
     }
     
-    println!("\n🎉 Compilation complete!");
-    println!("Next steps:");
-    println!("  1. Review generated code in {}", output.display());
-    println!("  2. Run platform-specific build commands");
-    println!("  3. Deploy to respective blockchains");
+    if emit_interface {
+        let path = output.join(format!("{}.interface.json", contract.name.to_lowercase()));
+        files.push((path, serde_json::to_string_pretty(&interface)? + "\n"));
+    }
+    
+    match output_mode {
+        OutputMode::Stdout => {
+            // 目标已检查为单个平台, 第一个文件就是它的主代码
+            let (_, code) = files.first().context("No code was generated")?;
+            print!("{}", code);
+            io::stdout().flush()?;
+            if warnings > 0 {
+                status!("⚠️  {} warning(s)", warnings);
+            }
+            return Ok(());
+        }
+        OutputMode::DryRun => {
+            for (path, contents) in &files {
+                status!("Would write {} ({} bytes)", path.display(), contents.len());
+            }
+            for (path, contents) in &scaffold {
+                if path.exists() && !overwrite {
+                    status!("Would keep {} (already exists)", path.display());
+                } else {
+                    status!("Would write {} ({} bytes)", path.display(), contents.len());
+                }
+            }
+            status!("\n🎉 Dry run complete, nothing was written");
+            if warnings > 0 {
+                status!("⚠️  {} warning(s)", warnings);
+            }
+            return Ok(());
+        }
+        OutputMode::Write => {
+            for (path, contents) in &files {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+                status!("✅ Wrote {}", path.display());
+            }
+            for (path, contents) in &scaffold {
+                if path.exists() && !overwrite {
+                    status!("⏭️  Kept {} (already exists; --overwrite replaces it)", path.display());
+                    continue;
+                }
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+                status!("✅ Wrote {}", path.display());
+            }
+        }
+    }
+    
+    status!("\n🎉 Compilation complete!");
+    if warnings > 0 {
+        status!("⚠️  {} warning(s)", warnings);
+    }
+    status!("Next steps:");
+    status!("  1. Review generated code in {}", output.display());
+    status!("  2. Run platform-specific build commands");
+    status!("  3. Deploy to respective blockchains");
     
     Ok(())
 }
 
+fn parse_program_id(id: &str) -> Result<String> {
+    solana::validate_program_id(id)?;
+    Ok(id.to_string())
+}
+
+fn parse_named_address(name: &str) -> Result<String> {
+    validate_named_address(name)?;
+    Ok(name.to_string())
+}
+
+fn watch_and_compile(input: &Path, options: &CompileOptions) -> Result<()> {
+    // 错误只打印不退出, 输出文件仅在编译成功时才会被改写
+    let run = || {
+        if let Err(e) = compile(input, options) {
+            eprintln!("❌ Compilation failed: {:#}", e);
+        }
+        println!("\n👀 Watching {} for changes (Ctrl+C to stop)...", input.display());
+    };
+    
+    run();
+    
+    // 合并编辑器在短时间内的多次写入
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(Duration::from_millis(300), tx)?;
+    
+    // 监听所在目录而不是文件本身, 以兼容先写临时文件再重命名的编辑器
+    let watch_dir = match input.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    debouncer.watcher().watch(&watch_dir, RecursiveMode::NonRecursive)?;
+    
+    for result in rx {
+        match result {
+            Ok(events) if events.iter().any(|e| is_same_file(&e.path, input)) => {
+                // 清屏
+                print!("\x1B[2J\x1B[1;1H");
+                run();
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("⚠️  Watch error: {}", e),
+        }
+    }
+    
+    Ok(())
+}
+
+fn is_same_file(changed: &Path, input: &Path) -> bool {
+    match (changed.canonicalize(), input.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => changed.file_name() == input.file_name(),
+    }
+}
+
 fn validate(input: PathBuf) -> Result<()> {
     println!("🔍 Validating DSL file: {}", input.display());
     
@@ -148,85 +623,139 @@ fn validate(input: PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn generate_example(output: PathBuf) -> Result<()> {
-    let example = include_str!("../examples/token.ccdsl");
-    fs::write(&output, example)?;
+fn lint(input: PathBuf) -> Result<()> {
+    let program = Program::load(&input)?;
     
-    println!("📝 Example DSL file generated: {}", output.display());
-    println!("Edit this file and run: ccdsl compile -i {} -t all", output.display());
+    // 每行一个结果, 格式为 `rule_id: file:line: message`, 方便 grep
+    let mut count = 0;
+    for contract in &program.contracts {
+        for finding in Linter::new().lint(contract) {
+            println!("{}: {}:{}: {}", finding.rule, input.display(), finding.line, finding);
+            count += 1;
+        }
+    }
     
+    if count > 0 {
+        anyhow::bail!("{} lint finding(s)", count);
+    }
     Ok(())
 }
 
-// 临时的示例生成函数
-fn generate_solana_example() -> String {
-    r#"use anchor_lang::prelude::*;
-
-declare_id!("11111111111111111111111111111111");
-
-#[program]
-pub mod token {
-    use super::*;
+#[cfg(feature = "verify")]
+fn verify(input: PathBuf, json: bool) -> Result<()> {
+    use formal_verification::verifier::VerificationResult;
+    use formal_verification::{Config, Context, FormalVerifier};
     
-    pub fn initialize(ctx: Context<Initialize>, initial_supply: u64, decimals: u8) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        state.total_supply = initial_supply;
-        state.decimals = decimals;
-        state.owner = ctx.accounts.owner.key();
-        Ok(())
+    let program = Program::load(&input)?;
+    if program.contracts.len() != 1 {
+        anyhow::bail!("Expected exactly one contract in {}, found {}", input.display(), program.contracts.len());
+    }
+    let context = Context::new(&Config::new());
+    let mut verifier = FormalVerifier::new(&context);
+    verifier.load_contract(cross_chain_dsl::verify::to_verifier_contract(&program.contracts[0])?);
+    let certificate = verifier.verify_correctness()?;
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&certificate)?);
+    } else {
+        println!("🔒 Proof certificate for {} ({})", certificate.contract_name, certificate.solver_version);
+        for property in &certificate.verified_properties {
+            let (mark, detail) = match &property.result {
+                VerificationResult::Verified => ("✅", String::new()),
+                VerificationResult::Violated(reason) => ("❌", format!(": {}", reason)),
+                VerificationResult::Unknown(reason) => ("❔", format!(": {}", reason)),
+                VerificationResult::Timeout => ("⏱️ ", ": timed out".to_string()),
+            };
+            println!("  {} {}{}", mark, property.property_name, detail);
+        }
+        for invariant in &certificate.invariants_checked {
+            let mark = if invariant.holds { "✅" } else { "❌" };
+            println!("  {} invariant {}", mark, invariant.invariant_name);
+        }
+        println!();
+        for (function, coverage) in &certificate.function_coverage {
+            println!("  {}: {}", function, coverage);
+        }
+        println!("Coverage: {:.1}%", certificate.coverage);
     }
     
-    pub fn transfer(ctx: Context<Transfer>, amount: u64) -> Result<()> {
-        // Transfer logic
-        Ok(())
+    // 只有 Violated 算作失败, Unknown 和超时只打印出来
+    let violated = certificate.verified_properties.iter()
+        .filter(|p| matches!(p.result, VerificationResult::Violated(_)))
+        .count();
+    if violated > 0 {
+        anyhow::bail!("Verification failed with {} violation(s)", violated);
     }
-}"#.to_string()
+    Ok(())
+}
+
+#[cfg(not(feature = "verify"))]
+fn verify(_input: PathBuf, _json: bool) -> Result<()> {
+    anyhow::bail!("ccdsl was built without the formal verifier; rebuild it with `--features verify` (needs Z3)")
 }
 
-fn generate_move_example() -> String {
-    r#"module token_addr::token {
-    use std::signer;
-    use aptos_framework::event;
+fn print_ast(input: PathBuf, pretty: bool) -> Result<()> {
+    let source = fs::read_to_string(&input)
+        .with_context(|| format!("Cannot read {}", input.display()))?;
+    let program = Program::parse(&source)
+        .with_context(|| format!("In {}", input.display()))?;
     
-    struct TokenState has key {
-        total_supply: u64,
-        owner: address,
-        decimals: u8,
-    }
+    // stdout 只输出 JSON, 方便其他工具直接读取
+    let json = if pretty {
+        serde_json::to_string_pretty(&program)?
+    } else {
+        serde_json::to_string(&program)?
+    };
+    println!("{}", json);
     
-    public entry fun initialize(account: &signer, initial_supply: u64, decimals: u8) {
-        move_to(account, TokenState {
-            total_supply: initial_supply,
-            owner: signer::address_of(account),
-            decimals,
-        });
-    }
+    Ok(())
+}
+
+fn repl() -> Result<()> {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut line = String::new();
     
-    public entry fun transfer(from: &signer, to: address, amount: u64) acquires TokenState {
-        // Transfer logic
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush()?;
+        }
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            break;
+        }
+        let source = line.trim().trim_end_matches(';');
+        if source.is_empty() {
+            continue;
+        }
+        match infer_type(source) {
+            Ok(ty) => println!("{}", ty),
+            Err(e) => println!("error: {:#}", e),
+        }
     }
-}"#.to_string()
+    
+    Ok(())
 }
 
-fn generate_sui_example() -> String {
-    r#"module token::token {
-    use sui::object::{Self, UID};
-    use sui::transfer;
-    use sui::tx_context::{Self, TxContext};
+/// Parses `source` as the value of a `let` in an otherwise empty contract and type-checks it
+fn infer_type(source: &str) -> Result<Type> {
+    let wrapped = format!("contract Repl {{ public fn eval() {{ let value = {}; }} }}", source);
+    let contract = Contract::parse(&wrapped)
+        .map_err(|_| anyhow::anyhow!("`{}` is not an expression", source))?;
+    let value = match contract.functions.first().map(|f| f.body.as_slice()) {
+        Some([Statement::Let { value, .. }]) => value,
+        _ => anyhow::bail!("`{}` is not an expression", source),
+    };
+    SemanticAnalyzer::new(contract.name.clone()).check_expression(value)
+}
+
+fn generate_example(output: PathBuf) -> Result<()> {
+    let example = include_str!("../examples/token.ccdsl");
+    fs::write(&output, example)?;
     
-    struct Token has key, store {
-        id: UID,
-        total_supply: u64,
-        decimals: u8,
-    }
+    println!("📝 Example DSL file generated: {}", output.display());
+    println!("Edit this file and run: ccdsl compile -i {} -t all", output.display());
     
-    public fun initialize(initial_supply: u64, decimals: u8, ctx: &mut TxContext) {
-        let token = Token {
-            id: object::new(ctx),
-            total_supply: initial_supply,
-            decimals,
-        };
-        transfer::share_object(token);
-    }
-}"#.to_string()
-}
\ No newline at end of file
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::{Result, anyhow};
+use crate::{BinaryOp, Contract, Expression, Function, Modifier, Statement, MatchArm};
+use crate::codegen::visit_expressions;
+use crate::optimizer::{collect_assigned, collect_bindings, substitute_statement};
+
+/// An access-control modifier whose body is exactly
+/// `require(msg_sender() == field, message); _;` (either side of the `==`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderCheck<'a> {
+    /// The variable the sender must equal; a state variable unless something shadows it
+    pub field: &'a str,
+    pub message: &'a Option<String>,
+}
+
+/// Recognizes `onlyOwner`-style modifiers, for backends that can check the
+/// sender before running the function. Anything else is `None`.
+pub fn sender_check(modifier: &Modifier) -> Option<SenderCheck<'_>> {
+    if !modifier.params.is_empty() {
+        return None;
+    }
+    let [Statement::Require { condition, message }, Statement::Placeholder] = modifier.body.as_slice() else {
+        return None;
+    };
+    let Expression::Binary { op: BinaryOp::Eq, left, right } = condition else {
+        return None;
+    };
+    match (left.as_ref(), right.as_ref()) {
+        (Expression::MsgSender, Expression::Identifier(field)) | (Expression::Identifier(field), Expression::MsgSender) => {
+            Some(SenderCheck { field, message })
+        }
+        _ => None,
+    }
+}
+
+/// Returns a copy of `contract` with every applied modifier spliced into its function.
+///
+/// Modifiers wrap in the order they are listed, so `onlyOwner whenNotPaused` runs
+/// `onlyOwner`'s checks first, then `whenNotPaused`'s, then the body. Each `_;` is
+/// replaced by everything inside it. A `return` in the body skips modifier code
+/// after the `_;`, which only matters for modifiers that do work afterwards.
+///
+/// The arguments of a modifier are bound with `let` just before its code, like
+/// Solidity evaluates them on entering the modifier. A parameter whose name the
+/// function already uses is renamed, so the binding can't shadow it.
+pub fn expand_modifiers(contract: &Contract) -> Result<Contract> {
+    let mut expanded = contract.clone();
+
+    for func in &mut expanded.functions {
+        let mut body = std::mem::take(&mut func.body);
+
+        for call in func.modifiers.iter().rev() {
+            let modifier = contract.modifiers.iter()
+                .find(|m| m.name == call.name)
+                .ok_or_else(|| anyhow!("Unknown modifier '{}' on function '{}'", call.name, func.name))?;
+            body = wrap(modifier, &call.args, func, body)?;
+        }
+
+        func.body = body;
+        func.modifiers.clear();
+    }
+
+    Ok(expanded)
+}
+
+fn wrap(modifier: &Modifier, args: &[Expression], func: &Function, body: Vec<Statement>) -> Result<Vec<Statement>> {
+    if args.len() != modifier.params.len() {
+        return Err(anyhow!(
+            "Modifier '{}' expects {} argument(s), got {} on function '{}'",
+            modifier.name, modifier.params.len(), args.len(), func.name
+        ));
+    }
+
+    let mut taken: HashSet<String> = func.params.iter().map(|p| p.name.clone()).collect();
+    collect_names(&body, &mut taken);
+    let mut own = HashSet::new();
+    collect_names(&modifier.body, &mut own);
+    taken.extend(own.into_iter().filter(|name| !modifier.params.iter().any(|p| p.name == *name)));
+
+    let mut wrapped = Vec::new();
+    let mut renames = HashMap::new();
+    for (param, arg) in modifier.params.iter().zip(args) {
+        let name = std::iter::once(param.name.clone())
+            .chain((1..).map(|n| format!("{}_{}", param.name, n)))
+            .find(|name| !taken.contains(name))
+            .unwrap();
+        taken.insert(name.clone());
+        if name != param.name {
+            renames.insert(param.name.clone(), Expression::Identifier(name.clone()));
+        }
+        wrapped.push(Statement::Let {
+            name,
+            ty: Some(param.ty.clone()),
+            value: arg.clone(),
+            is_mutable: param.is_mutable,
+        });
+    }
+
+    let code: Vec<Statement> = modifier.body.iter().map(|s| substitute_statement(s, &renames)).collect();
+    let mut found = false;
+    wrapped.extend(splice(&code, &body, &mut found));
+    if !found {
+        return Err(anyhow!("Modifier '{}' has no `_;` placeholder", modifier.name));
+    }
+    Ok(wrapped)
+}
+
+/// Every variable `statements` declares, assigns or reads
+fn collect_names(statements: &[Statement], names: &mut HashSet<String>) {
+    collect_assigned(statements, names);
+    collect_bindings(statements, names);
+    visit_expressions(statements, &mut |expr| {
+        if let Expression::Identifier(name) = expr {
+            names.insert(name.clone());
+        }
+    });
+}
+
+fn splice(statements: &[Statement], body: &[Statement], found: &mut bool) -> Vec<Statement> {
+    let mut result = Vec::new();
+
+    for stmt in statements {
+        match stmt {
+            Statement::Placeholder => {
+                *found = true;
+                result.extend(body.iter().cloned());
+            }
+            Statement::If { condition, then_block, else_block } => result.push(Statement::If {
+                condition: condition.clone(),
+                then_block: splice(then_block, body, found),
+                else_block: else_block.as_ref().map(|b| splice(b, body, found)),
+            }),
+            Statement::Match { value, arms } => result.push(Statement::Match {
+                value: value.clone(),
+                arms: arms.iter()
+                    .map(|arm| MatchArm { pattern: arm.pattern.clone(), body: splice(&arm.body, body, found) })
+                    .collect(),
+            }),
+            Statement::Block(inner) => result.push(Statement::Block(splice(inner, body, found))),
+            other => result.push(other.clone()),
+        }
+    }
+
+    result
+}
@@ -1,133 +1,788 @@
-use crate::{Contract, Function, Statement, Expression, BinaryOp, LValue};
-use std::collections::HashMap;
+use crate::{Contract, Function, Statement, Expression, BinaryOp, LValue, Type, Visibility, MatchArm};
+use crate::codegen::{visit_expressions, visit_statements};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Optimization level, mirroring the usual `-O0`/`-O1`/`-O2` compiler flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OptimizationLevel {
+    /// No optimization, the AST is handed to codegen untouched
+    O0,
+    /// Constant folding, algebraic simplification and dead code removal
+    O1,
+    /// Everything in O1 plus CSE, inlining and dead function elimination
+    O2,
+}
+
+impl FromStr for OptimizationLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" | "O0" | "o0" => Ok(OptimizationLevel::O0),
+            "1" | "O1" | "o1" => Ok(OptimizationLevel::O1),
+            "2" | "O2" | "o2" => Ok(OptimizationLevel::O2),
+            _ => Err(format!("Unknown optimization level '{}', expected 0, 1 or 2", s)),
+        }
+    }
+}
+
+impl fmt::Display for OptimizationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptimizationLevel::O0 => write!(f, "O0"),
+            OptimizationLevel::O1 => write!(f, "O1"),
+            OptimizationLevel::O2 => write!(f, "O2"),
+        }
+    }
+}
+
+/// Controls which optimization passes run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizerConfig {
+    pub level: OptimizationLevel,
+    pub enable_constant_folding: bool,
+    /// Report constant expressions that overflow as errors instead of warnings
+    pub strict_overflow: bool,
+    pub enable_simplification: bool,
+    /// Rewrite `*`, `/` and `%` by powers of two into shifts and masks
+    pub enable_strength_reduction: bool,
+    pub enable_dead_code_elimination: bool,
+    pub enable_cse: bool,
+    pub enable_inlining: bool,
+    pub enable_dead_function_elimination: bool,
+    /// Drop events that no remaining function emits
+    pub enable_dead_event_elimination: bool,
+    pub enable_loop_unrolling: bool,
+    /// Drop requires already checked earlier in the function and merge adjacent
+    /// message-less requires into one
+    pub enable_require_dedup: bool,
+    /// Largest callee (in expression nodes) that will be inlined
+    pub max_inline_size: usize,
+    /// Constant-bound loops with fewer iterations than this are unrolled
+    pub unroll_threshold: u64,
+}
+
+impl OptimizerConfig {
+    /// Default pass selection for an optimization level
+    pub fn for_level(level: OptimizationLevel) -> Self {
+        let o1 = level != OptimizationLevel::O0;
+        let o2 = level == OptimizationLevel::O2;
+
+        Self {
+            level,
+            enable_constant_folding: o1,
+            strict_overflow: false,
+            enable_simplification: o1,
+            enable_strength_reduction: o1,
+            enable_dead_code_elimination: o1,
+            enable_cse: o2,
+            enable_inlining: o2,
+            enable_dead_function_elimination: o2,
+            enable_dead_event_elimination: o2,
+            enable_loop_unrolling: o2,
+            enable_require_dedup: o1,
+            max_inline_size: 16,
+            unroll_threshold: 8,
+        }
+    }
+
+    pub fn builder() -> OptimizerConfigBuilder {
+        OptimizerConfigBuilder::new()
+    }
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> Self {
+        Self::for_level(OptimizationLevel::O1)
+    }
+}
+
+/// Builder for `OptimizerConfig`; `level` resets the individual
+/// passes to that level's defaults, so call it before any overrides
+pub struct OptimizerConfigBuilder {
+    config: OptimizerConfig,
+}
+
+impl OptimizerConfigBuilder {
+    pub fn new() -> Self {
+        Self { config: OptimizerConfig::default() }
+    }
+
+    pub fn level(mut self, level: OptimizationLevel) -> Self {
+        let max_inline_size = self.config.max_inline_size;
+        let unroll_threshold = self.config.unroll_threshold;
+        let strict_overflow = self.config.strict_overflow;
+        self.config = OptimizerConfig::for_level(level);
+        self.config.max_inline_size = max_inline_size;
+        self.config.unroll_threshold = unroll_threshold;
+        self.config.strict_overflow = strict_overflow;
+        self
+    }
+
+    pub fn strict_overflow(mut self, strict: bool) -> Self {
+        self.config.strict_overflow = strict;
+        self
+    }
+
+    pub fn enable_constant_folding(mut self, enabled: bool) -> Self {
+        self.config.enable_constant_folding = enabled;
+        self
+    }
+
+    pub fn enable_simplification(mut self, enabled: bool) -> Self {
+        self.config.enable_simplification = enabled;
+        self
+    }
+
+    pub fn enable_strength_reduction(mut self, enabled: bool) -> Self {
+        self.config.enable_strength_reduction = enabled;
+        self
+    }
+
+    pub fn enable_dead_code_elimination(mut self, enabled: bool) -> Self {
+        self.config.enable_dead_code_elimination = enabled;
+        self
+    }
+
+    pub fn enable_cse(mut self, enabled: bool) -> Self {
+        self.config.enable_cse = enabled;
+        self
+    }
+
+    pub fn enable_inlining(mut self, enabled: bool) -> Self {
+        self.config.enable_inlining = enabled;
+        self
+    }
+
+    pub fn enable_dead_function_elimination(mut self, enabled: bool) -> Self {
+        self.config.enable_dead_function_elimination = enabled;
+        self
+    }
+
+    pub fn enable_dead_event_elimination(mut self, enabled: bool) -> Self {
+        self.config.enable_dead_event_elimination = enabled;
+        self
+    }
+
+    pub fn enable_loop_unrolling(mut self, enabled: bool) -> Self {
+        self.config.enable_loop_unrolling = enabled;
+        self
+    }
+
+    pub fn enable_require_dedup(mut self, enabled: bool) -> Self {
+        self.config.enable_require_dedup = enabled;
+        self
+    }
+
+    pub fn max_inline_size(mut self, size: usize) -> Self {
+        self.config.max_inline_size = size;
+        self
+    }
+
+    pub fn unroll_threshold(mut self, threshold: u64) -> Self {
+        self.config.unroll_threshold = threshold;
+        self
+    }
+
+    pub fn build(self) -> OptimizerConfig {
+        self.config
+    }
+}
+
+impl Default for OptimizerConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How freely a pass may delete or move a statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Only touches locals, so it can be removed or rewritten once its result is unused
+    Local,
+    /// Emits, checks and calls: observable on chain, so no pass may delete one or move
+    /// code across it. The exceptions are checks proven to always pass and branches
+    /// proven never to run, which have no observable effect to keep.
+    Barrier,
+}
+
+/// Classifies a statement; compound statements are barriers if anything inside them is
+pub fn statement_effect(stmt: &Statement) -> Effect {
+    let mut calls = HashSet::new();
+    collect_calls(std::slice::from_ref(stmt), &mut calls);
+    let mut events = Vec::new();
+    collect_emits(std::slice::from_ref(stmt), &mut events);
+
+    if !calls.is_empty() || !events.is_empty() || contains_check(std::slice::from_ref(stmt)) {
+        Effect::Barrier
+    } else {
+        Effect::Local
+    }
+}
+
+/// Upper bound on passes over a function body; optimization stops earlier once a pass changes nothing
+pub const MAX_OPTIMIZATION_PASSES: usize = 10;
+
+/// What one pass over the function bodies changed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PassStats {
+    pub dead_code_removed: usize,
+    pub constants_folded: usize,
+    pub expressions_simplified: usize,
+    pub strength_reductions: usize,
+    pub common_subexpressions_eliminated: usize,
+    pub functions_inlined: usize,
+    pub loops_unrolled: usize,
+    pub requires_removed: usize,
+}
+
+impl PassStats {
+    fn snapshot(report: &OptimizationReport) -> Self {
+        Self {
+            dead_code_removed: report.dead_code_removed,
+            constants_folded: report.constants_folded,
+            expressions_simplified: report.expressions_simplified,
+            strength_reductions: report.strength_reductions,
+            common_subexpressions_eliminated: report.common_subexpressions_eliminated,
+            functions_inlined: report.functions_inlined,
+            loops_unrolled: report.loops_unrolled,
+            requires_removed: report.requires_removed,
+        }
+    }
+
+    fn add_difference(&mut self, before: &PassStats, after: &PassStats) {
+        self.dead_code_removed += after.dead_code_removed - before.dead_code_removed;
+        self.constants_folded += after.constants_folded - before.constants_folded;
+        self.expressions_simplified += after.expressions_simplified - before.expressions_simplified;
+        self.strength_reductions += after.strength_reductions - before.strength_reductions;
+        self.common_subexpressions_eliminated +=
+            after.common_subexpressions_eliminated - before.common_subexpressions_eliminated;
+        self.functions_inlined += after.functions_inlined - before.functions_inlined;
+        self.loops_unrolled += after.loops_unrolled - before.loops_unrolled;
+        self.requires_removed += after.requires_removed - before.requires_removed;
+    }
+}
+
+/// Statistics collected during a run of the optimizer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptimizationReport {
+    pub level: OptimizationLevel,
+    pub dead_code_removed: usize,
+    pub constants_folded: usize,
+    pub expressions_simplified: usize,
+    pub strength_reductions: usize,
+    pub common_subexpressions_eliminated: usize,
+    pub functions_inlined: usize,
+    pub loops_unrolled: usize,
+    /// Requires dropped as repeats of an earlier check or merged into the one before
+    pub requires_removed: usize,
+    pub dead_functions_removed: Vec<String>,
+    pub dead_events_removed: Vec<String>,
+    /// Changes made by each pass, summed over all functions; the last pass changed nothing
+    /// unless the `MAX_OPTIMIZATION_PASSES` limit was hit
+    pub passes: Vec<PassStats>,
+    /// Constant expressions left unfolded because they would overflow
+    pub warnings: Vec<String>,
+    /// Same as `warnings`, when `strict_overflow` is set
+    pub errors: Vec<String>,
+}
+
+impl OptimizationReport {
+    fn new(level: OptimizationLevel) -> Self {
+        Self {
+            level,
+            dead_code_removed: 0,
+            constants_folded: 0,
+            expressions_simplified: 0,
+            strength_reductions: 0,
+            common_subexpressions_eliminated: 0,
+            functions_inlined: 0,
+            loops_unrolled: 0,
+            requires_removed: 0,
+            dead_functions_removed: Vec::new(),
+            dead_events_removed: Vec::new(),
+            passes: Vec::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "✨ Optimization complete ({}):", self.level)?;
+        writeln!(f, "   - Dead code removed: {} statements", self.dead_code_removed)?;
+        writeln!(f, "   - Constants folded: {} expressions", self.constants_folded)?;
+        writeln!(f, "   - Expressions simplified: {} operations", self.expressions_simplified)?;
+        writeln!(f, "   - Strength reductions: {} operations", self.strength_reductions)?;
+        writeln!(f, "   - Common subexpressions eliminated: {}", self.common_subexpressions_eliminated)?;
+        writeln!(f, "   - Functions inlined: {} call sites", self.functions_inlined)?;
+        writeln!(f, "   - Loops unrolled: {}", self.loops_unrolled)?;
+        writeln!(f, "   - Redundant requires removed: {}", self.requires_removed)?;
+        writeln!(f, "   - Dead functions removed: {}", self.dead_functions_removed.len())?;
+        writeln!(f, "   - Dead events removed: {}", self.dead_events_removed.len())?;
+        write!(f, "   - Passes until stable: {}", self.passes.len())?;
+        for warning in &self.warnings {
+            write!(f, "\n⚠️  {}", warning)?;
+        }
+        for error in &self.errors {
+            write!(f, "\n❌ {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// A private function simple enough to be substituted at its call sites
+struct InlineCandidate {
+    params: Vec<String>,
+    body: Expression,
+}
 
 pub struct Optimizer {
+    config: OptimizerConfig,
     constant_values: HashMap<String, Expression>,
-    dead_code_removed: usize,
-    constants_folded: usize,
-    expressions_simplified: usize,
+    // Folded values of the contract's `const` declarations
+    contract_constants: HashMap<String, Expression>,
+    // Let-bound pure expressions available for reuse (CSE)
+    available_expressions: Vec<(Expression, String)>,
+    // Conditions known to hold at this point: earlier require/assert checks,
+    // their `&&` conjuncts and what they imply (see `establish`)
+    facts: Vec<Expression>,
+    // Whether the contract uses a signed integer type anywhere; the rewrites
+    // that rely on every integer being unsigned are off then
+    signed: bool,
+    // Names of the state variables, whose known values a call can change
+    state_vars: HashSet<String>,
+    // Parameters and locals of the function being optimized, which an inlined
+    // body must not refer to by accident
+    caller_bindings: HashSet<String>,
+    inline_candidates: HashMap<String, InlineCandidate>,
+    report: OptimizationReport,
 }
 
 impl Optimizer {
     pub fn new() -> Self {
+        Self::with_config(OptimizerConfig::default())
+    }
+
+    pub fn with_config(config: OptimizerConfig) -> Self {
+        let report = OptimizationReport::new(config.level);
         Self {
+            config,
             constant_values: HashMap::new(),
-            dead_code_removed: 0,
-            constants_folded: 0,
-            expressions_simplified: 0,
+            contract_constants: HashMap::new(),
+            available_expressions: Vec::new(),
+            facts: Vec::new(),
+            signed: false,
+            state_vars: HashSet::new(),
+            caller_bindings: HashSet::new(),
+            inline_candidates: HashMap::new(),
+            report,
         }
     }
-    
-    pub fn optimize(&mut self, contract: &mut Contract) {
-        println!("🔧 Starting optimization pass...");
-        
+
+    pub fn config(&self) -> &OptimizerConfig {
+        &self.config
+    }
+
+    /// Unrolls constant-bound loops with fewer than `threshold` iterations, whatever
+    /// the optimization level; 0 turns unrolling off
+    pub fn set_unroll_threshold(&mut self, threshold: u64) {
+        self.config.unroll_threshold = threshold;
+        self.config.enable_loop_unrolling = threshold > 0;
+    }
+
+    pub fn optimize(&mut self, contract: &mut Contract) -> OptimizationReport {
+        self.report = OptimizationReport::new(self.config.level);
+        self.signed = uses_signed_integers(contract);
+        self.state_vars = contract.state.iter().map(|v| v.name.clone()).collect();
+        self.caller_bindings.clear();
+
+        if self.config.enable_inlining {
+            self.collect_inline_candidates(contract);
+        }
+
+        self.fold_contract_constants(contract);
+
         // Optimize each function
         for func in &mut contract.functions {
             self.optimize_function(func);
         }
-        
-        // Report optimization statistics
-        self.report_stats();
+
+        // Runs last so helpers whose every call site was inlined are dropped too
+        if self.config.enable_dead_function_elimination {
+            self.eliminate_dead_functions(contract);
+        }
+
+        // After dead functions, so emits inside removed helpers don't keep events alive
+        if self.config.enable_dead_event_elimination {
+            self.eliminate_dead_events(contract);
+        }
+
+        self.inline_candidates.clear();
+        self.contract_constants.clear();
+        self.report.clone()
     }
-    
-    fn optimize_function(&mut self, func: &mut Function) {
-        // Clear constant tracking for new function
+
+    /// Folds each `const` initializer so later constants and every function can use its value,
+    /// then the initial values of state variables, which may use the constants
+    fn fold_contract_constants(&mut self, contract: &mut Contract) {
         self.constant_values.clear();
-        
-        // Optimize function body
-        func.body = self.optimize_statements(func.body.clone());
+        self.contract_constants.clear();
+        if !self.config.enable_constant_folding {
+            return;
+        }
+
+        for constant in &mut contract.constants {
+            constant.value = self.optimize_expression(constant.value.clone());
+            if self.is_constant(&constant.value) {
+                self.constant_values.insert(constant.name.clone(), constant.value.clone());
+                self.contract_constants.insert(constant.name.clone(), constant.value.clone());
+            }
+        }
+        for var in &mut contract.state {
+            if let Some(value) = var.initial_value.take() {
+                var.initial_value = Some(self.optimize_expression(value));
+            }
+        }
+    }
+
+    /// Re-runs the statement optimizer until the body stops changing, since each rewrite
+    /// (propagation, folding, simplification, DCE) can expose more work for the others
+    fn optimize_function(&mut self, func: &mut Function) {
+        self.caller_bindings = func.params.iter().map(|p| p.name.clone()).collect();
+        collect_bindings(&func.body, &mut self.caller_bindings);
+
+        for pass in 0..MAX_OPTIMIZATION_PASSES {
+            // Each pass starts from the contract's constants, minus any the parameters shadow
+            self.constant_values = self.contract_constants.clone();
+            for param in &func.params {
+                self.constant_values.remove(&param.name);
+            }
+            self.available_expressions.clear();
+            self.facts.clear();
+
+            let before = PassStats::snapshot(&self.report);
+            let body = self.optimize_statements(func.body.clone());
+            let after = PassStats::snapshot(&self.report);
+
+            if self.report.passes.len() <= pass {
+                self.report.passes.push(PassStats::default());
+            }
+            self.report.passes[pass].add_difference(&before, &after);
+
+            let changed = body != func.body;
+            func.body = body;
+            if !changed {
+                break;
+            }
+        }
     }
-    
+
     fn optimize_statements(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
         let mut optimized = Vec::new();
-        
+
         for stmt in statements {
-            match self.optimize_statement(stmt) {
-                Some(s) => optimized.push(s),
-                None => self.dead_code_removed += 1,
+            // An unrolled loop becomes one copy of its body per iteration, each
+            // optimized in turn so the now-constant induction variable folds
+            if let Some(copies) = self.try_unroll(&stmt) {
+                self.report.loops_unrolled += 1;
+                optimized.extend(self.optimize_statements(copies));
+                continue;
+            }
+
+            let is_if = matches!(stmt, Statement::If { .. });
+            let result = match statement_effect(&stmt) {
+                Effect::Local => self.optimize_statement(stmt),
+                Effect::Barrier => {
+                    let original = stmt.clone();
+                    let result = self.optimize_statement(stmt);
+                    keep_barrier(original, result)
+                }
+            };
+            match result {
+                Some(Statement::Block(live)) if is_if && !declares_locals(&live) => optimized.extend(live),
+                Some(s) => self.push_merging_requires(&mut optimized, s),
+                None => self.report.dead_code_removed += 1,
             }
         }
-        
+
         optimized
     }
-    
+
+    /// Appends `stmt`, folding it into the previous statement when both are
+    /// message-less requires: `require(a); require(b);` becomes `require(a && b);`
+    fn push_merging_requires(&mut self, statements: &mut Vec<Statement>, stmt: Statement) {
+        if self.config.enable_require_dedup {
+            if let (Some(Statement::Require { condition: previous, message: None }),
+                    Statement::Require { condition, message: None }) = (statements.last_mut(), &stmt) {
+                *previous = Expression::Binary {
+                    op: BinaryOp::And,
+                    left: Box::new(previous.clone()),
+                    right: Box::new(condition.clone()),
+                };
+                self.report.requires_removed += 1;
+                return;
+            }
+        }
+        statements.push(stmt);
+    }
+
+    fn try_unroll(&self, stmt: &Statement) -> Option<Vec<Statement>> {
+        if !self.config.enable_loop_unrolling {
+            return None;
+        }
+
+        let known: HashMap<String, u64> = self.constant_values.iter()
+            .filter_map(|(name, value)| match value {
+                Expression::Number(n) => Some((name.clone(), *n)),
+                _ => None,
+            })
+            .collect();
+
+        let (variable, values, body) = self.unroll_plan(stmt, &known)?;
+        // Locals the body declares stay scoped to their iteration, so they neither
+        // clash across copies nor shadow variables used after the loop
+        let scoped = declares_locals(body);
+        Some(values.into_iter()
+            .flat_map(|value| {
+                let bindings = HashMap::from([(variable.clone(), Expression::Number(value))]);
+                let copy: Vec<Statement> = body.iter().map(|s| substitute_statement(s, &bindings)).collect();
+                if scoped { vec![Statement::Block(copy)] } else { copy }
+            })
+            .collect())
+    }
+
+    /// Induction variable and its values for a `For` loop with constant bounds
+    /// and fewer than `unroll_threshold` iterations, if it can be unrolled
+    fn unroll_plan<'a>(&self, stmt: &'a Statement, known: &HashMap<String, u64>)
+        -> Option<(String, Vec<u64>, &'a [Statement])>
+    {
+        let (init, condition, update, body) = match stmt {
+            Statement::For { init, condition, update, body } => (init, condition, update, body),
+            _ => return None,
+        };
+
+        let (variable, start) = match init.as_ref() {
+            Statement::Let { name, value, .. } => (name, const_eval(value, known)?),
+            _ => return None,
+        };
+
+        let (op, end) = match condition {
+            Expression::Binary { op, left, right } if **left == Expression::Identifier(variable.clone()) => {
+                (op, const_eval(right, known)?)
+            }
+            _ => return None,
+        };
+
+        let step = match update.as_ref() {
+            Statement::Assign {
+                target: LValue::Identifier(target),
+                value: Expression::Binary { op: BinaryOp::Add, left, right },
+            } if target == variable && **left == Expression::Identifier(variable.clone()) => {
+                const_eval(right, known).filter(|step| *step > 0)?
+            }
+            _ => return None,
+        };
+
+        let mut values = Vec::new();
+        let mut current = start;
+        loop {
+            let in_range = match op {
+                BinaryOp::Lt => current < end,
+                BinaryOp::Le => current <= end,
+                BinaryOp::Ne => current != end,
+                _ => return None,
+            };
+            if !in_range {
+                break;
+            }
+            values.push(current);
+            if values.len() as u64 >= self.config.unroll_threshold {
+                return None;
+            }
+            current = current.checked_add(step)?;
+        }
+
+        // The body must not redefine the induction variable or leave the loop early
+        let mut assigned = HashSet::new();
+        collect_assigned(body, &mut assigned);
+        if assigned.contains(variable) || contains_loop_exit(body) {
+            return None;
+        }
+
+        // Nested loops must themselves be unrollable for every outer value
+        for value in &values {
+            let mut inner_known = known.clone();
+            inner_known.insert(variable.clone(), *value);
+            if !self.nested_loops_unrollable(body, &inner_known) {
+                return None;
+            }
+        }
+
+        Some((variable.clone(), values, body))
+    }
+
     fn optimize_statement(&mut self, stmt: Statement) -> Option<Statement> {
+        let dce = self.config.enable_dead_code_elimination;
+
+        // A call may write any state, so no earlier expression or value of a state
+        // variable can be reused past it
+        let mut callees = HashSet::new();
+        collect_calls(std::slice::from_ref(&stmt), &mut callees);
+        if !callees.is_empty() {
+            self.available_expressions.clear();
+            self.facts.clear();
+            let state_vars = &self.state_vars;
+            self.constant_values.retain(|name, _| !state_vars.contains(name));
+        }
+
         match stmt {
-            Statement::Let { name, value } => {
-                let optimized_value = self.optimize_expression(value);
-                
+            Statement::Let { name, ty, value, is_mutable } => {
+                let mut optimized_value = self.optimize_expression(value);
+                self.invalidate(&name);
+
                 // Track constant values for propagation
-                if self.is_constant(&optimized_value) {
+                if self.config.enable_constant_folding && self.is_constant(&optimized_value) {
                     self.constant_values.insert(name.clone(), optimized_value.clone());
                 }
-                
-                Some(Statement::Let { name, value: optimized_value })
+
+                if self.config.enable_cse && is_cse_candidate(&optimized_value) {
+                    let existing = self.available_expressions.iter()
+                        .find(|(expr, _)| *expr == optimized_value)
+                        .map(|(_, binding)| binding.clone());
+
+                    match existing {
+                        Some(binding) => {
+                            self.report.common_subexpressions_eliminated += 1;
+                            optimized_value = Expression::Identifier(binding);
+                        }
+                        None if !is_mutable => {
+                            self.available_expressions.push((optimized_value.clone(), name.clone()));
+                        }
+                        None => {}
+                    }
+                }
+
+                Some(Statement::Let { name, ty, value: optimized_value, is_mutable })
             }
-            
+
+            Statement::LetTuple { names, value } => {
+                let optimized_value = self.optimize_expression(value);
+                for name in &names {
+                    self.invalidate(name);
+                }
+                Some(Statement::LetTuple { names, value: optimized_value })
+            }
+
             Statement::Assign { target, value } => {
                 let optimized_value = self.optimize_expression(value);
-                
+
                 // Update constant tracking if target is a simple identifier
-                if let LValue::Identifier(name) = target {
-                    if self.is_constant(&optimized_value) {
+                let root = lvalue_root(&target).to_string();
+                self.invalidate(&root);
+                if let LValue::Identifier(name) = &target {
+                    if self.config.enable_constant_folding && self.is_constant(&optimized_value) {
                         self.constant_values.insert(name.clone(), optimized_value.clone());
-                    } else {
-                        self.constant_values.remove(name);
                     }
                 }
-                
-                Some(Statement::Assign { target: target.clone(), value: optimized_value })
+
+                Some(Statement::Assign { target, value: optimized_value })
             }
-            
+
             Statement::If { condition, then_block, else_block } => {
-                let optimized_condition = self.optimize_expression(condition);
-                
+                let mut optimized_condition = self.optimize_expression(condition);
+
                 // Check for constant conditions
-                match &optimized_condition {
-                    Expression::Bool(true) => {
-                        // Always true - remove else block
-                        self.dead_code_removed += else_block.as_ref().map(|b| b.len()).unwrap_or(0);
-                        return Some(Statement::Expression(Expression::Bool(true)));
+                if dce {
+                    // An earlier check may already decide the condition
+                    if let Some(known) = self.known_truth(&optimized_condition) {
+                        self.report.expressions_simplified += 1;
+                        optimized_condition = Expression::Bool(known);
                     }
-                    Expression::Bool(false) => {
-                        // Always false - use else block or remove
-                        self.dead_code_removed += then_block.len();
-                        if let Some(else_stmts) = else_block {
-                            return Some(Statement::Expression(Expression::Bool(false)));
+
+                    match &optimized_condition {
+                        Expression::Bool(true) => {
+                            // Always true - remove else block
+                            self.report.dead_code_removed += else_block.as_ref().map(|b| b.len()).unwrap_or(0);
+                            return Some(self.optimize_live_branch(then_block));
                         }
-                        return None;
+                        Expression::Bool(false) => {
+                            // Always false - use else block or remove
+                            self.report.dead_code_removed += then_block.len();
+                            return else_block.map(|b| self.optimize_live_branch(b));
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
-                
+
+                let then_block = self.optimize_block(then_block);
+                let else_block = else_block.map(|b| self.optimize_block(b));
+
                 Some(Statement::If {
                     condition: optimized_condition,
-                    then_block: self.optimize_statements(then_block),
-                    else_block: else_block.map(|b| self.optimize_statements(b)),
+                    then_block,
+                    else_block,
                 })
             }
-            
+
+            Statement::Match { value, arms } => {
+                let value = self.optimize_expression(value);
+                let arms = arms.into_iter()
+                    .map(|arm| MatchArm {
+                        pattern: arm.pattern,
+                        body: self.optimize_block(arm.body),
+                    })
+                    .collect();
+
+                Some(Statement::Match { value, arms })
+            }
+
             Statement::Require { condition, message } => {
                 let optimized_condition = self.optimize_expression(condition);
-                
+
                 // Check for always-true requires (can be removed)
-                if let Expression::Bool(true) = optimized_condition {
-                    self.dead_code_removed += 1;
+                if dce && matches!(optimized_condition, Expression::Bool(true)) {
+                    self.report.dead_code_removed += 1;
+                    return None;
+                }
+
+                // Nothing it reads has changed since the same check passed
+                if self.config.enable_require_dedup && self.facts.contains(&optimized_condition) {
+                    self.report.requires_removed += 1;
                     return None;
                 }
-                
+                if is_pure(&optimized_condition) {
+                    establish(&optimized_condition, !self.signed, &mut self.facts);
+                }
+
                 Some(Statement::Require {
                     condition: optimized_condition,
                     message,
                 })
             }
-            
+
+            Statement::Assert { condition, message } => {
+                let optimized_condition = self.optimize_expression(condition);
+                if is_pure(&optimized_condition) {
+                    establish(&optimized_condition, !self.signed, &mut self.facts);
+                }
+
+                Some(Statement::Assert {
+                    condition: optimized_condition,
+                    message,
+                })
+            }
+
             Statement::Return { value } => {
                 Some(Statement::Return {
                     value: value.map(|v| self.optimize_expression(v)),
                 })
             }
-            
+
             Statement::Emit { event, args } => {
                 Some(Statement::Emit {
                     event,
@@ -136,128 +791,183 @@ impl Optimizer {
                         .collect(),
                 })
             }
-            
+
             Statement::Expression(expr) => {
-                let optimized = self.optimize_expression(expr);
-                
+                let mut optimized = self.optimize_expression(expr);
+                if self.config.enable_simplification {
+                    optimized = effects_of(optimized);
+                }
+
                 // Remove no-op expressions
-                if self.is_no_op(&optimized) {
-                    self.dead_code_removed += 1;
+                if dce && self.is_no_op(&optimized) {
+                    self.report.dead_code_removed += 1;
                     None
                 } else {
                     Some(Statement::Expression(optimized))
                 }
             }
+
+            Statement::Block(body) => {
+                let body = self.optimize_block(body);
+                (!body.is_empty()).then_some(Statement::Block(body))
+            }
+
+            other => {
+                // Loops and other compound statements: anything assigned inside
+                // may change on a later iteration, so forget what we know
+                self.constant_values.clear();
+                self.available_expressions.clear();
+                self.facts.clear();
+                Some(other)
+            }
+        }
+    }
+
+    /// The branch of a constant `if` always runs, so it is optimized in the enclosing
+    /// scope and spliced in place of the `if` by optimize_statements. A branch that
+    /// declares locals stays a scoped block, so they don't shadow variables after it.
+    fn optimize_live_branch(&mut self, branch: Vec<Statement>) -> Statement {
+        if declares_locals(&branch) {
+            Statement::Block(self.optimize_block(branch))
+        } else {
+            Statement::Block(self.optimize_statements(branch))
+        }
+    }
+
+    /// Optimizes a nested block without leaking its facts into the enclosing one
+    fn optimize_block(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
+        let saved_constants = self.constant_values.clone();
+        let saved_available = self.available_expressions.clone();
+        let saved_facts = self.facts.clone();
+
+        let optimized = self.optimize_statements(statements);
+
+        // Anything assigned in the block may or may not have happened
+        let mut assigned = HashSet::new();
+        collect_outer_assigned(&optimized, &mut assigned);
+        self.constant_values = saved_constants;
+        self.available_expressions = saved_available;
+        self.facts = saved_facts;
+        for name in &assigned {
+            self.invalidate(name);
+        }
+
+        optimized
+    }
+
+    /// Whether the facts established so far decide `condition` outright
+    fn known_truth(&self, condition: &Expression) -> Option<bool> {
+        if self.facts.contains(condition) {
+            Some(true)
+        } else if negate(condition).is_some_and(|negated| self.facts.contains(&negated)) {
+            Some(false)
+        } else {
+            None
         }
     }
-    
+
+    /// Forgets everything derived from `name` after it is (re)assigned
+    fn invalidate(&mut self, name: &str) {
+        self.constant_values.remove(name);
+        self.available_expressions.retain(|(expr, binding)| {
+            binding != name && !expression_reads(expr, name)
+        });
+        self.facts.retain(|condition| !expression_reads(condition, name));
+    }
+
     fn optimize_expression(&mut self, expr: Expression) -> Expression {
+        let fold = self.config.enable_constant_folding;
+        let simplify = self.config.enable_simplification;
+        let reduce = self.config.enable_strength_reduction;
+
         match expr {
             Expression::Identifier(name) => {
                 // Constant propagation
                 if let Some(const_value) = self.constant_values.get(&name) {
-                    self.expressions_simplified += 1;
+                    self.report.expressions_simplified += 1;
                     return const_value.clone();
                 }
                 Expression::Identifier(name)
             }
-            
+
             Expression::Binary { op, left, right } => {
                 let left_opt = self.optimize_expression(*left);
                 let right_opt = self.optimize_expression(*right);
-                
+
                 // Constant folding
-                if let (Expression::Number(l), Expression::Number(r)) = (&left_opt, &right_opt) {
-                    self.constants_folded += 1;
-                    return self.fold_binary_op(op, *l, *r);
-                }
-                
-                // Algebraic simplifications
-                match (&op, &left_opt, &right_opt) {
-                    // x + 0 = x, 0 + x = x
-                    (BinaryOp::Add, expr, Expression::Number(0)) |
-                    (BinaryOp::Add, Expression::Number(0), expr) => {
-                        self.expressions_simplified += 1;
-                        return expr.clone();
-                    }
-                    
-                    // x - 0 = x
-                    (BinaryOp::Sub, expr, Expression::Number(0)) => {
-                        self.expressions_simplified += 1;
-                        return expr.clone();
-                    }
-                    
-                    // x * 1 = x, 1 * x = x
-                    (BinaryOp::Mul, expr, Expression::Number(1)) |
-                    (BinaryOp::Mul, Expression::Number(1), expr) => {
-                        self.expressions_simplified += 1;
-                        return expr.clone();
-                    }
-                    
-                    // x * 0 = 0, 0 * x = 0
-                    (BinaryOp::Mul, _, Expression::Number(0)) |
-                    (BinaryOp::Mul, Expression::Number(0), _) => {
-                        self.expressions_simplified += 1;
-                        return Expression::Number(0);
-                    }
-                    
-                    // x / 1 = x
-                    (BinaryOp::Div, expr, Expression::Number(1)) => {
-                        self.expressions_simplified += 1;
-                        return expr.clone();
-                    }
-                    
-                    // x && true = x, true && x = x
-                    (BinaryOp::And, expr, Expression::Bool(true)) |
-                    (BinaryOp::And, Expression::Bool(true), expr) => {
-                        self.expressions_simplified += 1;
-                        return expr.clone();
+                if fold {
+                    if let (Expression::Number(l), Expression::Number(r)) = (&left_opt, &right_opt) {
+                        if let Some(folded) = self.fold_binary_op(&op, *l, *r) {
+                            self.report.constants_folded += 1;
+                            return folded;
+                        }
+
+                        // Rewriting an unfoldable constant (e.g. into a shift) could hide the overflow
+                        return Expression::Binary {
+                            op,
+                            left: Box::new(left_opt),
+                            right: Box::new(right_opt),
+                        };
                     }
-                    
-                    // x && false = false, false && x = false
-                    (BinaryOp::And, _, Expression::Bool(false)) |
-                    (BinaryOp::And, Expression::Bool(false), _) => {
-                        self.expressions_simplified += 1;
-                        return Expression::Bool(false);
+
+                    let folded = match (&left_opt, &right_opt) {
+                        (Expression::Bool(l), Expression::Bool(r)) => fold_bool_op(&op, *l, *r),
+                        (Expression::String(l), Expression::String(r)) => fold_string_op(&op, l, r),
+                        _ => None,
+                    };
+                    if let Some(folded) = folded {
+                        self.report.constants_folded += 1;
+                        return folded;
                     }
-                    
-                    // x || false = x, false || x = x
-                    (BinaryOp::Or, expr, Expression::Bool(false)) |
-                    (BinaryOp::Or, Expression::Bool(false), expr) => {
-                        self.expressions_simplified += 1;
-                        return expr.clone();
+                }
+
+                // Algebraic simplifications
+                if simplify {
+                    if let Some(simplified) = self.simplify_binary(&op, &left_opt, &right_opt) {
+                        self.report.expressions_simplified += 1;
+                        return simplified;
                     }
-                    
-                    // x || true = true, true || x = true
-                    (BinaryOp::Or, _, Expression::Bool(true)) |
-                    (BinaryOp::Or, Expression::Bool(true), _) => {
-                        self.expressions_simplified += 1;
-                        return Expression::Bool(true);
+                }
+
+                // Shifts and masks only match division and remainder on unsigned
+                // integers, and only the contracts without signed types are known to have none
+                if reduce && !self.signed {
+                    if let Some(reduced) = reduce_strength(&op, &left_opt, &right_opt) {
+                        self.report.strength_reductions += 1;
+                        return reduced;
                     }
-                    
-                    _ => {}
                 }
-                
+
                 Expression::Binary {
                     op,
                     left: Box::new(left_opt),
                     right: Box::new(right_opt),
                 }
             }
-            
+
             Expression::Unary { op, expr } => {
                 let expr_opt = self.optimize_expression(*expr);
-                
+
                 // Constant folding for unary operations
                 match (&op, &expr_opt) {
-                    (crate::UnaryOp::Not, Expression::Bool(b)) => {
-                        self.constants_folded += 1;
+                    (crate::UnaryOp::Not, Expression::Bool(b)) if fold => {
+                        self.report.constants_folded += 1;
                         Expression::Bool(!b)
                     }
-                    (crate::UnaryOp::Neg, Expression::Number(n)) => {
-                        self.constants_folded += 1;
-                        // Note: This would need proper handling for unsigned types
-                        Expression::Number((!n).wrapping_add(1))
+                    (crate::UnaryOp::Neg, Expression::Number(0)) if fold => {
+                        self.report.constants_folded += 1;
+                        Expression::Number(0)
+                    }
+                    (crate::UnaryOp::Neg, Expression::Number(n)) if fold => {
+                        // Numbers are folded as u64, so there's nothing to fold to; it's
+                        // only wrong when every integer type is unsigned
+                        if !self.signed {
+                            self.overflow(format!("Cannot negate unsigned constant {}", n));
+                        }
+                        Expression::Unary {
+                            op,
+                            expr: Box::new(expr_opt),
+                        }
                     }
                     _ => Expression::Unary {
                         op,
@@ -265,85 +975,903 @@ impl Optimizer {
                     }
                 }
             }
-            
+
+            Expression::Cast { expr, ty } => Expression::Cast {
+                expr: Box::new(self.optimize_expression(*expr)),
+                ty,
+            },
+
+            Expression::OptionSome(value) => Expression::OptionSome(Box::new(self.optimize_expression(*value))),
+
             Expression::Call { func, args } => {
-                Expression::Call {
-                    func,
-                    args: args.into_iter()
-                        .map(|a| self.optimize_expression(a))
-                        .collect(),
+                let args: Vec<Expression> = args.into_iter()
+                    .map(|a| self.optimize_expression(a))
+                    .collect();
+
+                if let Expression::Identifier(name) = func.as_ref() {
+                    if let Some(inlined) = self.try_inline(name, &args) {
+                        self.report.functions_inlined += 1;
+                        return self.optimize_expression(inlined);
+                    }
                 }
+
+                Expression::Call { func, args }
             }
-            
+
             Expression::Index { array, index } => {
                 Expression::Index {
                     array,
                     index: Box::new(self.optimize_expression(*index)),
                 }
             }
-            
+
             _ => expr,
         }
     }
-    
-    fn fold_binary_op(&self, op: BinaryOp, left: u64, right: u64) -> Expression {
-        match op {
-            BinaryOp::Add => Expression::Number(left.wrapping_add(right)),
-            BinaryOp::Sub => Expression::Number(left.wrapping_sub(right)),
-            BinaryOp::Mul => Expression::Number(left.wrapping_mul(right)),
-            BinaryOp::Div => {
-                if right != 0 {
-                    Expression::Number(left / right)
-                } else {
-                    // Keep division by zero as-is for error reporting
-                    Expression::Binary {
-                        op: BinaryOp::Div,
-                        left: Box::new(Expression::Number(left)),
-                        right: Box::new(Expression::Number(0)),
-                    }
-                }
+
+    fn simplify_binary(&self, op: &BinaryOp, left: &Expression, right: &Expression) -> Option<Expression> {
+        match (op, left, right) {
+            // x + 0 = x, 0 + x = x
+            (BinaryOp::Add, expr, Expression::Number(0)) |
+            (BinaryOp::Add, Expression::Number(0), expr) => Some(expr.clone()),
+
+            // x - 0 = x
+            (BinaryOp::Sub, expr, Expression::Number(0)) => Some(expr.clone()),
+
+            // x * 1 = x, 1 * x = x
+            (BinaryOp::Mul, expr, Expression::Number(1)) |
+            (BinaryOp::Mul, Expression::Number(1), expr) => Some(expr.clone()),
+
+            // x * 0 = 0, 0 * x = 0
+            (BinaryOp::Mul, expr, Expression::Number(0)) |
+            (BinaryOp::Mul, Expression::Number(0), expr) if is_pure(expr) => Some(Expression::Number(0)),
+
+            // x / 1 = x
+            (BinaryOp::Div, expr, Expression::Number(1)) => Some(expr.clone()),
+
+            // x && true = x, true && x = x
+            (BinaryOp::And, expr, Expression::Bool(true)) |
+            (BinaryOp::And, Expression::Bool(true), expr) => Some(expr.clone()),
+
+            // x && false = false (x still runs); false && x = false (x never runs)
+            (BinaryOp::And, expr, Expression::Bool(false)) if is_pure(expr) => Some(Expression::Bool(false)),
+            (BinaryOp::And, Expression::Bool(false), _) => Some(Expression::Bool(false)),
+
+            // x || false = x, false || x = x
+            (BinaryOp::Or, expr, Expression::Bool(false)) |
+            (BinaryOp::Or, Expression::Bool(false), expr) => Some(expr.clone()),
+
+            // x || true = true (x still runs); true || x = true (x never runs)
+            (BinaryOp::Or, expr, Expression::Bool(true)) if is_pure(expr) => Some(Expression::Bool(true)),
+            (BinaryOp::Or, Expression::Bool(true), _) => Some(Expression::Bool(true)),
+
+            _ => None,
+        }
+    }
+
+    /// Folds `left op right` with the same checked semantics the generated
+    /// programs use; `None` leaves the expression as written
+    fn fold_binary_op(&mut self, op: &BinaryOp, left: u64, right: u64) -> Option<Expression> {
+        let (result, symbol) = match op {
+            BinaryOp::Add => (left.checked_add(right), "+"),
+            BinaryOp::Sub => (left.checked_sub(right), "-"),
+            BinaryOp::Mul => (left.checked_mul(right), "*"),
+            BinaryOp::Pow => (u32::try_from(right).ok().and_then(|exp| left.checked_pow(exp)), "**"),
+            // Bits shifted out of the top are an overflow too
+            BinaryOp::Shl => (
+                u32::try_from(right).ok()
+                    .and_then(|shift| left.checked_shl(shift))
+                    .filter(|shifted| shifted >> right == left),
+                "<<",
+            ),
+            BinaryOp::Shr => (u32::try_from(right).ok().and_then(|shift| left.checked_shr(shift)), ">>"),
+            BinaryOp::BitAnd => return Some(Expression::Number(left & right)),
+            BinaryOp::BitOr => return Some(Expression::Number(left | right)),
+            BinaryOp::BitXor => return Some(Expression::Number(left ^ right)),
+            // Division by zero is kept as-is for error reporting
+            BinaryOp::Div => return left.checked_div(right).map(Expression::Number),
+            BinaryOp::Mod => return left.checked_rem(right).map(Expression::Number),
+            BinaryOp::Eq => return Some(Expression::Bool(left == right)),
+            BinaryOp::Ne => return Some(Expression::Bool(left != right)),
+            BinaryOp::Lt => return Some(Expression::Bool(left < right)),
+            BinaryOp::Gt => return Some(Expression::Bool(left > right)),
+            BinaryOp::Le => return Some(Expression::Bool(left <= right)),
+            BinaryOp::Ge => return Some(Expression::Bool(left >= right)),
+            // Can't fold logical operators on numbers
+            _ => return None,
+        };
+
+        if result.is_none() {
+            self.overflow(format!("Constant expression {} {} {} overflows u64", left, symbol, right));
+        }
+        result.map(Expression::Number)
+    }
+
+    fn overflow(&mut self, message: String) {
+        // Later passes revisit the same unfolded expression
+        let diagnostics = if self.config.strict_overflow {
+            &mut self.report.errors
+        } else {
+            &mut self.report.warnings
+        };
+        if !diagnostics.contains(&message) {
+            diagnostics.push(message);
+        }
+    }
+
+    fn nested_loops_unrollable(&self, statements: &[Statement], known: &HashMap<String, u64>) -> bool {
+        statements.iter().all(|stmt| match stmt {
+            Statement::For { .. } => self.unroll_plan(stmt, known).is_some(),
+            Statement::While { .. } | Statement::ForEach { .. } => false,
+            Statement::If { then_block, else_block, .. } => {
+                self.nested_loops_unrollable(then_block, known)
+                    && else_block.as_deref().is_none_or(|b| self.nested_loops_unrollable(b, known))
             }
-            BinaryOp::Mod => {
-                if right != 0 {
-                    Expression::Number(left % right)
-                } else {
-                    Expression::Binary {
-                        op: BinaryOp::Mod,
-                        left: Box::new(Expression::Number(left)),
-                        right: Box::new(Expression::Number(0)),
-                    }
+            Statement::Block(body) => self.nested_loops_unrollable(body, known),
+            Statement::Match { arms, .. } => arms.iter().all(|arm| self.nested_loops_unrollable(&arm.body, known)),
+            _ => true,
+        })
+    }
+
+    /// Private helpers whose whole body is `return <expr>;` are inlined when small enough
+    fn collect_inline_candidates(&mut self, contract: &Contract) {
+        self.inline_candidates.clear();
+
+        for func in &contract.functions {
+            if !matches!(func.visibility, Visibility::Private | Visibility::Internal) {
+                continue;
+            }
+
+            if let [Statement::Return { value: Some(body) }] = func.body.as_slice() {
+                let mut callees = HashSet::new();
+                collect_calls_in_expression(body, &mut callees);
+
+                // Recursive helpers can't be expanded
+                if callees.contains(&func.name) || expression_size(body) > self.config.max_inline_size {
+                    continue;
                 }
+
+                self.inline_candidates.insert(func.name.clone(), InlineCandidate {
+                    params: func.params.iter().map(|p| p.name.clone()).collect(),
+                    body: body.clone(),
+                });
             }
-            BinaryOp::Eq => Expression::Bool(left == right),
-            BinaryOp::Ne => Expression::Bool(left != right),
-            BinaryOp::Lt => Expression::Bool(left < right),
-            BinaryOp::Gt => Expression::Bool(left > right),
-            BinaryOp::Le => Expression::Bool(left <= right),
-            BinaryOp::Ge => Expression::Bool(left >= right),
-            _ => {
-                // Can't fold logical operators on numbers
-                Expression::Binary {
-                    op,
-                    left: Box::new(Expression::Number(left)),
-                    right: Box::new(Expression::Number(right)),
+        }
+    }
+
+    fn try_inline(&self, name: &str, args: &[Expression]) -> Option<Expression> {
+        let candidate = self.inline_candidates.get(name)?;
+
+        // Only atomic arguments are substituted, so nothing is evaluated twice or dropped
+        if candidate.params.len() != args.len() || !args.iter().all(is_atomic) {
+            return None;
+        }
+
+        // A state variable or constant the helper reads would be captured by a
+        // caller's local of the same name
+        let captured = self.caller_bindings.iter()
+            .any(|name| !candidate.params.contains(name) && expression_reads(&candidate.body, name));
+        if captured {
+            return None;
+        }
+
+        let bindings: HashMap<String, Expression> = candidate.params.iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect();
+
+        Some(substitute(&candidate.body, &bindings))
+    }
+
+    /// Drops private/internal functions unreachable from any public entry point
+    fn eliminate_dead_functions(&mut self, contract: &mut Contract) {
+        let calls: HashMap<String, HashSet<String>> = contract.functions.iter()
+            .map(|f| {
+                let mut callees = HashSet::new();
+                collect_calls(&f.body, &mut callees);
+                (f.name.clone(), callees)
+            })
+            .collect();
+
+        let mut reachable: HashSet<String> = HashSet::new();
+        let mut worklist: Vec<String> = contract.functions.iter()
+            .filter(|f| matches!(f.visibility, Visibility::Public | Visibility::External))
+            .map(|f| f.name.clone())
+            .collect();
+
+        while let Some(name) = worklist.pop() {
+            if reachable.insert(name.clone()) {
+                if let Some(callees) = calls.get(&name) {
+                    worklist.extend(callees.iter().cloned());
                 }
             }
         }
+
+        let removed = &mut self.report.dead_functions_removed;
+        contract.functions.retain(|f| {
+            let keep = reachable.contains(&f.name);
+            if !keep {
+                removed.push(f.name.clone());
+            }
+            keep
+        });
+    }
+
+    /// Drops events that are never emitted by any function
+    fn eliminate_dead_events(&mut self, contract: &mut Contract) {
+        let mut emitted = HashSet::new();
+        for func in &contract.functions {
+            collect_emits(&func.body, &mut emitted);
+        }
+
+        let removed = &mut self.report.dead_events_removed;
+        contract.events.retain(|e| {
+            let keep = emitted.contains(&e.name);
+            if !keep {
+                removed.push(e.name.clone());
+            }
+            keep
+        });
     }
-    
+
     fn is_constant(&self, expr: &Expression) -> bool {
-        matches!(expr, Expression::Number(_) | Expression::Bool(_) | Expression::String(_))
+        matches!(expr, Expression::Number(_) | Expression::TypedNumber { .. } | Expression::Bool(_) | Expression::String(_))
     }
-    
+
     fn is_no_op(&self, expr: &Expression) -> bool {
         // Identify expressions that have no effect
-        matches!(expr, Expression::Number(_) | Expression::Bool(_) | Expression::String(_))
+        self.is_constant(expr)
+    }
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `x / 2^k` -> `x >> k`, `x % 2^k` -> `x & (2^k - 1)`. `x * 2^k` stays a
+/// multiplication: a shift drops the overflow check, and neither backend's `<<` aborts.
+fn reduce_strength(op: &BinaryOp, left: &Expression, right: &Expression) -> Option<Expression> {
+    let shift = |op: BinaryOp, expr: &Expression, n: u64| Expression::Binary {
+        op,
+        left: Box::new(expr.clone()),
+        right: Box::new(Expression::Number(n.trailing_zeros() as u64)),
+    };
+
+    match (op, left, right) {
+        // x / 1 is left to the simplifier
+        (BinaryOp::Div, expr, Expression::Number(n)) if *n > 1 && n.is_power_of_two() => {
+            Some(shift(BinaryOp::Shr, expr, *n))
+        }
+        (BinaryOp::Mod, expr, Expression::Number(n)) if n.is_power_of_two() => {
+            Some(Expression::Binary {
+                op: BinaryOp::BitAnd,
+                left: Box::new(expr.clone()),
+                right: Box::new(Expression::Number(n - 1)),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn fold_bool_op(op: &BinaryOp, left: bool, right: bool) -> Option<Expression> {
+    match op {
+        BinaryOp::Eq => Some(Expression::Bool(left == right)),
+        BinaryOp::Ne => Some(Expression::Bool(left != right)),
+        BinaryOp::And => Some(Expression::Bool(left && right)),
+        BinaryOp::Or => Some(Expression::Bool(left || right)),
+        _ => None,
+    }
+}
+
+fn fold_string_op(op: &BinaryOp, left: &str, right: &str) -> Option<Expression> {
+    match op {
+        BinaryOp::Eq => Some(Expression::Bool(left == right)),
+        BinaryOp::Ne => Some(Expression::Bool(left != right)),
+        _ => None,
+    }
+}
+
+/// Evaluates an integer expression built from literals and known constants
+fn const_eval(expr: &Expression, known: &HashMap<String, u64>) -> Option<u64> {
+    match expr {
+        Expression::Number(n) => Some(*n),
+        Expression::Identifier(name) => known.get(name).copied(),
+        Expression::Binary { op, left, right } => {
+            let l = const_eval(left, known)?;
+            let r = const_eval(right, known)?;
+            match op {
+                BinaryOp::Add => l.checked_add(r),
+                BinaryOp::Sub => l.checked_sub(r),
+                BinaryOp::Mul => l.checked_mul(r),
+                BinaryOp::Div => l.checked_div(r),
+                BinaryOp::Mod => l.checked_rem(r),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Enforces `Effect::Barrier` for every pass: whatever `optimize_statement` did, the
+/// barrier survives with its events in the same order, or the original is kept
+fn keep_barrier(original: Statement, optimized: Option<Statement>) -> Option<Statement> {
+    match (&original, optimized) {
+        // A check proven to always pass, or an `if` whose condition folded to false
+        (Statement::Require { .. } | Statement::Assert { .. } | Statement::If { .. }, None) => None,
+        (_, None) => Some(original),
+        // Folding the condition may drop the dead branch's events
+        (Statement::If { .. }, Some(stmt)) => Some(stmt),
+        (_, Some(stmt)) => {
+            let mut before = Vec::new();
+            collect_emits(std::slice::from_ref(&original), &mut before);
+            let mut after = Vec::new();
+            collect_emits(std::slice::from_ref(&stmt), &mut after);
+
+            if before == after {
+                Some(stmt)
+            } else {
+                Some(original)
+            }
+        }
+    }
+}
+
+fn contains_check(statements: &[Statement]) -> bool {
+    statements.iter().any(|stmt| match stmt {
+        Statement::Require { .. } | Statement::Assert { .. } => true,
+        Statement::If { then_block, else_block, .. } => {
+            contains_check(then_block) || else_block.as_deref().is_some_and(contains_check)
+        }
+        Statement::While { body, .. } | Statement::ForEach { body, .. } | Statement::Block(body) => {
+            contains_check(body)
+        }
+        Statement::For { body, .. } => contains_check(body),
+        Statement::Match { arms, .. } => arms.iter().any(|arm| contains_check(&arm.body)),
+        _ => false,
+    })
+}
+
+fn contains_loop_exit(statements: &[Statement]) -> bool {
+    statements.iter().any(|stmt| match stmt {
+        Statement::Break | Statement::Continue => true,
+        Statement::If { then_block, else_block, .. } => {
+            contains_loop_exit(then_block) || else_block.as_deref().is_some_and(contains_loop_exit)
+        }
+        Statement::Block(body) => contains_loop_exit(body),
+        Statement::Match { arms, .. } => arms.iter().any(|arm| contains_loop_exit(&arm.body)),
+        // A break inside an inner loop only exits that loop
+        _ => false,
+    })
+}
+
+fn is_atomic(expr: &Expression) -> bool {
+    matches!(expr,
+        Expression::Number(_) | Expression::TypedNumber { .. } | Expression::Bool(_) | Expression::String(_)
+            | Expression::Identifier(_))
+}
+
+/// Worth sharing: a non-trivial expression with no calls (and so no side effects)
+fn is_cse_candidate(expr: &Expression) -> bool {
+    matches!(expr, Expression::Binary { .. } | Expression::Unary { .. } | Expression::Index { .. })
+        && is_pure(expr)
+}
+
+/// No calls anywhere inside, so evaluating it can be skipped without changing behavior
+fn is_pure(expr: &Expression) -> bool {
+    match expr {
+        Expression::Call { .. } | Expression::MethodCall { .. } | Expression::Lambda { .. } => false,
+        Expression::Binary { left, right, .. } => is_pure(left) && is_pure(right),
+        Expression::Unary { expr, .. } | Expression::Cast { expr, .. } | Expression::OptionSome(expr) => is_pure(expr),
+        Expression::Ternary { condition, then_expr, else_expr } => {
+            is_pure(condition) && is_pure(then_expr) && is_pure(else_expr)
+        }
+        Expression::Index { array, index } => is_pure(array) && is_pure(index),
+        Expression::Field { object, .. } => is_pure(object),
+        Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => items.iter().all(is_pure),
+        Expression::StructLiteral { fields, .. } => fields.values().all(is_pure),
+        _ => true,
+    }
+}
+
+/// The part of an expression statement that still has to run once its value is discarded
+fn effects_of(expr: Expression) -> Expression {
+    match expr {
+        // `f() && false`, `f() || true`: the constant decides, but `f()` runs first
+        Expression::Binary { op: BinaryOp::And, left, right } if *right == Expression::Bool(false) => effects_of(*left),
+        Expression::Binary { op: BinaryOp::Or, left, right } if *right == Expression::Bool(true) => effects_of(*left),
+        Expression::Binary { op: BinaryOp::Mul, left, right } if *right == Expression::Number(0) => effects_of(*left),
+        Expression::Binary { op: BinaryOp::Mul, left, right } if *left == Expression::Number(0) => effects_of(*right),
+        other => other,
+    }
+}
+
+/// Whether any declaration, `let`, cast or literal of the contract has a signed integer
+/// type. Negating a value doesn't make it signed; in an unsigned contract that's an error.
+fn uses_signed_integers(contract: &Contract) -> bool {
+    fn signed(ty: &Type) -> bool {
+        match ty {
+            Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128 => true,
+            Type::Map(key, value) | Type::Result(key, value) => signed(key) || signed(value),
+            Type::Vec(inner) | Type::Array(inner, _) | Type::Option(inner) => signed(inner),
+            Type::Tuple(items) => items.iter().any(signed),
+            _ => false,
+        }
+    }
+
+    let declared = contract.state.iter().map(|v| &v.ty)
+        .chain(contract.constants.iter().map(|c| &c.ty))
+        .chain(contract.structs.iter().flat_map(|s| s.fields.iter().map(|f| &f.ty)))
+        .chain(contract.events.iter().flat_map(|e| e.params.iter().map(|p| &p.ty)))
+        .chain(contract.modifiers.iter().flat_map(|m| m.params.iter().map(|p| &p.ty)))
+        .chain(contract.functions.iter().flat_map(|f| f.params.iter().map(|p| &p.ty).chain(&f.return_type)));
+    let mut found = declared.into_iter().any(signed);
+    let bodies = contract.functions.iter().map(|f| &f.body).chain(contract.modifiers.iter().map(|m| &m.body));
+    for body in bodies {
+        visit_statements(body, &mut |stmt| {
+            if let Statement::Let { ty: Some(ty), .. } = stmt {
+                found |= signed(ty);
+            }
+        });
+        visit_expressions(body, &mut |expr| found |= match expr {
+            Expression::Cast { ty, .. } | Expression::TypedNumber { ty, .. } => signed(ty),
+            _ => false,
+        });
+    }
+    found
+}
+
+/// Records `condition` and, for `a && b`, each side as holding from here on
+fn establish(condition: &Expression, unsigned: bool, established: &mut Vec<Expression>) {
+    match condition {
+        Expression::Binary { op: BinaryOp::And, left, right } => {
+            establish(left, unsigned, established);
+            establish(right, unsigned, established);
+        }
+        // On unsigned integers `x > 0` and `0 < x` mean `x != 0`
+        Expression::Binary { op: BinaryOp::Gt, left: value, right: zero }
+        | Expression::Binary { op: BinaryOp::Lt, left: zero, right: value }
+            if unsigned && **zero == Expression::Number(0) =>
+        {
+            established.push(Expression::Binary {
+                op: BinaryOp::Ne,
+                left: value.clone(),
+                right: zero.clone(),
+            });
+        }
+        _ => {}
+    }
+    established.push(condition.clone());
+}
+
+/// The condition that holds exactly when `condition` doesn't, if it has a direct form
+fn negate(condition: &Expression) -> Option<Expression> {
+    match condition {
+        Expression::Unary { op: crate::UnaryOp::Not, expr } => Some((**expr).clone()),
+        Expression::Binary { op, left, right } => {
+            let negated = match op {
+                BinaryOp::Eq => BinaryOp::Ne,
+                BinaryOp::Ne => BinaryOp::Eq,
+                BinaryOp::Lt => BinaryOp::Ge,
+                BinaryOp::Ge => BinaryOp::Lt,
+                BinaryOp::Gt => BinaryOp::Le,
+                BinaryOp::Le => BinaryOp::Gt,
+                _ => return None,
+            };
+            Some(Expression::Binary { op: negated, left: left.clone(), right: right.clone() })
+        }
+        Expression::Bool(_) => None,
+        other => Some(Expression::Unary { op: crate::UnaryOp::Not, expr: Box::new(other.clone()) }),
+    }
+}
+
+fn lvalue_root(lvalue: &LValue) -> &str {
+    match lvalue {
+        LValue::Identifier(name) => name,
+        LValue::Index { array, .. } => lvalue_root(array),
+        LValue::Field { object, .. } => lvalue_root(object),
     }
-    
-    fn report_stats(&self) {
-        println!("✨ Optimization complete:");
-        println!("   - Dead code removed: {} statements", self.dead_code_removed);
-        println!("   - Constants folded: {} expressions", self.constants_folded);
-        println!("   - Expressions simplified: {} operations", self.expressions_simplified);
+}
+
+fn expression_reads(expr: &Expression, name: &str) -> bool {
+    match expr {
+        Expression::Identifier(id) => id == name,
+        Expression::Binary { left, right, .. } => expression_reads(left, name) || expression_reads(right, name),
+        Expression::Unary { expr, .. } | Expression::Cast { expr, .. } | Expression::OptionSome(expr) => expression_reads(expr, name),
+        Expression::Index { array, index } => expression_reads(array, name) || expression_reads(index, name),
+        Expression::Field { object, .. } => expression_reads(object, name),
+        Expression::Ternary { condition, then_expr, else_expr } => {
+            expression_reads(condition, name) || expression_reads(then_expr, name) || expression_reads(else_expr, name)
+        }
+        Expression::Call { args, .. } => args.iter().any(|a| expression_reads(a, name)),
+        Expression::MethodCall { object, args, .. } => {
+            expression_reads(object, name) || args.iter().any(|a| expression_reads(a, name))
+        }
+        Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => {
+            items.iter().any(|i| expression_reads(i, name))
+        }
+        Expression::StructLiteral { fields, .. } => fields.values().any(|v| expression_reads(v, name)),
+        _ => false,
+    }
+}
+
+fn expression_size(expr: &Expression) -> usize {
+    1 + match expr {
+        Expression::Binary { left, right, .. } => expression_size(left) + expression_size(right),
+        Expression::Unary { expr, .. } | Expression::Cast { expr, .. } | Expression::OptionSome(expr) => expression_size(expr),
+        Expression::Index { array, index } => expression_size(array) + expression_size(index),
+        Expression::Field { object, .. } => expression_size(object),
+        Expression::Ternary { condition, then_expr, else_expr } => {
+            expression_size(condition) + expression_size(then_expr) + expression_size(else_expr)
+        }
+        Expression::Call { func, args } => expression_size(func) + args.iter().map(expression_size).sum::<usize>(),
+        Expression::MethodCall { object, args, .. } => {
+            expression_size(object) + args.iter().map(expression_size).sum::<usize>()
+        }
+        Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => {
+            items.iter().map(expression_size).sum()
+        }
+        Expression::StructLiteral { fields, .. } => fields.values().map(expression_size).sum(),
+        _ => 0,
     }
-}
\ No newline at end of file
+}
+
+/// Replaces identifiers bound in `bindings` throughout `expr`
+fn substitute(expr: &Expression, bindings: &HashMap<String, Expression>) -> Expression {
+    let sub = |e: &Expression| Box::new(substitute(e, bindings));
+
+    match expr {
+        Expression::Identifier(name) => bindings.get(name).cloned().unwrap_or_else(|| expr.clone()),
+        Expression::Binary { op, left, right } => Expression::Binary {
+            op: op.clone(),
+            left: sub(left),
+            right: sub(right),
+        },
+        Expression::Unary { op, expr } => Expression::Unary { op: op.clone(), expr: sub(expr) },
+        Expression::Cast { expr, ty } => Expression::Cast { expr: sub(expr), ty: ty.clone() },
+        Expression::OptionSome(value) => Expression::OptionSome(sub(value)),
+        Expression::Ternary { condition, then_expr, else_expr } => Expression::Ternary {
+            condition: sub(condition),
+            then_expr: sub(then_expr),
+            else_expr: sub(else_expr),
+        },
+        Expression::Call { func, args } => Expression::Call {
+            func: func.clone(),
+            args: args.iter().map(|a| substitute(a, bindings)).collect(),
+        },
+        Expression::MethodCall { object, method, args } => Expression::MethodCall {
+            object: sub(object),
+            method: method.clone(),
+            args: args.iter().map(|a| substitute(a, bindings)).collect(),
+        },
+        Expression::Index { array, index } => Expression::Index { array: sub(array), index: sub(index) },
+        Expression::Field { object, field } => Expression::Field { object: sub(object), field: field.clone() },
+        Expression::ArrayLiteral(items) => {
+            Expression::ArrayLiteral(items.iter().map(|i| substitute(i, bindings)).collect())
+        }
+        Expression::TupleLiteral(items) => {
+            Expression::TupleLiteral(items.iter().map(|i| substitute(i, bindings)).collect())
+        }
+        Expression::StructLiteral { name, fields } => Expression::StructLiteral {
+            name: name.clone(),
+            fields: fields.iter().map(|(k, v)| (k.clone(), substitute(v, bindings))).collect(),
+        },
+        _ => expr.clone(),
+    }
+}
+
+fn substitute_lvalue(lvalue: &LValue, bindings: &HashMap<String, Expression>) -> LValue {
+    match lvalue {
+        // Only a rename can stand where the variable is assigned
+        LValue::Identifier(name) => match bindings.get(name) {
+            Some(Expression::Identifier(renamed)) => LValue::Identifier(renamed.clone()),
+            _ => lvalue.clone(),
+        },
+        LValue::Index { array, index } => LValue::Index {
+            array: Box::new(substitute_lvalue(array, bindings)),
+            index: Box::new(substitute(index, bindings)),
+        },
+        LValue::Field { object, field } => LValue::Field {
+            object: Box::new(substitute_lvalue(object, bindings)),
+            field: field.clone(),
+        },
+    }
+}
+
+/// `substitute` lifted over statements; bindings are never assignment targets, unless
+/// they rename the variable
+pub(crate) fn substitute_statement(stmt: &Statement, bindings: &HashMap<String, Expression>) -> Statement {
+    let block = |stmts: &Vec<Statement>| stmts.iter().map(|s| substitute_statement(s, bindings)).collect();
+
+    match stmt {
+        Statement::Let { name, ty, value, is_mutable } => Statement::Let {
+            name: name.clone(),
+            ty: ty.clone(),
+            value: substitute(value, bindings),
+            is_mutable: *is_mutable,
+        },
+        Statement::LetTuple { names, value } => Statement::LetTuple {
+            names: names.clone(),
+            value: substitute(value, bindings),
+        },
+        Statement::Assign { target, value } => Statement::Assign {
+            target: substitute_lvalue(target, bindings),
+            value: substitute(value, bindings),
+        },
+        Statement::If { condition, then_block, else_block } => Statement::If {
+            condition: substitute(condition, bindings),
+            then_block: block(then_block),
+            else_block: else_block.as_ref().map(block),
+        },
+        Statement::While { condition, body } => Statement::While {
+            condition: substitute(condition, bindings),
+            body: block(body),
+        },
+        Statement::For { init, condition, update, body } => Statement::For {
+            init: Box::new(substitute_statement(init, bindings)),
+            condition: substitute(condition, bindings),
+            update: Box::new(substitute_statement(update, bindings)),
+            body: block(body),
+        },
+        Statement::ForEach { variable, iterable, body } => Statement::ForEach {
+            variable: variable.clone(),
+            iterable: substitute(iterable, bindings),
+            body: block(body),
+        },
+        Statement::Require { condition, message } => Statement::Require {
+            condition: substitute(condition, bindings),
+            message: message.clone(),
+        },
+        Statement::Assert { condition, message } => Statement::Assert {
+            condition: substitute(condition, bindings),
+            message: message.clone(),
+        },
+        Statement::Emit { event, args } => Statement::Emit {
+            event: event.clone(),
+            args: args.iter().map(|a| substitute(a, bindings)).collect(),
+        },
+        Statement::Return { value } => Statement::Return {
+            value: value.as_ref().map(|v| substitute(v, bindings)),
+        },
+        Statement::Match { value, arms } => Statement::Match {
+            value: substitute(value, bindings),
+            arms: arms.iter()
+                .map(|arm| MatchArm { pattern: arm.pattern.clone(), body: block(&arm.body) })
+                .collect(),
+        },
+        Statement::Expression(expr) => Statement::Expression(substitute(expr, bindings)),
+        Statement::Block(body) => Statement::Block(block(body)),
+        Statement::Break | Statement::Continue | Statement::Placeholder => stmt.clone(),
+    }
+}
+
+pub(crate) fn collect_assigned(statements: &[Statement], assigned: &mut HashSet<String>) {
+    for stmt in statements {
+        match stmt {
+            Statement::Assign { target, .. } => {
+                assigned.insert(lvalue_root(target).to_string());
+            }
+            Statement::Let { name, .. } => {
+                assigned.insert(name.clone());
+            }
+            Statement::LetTuple { names, .. } => {
+                assigned.extend(names.iter().cloned());
+            }
+            Statement::If { then_block, else_block, .. } => {
+                collect_assigned(then_block, assigned);
+                if let Some(else_stmts) = else_block {
+                    collect_assigned(else_stmts, assigned);
+                }
+            }
+            Statement::While { body, .. } | Statement::ForEach { body, .. } | Statement::Block(body) => {
+                collect_assigned(body, assigned);
+            }
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    collect_assigned(&arm.body, assigned);
+                }
+            }
+            Statement::For { init, update, body, .. } => {
+                collect_assigned(std::slice::from_ref(init.as_ref()), assigned);
+                collect_assigned(std::slice::from_ref(update.as_ref()), assigned);
+                collect_assigned(body, assigned);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether `statements` declare locals of their own scope
+fn declares_locals(statements: &[Statement]) -> bool {
+    statements.iter().any(|s| matches!(s, Statement::Let { .. } | Statement::LetTuple { .. }))
+}
+
+/// Like `collect_assigned`, but leaves out the block's own locals once declared,
+/// since they go out of scope with it
+fn collect_outer_assigned(statements: &[Statement], assigned: &mut HashSet<String>) {
+    let mut locals = HashSet::new();
+    for stmt in statements {
+        match stmt {
+            Statement::Let { name, .. } => {
+                locals.insert(name.clone());
+            }
+            Statement::LetTuple { names, .. } => {
+                locals.extend(names.iter().cloned());
+            }
+            _ => {
+                let mut inner = HashSet::new();
+                collect_assigned(std::slice::from_ref(stmt), &mut inner);
+                assigned.extend(inner.into_iter().filter(|name| !locals.contains(name)));
+            }
+        }
+    }
+}
+
+/// Names bound by `let`, `for` and `for ... in` anywhere in `statements`
+pub(crate) fn collect_bindings(statements: &[Statement], bindings: &mut HashSet<String>) {
+    for stmt in statements {
+        match stmt {
+            Statement::Let { name, .. } => {
+                bindings.insert(name.clone());
+            }
+            Statement::LetTuple { names, .. } => {
+                bindings.extend(names.iter().cloned());
+            }
+            Statement::If { then_block, else_block, .. } => {
+                collect_bindings(then_block, bindings);
+                if let Some(else_stmts) = else_block {
+                    collect_bindings(else_stmts, bindings);
+                }
+            }
+            Statement::ForEach { variable, body, .. } => {
+                bindings.insert(variable.clone());
+                collect_bindings(body, bindings);
+            }
+            Statement::While { body, .. } | Statement::Block(body) => collect_bindings(body, bindings),
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    collect_bindings(&arm.body, bindings);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                collect_bindings(std::slice::from_ref(init.as_ref()), bindings);
+                collect_bindings(body, bindings);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Names of all functions called (directly) anywhere in `statements`. Calls of other
+/// contracts and of methods such as `push` that aren't [`Expression::VALUE_METHODS`] are
+/// recorded as `.method`, which no function is named, so that they count as calls too.
+fn collect_calls(statements: &[Statement], calls: &mut HashSet<String>) {
+    for stmt in statements {
+        match stmt {
+            Statement::Let { value, .. } | Statement::LetTuple { value, .. } | Statement::Assign { value, .. } => {
+                collect_calls_in_expression(value, calls);
+            }
+            Statement::If { condition, then_block, else_block } => {
+                collect_calls_in_expression(condition, calls);
+                collect_calls(then_block, calls);
+                if let Some(else_stmts) = else_block {
+                    collect_calls(else_stmts, calls);
+                }
+            }
+            Statement::While { condition, body } => {
+                collect_calls_in_expression(condition, calls);
+                collect_calls(body, calls);
+            }
+            Statement::For { init, condition, update, body } => {
+                collect_calls(std::slice::from_ref(init.as_ref()), calls);
+                collect_calls_in_expression(condition, calls);
+                collect_calls(std::slice::from_ref(update.as_ref()), calls);
+                collect_calls(body, calls);
+            }
+            Statement::ForEach { iterable, body, .. } => {
+                collect_calls_in_expression(iterable, calls);
+                collect_calls(body, calls);
+            }
+            Statement::Require { condition, .. } | Statement::Assert { condition, .. } => {
+                collect_calls_in_expression(condition, calls);
+            }
+            Statement::Emit { args, .. } => {
+                for arg in args {
+                    collect_calls_in_expression(arg, calls);
+                }
+            }
+            Statement::Return { value: Some(value) } | Statement::Expression(value) => {
+                collect_calls_in_expression(value, calls);
+            }
+            Statement::Match { value, arms } => {
+                collect_calls_in_expression(value, calls);
+                for arm in arms {
+                    collect_calls(&arm.body, calls);
+                }
+            }
+            Statement::Block(body) => collect_calls(body, calls),
+            _ => {}
+        }
+    }
+}
+
+/// Names of all events emitted anywhere in `statements`, in source order
+fn collect_emits(statements: &[Statement], emitted: &mut impl Extend<String>) {
+    for stmt in statements {
+        match stmt {
+            Statement::Emit { event, .. } => {
+                emitted.extend(std::iter::once(event.clone()));
+            }
+            Statement::If { then_block, else_block, .. } => {
+                collect_emits(then_block, emitted);
+                if let Some(else_stmts) = else_block {
+                    collect_emits(else_stmts, emitted);
+                }
+            }
+            Statement::While { body, .. } | Statement::ForEach { body, .. } | Statement::Block(body) => {
+                collect_emits(body, emitted);
+            }
+            Statement::For { init, update, body, .. } => {
+                collect_emits(std::slice::from_ref(init.as_ref()), emitted);
+                collect_emits(std::slice::from_ref(update.as_ref()), emitted);
+                collect_emits(body, emitted);
+            }
+            Statement::Match { arms, .. } => {
+                for arm in arms {
+                    collect_emits(&arm.body, emitted);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_calls_in_expression(expr: &Expression, calls: &mut HashSet<String>) {
+    match expr {
+        Expression::Call { func, args } => {
+            if let Expression::Identifier(name) = func.as_ref() {
+                calls.insert(name.clone());
+            }
+            for arg in args {
+                collect_calls_in_expression(arg, calls);
+            }
+        }
+        Expression::Binary { left, right, .. } => {
+            collect_calls_in_expression(left, calls);
+            collect_calls_in_expression(right, calls);
+        }
+        Expression::Unary { expr, .. } | Expression::Cast { expr, .. } | Expression::OptionSome(expr) => collect_calls_in_expression(expr, calls),
+        Expression::Ternary { condition, then_expr, else_expr } => {
+            collect_calls_in_expression(condition, calls);
+            collect_calls_in_expression(then_expr, calls);
+            collect_calls_in_expression(else_expr, calls);
+        }
+        Expression::MethodCall { object, method, args } => {
+            if !expr.is_value_method() {
+                calls.insert(format!(".{}", method));
+            }
+            collect_calls_in_expression(object, calls);
+            for arg in args {
+                collect_calls_in_expression(arg, calls);
+            }
+        }
+        Expression::Index { array, index } => {
+            collect_calls_in_expression(array, calls);
+            collect_calls_in_expression(index, calls);
+        }
+        Expression::Field { object, .. } => collect_calls_in_expression(object, calls),
+        Expression::ArrayLiteral(items) | Expression::TupleLiteral(items) => {
+            for item in items {
+                collect_calls_in_expression(item, calls);
+            }
+        }
+        Expression::StructLiteral { fields, .. } => {
+            for value in fields.values() {
+                collect_calls_in_expression(value, calls);
+            }
+        }
+        Expression::Lambda { body, .. } => collect_calls_in_expression(body, calls),
+        _ => {}
+    }
+}
@@ -2,15 +2,267 @@ use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use anyhow::{Result, anyhow, Context};
 use crate::{
-    Rule, Contract, StateVariable, Function, Visibility, Parameter, Type, 
-    Statement, Expression, BinaryOp, LValue
+    Rule, Program, Import, Contract, Bridge, ExternalContract, ExternalFunction, Constant, EventDefinition, EventParam, Modifier, ModifierCall, StateVariable, StructDefinition, StructField, Function, Visibility, Parameter, Type, 
+    Statement, Expression, BinaryOp, UnaryOp, LValue, MatchArm, Pattern
 };
 
-pub fn parse_contract_from_pairs(mut pairs: Pairs<Rule>) -> Result<Contract> {
+pub fn parse_contract_from_pairs(pairs: Pairs<Rule>) -> Result<Contract> {
+    let mut program = parse_program_from_pairs(pairs)?;
+    
+    if !program.imports.is_empty() {
+        return Err(anyhow!("Imports can only be resolved when loading a file with Program::load"));
+    }
+    
+    if !program.externals.is_empty() {
+        return Err(anyhow!("External contracts can only be declared in files loaded with Program::parse"));
+    }
+    
+    // Never drop the other contracts of a multi-contract file silently
+    if program.contracts.len() > 1 {
+        let names: Vec<&str> = program.contracts.iter().map(|c| c.name.as_str()).collect();
+        return Err(anyhow!(
+            "Expected a single contract but found {} ({}); use Program::parse for multi-contract files",
+            names.len(), names.join(", ")
+        ));
+    }
+    
+    Ok(program.contracts.remove(0))
+}
+
+/// A pest syntax error with the grammar's rule names replaced by what was expected in
+/// words; an error at a `,` says the comma is the problem, e.g. `fn f(,)` or `g(a,,b)`
+pub(crate) fn syntax_error(error: pest::error::Error<Rule>, input: &str) -> pest::error::Error<Rule> {
+    let stray_comma = match error.location {
+        pest::error::InputLocation::Pos(pos) => input[pos..].starts_with(','),
+        pest::error::InputLocation::Span(_) => false,
+    };
+    let mut error = error.renamed_rules(|rule| match rule {
+        Rule::identifier => "a name".to_string(),
+        Rule::expression | Rule::unary => "an expression".to_string(),
+        Rule::param => "a parameter `name: type`".to_string(),
+        Rule::event_param => "an event parameter `name: type`".to_string(),
+        Rule::type_spec => "a type".to_string(),
+        Rule::statement => "a statement".to_string(),
+        Rule::block => "a block `{ ... }`".to_string(),
+        Rule::view => "`view`".to_string(),
+        Rule::payable => "`payable`".to_string(),
+        other => format!("{:?}", other),
+    });
+    if stray_comma {
+        if let pest::error::ErrorVariant::CustomError { message } = &mut error.variant {
+            *message = format!("unexpected `,`; {}", message);
+        }
+    }
+    error
+}
+
+/// A syntax error found by [`Contract::parse_recovering`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseDiagnostic {
+    pub message: String,
+    /// Byte range of the source skipped to recover from the error; empty when
+    /// parsing stopped there
+    pub span: std::ops::Range<usize>,
+    /// 1-based line and column where the error was detected
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Parses a single contract, skipping each declaration or statement that fails to
+/// parse and continuing after it, so that every syntax error is reported at once.
+///
+/// A failed region runs from the previous `;`, `{` or `}` to the next `;` (included)
+/// or unmatched `}` (excluded); it is blanked out, keeping line breaks so positions stay valid,
+/// and the file is parsed again. The contract is only returned when the file has no
+/// errors. Never panics, whatever the input.
+pub fn parse_contract_recovering(input: &str) -> (Option<Contract>, Vec<ParseDiagnostic>) {
+    let mut source = input.to_string();
+    let mut diagnostics = Vec::new();
+    
+    loop {
+        let error = match crate::DslParser::parse(Rule::program, &source) {
+            Ok(pairs) => {
+                // Errors found after parsing (duplicate names and the like) have no position
+                return match parse_contract_from_pairs(pairs) {
+                    Ok(contract) if diagnostics.is_empty() => (Some(contract), diagnostics),
+                    Ok(_) => (None, diagnostics),
+                    Err(e) => {
+                        diagnostics.push(ParseDiagnostic { message: e.to_string(), span: 0..0, line: 1, column: 1 });
+                        (None, diagnostics)
+                    }
+                };
+            }
+            Err(error) => syntax_error(error, &source),
+        };
+        
+        let position = match error.location {
+            pest::error::InputLocation::Pos(pos) => pos,
+            pest::error::InputLocation::Span((start, _)) => start,
+        };
+        let (line, column) = match error.line_col {
+            pest::error::LineColLocation::Pos(lc) | pest::error::LineColLocation::Span(lc, _) => lc,
+        };
+        let span = recovery_span(&source, position);
+        diagnostics.push(ParseDiagnostic {
+            message: error.variant.message().to_string(),
+            span: span.clone().unwrap_or(position..position),
+            line,
+            column,
+        });
+        
+        // Nothing left to skip, e.g. a missing `}` at the end of the file
+        let Some(span) = span else {
+            return (None, diagnostics);
+        };
+        let mut bytes = std::mem::take(&mut source).into_bytes();
+        for byte in &mut bytes[span] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+        // The span starts and ends at ASCII characters, so only whole characters were replaced
+        source = String::from_utf8(bytes).expect("blanking whole characters keeps UTF-8 valid");
+    }
+}
+
+/// The region to blank out for an error at `position`; always holds something
+/// other than whitespace so that every retry makes progress
+fn recovery_span(source: &str, position: usize) -> Option<std::ops::Range<usize>> {
+    let bytes = source.as_bytes();
+    let start = bytes[..position].iter()
+        .rposition(|b| matches!(b, b';' | b'{' | b'}'))
+        .map_or(0, |i| i + 1);
+    
+    // Blocks opened after the error are skipped whole, so their `}` can't end the
+    // enclosing declaration early
+    let mut depth = 0;
+    let mut end = None;
+    for (i, byte) in bytes.iter().enumerate().skip(position) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' if depth == 0 => {
+                end = Some(i);
+                break;
+            }
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(i + 1);
+                    break;
+                }
+            }
+            b';' if depth == 0 => {
+                end = Some(i + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let end = end?;
+    
+    if bytes[start..end].iter().all(|b| b.is_ascii_whitespace()) {
+        // The error is at a closing brace with nothing before it, such as a stray `}`
+        return Some(end..end + 1);
+    }
+    Some(start..end)
+}
+
+pub fn parse_program_from_pairs(mut pairs: Pairs<Rule>) -> Result<Program> {
     let pair = pairs.next()
         .ok_or_else(|| anyhow!("No program found"))?;
     
-    parse_contract(pair.into_inner().next().unwrap())
+    let mut contracts: Vec<Contract> = Vec::new();
+    let mut externals: Vec<ExternalContract> = Vec::new();
+    let mut imports = Vec::new();
+    for item in pair.into_inner() {
+        match item.as_rule() {
+            Rule::import_stmt => imports.push(parse_import(item)?),
+            Rule::contract_def => {
+                let contract = parse_contract(item)?;
+                if contracts.iter().any(|c| c.name == contract.name) || externals.iter().any(|e| e.name == contract.name) {
+                    return Err(anyhow!("Duplicate contract '{}'", contract.name));
+                }
+                contracts.push(contract);
+            }
+            Rule::external_def => {
+                let external = parse_external(item)?;
+                if contracts.iter().any(|c| c.name == external.name) || externals.iter().any(|e| e.name == external.name) {
+                    return Err(anyhow!("Duplicate contract '{}'", external.name));
+                }
+                externals.push(external);
+            }
+            _ => {}
+        }
+    }
+    
+    if contracts.is_empty() {
+        return Err(anyhow!("No contract found"));
+    }
+    
+    Ok(Program {
+        contracts,
+        imports,
+        type_definitions: Vec::new(),
+        dependencies: Vec::new(),
+        externals,
+    })
+}
+
+fn parse_import(pair: Pair<Rule>) -> Result<Import> {
+    let mut items = Vec::new();
+    let mut path = None;
+    
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::identifier => items.push(part.as_str().to_string()),
+            Rule::string_lit => path = Some(parse_string_literal(part.as_str())),
+            _ => {}
+        }
+    }
+    
+    Ok(Import {
+        path: path.ok_or_else(|| anyhow!("Missing import path"))?,
+        items,
+    })
+}
+
+fn parse_external(pair: Pair<Rule>) -> Result<ExternalContract> {
+    let mut inner = pair.into_inner();
+    
+    let name = inner.next()
+        .ok_or_else(|| anyhow!("Missing contract name"))?
+        .as_str()
+        .to_string();
+    let address = inner.next()
+        .ok_or_else(|| anyhow!("Missing address of external contract '{}'", name))?;
+    let address = match address.as_rule() {
+        Rule::string_lit => parse_string_literal(address.as_str()),
+        _ => address.as_str().to_string(),
+    };
+    
+    let mut functions: Vec<ExternalFunction> = Vec::new();
+    for item in inner {
+        let mut parts = item.into_inner();
+        let func_name = parts.next()
+            .ok_or_else(|| anyhow!("Missing function name"))?
+            .as_str()
+            .to_string();
+        let mut params = Vec::new();
+        let mut return_type = None;
+        for part in parts {
+            match part.as_rule() {
+                Rule::param_list => params = parse_param_list(part)?,
+                Rule::type_spec => return_type = Some(parse_type(part)?),
+                _ => {}
+            }
+        }
+        if functions.iter().any(|f| f.name == func_name) {
+            return Err(anyhow!("Duplicate function '{}' in external contract '{}'", func_name, name));
+        }
+        functions.push(ExternalFunction { name: func_name, params, return_type });
+    }
+    
+    Ok(ExternalContract { name, address, functions })
 }
 
 fn parse_contract(pair: Pair<Rule>) -> Result<Contract> {
@@ -28,18 +280,33 @@ fn parse_contract(pair: Pair<Rule>) -> Result<Contract> {
     let mut events = Vec::new();
     let mut modifiers = Vec::new();
     let mut constants = Vec::new();
+    let mut bridge = None;
     
     for item in inner {
         match item.as_rule() {
+            Rule::const_def => {
+                constants.push(parse_constant(item)?);
+            }
             Rule::state_section => {
-                state = parse_state_section(item)?;
+                state.extend(parse_state_section(item)?);
             }
-            Rule::function_section => {
-                for func_pair in item.into_inner() {
-                    if func_pair.as_rule() == Rule::function_def {
-                        functions.push(parse_function(func_pair)?);
-                    }
+            Rule::bridge_def => {
+                if bridge.is_some() {
+                    return Err(anyhow!("Contract '{}' declares more than one bridge", name));
                 }
+                bridge = Some(parse_bridge(item)?);
+            }
+            Rule::struct_def => {
+                structs.push(parse_struct(item)?);
+            }
+            Rule::event_def => {
+                events.push(parse_event(item)?);
+            }
+            Rule::modifier_def => {
+                modifiers.push(parse_modifier(item)?);
+            }
+            Rule::function_def => {
+                functions.push(parse_function(item)?);
             }
             _ => {}
         }
@@ -53,45 +320,165 @@ fn parse_contract(pair: Pair<Rule>) -> Result<Contract> {
         events,
         modifiers,
         constants,
+        bridge,
     })
 }
 
+fn parse_bridge(pair: Pair<Rule>) -> Result<Bridge> {
+    let mut vault = false;
+    let mut authority = None;
+    
+    for item in pair.into_inner() {
+        let Some(inner) = item.into_inner().next() else { continue };
+        match inner.as_rule() {
+            Rule::bridge_vault if vault => return Err(anyhow!("Bridge declares `vault` more than once")),
+            Rule::bridge_vault => vault = true,
+            _ => {
+                let var = parse_state_var(inner)?;
+                if var.initial_value.is_some() {
+                    return Err(anyhow!("Bridge authority '{}' is set by init_bridge and can't have an initial value", var.name));
+                }
+                if var.ty != Type::Address {
+                    return Err(anyhow!("Bridge authority '{}' must be an address, found {}", var.name, var.ty));
+                }
+                if authority.is_some() {
+                    return Err(anyhow!("Bridge declares more than one authority"));
+                }
+                authority = Some(var.name);
+            }
+        }
+    }
+    
+    // Both are required: the vault counts what is locked, only the authority may release it
+    if !vault {
+        return Err(anyhow!("Bridge needs a `vault;` to hold locked tokens"));
+    }
+    let authority = authority.ok_or_else(|| anyhow!("Bridge needs an authority, e.g. `authority: address;`"))?;
+    Ok(Bridge { authority })
+}
+
+fn parse_constant(pair: Pair<Rule>) -> Result<Constant> {
+    let mut inner = pair.into_inner();
+    
+    let name = inner.next()
+        .ok_or_else(|| anyhow!("Missing constant name"))?
+        .as_str()
+        .to_string();
+    
+    let ty = parse_type(inner.next()
+        .ok_or_else(|| anyhow!("Missing constant type"))?)?;
+    
+    let value = parse_expression(inner.next()
+        .ok_or_else(|| anyhow!("Missing value for constant '{}'", name))?)?;
+    
+    Ok(Constant { name, ty, value })
+}
+
+fn parse_struct(pair: Pair<Rule>) -> Result<StructDefinition> {
+    let mut inner = pair.into_inner();
+    
+    let name = inner.next()
+        .ok_or_else(|| anyhow!("Missing struct name"))?
+        .as_str()
+        .to_string();
+    
+    let mut fields = Vec::new();
+    for field in inner {
+        let mut parts = field.into_inner();
+        let field_name = parts.next()
+            .ok_or_else(|| anyhow!("Missing field name in struct '{}'", name))?
+            .as_str()
+            .to_string();
+        let ty = parse_type(parts.next()
+            .ok_or_else(|| anyhow!("Missing type for field '{}' of struct '{}'", field_name, name))?)?;
+        fields.push(StructField { name: field_name, ty, is_public: true });
+    }
+    
+    Ok(StructDefinition { name, fields })
+}
+
+fn parse_event(pair: Pair<Rule>) -> Result<EventDefinition> {
+    let mut inner = pair.into_inner();
+    
+    let name = inner.next()
+        .ok_or_else(|| anyhow!("Missing event name"))?
+        .as_str()
+        .to_string();
+    
+    let mut params = Vec::new();
+    for param_pair in inner {
+        let mut indexed = false;
+        let mut name = None;
+        let mut ty = None;
+        
+        for part in param_pair.into_inner() {
+            match part.as_rule() {
+                Rule::indexed => indexed = true,
+                Rule::identifier => name = Some(part.as_str().to_string()),
+                Rule::type_spec => ty = Some(parse_type(part)?),
+                _ => {}
+            }
+        }
+        
+        params.push(EventParam {
+            name: name.ok_or_else(|| anyhow!("Missing event parameter name"))?,
+            ty: ty.ok_or_else(|| anyhow!("Missing event parameter type"))?,
+            indexed,
+        });
+    }
+    
+    Ok(EventDefinition { name, params })
+}
+
 fn parse_state_section(pair: Pair<Rule>) -> Result<Vec<StateVariable>> {
     let mut vars = Vec::new();
     
     for item in pair.into_inner() {
         if item.as_rule() == Rule::state_var {
-            let mut inner = item.into_inner();
-            
-            let name = inner.next()
-                .ok_or_else(|| anyhow!("Missing state variable name"))?
-                .as_str()
-                .to_string();
-            
-            let ty = parse_type(inner.next()
-                .ok_or_else(|| anyhow!("Missing state variable type"))?)?;
-            
-            vars.push(StateVariable { 
-                name, 
-                ty,
-                visibility: Visibility::Private,
-                is_mutable: true,
-                initial_value: None,
-            });
+            vars.push(parse_state_var(item)?);
         }
     }
     
     Ok(vars)
 }
 
+fn parse_state_var(pair: Pair<Rule>) -> Result<StateVariable> {
+    let mut inner = pair.into_inner();
+    
+    let name = inner.next()
+        .ok_or_else(|| anyhow!("Missing state variable name"))?
+        .as_str()
+        .to_string();
+    
+    let ty = parse_type(inner.next()
+        .ok_or_else(|| anyhow!("Missing state variable type"))?)?;
+    
+    let initial_value = inner.next().map(parse_expression).transpose()?;
+    
+    Ok(StateVariable { 
+        name, 
+        ty,
+        visibility: Visibility::Private,
+        is_mutable: true,
+        initial_value,
+    })
+}
+
 fn parse_function(pair: Pair<Rule>) -> Result<Function> {
+    let line = pair.as_span().start_pos().line_col().0;
     let mut inner = pair.into_inner();
     
+    let mut current = inner.next().ok_or_else(|| anyhow!("Empty function"))?;
+    let is_constructor = current.as_rule() == Rule::constructor_attr;
+    if is_constructor {
+        current = inner.next().ok_or_else(|| anyhow!("Missing function after #[constructor]"))?;
+    }
+    
     // Parse visibility
     let mut visibility = Visibility::Private;
-    let mut current = inner.next().ok_or_else(|| anyhow!("Empty function"))?;
     
-    if current.as_rule() == Rule::visibility {
+    let explicit_visibility = current.as_rule() == Rule::visibility;
+    if explicit_visibility {
         visibility = match current.as_str() {
             "public" => Visibility::Public,
             "private" => Visibility::Private,
@@ -102,23 +489,37 @@ fn parse_function(pair: Pair<Rule>) -> Result<Function> {
         current = inner.next().ok_or_else(|| anyhow!("Missing function name"))?;
     }
     
+    // `view fn` is a query for clients and `payable fn` takes a value with the call;
+    // both are public unless declared otherwise
+    let is_view = current.as_rule() == Rule::view;
+    let is_payable = current.as_rule() == Rule::payable;
+    if is_view || is_payable {
+        if !explicit_visibility {
+            visibility = Visibility::Public;
+        }
+        current = inner.next().ok_or_else(|| anyhow!("Missing function name"))?;
+    }
+    
     // Parse function name
+    if current.as_rule() != Rule::identifier {
+        return Err(anyhow!("Missing function name"));
+    }
     let name = current.as_str().to_string();
     
-    // Parse parameters
+    // Parse parameters, modifiers and return type
     let mut params = Vec::new();
-    if let Some(param_list) = inner.next() {
-        if param_list.as_rule() == Rule::param_list {
-            params = parse_param_list(param_list)?;
-        }
-    }
-    
-    // Parse return type
+    let mut modifiers = Vec::new();
     let mut return_type = None;
     let mut body_pair = None;
     
     for item in inner {
         match item.as_rule() {
+            Rule::param_list => {
+                params = parse_param_list(item)?;
+            }
+            Rule::modifier_list => {
+                modifiers = item.into_inner().map(parse_modifier_call).collect::<Result<_>>()?;
+            }
             Rule::type_spec => {
                 return_type = Some(parse_type(item)?);
             }
@@ -141,10 +542,51 @@ fn parse_function(pair: Pair<Rule>) -> Result<Function> {
         name,
         params,
         return_type,
-        modifiers: Vec::new(),
+        modifiers,
+        body,
+        is_payable,
+        is_view,
+        is_constructor,
+        line,
+    })
+}
+
+fn parse_modifier_call(pair: Pair<Rule>) -> Result<ModifierCall> {
+    let mut inner = pair.into_inner();
+    
+    let name = inner.next()
+        .ok_or_else(|| anyhow!("Missing modifier name"))?
+        .as_str()
+        .to_string();
+    
+    let mut args = Vec::new();
+    if let Some(arg_list) = inner.next() {
+        for arg in arg_list.into_inner() {
+            args.push(parse_expression(arg)?);
+        }
+    }
+    
+    Ok(ModifierCall { name, args })
+}
+
+fn parse_modifier(pair: Pair<Rule>) -> Result<Modifier> {
+    let mut name = None;
+    let mut params = Vec::new();
+    let mut body = Vec::new();
+    
+    for item in pair.into_inner() {
+        match item.as_rule() {
+            Rule::identifier => name = Some(item.as_str().to_string()),
+            Rule::param_list => params = parse_param_list(item)?,
+            Rule::block => body = parse_block(item)?,
+            _ => {}
+        }
+    }
+    
+    Ok(Modifier {
+        name: name.ok_or_else(|| anyhow!("Missing modifier name"))?,
+        params,
         body,
-        is_payable: false,
-        is_view: false,
     })
 }
 
@@ -178,13 +620,19 @@ fn parse_type(pair: Pair<Rule>) -> Result<Type> {
     let type_str = pair.as_str();
     let mut inner = pair.into_inner();
     
+    if let Some(named) = inner.peek().filter(|p| p.as_rule() == Rule::struct_type) {
+        return Ok(Type::Struct(named.as_str().to_string()));
+    }
+    
     match type_str {
         "u8" => Ok(Type::U8),
         "u64" => Ok(Type::U64),
         "u128" => Ok(Type::U128),
+        "u256" => Ok(Type::U256),
         "bool" => Ok(Type::Bool),
         "address" => Ok(Type::Address),
-        "string" => Ok(Type::String),
+        _ if type_str.starts_with("string") => Ok(Type::String(parse_max_len(inner.next())?)),
+        _ if type_str.starts_with("bytes") => Ok(Type::Bytes(parse_max_len(inner.next())?)),
         _ if type_str.starts_with("map") => {
             let key_type = parse_type(inner.next()
                 .ok_or_else(|| anyhow!("Missing map key type"))?)?;
@@ -197,10 +645,24 @@ fn parse_type(pair: Pair<Rule>) -> Result<Type> {
                 .ok_or_else(|| anyhow!("Missing vec element type"))?)?;
             Ok(Type::Vec(Box::new(elem_type)))
         }
+        _ if type_str.starts_with("option") => {
+            let inner_type = parse_type(inner.next()
+                .ok_or_else(|| anyhow!("Missing option value type"))?)?;
+            Ok(Type::Option(Box::new(inner_type)))
+        }
+        _ if type_str.starts_with('(') => {
+            let types = inner.map(parse_type).collect::<Result<Vec<_>>>()?;
+            Ok(Type::Tuple(types))
+        }
         _ => Err(anyhow!("Unknown type: {}", type_str))
     }
 }
 
+fn parse_max_len(pair: Option<Pair<Rule>>) -> Result<Option<usize>> {
+    pair.map(|p| p.as_str().parse::<usize>().context("Failed to parse max length"))
+        .transpose()
+}
+
 fn parse_block(pair: Pair<Rule>) -> Result<Vec<Statement>> {
     let mut statements = Vec::new();
     
@@ -219,13 +681,21 @@ fn parse_statement(pair: Pair<Rule>) -> Result<Statement> {
     
     match inner.as_rule() {
         Rule::let_stmt => parse_let_stmt(inner),
+        Rule::let_tuple_stmt => parse_let_tuple_stmt(inner),
         Rule::assign_stmt => parse_assign_stmt(inner),
         Rule::if_stmt => parse_if_stmt(inner),
+        Rule::match_stmt => parse_match_stmt(inner),
+        Rule::while_stmt => parse_while_stmt(inner),
+        Rule::for_stmt => parse_for_stmt(inner),
         Rule::require_stmt => parse_require_stmt(inner),
         Rule::emit_stmt => parse_emit_stmt(inner),
         Rule::return_stmt => parse_return_stmt(inner),
+        Rule::break_stmt => Ok(Statement::Break),
+        Rule::continue_stmt => Ok(Statement::Continue),
+        Rule::placeholder_stmt => Ok(Statement::Placeholder),
         Rule::expr_stmt => {
-            let expr = parse_expression(inner.into_inner().next().unwrap())?;
+            let expr = parse_expression(inner.into_inner().next()
+                .ok_or_else(|| anyhow!("Missing expression"))?)?;
             Ok(Statement::Expression(expr))
         }
         _ => Err(anyhow!("Unknown statement type"))
@@ -233,7 +703,9 @@ fn parse_statement(pair: Pair<Rule>) -> Result<Statement> {
 }
 
 fn parse_let_stmt(pair: Pair<Rule>) -> Result<Statement> {
-    let mut inner = pair.into_inner();
+    let mut inner = pair.into_inner().peekable();
+    
+    let is_mutable = inner.next_if(|p| p.as_rule() == Rule::mutable).is_some();
     
     let name = inner.next()
         .ok_or_else(|| anyhow!("Missing variable name"))?
@@ -247,7 +719,24 @@ fn parse_let_stmt(pair: Pair<Rule>) -> Result<Statement> {
         name, 
         ty: None,
         value,
-        is_mutable: false,
+        is_mutable,
+    })
+}
+
+fn parse_let_tuple_stmt(pair: Pair<Rule>) -> Result<Statement> {
+    let mut names = Vec::new();
+    let mut value = None;
+    
+    for item in pair.into_inner() {
+        match item.as_rule() {
+            Rule::identifier => names.push(item.as_str().to_string()),
+            _ => value = Some(parse_expression(item)?),
+        }
+    }
+    
+    Ok(Statement::LetTuple {
+        names,
+        value: value.ok_or_else(|| anyhow!("Missing tuple value"))?,
     })
 }
 
@@ -304,7 +793,12 @@ fn parse_if_stmt(pair: Pair<Rule>) -> Result<Statement> {
     let then_block = parse_block(inner.next()
         .ok_or_else(|| anyhow!("Missing then block"))?)?;
     
-    let else_block = inner.next().map(|b| parse_block(b)).transpose()?;
+    let else_block = inner.next()
+        .map(|b| match b.as_rule() {
+            Rule::if_stmt => parse_if_stmt(b).map(|elif| vec![elif]),
+            _ => parse_block(b),
+        })
+        .transpose()?;
     
     Ok(Statement::If {
         condition,
@@ -313,6 +807,160 @@ fn parse_if_stmt(pair: Pair<Rule>) -> Result<Statement> {
     })
 }
 
+fn parse_while_stmt(pair: Pair<Rule>) -> Result<Statement> {
+    let mut inner = pair.into_inner();
+    
+    let condition = parse_expression(inner.next()
+        .ok_or_else(|| anyhow!("Missing while condition"))?)?;
+    
+    let body = parse_block(inner.next()
+        .ok_or_else(|| anyhow!("Missing while body"))?)?;
+    
+    Ok(Statement::While { condition, body })
+}
+
+/// `for i in a..b { }` is lowered to the counter form `For { let i = a; i < b; i = i + 1 }`
+fn parse_for_stmt(pair: Pair<Rule>) -> Result<Statement> {
+    let mut inner = pair.into_inner();
+    
+    let variable = inner.next()
+        .ok_or_else(|| anyhow!("Missing loop variable"))?
+        .as_str()
+        .to_string();
+    
+    let start = parse_expression(inner.next()
+        .ok_or_else(|| anyhow!("Missing range start"))?)?;
+    
+    let inclusive = inner.next()
+        .map(|op| op.as_str() == "..=")
+        .unwrap_or(false);
+    
+    let end = parse_expression(inner.next()
+        .ok_or_else(|| anyhow!("Missing range end"))?)?;
+    
+    let body = parse_block(inner.next()
+        .ok_or_else(|| anyhow!("Missing for body"))?)?;
+    
+    let counter = || Box::new(Expression::Identifier(variable.clone()));
+    
+    Ok(Statement::For {
+        init: Box::new(Statement::Let {
+            name: variable.clone(),
+            ty: None,
+            value: start,
+            is_mutable: true,
+        }),
+        condition: Expression::Binary {
+            op: if inclusive { BinaryOp::Le } else { BinaryOp::Lt },
+            left: counter(),
+            right: Box::new(end),
+        },
+        update: Box::new(Statement::Assign {
+            target: LValue::Identifier(variable.clone()),
+            value: Expression::Binary {
+                op: BinaryOp::Add,
+                left: counter(),
+                right: Box::new(Expression::Number(1)),
+            },
+        }),
+        body,
+    })
+}
+
+fn parse_match_stmt(pair: Pair<Rule>) -> Result<Statement> {
+    let mut inner = pair.into_inner();
+    
+    let value = parse_expression(inner.next()
+        .ok_or_else(|| anyhow!("Missing match value"))?)?;
+    
+    let mut arms = Vec::new();
+    for arm_pair in inner {
+        let mut arm = arm_pair.into_inner();
+        
+        let pattern = parse_pattern(arm.next()
+            .ok_or_else(|| anyhow!("Missing match pattern"))?)?;
+        
+        let body_pair = arm.next()
+            .ok_or_else(|| anyhow!("Missing match arm body"))?;
+        let body = match body_pair.as_rule() {
+            Rule::block => parse_block(body_pair)?,
+            Rule::arm_return => vec![parse_return_stmt(body_pair)?],
+            _ => vec![Statement::Expression(parse_expression(body_pair)?)],
+        };
+        
+        arms.push(MatchArm { pattern, body });
+    }
+    
+    Ok(Statement::Match { value, arms })
+}
+
+fn parse_pattern(pair: Pair<Rule>) -> Result<Pattern> {
+    let inner = pair.into_inner().next()
+        .ok_or_else(|| anyhow!("Empty match pattern"))?;
+    
+    match inner.as_rule() {
+        Rule::wildcard_pattern => Ok(Pattern::Wildcard),
+        Rule::range_pattern => {
+            let mut parts = inner.into_inner();
+            let start = parse_pattern_number(parts.next())?;
+            let inclusive = parts.next()
+                .map(|op| op.as_str() == "..=")
+                .unwrap_or(false);
+            let end = parse_pattern_number(parts.next())?;
+            
+            Ok(Pattern::Range { start, end, inclusive })
+        }
+        Rule::literal_pattern => {
+            let literal = inner.into_inner().next()
+                .ok_or_else(|| anyhow!("Empty literal pattern"))?;
+            match literal.as_rule() {
+                Rule::bool_lit => Ok(Pattern::Literal(Expression::Bool(literal.as_str() == "true"))),
+                _ => Ok(Pattern::Literal(Expression::Number(parse_pattern_number(Some(literal))?))),
+            }
+        }
+        _ => Err(anyhow!("Unknown match pattern"))
+    }
+}
+
+fn parse_int_literal(pair: Pair<Rule>) -> Result<Expression> {
+    let mut inner = pair.into_inner();
+    let digits = inner.next()
+        .ok_or_else(|| anyhow!("Missing digits"))?
+        .as_str()
+        .replace('_', "");
+    
+    let Some(suffix) = inner.next() else {
+        let num = digits.parse::<u64>()
+            .with_context(|| format!("Number {} does not fit in u64; add a u128 suffix", digits))?;
+        return Ok(Expression::Number(num));
+    };
+    
+    let value = digits.parse::<u128>()
+        .with_context(|| format!("Number {} does not fit in u128", digits))?;
+    let (ty, max) = match suffix.as_str() {
+        "u8" => (Type::U8, u8::MAX as u128),
+        "u16" => (Type::U16, u16::MAX as u128),
+        "u32" => (Type::U32, u32::MAX as u128),
+        "u64" => (Type::U64, u64::MAX as u128),
+        "u128" => (Type::U128, u128::MAX),
+        // Literal values are stored as u128, so u256 literals share its range
+        "u256" => (Type::U256, u128::MAX),
+        other => return Err(anyhow!("Unknown integer suffix: {}", other)),
+    };
+    if value > max {
+        return Err(anyhow!("Number {} does not fit in {}", digits, suffix.as_str()));
+    }
+    
+    Ok(Expression::TypedNumber { value, ty })
+}
+
+fn parse_pattern_number(pair: Option<Pair<Rule>>) -> Result<u64> {
+    pair.ok_or_else(|| anyhow!("Missing range bound"))?
+        .as_str()
+        .parse::<u64>()
+        .context("Failed to parse number")
+}
+
 fn parse_require_stmt(pair: Pair<Rule>) -> Result<Statement> {
     let mut inner = pair.into_inner();
     
@@ -362,21 +1010,72 @@ fn parse_return_stmt(pair: Pair<Rule>) -> Result<Statement> {
 
 fn parse_expression(pair: Pair<Rule>) -> Result<Expression> {
     match pair.as_rule() {
-        Rule::expression | Rule::logical_or => parse_binary_expr(pair),
+        Rule::expression => parse_conditional(pair),
+        Rule::logical_or => parse_binary_expr(pair),
         Rule::primary => parse_primary(pair),
+        Rule::unary => parse_unary(pair),
+        Rule::cast => parse_cast(pair),
         _ => parse_binary_expr(pair),
     }
 }
 
-fn parse_binary_expr(pair: Pair<Rule>) -> Result<Expression> {
+fn parse_conditional(pair: Pair<Rule>) -> Result<Expression> {
+    let mut inner = pair.into_inner();
+    let condition = parse_expression(inner.next().ok_or_else(|| anyhow!("Empty expression"))?)?;
+    
+    match (inner.next(), inner.next()) {
+        (Some(then_expr), Some(else_expr)) => Ok(Expression::Ternary {
+            condition: Box::new(condition),
+            then_expr: Box::new(parse_expression(then_expr)?),
+            else_expr: Box::new(parse_expression(else_expr)?),
+        }),
+        _ => Ok(condition),
+    }
+}
+
+fn parse_unary(pair: Pair<Rule>) -> Result<Expression> {
     let mut inner = pair.into_inner();
     let first = inner.next().ok_or_else(|| anyhow!("Empty expression"))?;
     
-    let mut left = if first.as_rule() == Rule::primary {
-        parse_primary(first)?
-    } else {
-        parse_binary_expr(first)?
+    if first.as_rule() != Rule::unary_op {
+        return parse_primary(first);
+    }
+    
+    let op = match first.as_str() {
+        "!" => UnaryOp::Not,
+        "-" => UnaryOp::Neg,
+        other => return Err(anyhow!("Unknown unary operator: {}", other)),
     };
+    let operand = inner.next().ok_or_else(|| anyhow!("Missing operand"))?;
+    
+    Ok(Expression::Unary {
+        op,
+        expr: Box::new(parse_primary(operand)?),
+    })
+}
+
+fn parse_cast(pair: Pair<Rule>) -> Result<Expression> {
+    let mut inner = pair.into_inner();
+    let mut expr = parse_expression(inner.next().ok_or_else(|| anyhow!("Empty expression"))?)?;
+    
+    // `x as u64 as u128` applies the casts left to right
+    for item in inner {
+        if item.as_rule() == Rule::type_spec {
+            expr = Expression::Cast {
+                expr: Box::new(expr),
+                ty: parse_type(item)?,
+            };
+        }
+    }
+    
+    Ok(expr)
+}
+
+fn parse_binary_expr(pair: Pair<Rule>) -> Result<Expression> {
+    let mut inner = pair.into_inner();
+    let first = inner.next().ok_or_else(|| anyhow!("Empty expression"))?;
+    
+    let mut left = parse_expression(first)?;
     
     while let Some(op_or_right) = inner.next() {
         // This is simplified - in real implementation, handle operator precedence properly
@@ -400,11 +1099,7 @@ fn parse_primary(pair: Pair<Rule>) -> Result<Expression> {
         .unwrap_or(cloned_pair);
     
     match inner.as_rule() {
-        Rule::number_lit => {
-            let num = inner.as_str().parse::<u64>()
-                .context("Failed to parse number")?;
-            Ok(Expression::Number(num))
-        }
+        Rule::int_lit => parse_int_literal(inner),
         Rule::bool_lit => {
             let b = inner.as_str() == "true";
             Ok(Expression::Bool(b))
@@ -412,21 +1107,118 @@ fn parse_primary(pair: Pair<Rule>) -> Result<Expression> {
         Rule::string_lit => {
             Ok(Expression::String(parse_string_literal(inner.as_str())))
         }
-        Rule::identifier => {
-            let id = inner.as_str();
-            // Check for special identifiers
-            match id {
-                "msg_sender" => Ok(Expression::MsgSender),
-                "msg_value" => Ok(Expression::MsgValue),
-                "block_number" => Ok(Expression::BlockNumber),
-                "block_timestamp" => Ok(Expression::BlockTimestamp),
-                _ => Ok(Expression::Identifier(id.to_string()))
+        Rule::identifier => Ok(parse_identifier(inner.as_str())),
+        Rule::call_expr => {
+            let mut parts = inner.into_inner();
+            let name = parts.next()
+                .ok_or_else(|| anyhow!("Missing function name"))?
+                .as_str();
+            
+            let mut args = Vec::new();
+            if let Some(arg_list) = parts.next() {
+                for arg in arg_list.into_inner() {
+                    args.push(parse_expression(arg)?);
+                }
             }
+            
+            if name == "Some" {
+                let [value]: [Expression; 1] = args.try_into()
+                    .map_err(|_| anyhow!("Some(...) takes exactly one value"))?;
+                return Ok(Expression::OptionSome(Box::new(value)));
+            }
+            
+            // Built-ins such as msg_sender() are accepted with call syntax too
+            let callee = parse_identifier(name);
+            if args.is_empty() && !matches!(callee, Expression::Identifier(_)) {
+                return Ok(callee);
+            }
+            
+            Ok(Expression::Call {
+                func: Box::new(Expression::Identifier(name.to_string())),
+                args,
+            })
         }
+        Rule::method_call_expr => {
+            let mut parts = inner.into_inner().peekable();
+            let mut expr = parse_identifier(parts.next()
+                .ok_or_else(|| anyhow!("Missing call target"))?
+                .as_str());
+            
+            // Each method name is followed by its argument list, if any
+            while let Some(method) = parts.next() {
+                let mut args = Vec::new();
+                if let Some(arg_list) = parts.next_if(|p| p.as_rule() == Rule::arg_list) {
+                    for arg in arg_list.into_inner() {
+                        args.push(parse_expression(arg)?);
+                    }
+                }
+                expr = Expression::MethodCall { object: Box::new(expr), method: method.as_str().to_string(), args };
+            }
+            
+            Ok(expr)
+        }
+        Rule::index_expr => {
+            let mut parts = inner.into_inner();
+            let mut expr = parse_identifier(parts.next()
+                .ok_or_else(|| anyhow!("Missing indexed identifier"))?
+                .as_str());
+            
+            for part in parts {
+                expr = match part.as_rule() {
+                    Rule::identifier => Expression::Field {
+                        object: Box::new(expr),
+                        field: part.as_str().to_string(),
+                    },
+                    _ => Expression::Index {
+                        array: Box::new(expr),
+                        index: Box::new(parse_expression(part)?),
+                    },
+                };
+            }
+            
+            Ok(expr)
+        }
+        Rule::field_expr => {
+            let mut parts = inner.into_inner();
+            let object = parse_identifier(parts.next()
+                .ok_or_else(|| anyhow!("Missing field object"))?
+                .as_str());
+            let field = parts.next()
+                .ok_or_else(|| anyhow!("Missing field name"))?
+                .as_str()
+                .to_string();
+            
+            Ok(Expression::Field { object: Box::new(object), field })
+        }
+        Rule::array_lit => {
+            let items = inner.into_inner()
+                .map(parse_expression)
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Expression::ArrayLiteral(items))
+        }
+        Rule::tuple_lit => {
+            let items = inner.into_inner()
+                .map(parse_expression)
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Expression::TupleLiteral(items))
+        }
+        Rule::expression => parse_expression(inner),
         _ => Err(anyhow!("Unknown primary expression"))
     }
 }
 
+fn parse_identifier(id: &str) -> Expression {
+    // Check for special identifiers
+    match id {
+        "msg_sender" => Expression::MsgSender,
+        "msg_value" => Expression::MsgValue,
+        "block_number" => Expression::BlockNumber,
+        "block_timestamp" => Expression::BlockTimestamp,
+        "None" => Expression::OptionNone,
+        _ => Expression::Identifier(id.to_string())
+    }
+}
+
 fn parse_binary_op(op_str: &str) -> Result<BinaryOp> {
     match op_str {
         "+" => Ok(BinaryOp::Add),
@@ -434,6 +1226,7 @@ fn parse_binary_op(op_str: &str) -> Result<BinaryOp> {
         "*" => Ok(BinaryOp::Mul),
         "/" => Ok(BinaryOp::Div),
         "%" => Ok(BinaryOp::Mod),
+        "**" => Ok(BinaryOp::Pow),
         "==" => Ok(BinaryOp::Eq),
         "!=" => Ok(BinaryOp::Ne),
         "<" => Ok(BinaryOp::Lt),
@@ -442,6 +1235,11 @@ fn parse_binary_op(op_str: &str) -> Result<BinaryOp> {
         ">=" => Ok(BinaryOp::Ge),
         "&&" => Ok(BinaryOp::And),
         "||" => Ok(BinaryOp::Or),
+        "&" => Ok(BinaryOp::BitAnd),
+        "|" => Ok(BinaryOp::BitOr),
+        "^" => Ok(BinaryOp::BitXor),
+        "<<" => Ok(BinaryOp::Shl),
+        ">>" => Ok(BinaryOp::Shr),
         _ => Err(anyhow!("Unknown operator: {}", op_str))
     }
 }
@@ -0,0 +1,526 @@
+//! Prints the AST back as DSL source.
+//!
+//! The output is canonical: declarations come in a fixed order (constants, state,
+//! bridge, structs, events, modifiers, functions), bodies are indented by four
+//! spaces and expressions carry only the parentheses their precedence needs.
+//! Anything the parser produced parses back to the same tree, apart from
+//! `Function::line`; comments are not part of the AST and are lost.
+//!
+//! Nodes the grammar has no syntax for (`assert`, lambdas, struct literals, typed
+//! `let`, ...) are printed in the closest Rust-like form, which does not parse back.
+
+use crate::{
+    BinaryOp, Bridge, Contract, EventDefinition, Expression, ExternalContract, Function, Import, LValue,
+    MatchArm, Modifier, Parameter, Pattern, Program, Statement, Type, UnaryOp, Visibility,
+};
+
+const INDENT: &str = "    ";
+
+// Binding strength of each expression form, loosest first, following grammar.pest
+const TERNARY: u8 = 0;
+const OR: u8 = 1;
+const AND: u8 = 2;
+const EQUALITY: u8 = 3;
+const COMPARISON: u8 = 4;
+const BIT_OR: u8 = 5;
+const BIT_XOR: u8 = 6;
+const BIT_AND: u8 = 7;
+const SHIFT: u8 = 8;
+const ADDITIVE: u8 = 9;
+const MULTIPLICATIVE: u8 = 10;
+const CAST: u8 = 11;
+const POWER: u8 = 12;
+const UNARY: u8 = 13;
+const PRIMARY: u8 = 14;
+
+impl Program {
+    /// The program as DSL source: its imports, external contracts and contracts.
+    /// Imported contracts are not printed, only the imports that bring them in.
+    pub fn to_source(&self) -> String {
+        let mut items: Vec<String> = Vec::new();
+        if !self.imports.is_empty() {
+            items.push(self.imports.iter().map(import_source).collect());
+        }
+        items.extend(self.externals.iter().map(external_source));
+        items.extend(self.contracts.iter().map(Contract::to_source));
+        items.join("\n")
+    }
+}
+
+impl Contract {
+    /// The contract as canonical DSL source; `Contract::parse` of the result gives
+    /// back this contract
+    pub fn to_source(&self) -> String {
+        let mut printer = Printer::default();
+        printer.line(&format!("contract {} {{", self.name));
+        printer.level += 1;
+
+        // Declarations of a kind that fit on one line are grouped, other blocks separated by blank lines
+        let mut sections: Vec<String> = Vec::new();
+        if !self.constants.is_empty() {
+            sections.push(self.constants.iter()
+                .map(|c| format!("const {}: {} = {};\n", c.name, c.ty, expression(&c.value, TERNARY)))
+                .collect());
+        }
+        if !self.state.is_empty() {
+            let fields: Vec<(&str, &Type, Option<&Expression>)> = self.state.iter()
+                .map(|v| (v.name.as_str(), &v.ty, v.initial_value.as_ref()))
+                .collect();
+            sections.push(fields_block("state", &fields));
+        }
+        if let Some(bridge) = &self.bridge {
+            sections.push(bridge_source(bridge));
+        }
+        sections.extend(self.structs.iter().map(|s| {
+            let fields: Vec<(&str, &Type, Option<&Expression>)> = s.fields.iter().map(|f| (f.name.as_str(), &f.ty, None)).collect();
+            fields_block(&format!("struct {}", s.name), &fields)
+        }));
+        if !self.events.is_empty() {
+            sections.push(self.events.iter().map(event_source).collect());
+        }
+        sections.extend(self.modifiers.iter().map(modifier_source));
+        sections.extend(self.functions.iter().map(function_source));
+
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 {
+                printer.out.push('\n');
+            }
+            printer.lines(section);
+        }
+
+        printer.level -= 1;
+        printer.line("}");
+        printer.out
+    }
+}
+
+impl Expression {
+    /// The expression as DSL source, without enclosing parentheses
+    pub fn to_source(&self) -> String {
+        expression(self, TERNARY)
+    }
+}
+
+impl LValue {
+    /// The assignment target as DSL source
+    pub fn to_source(&self) -> String {
+        lvalue(self)
+    }
+}
+
+/// Collects output lines at the current indentation
+#[derive(Default)]
+struct Printer {
+    out: String,
+    level: usize,
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&INDENT.repeat(self.level));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Adds text of several lines, indenting each
+    fn lines(&mut self, text: &str) {
+        for line in text.lines() {
+            if line.is_empty() {
+                self.out.push('\n');
+            } else {
+                self.line(line);
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        self.level += 1;
+        for stmt in statements {
+            self.statement(stmt);
+        }
+        self.level -= 1;
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { name, ty, value, is_mutable } => {
+                let ty = ty.as_ref().map(|t| format!(": {}", t)).unwrap_or_default();
+                let keyword = if *is_mutable { "let mut" } else { "let" };
+                self.line(&format!("{} {}{} = {};", keyword, name, ty, expression(value, TERNARY)));
+            }
+            Statement::LetTuple { names, value } => {
+                self.line(&format!("let ({}) = {};", names.join(", "), expression(value, TERNARY)));
+            }
+            Statement::Assign { target, value } => {
+                self.line(&format!("{} = {};", lvalue(target), expression(value, TERNARY)));
+            }
+            Statement::If { .. } => {
+                let mut prefix = String::new();
+                let mut current = stmt;
+                // `else if` chains are an `if` as the only statement of an else block
+                loop {
+                    let Statement::If { condition, then_block, else_block } = current else { unreachable!() };
+                    self.line(&format!("{}if {} {{", prefix, expression(condition, TERNARY)));
+                    self.block(then_block);
+                    match else_block.as_deref() {
+                        Some([elif @ Statement::If { .. }]) => {
+                            prefix = "} else ".to_string();
+                            current = elif;
+                        }
+                        Some(else_block) => {
+                            self.line("} else {");
+                            self.block(else_block);
+                            self.line("}");
+                            break;
+                        }
+                        None => {
+                            self.line("}");
+                            break;
+                        }
+                    }
+                }
+            }
+            Statement::While { condition, body } => {
+                self.line(&format!("while {} {{", expression(condition, TERNARY)));
+                self.block(body);
+                self.line("}");
+            }
+            Statement::For { init, condition, update, body } => {
+                let header = match range_loop(init, condition, update) {
+                    Some((variable, start, op, end)) => format!("for {} in {}{}{} {{",
+                        variable, expression(start, TERNARY), op, expression(end, TERNARY)),
+                    None => format!("for ({}; {}; {}) {{",
+                        inline_statement(init), expression(condition, TERNARY), inline_statement(update)),
+                };
+                self.line(&header);
+                self.block(body);
+                self.line("}");
+            }
+            Statement::ForEach { variable, iterable, body } => {
+                self.line(&format!("for {} in {} {{", variable, expression(iterable, TERNARY)));
+                self.block(body);
+                self.line("}");
+            }
+            Statement::Require { condition, message } => {
+                self.line(&format!("require({});", checked(condition, message)));
+            }
+            Statement::Assert { condition, message } => {
+                self.line(&format!("assert({});", checked(condition, message)));
+            }
+            Statement::Emit { event, args } => {
+                self.line(&format!("emit {}({});", event, arguments(args)));
+            }
+            Statement::Return { value: Some(value) } => {
+                self.line(&format!("return {};", expression(value, TERNARY)));
+            }
+            Statement::Return { value: None } => self.line("return;"),
+            Statement::Match { value, arms } => {
+                self.line(&format!("match {} {{", expression(value, TERNARY)));
+                self.level += 1;
+                for arm in arms {
+                    self.match_arm(arm);
+                }
+                self.level -= 1;
+                self.line("}");
+            }
+            Statement::Break => self.line("break;"),
+            Statement::Continue => self.line("continue;"),
+            Statement::Placeholder => self.line("_;"),
+            Statement::Expression(expr) => self.line(&format!("{};", expression(expr, TERNARY))),
+            Statement::Block(statements) => {
+                self.line("{");
+                self.block(statements);
+                self.line("}");
+            }
+        }
+    }
+
+    /// A single expression or return is written after the `=>`, anything else as a block
+    fn match_arm(&mut self, arm: &MatchArm) {
+        let pattern = match &arm.pattern {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Range { start, end, inclusive } => format!("{}{}{}", start, if *inclusive { "..=" } else { ".." }, end),
+            Pattern::Literal(value) => expression(value, PRIMARY),
+        };
+        match arm.body.as_slice() {
+            [Statement::Expression(expr)] => self.line(&format!("{} => {},", pattern, expression(expr, TERNARY))),
+            [Statement::Return { value: Some(value) }] => self.line(&format!("{} => return {},", pattern, expression(value, TERNARY))),
+            [Statement::Return { value: None }] => self.line(&format!("{} => return,", pattern)),
+            body => {
+                self.line(&format!("{} => {{", pattern));
+                self.block(body);
+                self.line("}");
+            }
+        }
+    }
+}
+
+fn import_source(import: &Import) -> String {
+    format!("import {{ {} }} from {};\n", import.items.join(", "), string_literal(&import.path))
+}
+
+fn external_source(external: &ExternalContract) -> String {
+    // Program ids and `0x` addresses are written bare, anything else as a string
+    let address = if !external.address.is_empty() && external.address.chars().all(|c| c.is_ascii_alphanumeric()) {
+        external.address.clone()
+    } else {
+        string_literal(&external.address)
+    };
+    let mut printer = Printer::default();
+    printer.line(&format!("external contract {} at {} {{", external.name, address));
+    printer.level += 1;
+    for func in &external.functions {
+        printer.line(&format!("fn {}({}){};", func.name, parameters(&func.params), returns(&func.return_type)));
+    }
+    printer.level -= 1;
+    printer.line("}");
+    printer.out
+}
+
+/// `state { ... }` or `struct Name { ... }`, one `name: type;` (or `name: type = value;`) per line
+fn fields_block(header: &str, fields: &[(&str, &Type, Option<&Expression>)]) -> String {
+    let mut printer = Printer::default();
+    printer.line(&format!("{} {{", header));
+    printer.level += 1;
+    for (name, ty, value) in fields {
+        match value {
+            Some(value) => printer.line(&format!("{}: {} = {};", name, ty, expression(value, TERNARY))),
+            None => printer.line(&format!("{}: {};", name, ty)),
+        }
+    }
+    printer.level -= 1;
+    printer.line("}");
+    printer.out
+}
+
+fn bridge_source(bridge: &Bridge) -> String {
+    format!("bridge {{\n{i}vault;\n{i}{}: address;\n}}\n", bridge.authority, i = INDENT)
+}
+
+fn event_source(event: &EventDefinition) -> String {
+    let params: Vec<String> = event.params.iter()
+        .map(|p| format!("{}{}: {}", if p.indexed { "indexed " } else { "" }, p.name, p.ty))
+        .collect();
+    format!("event {}({});\n", event.name, params.join(", "))
+}
+
+fn modifier_source(modifier: &Modifier) -> String {
+    let mut printer = Printer::default();
+    printer.line(&format!("modifier {}({}) {{", modifier.name, parameters(&modifier.params)));
+    printer.block(&modifier.body);
+    printer.line("}");
+    printer.out
+}
+
+fn function_source(func: &Function) -> String {
+    let mut header = String::new();
+    // `view` and `payable` functions are public unless declared otherwise, the rest private
+    let default = if func.is_view || func.is_payable { Visibility::Public } else { Visibility::Private };
+    if func.visibility != default {
+        header.push_str(match func.visibility {
+            Visibility::Public => "public ",
+            Visibility::Private => "private ",
+            Visibility::Internal => "internal ",
+            Visibility::External => "external ",
+        });
+    }
+    if func.is_view {
+        header.push_str("view ");
+    }
+    if func.is_payable {
+        header.push_str("payable ");
+    }
+    header.push_str(&format!("fn {}({})", func.name, parameters(&func.params)));
+    for modifier in &func.modifiers {
+        header.push(' ');
+        header.push_str(&modifier.name);
+        if !modifier.args.is_empty() {
+            header.push_str(&format!("({})", arguments(&modifier.args)));
+        }
+    }
+    header.push_str(&returns(&func.return_type));
+
+    let mut printer = Printer::default();
+    if func.is_constructor {
+        printer.line("#[constructor]");
+    }
+    printer.line(&format!("{} {{", header));
+    printer.block(&func.body);
+    printer.line("}");
+    printer.out
+}
+
+fn parameters(params: &[Parameter]) -> String {
+    params.iter().map(|p| format!("{}: {}", p.name, p.ty)).collect::<Vec<_>>().join(", ")
+}
+
+fn returns(return_type: &Option<Type>) -> String {
+    return_type.as_ref().map(|t| format!(" -> {}", t)).unwrap_or_default()
+}
+
+fn arguments(args: &[Expression]) -> String {
+    args.iter().map(|a| expression(a, TERNARY)).collect::<Vec<_>>().join(", ")
+}
+
+/// The arguments of `require`/`assert`: the condition and the optional message
+fn checked(condition: &Expression, message: &Option<String>) -> String {
+    match message {
+        Some(message) => format!("{}, {}", expression(condition, TERNARY), string_literal(message)),
+        None => expression(condition, TERNARY),
+    }
+}
+
+/// Recognizes the counter loop `for i in a..b` is parsed into, returning
+/// the variable, the start, the range operator and the end
+pub(crate) fn range_loop<'a>(init: &'a Statement, condition: &'a Expression, update: &Statement)
+    -> Option<(&'a str, &'a Expression, &'static str, &'a Expression)>
+{
+    let Statement::Let { name, ty: None, value: start, is_mutable: true } = init else { return None };
+    let counter = Expression::Identifier(name.clone());
+    let Expression::Binary { op, left, right: end } = condition else { return None };
+    let op = match op {
+        BinaryOp::Lt => "..",
+        BinaryOp::Le => "..=",
+        _ => return None,
+    };
+    let increment = Statement::Assign {
+        target: LValue::Identifier(name.clone()),
+        value: Expression::Binary { op: BinaryOp::Add, left: Box::new(counter.clone()), right: Box::new(Expression::Number(1)) },
+    };
+    (**left == counter && *update == increment).then_some((name.as_str(), start, op, end.as_ref()))
+}
+
+/// A statement inside a C-style `for` header, without its `;`
+fn inline_statement(stmt: &Statement) -> String {
+    let mut printer = Printer::default();
+    printer.statement(stmt);
+    printer.out.trim_end().trim_end_matches(';').to_string()
+}
+
+fn lvalue(target: &LValue) -> String {
+    match target {
+        LValue::Identifier(name) => name.clone(),
+        LValue::Index { array, index } => format!("{}[{}]", lvalue(array), expression(index, TERNARY)),
+        LValue::Field { object, field } => format!("{}.{}", lvalue(object), field),
+    }
+}
+
+/// Writes a string so the parser reads it back unchanged. The grammar has no way to
+/// escape a `"`, so strings containing one cannot be written.
+fn string_literal(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+fn binary_op(op: &BinaryOp) -> (&'static str, u8) {
+    match op {
+        BinaryOp::Or => ("||", OR),
+        BinaryOp::And => ("&&", AND),
+        BinaryOp::Eq => ("==", EQUALITY),
+        BinaryOp::Ne => ("!=", EQUALITY),
+        BinaryOp::Lt => ("<", COMPARISON),
+        BinaryOp::Gt => (">", COMPARISON),
+        BinaryOp::Le => ("<=", COMPARISON),
+        BinaryOp::Ge => (">=", COMPARISON),
+        BinaryOp::BitOr => ("|", BIT_OR),
+        BinaryOp::BitXor => ("^", BIT_XOR),
+        BinaryOp::BitAnd => ("&", BIT_AND),
+        BinaryOp::Shl => ("<<", SHIFT),
+        BinaryOp::Shr => (">>", SHIFT),
+        BinaryOp::Add => ("+", ADDITIVE),
+        BinaryOp::Sub => ("-", ADDITIVE),
+        BinaryOp::Mul => ("*", MULTIPLICATIVE),
+        BinaryOp::Div => ("/", MULTIPLICATIVE),
+        BinaryOp::Mod => ("%", MULTIPLICATIVE),
+        BinaryOp::Pow => ("**", POWER),
+    }
+}
+
+/// Prints `expr`, parenthesized unless it binds at least as tightly as `min`
+fn expression(expr: &Expression, min: u8) -> String {
+    let (text, precedence) = match expr {
+        Expression::Ternary { condition, then_expr, else_expr } => (format!("{} ? {} : {}",
+            expression(condition, OR), expression(then_expr, TERNARY), expression(else_expr, TERNARY)), TERNARY),
+        // `**` is right-associative, every other operator left-associative
+        Expression::Binary { op: BinaryOp::Pow, left, right } => {
+            (format!("{} ** {}", expression(left, UNARY), expression(right, POWER)), POWER)
+        }
+        Expression::Binary { op, left, right } => {
+            let (symbol, precedence) = binary_op(op);
+            (format!("{} {} {}", expression(left, precedence), symbol, expression(right, precedence + 1)), precedence)
+        }
+        Expression::Cast { expr, ty } => (format!("{} as {}", expression(expr, CAST), ty), CAST),
+        // The operand of a unary operator is a primary expression
+        Expression::Unary { op, expr } => {
+            let symbol = match op {
+                UnaryOp::Not => "!",
+                UnaryOp::Neg => "-",
+                UnaryOp::BitNot => "~",
+            };
+            (format!("{}{}", symbol, expression(expr, PRIMARY)), UNARY)
+        }
+        Expression::Lambda { params, body } => (format!("|{}| {}", parameters(params), expression(body, TERNARY)), TERNARY),
+        _ => (primary(expr), PRIMARY),
+    };
+    if precedence < min {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn primary(expr: &Expression) -> String {
+    match expr {
+        Expression::Number(n) => n.to_string(),
+        Expression::TypedNumber { value, ty } => format!("{}{}", value, ty),
+        Expression::Float(f) => f.to_string(),
+        Expression::Bool(b) => b.to_string(),
+        Expression::String(s) => string_literal(s),
+        Expression::Bytes(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("hex\"{}\"", hex)
+        }
+        Expression::Identifier(name) => name.clone(),
+        Expression::Call { func, args } => format!("{}({})", expression(func, PRIMARY), arguments(args)),
+        Expression::MethodCall { object, method, args } => format!("{}.{}({})", receiver(object), method, arguments(args)),
+        Expression::Index { array, index } => format!("{}[{}]", receiver(array), expression(index, TERNARY)),
+        Expression::Field { object, field } => format!("{}.{}", receiver(object), field),
+        Expression::ArrayLiteral(items) => format!("[{}]", arguments(items)),
+        // A one-element tuple needs the trailing comma to differ from parentheses
+        Expression::TupleLiteral(items) if items.len() == 1 => format!("({},)", expression(&items[0], TERNARY)),
+        Expression::TupleLiteral(items) => format!("({})", arguments(items)),
+        Expression::StructLiteral { name, fields } => {
+            // Sorted, since the fields are stored unordered
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_by_key(|(field, _)| field.as_str());
+            let fields: Vec<String> = fields.iter()
+                .map(|(field, value)| format!("{}: {}", field, expression(value, TERNARY)))
+                .collect();
+            format!("{} {{ {} }}", name, fields.join(", "))
+        }
+        Expression::MsgSender | Expression::MsgValue | Expression::BlockNumber | Expression::BlockTimestamp => {
+            format!("{}()", builtin(expr).unwrap_or_default())
+        }
+        Expression::OptionSome(value) => format!("Some({})", expression(value, TERNARY)),
+        Expression::OptionNone => "None".to_string(),
+        Expression::Ternary { .. } | Expression::Binary { .. } | Expression::Cast { .. }
+        | Expression::Unary { .. } | Expression::Lambda { .. } => expression(expr, PRIMARY),
+    }
+}
+
+/// The name of a built-in such as `msg_sender`
+fn builtin(expr: &Expression) -> Option<&'static str> {
+    match expr {
+        Expression::MsgSender => Some("msg_sender"),
+        Expression::MsgValue => Some("msg_value"),
+        Expression::BlockNumber => Some("block_number"),
+        Expression::BlockTimestamp => Some("block_timestamp"),
+        _ => None,
+    }
+}
+
+/// The expression before `.` or `[`, where the grammar only takes a name: built-ins
+/// are written without their `()` there
+fn receiver(expr: &Expression) -> String {
+    builtin(expr).map(str::to_string).unwrap_or_else(|| expression(expr, PRIMARY))
+}
@@ -128,15 +128,47 @@ impl SemanticAnalyzer {
     
     fn analyze_statement(&mut self, stmt: &Statement) -> Result<()> {
         match stmt {
-            Statement::Let { name, value, .. } => {
-                let value_type = self.infer_expression_type(value)?;
-                self.add_symbol(name, &value_type, false)?;
+            Statement::Let { name, ty, value, is_mutable } => {
+                let value_type = match (ty, value) {
+                    // `None` takes the declared option type
+                    (Some(declared), Expression::OptionNone) if matches!(declared, Type::Option(_)) => declared.clone(),
+                    (Some(declared), _) => {
+                        let value_type = self.infer_expression_type(value)?;
+                        if !self.types_compatible(declared, &value_type) {
+                            self.errors.push(format!(
+                                "Type mismatch: cannot assign {:?} to {:?}",
+                                value_type, declared
+                            ));
+                        }
+                        declared.clone()
+                    }
+                    (None, _) => self.infer_expression_type(value)?,
+                };
+                self.add_symbol(name, &value_type, *is_mutable)?;
                 self.initialized.insert(name.clone());
             }
             
+            Statement::LetTuple { names, value } => {
+                match self.infer_expression_type(value)? {
+                    Type::Tuple(types) if types.len() == names.len() => {
+                        for (name, ty) in names.iter().zip(&types) {
+                            self.add_symbol(name, ty, false)?;
+                            self.initialized.insert(name.clone());
+                        }
+                    }
+                    other => self.errors.push(format!(
+                        "Cannot destructure {:?} into {} variables",
+                        other, names.len()
+                    )),
+                }
+            }
+            
             Statement::Assign { target, value } => {
                 // Check if target exists and is mutable
                 let target_type = self.check_lvalue(target)?;
+                if matches!((&target_type, value), (Type::Option(_), Expression::OptionNone)) {
+                    return Ok(());
+                }
                 let value_type = self.infer_expression_type(value)?;
                 
                 if !self.types_compatible(&target_type, &value_type) {
@@ -168,6 +200,38 @@ impl SemanticAnalyzer {
                 }
             }
             
+            Statement::While { condition, body } => {
+                let cond_type = self.infer_expression_type(condition)?;
+                if !matches!(cond_type, Type::Bool) {
+                    self.errors.push("While condition must be boolean".to_string());
+                }
+                self.analyze_block(body)?;
+            }
+            
+            Statement::For { init, condition, update, body } => {
+                // The loop variable is scoped to the loop
+                self.enter_scope();
+                self.analyze_statement(init)?;
+                let cond_type = self.infer_expression_type(condition)?;
+                if !matches!(cond_type, Type::Bool) {
+                    self.errors.push("For condition must be boolean".to_string());
+                }
+                self.analyze_statement(update)?;
+                self.analyze_block(body)?;
+                self.exit_scope();
+            }
+            
+            Statement::ForEach { variable, iterable, body } => {
+                let elem_type = match self.infer_expression_type(iterable)? {
+                    Type::Vec(elem_type) | Type::Array(elem_type, _) => *elem_type,
+                    other => return Err(anyhow!("Cannot iterate over {:?}", other)),
+                };
+                self.enter_scope();
+                self.add_symbol(variable, &elem_type, false)?;
+                self.analyze_block(body)?;
+                self.exit_scope();
+            }
+            
             Statement::Require { condition, .. } => {
                 let cond_type = self.infer_expression_type(condition)?;
                 if !matches!(cond_type, Type::Bool) {
@@ -175,6 +239,24 @@ impl SemanticAnalyzer {
                 }
             }
             
+            Statement::Assert { condition, .. } => {
+                let cond_type = self.infer_expression_type(condition)?;
+                if !matches!(cond_type, Type::Bool) {
+                    self.errors.push("Assert condition must be boolean".to_string());
+                }
+            }
+            
+            Statement::Match { value, arms } => {
+                self.infer_expression_type(value)?;
+                for arm in arms {
+                    self.analyze_block(&arm.body)?;
+                }
+            }
+            
+            Statement::Block(body) => self.analyze_block(body)?,
+            
+            Statement::Break | Statement::Continue | Statement::Placeholder => {}
+            
             Statement::Emit { event, args } => {
                 // Check event exists and arguments match
                 for arg in args {
@@ -196,11 +278,25 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    fn analyze_block(&mut self, statements: &[Statement]) -> Result<()> {
+        self.enter_scope();
+        for s in statements {
+            self.analyze_statement(s)?;
+        }
+        self.exit_scope();
+        Ok(())
+    }
+    
     fn infer_expression_type(&self, expr: &Expression) -> Result<Type> {
         match expr {
             Expression::Number(_) => Ok(Type::U64),
+            Expression::TypedNumber { ty, .. } => Ok(ty.clone()),
+            Expression::Float(_) => Err(anyhow!("Floating-point numbers are not supported")),
             Expression::Bool(_) => Ok(Type::Bool),
-            Expression::String(_) => Ok(Type::String),
+            Expression::String(_) => Ok(Type::String(None)),
+            Expression::Bytes(_) => Ok(Type::Bytes(None)),
+            Expression::MsgSender => Ok(Type::Address),
+            Expression::MsgValue | Expression::BlockNumber | Expression::BlockTimestamp => Ok(Type::U64),
             
             Expression::Identifier(name) => {
                 self.get_symbol_type(name)
@@ -212,8 +308,8 @@ impl SemanticAnalyzer {
                 
                 use crate::BinaryOp::*;
                 match op {
-                    Add | Sub | Mul | Div | Mod => {
-                        if matches!(left_type, Type::U8 | Type::U64 | Type::U128) &&
+                    Add | Sub | Mul | Div | Mod | Pow => {
+                        if is_integer(&left_type) &&
                            self.types_compatible(&left_type, &right_type) {
                             Ok(left_type)
                         } else {
@@ -228,7 +324,7 @@ impl SemanticAnalyzer {
                         }
                     }
                     Lt | Gt | Le | Ge => {
-                        if matches!(left_type, Type::U8 | Type::U64 | Type::U128) &&
+                        if is_integer(&left_type) &&
                            self.types_compatible(&left_type, &right_type) {
                             Ok(Type::Bool)
                         } else {
@@ -242,6 +338,21 @@ impl SemanticAnalyzer {
                             Err(anyhow!("Logical operators require boolean types"))
                         }
                     }
+                    BitAnd | BitOr | BitXor => {
+                        if is_integer(&left_type) && self.types_compatible(&left_type, &right_type) {
+                            Ok(left_type)
+                        } else {
+                            Err(anyhow!("Bitwise operators require integer types"))
+                        }
+                    }
+                    // The shift amount may be any integer type
+                    Shl | Shr => {
+                        if is_integer(&left_type) && is_integer(&right_type) {
+                            Ok(left_type)
+                        } else {
+                            Err(anyhow!("Shifts require integer types"))
+                        }
+                    }
                 }
             }
             
@@ -258,12 +369,19 @@ impl SemanticAnalyzer {
                         }
                     }
                     Neg => {
-                        if matches!(expr_type, Type::U8 | Type::U64 | Type::U128) {
+                        if is_integer(&expr_type) {
                             Ok(expr_type)
                         } else {
                             Err(anyhow!("Negation requires numeric type"))
                         }
                     }
+                    BitNot => {
+                        if is_integer(&expr_type) {
+                            Ok(expr_type)
+                        } else {
+                            Err(anyhow!("Bitwise NOT requires an integer type"))
+                        }
+                    }
                 }
             }
             
@@ -301,6 +419,75 @@ impl SemanticAnalyzer {
                 }
             }
             
+            Expression::Ternary { condition, then_expr, else_expr } => {
+                if !matches!(self.infer_expression_type(condition)?, Type::Bool) {
+                    return Err(anyhow!("Ternary condition must be boolean"));
+                }
+                let then_type = self.infer_expression_type(then_expr)?;
+                let else_type = self.infer_expression_type(else_expr)?;
+                if self.types_compatible(&then_type, &else_type) {
+                    Ok(then_type)
+                } else {
+                    Err(anyhow!("Ternary branches have different types"))
+                }
+            }
+            
+            Expression::MethodCall { object, method, args } => {
+                let object_type = self.infer_expression_type(object);
+                match (object_type, method.as_str()) {
+                    (Ok(Type::Option(_)), "is_some" | "is_none") => Ok(Type::Bool),
+                    (Ok(Type::Option(inner)), "unwrap_or") => Ok(*inner),
+                    (Ok(Type::Map(_, value)), "get") => Ok(Type::Option(value)),
+                    // Calls of other contracts aren't checked
+                    _ => {
+                        for arg in args {
+                            self.infer_expression_type(arg)?;
+                        }
+                        Err(anyhow!("Cannot infer the result of {}()", method))
+                    }
+                }
+            }
+            
+            Expression::Cast { expr, ty } => {
+                if is_integer(&self.infer_expression_type(expr)?) && is_integer(ty) {
+                    Ok(ty.clone())
+                } else {
+                    Err(anyhow!("Only integers can be cast"))
+                }
+            }
+            
+            Expression::ArrayLiteral(items) => {
+                let elem_type = match items.first() {
+                    Some(first) => self.infer_expression_type(first)?,
+                    None => return Err(anyhow!("Cannot infer the type of an empty array")),
+                };
+                for item in &items[1..] {
+                    if !self.types_compatible(&elem_type, &self.infer_expression_type(item)?) {
+                        return Err(anyhow!("Array elements have different types"));
+                    }
+                }
+                Ok(Type::Vec(Box::new(elem_type)))
+            }
+            
+            Expression::TupleLiteral(items) => {
+                let types = items.iter()
+                    .map(|item| self.infer_expression_type(item))
+                    .collect::<Result<_>>()?;
+                Ok(Type::Tuple(types))
+            }
+            
+            Expression::OptionSome(value) => Ok(Type::Option(Box::new(self.infer_expression_type(value)?))),
+            Expression::OptionNone => Err(anyhow!("Cannot infer the type of None")),
+            
+            Expression::StructLiteral { name, fields } => {
+                for value in fields.values() {
+                    self.infer_expression_type(value)?;
+                }
+                Ok(Type::Struct(name.clone()))
+            }
+            
+            Expression::Lambda { .. } => Err(anyhow!("Lambdas are not supported")),
+            
             Expression::Index { array, index } => {
                 let array_type = self.infer_expression_type(array)?;
                 let index_type = self.infer_expression_type(index)?;
@@ -312,7 +499,7 @@ impl SemanticAnalyzer {
                         }
                         Ok(*value_type)
                     }
-                    Type::Vec(elem_type) => {
+                    Type::Vec(elem_type) | Type::Array(elem_type, _) => {
                         if !matches!(index_type, Type::U64) {
                             return Err(anyhow!("Array index must be u64"));
                         }
@@ -362,15 +549,17 @@ impl SemanticAnalyzer {
     
     fn types_compatible(&self, t1: &Type, t2: &Type) -> bool {
         match (t1, t2) {
-            (Type::U8, Type::U8) | (Type::U64, Type::U64) | (Type::U128, Type::U128) => true,
-            (Type::Bool, Type::Bool) => true,
-            (Type::Address, Type::Address) => true,
-            (Type::String, Type::String) => true,
-            (Type::Map(k1, v1), Type::Map(k2, v2)) => {
+            // Length bounds are checked by the semantic analyzer
+            (Type::String(_), Type::String(_)) | (Type::Bytes(_), Type::Bytes(_)) => true,
+            (Type::Map(k1, v1), Type::Map(k2, v2)) | (Type::Result(k1, v1), Type::Result(k2, v2)) => {
                 self.types_compatible(k1, k2) && self.types_compatible(v1, v2)
             }
-            (Type::Vec(e1), Type::Vec(e2)) => self.types_compatible(e1, e2),
-            _ => false,
+            (Type::Vec(e1), Type::Vec(e2)) | (Type::Option(e1), Type::Option(e2)) => self.types_compatible(e1, e2),
+            (Type::Array(e1, n1), Type::Array(e2, n2)) => n1 == n2 && self.types_compatible(e1, e2),
+            (Type::Tuple(ts1), Type::Tuple(ts2)) => {
+                ts1.len() == ts2.len() && ts1.iter().zip(ts2).all(|(a, b)| self.types_compatible(a, b))
+            }
+            _ => t1 == t2,
         }
     }
     
@@ -388,8 +577,7 @@ impl SemanticAnalyzer {
         match lvalue {
             LValue::Identifier(name) => self.get_symbol_type(name),
             LValue::Index { array, index } => {
-                // Get type of the array identifier
-                let array_type = self.get_symbol_type(array)?;
+                let array_type = self.check_lvalue(array)?;
                 let _ = self.infer_expression_type(index)?;
                 match array_type {
                     Type::Vec(elem_type) | Type::Array(elem_type, _) => Ok(*elem_type),
@@ -397,6 +585,17 @@ impl SemanticAnalyzer {
                     _ => Err(anyhow!("Cannot index non-collection type"))
                 }
             },
+            LValue::Field { object, .. } => {
+                // Struct fields aren't checked yet, as in `infer_expression_type`
+                self.check_lvalue(object)?;
+                Ok(Type::U64)
+            }
         }
     }
+}
+
+fn is_integer(ty: &Type) -> bool {
+    matches!(ty,
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U128 | Type::U256 |
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128)
 }
\ No newline at end of file
@@ -1,9 +1,10 @@
 use std::collections::{HashMap, HashSet};
 use anyhow::{Result, anyhow, Context};
+use crate::optimizer::collect_assigned;
 use crate::{
-    Contract, Function, Statement, Expression, Type, StateVariable, 
+    Program, Contract, Function, Statement, Expression, Type, StateVariable, 
     Parameter, Visibility, LValue, BinaryOp, UnaryOp, StructDefinition,
-    EventDefinition, Modifier, Constant
+    EventDefinition, Modifier, Constant, MatchArm, Pattern, Bridge, DEFAULT_MAX_STRING_LEN
 };
 
 /// Symbol information stored in the symbol table
@@ -105,6 +106,15 @@ pub struct TypeContext {
     pub structs: HashMap<String, StructDefinition>,
     pub events: HashMap<String, EventDefinition>,
     pub modifiers: HashMap<String, Modifier>,
+    /// Functions of the other contracts in the program and of the external contracts
+    /// it declares; the public ones are callable as `Other.method(...)`
+    pub contracts: HashMap<String, Vec<Function>>,
+    /// Functions of the contract being checked, callable by name
+    pub functions: HashMap<String, Function>,
+    /// Number of loops enclosing the statement being checked
+    pub loop_depth: usize,
+    /// The contract's `bridge` block; `bridge_lock`/`bridge_receive` need one
+    pub bridge: Option<Bridge>,
 }
 
 #[derive(Debug)]
@@ -131,6 +141,10 @@ impl TypeContext {
             structs: HashMap::new(),
             events: HashMap::new(),
             modifiers: HashMap::new(),
+            contracts: HashMap::new(),
+            functions: HashMap::new(),
+            loop_depth: 0,
+            bridge: None,
         }
     }
     
@@ -197,9 +211,15 @@ impl TypeInference {
         let t2_str = format!("{:?}", t2);
         
         match (t1, t2) {
-            (Type::U8, Type::U8) | (Type::U64, Type::U64) | 
+            (Type::U8, Type::U8) | (Type::U16, Type::U16) | (Type::U32, Type::U32) |
+            (Type::U64, Type::U64) | (Type::U128, Type::U128) | (Type::U256, Type::U256) | 
+            (Type::I8, Type::I8) | (Type::I16, Type::I16) | (Type::I32, Type::I32) |
+            (Type::I64, Type::I64) | (Type::I128, Type::I128) |
             (Type::Bool, Type::Bool) | (Type::Address, Type::Address) => Ok(()),
             
+            // Length bounds are checked against literals separately
+            (Type::String(_), Type::String(_)) | (Type::Bytes(_), Type::Bytes(_)) => Ok(()),
+            
             (Type::Map(k1, v1), Type::Map(k2, v2)) => {
                 self.unify(*k1, *k2)?;
                 self.unify(*v1, *v2)
@@ -207,6 +227,15 @@ impl TypeInference {
             
             (Type::Vec(t1), Type::Vec(t2)) => self.unify(*t1, *t2),
             
+            (Type::Array(t1, n1), Type::Array(t2, n2)) if n1 == n2 => self.unify(*t1, *t2),
+            
+            (Type::Tuple(ts1), Type::Tuple(ts2)) if ts1.len() == ts2.len() => {
+                for (t1, t2) in ts1.into_iter().zip(ts2) {
+                    self.unify(t1, t2)?;
+                }
+                Ok(())
+            }
+            
             (Type::Option(t1), Type::Option(t2)) => self.unify(*t1, *t2),
             
             (Type::Result(ok1, err1), Type::Result(ok2, err2)) => {
@@ -240,6 +269,11 @@ impl TypeInference {
 pub struct SemanticAnalyzer {
     context: TypeContext,
     type_inference: TypeInference,
+    default_string_len: usize,
+    // Values of the contract's integer constants
+    constant_values: HashMap<String, u128>,
+    // Constants plus locals currently holding a known integer, for spotting zero divisors
+    known_values: HashMap<String, u128>,
 }
 
 impl SemanticAnalyzer {
@@ -247,20 +281,68 @@ impl SemanticAnalyzer {
         Self {
             context: TypeContext::new(contract_name),
             type_inference: TypeInference::new(),
+            default_string_len: DEFAULT_MAX_STRING_LEN,
+            constant_values: HashMap::new(),
+            known_values: HashMap::new(),
+        }
+    }
+    
+    /// Length assumed (and warned about) for state strings declared without a bound
+    pub fn with_default_string_len(mut self, len: usize) -> Self {
+        self.default_string_len = len;
+        self
+    }
+    
+    /// Makes the public functions of the program's other contracts, and of the
+    /// external contracts it declares, callable
+    pub fn with_program(mut self, program: &Program) -> Self {
+        for contract in program.contracts.iter().chain(&program.dependencies) {
+            if contract.name == self.context.contract_name {
+                continue;
+            }
+            // 私有函数也登记, 以便调用它们时给出更明确的错误
+            self.context.contracts.insert(contract.name.clone(), contract.functions.clone());
+        }
+        // External contracts are only declared; calls are checked against body-less signatures
+        for external in &program.externals {
+            let callable = external.functions.iter()
+                .map(|f| Function {
+                    visibility: Visibility::External,
+                    name: f.name.clone(),
+                    params: f.params.clone(),
+                    return_type: f.return_type.clone(),
+                    modifiers: Vec::new(),
+                    body: Vec::new(),
+                    is_payable: false,
+                    is_view: false,
+                    is_constructor: false,
+                    line: 0,
+                })
+                .collect();
+            self.context.contracts.insert(external.name.clone(), callable);
         }
+        self
     }
     
     pub fn analyze(&mut self, contract: &Contract) -> Result<()> {
         // First pass: Register all type definitions
         self.register_types(contract)?;
+        self.context.bridge = contract.bridge.clone();
+        
+        // Constants come before state so initializers can't refer to state
+        self.register_constants(contract)?;
         
         // Second pass: Register state variables
         self.register_state_variables(contract)?;
         
         // Third pass: Check all functions
+        for function in &contract.functions {
+            self.context.functions.insert(function.name.clone(), function.clone());
+        }
         for function in &contract.functions {
             self.check_function(function)?;
         }
+        self.check_constructor(contract);
         
         // Fourth pass: Solve type constraints
         self.type_inference.solve()?;
@@ -277,6 +359,18 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    /// Type-checks a single expression against what has been analyzed so far and
+    /// returns its type; `ccdsl repl` uses this on an empty analyzer
+    pub fn check_expression(&mut self, expr: &Expression) -> Result<Type> {
+        let ty = self.infer_expression_type(expr)?;
+        self.type_inference.solve()?;
+        
+        if let Some(error) = self.context.errors.first() {
+            return Err(anyhow!("{}", error.message));
+        }
+        Ok(ty)
+    }
+    
     fn register_types(&mut self, contract: &Contract) -> Result<()> {
         // Register structs
         for struct_def in &contract.structs {
@@ -288,6 +382,23 @@ impl SemanticAnalyzer {
             self.context.events.insert(event.name.clone(), event.clone());
         }
         
+        // Every struct a declaration mentions must be defined
+        let declared = contract.state.iter().map(|v| (&v.name, &v.ty))
+            .chain(contract.structs.iter().flat_map(|s| s.fields.iter().map(|f| (&f.name, &f.ty))))
+            .chain(contract.events.iter().flat_map(|e| e.params.iter().map(|p| (&p.name, &p.ty))))
+            .chain(contract.functions.iter().flat_map(|f| {
+                f.params.iter().map(|p| (&p.name, &p.ty)).chain(f.return_type.iter().map(move |t| (&f.name, t)))
+            }));
+        for (name, ty) in declared {
+            let mut missing = Vec::new();
+            struct_names(ty, &mut missing);
+            for struct_name in missing {
+                if !self.context.structs.contains_key(&struct_name) {
+                    self.context.add_error(format!("Unknown type '{}' in the declaration of '{}'", struct_name, name));
+                }
+            }
+        }
+        
         // Register modifiers
         for modifier in &contract.modifiers {
             self.context.modifiers.insert(modifier.name.clone(), modifier.clone());
@@ -296,8 +407,67 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    fn register_constants(&mut self, contract: &Contract) -> Result<()> {
+        let mut values: HashMap<String, u128> = HashMap::new();
+        
+        for constant in &contract.constants {
+            let value_type = self.infer_expression_type(&constant.value)?;
+            
+            match (integer_max(&constant.ty), eval_constant(&constant.value, &values)) {
+                // Integer literals default to u64, so check the value against the declared type instead
+                (Some(max), Some(value)) => {
+                    if value > max {
+                        self.context.add_error(format!(
+                            "Constant '{}' = {} does not fit in {:?}", constant.name, value, constant.ty
+                        ));
+                    }
+                    values.insert(constant.name.clone(), value);
+                }
+                (Some(_), None) if is_integer_constant(&constant.value, &values) => {
+                    self.context.add_error(format!(
+                        "Constant '{}' overflows or divides by zero", constant.name
+                    ));
+                }
+                _ => {
+                    self.check_literal_length(&constant.ty, &constant.value);
+                    self.type_inference.add_constraint(
+                        TypeConstraint::Equal(value_type, constant.ty.clone())
+                    );
+                }
+            }
+            
+            let symbol = Symbol {
+                name: constant.name.clone(),
+                ty: constant.ty.clone(),
+                kind: SymbolKind::Constant,
+                mutable: false,
+                scope_level: 0,
+                defined_at: Location { line: 0, column: 0 },
+            };
+            
+            self.context.symbol_table.declare(symbol)?;
+        }
+        
+        self.constant_values = values;
+        Ok(())
+    }
+    
     fn register_state_variables(&mut self, contract: &Contract) -> Result<()> {
+        // Initializers are checked before any state is declared, so they can only see constants
         for state_var in &contract.state {
+            if let Some(value) = &state_var.initial_value {
+                self.check_initializer(state_var, value)?;
+            }
+        }
+        
+        for state_var in &contract.state {
+            if has_unbounded_string(&state_var.ty) {
+                self.context.add_warning(format!(
+                    "State variable '{}' has an unbounded string type, assuming a max length of {} bytes",
+                    state_var.name, self.default_string_len
+                ));
+            }
+            
             let symbol = Symbol {
                 name: state_var.name.clone(),
                 ty: state_var.ty.clone(),
@@ -313,14 +483,67 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    /// The constructor creates the contract's state when the deployer calls it, once
+    fn check_constructor(&mut self, contract: &Contract) {
+        let marked: Vec<&str> = contract.functions.iter()
+            .filter(|f| f.is_constructor)
+            .map(|f| f.name.as_str())
+            .collect();
+        if marked.len() > 1 {
+            self.context.add_error(format!(
+                "Contract '{}' has {} constructors ({}), but can have only one", contract.name, marked.len(), marked.join(", ")
+            ));
+        }
+        
+        let Some(constructor) = contract.constructor() else {
+            // An unmarked `initialize` the deployer can't call is an ordinary function
+            if let Some(function) = contract.functions.iter().find(|f| f.name == Contract::DEFAULT_CONSTRUCTOR) {
+                self.context.add_warning(format!(
+                    "Function '{}' is not a public, non-view function, so it is not the constructor; \
+                     make it public or mark the constructor with #[constructor]",
+                    function.name
+                ));
+            }
+            return;
+        };
+        if !matches!(constructor.visibility, Visibility::Public | Visibility::External) || constructor.is_view {
+            self.context.add_error(format!(
+                "Constructor '{}' must be a public, non-view function: the deployer calls it to create the contract's state",
+                constructor.name
+            ));
+        }
+        for function in &contract.functions {
+            let mut calls = false;
+            crate::codegen::visit_expressions(&function.body, &mut |expr| {
+                calls |= matches!(expr, Expression::Call { func, .. } if **func == Expression::Identifier(constructor.name.clone()));
+            });
+            if calls {
+                self.context.add_error(format!(
+                    "Function '{}' calls the constructor '{}', which only runs once, when the contract is deployed",
+                    function.name, constructor.name
+                ));
+            }
+        }
+    }
+    
     fn check_function(&mut self, function: &Function) -> Result<()> {
         // Set current function context
         self.context.current_function = Some(function.name.clone());
         self.context.current_return_type = function.return_type.clone();
         
+        // Value can only be attached to a call from outside the contract
+        if function.is_payable && !matches!(function.visibility, Visibility::Public | Visibility::External) {
+            self.context.add_error(format!("Payable function '{}' must be public", function.name));
+        }
+        
         // Enter new scope for function
         self.context.symbol_table.enter_scope();
         
+        self.known_values = self.constant_values.clone();
+        for param in &function.params {
+            self.known_values.remove(&param.name);
+        }
+        
         // Register parameters
         for param in &function.params {
             let symbol = Symbol {
@@ -335,6 +558,27 @@ impl SemanticAnalyzer {
             self.context.symbol_table.declare(symbol)?;
         }
         
+        // Modifier arguments are evaluated with the function's parameters in scope
+        for call in &function.modifiers {
+            let Some(modifier) = self.context.modifiers.get(&call.name).cloned() else {
+                self.context.add_error(format!(
+                    "Unknown modifier '{}' on function '{}'", call.name, function.name
+                ));
+                continue;
+            };
+            if call.args.len() != modifier.params.len() {
+                self.context.add_error(format!(
+                    "Modifier '{}' expects {} argument(s), got {} on function '{}'",
+                    call.name, modifier.params.len(), call.args.len(), function.name
+                ));
+                continue;
+            }
+            for (arg, param) in call.args.iter().zip(&modifier.params) {
+                let arg_type = self.infer_expected_type(arg, &param.ty)?;
+                self.type_inference.add_constraint(TypeConstraint::Equal(arg_type, param.ty.clone()));
+            }
+        }
+        
         // Check function body
         for statement in &function.body {
             self.check_statement(statement)?;
@@ -357,13 +601,40 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    fn forget_assigned(&mut self, statements: &[Statement]) {
+        let mut assigned = HashSet::new();
+        collect_assigned(statements, &mut assigned);
+        self.known_values.retain(|name, _| !assigned.contains(name));
+    }
+    
+    /// Checks a loop body, where `break` and `continue` are allowed
+    fn check_loop_body(&mut self, body: &[Statement]) -> Result<()> {
+        // Anything the body assigns only keeps its value until the first iteration ends
+        self.forget_assigned(body);
+        
+        self.context.loop_depth += 1;
+        let result = body.iter().try_for_each(|stmt| self.check_statement(stmt));
+        self.context.loop_depth -= 1;
+        result
+    }
+    
     fn check_statement(&mut self, statement: &Statement) -> Result<()> {
         match statement {
             Statement::Let { name, ty, value, is_mutable } => {
                 // Infer or check type
-                let value_type = self.infer_expression_type(value)?;
+                let value_type = match ty {
+                    Some(declared_type) => self.infer_expected_type(value, declared_type)?,
+                    None => self.infer_expression_type(value)?,
+                };
+                
+                match eval_constant(value, &self.known_values) {
+                    Some(known) => self.known_values.insert(name.clone(), known),
+                    None => self.known_values.remove(name),
+                };
                 
                 let actual_type = if let Some(declared_type) = ty {
+                    self.check_literal_length(declared_type, value);
+                    
                     // Check that value type matches declared type
                     self.type_inference.add_constraint(
                         TypeConstraint::Equal(value_type.clone(), declared_type.clone())
@@ -386,13 +657,39 @@ impl SemanticAnalyzer {
                 self.context.symbol_table.declare(symbol)?;
             }
             
+            Statement::LetTuple { names, value } => {
+                let types = match self.infer_expression_type(value)? {
+                    Type::Tuple(types) if types.len() == names.len() => types,
+                    other => return Err(anyhow!(
+                        "Cannot destructure a value of type {:?} into {} variables", other, names.len()
+                    )),
+                };
+                
+                for (name, ty) in names.iter().zip(types) {
+                    self.known_values.remove(name);
+                    let symbol = Symbol {
+                        name: name.clone(),
+                        ty,
+                        kind: SymbolKind::LocalVariable,
+                        mutable: false,
+                        scope_level: self.context.symbol_table.current_scope_level,
+                        defined_at: Location { line: 0, column: 0 },
+                    };
+                    self.context.symbol_table.declare(symbol)?;
+                }
+            }
+            
             Statement::Assign { target, value } => {
                 // Check that target exists and get its type
                 let target_type = self.check_lvalue(target)?;
-                let value_type = self.infer_expression_type(value)?;
+                let value_type = self.infer_expected_type(value, &target_type)?;
                 
                 // Check mutability separately
                 self.check_lvalue_mutability(target);
+                if let LValue::Identifier(name) = target {
+                    self.known_values.remove(name);
+                }
+                self.check_literal_length(&target_type, value);
                 
                 // Types must match
                 self.type_inference.add_constraint(
@@ -433,19 +730,91 @@ impl SemanticAnalyzer {
                 
                 // Check body
                 self.context.symbol_table.enter_scope();
-                for stmt in body {
-                    self.check_statement(stmt)?;
-                }
+                self.check_loop_body(body)?;
+                self.context.symbol_table.exit_scope();
+            }
+            
+            Statement::For { init, condition, update, body } => {
+                // The counter is scoped to the loop
+                self.context.symbol_table.enter_scope();
+                self.check_statement(init)?;
+                self.forget_assigned(std::slice::from_ref(update.as_ref()));
+                
+                let cond_type = self.infer_expression_type(condition)?;
+                self.type_inference.add_constraint(
+                    TypeConstraint::Equal(cond_type, Type::Bool)
+                );
+                
+                self.context.symbol_table.enter_scope();
+                self.check_loop_body(body)?;
+                self.context.symbol_table.exit_scope();
+                
+                self.check_statement(update)?;
+                self.context.symbol_table.exit_scope();
+            }
+            
+            Statement::ForEach { variable, iterable, body } => {
+                let element_type = match self.infer_expression_type(iterable)? {
+                    Type::Vec(element) | Type::Array(element, _) => *element,
+                    other => return Err(anyhow!("Cannot iterate over {:?}", other)),
+                };
+                
+                self.context.symbol_table.enter_scope();
+                self.context.symbol_table.declare(Symbol {
+                    name: variable.clone(),
+                    ty: element_type,
+                    kind: SymbolKind::LocalVariable,
+                    mutable: false,
+                    scope_level: self.context.symbol_table.current_scope_level,
+                    defined_at: Location { line: 0, column: 0 },
+                })?;
+                self.check_loop_body(body)?;
                 self.context.symbol_table.exit_scope();
             }
             
+            Statement::Break | Statement::Continue if self.context.loop_depth == 0 => {
+                let keyword = if matches!(statement, Statement::Break) { "break" } else { "continue" };
+                self.context.add_error(format!("`{}` used outside of a loop", keyword));
+            }
+            
+            Statement::Match { value, arms } => {
+                let value_type = self.infer_expression_type(value)?;
+                
+                for arm in arms {
+                    self.check_pattern(&arm.pattern, &value_type);
+                    
+                    self.context.symbol_table.enter_scope();
+                    for stmt in &arm.body {
+                        self.check_statement(stmt)?;
+                    }
+                    self.context.symbol_table.exit_scope();
+                }
+                
+                if let Some(position) = arms.iter().position(|arm| arm.pattern == Pattern::Wildcard) {
+                    if position + 1 < arms.len() {
+                        self.context.add_warning(
+                            "Unreachable match arms after wildcard pattern".to_string()
+                        );
+                    }
+                } else if !self.match_is_exhaustive(&value_type, arms) {
+                    self.context.add_error(format!(
+                        "Non-exhaustive match on {:?}, add a wildcard arm `_ => ...`",
+                        value_type
+                    ));
+                }
+            }
+            
             Statement::Return { value } => {
                 if let Some(expr) = value {
-                    let return_type = self.infer_expression_type(expr)?;
+                    let return_type = match self.context.current_return_type.clone() {
+                        Some(expected) => self.infer_expected_type(expr, &expected)?,
+                        None => self.infer_expression_type(expr)?,
+                    };
                     
-                    if let Some(expected) = &self.context.current_return_type {
+                    if let Some(expected) = self.context.current_return_type.clone() {
+                        self.check_literal_length(&expected, expr);
                         self.type_inference.add_constraint(
-                            TypeConstraint::Equal(return_type, expected.clone())
+                            TypeConstraint::Equal(return_type, expected)
                         );
                     } else {
                         self.context.add_error(
@@ -486,6 +855,10 @@ impl SemanticAnalyzer {
                 self.infer_expression_type(expr)?;
             }
             
+            Statement::Placeholder => {
+                self.context.add_error("`_;` is only allowed inside a modifier".to_string());
+            }
+            
             _ => {} // Handle other statement types
         }
         
@@ -496,7 +869,9 @@ impl SemanticAnalyzer {
         match lvalue {
             LValue::Identifier(name) => {
                 if let Some(symbol) = self.context.symbol_table.lookup(name) {
-                    if !symbol.mutable {
+                    if matches!(symbol.kind, SymbolKind::Constant) {
+                        self.context.add_error(format!("Cannot assign to constant '{}'", name));
+                    } else if !symbol.mutable {
                         self.context.add_error(format!(
                             "Cannot assign to immutable variable '{}'", name
                         ));
@@ -564,13 +939,44 @@ impl SemanticAnalyzer {
         }
     }
     
+    /// Rejects `x < 0` and `x >= 0` (or `0 > x`, `0 <= x`) on a signed `x`. Solana keeps
+    /// signed integers, but Move has none and compiles them as unsigned, where these
+    /// comparisons are constant, so the contract would behave differently on each chain.
+    fn check_sign_test(&mut self, comparison: &Expression, left_type: &Type, right_type: &Type) {
+        let Expression::Binary { op, left, right } = comparison else { return };
+        let is_zero = |expr: &Expression| matches!(expr, Expression::Number(0) | Expression::TypedNumber { value: 0, .. });
+        let (ty, always) = match op {
+            BinaryOp::Lt if is_signed(left_type) && is_zero(right) => (left_type, false),
+            BinaryOp::Ge if is_signed(left_type) && is_zero(right) => (left_type, true),
+            BinaryOp::Gt if is_zero(left) && is_signed(right_type) => (right_type, false),
+            BinaryOp::Le if is_zero(left) && is_signed(right_type) => (right_type, true),
+            _ => return,
+        };
+        self.context.add_error(format!(
+            "Comparison '{}' in function '{}' tests the sign of an {}, but Move has no signed integers: \
+             {} is compiled as unsigned there, where the comparison is always {}",
+            comparison.to_source(),
+            self.context.current_function.as_deref().unwrap_or("<unknown>"),
+            ty, ty, always
+        ));
+    }
+    
+    /// Like `infer_expression_type`, but `None` takes the option type the context expects
+    fn infer_expected_type(&mut self, expr: &Expression, expected: &Type) -> Result<Type> {
+        match (expr, expected) {
+            (Expression::OptionNone, Type::Option(_)) => Ok(expected.clone()),
+            _ => self.infer_expression_type(expr),
+        }
+    }
+    
     fn infer_expression_type(&mut self, expr: &Expression) -> Result<Type> {
         match expr {
             Expression::Number(_) => Ok(Type::U64), // Default to U64
+            Expression::TypedNumber { ty, .. } => Ok(ty.clone()),
             Expression::Float(_) => Ok(Type::U64), // No float type, convert to U64
             Expression::Bool(_) => Ok(Type::Bool),
-            Expression::String(_) => Ok(Type::String),
-            Expression::Bytes(_) => Ok(Type::Bytes),
+            Expression::String(_) => Ok(Type::String(None)),
+            Expression::Bytes(_) => Ok(Type::Bytes(None)),
             
             Expression::Identifier(name) => {
                 if let Some(symbol) = self.context.symbol_table.lookup(name) {
@@ -587,6 +993,15 @@ impl SemanticAnalyzer {
                 match op {
                     BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | 
                     BinaryOp::Div | BinaryOp::Mod => {
+                        if matches!(op, BinaryOp::Div | BinaryOp::Mod)
+                            && eval_constant(right, &self.known_values) == Some(0)
+                        {
+                            self.context.add_error(format!(
+                                "Division by zero in function '{}': the divisor is always 0",
+                                self.context.current_function.as_deref().unwrap_or("<unknown>")
+                            ));
+                        }
+
                         // Numeric operations
                         self.type_inference.add_constraint(
                             TypeConstraint::Equal(left_type.clone(), right_type)
@@ -596,10 +1011,15 @@ impl SemanticAnalyzer {
                     
                     BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | 
                     BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge => {
-                        // Comparison operations
-                        self.type_inference.add_constraint(
-                            TypeConstraint::Equal(left_type, right_type)
-                        );
+                        self.check_sign_test(expr, &left_type, &right_type);
+                        // An untyped literal compared with a signed value takes its type
+                        let literal_vs_signed = (matches!(**left, Expression::Number(_)) && is_signed(&right_type))
+                            || (matches!(**right, Expression::Number(_)) && is_signed(&left_type));
+                        if !literal_vs_signed {
+                            self.type_inference.add_constraint(
+                                TypeConstraint::Equal(left_type, right_type)
+                            );
+                        }
                         Ok(Type::Bool)
                     }
                     
@@ -614,6 +1034,31 @@ impl SemanticAnalyzer {
                         Ok(Type::Bool)
                     }
                     
+                    BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor |
+                    BinaryOp::Shl | BinaryOp::Shr => {
+                        // Bool and address have no bits to operate on
+                        for ty in [&left_type, &right_type] {
+                            if integer_bits(ty).is_none() {
+                                return Err(anyhow!("Bitwise operators need integer operands, found {}", ty));
+                            }
+                        }
+                        if matches!(op, BinaryOp::Shl | BinaryOp::Shr) {
+                            // The amount may be any integer type, but must be less than the width
+                            let bits = integer_bits(&left_type).unwrap_or_default();
+                            if let Some(amount) = eval_constant(right, &self.known_values).filter(|n| *n >= bits as u128) {
+                                self.context.add_error(format!(
+                                    "Shift by {} in function '{}' is not less than the {} bits of {}",
+                                    amount, self.context.current_function.as_deref().unwrap_or("<unknown>"), bits, left_type
+                                ));
+                            }
+                        } else {
+                            self.type_inference.add_constraint(
+                                TypeConstraint::Equal(left_type.clone(), right_type)
+                            );
+                        }
+                        Ok(left_type)
+                    }
+                    
                     _ => Ok(left_type) // For other operators
                 }
             }
@@ -636,19 +1081,175 @@ impl SemanticAnalyzer {
                 }
             }
             
-            Expression::Call { func, args: _ } => {
-                // Look up function type
-                if let Expression::Identifier(_func_name) = &**func {
-                    // Find function in contract
-                    // This is simplified - would need to look up actual function
-                    Ok(Type::U64) // Placeholder
+            Expression::Cast { expr, ty } => {
+                let from = self.infer_expression_type(expr)?;
+                let Some(max) = integer_max(ty).filter(|_| integer_max(&from).is_some()) else {
+                    return Err(anyhow!("Cannot cast {:?} to {:?}; only integer types can be cast", from, ty));
+                };
+                
+                // Narrowing is checked at runtime, but a constant can be checked now
+                if let Some(value) = eval_constant(expr, &self.known_values) {
+                    if value > max {
+                        self.context.add_error(format!("Constant {} does not fit in {:?}", value, ty));
+                    }
+                }
+                Ok(ty.clone())
+            }
+            
+            Expression::Ternary { condition, then_expr, else_expr } => {
+                let condition_type = self.infer_expression_type(condition)?;
+                self.type_inference.add_constraint(
+                    TypeConstraint::Equal(condition_type, Type::Bool)
+                );
+                
+                // Both branches must produce the same type; `None` takes the other's
+                let (then_type, else_type) = if matches!(**then_expr, Expression::OptionNone) {
+                    let else_type = self.infer_expression_type(else_expr)?;
+                    (self.infer_expected_type(then_expr, &else_type)?, else_type)
                 } else {
-                    Err(anyhow!("Complex function calls not yet supported"))
+                    let then_type = self.infer_expression_type(then_expr)?;
+                    let else_type = self.infer_expected_type(else_expr, &then_type)?;
+                    (then_type, else_type)
+                };
+                self.type_inference.add_constraint(
+                    TypeConstraint::Equal(then_type.clone(), else_type)
+                );
+                Ok(then_type)
+            }
+            
+            Expression::ArrayLiteral(items) => {
+                let (first, rest) = items.split_first()
+                    .ok_or_else(|| anyhow!("Cannot infer the element type of an empty array literal"))?;
+                
+                // Every element must have the first element's type
+                let element_type = self.infer_expression_type(first)?;
+                for item in rest {
+                    let item_type = self.infer_expression_type(item)?;
+                    self.type_inference.add_constraint(
+                        TypeConstraint::Equal(element_type.clone(), item_type)
+                    );
+                }
+                Ok(Type::Vec(Box::new(element_type)))
+            }
+            
+            Expression::TupleLiteral(items) => {
+                let types = items.iter()
+                    .map(|item| self.infer_expression_type(item))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Type::Tuple(types))
+            }
+            
+            Expression::OptionSome(value) => Ok(Type::Option(Box::new(self.infer_expression_type(value)?))),
+            Expression::OptionNone => Err(anyhow!(
+                "Cannot infer the type of None here; use it where an option is expected, or test an option with is_none()"
+            )),
+            
+            Expression::Call { func, args } => {
+                let Expression::Identifier(func_name) = &**func else {
+                    return Err(anyhow!("Complex function calls not yet supported"));
+                };
+                if func_name == Bridge::LOCK || func_name == Bridge::RECEIVE {
+                    return self.check_bridge_call(func_name, args);
+                }
+                let Some(callee) = self.context.functions.get(func_name).cloned() else {
+                    // Not a function of this contract; builtins aren't modelled yet
+                    return Ok(Type::U64);
+                };
+                
+                if callee.params.len() != args.len() {
+                    return Err(anyhow!(
+                        "'{}' expects {} argument(s), got {}",
+                        func_name, callee.params.len(), args.len()
+                    ));
+                }
+                for (arg, param) in args.iter().zip(&callee.params) {
+                    let arg_type = self.infer_expected_type(arg, &param.ty)?;
+                    self.type_inference.add_constraint(
+                        TypeConstraint::Equal(arg_type, param.ty.clone())
+                    );
+                }
+                
+                Ok(callee.return_type.unwrap_or(Type::Tuple(Vec::new())))
+            }
+            
+            Expression::MethodCall { object, method, args } if expr.is_value_method() => {
+                let receiver = self.infer_expression_type(object)?;
+                let expected_args = if method == "is_some" || method == "is_none" { 0 } else { 1 };
+                if args.len() != expected_args {
+                    return Err(anyhow!("'{}' expects {} argument(s), got {}", method, expected_args, args.len()));
+                }
+                
+                match (method.as_str(), receiver) {
+                    // A missing entry reads as None rather than as the zero value `map[key]` gives
+                    ("get", Type::Map(_, value)) if matches!(*value, Type::Map(..)) => {
+                        Err(anyhow!("'get' reads maps of values; index nested maps instead"))
+                    }
+                    ("get", Type::Map(key, value)) => {
+                        let key_type = self.infer_expression_type(&args[0])?;
+                        self.type_inference.add_constraint(TypeConstraint::Equal(key_type, *key));
+                        Ok(Type::Option(value))
+                    }
+                    ("get", other) => Err(anyhow!("'get' needs a map, found {}", other)),
+                    ("unwrap_or", Type::Option(value)) => {
+                        let default_type = self.infer_expected_type(&args[0], &value)?;
+                        self.type_inference.add_constraint(TypeConstraint::Equal(default_type, (*value).clone()));
+                        Ok(*value)
+                    }
+                    (_, Type::Option(_)) => Ok(Type::Bool),
+                    (_, other) => Err(anyhow!("'{}' needs an option, found {}", method, other)),
                 }
             }
             
+            Expression::MethodCall { object, method, args } => {
+                let target = match &**object {
+                    Expression::Identifier(name) if self.context.symbol_table.lookup(name).is_none() => name,
+                    _ => return Err(anyhow!("Method calls are only supported on other contracts")),
+                };
+                let callee = self.context.contracts.get(target)
+                    .ok_or_else(|| anyhow!("Unknown contract '{}'", target))?
+                    .iter()
+                    .find(|f| f.name == *method)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Contract '{}' has no public function '{}'", target, method))?;
+                let hidden = match callee.visibility {
+                    Visibility::Private => Some("private"),
+                    Visibility::Internal => Some("internal"),
+                    Visibility::Public | Visibility::External => None,
+                };
+                if let Some(visibility) = hidden {
+                    return Err(anyhow!(
+                        "Contract '{}' has no public function '{}'; it is {}, so only '{}' itself can call it",
+                        target, method, visibility, target
+                    ));
+                }
+                
+                if callee.params.len() != args.len() {
+                    return Err(anyhow!(
+                        "'{}.{}' expects {} argument(s), got {}",
+                        target, method, callee.params.len(), args.len()
+                    ));
+                }
+                for (arg, param) in args.iter().zip(&callee.params) {
+                    let arg_type = self.infer_expected_type(arg, &param.ty)?;
+                    self.type_inference.add_constraint(
+                        TypeConstraint::Equal(arg_type, param.ty.clone())
+                    );
+                }
+                
+                // No unit type yet; a call without a return value is the empty tuple
+                Ok(callee.return_type.unwrap_or(Type::Tuple(Vec::new())))
+            }
+            
             Expression::MsgSender => Ok(Type::Address),
-            Expression::MsgValue => Ok(Type::U64),
+            Expression::MsgValue => {
+                // Without `payable` no value comes with the call, so msg_value() would be meaningless
+                let function = self.context.current_function.as_ref().and_then(|name| self.context.functions.get(name));
+                if let Some(function) = function.filter(|f| !f.is_payable) {
+                    let message = format!("msg_value() is only available in payable functions, not in '{}'", function.name);
+                    self.context.add_error(message);
+                }
+                Ok(Type::U64)
+            }
             Expression::BlockNumber => Ok(Type::U64),
             Expression::BlockTimestamp => Ok(Type::U64),
             
@@ -656,6 +1257,27 @@ impl SemanticAnalyzer {
         }
     }
     
+    /// `bridge_lock(amount, target_chain)` and `bridge_receive(to, amount, source_chain)`,
+    /// which only exist in contracts with a `bridge` block
+    fn check_bridge_call(&mut self, name: &str, args: &[Expression]) -> Result<Type> {
+        if self.context.bridge.is_none() {
+            return Err(anyhow!("'{}' needs a `bridge {{ vault; authority: address; }}` block", name));
+        }
+        let params: &[Type] = if name == Bridge::LOCK {
+            &[Type::U64, Type::U64]
+        } else {
+            &[Type::Address, Type::U64, Type::U64]
+        };
+        if params.len() != args.len() {
+            return Err(anyhow!("'{}' expects {} argument(s), got {}", name, params.len(), args.len()));
+        }
+        for (arg, ty) in args.iter().zip(params) {
+            let arg_type = self.infer_expression_type(arg)?;
+            self.type_inference.add_constraint(TypeConstraint::Equal(arg_type, ty.clone()));
+        }
+        Ok(Type::Tuple(Vec::new()))
+    }
+    
     fn all_paths_return(&self, statements: &[Statement]) -> bool {
         // Simplified check - would need more sophisticated control flow analysis
         for stmt in statements {
@@ -669,10 +1291,152 @@ impl SemanticAnalyzer {
                     }
                 }
             }
+            // Exhaustiveness is checked separately, so every arm returning is enough
+            if let Statement::Match { arms, .. } = stmt {
+                if arms.iter().all(|arm| self.all_paths_return(&arm.body)) {
+                    return true;
+                }
+            }
         }
         false
     }
     
+    /// Checks the initial value of a state variable: built from literals and constants, which
+    /// fold to a value at compile time, or using `msg_sender()`, the deployer, which the
+    /// backends evaluate when they create the state
+    fn check_initializer(&mut self, var: &StateVariable, value: &Expression) -> Result<()> {
+        if matches!(var.ty, Type::Map(..)) {
+            return Err(anyhow!("State variable '{}' is a map, which starts empty and can't have an initial value", var.name));
+        }
+        let mut invalid = None;
+        crate::codegen::visit_expression(value, &mut |expr| {
+            let allowed = match expr {
+                Expression::Identifier(name) => self.context.symbol_table.lookup(name)
+                    .is_some_and(|symbol| matches!(symbol.kind, SymbolKind::Constant)),
+                Expression::Number(_) | Expression::TypedNumber { .. } | Expression::Bool(_)
+                | Expression::String(_) | Expression::Bytes(_) | Expression::MsgSender
+                | Expression::Binary { .. } | Expression::Unary { .. } | Expression::Cast { .. }
+                | Expression::Ternary { .. } | Expression::ArrayLiteral(_) | Expression::TupleLiteral(_)
+                | Expression::OptionSome(_) | Expression::OptionNone => true,
+                _ => false,
+            };
+            if !allowed && invalid.is_none() {
+                invalid = Some(expr.to_source());
+            }
+        });
+        if let Some(expr) = invalid {
+            return Err(anyhow!(
+                "Initial value of state variable '{}' uses '{}'; it can only use literals, constants and msg_sender()",
+                var.name, expr
+            ));
+        }
+        
+        let value_type = self.infer_expression_type(value)?;
+        match (integer_max(&var.ty), eval_constant(value, &self.constant_values)) {
+            (Some(max), Some(n)) if n > max => self.context.add_error(format!(
+                "State variable '{}' = {} does not fit in {:?}", var.name, n, var.ty
+            )),
+            (Some(_), Some(_)) => {}
+            (Some(_), None) if is_integer_constant(value, &self.constant_values) => self.context.add_error(format!(
+                "Initial value of state variable '{}' overflows or divides by zero", var.name
+            )),
+            _ => {
+                self.check_literal_length(&var.ty, value);
+                self.type_inference.add_constraint(TypeConstraint::Equal(value_type, var.ty.clone()));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Rejects string/bytes literals longer than the bound on the type they're stored into
+    fn check_literal_length(&mut self, expected: &Type, value: &Expression) {
+        let (len, max) = match (expected, value) {
+            // `len` is the UTF-8 encoded size, which is what ends up on chain
+            (Type::String(Some(max)), Expression::String(s)) => (s.len(), *max),
+            (Type::Bytes(Some(max)), Expression::Bytes(b)) => (b.len(), *max),
+            _ => return,
+        };
+        
+        if len > max {
+            self.context.add_error(format!(
+                "Literal of {} bytes exceeds the maximum length of {} for {:?}",
+                len, max, expected
+            ));
+        }
+    }
+    
+    fn check_pattern(&mut self, pattern: &Pattern, value_type: &Type) {
+        match pattern {
+            Pattern::Wildcard => {}
+            Pattern::Literal(Expression::Bool(_)) => {
+                if *value_type != Type::Bool {
+                    self.context.add_error(format!(
+                        "Boolean pattern cannot match value of type {:?}", value_type
+                    ));
+                }
+            }
+            Pattern::Literal(Expression::Number(n)) => {
+                match integer_max(value_type) {
+                    Some(max) if *n as u128 > max => self.context.add_error(format!(
+                        "Pattern {} is out of range for {:?}", n, value_type
+                    )),
+                    Some(_) => {}
+                    None => self.context.add_error(format!(
+                        "Integer pattern cannot match value of type {:?}", value_type
+                    )),
+                }
+            }
+            Pattern::Literal(other) => {
+                self.context.add_error(format!("Unsupported match pattern {:?}", other));
+            }
+            Pattern::Range { start, end, inclusive } => {
+                if integer_max(value_type).is_none() {
+                    self.context.add_error(format!(
+                        "Range pattern cannot match value of type {:?}", value_type
+                    ));
+                }
+                if start > end || (start == end && !inclusive) {
+                    self.context.add_error(format!("Empty range pattern {}..{}", start, end));
+                }
+            }
+        }
+    }
+    
+    /// Without a wildcard, a match is exhaustive only if its patterns cover every value
+    fn match_is_exhaustive(&self, value_type: &Type, arms: &[MatchArm]) -> bool {
+        if *value_type == Type::Bool {
+            let covers = |b: bool| arms.iter().any(|arm| arm.pattern == Pattern::Literal(Expression::Bool(b)));
+            return covers(true) && covers(false);
+        }
+        
+        let max = match integer_max(value_type) {
+            Some(max) => max,
+            None => return false,
+        };
+        
+        let mut ranges: Vec<(u128, u128)> = arms.iter()
+            .filter_map(|arm| match &arm.pattern {
+                Pattern::Literal(Expression::Number(n)) => Some((*n as u128, *n as u128)),
+                Pattern::Range { start, end, inclusive: true } => Some((*start as u128, *end as u128)),
+                Pattern::Range { start, end, inclusive: false } if end > start => {
+                    Some((*start as u128, *end as u128 - 1))
+                }
+                _ => None,
+            })
+            .collect();
+        ranges.sort();
+        
+        // Sweep the sorted ranges looking for the first uncovered value
+        let mut next = 0u128;
+        for (start, end) in ranges {
+            if start > next {
+                return false;
+            }
+            next = next.max(end + 1);
+        }
+        next > max
+    }
+    
     pub fn get_errors(&self) -> &[SemanticError] {
         &self.context.errors
     }
@@ -682,6 +1446,104 @@ impl SemanticAnalyzer {
     }
 }
 
+/// Collects the struct names a type refers to
+fn struct_names(ty: &Type, names: &mut Vec<String>) {
+    match ty {
+        Type::Struct(name) => names.push(name.clone()),
+        Type::Map(k, v) | Type::Result(k, v) => {
+            struct_names(k, names);
+            struct_names(v, names);
+        }
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => struct_names(t, names),
+        Type::Tuple(types) => types.iter().for_each(|t| struct_names(t, names)),
+        _ => {}
+    }
+}
+
+fn has_unbounded_string(ty: &Type) -> bool {
+    match ty {
+        Type::String(None) | Type::Bytes(None) => true,
+        Type::Map(k, v) | Type::Result(k, v) => has_unbounded_string(k) || has_unbounded_string(v),
+        Type::Vec(t) | Type::Array(t, _) | Type::Option(t) => has_unbounded_string(t),
+        Type::Tuple(types) => types.iter().any(has_unbounded_string),
+        _ => false,
+    }
+}
+
+/// Largest value of an unsigned integer type, `None` for anything a pattern can't cover
+fn integer_max(ty: &Type) -> Option<u128> {
+    match ty {
+        Type::U8 => Some(u8::MAX as u128),
+        Type::U16 => Some(u16::MAX as u128),
+        Type::U32 => Some(u32::MAX as u128),
+        Type::U64 => Some(u64::MAX as u128),
+        // Patterns are u64 literals, so only a wildcard covers these
+        Type::U128 | Type::U256 => Some(u128::MAX),
+        _ => None,
+    }
+}
+
+fn is_signed(ty: &Type) -> bool {
+    matches!(ty, Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::I128)
+}
+
+/// Width of an integer type, signed or not
+fn integer_bits(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::U8 | Type::I8 => Some(8),
+        Type::U16 | Type::I16 => Some(16),
+        Type::U32 | Type::I32 => Some(32),
+        Type::U64 | Type::I64 => Some(64),
+        Type::U128 | Type::I128 => Some(128),
+        Type::U256 => Some(256),
+        _ => None,
+    }
+}
+
+/// Evaluates an integer constant initializer, `None` on overflow or if it isn't constant
+fn eval_constant(expr: &Expression, known: &HashMap<String, u128>) -> Option<u128> {
+    match expr {
+        Expression::Number(n) => Some(*n as u128),
+        Expression::TypedNumber { value, .. } => Some(*value),
+        Expression::Identifier(name) => known.get(name).copied(),
+        Expression::Cast { expr, .. } => eval_constant(expr, known),
+        Expression::Binary { op, left, right } => {
+            let l = eval_constant(left, known)?;
+            let r = eval_constant(right, known)?;
+            match op {
+                BinaryOp::Add => l.checked_add(r),
+                BinaryOp::Sub => l.checked_sub(r),
+                BinaryOp::Mul => l.checked_mul(r),
+                BinaryOp::Div => l.checked_div(r),
+                BinaryOp::Mod => l.checked_rem(r),
+                BinaryOp::Pow => l.checked_pow(u32::try_from(r).ok()?),
+                BinaryOp::Shl => l.checked_shl(u32::try_from(r).ok()?).filter(|v| v >> r == l),
+                BinaryOp::Shr => l.checked_shr(u32::try_from(r).ok()?),
+                BinaryOp::BitAnd => Some(l & r),
+                BinaryOp::BitOr => Some(l | r),
+                BinaryOp::BitXor => Some(l ^ r),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Built only from integer literals, known constants and arithmetic
+fn is_integer_constant(expr: &Expression, known: &HashMap<String, u128>) -> bool {
+    match expr {
+        Expression::Number(_) | Expression::TypedNumber { .. } => true,
+        Expression::Identifier(name) => known.contains_key(name),
+        Expression::Binary { op, left, right } => {
+            !matches!(op, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Gt |
+                          BinaryOp::Le | BinaryOp::Ge | BinaryOp::And | BinaryOp::Or)
+                && is_integer_constant(left, known)
+                && is_integer_constant(right, known)
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -720,4 +1582,648 @@ mod tests {
         inference2.add_constraint(TypeConstraint::Equal(Type::U64, Type::Bool));
         assert!(inference2.solve().is_err());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_string_length_bounds() {
+        let input = r#"
+            contract Profile {
+                state {
+                    name: string<8>;
+                    bio: string;
+                }
+                
+                public fn rename(version: u64) {
+                    name = "a name that is far too long";
+                }
+            }
+        "#;
+        
+        let contract = Contract::parse(input).expect("Failed to parse");
+        let mut analyzer = SemanticAnalyzer::new("Profile".to_string()).with_default_string_len(128);
+        
+        let err = analyzer.analyze(&contract).unwrap_err();
+        assert!(err.to_string().contains("exceeds the maximum length of 8"));
+        
+        let warnings = analyzer.get_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("'bio'"));
+        assert!(warnings[0].message.contains("128 bytes"));
+    }
+    
+    #[test]
+    fn test_match_exhaustiveness() {
+        let check = |arms: &str| {
+            let input = format!(r#"
+                contract MatchCheck {{
+                    public fn test(flag: bool, level: u8) {{
+                        {}
+                    }}
+                }}
+            "#, arms);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("MatchCheck".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("match flag { true => emit_nothing(), false => emit_nothing() }").is_ok());
+        assert!(check("match level { 0..128 => emit_nothing(), 128..=255 => emit_nothing() }").is_ok());
+        assert!(check("match level { 0 => emit_nothing(), _ => emit_nothing() }").is_ok());
+        
+        let err = check("match level { 0..100 => emit_nothing(), 101..=255 => emit_nothing() }")
+            .unwrap_err();
+        assert!(err.to_string().contains("Non-exhaustive match"));
+        assert!(check("match flag { true => emit_nothing() }").is_err());
+        assert!(check("match level { 0 => emit_nothing(), 256 => emit_nothing(), _ => emit_nothing() }").is_err());
+    }
+    
+    #[test]
+    fn test_cross_contract_calls() {
+        let check = |call: &str| {
+            let input = format!(r#"
+                contract Oracle {{
+                    public fn price(asset: address) -> u64 {{
+                        return 100;
+                    }}
+                    
+                    private fn secret(asset: address) -> u64 {{
+                        return 1;
+                    }}
+                    
+                    internal fn spread(asset: address) -> u64 {{
+                        return 2;
+                    }}
+                }}
+                
+                contract Vault {{
+                    public fn value(asset: address, amount: u64) -> u64 {{
+                        {}
+                    }}
+                }}
+            "#, call);
+            let program = Program::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Vault".to_string())
+                .with_program(&program)
+                .analyze(&program.contracts[1])
+        };
+        
+        assert!(check("return Oracle.price(asset) * amount;").is_ok());
+        
+        let err = check("return Oracle.secret(asset);").unwrap_err();
+        assert!(err.to_string().contains("no public function 'secret'; it is private"));
+        let err = check("return Oracle.spread(asset);").unwrap_err();
+        assert!(err.to_string().contains("it is internal, so only 'Oracle' itself can call it"));
+        let err = check("return Oracle.price(asset, amount);").unwrap_err();
+        assert!(err.to_string().contains("expects 1 argument(s), got 2"));
+        let err = check("return Bank.price(asset);").unwrap_err();
+        assert!(err.to_string().contains("Unknown contract 'Bank'"));
+        assert!(check("return Oracle.price(amount);").is_err());
+    }
+    
+    #[test]
+    fn test_external_contract_calls() {
+        let check = |call: &str| {
+            let input = format!(r#"
+                external contract Oracle at Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS {{
+                    fn get_price(pair: string) -> u64;
+                }}
+                
+                contract Vault {{
+                    public fn value(pair: string, amount: u64) -> u64 {{
+                        {}
+                    }}
+                }}
+            "#, call);
+            let program = Program::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Vault".to_string())
+                .with_program(&program)
+                .analyze(&program.contracts[0])
+        };
+        
+        assert!(check("return Oracle.get_price(pair) * amount;").is_ok());
+        
+        let err = check("return Oracle.get_price(pair, amount);").unwrap_err();
+        assert!(err.to_string().contains("expects 1 argument(s), got 2"));
+        let err = check("return Oracle.set_price(pair);").unwrap_err();
+        assert!(err.to_string().contains("no public function 'set_price'"));
+        assert!(check("return Oracle.get_price(amount);").is_err());
+    }
+    
+    #[test]
+    fn test_bridge_calls() {
+        let check = |bridge: &str, body: &str| {
+            let input = format!(r#"
+                contract Bridged {{
+                    {}
+                    public fn run(to: address, amount: u64, chain: u64) {{
+                        {}
+                    }}
+                }}
+            "#, bridge, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Bridged".to_string()).analyze(&contract)
+        };
+        let bridge = "bridge { vault; authority: address; }";
+        
+        assert!(check(bridge, "bridge_lock(amount, chain); bridge_receive(to, amount, 2);").is_ok());
+        
+        let err = check("", "bridge_lock(amount, chain);").unwrap_err();
+        assert!(err.to_string().contains("'bridge_lock' needs a `bridge"));
+        let err = check(bridge, "bridge_receive(amount, chain);").unwrap_err();
+        assert!(err.to_string().contains("expects 3 argument(s), got 2"));
+        assert!(check(bridge, "bridge_lock(to, chain);").is_err());
+    }
+    
+    #[test]
+    fn test_msg_value_needs_payable() {
+        let check = |func: &str| {
+            let input = format!("contract Vault {{ state {{ deposited: u64; }} {} }}", func);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Vault".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("payable fn deposit() { deposited = deposited + msg_value(); }").is_ok());
+        
+        let err = check("public fn deposit() { deposited = deposited + msg_value(); }").unwrap_err();
+        assert!(err.to_string().contains("msg_value() is only available in payable functions, not in 'deposit'"));
+        let err = check("private payable fn deposit() { }").unwrap_err();
+        assert!(err.to_string().contains("Payable function 'deposit' must be public"));
+    }
+    
+    #[test]
+    fn test_constants() {
+        let check = |decls: &str, body: &str| {
+            let input = format!(r#"
+                contract Consts {{
+                    {}
+                    state {{
+                        total: u64;
+                    }}
+                    
+                    public fn test(amount: u64) {{
+                        {}
+                    }}
+                }}
+            "#, decls, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Consts".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("const FEE: u64 = 30; const CAP: u8 = 255;", "total = amount * FEE;").is_ok());
+        assert!(check("const BASE: u64 = 100; const CAP: u8 = BASE * 2;", "").is_ok());
+        
+        let err = check("const CAP: u8 = 256;", "").unwrap_err();
+        assert!(err.to_string().contains("Constant 'CAP' = 256 does not fit in U8"));
+        let err = check("const BASE: u64 = 100; const CAP: u8 = BASE * 3;", "").unwrap_err();
+        assert!(err.to_string().contains("Constant 'CAP' = 300 does not fit in U8"));
+        let err = check("const LOW: u64 = 1; const BAD: u64 = LOW - 2;", "").unwrap_err();
+        assert!(err.to_string().contains("Constant 'BAD' overflows or divides by zero"));
+        let err = check("const FEE: u64 = 30;", "FEE = amount;").unwrap_err();
+        assert!(err.to_string().contains("Cannot assign to constant 'FEE'"));
+    }
+    
+    #[test]
+    fn test_state_initializers() {
+        let check = |state: &str| {
+            let input = format!(r#"
+                contract Pool {{
+                    const BASE_FEE: u64 = 10;
+                    
+                    state {{
+                        {}
+                    }}
+                }}
+            "#, state);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Pool".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("owner: address = msg_sender(); fee: u64 = 30; label: string<8> = \"pool\";").is_ok());
+        assert!(check("fee: u8 = BASE_FEE * 2; paused: bool = !false;").is_ok());
+        
+        let err = check("fee: u8 = BASE_FEE * 30;").unwrap_err();
+        assert!(err.to_string().contains("State variable 'fee' = 300 does not fit in U8"), "{}", err);
+        let err = check("fee: u64 = BASE_FEE - 11;").unwrap_err();
+        assert!(err.to_string().contains("Initial value of state variable 'fee' overflows"), "{}", err);
+        let err = check("owner: address = 30;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify U64 with Address"), "{}", err);
+        let err = check("label: string<2> = \"pool\";").unwrap_err();
+        assert!(err.to_string().contains("exceeds the maximum length of 2"), "{}", err);
+        let err = check("total: u64; fee: u64 = total;").unwrap_err();
+        assert!(err.to_string().contains("Initial value of state variable 'fee' uses 'total'"), "{}", err);
+        let err = check("fee: u64 = block_timestamp();").unwrap_err();
+        assert!(err.to_string().contains("can only use literals, constants and msg_sender()"), "{}", err);
+        let err = check("balances: map<address, u64> = 0;").unwrap_err();
+        assert!(err.to_string().contains("is a map"), "{}", err);
+    }
+    
+    #[test]
+    fn test_signed_sign_tests() {
+        // The grammar has no signed types yet; JSON ASTs can contain them
+        let check = |condition: &str| {
+            let input = format!(r#"
+                contract Ledger {{
+                    state {{
+                        balance: u64;
+                    }}
+                    
+                    public fn adjust(delta: u64) {{
+                        require({}, "Rejected");
+                        balance = delta;
+                    }}
+                }}
+            "#, condition);
+            let mut contract = Contract::parse(&input).expect("Failed to parse");
+            contract.state[0].ty = Type::I64;
+            contract.functions[0].params[0].ty = Type::I64;
+            SemanticAnalyzer::new("Ledger".to_string()).analyze(&contract)
+        };
+        
+        // Signed values compare with each other and with literals
+        assert!(check("delta > balance").is_ok());
+        assert!(check("delta > 0 && 0 < balance").is_ok());
+        assert!(check("delta != 0").is_ok());
+        
+        // Sign tests are constant once Move compiles i64 as u64
+        let err = check("delta < 0").unwrap_err();
+        assert!(err.to_string().contains(
+            "Comparison 'delta < 0' in function 'adjust' tests the sign of an i64, but Move has no signed integers: \
+             i64 is compiled as unsigned there, where the comparison is always false"
+        ), "{}", err);
+        let err = check("0 <= balance").unwrap_err();
+        assert!(err.to_string().contains("Comparison '0 <= balance'"), "{}", err);
+        assert!(err.to_string().contains("always true"), "{}", err);
+    }
+    
+    #[test]
+    fn test_ternary_types() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Ternary {{
+                    state {{
+                        owner: address;
+                    }}
+                    
+                    public fn test(amount: u64, flag: bool) {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Ternary".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("let fee = amount > 1000 ? amount / 100 : 10;").is_ok());
+        assert!(check("let fee = flag ? amount : 0;").is_ok());
+        
+        let err = check("let fee = amount ? amount : 0;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify"));
+        let err = check("let fee = flag ? amount : owner;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify"));
+    }
+    
+    #[test]
+    fn test_array_and_tuple_literal_types() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Literals {{
+                    public fn test(amount: u64, flag: bool) -> u64 {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Literals".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("let limits = [amount, amount * 2, 10]; return limits[0];").is_ok());
+        assert!(check("let pair = (amount, flag); return amount;").is_ok());
+        
+        let err = check("let limits = [amount, flag]; return amount;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify"));
+        let err = check("let limits = []; return amount;").unwrap_err();
+        assert!(err.to_string().contains("empty array literal"));
+        let err = check("let pair = (amount, flag); return pair;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify"));
+    }
+    
+    #[test]
+    fn test_break_and_continue_need_a_loop() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Loops {{
+                    public fn test(limit: u64) {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Loops".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("for i in 0..limit { if i == 3 { continue; } if i > 5 { break; } }").is_ok());
+        assert!(check("while limit > 0 { for i in 0..limit { break; } break; }").is_ok());
+        
+        let err = check("break;").unwrap_err();
+        assert!(err.to_string().contains("`break` used outside of a loop"));
+        let err = check("if limit > 0 { continue; }").unwrap_err();
+        assert!(err.to_string().contains("`continue` used outside of a loop"));
+        let err = check("for i in 0..limit { } break;").unwrap_err();
+        assert!(err.to_string().contains("`break` used outside of a loop"));
+    }
+    
+    #[test]
+    fn test_integer_suffixes() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Pool {{
+                    const SCALE: u128 = 1_000_000_000_000_000_000_000u128;
+                    
+                    state {{
+                        reserve: u128;
+                        fee: u8;
+                    }}
+                    
+                    public fn test(amount: u64) {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Pool".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("reserve = reserve * 2u128 + SCALE;").is_ok());
+        assert!(check("fee = 30u8;").is_ok());
+        
+        let err = check("fee = 30u64;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify"));
+        let err = check("reserve = 5u8;").unwrap_err();
+        assert!(err.to_string().contains("Cannot unify"));
+    }
+    
+    #[test]
+    fn test_division_by_constant_zero() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Divide {{
+                    const NONE: u64 = 0;
+                    
+                    public fn test(amount: u64) {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Divide".to_string()).analyze(&contract)
+        };
+        
+        let err = check("let x = 10 / 0;").unwrap_err();
+        assert!(err.to_string().contains("Division by zero in function 'test'"));
+        assert!(check("let x = amount % NONE;").is_err());
+        assert!(check("let d = 5 - 5; let x = amount / d;").is_err());
+        
+        assert!(check("let x = amount / 2;").is_ok());
+        assert!(check("let d = amount; let x = 10 / d;").is_ok());
+        assert!(check("for i in 0..amount { let x = 10 / i; }").is_ok());
+    }
+    
+    #[test]
+    fn test_tuple_returns() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Pool {{
+                    public fn quote(amount: u64) -> (u64, u64) {{
+                        let fee = amount / 100;
+                        return (amount - fee, fee);
+                    }}
+                    
+                    public fn test(amount: u64) -> u64 {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Pool".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("let (out, fee) = quote(amount); return out + fee;").is_ok());
+        
+        let err = check("let (out, fee, extra) = quote(amount); return out;").unwrap_err();
+        assert!(err.to_string().contains("into 3 variables"));
+        assert!(check("let (out, fee) = amount; return out;").is_err());
+        assert!(check("let (out, fee) = quote(amount, 1); return out;").is_err());
+        // The elements are immutable bindings
+        assert!(check("let (out, fee) = quote(amount); out = 1; return out;").is_err());
+        assert!(check("let (out, fee) = quote(amount); return (out, fee);").is_err());
+    }
+    
+    #[test]
+    fn test_casts() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Amm {{
+                    state {{
+                        reserve: u64;
+                    }}
+                    
+                    public fn test(amount: u128, flag: bool) -> u64 {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Amm".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("return amount as u64;").is_ok());
+        assert!(check("let wide = reserve as u128 * amount; return wide as u64;").is_ok());
+        assert!(check("return 255 as u8 as u64;").is_ok());
+        
+        // Without the cast the types don't unify
+        assert!(check("return amount;").is_err());
+        let err = check("return 256 as u8 as u64;").unwrap_err();
+        assert!(err.to_string().contains("Constant 256 does not fit in U8"));
+        let err = check("return flag as u64;").unwrap_err();
+        assert!(err.to_string().contains("only integer types can be cast"));
+    }
+    
+    #[test]
+    fn test_bitwise_operands() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Flags {{
+                    state {{
+                        flags: u64;
+                        owner: address;
+                    }}
+                    
+                    public fn test(x: u64, n: u8) {{
+                        {}
+                    }}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Flags".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("flags = flags | (x << 2u8);").is_ok());
+        assert!(check("flags = (flags >> n) & 255; let clear = flags & x == 0;").is_ok());
+        
+        let err = check("let b = true & false;").unwrap_err();
+        assert!(err.to_string().contains("Bitwise operators need integer operands, found bool"), "{}", err);
+        assert!(check("let a = owner ^ owner;").unwrap_err().to_string().contains("found address"));
+        assert!(check("let b = x << true;").is_err());
+        // Both sides of &, | and ^ have the same type; the shift amount may differ
+        assert!(check("let b = x & n;").is_err());
+        let err = check("flags = x << 64;").unwrap_err();
+        assert!(err.to_string().contains("Shift by 64 in function 'test' is not less than the 64 bits of u64"), "{}", err);
+    }
+    
+    #[test]
+    fn test_struct_types() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Dex {{
+                    state {{
+                        pools: map<address, Pool>;
+                    }}
+                    
+                    struct Pool {{
+                        reserve_a: u64;
+                        locked: bool;
+                    }}
+                    
+                    {}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Dex".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("public fn lock(pool: address) { pools[pool].locked = true; }").is_ok());
+        assert!(check("public fn reserve(pool: address) -> u64 { return pools[pool].reserve_a; }").is_ok());
+        assert!(check("public fn lock(pool: address) { pools[pool].locked = 1; }").is_err());
+        
+        let err = check("public fn quote(pair: Pair) -> u64 { return 0; }").unwrap_err();
+        assert!(err.to_string().contains("Unknown type 'Pair' in the declaration of 'pair'"), "{}", err);
+    }
+    
+    #[test]
+    fn test_applied_modifiers() {
+        let check = |modifiers: &str| {
+            let input = format!(r#"
+                contract Guarded {{
+                    state {{
+                        owner: address;
+                        cap: u64;
+                    }}
+                    
+                    modifier onlyOwner() {{
+                        require(msg_sender() == owner, "Not owner");
+                        _;
+                    }}
+                    
+                    modifier below(limit: u64) {{
+                        require(cap < limit, "Over limit");
+                        _;
+                    }}
+                    
+                    public fn raise(amount: u64) {} {{
+                        cap = cap + amount;
+                    }}
+                }}
+            "#, modifiers);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Guarded".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("onlyOwner").is_ok());
+        
+        let err = check("onlyOnwer").unwrap_err();
+        assert!(err.to_string().contains("Unknown modifier 'onlyOnwer' on function 'raise'"), "{}", err);
+        let err = check("onlyOwner below").unwrap_err();
+        assert!(err.to_string().contains("Modifier 'below' expects 1 argument(s), got 0 on function 'raise'"), "{}", err);
+        
+        // Arguments see the function's parameters
+        assert!(check("onlyOwner below(amount * 2)").is_ok());
+        let err = check("below(100, 200)").unwrap_err();
+        assert!(err.to_string().contains("Modifier 'below' expects 1 argument(s), got 2 on function 'raise'"), "{}", err);
+        assert!(check("below(true)").is_err());
+        assert!(check("below(missing)").is_err());
+    }
+    
+    #[test]
+    fn test_option_operations() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Registry {{
+                    state {{
+                        owners: map<u64, address>;
+                        allowances: map<address, map<address, u64>>;
+                        fallback: option<u64>;
+                    }}
+                    
+                    {}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Registry".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("public fn set(v: u64) { fallback = Some(v); }").is_ok());
+        assert!(check("public fn clear() { fallback = None; }").is_ok());
+        assert!(check("view fn owner(id: u64) -> option<address> { return owners.get(id); }").is_ok());
+        assert!(check("view fn known(id: u64) -> bool { return owners.get(id).is_some() && fallback.is_none(); }").is_ok());
+        assert!(check("view fn limit(d: option<u64>) -> u64 { return d.unwrap_or(fallback.unwrap_or(1)); }").is_ok());
+        assert!(check("public fn set(v: bool) { fallback = Some(v); }").is_err());
+        assert!(check("view fn limit() -> u64 { return fallback.unwrap_or(true); }").is_err());
+        
+        let err = check("view fn nothing() -> bool { let x = None; return true; }").unwrap_err();
+        assert!(err.to_string().contains("Cannot infer the type of None here"), "{}", err);
+        let err = check("view fn wrong(v: u64) -> bool { return v.is_some(); }").unwrap_err();
+        assert!(err.to_string().contains("'is_some' needs an option, found"), "{}", err);
+        let err = check("view fn wrong(a: address) -> bool { return allowances.get(a).is_some(); }").unwrap_err();
+        assert!(err.to_string().contains("'get' reads maps of values; index nested maps instead"), "{}", err);
+        let err = check("view fn wrong() -> u64 { return fallback.unwrap_or(); }").unwrap_err();
+        assert!(err.to_string().contains("'unwrap_or' expects 1 argument(s), got 0"), "{}", err);
+    }
+    
+    #[test]
+    fn test_constructors() {
+        let check = |body: &str| {
+            let input = format!(r#"
+                contract Vault {{
+                    state {{
+                        limit: u64;
+                    }}
+                    
+                    {}
+                }}
+            "#, body);
+            let contract = Contract::parse(&input).expect("Failed to parse");
+            SemanticAnalyzer::new("Vault".to_string()).analyze(&contract)
+        };
+        
+        assert!(check("#[constructor] public fn setup(l: u64) { limit = l; }").is_ok());
+        assert!(check("public fn initialize(l: u64) { limit = l; }").is_ok());
+        // A marked constructor takes precedence over one named initialize
+        assert!(check("#[constructor] public fn setup() {} fn initialize() {}").is_ok());
+        
+        let err = check("#[constructor] public fn setup() {} #[constructor] public fn start() {}").unwrap_err();
+        assert!(err.to_string().contains("Contract 'Vault' has 2 constructors (setup, start), but can have only one"), "{}", err);
+        let err = check("#[constructor] fn setup() {}").unwrap_err();
+        assert!(err.to_string().contains("Constructor 'setup' must be a public, non-view function"), "{}", err);
+        let err = check("#[constructor] public view fn setup() -> u64 { return limit; }").unwrap_err();
+        assert!(err.to_string().contains("Constructor 'setup' must be a public, non-view function"), "{}", err);
+        let err = check("#[constructor] public fn setup() {} public fn reset() { setup(); }").unwrap_err();
+        assert!(err.to_string().contains("Function 'reset' calls the constructor 'setup', which only runs once"), "{}", err);
+        
+        // An unmarked initialize that isn't public is an ordinary function, with a warning
+        let contract = Contract::parse("contract Vault { private fn initialize() {} public fn run() { initialize(); } }").unwrap();
+        let mut analyzer = SemanticAnalyzer::new("Vault".to_string());
+        assert!(analyzer.analyze(&contract).is_ok());
+        assert!(contract.constructor().is_none());
+        let warnings = analyzer.get_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("Function 'initialize' is not a public, non-view function, so it is not the constructor"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Lowers a contract to the model of the `formal-verification` crate, for `ccdsl verify`.
+//!
+//! The verifier reads conditions and expressions as source text, so they are printed
+//! back as DSL. Modifiers are expanded first; the `require`s a function starts with,
+//! including those of its modifiers, become its preconditions and stay in its body,
+//! where they constrain the paths after them. Events have no effect on the verified
+//! properties and are left out. Statements the model cannot express (`while`, `match`,
+//! loops over collections, `break` and `continue`) are errors rather than skipped,
+//! so a certificate never covers less of a function than it claims.
+
+use crate::modifiers::expand_modifiers;
+use crate::printer::range_loop;
+use crate::{Contract, Expression, Statement, Type};
+use anyhow::{anyhow, Result};
+use formal_verification::verifier::{self, VarType};
+
+/// The verifier's view of `contract`, ready for `FormalVerifier::load_contract`
+pub fn to_verifier_contract(contract: &Contract) -> Result<verifier::Contract> {
+    let contract = expand_modifiers(contract)?;
+
+    let state = contract.state.iter()
+        .map(|var| verifier::StateVariable {
+            name: var.name.clone(),
+            var_type: var_type(&var.ty),
+            initial_value: var.initial_value.as_ref().map(Expression::to_source),
+        })
+        .collect();
+
+    let functions = contract.functions.iter()
+        .map(|func| {
+            let requires = func.body.iter()
+                .map_while(|stmt| match stmt {
+                    Statement::Require { condition, .. } | Statement::Assert { condition, .. } => Some(condition.to_source()),
+                    _ => None,
+                })
+                .collect();
+            Ok(verifier::Function {
+                name: func.name.clone(),
+                params: func.params.iter()
+                    .map(|p| verifier::Parameter { name: p.name.clone(), param_type: var_type(&p.ty) })
+                    .collect(),
+                return_type: func.return_type.as_ref().map(var_type),
+                requires,
+                ensures: Vec::new(),
+                body: statements(&func.name, &func.body)?,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(verifier::Contract {
+        name: contract.name.clone(),
+        state,
+        functions,
+        invariants: Vec::new(),
+    })
+}
+
+/// Narrower unsigned integers are checked as `U64`; types the verifier has no
+/// variant for keep their DSL name
+fn var_type(ty: &Type) -> VarType {
+    match ty {
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 => VarType::U64,
+        Type::U128 => VarType::U128,
+        Type::Bool => VarType::Bool,
+        Type::Address => VarType::Address,
+        Type::Map(key, value) => VarType::Map(Box::new(var_type(key)), Box::new(var_type(value))),
+        other => VarType::Custom(other.to_string()),
+    }
+}
+
+fn statements(function: &str, body: &[Statement]) -> Result<Vec<verifier::Statement>> {
+    let mut lowered = Vec::new();
+    for stmt in body {
+        lowered.extend(statement(function, stmt)?);
+    }
+    Ok(lowered)
+}
+
+fn statement(function: &str, stmt: &Statement) -> Result<Vec<verifier::Statement>> {
+    let unsupported = |what: &str| anyhow!("Function '{}' uses {}, which the verifier cannot model yet", function, what);
+    let lowered = match stmt {
+        Statement::Let { name, value, .. } => verifier::Statement::Assignment(name.clone(), value.to_source()),
+        Statement::LetTuple { names, value } => {
+            verifier::Statement::Assignment(format!("({})", names.join(", ")), value.to_source())
+        }
+        Statement::Assign { target, value } => verifier::Statement::Assignment(target.to_source(), value.to_source()),
+        Statement::Require { condition, .. } | Statement::Assert { condition, .. } => {
+            verifier::Statement::Require(condition.to_source())
+        }
+        Statement::If { condition, then_block, else_block } => verifier::Statement::If(
+            condition.to_source(),
+            statements(function, then_block)?,
+            else_block.as_ref().map(|block| statements(function, block)).transpose()?,
+        ),
+        // `for i in a..=b` runs to `b + 1`, exclusive
+        Statement::For { init, condition, update, body } => {
+            let (variable, start, op, end) = range_loop(init, condition, update)
+                .ok_or_else(|| unsupported("a loop other than `for i in a..b`"))?;
+            let end = if op == "..=" { format!("{} + 1", end.to_source()) } else { end.to_source() };
+            verifier::Statement::For(variable.to_string(), start.to_source(), end, statements(function, body)?)
+        }
+        Statement::Return { value } => verifier::Statement::Return(value.as_ref().map(Expression::to_source)),
+        Statement::Block(block) => return statements(function, block),
+        Statement::Emit { .. } | Statement::Expression(_) | Statement::Placeholder => return Ok(Vec::new()),
+        Statement::While { .. } => return Err(unsupported("`while`")),
+        Statement::ForEach { .. } => return Err(unsupported("a loop over a collection")),
+        Statement::Match { .. } => return Err(unsupported("`match`")),
+        Statement::Break | Statement::Continue => return Err(unsupported("`break` or `continue`")),
+    };
+    Ok(vec![lowered])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lowers_modifiers_to_preconditions() {
+        let contract = Contract::parse(r#"
+            contract Vault {
+                state {
+                    owner: address;
+                    balances: map<address, u64>;
+                }
+
+                modifier onlyOwner() {
+                    require(msg_sender() == owner, "Only owner");
+                    _;
+                }
+
+                public fn credit(to: address, amount: u64) onlyOwner {
+                    require(amount > 0);
+                    balances[to] = balances[to] + amount;
+                    for i in 0..=amount {
+                        emit Credited(to, i);
+                    }
+                }
+            }
+        "#).expect("Failed to parse");
+
+        let lowered = to_verifier_contract(&contract).unwrap();
+        assert!(matches!(lowered.state[1].var_type, VarType::Map(..)));
+        let credit = &lowered.functions[0];
+        assert_eq!(credit.requires, ["msg_sender() == owner", "amount > 0"]);
+        assert!(matches!(&credit.body[2], verifier::Statement::Assignment(target, value)
+            if target == "balances[to]" && value == "balances[to] + amount"));
+        assert!(matches!(&credit.body[3], verifier::Statement::For(i, start, end, body)
+            if i == "i" && start == "0" && end == "amount + 1" && body.is_empty()));
+    }
+
+    #[test]
+    fn test_rejects_statements_it_cannot_model() {
+        let contract = Contract::parse("contract C { public fn spin(n: u64) { while n > 0 { n = n - 1; } } }").unwrap();
+        let err = to_verifier_contract(&contract).unwrap_err();
+        assert!(err.to_string().contains("Function 'spin' uses `while`"), "{}", err);
+    }
+}
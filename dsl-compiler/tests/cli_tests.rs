@@ -0,0 +1,603 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::process::{Command, Output};
+
+fn ccdsl(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cross-chain-dsl"))
+        .args(args)
+        .output()
+        .expect("Failed to run the compiler")
+}
+
+/// A fresh output directory for one test
+fn output_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ccdsl-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn example(file: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("examples").join(file).display().to_string()
+}
+
+#[test]
+fn test_compile_generates_code_for_the_input_contract() {
+    let out = output_dir("token");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let solana = fs::read_to_string(out.join("solana/programs/token/src/lib.rs")).unwrap();
+    let aptos = fs::read_to_string(out.join("aptos/sources/token.move")).unwrap();
+    for name in ["transfer", "approve", "transfer_from", "mint", "burn", "balance_of", "get_total_supply"] {
+        assert!(solana.contains(&format!("pub fn {}(", name)), "Solana output is missing {}", name);
+        assert!(aptos.contains(&format!(" fun {}(", name)), "Move output is missing {}", name);
+    }
+}
+
+#[test]
+fn test_compile_fails_with_semantic_errors() {
+    let out = output_dir("invalid");
+    fs::create_dir_all(&out).unwrap();
+    let input = out.join("invalid.ccdsl");
+    fs::write(&input, r#"
+        contract Broken {
+            state {
+                total: u64;
+            }
+            
+            public fn set(flag: bool) {
+                emit Missing(flag);
+            }
+        }
+    "#).unwrap();
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap()]);
+    assert!(!result.status.success());
+    
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("Unknown event 'Missing'"), "{}", stderr);
+    assert!(!out.join("solana").exists());
+    assert!(!String::from_utf8_lossy(&result.stdout).contains("✅"));
+}
+
+#[test]
+fn test_ast_prints_the_parsed_program_as_json() {
+    let result = ccdsl(&["ast", "-i", &example("token.ccdsl")]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    let program: cross_chain_dsl::Program = serde_json::from_str(&stdout).expect("Output is not a program");
+    assert_eq!(program.contracts[0].name, "Token");
+    assert!(program.contracts[0].functions.iter().any(|f| f.name == "transfer_from"));
+    
+    let pretty = ccdsl(&["ast", "-i", &example("token.ccdsl"), "--pretty"]);
+    assert!(pretty.status.success());
+    assert!(String::from_utf8(pretty.stdout).unwrap().lines().count() > 1);
+}
+
+#[test]
+fn test_ast_reports_parse_errors_on_stderr() {
+    let dir = output_dir("ast-error");
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("broken.ccdsl");
+    fs::write(&input, "contract Broken { state { x: u64 }").unwrap();
+    
+    let result = ccdsl(&["ast", "-i", input.to_str().unwrap()]);
+    assert!(!result.status.success());
+    assert!(result.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("broken.ccdsl"));
+}
+
+#[test]
+fn test_compile_accepts_a_json_ast() {
+    let out = output_dir("json");
+    fs::create_dir_all(&out).unwrap();
+    let ast = ccdsl(&["ast", "-i", &example("token.ccdsl")]);
+    assert!(ast.status.success());
+    let input = out.join("token.json");
+    fs::write(&input, &ast.stdout).unwrap();
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "--input-format", "json", "-t", "solana", "-o", out.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let solana = fs::read_to_string(out.join("solana/programs/token/src/lib.rs")).unwrap();
+    for name in ["transfer", "approve", "mint", "burn"] {
+        assert!(solana.contains(&format!("pub fn {}(", name)), "Solana output is missing {}", name);
+    }
+}
+
+#[test]
+fn test_json_ast_must_pass_semantic_analysis() {
+    let out = output_dir("json-invalid");
+    fs::create_dir_all(&out).unwrap();
+    let source = out.join("broken.ccdsl");
+    fs::write(&source, r#"
+        contract Broken {
+            public fn set(flag: bool) {
+                emit Missing(flag);
+            }
+        }
+    "#).unwrap();
+    let ast = ccdsl(&["ast", "-i", source.to_str().unwrap()]);
+    assert!(ast.status.success());
+    let input = out.join("broken.json");
+    fs::write(&input, &ast.stdout).unwrap();
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "--input-format", "json", "-t", "solana", "-o", out.to_str().unwrap()]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Unknown event 'Missing'"));
+    assert!(!out.join("solana").exists());
+    
+    fs::write(&input, r#"{"contracts": 1}"#).unwrap();
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "--input-format", "json", "-o", out.to_str().unwrap()]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Invalid program JSON"));
+}
+
+#[test]
+fn test_lint_prints_greppable_findings() {
+    let dir = output_dir("lint");
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("vault.ccdsl");
+    fs::write(&input, r#"contract Vault {
+    state {
+        owner: address;
+        total: u64;
+    }
+    
+    public fn set_total(value: u64) {
+        total = value;
+    }
+}
+"#).unwrap();
+    
+    let result = ccdsl(&["lint", "-i", input.to_str().unwrap()]);
+    assert!(!result.status.success());
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    assert_eq!(
+        stdout.trim(),
+        format!("missing-access-control: {}:7: [high] Public function 'set_total' writes 'total' without checking msg_sender()", input.display())
+    );
+    
+    fs::write(&input, r#"contract Vault {
+    state {
+        owner: address;
+        total: u64;
+    }
+    
+    public fn set_total(value: u64) {
+        require(msg_sender() == owner, "Only owner");
+        total = value;
+    }
+}
+"#).unwrap();
+    let result = ccdsl(&["lint", "-i", input.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stdout));
+}
+
+#[test]
+fn test_compile_injects_the_program_id() {
+    let out = output_dir("program-id");
+    fs::create_dir_all(&out).unwrap();
+    let input = out.join("counter.ccdsl");
+    fs::write(&input, r#"
+        contract Counter {
+            state {
+                count: u64;
+            }
+            
+            public fn increment() {
+                count = count + 1;
+            }
+        }
+    "#).unwrap();
+    let compile = |extra: &[&str]| {
+        let mut args = vec!["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap()];
+        args.extend_from_slice(extra);
+        ccdsl(&args)
+    };
+    
+    // Without one, a placeholder is used and the compile reports a warning
+    let result = compile(&[]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(String::from_utf8_lossy(&result.stdout).contains("1 warning(s)"));
+    let solana = fs::read_to_string(out.join("solana/programs/counter/src/lib.rs")).unwrap();
+    assert!(solana.contains("// TODO: placeholder program id"), "{}", solana);
+    
+    // From ccdsl.toml next to the input; --overwrite refreshes the Anchor.toml written above
+    fs::write(out.join("ccdsl.toml"), "[solana]\nprogram_id = \"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA\"\n").unwrap();
+    let result = compile(&["--overwrite"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(!String::from_utf8_lossy(&result.stdout).contains("warning(s)"));
+    let solana = fs::read_to_string(out.join("solana/programs/counter/src/lib.rs")).unwrap();
+    assert!(solana.contains("declare_id!(\"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA\");"), "{}", solana);
+    assert!(!solana.contains("TODO"), "{}", solana);
+    let anchor = fs::read_to_string(out.join("solana/Anchor.toml")).unwrap();
+    assert!(anchor.contains("[programs.localnet]\ncounter = \"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA\""), "{}", anchor);
+    
+    // The flag wins over the file
+    let result = compile(&["--program-id", "11111111111111111111111111111111"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let solana = fs::read_to_string(out.join("solana/programs/counter/src/lib.rs")).unwrap();
+    assert!(solana.contains("declare_id!(\"11111111111111111111111111111111\");"), "{}", solana);
+    
+    // Ids must be 32-byte base58 keys
+    let result = compile(&["--program-id", "not-base58!"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("is not valid base58"));
+    fs::write(out.join("ccdsl.toml"), "[solana]\nprogram_id = \"abc\"\n").unwrap();
+    let result = compile(&[]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("a public key has 32"), "{}", String::from_utf8_lossy(&result.stderr));
+}
+
+#[test]
+fn test_compile_targets_the_configured_versions() {
+    let out = output_dir("target-versions");
+    fs::create_dir_all(&out).unwrap();
+    let input = out.join("counter.ccdsl");
+    fs::write(&input, "contract Counter { event Counted(n: u64); public fn bump() { emit Counted(1); } }").unwrap();
+    let compile = |target: &str, extra: &[&str]| {
+        let mut args = vec!["compile", "-i", input.to_str().unwrap(), "-t", target, "-o", out.to_str().unwrap()];
+        args.extend_from_slice(extra);
+        ccdsl(&args)
+    };
+    
+    fs::write(out.join("ccdsl.toml"), "[solana]\nanchor_version = \"0.30.1\"\n\n[aptos]\nframework_rev = \"aptos-node-v1.5.0\"\n").unwrap();
+    let result = compile("solana", &[]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let anchor = fs::read_to_string(out.join("solana/Anchor.toml")).unwrap();
+    assert!(anchor.contains("anchor_version = \"0.30.1\""), "{}", anchor);
+    let result = compile("aptos", &[]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("predates module events"));
+    
+    // The flags win over the file
+    let result = compile("solana", &["--anchor-version", "0.29", "--overwrite"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let anchor = fs::read_to_string(out.join("solana/Anchor.toml")).unwrap();
+    assert!(anchor.contains("anchor_version = \"0.29.0\""), "{}", anchor);
+    let result = compile("aptos", &["--aptos-framework-rev", "mainnet"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let result = compile("solana", &["--anchor-version", "0.12"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Unsupported Anchor version '0.12'"));
+    fs::write(out.join("ccdsl.toml"), "[solana]\nanchor_version = \"latest\"\n").unwrap();
+    let result = compile("solana", &[]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Invalid [solana] anchor_version"));
+}
+
+#[test]
+fn test_compile_move_writes_a_package() {
+    let out = output_dir("move-package");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap(), "--move-address", "my_token"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    for (chain, framework) in [("aptos", "AptosFramework"), ("sui", "Sui")] {
+        let manifest: toml::Value = toml::from_str(&fs::read_to_string(out.join(chain).join("Move.toml")).unwrap())
+            .unwrap_or_else(|e| panic!("{}/Move.toml is not TOML: {}", chain, e));
+        assert_eq!(manifest["package"]["name"].as_str(), Some("Token"));
+        assert!(manifest["addresses"].get("my_token").is_some(), "{}", manifest);
+        assert!(manifest["dependencies"][framework]["git"].is_str(), "{}", manifest);
+        let module = fs::read_to_string(out.join(chain).join("sources/token.move")).unwrap();
+        assert!(module.starts_with("module my_token::token {"), "{}", module);
+    }
+    
+    // From ccdsl.toml, per chain
+    let out = output_dir("move-address-config");
+    fs::create_dir_all(&out).unwrap();
+    let input = out.join("counter.ccdsl");
+    fs::write(&input, "contract Counter { state { n: u64; } public fn bump() { n = n + 1; } }").unwrap();
+    fs::write(out.join("ccdsl.toml"), "[aptos]\nnamed_address = \"counter_aptos\"\n\n[sui]\nnamed_address = \"counter_sui\"\n").unwrap();
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "all", "-o", out.to_str().unwrap()]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(fs::read_to_string(out.join("aptos/Move.toml")).unwrap().contains("counter_aptos = \"_\""));
+    assert!(fs::read_to_string(out.join("sui/sources/counter.move")).unwrap().starts_with("module counter_sui::counter {"));
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "aptos", "-o", out.to_str().unwrap(), "--move-address", "my-token"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Named address 'my-token' is not a Move identifier"));
+}
+
+#[test]
+fn test_compile_solana_writes_an_anchor_workspace() {
+    let out = output_dir("workspace");
+    let token = example("token.ccdsl");
+    let compile = |extra: &[&str]| {
+        let mut args = vec!["compile", "-i", &token, "-t", "solana", "-o", out.to_str().unwrap()];
+        args.extend_from_slice(extra);
+        ccdsl(&args)
+    };
+    let result = compile(&["--anchor-version", "0.30"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let solana = out.join("solana");
+    for file in ["Anchor.toml", "Cargo.toml", "programs/token/Cargo.toml", "programs/token/Xargo.toml", "programs/token/src/lib.rs"] {
+        assert!(solana.join(file).is_file(), "{} was not written", file);
+    }
+    let workspace: toml::Value = toml::from_str(&fs::read_to_string(solana.join("Cargo.toml")).unwrap()).unwrap();
+    assert_eq!(workspace["workspace"]["members"][0].as_str(), Some("programs/*"));
+    let program: toml::Value = toml::from_str(&fs::read_to_string(solana.join("programs/token/Cargo.toml")).unwrap()).unwrap();
+    assert_eq!(program["package"]["name"].as_str(), Some("token"));
+    assert_eq!(program["dependencies"]["anchor-lang"]["version"].as_str(), Some("0.30.0"));
+    assert!(program["features"].get("idl-build").is_some(), "{}", program);
+    let anchor: toml::Value = toml::from_str(&fs::read_to_string(solana.join("Anchor.toml")).unwrap()).unwrap();
+    assert!(anchor["programs"]["localnet"].get("token").is_some(), "{}", anchor);
+    
+    // Recompiling rewrites the program but keeps edited project files
+    fs::write(solana.join("programs/token/Cargo.toml"), "# edited\n").unwrap();
+    fs::write(solana.join("programs/token/src/lib.rs"), "// edited\n").unwrap();
+    let result = compile(&[]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(String::from_utf8_lossy(&result.stdout).contains("Kept"));
+    assert_eq!(fs::read_to_string(solana.join("programs/token/Cargo.toml")).unwrap(), "# edited\n");
+    assert!(fs::read_to_string(solana.join("programs/token/src/lib.rs")).unwrap().contains("pub fn transfer("));
+    
+    let result = compile(&["--overwrite"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(fs::read_to_string(solana.join("programs/token/Cargo.toml")).unwrap().contains("[dependencies]"));
+    
+    // The cluster comes from ccdsl.toml
+    let input = out.join("token.ccdsl");
+    fs::copy(&token, &input).unwrap();
+    fs::write(out.join("ccdsl.toml"), "[solana]\ncluster = \"devnet\"\n").unwrap();
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap(), "--overwrite"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let anchor = fs::read_to_string(solana.join("Anchor.toml")).unwrap();
+    assert!(anchor.contains("[programs.devnet]") && anchor.contains("cluster = \"Devnet\""), "{}", anchor);
+    fs::write(out.join("ccdsl.toml"), "[solana]\ncluster = \"moon\"\n").unwrap();
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap()]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Invalid [solana] cluster 'moon'"));
+}
+
+#[test]
+fn test_with_tests_emits_anchor_tests() {
+    let out = output_dir("with-tests");
+    let input = example("token.ccdsl");
+    let compile = |extra: &[&str]| {
+        let mut args = vec!["compile", "-i", &input, "-t", "solana", "-o", out.to_str().unwrap()];
+        args.extend_from_slice(extra);
+        ccdsl(&args)
+    };
+    
+    let result = compile(&[]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(!out.join("solana/tests").exists());
+    
+    let result = compile(&["--with-tests"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    let tests = fs::read_to_string(out.join("solana/tests/token.ts")).unwrap();
+    assert!(tests.contains("import { Token } from \"../target/types/token\";"), "{}", tests);
+    for method in ["transfer", "approve", "transferFrom", "mint", "burn", "balanceOf", "getTotalSupply"] {
+        assert!(tests.contains(&format!("      .{}(", method)), "The tests don't call {}", method);
+    }
+    let anchor = fs::read_to_string(out.join("solana/Anchor.toml")).unwrap();
+    assert!(anchor.contains("[scripts]\ntest = "), "{}", anchor);
+}
+
+#[test]
+fn test_with_tests_emits_move_unit_tests() {
+    let out = output_dir("with-move-tests");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "aptos", "-o", out.to_str().unwrap(),
+        "--move-address", "token_addr", "--with-tests"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let tests = fs::read_to_string(out.join("aptos/tests/token_tests.move")).unwrap();
+    assert!(tests.starts_with("#[test_only]\nmodule token_addr::token_tests {\n"), "{}", tests);
+    assert!(tests.contains("account::create_account_for_test(@token_addr)"), "{}", tests);
+    for function in ["transfer", "approve", "mint", "burn"] {
+        assert!(tests.contains(&format!("    fun test_{}() {{\n", function)), "No test for {}:\n{}", function, tests);
+    }
+    let manifest = fs::read_to_string(out.join("aptos/Move.toml")).unwrap();
+    assert!(manifest.contains("[dev-addresses]\ntoken_addr = "), "{}", manifest);
+    
+    // --emit-tests is the same option, and covers every target
+    let out = output_dir("emit-tests");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap(), "--emit-tests"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    for file in ["solana/tests/token.ts", "aptos/tests/token_tests.move", "sui/tests/token_tests.move"] {
+        assert!(out.join(file).is_file(), "{} was not written", file);
+    }
+    let tests = fs::read_to_string(out.join("sui/tests/token_tests.move")).unwrap();
+    assert!(tests.contains("        let state = test_scenario::take_shared<State>(&scenario);\n        token::mint(&mut state, @0x0, 0, test_scenario::ctx(&mut scenario));\n"), "{}", tests);
+}
+
+#[test]
+fn test_move_capabilities_guards_mint_with_a_resource() {
+    let out = output_dir("move-capabilities");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap(), "--move-capabilities"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let aptos = fs::read_to_string(out.join("aptos/sources/token.move")).unwrap();
+    assert!(aptos.contains("    struct MintCapability has key {}\n"), "{}", aptos);
+    assert!(aptos.contains("acquires State, MintCapability {"), "{}", aptos);
+    // Sui keeps the address check
+    let sui = fs::read_to_string(out.join("sui/sources/token.move")).unwrap();
+    assert!(!sui.contains("MintCapability"), "{}", sui);
+}
+
+#[test]
+fn test_check_output_rejects_solana_code_that_does_not_parse() {
+    let out = output_dir("check-output");
+    fs::create_dir_all(&out).unwrap();
+    let input = out.join("ledger.ccdsl");
+    // `impl` is a Rust keyword
+    fs::write(&input, r#"
+        contract Ledger {
+            state {
+                balance: u64;
+            }
+            
+            public fn adjust(impl: u64) {
+                balance = impl;
+            }
+        }
+    "#).unwrap();
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap()]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("Generated Rust does not parse"), "{}", stderr);
+    assert!(stderr.contains("pub fn adjust(ctx: Context<Adjust>, impl: u64)"), "{}", stderr);
+    assert!(!out.join("solana").exists());
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap(), "--check-output=false"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(out.join("solana/programs/ledger/src/lib.rs").exists());
+}
+
+#[test]
+fn test_emit_interface_describes_each_chain_entrypoint() {
+    let out = output_dir("interface");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap(), "--emit-interface"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let json = fs::read_to_string(out.join("token.interface.json")).unwrap();
+    let interface: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(interface["contract"], "Token");
+    let transfer = interface["functions"].as_array().unwrap().iter()
+        .find(|f| f["name"] == "transfer")
+        .unwrap();
+    assert_eq!(transfer["params"][0], serde_json::json!({ "name": "to", "type": "address" }));
+    
+    let solana = &transfer["chains"]["solana"];
+    assert_eq!(solana["name"], "transfer");
+    assert_eq!(solana["args"][0]["type"], "Pubkey");
+    assert_eq!(solana["signers"], serde_json::json!(["user"]));
+    let accounts: Vec<&str> = solana["accounts"].as_array().unwrap().iter()
+        .map(|a| a["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(accounts, ["user", "balances_from", "balances_to", "system_program"]);
+    assert_eq!(solana["accounts"][2]["seeds"][1], serde_json::json!({ "kind": "arg", "value": "to" }));
+    
+    let aptos = &transfer["chains"]["aptos"];
+    assert_eq!(aptos["name"], "cross_chain::token::transfer");
+    assert_eq!(aptos["entry"], true);
+    assert_eq!(aptos["args"][0]["type"], "address");
+    assert_eq!(aptos["signers"], serde_json::json!(["account"]));
+    assert!(aptos.get("accounts").is_none());
+    
+    // Sui takes the shared State object first and the sender from the TxContext
+    let sui = &transfer["chains"]["sui"];
+    assert_eq!(sui["name"], "cross_chain::token::transfer");
+    assert_eq!(sui["entry"], true);
+    assert_eq!(sui["args"][0], serde_json::json!({ "name": "state", "type": "&mut State" }));
+    assert_eq!(sui["args"][1]["type"], "address");
+    assert_eq!(sui["signers"], serde_json::json!([]));
+}
+
+#[test]
+fn test_repl_prints_expression_types() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cross-chain-dsl"))
+        .arg("repl")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to run the compiler");
+    child.stdin.take().unwrap()
+        .write_all(b"10 + 20 * 2\n\n1_000u128 as u8\ntrue && 1\nmissing + 1;\nlet x = 1\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", stdout);
+    assert_eq!(lines[0], "u64");
+    assert_eq!(lines[1], "error: Constant 1000 does not fit in U8");
+    assert!(lines[2].starts_with("error: ") && lines[2].contains("Bool"), "{}", lines[2]);
+    assert_eq!(lines[3], "error: Undefined identifier 'missing'");
+    assert_eq!(lines[4], "error: `let x = 1` is not an expression");
+}
+
+#[test]
+fn test_spl_token_standard_uses_the_token_program() {
+    let out = output_dir("spl");
+    fs::create_dir_all(&out).unwrap();
+    let input = out.join("coin.ccdsl");
+    fs::write(&input, r#"
+        contract Coin {
+            state {
+                total_supply: u64;
+                balances: map<address, u64>;
+            }
+            
+            public fn initialize(supply: u64) {
+                total_supply = supply;
+                balances[msg_sender()] = supply;
+            }
+            
+            public fn transfer(to: address, amount: u64) {
+                balances[msg_sender()] = balances[msg_sender()] - amount;
+                balances[to] = balances[to] + amount;
+            }
+        }
+    "#).unwrap();
+    
+    let result = ccdsl(&["compile", "-i", input.to_str().unwrap(), "-t", "solana", "-o", out.to_str().unwrap(),
+        "--solana-token-standard", "spl", "--with-tests", "--emit-interface"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let program = fs::read_to_string(out.join("solana/programs/coin/src/lib.rs")).unwrap();
+    assert!(program.contains("anchor_spl::token::mint_to("), "{}", program);
+    assert!(program.contains("anchor_spl::token::transfer("), "{}", program);
+    assert!(!program.contains("pub struct State"), "{}", program);
+    let tests = fs::read_to_string(out.join("solana/tests/coin.ts")).unwrap();
+    assert!(tests.contains("getAssociatedTokenAddressSync(pda(Buffer.from(\"mint\")), provider.wallet.publicKey)"), "{}", tests);
+    let interface: serde_json::Value = serde_json::from_str(&fs::read_to_string(out.join("coin.interface.json")).unwrap()).unwrap();
+    let accounts = &interface["functions"][0]["chains"]["solana"]["accounts"];
+    assert_eq!(accounts[1]["name"], "mint");
+    assert_eq!(accounts[1]["seeds"][0]["value"], "mint");
+    
+    // transfer_from moves tokens the signer doesn't own
+    let token = example("token.ccdsl");
+    let result = ccdsl(&["compile", "-i", &token, "-t", "solana", "-o", out.to_str().unwrap(), "--solana-token-standard", "spl"]);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("'transfer_from' can only move tokens out of msg_sender()'s balance"), "{}", stderr);
+}
+
+#[test]
+fn test_compile_stdout_prints_code_without_writing() {
+    let out = output_dir("stdout");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "solana", "-o", out.to_str().unwrap(), "--stdout"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(stdout.starts_with("use anchor_lang::prelude::*;"), "{}", stdout);
+    assert!(stdout.contains("pub fn transfer("), "{}", stdout);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Generating Solana code"));
+    assert!(!out.exists());
+    
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap(), "--stdout"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("--stdout needs a single target"));
+    assert!(!out.exists());
+}
+
+#[cfg(not(feature = "verify"))]
+#[test]
+fn test_verify_needs_the_verify_feature() {
+    let result = ccdsl(&["verify", "-i", &example("token.ccdsl")]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("rebuild it with `--features verify`"));
+}
+
+#[test]
+fn test_compile_dry_run_lists_files_without_writing() {
+    let out = output_dir("dry-run");
+    let result = ccdsl(&["compile", "-i", &example("token.ccdsl"), "-t", "all", "-o", out.to_str().unwrap(), "--emit-idl", "--dry-run"]);
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    for file in ["solana/programs/token/src/lib.rs", "solana/Anchor.toml", "solana/Cargo.toml", "solana/programs/token/Cargo.toml", "solana/token.idl.json", "aptos/sources/token.move", "aptos/Move.toml", "aptos/token.errors.json", "aptos/token.idl.json", "sui/sources/token.move", "sui/Move.toml", "sui/token.errors.json"] {
+        let path = out.join(file);
+        assert!(stdout.contains(&format!("Would write {} (", path.display())), "{} is not listed:\n{}", file, stdout);
+    }
+    assert!(!out.exists());
+}
@@ -0,0 +1,2941 @@
+use cross_chain_dsl::{Contract, Expression, ModifierCall, Program, Statement, Type};
+use cross_chain_dsl::idl::IdlTypeDefinitionTy;
+use cross_chain_dsl::modifiers::expand_modifiers;
+use cross_chain_dsl::interface::Argument;
+use cross_chain_dsl::codegen::{ArithmeticMode, solana::{AnchorVersion, SolanaCodeGenerator, TokenStandard}, move_gen::{MoveChain, MoveCodeGenerator}, sui_gen::SuiCodeGenerator};
+
+#[test]
+fn test_solana_state_len_uses_string_bounds() {
+    let input = r#"
+        contract Profile {
+            state {
+                owner: address;
+                name: string<32>;
+                bio: string;
+                score: u64;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    // `bio` has no bound, so its size is unknown unless a default is configured
+    let err = SolanaCodeGenerator::new().generate(&contract).unwrap_err();
+    assert!(err.to_string().contains("state variable 'bio'"), "{}", err);
+    
+    let code = SolanaCodeGenerator::new()
+        .with_default_max_len(64)
+        .generate(&contract)
+        .expect("Failed to generate");
+    assert!(code.contains("pub const LEN: usize = 32 + 36 + 68 + 8;"));
+    
+    let code = SolanaCodeGenerator::new()
+        .with_default_max_len(16)
+        .generate(&contract)
+        .expect("Failed to generate");
+    assert!(code.contains("pub const LEN: usize = 32 + 36 + 20 + 8;"));
+}
+
+#[test]
+fn test_solana_checks_string_length_on_write() {
+    let input = r#"
+        contract Profile {
+            state {
+                name: string<32>;
+                labels: map<address, string<16>>;
+            }
+            
+            public fn rename(new_name: string) {
+                name = new_name;
+            }
+            
+            public fn reset() {
+                name = "anonymous";
+            }
+            
+            public fn label(text: string) {
+                labels[msg_sender()] = text;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(code.contains("pub const LEN: usize = 36;"), "{}", code);
+    assert!(code.contains("require!(new_name.len() <= 32, ErrorCode::ValueTooLong);\n        ctx.accounts.state.name = new_name;"), "{}", code);
+    assert!(code.contains("require!(text.len() <= 16, ErrorCode::ValueTooLong);"), "{}", code);
+    assert!(code.contains("#[msg(\"Value exceeds maximum length\")]\n    ValueTooLong,"), "{}", code);
+    // A literal that fits needs no check
+    let reset = &code[code.find("pub fn reset").unwrap()..code.find("pub fn label").unwrap()];
+    assert!(!reset.contains("require!"), "{}", reset);
+    
+    // Move vectors grow as needed, so unbounded strings are fine there
+    let unbounded = Contract::parse(&input.replace("string<32>", "string")).expect("Failed to parse");
+    assert!(SolanaCodeGenerator::new().generate(&unbounded).is_err());
+    assert!(MoveCodeGenerator::new().generate(&unbounded).is_ok());
+}
+
+#[test]
+fn test_cross_contract_calls_become_cpi_and_module_calls() {
+    let input = r#"
+        contract Oracle {
+            public fn price(asset: address) -> u64 {
+                return 100;
+            }
+        }
+        
+        contract Vault {
+            public fn value(asset: address) -> u64 {
+                return Oracle.price(asset);
+            }
+        }
+    "#;
+    
+    let program = Program::parse(input).expect("Failed to parse");
+    let vault = &program.contracts[1];
+    
+    let solana = SolanaCodeGenerator::new().generate(vault).expect("Failed to generate");
+    assert!(solana.contains("oracle::cpi::price(CpiContext::new(ctx.accounts.oracle_program.to_account_info()"));
+    assert!(solana.contains("pub oracle_program: Program<'info, oracle::program::Oracle>,"));
+    
+    let aptos = MoveCodeGenerator::new().generate(vault).expect("Failed to generate");
+    assert!(aptos.contains("cross_chain::oracle::price(asset)"));
+}
+
+#[test]
+fn test_constants_are_emitted() {
+    let input = r#"
+        contract Fees {
+            const FEE_BPS: u64 = 30;
+            const NAME: string = "fees";
+            
+            public fn fee(amount: u64) -> u64 {
+                return amount * FEE_BPS;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("pub const FEE_BPS: u64 = 30;"));
+    assert!(solana.contains("pub const NAME: &str = \"fees\";"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    const FEE_BPS: u64 = 30;"));
+    assert!(aptos.contains("    const NAME: vector<u8> = b\"fees\";"));
+}
+
+#[test]
+fn test_modifiers_are_expanded_around_function_bodies() {
+    let input = r#"
+        contract Secure {
+            state {
+                owner: address;
+                paused: bool;
+                total: u64;
+            }
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Only owner");
+                _;
+            }
+            
+            modifier whenNotPaused() {
+                require(!paused, "Paused");
+                _;
+            }
+            
+            public fn withdraw(amount: u64) onlyOwner whenNotPaused {
+                total = total - amount;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let expanded = expand_modifiers(&contract).expect("Failed to expand");
+    let body = &expanded.functions[0].body;
+    assert_eq!(body.len(), 3);
+    assert!(matches!(&body[0], Statement::Require { message: Some(m), .. } if m == "Only owner"));
+    assert!(matches!(&body[1], Statement::Require { message: Some(m), .. } if m == "Paused"));
+    assert!(matches!(&body[2], Statement::Assign { .. }));
+    assert!(expanded.functions[0].modifiers.is_empty());
+    
+    // onlyOwner becomes a constraint on the signer; whenNotPaused stays inline
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("    #[account(address = state.owner @ ErrorCode::OnlyOwner)]\n    pub user: Signer<'info>,"), "{}", solana);
+    assert!(!solana.contains("require!((ctx.accounts.user.key() == ctx.accounts.state.owner)"), "{}", solana);
+    let paused_check = solana.find("require!(!(ctx.accounts.state.paused)").expect("pause check missing");
+    let update = solana.find("ctx.accounts.state.total = ctx.accounts.state.total.checked_sub(amount).ok_or(ErrorCode::Underflow)?;").expect("body missing");
+    assert!(paused_check < update);
+    
+    let mut unknown = contract.clone();
+    unknown.functions[0].modifiers.push(ModifierCall { name: "onlyAdmin".to_string(), args: vec![] });
+    let err = SolanaCodeGenerator::new().generate(&unknown).unwrap_err();
+    assert!(err.to_string().contains("Unknown modifier 'onlyAdmin' on function 'withdraw'"));
+}
+
+#[test]
+fn test_modifier_arguments_are_bound_before_their_code() {
+    let input = r#"
+        contract Capped {
+            state {
+                total: u64;
+            }
+            
+            modifier below(limit: u64) {
+                require(total < limit, "Over limit");
+                _;
+            }
+            
+            public fn deposit(amount: u64, limit: u64) below(amount * 2) {
+                total = total + amount + limit;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    // The function's own `limit` keeps its name; the modifier's is renamed
+    let expanded = expand_modifiers(&contract).expect("Failed to expand");
+    let body = &expanded.functions[0].body;
+    assert!(matches!(&body[0], Statement::Let { name, ty: Some(Type::U64), .. } if name == "limit_1"));
+    assert!(matches!(
+        &body[1],
+        Statement::Require { condition: Expression::Binary { right, .. }, .. } if **right == Expression::Identifier("limit_1".to_string())
+    ));
+    assert_eq!(body[2], contract.functions[0].body[0]);
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("let limit_1 = amount.checked_mul(2u64).ok_or(ErrorCode::Overflow)?;"), "{}", solana);
+    assert!(solana.contains("require!((ctx.accounts.state.total < limit_1), ErrorCode::OverLimit);"), "{}", solana);
+    
+    let mut missing = contract.clone();
+    missing.functions[0].modifiers[0].args.clear();
+    let err = expand_modifiers(&missing).unwrap_err();
+    assert!(err.to_string().contains("Modifier 'below' expects 1 argument(s), got 0 on function 'deposit'"), "{}", err);
+}
+
+#[test]
+fn test_ternaries_become_if_expressions() {
+    let input = r#"
+        contract Fees {
+            public fn fee(amount: u64) -> u64 {
+                let fee = amount > 1000 ? amount / 100 : 10;
+                return (amount < fee ? amount : fee) * 2;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    // Arithmetic in a branch stays inline so it only runs when the branch is taken
+    assert!(solana.contains("let fee = if (amount > 1000) { amount.checked_div(100u64).ok_or(ErrorCode::DivisionByZero)? } else { 10 };"));
+    assert!(solana.contains("(if (amount < fee) { amount } else { fee }).checked_mul(2u64).ok_or(ErrorCode::Overflow)?"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("let fee = (if ((amount > 1000)) (amount / 100) else 10);"));
+    assert!(aptos.contains("((if ((amount < fee)) amount else fee) * 2)"));
+}
+
+#[test]
+fn test_array_and_tuple_literals() {
+    let input = r#"
+        contract Tiers {
+            public fn tiers(base: u64) -> u64 {
+                let limits = [base, base * 2, 1000];
+                let pair = (base, true);
+                let single = (base,);
+                let grouped = (base + 1) * 2;
+                return limits[1];
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("let __tmp0 = base.checked_mul(2u64).ok_or(ErrorCode::Overflow)?;\n        let limits = vec![base, __tmp0, 1000];"));
+    assert!(solana.contains("let pair = (base, true);"));
+    assert!(solana.contains("let single = (base,);"));
+    assert!(solana.contains("let __tmp1 = base.checked_add(1u64).ok_or(ErrorCode::Overflow)?;\n        let grouped = __tmp1.checked_mul(2u64).ok_or(ErrorCode::Overflow)?;"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("let limits = vector[base, (base * 2), 1000];"));
+    assert!(aptos.contains("let pair = (base, true);"));
+}
+
+#[test]
+fn test_integer_suffixes_are_kept() {
+    let input = r#"
+        contract Amm {
+            const MAX_RESERVE: u128 = 340_000_000_000_000_000_000u128;
+            
+            public fn fee(amount: u64) -> u8 {
+                let scaled = 1_000_000u128;
+                return 3u8;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("pub const MAX_RESERVE: u128 = 340000000000000000000u128;"));
+    assert!(solana.contains("let scaled = 1000000u128;"));
+    assert!(solana.contains("return Ok(3u8);"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("const MAX_RESERVE: u128 = 340000000000000000000u128;"));
+    assert!(aptos.contains("let scaled = 1000000u128;"));
+}
+
+#[test]
+fn test_pow_is_lowered_for_both_targets() {
+    let input = r#"
+        contract Token {
+            public fn scale(decimals: u64) -> u64 {
+                let unit = 10 ** decimals;
+                let cube = decimals ** 3;
+                return unit;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains(
+        "let unit = 10u64.checked_pow(u32::try_from(decimals).map_err(|_| ErrorCode::Overflow)?).ok_or(ErrorCode::Overflow)?;"
+    ));
+    assert!(solana.contains("let cube = decimals.checked_pow(3).ok_or(ErrorCode::Overflow)?;"));
+    assert!(solana.contains("    Overflow,\n"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("let unit = pow(10, decimals);"));
+    assert!(aptos.contains("let cube = pow(decimals, 3);"));
+    assert!(aptos.contains("fun pow(base: u64, exponent: u64): u64 {"));
+    assert!(!aptos.contains('^'));
+    
+    // Without `**` there is no helper
+    let plain = Contract::parse("contract A { public fn f(x: u64) -> u64 { return x * 2; } }").expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&plain).expect("Failed to generate");
+    assert!(!aptos.contains("fun pow"));
+}
+
+#[test]
+fn test_runtime_divisors_are_checked() {
+    let input = r#"
+        contract Vault {
+            state {
+                total_shares: u64;
+            }
+            
+            public fn share_value(assets: u64) -> u64 {
+                let half = assets / 2;
+                let safe = total_shares == 0 ? 0 : assets / total_shares;
+                return assets * 100 % total_shares;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains(
+        "require!(ctx.accounts.state.total_shares != 0, ErrorCode::DivisionByZero);\n        \
+         let __tmp0 = assets.checked_mul(100u64).ok_or(ErrorCode::Overflow)?;\n        \
+         return Ok((__tmp0 % ctx.accounts.state.total_shares));"
+    ));
+    assert_eq!(solana.matches("require!(").count(), 1);
+    assert!(solana.contains("    DivisionByZero,\n"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("const E_DIVISION_BY_ZERO: u64 = 1;"));
+    assert!(aptos.contains("assert!(state.total_shares != 0, E_DIVISION_BY_ZERO);\n        ((assets * 100) % state.total_shares)"));
+    assert_eq!(aptos.matches("assert!(").count(), 1);
+}
+
+#[test]
+fn test_u256_uses_a_big_integer_type() {
+    let input = r#"
+        contract Vault {
+            const SCALE: u256 = 1_000_000u256;
+            
+            state {
+                total_assets: u256;
+                balances: map<address, u256>;
+            }
+            
+            public fn deposit(amount: u256) {
+                let scaled = amount * SCALE;
+                balances[msg_sender()] = balances[msg_sender()] + scaled;
+                total_assets = total_assets + 1u256;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("use primitive_types::U256;"));
+    assert!(solana.contains("pub const SCALE: U256 = U256([1000000, 0, 0, 0]);"));
+    assert!(solana.contains("pub total_assets: U256,"));
+    assert!(solana.contains("pub value: U256,"));
+    assert!(solana.contains("pub fn deposit(ctx: Context<Deposit>, amount: U256)"));
+    assert!(solana.contains("let scaled = amount.checked_mul(SCALE).ok_or(ErrorCode::Overflow)?;"));
+    assert!(solana.contains(
+        "ctx.accounts.balances_sender.value = ctx.accounts.balances_sender.value.checked_add(scaled).ok_or(ErrorCode::Overflow)?;"
+    ));
+    assert!(solana.contains(
+        "ctx.accounts.state.total_assets = ctx.accounts.state.total_assets.checked_add(U256::from(1u128)).ok_or(ErrorCode::Overflow)?;"
+    ));
+    assert!(!solana.contains("u256"));
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("const SCALE: u256 = 1000000u256;"));
+    assert!(aptos.contains("amount: u256"));
+    
+    // Contracts without U256 don't need the extra dependency
+    let plain = Contract::parse("contract Plain { public fn id(x: u64) -> u64 { return x; } }").unwrap();
+    assert!(!SolanaCodeGenerator::new().generate(&plain).unwrap().contains("primitive_types"));
+}
+
+#[test]
+fn test_accounts_match_state_usage() {
+    let input = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    
+    let accounts = |name: &str| {
+        let start = solana.find(&format!("pub struct {}<'info> {{", name))
+            .unwrap_or_else(|| panic!("No accounts struct {}", name));
+        let end = start + solana[start..].find("}\n").unwrap();
+        solana[start..end].to_string()
+    };
+    
+    // Read-only functions get the state account without `mut`
+    let get_total_supply = accounts("GetTotalSupply");
+    assert!(get_total_supply.contains("pub state: Account<'info, State>"));
+    assert!(!get_total_supply.contains("mut,"));
+    assert!(!get_total_supply.contains("system_program"));
+    
+    // Functions that only touch map entries don't need the state account
+    let balance_of = accounts("BalanceOf");
+    assert!(!balance_of.contains("pub state:"));
+    assert!(!balance_of.contains("init_if_needed"));
+    
+    // The `Transfer` event keeps its name, so the instruction context is renamed
+    assert!(solana.contains("pub fn transfer(ctx: Context<TransferAccounts>"));
+    let mint = accounts("MintAccounts");
+    assert!(mint.contains("        mut,\n        seeds = [b\"state\"]"));
+    
+    // A helper's state writes count for its callers
+    let input = r#"
+        contract Counter {
+            state {
+                count: u64;
+            }
+            
+            public fn bump(by: u64) {
+                add(by);
+            }
+            
+            private fn add(by: u64) {
+                count = count + by;
+            }
+            
+            public fn double(count: u64) -> u64 {
+                return count * 2;
+            }
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("pub struct Bump<'info> {\n    #[account(\n        mut,"));
+    
+    // `count` here is the parameter, not the state variable, so no accounts are needed
+    assert!(solana.contains("#[derive(Accounts)]\npub struct Double {}\n"));
+}
+
+#[test]
+fn test_maps_become_pda_accounts() {
+    let input = r#"
+        contract Token {
+            state {
+                balances: map<address, u64>;
+                allowances: map<address, map<address, u64>>;
+            }
+            
+            public fn transfer(to: address, amount: u64) {
+                let from = msg_sender();
+                require(balances[from] >= amount, "Insufficient balance");
+                balances[from] = balances[from] - amount;
+                balances[to] = balances[to] + amount;
+            }
+            
+            public fn allowance(owner: address, spender: address) -> u64 {
+                return allowances[owner][spender];
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    
+    // Same shape as the hand-written program: one account per touched entry, seeded by its key.
+    // The debited entry must exist, and can't be the credited one
+    assert!(solana.contains("require!((ctx.accounts.balances_from.value >= amount)"));
+    assert!(solana.contains("ctx.accounts.balances_from.value = ctx.accounts.balances_from.value.checked_sub(amount).ok_or(ErrorCode::Underflow)?;"));
+    assert!(solana.contains("ctx.accounts.balances_to.value = ctx.accounts.balances_to.value.checked_add(amount).ok_or(ErrorCode::Overflow)?;"));
+    assert!(solana.contains(r#"#[derive(Accounts)]
+#[instruction(to: Pubkey, amount: u64)]
+pub struct Transfer<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"balances", user.key().as_ref()],
+        bump
+    )]
+    pub balances_from: Account<'info, BalancesEntry>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BalancesEntry::LEN,
+        constraint = balances_from.key() != balances_to.key() @ ErrorCode::InvalidParameter,
+        seeds = [b"balances", to.as_ref()],
+        bump
+    )]
+    pub balances_to: Account<'info, BalancesEntry>,
+    pub system_program: Program<'info, System>,
+}"#));
+    
+    // Nested maps concatenate their keys; reads don't create the entry
+    assert!(solana.contains("return Ok(ctx.accounts.allowances_owner_spender.value);"));
+    assert!(solana.contains(
+        "    #[account(\n        seeds = [b\"allowances\", owner.as_ref(), spender.as_ref()],\n        bump\n    )]\n    pub allowances_owner_spender: Account<'info, AllowancesEntry>,"
+    ));
+    
+    // Maps live in their own accounts, not in State
+    assert!(solana.contains("pub struct BalancesEntry {\n    pub value: u64,\n}"));
+    assert!(solana.contains("impl BalancesEntry {\n    pub const LEN: usize = 8;\n}"));
+    assert!(!solana.contains("HashMap"));
+    assert!(!solana.contains("pub struct State"));
+    
+    let generate = |body: &str| {
+        let input = format!(r#"
+            contract Keys {{
+                state {{
+                    owner: address;
+                    balances: map<address, u64>;
+                    allowances: map<address, map<address, u64>>;
+                }}
+                
+                public fn test(to: address, amount: u64) {{
+                    {}
+                }}
+            }}
+        "#, body);
+        SolanaCodeGenerator::new().generate(&Contract::parse(&input).expect("Failed to parse"))
+    };
+    
+    // State fields work as seeds, unless the instruction changes them first
+    let solana = generate("balances[owner] = amount;").expect("Failed to generate");
+    assert!(solana.contains("seeds = [b\"balances\", state.owner.as_ref()],"));
+    assert!(generate("owner = to; balances[owner] = amount;").is_err());
+    
+    let err = generate("balances[balances_key(to)] = amount;").unwrap_err();
+    assert!(err.to_string().contains("Cannot derive a PDA seed for 'balances'"));
+    let err = generate("let inner = allowances[to];").unwrap_err();
+    assert!(err.to_string().contains("must be indexed down to its values"));
+}
+
+#[test]
+fn test_solana_events_snapshot() {
+    let input = r#"
+contract EventContract {
+    event Transfer(from: address, to: address, amount: u64);
+    event Approval(owner: address, spender: address, amount: u64);
+    
+    state {
+        balances: map<address, u64>;
+    }
+    
+    public fn transfer(to: address, amount: u64) {
+        let from = msg_sender();
+        require(balances[from] >= amount, "Insufficient balance");
+        
+        balances[from] = balances[from] - amount;
+        balances[to] = balances[to] + amount;
+        
+        emit Transfer(from, to, amount);
+    }
+}
+"#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!(code);
+}
+
+#[test]
+fn test_move_events_snapshot() {
+    let input = r#"
+contract EventContract {
+    event Transfer(from: address, to: address, amount: u64);
+    event Approval(owner: address, spender: address, amount: u64);
+    
+    state {
+        balances: map<address, u64>;
+    }
+    
+    public fn transfer(to: address, amount: u64) {
+        let from = msg_sender();
+        require(balances[from] >= amount, "Insufficient balance");
+        
+        balances[from] = balances[from] - amount;
+        balances[to] = balances[to] + amount;
+        
+        emit Transfer(from, to, amount);
+        emit Approval(to, from, amount * 2);
+    }
+}
+"#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = MoveCodeGenerator::new();
+    let code = generator.generate(&contract).expect("Failed to generate");
+    assert!(code.contains("    #[event]\n    struct Transfer has drop, store {\n        from: address,\n        to: address,\n        amount: u64,\n    }"), "{}", code);
+    assert!(code.contains("event::emit(Transfer { from, to, amount });"), "{}", code);
+    assert!(code.contains("event::emit(Approval { owner: to, spender: from, amount: (amount * 2) });"), "{}", code);
+    insta::assert_snapshot!(code);
+    
+    // Sui events are copy, drop structs without the attribute
+    let sui = generator.event_structs(&contract.events, MoveChain::Sui);
+    assert!(sui.starts_with("    struct Transfer has copy, drop {\n"), "{}", sui);
+    assert!(!sui.contains("#[event]"), "{}", sui);
+}
+
+#[test]
+fn test_move_acquires_only_where_state_is_used() {
+    let input = r#"
+        contract Calc {
+            state {
+                total: u64;
+            }
+            
+            fn square(x: u64) -> u64 {
+                return x * x;
+            }
+            
+            fn add_total(x: u64) -> u64 {
+                return total + x;
+            }
+            
+            public fn quote(x: u64) -> u64 {
+                return add_total(square(x));
+            }
+            
+            public fn pure_math(a: u64, b: u64) -> u64 {
+                let total = a + b;
+                return square(total);
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    fun square(x: u64): u64 {"), "{}", aptos);
+    // A private helper reads the State its caller borrowed, and the caller acquires it
+    assert!(aptos.contains("    fun add_total(state: &State, x: u64): u64 {"), "{}", aptos);
+    assert!(aptos.contains("    public fun quote(x: u64): u64 acquires State {\n        let state = borrow_global<State>(@cross_chain);\n        add_total(state, square(x))"), "{}", aptos);
+    // A local named like a state variable doesn't touch State
+    assert!(aptos.contains("    public fun pure_math(a: u64, b: u64): u64 {"), "{}", aptos);
+    
+    // Without state there is no State resource to acquire
+    let stateless = Contract::parse(r#"
+        contract Math {
+            public fn double(x: u64) -> u64 {
+                return x * 2;
+            }
+        }
+    "#).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&stateless).expect("Failed to generate");
+    assert!(!aptos.contains("acquires"), "{}", aptos);
+    assert!(!aptos.contains("struct State"), "{}", aptos);
+}
+
+#[test]
+fn test_move_state_is_borrowed_from_the_named_address() {
+    let input = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    let body = |name: &str| {
+        let start = aptos.find(&format!(" fun {}(", name)).unwrap_or_else(|| panic!("no function {}:\n{}", name, aptos));
+        let end = start + aptos[start..].find("\n    }\n").unwrap();
+        aptos[start..end].to_string()
+    };
+    
+    // Each function borrows State once, before its body, and reaches the fields through it
+    let transfer = body("transfer");
+    assert!(transfer.contains("acquires State {\n        let state = borrow_global_mut<State>(@cross_chain);\n        let from = "), "{}", transfer);
+    assert_eq!(transfer.matches("borrow_global").count(), 1, "{}", transfer);
+    assert!(transfer.contains("        table::upsert(&mut state.balances, from, "), "{}", transfer);
+    assert!(!transfer.contains(" balances["), "{}", transfer);
+    let mint = body("mint");
+    assert!(mint.contains("state.total_supply = (state.total_supply + amount);"), "{}", mint);
+    // Functions that only read borrow immutably
+    let balance_of = body("balance_of");
+    assert!(balance_of.contains("let state = borrow_global<State>(@cross_chain);"), "{}", balance_of);
+    
+    // The constructor creates State before using it, so there is no init_module
+    let initialize = body("initialize");
+    let move_to = initialize.find("move_to(account, State { total_supply: 0, owner: @0x0, balances: table::new(), ")
+        .unwrap_or_else(|| panic!("State is not created:\n{}", initialize));
+    let borrow = initialize.find("let state = borrow_global_mut<State>(@cross_chain);").expect("State is not borrowed");
+    assert!(move_to < borrow, "{}", initialize);
+    assert!(initialize.contains("state.total_supply = initial_supply;"), "{}", initialize);
+    assert!(initialize.contains("event::emit(TokenInitialized { owner: state.owner, initial_supply });"), "{}", initialize);
+    assert!(!aptos.contains("init_module"), "{}", aptos);
+    
+    let aptos = MoveCodeGenerator::new().with_named_address("my_token").generate(&contract).expect("Failed to generate");
+    assert!(aptos.starts_with("module my_token::token {"), "{}", aptos);
+    assert!(aptos.contains("borrow_global_mut<State>(@my_token)"), "{}", aptos);
+    assert!(!aptos.contains("cross_chain"), "{}", aptos);
+}
+
+#[test]
+fn test_token_move_matches_golden_file() {
+    let input = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    
+    // CCDSL_UPDATE_GOLDEN=1 rewrites the file after an intended change
+    let golden = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/token.move");
+    if std::env::var_os("CCDSL_UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &aptos).unwrap();
+    }
+    assert_eq!(aptos, std::fs::read_to_string(&golden).unwrap());
+}
+
+#[test]
+fn test_token_sui_matches_golden_file() {
+    let input = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    
+    let golden = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/token.sui.move");
+    if std::env::var_os("CCDSL_UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &sui).unwrap();
+    }
+    assert_eq!(sui, std::fs::read_to_string(&golden).unwrap());
+}
+
+#[test]
+fn test_token_aptos_and_sui_modules_match() {
+    let input = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    
+    // Same module, abort codes and events on both chains
+    for code in [&aptos, &sui] {
+        assert!(code.starts_with("module cross_chain::token {"));
+        assert!(code.contains("const E_INSUFFICIENT_BALANCE: u64 = 928740058;"));
+        assert!(code.contains("event::emit(Transfer { from, to, amount });"));
+    }
+    
+    // Aptos stores State under the deployer's account, once, and borrows it back
+    assert!(aptos.contains("struct State has key {\n        total_supply: u64,"));
+    assert!(aptos.contains("assert!(signer::address_of(account) == @cross_chain, E_NOT_MODULE_ADDRESS);\n        \
+         assert!(!exists<State>(@cross_chain), E_ALREADY_INITIALIZED);\n        move_to(account, State {"));
+    assert!(aptos.contains("public entry fun transfer(account: &signer, to: address, amount: u64) acquires State {"));
+    assert!(aptos.contains("let state = borrow_global_mut<State>(@cross_chain);"));
+    assert!(aptos.contains("#[event]\n    struct Transfer has drop, store {"));
+    
+    // Sui shares a State object that functions take by reference; the constructor runs on it once
+    assert!(sui.contains("use sui::event;"));
+    assert!(sui.contains("struct State has key {\n        id: UID,\n        total_supply: u64,"));
+    assert!(sui.contains("transfer::share_object(State { id: object::new(ctx), total_supply: 0,"));
+    assert!(sui.contains("public entry fun initialize(state: &mut State, initial_supply: u64,"));
+    assert!(sui.contains("assert!(!state.is_initialized, E_ALREADY_INITIALIZED);\n        state.is_initialized = true;"));
+    assert!(sui.contains("public entry fun transfer(state: &mut State, to: address, amount: u64, ctx: &mut TxContext) {"));
+    assert!(sui.contains("let from = tx_context::sender(ctx);"));
+    assert!(sui.contains("public fun balance_of(state: &State, account: address): u64 {"));
+    assert!(sui.contains("struct Transfer has copy, drop {"));
+    assert!(!sui.contains("borrow_global") && !sui.contains("acquires"));
+}
+
+#[test]
+fn test_sui_passes_objects_to_internal_calls() {
+    let input = r#"
+        contract Counter {
+            state {
+                count: u64;
+                last: address;
+            }
+            
+            public fn bump() {
+                record();
+            }
+            
+            fn record() {
+                count = count + 1;
+                last = msg_sender();
+            }
+            
+            public fn current() -> u64 {
+                return count;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    
+    assert!(sui.contains("public entry fun bump(state: &mut State, ctx: &mut TxContext) {\n        record(state, ctx);"));
+    assert!(sui.contains("fun record(state: &mut State, ctx: &mut TxContext) {"));
+    assert!(sui.contains("public fun current(state: &State): u64 {"));
+    // Without a constructor the module shares a zeroed State when it is published
+    assert!(sui.contains("fun init(ctx: &mut TxContext) {\n        transfer::share_object(State { id: object::new(ctx), count: 0, last: @0x0 });"));
+}
+
+#[test]
+fn test_move_strings() {
+    let contract = Contract::parse(r#"
+        contract Registry {
+            const DEFAULT_NAME: string = "anon";
+            
+            state {
+                name: string<32>;
+                names: map<address, string>;
+            }
+            
+            event Renamed(old: string, new_name: string);
+            
+            public fn rename(new_name: string) {
+                require(new_name != "", "Empty name");
+                let old = name;
+                if (new_name < name) {
+                    name = DEFAULT_NAME;
+                } else {
+                    name = new_name;
+                }
+                names[msg_sender()] = new_name;
+                emit Renamed(old, new_name);
+            }
+            
+            view fn is_after(who: address, other: string) -> bool {
+                let mine = names[who];
+                return mine >= other;
+            }
+        }
+    "#).expect("Failed to parse");
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    use std::string::{Self, String};\n"), "{}", aptos);
+    // Move constants can't be strings: the bytes are converted where they're used
+    assert!(aptos.contains("    const DEFAULT_NAME: vector<u8> = b\"anon\";\n"), "{}", aptos);
+    assert!(aptos.contains("state.name = string::utf8(DEFAULT_NAME);"), "{}", aptos);
+    assert!(aptos.contains("        name: String,\n        names: Table<address, String>,\n"), "{}", aptos);
+    assert!(aptos.contains("        old: String,\n        new_name: String,\n"), "{}", aptos);
+    assert!(aptos.contains("public entry fun rename(account: &signer, new_name: String)"), "{}", aptos);
+    assert!(aptos.contains("assert!((new_name != string::utf8(b\"\")), E_EMPTY_NAME);"), "{}", aptos);
+    assert!(aptos.contains("if (aptos_std::comparator::is_smaller_than(&aptos_std::comparator::compare_u8_vector(*string::bytes(&new_name), *string::bytes(&state.name))))"), "{}", aptos);
+    assert!(aptos.contains("*table::borrow_with_default(&state.names, who, &string::utf8(b\"\"))"), "{}", aptos);
+    assert!(aptos.contains("!aptos_std::comparator::is_smaller_than(&aptos_std::comparator::compare_u8_vector(*string::bytes(&mine), *string::bytes(&other)))"), "{}", aptos);
+    assert!(aptos.contains("State { name: string::utf8(b\"\"), names: table::new() }"), "{}", aptos);
+    
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(sui.contains("    use std::ascii::{Self, String};\n"), "{}", sui);
+    assert!(sui.contains("assert!((new_name != ascii::string(b\"\")), E_EMPTY_NAME);"), "{}", sui);
+    assert!(sui.contains("state.name = ascii::string(DEFAULT_NAME);"), "{}", sui);
+    assert!(sui.contains("if ((compare_bytes(ascii::as_bytes(&new_name), ascii::as_bytes(&state.name)) == 0))"), "{}", sui);
+    assert!(sui.contains("(compare_bytes(ascii::as_bytes(&mine), ascii::as_bytes(&other)) != 0)"), "{}", sui);
+    assert!(sui.contains("    fun compare_bytes(a: &vector<u8>, b: &vector<u8>): u8 {"), "{}", sui);
+    assert!(!aptos.contains("compare_bytes"), "{}", aptos);
+    
+    // Sui strings are ASCII
+    let mut accented = contract.clone();
+    accented.constants[0].value = Expression::String("café".to_string());
+    let err = SuiCodeGenerator::new().generate(&accented).unwrap_err();
+    assert!(err.to_string().contains("String literal \"café\" is not ASCII"), "{}", err);
+    assert!(MoveCodeGenerator::new().generate(&accented).is_ok());
+}
+
+#[test]
+fn test_move_mint_capability() {
+    let input = r#"
+        contract Coin {
+            state {
+                owner: address = msg_sender();
+                supply: u64;
+            }
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Only owner");
+                _;
+            }
+            
+            public fn mint(amount: u64) onlyOwner {
+                supply = supply + amount;
+            }
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = MoveCodeGenerator::new().with_capabilities(true);
+    let aptos = generator.generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    struct MintCapability has key {}\n"), "{}", aptos);
+    assert!(aptos.contains("        move_to(account, State { owner: signer::address_of(account), supply: 0 });\n        move_to(account, MintCapability {});\n"), "{}", aptos);
+    // The signer must hold the capability; a stranger fails with the require's abort code
+    assert!(aptos.contains("public entry fun mint(account: &signer, amount: u64) acquires State, MintCapability {\n        \
+        assert!(exists<MintCapability>(signer::address_of(account)), E_ONLY_OWNER);\n        \
+        let _cap = borrow_global<MintCapability>(signer::address_of(account));\n"), "{}", aptos);
+    assert!(!aptos.contains("(signer::address_of(account) == state.owner)"), "{}", aptos);
+    let tests = generator.generate_tests(&contract).expect("Failed to generate tests");
+    assert!(tests.contains("    fun test_mint() {\n        let deployer = setup();\n        coin::mint(&deployer, 0);\n"), "{}", tests);
+    assert!(tests.contains("    #[expected_failure(abort_code = "), "{}", tests);
+    assert!(tests.contains("    fun test_mint_fails_with_e_only_owner() {\n"), "{}", tests);
+    let idl = generator.idl(&contract).expect("Failed to generate IDL");
+    assert!(idl.structs.iter().any(|s| s.name == "MintCapability" && s.abilities == ["key"]), "{:?}", idl.structs);
+    
+    // Without the flag, or on Sui, the owner check stays an address comparison
+    for code in [
+        MoveCodeGenerator::new().generate(&contract).expect("Failed to generate"),
+        SuiCodeGenerator::new().generate(&contract).expect("Failed to generate"),
+    ] {
+        assert!(!code.contains("MintCapability"), "{}", code);
+        assert!(code.contains("== state.owner), E_ONLY_OWNER);"), "{}", code);
+    }
+    
+    // A transferable owner could lose the right to mint while the deployer keeps the capability
+    let transferable = Contract::parse(&input.replace("public fn mint", "public fn set_owner(new_owner: address) onlyOwner {\n owner = new_owner;\n }\n public fn mint")).expect("Failed to parse");
+    let aptos = generator.generate(&transferable).expect("Failed to generate");
+    assert!(!aptos.contains("MintCapability"), "{}", aptos);
+    assert!(aptos.contains("assert!((signer::address_of(account) == state.owner), E_ONLY_OWNER);"), "{}", aptos);
+    let tests = generator.generate_tests(&transferable).expect("Failed to generate tests");
+    assert!(tests.contains("    fun test_mint_fails_with_e_only_owner() {\n"), "{}", tests);
+}
+
+#[test]
+fn test_move_maps_are_tables() {
+    let input = r#"
+        contract Ledger {
+            state {
+                balances: map<address, u64>;
+                allowances: map<address, map<address, u64>>;
+            }
+            
+            public fn approve(owner: address, spender: address, amount: u64) {
+                allowances[owner][spender] = amount;
+            }
+            
+            public fn spend(owner: address, spender: address, amount: u64) {
+                allowances[owner][spender] = allowances[owner][spender] - amount;
+                balances[owner] = balances[owner] - amount;
+                balances[spender] = amount;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    use aptos_std::table::{Self, Table};\n"), "{}", aptos);
+    assert!(aptos.contains("        allowances: Table<address, Table<address, u64>>,\n"), "{}", aptos);
+    assert!(aptos.contains("move_to(account, State { balances: table::new(), allowances: table::new() });"), "{}", aptos);
+    
+    // Inner tables are created on first write
+    assert!(aptos.contains(
+        "        if (!table::contains(&state.allowances, owner)) table::add(&mut state.allowances, owner, table::new());\n\
+         \x20       table::upsert(table::borrow_mut(&mut state.allowances, owner), spender, amount);\n"
+    ), "{}", aptos);
+    // Missing entries read as zero; values that read a table are computed before the mutable borrow
+    assert!(aptos.contains(
+        "        let new_allowances = ((if (table::contains(&state.allowances, owner)) \
+         *table::borrow_with_default(table::borrow(&state.allowances, owner), spender, &0) else 0) - amount);\n"
+    ), "{}", aptos);
+    assert!(aptos.contains(
+        "        let new_balances = (*table::borrow_with_default(&state.balances, owner, &0) - amount);\n\
+         \x20       table::upsert(&mut state.balances, owner, new_balances);\n"
+    ), "{}", aptos);
+    assert!(aptos.contains("        table::upsert(&mut state.balances, spender, amount);\n"), "{}", aptos);
+}
+
+#[test]
+fn test_move_locals_shadow_state() {
+    let input = r#"
+        contract Counter {
+            state {
+                count: u64;
+                limit: u64;
+            }
+            
+            public fn bump(limit: u64) {
+                if (count < limit) {
+                    let count = 5;
+                    count = count + 1;
+                }
+                count = count + 1;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("if ((state.count < limit)) {"), "{}", aptos);
+    assert!(aptos.contains("            count = (count + 1);"), "{}", aptos);
+    // The local goes out of scope with its block
+    assert!(aptos.contains("        state.count = (state.count + 1);"), "{}", aptos);
+    // Without a constructor, publishing the module creates State
+    assert!(aptos.contains("    fun init_module(account: &signer) {\n        move_to(account, State { count: 0, limit: 0 });\n    }"), "{}", aptos);
+    
+    let private = Contract::parse("contract C { state { n: u64; } #[constructor] fn initialize() { n = 1; } }").expect("Failed to parse");
+    let err = MoveCodeGenerator::new().generate(&private).unwrap_err();
+    assert!(err.to_string().contains("Constructor 'initialize' must be a public, non-view function"), "{}", err);
+    // Unmarked, a private initialize is an ordinary function and publishing creates State
+    let helper = Contract::parse("contract C { state { n: u64; } fn initialize() { n = 1; } }").expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&helper).expect("Failed to generate");
+    assert!(aptos.contains("    fun initialize(state: &mut State) {"), "{}", aptos);
+    assert!(aptos.contains("    fun init_module(account: &signer) {"), "{}", aptos);
+    let err = SolanaCodeGenerator::new().generate(&helper).unwrap_err();
+    assert!(err.to_string().contains("Function 'initialize' is not the constructor"), "{}", err);
+}
+
+#[test]
+fn test_solana_emit_converts_arguments() {
+    let input = r#"
+        contract Registry {
+            state {
+                name: string<32>;
+            }
+            
+            event Renamed(old: string, new_name: string, label: string, weight: u256);
+            
+            public fn rename(new_name: string) {
+                emit Renamed(name, new_name, "rename", 5);
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(code.contains("pub struct Renamed {\n    pub old: String,\n    pub new_name: String,\n    pub label: String,\n    pub weight: U256,\n}"), "{}", code);
+    assert!(code.contains(
+        "emit!(Renamed { old: ctx.accounts.state.name.clone(), new_name: new_name.clone(), \
+         label: \"rename\".to_string(), weight: U256::from(5u128) });"
+    ), "{}", code);
+}
+
+#[test]
+fn test_solana_indexed_event_fields() {
+    let input = r#"
+        contract Token {
+            event Transfer(indexed from: address, indexed to: address, amount: u64);
+            
+            public fn transfer(to: address, amount: u64) {
+                emit Transfer(msg_sender(), to, amount);
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(code.contains(
+        "pub struct Transfer {\n    #[index]\n    pub from: Pubkey,\n    #[index]\n    pub to: Pubkey,\n    pub amount: u64,\n}"
+    ), "{}", code);
+}
+
+#[test]
+fn test_solana_anchor_tests_snapshot() {
+    let input = r#"
+        contract Vault {
+            state {
+                owner: address;
+                paused: bool;
+                deposits: map<address, u64>;
+            }
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Only owner");
+                _;
+            }
+            
+            public fn deposit(amount: u64, memo: string) {
+                require(amount > 0, "Amount must be positive");
+                require(!paused, "Paused");
+                deposits[msg_sender()] = deposits[msg_sender()] + amount;
+            }
+            
+            public fn withdraw(amount: u64) {
+                require(deposits[msg_sender()] >= amount, "Insufficient balance");
+                deposits[msg_sender()] = deposits[msg_sender()] - amount;
+            }
+            
+            public fn set_paused(value: bool) onlyOwner {
+                paused = value;
+            }
+            
+            public fn balance_of(account: address) -> u64 {
+                return deposits[account];
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let tests = SolanaCodeGenerator::new().generate_tests(&contract).expect("Failed to generate");
+    insta::assert_snapshot!(tests);
+}
+
+#[test]
+fn test_move_unit_tests_snapshot() {
+    let input = r#"
+        contract Vault {
+            state {
+                owner: address;
+                paused: bool;
+                limit: u64;
+                deposits: map<address, u64>;
+            }
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Only owner");
+                _;
+            }
+            
+            public fn deposit(amount: u64, memo: string) {
+                require(amount > 0, "Amount must be positive");
+                require(!paused, "Paused");
+                deposits[msg_sender()] = deposits[msg_sender()] + amount;
+            }
+            
+            public fn set_limit(value: u64) onlyOwner {
+                require(value <= 1000, "Limit too high");
+                limit = value;
+            }
+            
+            view fn get_limit() -> u64 {
+                return limit;
+            }
+            
+            public fn balance_of(account: address) -> u64 {
+                return deposits[account];
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = MoveCodeGenerator::new();
+    let module = generator.generate(&contract).expect("Failed to generate");
+    // init_module is private, so the tests publish State through a test-only wrapper
+    assert!(module.contains("    #[test_only]\n    public fun init_module_for_test(account: &signer) {\n        init_module(account);\n    }\n"), "{}", module);
+    
+    let tests = generator.generate_tests(&contract).expect("Failed to generate");
+    // The expected abort codes are the module's
+    let codes = generator.abort_codes(&contract).unwrap();
+    let only_owner = codes.errors.iter().find(|e| e.name == "E_ONLY_OWNER").unwrap();
+    assert!(tests.contains(&format!("#[expected_failure(abort_code = {}, location = cross_chain::vault)]", only_owner.code)), "{}", tests);
+    insta::assert_snapshot!(tests);
+    
+    // On Sui the tests take the shared State in a test scenario
+    let generator = SuiCodeGenerator::new();
+    let module = generator.generate(&contract).expect("Failed to generate");
+    assert!(module.contains("    #[test_only]\n    public fun init_for_testing(ctx: &mut TxContext) {\n        init(ctx);\n    }\n"), "{}", module);
+    let tests = generator.generate_tests(&contract).expect("Failed to generate");
+    assert!(tests.contains(&format!("#[expected_failure(abort_code = {}, location = cross_chain::vault)]", only_owner.code)), "{}", tests);
+    insta::assert_snapshot!("move_unit_tests_sui", tests);
+}
+
+#[test]
+fn test_move_unit_tests_follow_the_constructor_and_named_address() {
+    let input = r#"
+        contract Registry {
+            state {
+                admin: address;
+                entries: map<u64, Entry>;
+            }
+            
+            struct Entry {
+                value: u64;
+            }
+            
+            public fn initialize(admin_address: address) {
+                admin = admin_address;
+            }
+            
+            public fn put(id: u64, entry: Entry) {
+                entries[id] = entry;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = MoveCodeGenerator::new().with_named_address("registry_addr");
+    let tests = generator.generate_tests(&contract).expect("Failed to generate");
+    assert!(tests.starts_with("#[test_only]\nmodule registry_addr::registry_tests {\n"), "{}", tests);
+    assert!(tests.contains("        let deployer = account::create_account_for_test(@registry_addr);\n        registry::initialize(&deployer, @0x0);\n        deployer\n"), "{}", tests);
+    // The constructor only runs once, in setup()
+    assert!(!tests.contains("registry::initialize(&deployer, @0x0);\n    }\n\n    #[test]"), "{}", tests);
+    // Another module can't construct an Entry
+    assert!(tests.contains("    // TODO: test put, whose parameters a test module can't construct\n"), "{}", tests);
+    assert!(generator.move_toml(&contract).contains("[dev-addresses]\nregistry_addr = \"0xcafe\"\n"));
+}
+
+#[test]
+fn test_arithmetic_mode_controls_unsigned_subtraction() {
+    let input = r#"
+        contract Vault {
+            state {
+                balance: u64;
+                offset: u64;
+            }
+            
+            public fn withdraw(amount: u64, shift: u64) {
+                balance = balance - amount;
+                offset = offset - shift;
+                let spare = 100 - amount;
+            }
+        }
+    "#;
+    
+    // The grammar has no signed types yet
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    contract.state[1].ty = Type::I64;
+    contract.functions[0].params[1].ty = Type::I64;
+    let generate = |mode| SolanaCodeGenerator::new().with_arithmetic(mode).generate(&contract).expect("Failed to generate");
+    
+    let checked = generate(ArithmeticMode::Checked);
+    assert!(checked.contains("ctx.accounts.state.balance = ctx.accounts.state.balance.checked_sub(amount).ok_or(ErrorCode::Underflow)?;"));
+    assert!(checked.contains("let spare = 100u64.checked_sub(amount).ok_or(ErrorCode::Underflow)?;"));
+    assert!(checked.contains("    Underflow,\n"));
+    // Signed subtraction can only overflow
+    assert!(checked.contains("ctx.accounts.state.offset = ctx.accounts.state.offset.checked_sub(shift).ok_or(ErrorCode::Overflow)?;"));
+    assert_eq!(checked, SolanaCodeGenerator::new().generate(&contract).unwrap());
+    
+    let wrapping = generate(ArithmeticMode::Wrapping);
+    assert!(wrapping.contains("ctx.accounts.state.balance = ctx.accounts.state.balance.wrapping_sub(amount);"));
+    assert!(wrapping.contains("let spare = 100u64.wrapping_sub(amount);"));
+    
+    let unchecked = generate(ArithmeticMode::Unchecked);
+    assert!(unchecked.contains("ctx.accounts.state.balance = (ctx.accounts.state.balance - amount);"));
+    
+    // Move aborts on underflow by itself and cannot wrap
+    let aptos = MoveCodeGenerator::new().with_arithmetic(ArithmeticMode::Checked).generate(&contract).unwrap();
+    assert!(aptos.contains("let spare = (100 - amount);"), "{}", aptos);
+    let err = MoveCodeGenerator::new().with_arithmetic(ArithmeticMode::Wrapping).generate(&contract).unwrap_err();
+    assert!(err.to_string().contains("Move has no wrapping arithmetic"));
+    
+    assert_eq!("wrapping".parse::<ArithmeticMode>(), Ok(ArithmeticMode::Wrapping));
+    assert!("saturating".parse::<ArithmeticMode>().is_err());
+}
+
+#[test]
+fn test_require_messages_become_error_codes() {
+    let input = r#"
+        contract Guarded {
+            state {
+                owner: address;
+                paused: bool;
+                limit: u64;
+            }
+            
+            public fn configure(limit_value: u64) {
+                require(msg_sender() == owner, "Only owner");
+                require(!paused, "Contract is paused");
+                require(limit_value > 0, "Limit must be > 0");
+                limit = limit_value;
+            }
+            
+            public fn pause() {
+                require(msg_sender() == owner, "Only owner");
+                require(!paused);
+                paused = true;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!("require_errors_solana", solana);
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!("require_errors_move", aptos);
+}
+
+#[test]
+fn test_token_transfer_uses_checked_arithmetic() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/token.ccdsl");
+    let program = Program::load(&path).expect("Failed to load");
+    let solana = SolanaCodeGenerator::new().generate(&program.contracts[0]).expect("Failed to generate");
+    
+    let start = solana.find("    pub fn transfer(").expect("transfer missing");
+    let end = start + solana[start..].find("    }\n").unwrap();
+    insta::assert_snapshot!(&solana[start..end]);
+}
+
+#[test]
+fn test_nested_checked_arithmetic_uses_temporaries() {
+    let input = r#"
+        contract Pool {
+            state {
+                reserve: u64;
+            }
+            
+            public fn quote(amount: u64, fee: u64) -> u64 {
+                require(reserve + amount > fee * 2, "Pool too small");
+                let safe = amount > 0 && reserve / amount > 1;
+                return (amount - fee) * reserve / (reserve + amount);
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains(
+        "let __tmp0 = ctx.accounts.state.reserve.checked_add(amount).ok_or(ErrorCode::Overflow)?;\n        \
+         let __tmp1 = fee.checked_mul(2u64).ok_or(ErrorCode::Overflow)?;\n        \
+         require!((__tmp0 > __tmp1), ErrorCode::PoolTooSmall);"
+    ), "{}", solana);
+    // The right side of `&&` only runs when the left is true, so it stays inline
+    assert!(solana.contains(
+        "let safe = ((amount > 0) && (ctx.accounts.state.reserve.checked_div(amount).ok_or(ErrorCode::DivisionByZero)? > 1));"
+    ), "{}", solana);
+    assert!(solana.contains(
+        "require!(ctx.accounts.state.reserve.checked_add(amount).ok_or(ErrorCode::Overflow)? != 0, ErrorCode::DivisionByZero);"
+    ), "{}", solana);
+    assert!(solana.contains(
+        "let __tmp2 = amount.checked_sub(fee).ok_or(ErrorCode::Underflow)?;\n        \
+         let __tmp3 = __tmp2.checked_mul(ctx.accounts.state.reserve).ok_or(ErrorCode::Overflow)?;\n        \
+         let __tmp4 = ctx.accounts.state.reserve.checked_add(amount).ok_or(ErrorCode::Overflow)?;\n        \
+         return Ok(__tmp3.checked_div(__tmp4).ok_or(ErrorCode::DivisionByZero)?);"
+    ), "{}", solana);
+    
+    let unchecked = SolanaCodeGenerator::new()
+        .with_arithmetic(ArithmeticMode::Unchecked)
+        .generate(&contract)
+        .expect("Failed to generate");
+    assert!(!unchecked.contains("__tmp"));
+    assert!(unchecked.contains("return Ok((((amount - fee) * ctx.accounts.state.reserve) / (ctx.accounts.state.reserve + amount)));"));
+}
+
+#[test]
+fn test_solana_control_flow_is_nested() {
+    let input = r#"
+contract ControlFlow {
+    public fn complex_logic(x: u64) -> u64 {
+        let mut result = 0;
+        
+        if x > 100 {
+            result = x * 2;
+        } else if x > 50 {
+            result = x + 50;
+        } else {
+            result = x;
+        }
+        
+        while result < 1000 {
+            result = result * 2;
+        }
+        
+        for i in 0..10 {
+            if i == 5 {
+                continue;
+            }
+            result = result + i;
+        }
+        
+        match result {
+            0 => return 1,
+            1..10 => return 2,
+            _ => return result
+        }
+    }
+}
+"#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    // A configured program id leaves no placeholder TODO either
+    let solana = SolanaCodeGenerator::new()
+        .with_program_id("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")
+        .generate(&contract)
+        .expect("Failed to generate");
+    
+    assert!(!solana.contains("TODO"), "{}", solana);
+    assert_eq!(solana.matches('{').count(), solana.matches('}').count(), "{}", solana);
+    
+    let start = solana.find("    pub fn complex_logic(").expect("complex_logic missing");
+    let end = start + solana[start..].find("\n    }\n").unwrap();
+    insta::assert_snapshot!(&solana[start..end]);
+}
+
+#[test]
+fn test_solana_output_parses_as_rust() {
+    let token = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let registry = r#"
+contract Registry {
+    state {
+        owner: address;
+        names: map<address, string>;
+        scores: map<address, map<u64, u64>>;
+        history: vec<u64>;
+    }
+    
+    event Scored(who: address, round: u64, score: u64);
+    
+    public fn initialize() {
+        owner = msg_sender();
+    }
+    
+    public fn record(round: u64, score: u64) {
+        require(score <= 100, "Score too high");
+        let total = 0;
+        for i in 0..round {
+            if i % 2 == 0 {
+                total = total + i;
+            } else {
+                continue;
+            }
+        }
+        match score {
+            0 => { history.push(total); },
+            _ => { scores[msg_sender()][round] = score / 2 + total; }
+        }
+        emit Scored(msg_sender(), round, score);
+    }
+    
+    public fn rename(name: string) {
+        names[msg_sender()] = name;
+    }
+}
+"#;
+    
+    for input in [token.as_str(), registry] {
+        let contract = Contract::parse(input).expect("Failed to parse");
+        for mode in [ArithmeticMode::Checked, ArithmeticMode::Wrapping, ArithmeticMode::Unchecked] {
+            // Parse the output here rather than relying on the generator's own check
+            let solana = SolanaCodeGenerator::new()
+                .with_arithmetic(mode)
+                .with_default_max_len(32)
+                .with_output_check(false)
+                .generate(&contract)
+                .expect("Failed to generate");
+            if let Err(error) = syn::parse_file(&solana) {
+                panic!("{} ({}): {}\n{}", contract.name, mode, error, solana);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_solana_output_check_quotes_the_broken_lines() {
+    // `impl` is a Rust keyword, so the generated instruction does not parse
+    let contract = Contract::parse(r#"
+        contract Ledger {
+            state {
+                balance: u64;
+            }
+            
+            public fn adjust(impl: u64) {
+                balance = impl;
+            }
+        }
+    "#).expect("Failed to parse");
+    
+    let error = SolanaCodeGenerator::new().generate(&contract).unwrap_err();
+    let message = format!("{:#}", error);
+    assert!(message.starts_with("In the Solana program of 'Ledger': Generated Rust does not parse: "), "{}", message);
+    assert!(message.contains("   14 |     pub fn adjust(ctx: Context<Adjust>, impl: u64) -> Result<()> {\n      |                                         ^"), "{}", message);
+    
+    assert!(SolanaCodeGenerator::new().with_output_check(false).generate(&contract).is_ok());
+}
+
+#[test]
+fn test_move_control_flow_is_sequenced() {
+    let input = r#"
+contract ControlFlow {
+    public fn complex_logic(x: u64) -> u64 {
+        let mut result = 0;
+        
+        if x > 100 {
+            result = x * 2;
+        } else if x > 50 {
+            result = x + 50;
+        } else {
+            result = x;
+        }
+        
+        while result < 1000 {
+            result = result * 2;
+        }
+        
+        for i in 0..10 {
+            if i == 5 {
+                continue;
+            }
+            result = result + i;
+        }
+        
+        match result {
+            0 => return 1,
+            1..10 => return 2,
+            _ => return result
+        }
+    }
+    
+    public fn find(values: vec<u64>, target: u64) -> u64 {
+        let mut i = 0;
+        while i < 100 {
+            if values[i] == target {
+                return i;
+            }
+            if values[i] > target {
+                break;
+            }
+            i = i + 1;
+        }
+        return 100;
+    }
+}
+"#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    for (chain, code) in [
+        ("aptos", MoveCodeGenerator::new().generate(&contract).expect("Failed to generate")),
+        ("sui", SuiCodeGenerator::new().generate(&contract).expect("Failed to generate")),
+    ] {
+        assert!(!code.contains("TODO") && !code.contains("/*"), "{}: {}", chain, code);
+        assert_eq!(code.matches('{').count(), code.matches('}').count(), "{}: {}", chain, code);
+        // An if followed by more statements ends with `;`, like a loop; the last one is the function's value
+        assert!(code.contains("                result = x;\n            }\n        };\n        while ((result < 1000)) {\n"), "{}: {}", chain, code);
+        assert!(code.contains("                continue;\n            };\n"), "{}: {}", chain, code);
+        assert!(code.contains("        } else {\n            result\n        }\n    }\n"), "{}: {}", chain, code);
+        // Early returns keep `return`; the last one is the value
+        assert!(code.contains("            if ((*vector::borrow(&values, i) == target)) {\n                return i\n            };\n"), "{}: {}", chain, code);
+        assert!(code.contains("                break;\n            };\n"), "{}: {}", chain, code);
+        assert!(code.contains("        };\n        100\n    }\n"), "{}: {}", chain, code);
+    }
+}
+
+#[test]
+fn test_signed_integers() {
+    let mut contract = Contract::parse(r#"
+        contract Ledger {
+            state {
+                balance: u64;
+                limit: u64;
+            }
+            
+            public fn adjust(delta: u64) {
+                require(delta > limit, "Below limit");
+                balance = balance + delta;
+            }
+            
+            public fn flip(x: u64) -> u64 {
+                return x;
+            }
+        }
+    "#).expect("Failed to parse");
+    // The grammar has no signed types yet; JSON ASTs can contain them
+    for var in &mut contract.state {
+        var.ty = Type::I64;
+    }
+    for func in &mut contract.functions {
+        func.params[0].ty = Type::I64;
+    }
+    contract.functions[1].return_type = Some(Type::I64);
+    
+    // Solana keeps i64, so comparisons are signed
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("pub fn adjust(ctx: Context<Adjust>, delta: i64) -> Result<()> {"), "{}", solana);
+    assert!(solana.contains("require!((delta > ctx.accounts.state.limit), ErrorCode::BelowLimit);"), "{}", solana);
+    assert!(solana.contains("pub balance: i64,"), "{}", solana);
+    
+    // Move compiles them as unsigned, which can't be negated
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("public entry fun adjust(delta: u64)"), "{}", aptos);
+    let Statement::Return { value: Some(value) } = &mut contract.functions[1].body[0] else { unreachable!() };
+    *value = Expression::Unary { op: cross_chain_dsl::UnaryOp::Neg, expr: Box::new(value.clone()) };
+    assert!(SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate").contains("return Ok(-(x));"));
+    for err in [MoveCodeGenerator::new().generate(&contract).unwrap_err(), SuiCodeGenerator::new().generate(&contract).unwrap_err()] {
+        assert!(err.to_string().contains("Function 'flip' negates a value, which cannot be compiled to Move: Move has no signed integers"), "{}", err);
+    }
+}
+
+#[test]
+fn test_solana_assert_and_block_statements() {
+    let mut contract = Contract::parse(r#"
+        contract Vault {
+            state {
+                total: u64;
+            }
+            
+            public fn check(amount: u64) {
+                require(amount > 0, "Zero amount");
+            }
+        }
+    "#).expect("Failed to parse");
+    // The grammar has no `assert` or bare blocks, but JSON ASTs can contain them
+    let body = &mut contract.functions[0].body;
+    let Statement::Require { condition, .. } = body.remove(0) else { unreachable!() };
+    body.push(Statement::Block(vec![Statement::Assert {
+        condition,
+        message: Some("Amount must be positive".to_string()),
+    }]));
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains(
+        "        {\n            require!((amount > 0), ErrorCode::AmountMustBePositive);\n        }\n"
+    ), "{}", solana);
+    assert!(solana.contains("#[msg(\"Amount must be positive\")]"), "{}", solana);
+}
+
+#[test]
+fn test_tuple_returns_and_destructuring() {
+    let input = r#"
+        contract Pool {
+            public fn quote(amount: u64) -> (u64, u64) {
+                let fee = amount / 100;
+                return (amount - fee, fee);
+            }
+            
+            public fn swap(amount: u64) -> u64 {
+                let (out, fee) = quote(amount);
+                return out;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new()
+        .with_arithmetic(ArithmeticMode::Unchecked)
+        .generate(&contract)
+        .expect("Failed to generate");
+    assert!(solana.contains("pub fn quote(ctx: Context<Quote>, amount: u64) -> Result<(u64, u64)> {"), "{}", solana);
+    assert!(solana.contains("return Ok(((amount - fee), fee));\n    }\n"), "{}", solana);
+    assert!(solana.contains("let (out, fee) = quote(amount);"), "{}", solana);
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("public fun quote(amount: u64): (u64, u64)"), "{}", aptos);
+    assert!(aptos.contains("let (out, fee) = quote(amount);"), "{}", aptos);
+}
+
+#[test]
+fn test_casts_guard_narrowing() {
+    let input = r#"
+        contract Amm {
+            state {
+                reserve: u64;
+            }
+            
+            public fn quote(amount: u64) -> u64 {
+                let product = reserve as u128 * amount as u128;
+                return (product / 1000) as u64;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains(
+        "let product = (ctx.accounts.state.reserve as u128).checked_mul((amount as u128)).ok_or(ErrorCode::Overflow)?;"
+    ), "{}", solana);
+    assert!(solana.contains(
+        "let __tmp0 = product.checked_div(1000u128).ok_or(ErrorCode::DivisionByZero)?;\n        \
+         return Ok(u64::try_from(__tmp0).map_err(|_| ErrorCode::Overflow)?);"
+    ), "{}", solana);
+    
+    let wrapping = SolanaCodeGenerator::new()
+        .with_arithmetic(ArithmeticMode::Wrapping)
+        .generate(&contract)
+        .expect("Failed to generate");
+    assert!(wrapping.contains("return Ok(((product / 1000u128) as u64));"), "{}", wrapping);
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("((product / 1000) as u64)"), "{}", aptos);
+}
+
+#[test]
+fn test_solana_initialize_allocates_state() {
+    let input = r#"
+        contract Counter {
+            state {
+                count: u64;
+                owner: address;
+            }
+            
+            public fn increment() {
+                count = count + 1;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {\n        Ok(())\n    }\n"), "{}", solana);
+    assert!(solana.contains(
+        "pub struct Initialize<'info> {\n    \
+         #[account(mut)]\n    \
+         pub user: Signer<'info>,\n    \
+         #[account(\n        init,\n        payer = user,\n        space = 8 + State::LEN,\n        seeds = [b\"state\"],\n        bump\n    )]\n    \
+         pub state: Account<'info, State>,\n    \
+         pub system_program: Program<'info, System>,\n}"
+    ), "{}", solana);
+    assert!(solana.contains("pub const LEN: usize = 8 + 32;"), "{}", solana);
+    
+    // A contract's own initialize creates the account instead
+    let input = input.replace("fn increment()", "fn initialize()");
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert_eq!(solana.matches("pub fn initialize(").count(), 1, "{}", solana);
+    assert!(solana.contains("        init,\n        payer = user,\n        space = 8 + State::LEN,"), "{}", solana);
+    assert!(solana.contains("ctx.accounts.state.count = ctx.accounts.state.count.checked_add(1u64)"), "{}", solana);
+}
+
+#[test]
+fn test_state_initial_values() {
+    let input = r#"
+        contract Pool {
+            state {
+                owner: address = msg_sender();
+                fee: u64 = 30;
+                total: u64;
+            }
+            
+            public fn set_fee(new_fee: u64) {
+                require(msg_sender() == owner, "Only owner");
+                fee = new_fee;
+            }
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    // Solana sets them in initialize, where the signer is the deployer
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains(
+        "    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {\n        \
+         ctx.accounts.state.owner = ctx.accounts.user.key();\n        \
+         ctx.accounts.state.fee = 30;\n        \
+         Ok(())\n    }\n"
+    ), "{}", solana);
+    assert!(solana.contains("pub struct Initialize<'info> {\n    #[account(mut)]\n    pub user: Signer<'info>,\n"), "{}", solana);
+    
+    // Move builds them into the resource
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("move_to(account, State { owner: signer::address_of(account), fee: 30, total: 0 });"), "{}", aptos);
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(sui.contains("transfer::share_object(State { id: object::new(ctx), owner: tx_context::sender(ctx), fee: 30, total: 0 });"), "{}", sui);
+    
+    // A contract's own initialize sets them first, then runs its body
+    let input = input.replace("public fn set_fee(new_fee: u64) {\n                require(msg_sender() == owner, \"Only owner\");", "public fn initialize(new_fee: u64) {");
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert_eq!(solana.matches("pub fn initialize(").count(), 1, "{}", solana);
+    assert!(solana.contains(
+        "ctx.accounts.state.owner = ctx.accounts.user.key();\n        \
+         ctx.accounts.state.fee = 30;\n        \
+         ctx.accounts.state.fee = new_fee;\n"
+    ), "{}", solana);
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("public entry fun initialize(account: &signer, new_fee: u64) acquires State {\n        \
+         assert!(signer::address_of(account) == @cross_chain, E_NOT_MODULE_ADDRESS);\n        \
+         assert!(!exists<State>(@cross_chain), E_ALREADY_INITIALIZED);\n        \
+         move_to(account, State { owner: signer::address_of(account), fee: 30, total: 0 });"), "{}", aptos);
+}
+
+#[test]
+fn test_constructor_attribute_names_the_function_that_creates_state() {
+    let input = r#"
+        contract Vault {
+            state {
+                owner: address;
+                fee: u64 = 30;
+                limit: u64;
+            }
+            
+            #[constructor]
+            public fn setup(initial_limit: u64) {
+                owner = msg_sender();
+                limit = initial_limit;
+            }
+            
+            public fn set_limit(new_limit: u64) {
+                require(msg_sender() == owner, "Only owner");
+                limit = new_limit;
+            }
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    // Solana creates the state account in setup, so a second call fails on the existing account
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(!solana.contains("pub fn initialize("), "{}", solana);
+    assert!(solana.contains(
+        "    pub fn setup(ctx: Context<Setup>, initial_limit: u64) -> Result<()> {\n        \
+         ctx.accounts.state.fee = 30;\n"
+    ), "{}", solana);
+    assert!(solana.contains("pub struct Setup<'info> {\n    #[account(mut)]\n    pub user: Signer<'info>,\n    #[account(\n        init,"), "{}", solana);
+    let tests = SolanaCodeGenerator::new().generate_tests(&contract).expect("Failed to generate tests");
+    assert!(tests.contains("    await program.methods\n      .setup(new BN(0))"), "{}", tests);
+    assert!(tests.contains("  it(\"setup\", async () => {\n    // setup runs once, in before()"), "{}", tests);
+    
+    // Aptos checks setup runs from the module's account, where the other functions borrow
+    // State, and that State doesn't exist yet, before moving it there
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("const E_ALREADY_INITIALIZED: u64 = 2;"), "{}", aptos);
+    assert!(aptos.contains("const E_NOT_MODULE_ADDRESS: u64 = 3;"), "{}", aptos);
+    assert!(aptos.contains("    use std::signer;\n"), "{}", aptos);
+    assert!(aptos.contains("let state = borrow_global_mut<State>(@cross_chain);"), "{}", aptos);
+    let codes = MoveCodeGenerator::new().abort_codes(&contract).expect("Failed to list abort codes");
+    assert!(codes.errors.iter().any(|e| e.name == "E_NOT_MODULE_ADDRESS" && e.code == 3), "{:?}", codes.errors);
+    assert!(aptos.contains("public entry fun setup(account: &signer, initial_limit: u64) acquires State {\n        \
+         assert!(signer::address_of(account) == @cross_chain, E_NOT_MODULE_ADDRESS);\n        \
+         assert!(!exists<State>(@cross_chain), E_ALREADY_INITIALIZED);\n        \
+         move_to(account, State { owner: @0x0, fee: 30, limit: 0 });"), "{}", aptos);
+    assert!(!aptos.contains("init_module"), "{}", aptos);
+    let aptos_tests = MoveCodeGenerator::new().generate_tests(&contract).expect("Failed to generate tests");
+    assert!(aptos_tests.contains("    #[expected_failure(abort_code = 2, location = cross_chain::vault)]\n    \
+         fun test_setup_only_runs_once() {\n        \
+         let deployer = setup();\n        \
+         vault::setup(&deployer, 0);"), "{}", aptos_tests);
+    
+    // Sui shares State when the module is published; setup runs on it once
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(sui.contains("        limit: u64,\n        is_initialized: bool,\n    }"), "{}", sui);
+    assert!(sui.contains("transfer::share_object(State { id: object::new(ctx), owner: @0x0, fee: 30, limit: 0, is_initialized: false });"), "{}", sui);
+    assert!(sui.contains("public entry fun setup(state: &mut State, initial_limit: u64, ctx: &mut TxContext) {\n        \
+         assert!(!state.is_initialized, E_ALREADY_INITIALIZED);\n        \
+         state.is_initialized = true;\n"), "{}", sui);
+    assert!(!sui.contains("E_NOT_MODULE_ADDRESS"), "{}", sui);
+    let sui_tests = SuiCodeGenerator::new().generate_tests(&contract).expect("Failed to generate tests");
+    assert!(sui_tests.contains("        vault::init_for_testing(test_scenario::ctx(&mut scenario));\n"), "{}", sui_tests);
+    assert!(sui_tests.contains("fun test_setup_only_runs_once() {"), "{}", sui_tests);
+    
+    // Sui keeps the flag in State, so no state variable can have its name
+    let clash = Contract::parse(&input.replace("limit: u64;", "limit: u64;\n                is_initialized: bool;")).unwrap();
+    let err = SuiCodeGenerator::new().generate(&clash).unwrap_err();
+    assert!(err.to_string().contains("State variable 'is_initialized' clashes"), "{}", err);
+    assert!(MoveCodeGenerator::new().generate(&clash).is_ok());
+}
+
+#[test]
+fn test_solana_type_names_are_camel_case_and_distinct() {
+    let input = r#"
+        contract Pools {
+            struct Pool {
+                reserve: u64;
+            }
+            
+            state {
+                pool_reserves: map<address, u64>;
+            }
+            
+            event pool_created(creator: address);
+            
+            public fn create_pool() {
+                pool_reserves[msg_sender()] = 0;
+                emit pool_created(msg_sender());
+            }
+            
+            public fn pool() { }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = SolanaCodeGenerator::new();
+    let solana = generator.generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("pub fn create_pool(ctx: Context<CreatePool>)"), "{}", solana);
+    assert!(solana.contains("pub struct PoolReservesEntry {"));
+    assert!(solana.contains("pub struct PoolCreated {"));
+    assert!(solana.contains("emit!(PoolCreated { creator: ctx.accounts.user.key() });"));
+    // A function named like a struct gets a suffixed context
+    assert!(solana.contains("pub fn pool(ctx: Context<PoolAccounts>)"));
+    let idl = generator.idl(&contract).expect("Failed to build the IDL");
+    assert_eq!(idl.events[0].name, "PoolCreated");
+    
+    let error = |from: &str, to: &str| {
+        let contract = Contract::parse(&input.replacen(from, to, 1)).expect("Failed to parse");
+        format!("{:#}", generator.generate(&contract).unwrap_err())
+    };
+    assert_eq!(error("fn pool()", "fn createPool()"),
+        "function 'create_pool', function 'createPool' all become 'CreatePool' in the Solana program; rename all but one");
+    assert!(error("struct Pool", "struct PoolCreated").contains("struct 'PoolCreated', event 'pool_created' all become 'PoolCreated'"));
+    assert!(error("struct Pool", "struct PoolReservesEntry").contains("map 'pool_reserves' all become 'PoolReservesEntry'"));
+}
+
+#[test]
+fn test_solana_signer_only_where_needed() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/token.ccdsl");
+    let program = Program::load(&path).expect("Failed to load");
+    let solana = SolanaCodeGenerator::new().generate(&program.contracts[0]).expect("Failed to generate");
+    
+    let context = |name: &str| {
+        let start = solana.find(&format!("pub struct {}<'info> {{", name)).expect("context missing");
+        solana[start..start + solana[start..].find("\n}\n").unwrap()].to_string()
+    };
+    // transfer reads msg_sender() and may create the recipient's balance account
+    assert!(context("TransferAccounts").contains("    #[account(mut)]\n    pub user: Signer<'info>,"), "{}", solana);
+    // balance_of only reads
+    assert!(!context("BalanceOf").contains("Signer"), "{}", solana);
+    
+    let contract = Contract::parse(r#"
+        contract Owned {
+            state {
+                owner: address;
+                fee: u64;
+            }
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Only owner");
+                _;
+            }
+            
+            public fn set_fee(new_fee: u64) onlyOwner {
+                fee = new_fee;
+            }
+        }
+    "#).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    // Checking the signer's key needs no `mut`
+    assert!(solana.contains("pub struct SetFee<'info> {\n    #[account(address = state.owner @ ErrorCode::OnlyOwner)]\n    pub user: Signer<'info>,\n"), "{}", solana);
+}
+
+#[test]
+fn test_private_and_internal_functions_are_not_entrypoints() {
+    let contract = Contract::parse(r#"
+        contract Vault {
+            state {
+                total: u64;
+                fee_bps: u64;
+            }
+            
+            public fn deposit(amount: u64) {
+                let net = apply_fee(amount);
+                add_total(net);
+            }
+            
+            private fn apply_fee(amount: u64) -> u64 {
+                require(amount > 0, "Amount must be positive");
+                return amount - fee(amount);
+            }
+            
+            internal fn fee(amount: u64) -> u64 {
+                return amount * fee_bps / 10000;
+            }
+            
+            private fn add_total(amount: u64) {
+                total = total + amount;
+            }
+        }
+    "#).expect("Failed to parse");
+    
+    let generator = SolanaCodeGenerator::new();
+    let solana = generator.generate(&contract).expect("Failed to generate");
+    let program = &solana[solana.find("#[program]").unwrap()..solana.find("\n}\n").unwrap()];
+    assert!(program.contains("pub fn deposit(ctx: Context<Deposit>, amount: u64)"), "{}", solana);
+    assert!(!program.contains("fn apply_fee") && !program.contains("fn fee") && !program.contains("fn add_total"), "{}", solana);
+    assert!(!solana.contains("pub struct ApplyFee<'info>") && !solana.contains("pub struct AddTotal<'info>"), "{}", solana);
+    // Helpers get the caller's state, mutably only if they write it
+    assert!(solana.contains("let net = apply_fee(&ctx.accounts.state, amount)?;"), "{}", solana);
+    assert!(solana.contains("add_total(&mut ctx.accounts.state, net)?;"), "{}", solana);
+    assert!(solana.contains("\nfn apply_fee(state: &State, amount: u64) -> Result<u64> {"), "{}", solana);
+    assert!(solana.contains("fee(state, amount)?"), "{}", solana);
+    assert!(solana.contains("\nfn add_total(state: &mut State, amount: u64) -> Result<()> {\n    state.total = "), "{}", solana);
+    let idl = generator.idl(&contract).expect("Failed to build the IDL");
+    let names: Vec<&str> = idl.instructions.iter().map(|i| i.name.as_str()).collect();
+    assert!(!names.contains(&"applyFee") && !names.contains(&"apply_fee") && !names.contains(&"fee"), "{:?}", names);
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    public entry fun deposit("), "{}", aptos);
+    assert!(aptos.contains("    fun apply_fee(amount: u64): u64"), "{}", aptos);
+    assert!(aptos.contains("    public(friend) fun fee(amount: u64): u64"), "{}", aptos);
+    
+    // A helper gets no accounts, so it can't reach the signer or map entries
+    let private_sender = Contract::parse(r#"
+        contract Vault {
+            state {
+                owner: address;
+                deposits: map<address, u64>;
+            }
+            
+            public fn claim() {
+                check_owner();
+            }
+            
+            private fn check_owner() {
+                require(msg_sender() == owner, "Only owner");
+            }
+            
+            internal fn credit(to: address, amount: u64) {
+                deposits[to] = deposits[to] + amount;
+            }
+        }
+    "#).expect("Failed to parse");
+    let err = SolanaCodeGenerator::new().generate(&private_sender).unwrap_err();
+    assert!(err.to_string().contains("Private function 'check_owner' uses msg_sender()"), "{}", err);
+    let mut map_entry = private_sender.clone();
+    map_entry.functions.remove(1);
+    map_entry.functions[0].body.clear();
+    let err = SolanaCodeGenerator::new().generate(&map_entry).unwrap_err();
+    assert!(err.to_string().contains("Internal function 'credit' uses the map 'deposits'"), "{}", err);
+}
+
+#[test]
+fn test_move_signer_only_where_needed() {
+    let input = r#"
+        contract Vault {
+            state {
+                owner: address;
+                total: u64;
+                deposits: map<address, u64>;
+            }
+            
+            public fn deposit(amount: u64) {
+                credit(msg_sender(), amount);
+                total = total + amount;
+            }
+            
+            public fn bump(amount: u64) {
+                total = total + amount;
+            }
+            
+            public fn claim() -> u64 {
+                return deposits[msg_sender()];
+            }
+            
+            view fn balance(who: address) -> u64 {
+                return deposits[who];
+            }
+            
+            fn credit(who: address, amount: u64) {
+                deposits[who] = deposits[who] + amount;
+            }
+            
+            fn only_owner() {
+                require(msg_sender() == owner, "Only owner");
+            }
+            
+            public fn reset() {
+                only_owner();
+                total = 0;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    // Reads the sender and writes state
+    assert!(aptos.contains("    public entry fun deposit(account: &signer, amount: u64) acquires State {"), "{}", aptos);
+    assert!(aptos.contains("        credit(state, signer::address_of(account), amount);"), "{}", aptos);
+    // Writes state without knowing who called
+    assert!(aptos.contains("    public entry fun bump(amount: u64) acquires State {"), "{}", aptos);
+    // Returns a value, so it can't be an entry function
+    assert!(aptos.contains("    public fun claim(account: &signer): u64 acquires State {"), "{}", aptos);
+    assert!(aptos.contains("    #[view]\n    public fun balance(who: address): u64 acquires State {"), "{}", aptos);
+    // Private helpers use the caller's borrow of State instead of acquiring it again
+    assert!(aptos.contains("    fun credit(state: &mut State, who: address, amount: u64) {"), "{}", aptos);
+    assert!(aptos.contains("    fun only_owner(account: &signer, state: &State) {"), "{}", aptos);
+    assert!(aptos.contains("        only_owner(account, state);"), "{}", aptos);
+    
+    let entrypoints = MoveCodeGenerator::new().entrypoints(&contract);
+    assert_eq!(entrypoints["deposit"].signers, ["account"]);
+    assert!(entrypoints["bump"].entry && entrypoints["bump"].signers.is_empty());
+    assert!(!entrypoints["claim"].entry);
+    
+    // An Aptos view function is called without a signer to take the sender from
+    let contract = Contract::parse(r#"
+        contract Vault {
+            state {
+                deposits: map<address, u64>;
+            }
+            
+            view fn mine() -> u64 {
+                return deposits[msg_sender()];
+            }
+        }
+    "#).expect("Failed to parse");
+    let err = MoveCodeGenerator::new().generate(&contract).unwrap_err();
+    assert!(err.to_string().contains("View function 'mine' needs the caller's address"), "{}", err);
+}
+
+#[test]
+fn test_sender_check_modifiers_become_account_constraints() {
+    let contract = Contract::parse(r#"
+        contract Admin {
+            state {
+                owner: address;
+                admin: address;
+                paused: bool;
+                fee: u64;
+            }
+            
+            modifier onlyOwner() {
+                require(owner == msg_sender(), "Only owner");
+                _;
+            }
+            
+            modifier onlyAdmin() {
+                require(msg_sender() == admin);
+                _;
+            }
+            
+            modifier whenNotPaused() {
+                require(!paused, "Paused");
+                _;
+            }
+            
+            modifier ownerOrAdmin() {
+                require(msg_sender() == owner || msg_sender() == admin, "Not allowed");
+                _;
+            }
+            
+            public fn set_fee(new_fee: u64) onlyOwner onlyAdmin {
+                fee = new_fee;
+            }
+            
+            public fn pause() whenNotPaused onlyOwner {
+                paused = true;
+            }
+            
+            public fn unpause() ownerOrAdmin {
+                paused = false;
+            }
+            
+            public fn hand_over(owner: address) onlyOwner {
+                fee = 0;
+            }
+        }
+    "#).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    let context = |name: &str| {
+        let start = solana.find(&format!("pub struct {}<'info> {{", name)).expect("context missing");
+        solana[start..start + solana[start..].find("\n}\n").unwrap()].to_string()
+    };
+    let function = |name: &str| {
+        let start = solana.find(&format!("pub fn {}(", name)).expect("function missing");
+        solana[start..start + solana[start..].find("\n    }\n").unwrap()].to_string()
+    };
+    
+    // Anchor takes one address constraint; further checks are plain constraints
+    assert!(context("SetFee").contains(
+        "    #[account(address = state.owner @ ErrorCode::OnlyOwner, constraint = user.key() == state.admin @ ErrorCode::RequirementFailed)]\n    pub user: Signer<'info>,"),
+        "{}", solana);
+    assert!(!function("set_fee").contains("require!"), "{}", solana);
+    
+    // Only leading checks move: onlyOwner after whenNotPaused stays in the body
+    assert!(!context("Pause").contains("address ="), "{}", solana);
+    let pause = function("pause");
+    assert!(pause.find("ErrorCode::Paused").unwrap() < pause.find("ErrorCode::OnlyOwner").unwrap(), "{}", pause);
+    
+    // Anything but a single comparison with the sender falls back to require!
+    assert!(!context("Unpause").contains("address ="), "{}", solana);
+    assert!(function("unpause").contains("ErrorCode::NotAllowed);"), "{}", solana);
+    
+    // A parameter shadowing the state field is compared in the body
+    assert!(!context("HandOver").contains("address ="), "{}", solana);
+    assert!(function("hand_over").contains("require!((owner == ctx.accounts.user.key()), ErrorCode::OnlyOwner);"), "{}", solana);
+}
+
+#[test]
+fn test_solana_struct_definitions_snapshot() {
+    // The Pool struct from examples/amm_dex.ccdsl
+    let input = r#"
+        contract AmmDex {
+            state {
+                pools: map<address, Pool>;
+                pool_count: u64;
+            }
+            
+            struct Pool {
+                token_a: address;
+                token_b: address;
+                reserve_a: u64;
+                reserve_b: u64;
+                k_last: u128;
+                block_timestamp_last: u64;
+                price_cumulative_a: u128;
+                price_cumulative_b: u128;
+                locked: bool;
+            }
+            
+            public fn update_reserves(pool_address: address, reserve_a: u64, reserve_b: u64) {
+                pools[pool_address].reserve_a = reserve_a;
+                pools[pool_address].reserve_b = reserve_b;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!(code);
+    
+    // Unbounded struct fields need a maximum length like state variables do
+    let input = input.replace("locked: bool;", "locked: bool;\n                label: string;");
+    let contract = Contract::parse(&input).expect("Failed to parse");
+    let err = SolanaCodeGenerator::new().generate(&contract).unwrap_err().to_string();
+    assert!(err.contains("state variable 'pools': Pool.label (String) has no maximum length"), "{}", err);
+}
+
+#[test]
+fn test_move_struct_abilities_snapshot() {
+    // The Pool struct from examples/amm_dex.ccdsl
+    let input = r#"
+        contract AmmDex {
+            state {
+                pools: map<address, Pool>;
+                books: map<address, Book>;
+            }
+            
+            struct Pool {
+                token_a: address;
+                token_b: address;
+                reserve_a: u64;
+                reserve_b: u64;
+                k_last: u128;
+                block_timestamp_last: u64;
+                price_cumulative_a: u128;
+                price_cumulative_b: u128;
+                locked: bool;
+            }
+            
+            struct Book {
+                pool: Pool;
+                orders: map<u64, u64>;
+            }
+            
+            public fn update_reserves(pool_address: address, reserve_a: u64, reserve_b: u64) {
+                pools[pool_address].reserve_a = reserve_a;
+                pools[pool_address].reserve_b = reserve_b;
+            }
+            
+            view fn get_pool_info(pool_address: address) -> Pool {
+                return pools[pool_address];
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!("move_struct_abilities_aptos", aptos);
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!("move_struct_abilities_sui", sui);
+    
+    let idl = MoveCodeGenerator::new().idl(&contract).expect("Failed to generate");
+    assert_eq!(idl.structs[0].abilities, ["copy", "drop", "store"]);
+    // A Table can't be copied or dropped, and neither can a struct holding one
+    assert_eq!(idl.structs[1].abilities, ["store"]);
+    assert_eq!(idl.structs[2].name, "State");
+    
+    // Kept in state but never returned: no `copy`
+    let stored = input.replace("view fn get_pool_info(pool_address: address) -> Pool {\n                return pools[pool_address];", "view fn get_reserve_a(pool_address: address) -> u64 {\n                return pools[pool_address].reserve_a;");
+    let aptos = MoveCodeGenerator::new().generate(&Contract::parse(&stored).expect("Failed to parse")).expect("Failed to generate");
+    assert!(aptos.contains("    struct Pool has drop, store {"), "{}", aptos);
+    
+    // Returning a struct copies it out of its Table
+    let returned = input.replace("-> Pool {", "-> Book {").replace("return pools[pool_address];", "return books[pool_address];");
+    let contract = Contract::parse(&returned).expect("Failed to parse");
+    for err in [MoveCodeGenerator::new().generate(&contract).unwrap_err(), SuiCodeGenerator::new().generate(&contract).unwrap_err()] {
+        assert!(err.to_string().contains("Struct 'Book' needs `copy` because function 'get_pool_info' returns it, but its field 'orders' is a map"), "{}", err);
+    }
+}
+
+#[test]
+fn test_move_assignments_write_through_the_right_place() {
+    let contract = Contract::parse(r#"
+        contract Amm {
+            state {
+                total: u64;
+                pools: map<address, Pool>;
+                reserves: vec<u64>;
+                slots: vec<Pool>;
+            }
+            
+            struct Pool {
+                reserve_a: u64;
+                locked: bool;
+            }
+            
+            public fn set(a: address, i: u64, v: u64) {
+                let count = v;
+                count = count + 1;
+                total = count;
+                pools[a].reserve_a = v;
+                reserves[i] = reserves[i] + v;
+                slots[i].locked = true;
+            }
+            
+            public fn bumped(a: address, v: u64) -> u64 {
+                let pool = pools[a];
+                pool.reserve_a = pool.reserve_a + v;
+                return pool.reserve_a;
+            }
+        }
+    "#).expect("Failed to parse");
+    
+    for code in [
+        MoveCodeGenerator::new().generate(&contract).expect("Failed to generate"),
+        SuiCodeGenerator::new().generate(&contract).expect("Failed to generate"),
+    ] {
+        assert!(code.contains("    use std::vector;\n"), "{}", code);
+        // Locals and state fields are places of their own
+        assert!(code.contains("        count = (count + 1);\n        state.total = count;\n"), "{}", code);
+        assert!(code.contains("        pool.reserve_a = (pool.reserve_a + v);\n"), "{}", code);
+        // Fields of map entries and vector elements are written through the mutable borrow
+        assert!(code.contains("        table::borrow_mut(&mut state.pools, a).reserve_a = v;\n"), "{}", code);
+        assert!(code.contains("        vector::borrow_mut(&mut state.slots, i).locked = true;\n"), "{}", code);
+        // A whole element is written through the reference
+        assert!(code.contains("        *vector::borrow_mut(&mut state.reserves, i) = (*vector::borrow(&state.reserves, i) + v);\n"), "{}", code);
+    }
+}
+
+#[test]
+fn test_spl_token_standard_snapshot() {
+    let input = r#"
+        contract Token {
+            state {
+                total_supply: u64;
+                owner: address;
+                balances: map<address, u64>;
+                decimals: u8;
+            }
+            
+            event Transfer(indexed from: address, indexed to: address, amount: u64);
+            
+            public fn initialize(initial_supply: u64, token_decimals: u8) {
+                total_supply = initial_supply;
+                decimals = token_decimals;
+                owner = msg_sender();
+                balances[msg_sender()] = initial_supply;
+            }
+            
+            public fn transfer(to: address, amount: u64) {
+                let from = msg_sender();
+                require(balances[from] >= amount, "Insufficient balance");
+                balances[from] = balances[from] - amount;
+                balances[to] = balances[to] + amount;
+                emit Transfer(from, to, amount);
+            }
+            
+            public fn mint(to: address, amount: u64) {
+                require(msg_sender() == owner, "Only owner can mint");
+                total_supply = total_supply + amount;
+                balances[to] = balances[to] + amount;
+            }
+            
+            public fn burn(amount: u64) {
+                balances[msg_sender()] = balances[msg_sender()] - amount;
+                total_supply = total_supply - amount;
+            }
+            
+            public fn balance_of(account: address) -> u64 {
+                return balances[account];
+            }
+            
+            public fn get_total_supply() -> u64 {
+                return total_supply;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = SolanaCodeGenerator::new().with_token_standard(TokenStandard::Spl);
+    let code = generator.generate(&contract).expect("Failed to generate");
+    insta::assert_snapshot!(code);
+    
+    // The supply and decimals live in the mint, and balances in token accounts
+    assert!(!code.contains("pub total_supply: u64"), "{}", code);
+    assert!(!code.contains("BalancesEntry"), "{}", code);
+    assert!(code.contains("return Ok(ctx.accounts.mint.supply);"), "{}", code);
+}
+
+#[test]
+fn test_target_versions_select_code_variants() {
+    let token = r#"
+        contract Token {
+            state {
+                total_supply: u64;
+                balances: map<address, u64>;
+            }
+            
+            public fn mint(to: address, amount: u64) {
+                total_supply = total_supply + amount;
+                balances[to] = balances[to] + amount;
+            }
+        }
+    "#;
+    let contract = Contract::parse(token).expect("Failed to parse");
+    let spl = |version: &str| SolanaCodeGenerator::new()
+        .with_token_standard(TokenStandard::Spl)
+        .with_anchor_version(version.parse().unwrap());
+    
+    // Bumps became fields of ctx.bumps in 0.29
+    let code = spl("0.28").generate(&contract).expect("Failed to generate");
+    assert!(code.contains("&[&[b\"mint\", &[*ctx.bumps.get(\"mint\").unwrap()]]],"), "{}", code);
+    let code = spl("0.30.1").generate(&contract).expect("Failed to generate");
+    assert!(code.contains("&[&[b\"mint\", &[ctx.bumps.mint]]],"), "{}", code);
+    
+    // Anchor.toml pins the selected release, and 0.30 replaced the seeds feature
+    let anchor = spl("0.30.1").anchor_toml(&contract);
+    assert!(anchor.starts_with("[toolchain]\nanchor_version = \"0.30.1\"\n\n[features]\nresolution = true\n"), "{}", anchor);
+    let anchor = SolanaCodeGenerator::new().anchor_toml(&contract);
+    assert!(anchor.starts_with("[features]\nseeds = false\n"), "{}", anchor);
+    
+    assert_eq!("0.29".parse::<AnchorVersion>().unwrap().to_string(), "0.29.0");
+    assert!("0.27.0".parse::<AnchorVersion>().unwrap_err().contains("expected 0.28 to 0.31"));
+    assert!("1.0".parse::<AnchorVersion>().is_err());
+    
+    // Frameworks before module events can't emit; before view functions `view` is a plain function
+    let input = r#"
+        contract Counter {
+            state {
+                count: u64;
+            }
+            
+            event Counted(count: u64);
+            
+            view fn get() -> u64 {
+                return count;
+            }
+            
+            public fn bump() {
+                emit Counted(count);
+            }
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = |rev: &str| MoveCodeGenerator::new().with_framework(rev.parse().unwrap());
+    assert!(aptos("mainnet").generate(&contract).unwrap().contains("#[view]"));
+    assert!(aptos("aptos-node-v1.8.0").generate(&contract).is_ok());
+    let err = aptos("aptos-node-v1.5.0").generate(&contract).unwrap_err();
+    assert!(err.to_string().contains("Aptos framework 'aptos-node-v1.5.0' predates module events"), "{}", err);
+    let quiet = Contract::parse(&input.replace("emit Counted(count);", "")).expect("Failed to parse");
+    let code = aptos("v1.2").generate(&quiet).expect("Failed to generate");
+    assert!(!code.contains("#[view]"), "{}", code);
+    assert!(!aptos("v1.2").idl(&quiet).unwrap().functions[0].view);
+}
+
+#[test]
+fn test_spl_token_standard_rejects_incompatible_patterns() {
+    let generator = SolanaCodeGenerator::new().with_token_standard(TokenStandard::Spl);
+    let error = |input: &str| {
+        let contract = Contract::parse(input).expect("Failed to parse");
+        generator.generate(&contract).unwrap_err().to_string()
+    };
+    
+    // transfer_from moves tokens the signer doesn't own
+    let token = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/token.ccdsl")).unwrap();
+    let err = error(&token);
+    assert!(err.contains("'transfer_from' can only move tokens out of msg_sender()'s balance"), "{}", err);
+    
+    // Supply changes without a matching balance change
+    let err = error(r#"
+        contract Token {
+            state { total_supply: u64; balances: map<address, u64>; }
+            public fn inflate(amount: u64) { total_supply = total_supply + amount; }
+        }
+    "#);
+    assert!(err.contains("every write to 'balances' or 'total_supply' in 'inflate' must be part of a mint"), "{}", err);
+    
+    // Balances must be a map of addresses to u64
+    let err = error(r#"
+        contract Vault {
+            state { deposits: map<address, u64>; }
+            public fn deposit(amount: u64) { deposits[msg_sender()] = deposits[msg_sender()] + amount; }
+        }
+    "#);
+    assert!(err.contains("SPL token mode needs a `balances: map<address, u64>` state variable"), "{}", err);
+}
+
+#[test]
+fn test_move_loops_snapshot() {
+    let input = r#"
+        contract Airdrop {
+            state {
+                rounds: u64;
+            }
+            
+            public fn countdown(n: u64) -> u64 {
+                let mut left = n;
+                while (left > 10) {
+                    left = left - 10;
+                }
+                return left;
+            }
+            
+            public fn sum_odd(n: u64) -> u64 {
+                let mut total = 0;
+                for i in 0..n {
+                    if (i % 2 == 0) {
+                        continue;
+                    }
+                    total = total + i;
+                }
+                return total;
+            }
+            
+            public fn airdrop(recipients: vec<address>, amount: u64) {
+                require(amount > 0, "Amount must be positive");
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    // `for x in v` has no surface syntax yet; frontends emitting JSON ASTs produce it
+    contract.functions[2].body.push(Statement::ForEach {
+        variable: "recipient".to_string(),
+        iterable: Expression::Identifier("recipients".to_string()),
+        body: vec![Statement::Require {
+            condition: Expression::Binary {
+                op: cross_chain_dsl::BinaryOp::Ne,
+                left: Box::new(Expression::Identifier("recipient".to_string())),
+                right: Box::new(Expression::MsgSender),
+            },
+            message: None,
+        }],
+    });
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(!aptos.contains("TODO"), "{}", aptos);
+    assert!(aptos.contains("use std::vector;"), "{}", aptos);
+    insta::assert_snapshot!(aptos);
+}
+
+#[test]
+fn test_move_range_bound_is_evaluated_once() {
+    let input = r#"
+        contract Counter {
+            state { count: u64; }
+            public fn run() {
+                let mut k = 4;
+                for i in 0..k {
+                    k = k - 1;
+                    count = count + 1;
+                }
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    // Solana evaluates `0..k` once, so the loop runs four times on both chains
+    assert!(aptos.contains("let i_end = k;"), "{}", aptos);
+    assert!(aptos.contains("while ((i < i_end))"), "{}", aptos);
+    assert!(!aptos.contains("(i < k)"), "{}", aptos);
+}
+
+#[test]
+fn test_external_contract_calls_snapshot() {
+    // A mock of the external program, compiled with the same compiler: Anchor sets the
+    // return data of `get_price` for the caller to read
+    let callee = r#"
+        contract Oracle {
+            public fn get_price(pair: string) -> u64 {
+                return 42;
+            }
+        }
+    "#;
+    let caller = r#"
+        external contract Oracle at Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS {
+            fn get_price(pair: string) -> u64;
+        }
+        
+        contract Vault {
+            state {
+                last_price: u64;
+            }
+            
+            public fn refresh(pair: string) {
+                last_price = Oracle.get_price(pair);
+            }
+        }
+    "#;
+    
+    let mock = Contract::parse(callee).expect("Failed to parse");
+    let mock = SolanaCodeGenerator::new().generate(&mock).expect("Failed to generate");
+    assert!(mock.contains("pub fn get_price(ctx: Context<") && mock.contains(">, pair: String) -> Result<u64> {"));
+    
+    let program = Program::parse(caller).expect("Failed to parse");
+    let solana = SolanaCodeGenerator::new()
+        .with_externals(program.externals.clone())
+        .generate(&program.contracts[0])
+        .expect("Failed to generate");
+    assert!(solana.contains("oracle::get_price(ctx.accounts.oracle_program.to_account_info(), ctx.remaining_accounts, pair)?"));
+    assert!(solana.contains("pub oracle_program: Program<'info, oracle::program::Oracle>,"));
+    assert!(solana.contains("hash(b\"global:get_price\")"));
+    assert!(solana.contains("get_return_data()"));
+    insta::assert_snapshot!(solana);
+    
+    let aptos = MoveCodeGenerator::new()
+        .with_externals(program.externals.clone())
+        .generate(&program.contracts[0])
+        .expect("Failed to generate");
+    assert!(aptos.contains("    use oracle::oracle;\n"));
+    assert!(aptos.contains("oracle::get_price(pair)"));
+    
+    // A program id that isn't a Solana public key is rejected when the program calls it
+    let program = Program::parse(&caller.replace("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS", "\"0x1\"")).expect("Failed to parse");
+    let err = SolanaCodeGenerator::new()
+        .with_externals(program.externals.clone())
+        .generate(&program.contracts[0])
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("External contract 'Oracle' needs a Solana program id"));
+    let aptos = MoveCodeGenerator::new()
+        .with_externals(program.externals.clone())
+        .generate(&program.contracts[0])
+        .expect("Failed to generate");
+    assert!(aptos.contains("    use 0x1::oracle;\n"));
+}
+
+#[test]
+fn test_view_functions_are_read_only_queries() {
+    let input = r#"
+        contract Bank {
+            state {
+                total: u64;
+                balances: map<address, u64>;
+            }
+            
+            view fn get_balance(addr: address) -> u64 {
+                return balances[addr];
+            }
+            
+            view fn get_total() -> u64 {
+                return total;
+            }
+            
+            public fn deposit(amount: u64) -> u64 {
+                total = total + amount;
+                return total;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("addr: Pubkey) -> Result<u64> {\n        return Ok("), "{}", solana);
+    // Anchor's `.view()` simulates the call, which needs every account read-only
+    let entrypoints = SolanaCodeGenerator::new().entrypoints(&contract).expect("Failed to generate");
+    for view in ["get_balance", "get_total"] {
+        assert!(entrypoints[view].accounts.iter().all(|a| !a.writable && !a.signer), "{:?}", entrypoints[view]);
+    }
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("    #[view]\n    public fun get_balance(addr: address): u64 acquires State {"), "{}", aptos);
+    assert!(aptos.contains("    #[view]\n    public fun get_total(): u64 acquires State {"));
+    // Entry functions can't return values
+    assert!(aptos.contains("    public fun deposit(amount: u64): u64 acquires State {"));
+    
+    let entrypoints = MoveCodeGenerator::new().entrypoints(&contract);
+    assert!(!entrypoints["get_total"].entry);
+    assert!(entrypoints["get_total"].signers.is_empty());
+    // Sui passes the shared State object, read-only to view functions
+    let entrypoints = SuiCodeGenerator::new().entrypoints(&contract).expect("Failed to generate");
+    assert_eq!(entrypoints["get_total"].args, [Argument { name: "state".to_string(), ty: "&State".to_string() }]);
+    assert_eq!(entrypoints["deposit"].args[0].ty, "&mut State");
+    assert!(!entrypoints["deposit"].entry);
+    
+    let check = |body: &str| {
+        let input = format!(r#"
+            contract Bank {{
+                state {{
+                    total: u64;
+                }}
+                
+                event Read(total: u64);
+                
+                fn bump() {{
+                    total = total + 1;
+                }}
+                
+                {}
+            }}
+        "#, body);
+        let contract = Contract::parse(&input).expect("Failed to parse");
+        let solana = SolanaCodeGenerator::new().generate(&contract).map(|_| ());
+        let aptos = MoveCodeGenerator::new().generate(&contract).map(|_| ());
+        assert_eq!(solana.is_ok(), aptos.is_ok());
+        solana
+    };
+    
+    let err = check("view fn get() -> u64 { total = 1; return total; }").unwrap_err();
+    assert!(err.to_string().contains("View function 'get' modifies state"));
+    // Writes through an internal helper count too
+    let err = check("view fn get() -> u64 { bump(); return total; }").unwrap_err();
+    assert!(err.to_string().contains("View function 'get' modifies state"));
+    let err = check("view fn get() -> u64 { emit Read(total); return total; }").unwrap_err();
+    assert!(err.to_string().contains("View function 'get' emits an event"));
+    let err = check("view fn get() { }").unwrap_err();
+    assert!(err.to_string().contains("View function 'get' must return a value"));
+}
+
+#[test]
+fn test_payable_functions_transfer_the_attached_lamports() {
+    let input = r#"
+        contract Vault {
+            state {
+                deposited: u64;
+            }
+            
+            payable fn deposit() {
+                require(msg_value() > 0, "Nothing deposited");
+                deposited = deposited + msg_value();
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = SolanaCodeGenerator::new();
+    let solana = generator.generate(&contract).expect("Failed to generate");
+    
+    // The amount is an instruction argument moved into the state account, not the signer's balance
+    assert!(solana.contains("pub fn deposit(ctx: Context<Deposit>, msg_value: u64) -> Result<()> {"), "{}", solana);
+    assert!(solana.contains("anchor_lang::system_program::transfer(CpiContext::new(ctx.accounts.system_program.to_account_info(), \
+        anchor_lang::system_program::Transfer { from: ctx.accounts.user.to_account_info(), to: ctx.accounts.state.to_account_info() }), msg_value)?;"));
+    assert!(solana.contains("ctx.accounts.state.deposited.checked_add(msg_value)"));
+    assert!(!solana.contains("lamports()"));
+    
+    let idl = generator.idl(&contract).expect("Failed to build the IDL");
+    let deposit = idl.instructions.iter().find(|i| i.name == "deposit").unwrap();
+    assert_eq!(deposit.args[0].name, "msgValue");
+    assert!(deposit.accounts.iter().any(|a| a.name == "user" && a.is_mut && a.is_signer));
+    assert!(deposit.accounts.iter().any(|a| a.name == "state" && a.is_mut));
+    assert!(deposit.accounts.iter().any(|a| a.name == "systemProgram"));
+    
+    // The generated tests send the amount as the argument, and nothing to hit the require
+    let tests = generator.generate_tests(&contract).expect("Failed to generate tests");
+    let failing = &tests[tests.find("it(\"deposit fails with NothingDeposited\"").expect("no failing test")..];
+    assert!(failing.contains(".deposit(new BN(0))"), "{}", tests);
+    
+    let err = MoveCodeGenerator::new().generate(&contract).unwrap_err();
+    assert!(err.to_string().contains("Payable function 'deposit' cannot be compiled to Move"));
+    
+    let clash = input.replace("fn deposit()", "fn deposit(msg_value: u64)");
+    let err = generator.generate(&Contract::parse(&clash).unwrap()).unwrap_err();
+    assert!(err.to_string().contains("cannot have a parameter named 'msg_value'"));
+}
+
+#[test]
+fn test_bridge_vault_and_authority_snapshot() {
+    let input = r#"
+        contract BridgeToken {
+            state {
+                balances: map<address, u64>;
+            }
+            
+            bridge {
+                vault;
+                authority: address;
+            }
+            
+            public fn lock_for_bridge(amount: u64, target_chain: u64) {
+                require(balances[msg_sender()] >= amount, "Insufficient balance");
+                balances[msg_sender()] = balances[msg_sender()] - amount;
+                bridge_lock(amount, target_chain);
+            }
+            
+            public fn receive_from_bridge(to: address, amount: u64, source_chain: u64) {
+                bridge_receive(to, amount, source_chain);
+                balances[to] = balances[to] + amount;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = SolanaCodeGenerator::new();
+    let solana = generator.generate(&contract).expect("Failed to generate");
+    
+    // The same structure as the hand-written bridge in solana-impl
+    assert!(solana.contains("pub fn init_bridge(ctx: Context<"));
+    assert!(solana.contains("        init,\n        payer = user,\n        space = 8 + BridgeVault::LEN,\n        seeds = [b\"bridge_vault\"],"));
+    assert!(solana.contains("pub struct BridgeVault {\n    pub authority: Pubkey,\n    pub locked_amount: u64,\n    pub sequence: u64,\n    pub inbound_sequences: [u64; 2],\n}"), "{}", solana);
+    assert!(solana.contains("require!(ctx.accounts.authority.key() == ctx.accounts.bridge_vault.authority, ErrorCode::Unauthorized);"));
+    assert!(solana.contains("emit!(CrossChainLockEvent { from: ctx.accounts.user.key(), amount, target_chain: remote_chain(target_chain)?, \
+                             sequence: next_outbound(&mut ctx.accounts.bridge_vault)?,"), "{}", solana);
+    assert!(solana.contains("pub struct CrossChainReceiveEvent {\n    pub to: Pubkey,\n    pub amount: u64,\n    pub source_chain: u32,\n    pub sequence: u64,\n    pub timestamp: i64,\n}"));
+    assert!(solana.contains("pub const APTOS_CHAIN_ID: u32 = 2;"));
+    assert!(solana.contains("InsufficientVaultBalance,"));
+    // Only the receiving side checks the authority
+    let lock = &solana[solana.find("pub fn lock_for_bridge").unwrap()..solana.find("pub fn receive_from_bridge").unwrap()];
+    assert!(!lock.contains("authority"), "{}", lock);
+    insta::assert_snapshot!(solana);
+    
+    let idl = generator.idl(&contract).expect("Failed to build the IDL");
+    assert_eq!(idl.instructions[0].name, "initBridge");
+    assert!(idl.accounts.iter().any(|a| a.name == "BridgeVault"));
+    assert!(idl.events.iter().any(|e| e.name == "CrossChainLockEvent"));
+    let receive = idl.instructions.iter().find(|i| i.name == "receiveFromBridge").unwrap();
+    assert!(receive.accounts.iter().any(|a| a.name == "authority" && a.is_signer));
+    
+    let tests = generator.generate_tests(&contract).expect("Failed to generate tests");
+    assert!(tests.contains(".initBridge(provider.wallet.publicKey)"), "{}", tests);
+    assert!(tests.contains(".lockForBridge(new BN(0), new BN(2))"), "{}", tests);
+    
+    let err = MoveCodeGenerator::new().generate(&contract).unwrap_err();
+    assert!(err.to_string().contains("only the Solana backend supports"));
+    
+    let clash = input.replace("lock_for_bridge", "init_bridge");
+    let err = SolanaCodeGenerator::new().generate(&Contract::parse(&clash).unwrap()).unwrap_err();
+    assert!(err.to_string().contains("clashes with the instruction generated for the bridge"));
+}
+
+#[test]
+fn test_bridge_messages_are_released_once_in_sequence() {
+    let input = r#"
+        contract BridgeToken {
+            bridge {
+                vault;
+                relayer: address;
+            }
+            
+            public fn unlock(to: address, amount: u64, source_chain: u64) {
+                bridge_receive(to, amount, source_chain);
+            }
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let generator = SolanaCodeGenerator::new();
+    let solana = generator.generate(&contract).expect("Failed to generate");
+    
+    // The relayer passes the sequence number of the lock event it delivers
+    assert!(solana.contains("pub fn unlock(ctx: Context<Unlock>, to: Pubkey, amount: u64, source_chain: u64, sequence: u64)"), "{}", solana);
+    let unlock = &solana[solana.find("pub fn unlock").unwrap()..];
+    let unlock = &unlock[..unlock.find("\n    }\n").unwrap()];
+    let check = unlock.find("process_inbound(&mut ctx.accounts.bridge_vault, remote_chain(source_chain)?, sequence)?;").expect("replay check missing");
+    assert!(check < unlock.find("locked_amount -= amount").unwrap(), "{}", unlock);
+    assert!(unlock.contains("source_chain: remote_chain(source_chain)?, sequence, timestamp"), "{}", unlock);
+    
+    // A (source chain, sequence) pair is accepted once: the chain's next expected number only grows
+    let process = &solana[solana.find("fn process_inbound(").unwrap()..];
+    let process = &process[..process.find("\n}\n").unwrap()];
+    assert!(process.contains("APTOS_CHAIN_ID => &mut vault.inbound_sequences[0],\n        SUI_CHAIN_ID => &mut vault.inbound_sequences[1],"), "{}", process);
+    let processed = process.find("require!(sequence >= *next, ErrorCode::AlreadyProcessed);").expect("replay check missing");
+    let gap = process.find("require!(sequence == *next, ErrorCode::SequenceGap);").expect("gap check missing");
+    let advance = process.find("*next += 1;").expect("sequence is not advanced");
+    assert!(processed < gap && gap < advance, "{}", process);
+    assert!(solana.contains("    AlreadyProcessed,\n"), "{}", solana);
+    
+    let idl = generator.idl(&contract).expect("Failed to build the IDL");
+    let unlock = idl.instructions.iter().find(|i| i.name == "unlock").unwrap();
+    assert_eq!(unlock.args.last().unwrap().name, "sequence");
+    let IdlTypeDefinitionTy::Struct { fields } = &idl.accounts.iter().find(|a| a.name == "BridgeVault").unwrap().ty;
+    assert!(fields.iter().any(|f| f.name == "inboundSequences"), "{:?}", fields);
+    
+    let twice = input.replace("bridge_receive(to, amount, source_chain);", "bridge_receive(to, amount, source_chain); bridge_receive(to, amount, source_chain);");
+    let err = generator.generate(&Contract::parse(&twice).unwrap()).unwrap_err();
+    assert!(err.to_string().contains("calls bridge_receive more than once"), "{}", err);
+    let clash = input.replace("source_chain: u64)", "source_chain: u64, sequence: u64)");
+    let err = generator.generate(&Contract::parse(&clash).unwrap()).unwrap_err();
+    assert!(err.to_string().contains("cannot have a parameter named 'sequence'"), "{}", err);
+}
+
+#[test]
+fn test_bitwise_operators_lower_to_each_target() {
+    let contract = Contract::parse(r#"
+        contract Flags {
+            state {
+                flags: u64;
+            }
+            
+            public fn pack(x: u64, n: u64) {
+                flags = (flags | (x << 2u8)) & (x >> n) ^ (x >> 3);
+            }
+        }
+    "#).expect("Failed to parse");
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("(x << 2u8)"), "{}", solana);
+    assert!(solana.contains("(x >> n)"), "{}", solana);
+    
+    // Move shifts take a u8 amount
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("(x << 2u8)"), "{}", aptos);
+    assert!(aptos.contains("(x >> (n as u8))"), "{}", aptos);
+    assert!(aptos.contains("(x >> 3)"), "{}", aptos);
+    assert!(aptos.contains(" | ") && aptos.contains(" & ") && aptos.contains(" ^ "), "{}", aptos);
+}
+
+#[test]
+fn test_options_and_map_get_lower_to_each_target() {
+    let contract = Contract::parse(r#"
+        contract Registry {
+            state {
+                owners: map<u64, address>;
+                limits: map<address, u64>;
+                fallback: option<u64>;
+            }
+            
+            public fn clear() {
+                fallback = None;
+            }
+            
+            view fn owner_of(id: u64) -> option<address> {
+                return owners.get(id);
+            }
+            
+            view fn limit(who: address) -> u64 {
+                return limits.get(who).unwrap_or(fallback.unwrap_or(100));
+            }
+        }
+    "#).expect("Failed to parse");
+    
+    // A missing entry is an empty PDA, so the account is unchecked and only read if it holds data
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(solana.contains("pub owners_id: UncheckedAccount<'info>,"), "{}", solana);
+    assert!(solana.contains("if ctx.accounts.owners_id.data_is_empty() { None } else { Some(OwnersEntry::try_deserialize("), "{}", solana);
+    assert!(solana.contains(".unwrap_or(ctx.accounts.state.fallback.clone().unwrap_or(100))"), "{}", solana);
+    assert!(solana.contains("ctx.accounts.state.fallback = None;"), "{}", solana);
+    
+    let aptos = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(aptos.contains("fallback: std::option::Option<u64>,"), "{}", aptos);
+    assert!(aptos.contains("(if (table::contains(&state.owners, id)) std::option::some(*table::borrow(&state.owners, id)) else std::option::none())"), "{}", aptos);
+    assert!(aptos.contains("*table::borrow_with_default(&state.limits, who, &std::option::get_with_default(&state.fallback, 100))"), "{}", aptos);
+    
+    // Sui tables have no borrow_with_default
+    let sui = SuiCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(sui.contains("(if (table::contains(&state.limits, who)) *table::borrow(&state.limits, who) else std::option::get_with_default(&state.fallback, 100))"), "{}", sui);
+}
+
+#[test]
+fn test_move_abort_codes_are_stable() {
+    let contract = |extra: &str| Contract::parse(&format!(r#"
+        contract Vault {{
+            state {{
+                paused: bool;
+                balance: u64;
+            }}
+            
+            public fn withdraw(amount: u64) {{
+                {}
+                require(balance >= amount, "Insufficient balance");
+                require(amount > 0);
+                balance = balance - amount;
+            }}
+        }}
+    "#, extra)).expect("Failed to parse");
+    
+    let generator = MoveCodeGenerator::new();
+    let before = generator.generate(&contract("")).expect("Failed to generate");
+    assert!(before.contains("const E_INSUFFICIENT_BALANCE: u64 = 928740058;"), "{}", before);
+    assert!(before.contains("const E_REQUIREMENT_FAILED: u64 = 782503533;"), "{}", before);
+    assert!(before.contains("assert!((state.balance >= amount), E_INSUFFICIENT_BALANCE);"), "{}", before);
+    assert_eq!(generator.generate(&contract("")).unwrap(), before);
+    
+    // A new require keeps the codes of the existing ones
+    let after = generator.generate(&contract(r#"require(!paused, "Paused");"#)).expect("Failed to generate");
+    assert!(after.contains("const E_PAUSED: u64 = 3727171563;"), "{}", after);
+    assert!(after.contains("const E_INSUFFICIENT_BALANCE: u64 = 928740058;"), "{}", after);
+    assert!(after.contains("const E_REQUIREMENT_FAILED: u64 = 782503533;"), "{}", after);
+    
+    // The sidecar maps the same codes to their messages, on both Move chains
+    let codes = generator.abort_codes(&contract(r#"require(!paused, "Paused");"#)).unwrap();
+    assert_eq!(serde_json::to_value(&codes).unwrap(), serde_json::json!({
+        "module": "cross_chain::vault",
+        "errors": [
+            { "code": 3727171563u64, "name": "E_PAUSED", "msg": "Paused" },
+            { "code": 928740058, "name": "E_INSUFFICIENT_BALANCE", "msg": "Insufficient balance" },
+            { "code": 782503533, "name": "E_REQUIREMENT_FAILED", "msg": "Requirement failed" },
+        ],
+    }));
+    assert_eq!(SuiCodeGenerator::new().abort_codes(&contract(r#"require(!paused, "Paused");"#)).unwrap(), codes);
+}
@@ -0,0 +1,112 @@
+use cross_chain_dsl::Contract;
+use cross_chain_dsl::estimator::{CostEstimator, CostReport, FunctionCost};
+
+fn cost<'a>(report: &'a CostReport, name: &str) -> &'a FunctionCost {
+    report.functions.iter()
+        .find(|f| f.name == name)
+        .expect("function missing from report")
+}
+
+#[test]
+fn test_estimate_ranks_storage_heavy_functions_higher() {
+    let input = r#"
+        contract Token {
+            state {
+                total_supply: u64;
+                balances: map<address, u64>;
+            }
+            
+            public fn double(x: u64) -> u64 {
+                return x * 2;
+            }
+            
+            public fn supply(x: u64) -> u64 {
+                return total_supply + x;
+            }
+            
+            public fn transfer(to: address, amount: u64) {
+                balances[msg_sender()] = balances[msg_sender()] - amount;
+                balances[to] = balances[to] + amount;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let report = CostEstimator::new().estimate(&contract);
+    
+    assert_eq!(report.functions.len(), 3);
+    let double = cost(&report, "double");
+    let supply = cost(&report, "supply");
+    let transfer = cost(&report, "transfer");
+    
+    assert!(double.compute_units < supply.compute_units);
+    assert!(supply.compute_units < transfer.compute_units);
+    assert!(double.move_gas < supply.move_gas);
+    assert!(supply.move_gas < transfer.move_gas);
+}
+
+#[test]
+fn test_estimate_scales_with_static_loop_bounds() {
+    let input = r#"
+        contract Loops {
+            state {
+                counter: u64;
+            }
+            
+            public fn four(x: u64) {
+                for i in 0..4 {
+                    counter = counter + x;
+                }
+            }
+            
+            public fn eight(x: u64) {
+                for i in 0..8 {
+                    counter = counter + x;
+                }
+            }
+            
+            public fn unknown(n: u64) {
+                for i in 0..n {
+                    counter = counter + 1;
+                }
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let report = CostEstimator::new().estimate(&contract);
+    
+    let four = cost(&report, "four");
+    let eight = cost(&report, "eight");
+    assert!(four.statically_bounded);
+    assert!(eight.statically_bounded);
+    assert!(eight.compute_units > four.compute_units);
+    
+    let unknown = cost(&report, "unknown");
+    assert!(!unknown.statically_bounded);
+    assert!(report.to_string().contains("~"));
+}
+
+#[test]
+fn test_estimate_ignores_locals_shadowing_state() {
+    let input = r#"
+        contract Shadow {
+            state {
+                owner: address;
+            }
+            
+            public fn read_state(x: u64) -> address {
+                return owner;
+            }
+            
+            public fn read_local(owner: address) -> address {
+                return owner;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    let report = CostEstimator::new().estimate(&contract);
+    
+    assert!(cost(&report, "read_local").compute_units < cost(&report, "read_state").compute_units);
+}
@@ -0,0 +1,139 @@
+module cross_chain::token {
+    use std::signer;
+    use std::string::{Self, String};
+    use aptos_std::table::{Self, Table};
+    use aptos_framework::event;
+    use aptos_framework::timestamp;
+
+    const E_ALREADY_INITIALIZED: u64 = 2;
+    const E_NOT_MODULE_ADDRESS: u64 = 3;
+    const E_INSUFFICIENT_BALANCE: u64 = 928740058;
+    const E_INSUFFICIENT_ALLOWANCE: u64 = 1549635412;
+    const E_ONLY_OWNER_CAN_MINT: u64 = 2002378302;
+
+    /// Main state resource
+    struct State has key {
+        total_supply: u64,
+        owner: address,
+        balances: Table<address, u64>,
+        allowances: Table<address, Table<address, u64>>,
+        decimals: u8,
+        name: String,
+        symbol: String,
+    }
+
+    #[event]
+    struct TokenInitialized has drop, store {
+        owner: address,
+        initial_supply: u64,
+    }
+
+    #[event]
+    struct Transfer has drop, store {
+        from: address,
+        to: address,
+        amount: u64,
+    }
+
+    #[event]
+    struct Approval has drop, store {
+        owner: address,
+        spender: address,
+        amount: u64,
+    }
+
+    #[event]
+    struct Mint has drop, store {
+        to: address,
+        amount: u64,
+    }
+
+    #[event]
+    struct Burn has drop, store {
+        from: address,
+        amount: u64,
+    }
+
+    public entry fun initialize(account: &signer, initial_supply: u64, token_name: String, token_symbol: String, token_decimals: u8) acquires State {
+        assert!(signer::address_of(account) == @cross_chain, E_NOT_MODULE_ADDRESS);
+        assert!(!exists<State>(@cross_chain), E_ALREADY_INITIALIZED);
+        move_to(account, State { total_supply: 0, owner: @0x0, balances: table::new(), allowances: table::new(), decimals: 0, name: string::utf8(b""), symbol: string::utf8(b"") });
+        let state = borrow_global_mut<State>(@cross_chain);
+        state.total_supply = initial_supply;
+        state.name = token_name;
+        state.symbol = token_symbol;
+        state.decimals = token_decimals;
+        state.owner = signer::address_of(account);
+        table::upsert(&mut state.balances, signer::address_of(account), initial_supply);
+        event::emit(TokenInitialized { owner: state.owner, initial_supply });
+    }
+
+    public entry fun transfer(account: &signer, to: address, amount: u64) acquires State {
+        let state = borrow_global_mut<State>(@cross_chain);
+        let from = signer::address_of(account);
+        assert!((*table::borrow_with_default(&state.balances, from, &0) >= amount), E_INSUFFICIENT_BALANCE);
+        let new_balances = (*table::borrow_with_default(&state.balances, from, &0) - amount);
+        table::upsert(&mut state.balances, from, new_balances);
+        let new_balances = (*table::borrow_with_default(&state.balances, to, &0) + amount);
+        table::upsert(&mut state.balances, to, new_balances);
+        event::emit(Transfer { from, to, amount });
+    }
+
+    public entry fun approve(account: &signer, spender: address, amount: u64) acquires State {
+        let state = borrow_global_mut<State>(@cross_chain);
+        let owner = signer::address_of(account);
+        if (!table::contains(&state.allowances, owner)) table::add(&mut state.allowances, owner, table::new());
+        table::upsert(table::borrow_mut(&mut state.allowances, owner), spender, amount);
+        event::emit(Approval { owner, spender, amount });
+    }
+
+    public entry fun transfer_from(account: &signer, from: address, to: address, amount: u64) acquires State {
+        let state = borrow_global_mut<State>(@cross_chain);
+        let spender = signer::address_of(account);
+        assert!(((if (table::contains(&state.allowances, from)) *table::borrow_with_default(table::borrow(&state.allowances, from), spender, &0) else 0) >= amount), E_INSUFFICIENT_ALLOWANCE);
+        assert!((*table::borrow_with_default(&state.balances, from, &0) >= amount), E_INSUFFICIENT_BALANCE);
+        if (!table::contains(&state.allowances, from)) table::add(&mut state.allowances, from, table::new());
+        let new_allowances = ((if (table::contains(&state.allowances, from)) *table::borrow_with_default(table::borrow(&state.allowances, from), spender, &0) else 0) - amount);
+        table::upsert(table::borrow_mut(&mut state.allowances, from), spender, new_allowances);
+        let new_balances = (*table::borrow_with_default(&state.balances, from, &0) - amount);
+        table::upsert(&mut state.balances, from, new_balances);
+        let new_balances = (*table::borrow_with_default(&state.balances, to, &0) + amount);
+        table::upsert(&mut state.balances, to, new_balances);
+        event::emit(Transfer { from, to, amount });
+    }
+
+    public entry fun mint(account: &signer, to: address, amount: u64) acquires State {
+        let state = borrow_global_mut<State>(@cross_chain);
+        assert!((signer::address_of(account) == state.owner), E_ONLY_OWNER_CAN_MINT);
+        state.total_supply = (state.total_supply + amount);
+        let new_balances = (*table::borrow_with_default(&state.balances, to, &0) + amount);
+        table::upsert(&mut state.balances, to, new_balances);
+        event::emit(Mint { to, amount });
+    }
+
+    public entry fun burn(account: &signer, amount: u64) acquires State {
+        let state = borrow_global_mut<State>(@cross_chain);
+        let from = signer::address_of(account);
+        assert!((*table::borrow_with_default(&state.balances, from, &0) >= amount), E_INSUFFICIENT_BALANCE);
+        let new_balances = (*table::borrow_with_default(&state.balances, from, &0) - amount);
+        table::upsert(&mut state.balances, from, new_balances);
+        state.total_supply = (state.total_supply - amount);
+        event::emit(Burn { from, amount });
+    }
+
+    public fun balance_of(account: address): u64 acquires State {
+        let state = borrow_global<State>(@cross_chain);
+        *table::borrow_with_default(&state.balances, account, &0)
+    }
+
+    public fun allowance(owner: address, spender: address): u64 acquires State {
+        let state = borrow_global<State>(@cross_chain);
+        (if (table::contains(&state.allowances, owner)) *table::borrow_with_default(table::borrow(&state.allowances, owner), spender, &0) else 0)
+    }
+
+    public fun get_total_supply(): u64 acquires State {
+        let state = borrow_global<State>(@cross_chain);
+        state.total_supply
+    }
+
+}
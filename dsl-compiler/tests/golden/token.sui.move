@@ -0,0 +1,135 @@
+module cross_chain::token {
+    use sui::object::{Self, UID};
+    use sui::transfer;
+    use sui::tx_context::{Self, TxContext};
+    use sui::event;
+    use sui::table::{Self, Table};
+    use std::ascii::{Self, String};
+
+    const E_ALREADY_INITIALIZED: u64 = 2;
+    const E_INSUFFICIENT_BALANCE: u64 = 928740058;
+    const E_INSUFFICIENT_ALLOWANCE: u64 = 1549635412;
+    const E_ONLY_OWNER_CAN_MINT: u64 = 2002378302;
+
+    /// Main state resource
+    struct State has key {
+        id: UID,
+        total_supply: u64,
+        owner: address,
+        balances: Table<address, u64>,
+        allowances: Table<address, Table<address, u64>>,
+        decimals: u8,
+        name: String,
+        symbol: String,
+        is_initialized: bool,
+    }
+
+    struct TokenInitialized has copy, drop {
+        owner: address,
+        initial_supply: u64,
+    }
+
+    struct Transfer has copy, drop {
+        from: address,
+        to: address,
+        amount: u64,
+    }
+
+    struct Approval has copy, drop {
+        owner: address,
+        spender: address,
+        amount: u64,
+    }
+
+    struct Mint has copy, drop {
+        to: address,
+        amount: u64,
+    }
+
+    struct Burn has copy, drop {
+        from: address,
+        amount: u64,
+    }
+
+    public entry fun initialize(state: &mut State, initial_supply: u64, token_name: String, token_symbol: String, token_decimals: u8, ctx: &mut TxContext) {
+        assert!(!state.is_initialized, E_ALREADY_INITIALIZED);
+        state.is_initialized = true;
+        state.total_supply = initial_supply;
+        state.name = token_name;
+        state.symbol = token_symbol;
+        state.decimals = token_decimals;
+        state.owner = tx_context::sender(ctx);
+        if (table::contains(&state.balances, tx_context::sender(ctx))) { *table::borrow_mut(&mut state.balances, tx_context::sender(ctx)) = initial_supply } else { table::add(&mut state.balances, tx_context::sender(ctx), initial_supply) };
+        event::emit(TokenInitialized { owner: state.owner, initial_supply });
+    }
+
+    public entry fun transfer(state: &mut State, to: address, amount: u64, ctx: &mut TxContext) {
+        let from = tx_context::sender(ctx);
+        assert!(((if (table::contains(&state.balances, from)) *table::borrow(&state.balances, from) else 0) >= amount), E_INSUFFICIENT_BALANCE);
+        let new_balances = ((if (table::contains(&state.balances, from)) *table::borrow(&state.balances, from) else 0) - amount);
+        if (table::contains(&state.balances, from)) { *table::borrow_mut(&mut state.balances, from) = new_balances } else { table::add(&mut state.balances, from, new_balances) };
+        let new_balances = ((if (table::contains(&state.balances, to)) *table::borrow(&state.balances, to) else 0) + amount);
+        if (table::contains(&state.balances, to)) { *table::borrow_mut(&mut state.balances, to) = new_balances } else { table::add(&mut state.balances, to, new_balances) };
+        event::emit(Transfer { from, to, amount });
+    }
+
+    public entry fun approve(state: &mut State, spender: address, amount: u64, ctx: &mut TxContext) {
+        let owner = tx_context::sender(ctx);
+        if (!table::contains(&state.allowances, owner)) table::add(&mut state.allowances, owner, table::new(ctx));
+        if (table::contains(table::borrow(&state.allowances, owner), spender)) { *table::borrow_mut(table::borrow_mut(&mut state.allowances, owner), spender) = amount } else { table::add(table::borrow_mut(&mut state.allowances, owner), spender, amount) };
+        event::emit(Approval { owner, spender, amount });
+    }
+
+    public entry fun transfer_from(state: &mut State, from: address, to: address, amount: u64, ctx: &mut TxContext) {
+        let spender = tx_context::sender(ctx);
+        assert!(((if (table::contains(&state.allowances, from) && table::contains(table::borrow(&state.allowances, from), spender)) *table::borrow(table::borrow(&state.allowances, from), spender) else 0) >= amount), E_INSUFFICIENT_ALLOWANCE);
+        assert!(((if (table::contains(&state.balances, from)) *table::borrow(&state.balances, from) else 0) >= amount), E_INSUFFICIENT_BALANCE);
+        if (!table::contains(&state.allowances, from)) table::add(&mut state.allowances, from, table::new(ctx));
+        let new_allowances = ((if (table::contains(&state.allowances, from) && table::contains(table::borrow(&state.allowances, from), spender)) *table::borrow(table::borrow(&state.allowances, from), spender) else 0) - amount);
+        if (table::contains(table::borrow(&state.allowances, from), spender)) { *table::borrow_mut(table::borrow_mut(&mut state.allowances, from), spender) = new_allowances } else { table::add(table::borrow_mut(&mut state.allowances, from), spender, new_allowances) };
+        let new_balances = ((if (table::contains(&state.balances, from)) *table::borrow(&state.balances, from) else 0) - amount);
+        if (table::contains(&state.balances, from)) { *table::borrow_mut(&mut state.balances, from) = new_balances } else { table::add(&mut state.balances, from, new_balances) };
+        let new_balances = ((if (table::contains(&state.balances, to)) *table::borrow(&state.balances, to) else 0) + amount);
+        if (table::contains(&state.balances, to)) { *table::borrow_mut(&mut state.balances, to) = new_balances } else { table::add(&mut state.balances, to, new_balances) };
+        event::emit(Transfer { from, to, amount });
+    }
+
+    public entry fun mint(state: &mut State, to: address, amount: u64, ctx: &mut TxContext) {
+        assert!((tx_context::sender(ctx) == state.owner), E_ONLY_OWNER_CAN_MINT);
+        state.total_supply = (state.total_supply + amount);
+        let new_balances = ((if (table::contains(&state.balances, to)) *table::borrow(&state.balances, to) else 0) + amount);
+        if (table::contains(&state.balances, to)) { *table::borrow_mut(&mut state.balances, to) = new_balances } else { table::add(&mut state.balances, to, new_balances) };
+        event::emit(Mint { to, amount });
+    }
+
+    public entry fun burn(state: &mut State, amount: u64, ctx: &mut TxContext) {
+        let from = tx_context::sender(ctx);
+        assert!(((if (table::contains(&state.balances, from)) *table::borrow(&state.balances, from) else 0) >= amount), E_INSUFFICIENT_BALANCE);
+        let new_balances = ((if (table::contains(&state.balances, from)) *table::borrow(&state.balances, from) else 0) - amount);
+        if (table::contains(&state.balances, from)) { *table::borrow_mut(&mut state.balances, from) = new_balances } else { table::add(&mut state.balances, from, new_balances) };
+        state.total_supply = (state.total_supply - amount);
+        event::emit(Burn { from, amount });
+    }
+
+    public fun balance_of(state: &State, account: address): u64 {
+        (if (table::contains(&state.balances, account)) *table::borrow(&state.balances, account) else 0)
+    }
+
+    public fun allowance(state: &State, owner: address, spender: address): u64 {
+        (if (table::contains(&state.allowances, owner) && table::contains(table::borrow(&state.allowances, owner), spender)) *table::borrow(table::borrow(&state.allowances, owner), spender) else 0)
+    }
+
+    public fun get_total_supply(state: &State): u64 {
+        state.total_supply
+    }
+
+    fun init(ctx: &mut TxContext) {
+        transfer::share_object(State { id: object::new(ctx), total_supply: 0, owner: @0x0, balances: table::new(ctx), allowances: table::new(ctx), decimals: 0, name: ascii::string(b""), symbol: ascii::string(b""), is_initialized: false });
+    }
+
+    #[test_only]
+    public fun init_for_testing(ctx: &mut TxContext) {
+        init(ctx);
+    }
+
+}
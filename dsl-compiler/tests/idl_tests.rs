@@ -0,0 +1,69 @@
+use cross_chain_dsl::codegen::move_gen::MoveCodeGenerator;
+use cross_chain_dsl::codegen::solana::SolanaCodeGenerator;
+use cross_chain_dsl::idl::{AnchorIdl, IdlType, MoveIdl};
+use cross_chain_dsl::{Contract, Program, SemanticAnalyzer};
+use std::fs;
+use std::path::Path;
+
+/// The token example, checked the way `ccdsl compile` checks it
+fn token() -> Contract {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let program = Program::load(&root.join("examples/token.ccdsl")).expect("Failed to load");
+    let contract = program.contracts[0].clone();
+    SemanticAnalyzer::new(contract.name.clone())
+        .with_program(&program)
+        .analyze(&contract)
+        .expect("Semantic analysis failed");
+    contract
+}
+
+#[test]
+fn test_token_idl_matches_golden_file() {
+    let idl = SolanaCodeGenerator::new().idl(&token()).expect("Failed to generate the IDL");
+    let json = serde_json::to_string_pretty(&idl).unwrap() + "\n";
+    
+    // CCDSL_UPDATE_GOLDEN=1 rewrites the file after an intended change
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/token.idl.json");
+    if std::env::var_os("CCDSL_UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &json).unwrap();
+    }
+    assert_eq!(json, fs::read_to_string(&golden).unwrap());
+}
+
+#[test]
+fn test_anchor_idl_roundtrips_through_json() {
+    let idl = SolanaCodeGenerator::new()
+        .with_program_id("11111111111111111111111111111111")
+        .idl(&token())
+        .expect("Failed to generate the IDL");
+    let json = serde_json::to_string(&idl).unwrap();
+    assert_eq!(serde_json::from_str::<AnchorIdl>(&json).unwrap(), idl);
+    
+    // Types use Anchor's notation
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["instructions"][1]["args"][0], serde_json::json!({ "name": "to", "type": "publicKey" }));
+    assert_eq!(value["metadata"]["address"], "11111111111111111111111111111111");
+    let nested: IdlType = serde_json::from_str(r#"{ "vec": { "array": ["u8", 32] } }"#).unwrap();
+    assert_eq!(nested, IdlType::Vec(Box::new(IdlType::Array(Box::new(IdlType::U8), 32))));
+}
+
+#[test]
+fn test_move_idl_roundtrips_through_json() {
+    let idl = MoveCodeGenerator::new().idl(&token()).expect("Failed to generate the IDL");
+    let json = serde_json::to_string(&idl).unwrap();
+    assert_eq!(serde_json::from_str::<MoveIdl>(&json).unwrap(), idl);
+    
+    let transfer = idl.functions.iter().find(|f| f.name == "transfer").unwrap();
+    assert!(transfer.entry);
+    assert_eq!(transfer.params[0].ty, "address");
+    assert_eq!(idl.errors.iter().map(|e| (e.name.as_str(), e.code)).collect::<Vec<_>>(),
+        [("E_ALREADY_INITIALIZED", 2), ("E_NOT_MODULE_ADDRESS", 3), ("E_INSUFFICIENT_BALANCE", 928740058), ("E_INSUFFICIENT_ALLOWANCE", 1549635412), ("E_ONLY_OWNER_CAN_MINT", 2002378302)]);
+}
+
+#[test]
+fn test_idl_is_stable_across_runs() {
+    let contract = token();
+    let first = serde_json::to_string(&SolanaCodeGenerator::new().idl(&contract).unwrap()).unwrap();
+    let second = serde_json::to_string(&SolanaCodeGenerator::new().idl(&contract).unwrap()).unwrap();
+    assert_eq!(first, second);
+}
@@ -0,0 +1,128 @@
+use cross_chain_dsl::{Contract, Program, Import, SemanticAnalyzer};
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes `files` into a fresh directory and returns its path
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ccdsl-imports-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (file, content) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_parse_import_statements() {
+    let input = r#"
+        import { TokenOps, fee } from "./lib/token.ccdsl";
+        
+        contract Main {
+            public fn test(x: u64) -> u64 {
+                return x;
+            }
+        }
+    "#;
+    
+    let program = Program::parse(input).expect("Failed to parse");
+    assert_eq!(program.imports, vec![Import {
+        path: "./lib/token.ccdsl".to_string(),
+        items: vec!["TokenOps".to_string(), "fee".to_string()],
+    }]);
+    
+    // Contract::parse has nowhere to resolve them from
+    assert!(Contract::parse(input).is_err());
+}
+
+#[test]
+fn test_import_functions_and_contracts() {
+    let dir = project("resolve", &[
+        ("lib/math.ccdsl", r#"
+            contract MathLib {
+                private fn fee(amount: u64) -> u64 {
+                    return amount / 100;
+                }
+            }
+        "#),
+        ("lib/token.ccdsl", r#"
+            contract TokenOps {
+                public fn balance(owner: address) -> u64 {
+                    return 0;
+                }
+            }
+        "#),
+        ("main.ccdsl", r#"
+            import { fee } from "./lib/math.ccdsl";
+            import { TokenOps } from "./lib/token.ccdsl";
+            
+            contract Vault {
+                public fn net(owner: address) -> u64 {
+                    let balance = TokenOps.balance(owner);
+                    return balance - fee(balance);
+                }
+            }
+        "#),
+    ]);
+    
+    let program = Program::load(&dir.join("main.ccdsl")).expect("Failed to load");
+    
+    assert_eq!(program.contracts.len(), 1);
+    let vault = &program.contracts[0];
+    let names: Vec<&str> = vault.functions.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["net", "fee"]);
+    assert_eq!(program.dependencies.len(), 1);
+    assert_eq!(program.dependencies[0].name, "TokenOps");
+    
+    assert!(SemanticAnalyzer::new("Vault".to_string())
+        .with_program(&program)
+        .analyze(vault)
+        .is_ok());
+}
+
+#[test]
+fn test_import_errors() {
+    let dir = project("errors", &[
+        ("a.ccdsl", r#"
+            import { helper } from "./b.ccdsl";
+            contract A {
+                public fn run(x: u64) -> u64 { return x; }
+            }
+        "#),
+        ("b.ccdsl", r#"
+            import { run } from "./a.ccdsl";
+            contract B {
+                private fn helper(x: u64) -> u64 { return x; }
+            }
+        "#),
+        ("missing.ccdsl", r#"
+            import { nothing } from "./lib.ccdsl";
+            contract C {
+                public fn run(x: u64) -> u64 { return x; }
+            }
+        "#),
+        ("clash.ccdsl", r#"
+            import { helper } from "./lib.ccdsl";
+            contract D {
+                private fn helper(x: u64) -> u64 { return x; }
+            }
+        "#),
+        ("lib.ccdsl", r#"
+            contract Lib {
+                private fn helper(x: u64) -> u64 { return x; }
+            }
+        "#),
+    ]);
+    
+    let err = Program::load(&dir.join("a.ccdsl")).unwrap_err();
+    assert!(format!("{:#}", err).contains("Circular import"));
+    
+    let err = Program::load(&dir.join("missing.ccdsl")).unwrap_err();
+    assert!(format!("{:#}", err).contains("'nothing' is not defined in \"./lib.ccdsl\""));
+    
+    let err = Program::load(&dir.join("clash.ccdsl")).unwrap_err();
+    assert!(format!("{:#}", err).contains("conflicts with an existing definition in 'D'"));
+    
+    assert!(Program::load(&dir.join("absent.ccdsl")).is_err());
+}
@@ -1,4 +1,6 @@
-use cross_chain_dsl::{Contract, optimizer::Optimizer, Expression, Statement};
+use cross_chain_dsl::{Contract, optimizer::{Optimizer, OptimizerConfig, OptimizationLevel, OptimizationReport, PassStats}, Expression, Statement};
+use cross_chain_dsl::{BinaryOp, LValue, EventDefinition, EventParam, Type, UnaryOp};
+use cross_chain_dsl::codegen::{solana::SolanaCodeGenerator, move_gen::MoveCodeGenerator};
 
 #[test]
 fn test_optimizer_constant_folding() {
@@ -78,9 +80,77 @@ fn test_optimizer_dead_code_elimination() {
     
     optimizer.optimize(&mut contract);
     
-    // Check that dead code was removed
+    // Check that dead code was removed and the live branch kept, scoped since it declares a local
     let func = &contract.functions[0];
-    assert!(func.body.len() < 3); // Some statements should be removed
+    assert_eq!(func.body, vec![Statement::Block(vec![Statement::Let {
+        name: "a".to_string(),
+        ty: None,
+        value: Expression::Number(10),
+        is_mutable: false,
+    }])]);
+}
+
+#[test]
+fn test_optimizer_keeps_live_branch_of_constant_if() {
+    let input = r#"
+        contract LiveBranchTest {
+            state {
+                counter: u64;
+            }
+            
+            public fn test(x: u64) {
+                if (1 < 2) {
+                    counter = counter + x;
+                } else {
+                    counter = 0;
+                }
+                if (false) {
+                    counter = 1;
+                } else {
+                    counter = counter * x;
+                }
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    let body = &contract.functions[0].body;
+    assert_eq!(body.len(), 2);
+    assert!(body.iter().all(|s| matches!(s, Statement::Assign { .. })));
+    assert_eq!(report.dead_code_removed, 2);
+    
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(code.contains("ctx.accounts.state.counter = ctx.accounts.state.counter.checked_add(x).ok_or(ErrorCode::Overflow)?;"));
+    assert!(code.contains("ctx.accounts.state.counter = ctx.accounts.state.counter.checked_mul(x).ok_or(ErrorCode::Overflow)?;"));
+    assert!(!code.contains("counter = 0;"));
+}
+
+#[test]
+fn test_optimizer_keeps_live_branch_locals_scoped() {
+    let input = r#"
+        contract ScopeTest {
+            public fn test() -> u64 {
+                let x = 1;
+                if (true) {
+                    let x = 2;
+                }
+                return x;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    Optimizer::new().optimize(&mut contract);
+    
+    let body = &contract.functions[0].body;
+    assert!(matches!(body[1], Statement::Block(_)));
+    assert_eq!(body.last(), Some(&Statement::Return { value: Some(Expression::Number(1)) }));
+    
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(code.contains("return Ok(1)"));
+    assert!(!code.contains("return Ok(2)"));
 }
 
 #[test]
@@ -99,9 +169,17 @@ fn test_optimizer_constant_propagation() {
     let mut contract = Contract::parse(input).expect("Failed to parse");
     let mut optimizer = Optimizer::new();
     
-    optimizer.optimize(&mut contract);
+    let report = optimizer.optimize(&mut contract);
     
     // After optimization, expressions using constants should be folded
+    let body = &contract.functions[0].body;
+    assert!(matches!(&body[2], Statement::Let { name, value: Expression::Number(30), .. } if name == "z"));
+    assert_eq!(body[3], Statement::Return { value: Some(Expression::Number(30)) });
+    
+    // The first pass does all the work, the second confirms nothing is left
+    assert_eq!(report.passes.len(), 2);
+    assert_eq!(report.passes[0].constants_folded, 2);
+    assert_eq!(report.passes[1], PassStats::default());
 }
 
 #[test]
@@ -124,4 +202,927 @@ fn test_optimizer_boolean_simplification() {
     optimizer.optimize(&mut contract);
     
     // Boolean expressions should be simplified
-}
\ No newline at end of file
+}
+#[test]
+fn test_optimizer_o0_leaves_contract_untouched() {
+    let input = r#"
+        contract NoOptTest {
+            public fn calculate() -> u64 {
+                let a = 10 + 20;
+                require(true, "Always passes");
+                return a;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let original = contract.clone();
+    let config = OptimizerConfig::for_level(OptimizationLevel::O0);
+    let mut optimizer = Optimizer::with_config(config);
+    
+    let report = optimizer.optimize(&mut contract);
+    
+    assert_eq!(contract, original);
+    assert_eq!(report.constants_folded, 0);
+    assert_eq!(report.dead_code_removed, 0);
+}
+
+#[test]
+fn test_optimizer_config_builder() {
+    let config = OptimizerConfig::builder()
+        .level(OptimizationLevel::O2)
+        .enable_inlining(false)
+        .max_inline_size(4)
+        .build();
+    
+    assert_eq!(config.level, OptimizationLevel::O2);
+    assert!(config.enable_cse);
+    assert!(!config.enable_inlining);
+    assert_eq!(config.max_inline_size, 4);
+    
+    assert_eq!("2".parse::<OptimizationLevel>(), Ok(OptimizationLevel::O2));
+    assert!("3".parse::<OptimizationLevel>().is_err());
+}
+
+#[test]
+fn test_optimizer_o2_common_subexpression_elimination() {
+    let input = r#"
+        contract CseTest {
+            public fn test(x: u64, y: u64) -> u64 {
+                let a = x * y;
+                let b = x * y;
+                return a + b;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let mut optimizer = Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2));
+    
+    let report = optimizer.optimize(&mut contract);
+    
+    assert_eq!(report.common_subexpressions_eliminated, 1);
+    match &contract.functions[0].body[1] {
+        Statement::Let { value, .. } => assert_eq!(value, &Expression::Identifier("a".to_string())),
+        other => panic!("Expected let, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_optimizer_o2_inlines_and_removes_dead_functions() {
+    let input = r#"
+        contract InlineTest {
+            public fn test(x: u64) -> u64 {
+                return x;
+            }
+            
+            private fn double(value: u64) -> u64 {
+                return value * 2;
+            }
+            
+            private fn unused() -> u64 {
+                return 1;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    contract.functions[0].body = vec![Statement::Return {
+        value: Some(Expression::Call {
+            func: Box::new(Expression::Identifier("double".to_string())),
+            args: vec![Expression::Number(21)],
+        }),
+    }];
+    let mut optimizer = Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2));
+    
+    let report = optimizer.optimize(&mut contract);
+    
+    assert_eq!(report.functions_inlined, 1);
+    assert_eq!(contract.functions[0].body[0], Statement::Return { value: Some(Expression::Number(42)) });
+    assert_eq!(report.dead_functions_removed, vec!["double".to_string(), "unused".to_string()]);
+    assert_eq!(contract.functions.len(), 1);
+}
+
+#[test]
+fn test_optimizer_report_serializes_to_json() {
+    let input = r#"
+        contract ReportTest {
+            public fn calculate() -> u64 {
+                let a = 10 + 20;
+                return a;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    let json = serde_json::to_string(&report).expect("Failed to serialize report");
+    let decoded: OptimizationReport = serde_json::from_str(&json).expect("Failed to deserialize report");
+    assert_eq!(decoded, report);
+    assert_eq!(decoded.level, OptimizationLevel::O1);
+    assert!(decoded.constants_folded >= 1);
+}
+
+#[test]
+fn test_optimizer_strength_reduction() {
+    let input = r#"
+        contract FeeTest {
+            public fn calculate(fee: u64) -> u64 {
+                let a = fee / 1024;
+                let b = fee * 8;
+                let c = fee % 16;
+                let d = fee / 10;
+                return a + b + c + d;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    assert_eq!(report.strength_reductions, 2);
+    
+    let code = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate");
+    assert!(code.contains("fee >> 10"));
+    assert!(code.contains("fee & 15"));
+    assert!(code.contains("fee.checked_div(10u64)"));
+    // A shift would drop the overflow check of the multiplication
+    assert!(code.contains("fee.checked_mul(8u64)"), "{}", code);
+    assert!(!code.contains("<<"), "{}", code);
+}
+
+#[test]
+fn test_optimizer_keeps_signed_arithmetic() {
+    let input = r#"
+        contract SignedTest {
+            state {
+                balance: u64;
+                total: u64;
+            }
+            
+            public fn update(delta: u64) {
+                require(delta > 0, "Not positive");
+                if delta != 0 {
+                    balance = balance / 4 + delta % 8;
+                }
+            }
+        }
+    "#;
+    
+    // Signed types have no surface syntax yet; JSON ASTs can use them
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    contract.state[0].ty = Type::I64;
+    contract.functions[0].params[0].ty = Type::I64;
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    // `-7 / 4` rounds toward zero but `-7 >> 2` down, and `delta > 0` leaves
+    // negative values for `delta != 0`
+    assert_eq!(report.strength_reductions, 0);
+    let body = &contract.functions[0].body;
+    assert_eq!(body.len(), 2);
+    assert!(matches!(body[1], Statement::If { .. }));
+}
+
+#[test]
+fn test_optimizer_o2_removes_dead_functions_and_events() {
+    let input = r#"
+        contract DeadCodeTest {
+            public fn transfer(to: address, amount: u64) {
+                emit Transfer(to, amount);
+            }
+            
+            private fn orphaned_helper(amount: u64) {
+                emit Audit(amount);
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let event = |name: &str| EventDefinition {
+        name: name.to_string(),
+        params: vec![EventParam { name: "amount".to_string(), ty: Type::U64, indexed: false }],
+    };
+    contract.events = vec![event("Transfer"), event("Audit"), event("Unused")];
+    
+    let mut kept = contract.clone();
+    let config = OptimizerConfig::builder()
+        .level(OptimizationLevel::O2)
+        .enable_dead_function_elimination(false)
+        .enable_dead_event_elimination(false)
+        .build();
+    Optimizer::with_config(config).optimize(&mut kept);
+    assert_eq!(kept.functions.len(), 2);
+    assert_eq!(kept.events.len(), 3);
+    
+    let mut optimizer = Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2));
+    let report = optimizer.optimize(&mut contract);
+    
+    assert_eq!(report.dead_functions_removed, vec!["orphaned_helper".to_string()]);
+    assert_eq!(report.dead_events_removed, vec!["Audit".to_string(), "Unused".to_string()]);
+    assert_eq!(contract.events.len(), 1);
+    
+    let solana = SolanaCodeGenerator::new().generate(&contract).expect("Failed to generate Solana");
+    let move_code = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate Move");
+    assert!(solana.contains("fn transfer"));
+    assert!(move_code.contains("fun transfer"));
+    assert!(!solana.contains("orphaned_helper"));
+    assert!(!move_code.contains("orphaned_helper"));
+}
+
+#[test]
+fn test_optimizer_o2_unrolls_small_constant_loops() {
+    let input = r#"
+        contract UnrollTest {
+            state {
+                rates: vec<u64>;
+                total: u64;
+            }
+            
+            public fn accumulate(scale: u64) {
+                for i in 0..4 {
+                    total = total + rates[i * 2];
+                }
+                
+                for j in 0..100 {
+                    total = total + rates[j];
+                }
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let mut optimizer = Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2));
+    
+    let report = optimizer.optimize(&mut contract);
+    
+    assert_eq!(report.loops_unrolled, 1);
+    let body = &contract.functions[0].body;
+    assert_eq!(body.len(), 5);
+    for (k, stmt) in body[..4].iter().enumerate() {
+        match stmt {
+            Statement::Assign { value: Expression::Binary { right, .. }, .. } => match right.as_ref() {
+                Expression::Index { index, .. } => assert_eq!(**index, Expression::Number(k as u64 * 2)),
+                other => panic!("Expected folded index, got {:?}", other),
+            },
+            other => panic!("Expected assignment, got {:?}", other),
+        }
+    }
+    assert!(matches!(body[4], Statement::For { .. }));
+}
+
+#[test]
+fn test_optimizer_unroll_threshold_and_nested_loops() {
+    let input = r#"
+        contract NestedUnrollTest {
+            state {
+                grid: vec<u64>;
+                total: u64;
+            }
+            
+            public fn sum(scale: u64) {
+                for i in 0..2 {
+                    for j in 0..=2 {
+                        total = total + grid[i * 3 + j];
+                    }
+                }
+            }
+        }
+    "#;
+    
+    let parsed = Contract::parse(input).expect("Failed to parse");
+    
+    let mut contract = parsed.clone();
+    let config = OptimizerConfig::builder().level(OptimizationLevel::O2).build();
+    let report = Optimizer::with_config(config).optimize(&mut contract);
+    assert_eq!(report.loops_unrolled, 3);
+    assert_eq!(contract.functions[0].body.len(), 6);
+    
+    // The inner loop is over the threshold, so the outer one is left alone too
+    let mut contract = parsed.clone();
+    let config = OptimizerConfig::builder()
+        .level(OptimizationLevel::O2)
+        .unroll_threshold(3)
+        .build();
+    let report = Optimizer::with_config(config).optimize(&mut contract);
+    assert_eq!(report.loops_unrolled, 0);
+    assert_eq!(contract.functions[0].body.len(), 1);
+}
+
+#[test]
+fn test_optimizer_set_unroll_threshold() {
+    let input = r#"
+        contract Reserves {
+            state {
+                reserves: vec<u64>;
+                total: u64;
+            }
+            
+            public fn update(rate: u64, n: u64) {
+                let share = 1;
+                for i in 0..4 {
+                    let share = reserves[i] * rate;
+                    reserves[i] = share + i;
+                }
+                total = share;
+                
+                for j in 0..n {
+                    total = total + reserves[j];
+                }
+            }
+        }
+    "#;
+    
+    let parsed = Contract::parse(input).expect("Failed to parse");
+    let mut contract = parsed.clone();
+    let mut optimizer = Optimizer::new();
+    optimizer.set_unroll_threshold(5);
+    let report = optimizer.optimize(&mut contract);
+    // The second loop's bound is a parameter
+    assert_eq!(report.loops_unrolled, 1);
+    
+    let body = &contract.functions[0].body;
+    for (k, stmt) in body[1..5].iter().enumerate() {
+        // Each iteration keeps its own `share`, with the induction variable folded in
+        let Statement::Block(copy) = stmt else { panic!("Expected a block, got {:?}", stmt) };
+        assert!(matches!(&copy[1], Statement::Assign { target: LValue::Index { index, .. }, .. } if **index == Expression::Number(k as u64)), "{:?}", copy);
+    }
+    // The local declared before the loop is not shadowed by the unrolled ones
+    assert!(matches!(&body[0], Statement::Let { name, .. } if name == "share"));
+    assert!(matches!(&body[5], Statement::Assign { value: Expression::Number(1), .. }), "{:?}", body[5]);
+    assert!(matches!(body[6], Statement::For { .. }));
+    
+    let move_code = MoveCodeGenerator::new().generate(&contract).expect("Failed to generate Move");
+    assert!(move_code.contains("        {\n            let share = (*vector::borrow(&state.reserves, 0) * rate);\n"), "{}", move_code);
+    
+    // Exactly 4 iterations is not fewer than 4
+    let mut contract = parsed.clone();
+    let mut optimizer = Optimizer::new();
+    optimizer.set_unroll_threshold(4);
+    assert_eq!(optimizer.optimize(&mut contract).loops_unrolled, 0);
+    
+    let mut contract = parsed;
+    let mut optimizer = Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2));
+    optimizer.set_unroll_threshold(0);
+    assert_eq!(optimizer.optimize(&mut contract).loops_unrolled, 0);
+}
+
+#[test]
+fn test_optimizer_overflow_is_not_folded() {
+    let input = r#"
+        contract OverflowTest {
+            public fn test(x: u64) -> u64 {
+                let a = 18446744073709551615 + 1;
+                let b = 1 - 2;
+                let c = 18446744073709551615 - 1;
+                return a;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    let body = &contract.functions[0].body;
+    assert!(matches!(body[0], Statement::Let { value: Expression::Binary { .. }, .. }));
+    assert!(matches!(body[1], Statement::Let { value: Expression::Binary { .. }, .. }));
+    assert!(matches!(body[2], Statement::Let { value: Expression::Number(18446744073709551614), .. }));
+    assert_eq!(report.warnings.len(), 2);
+    assert!(report.warnings[0].contains("18446744073709551615 + 1"));
+    assert!(report.warnings[1].contains("1 - 2"));
+    assert!(report.errors.is_empty());
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let config = OptimizerConfig::builder().strict_overflow(true).build();
+    let report = Optimizer::with_config(config).optimize(&mut contract);
+    assert_eq!(report.errors.len(), 2);
+    assert!(report.warnings.is_empty());
+}
+
+#[test]
+fn test_optimizer_rejects_negated_unsigned_constant() {
+    let input = r#"
+        contract NegTest {
+            public fn test(x: u64) -> u64 {
+                return x;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let negated = Expression::Unary {
+        op: UnaryOp::Neg,
+        expr: Box::new(Expression::Number(5)),
+    };
+    contract.functions[0].body = vec![Statement::Return { value: Some(negated.clone()) }];
+    
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    assert_eq!(contract.functions[0].body[0], Statement::Return { value: Some(negated) });
+    assert_eq!(report.warnings, vec!["Cannot negate unsigned constant 5".to_string()]);
+}
+
+#[test]
+fn test_optimizer_allows_negated_constant_in_signed_contract() {
+    let input = r#"
+        contract NegTest {
+            public fn test(x: u64) -> u64 {
+                return x;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    contract.functions[0].params[0].ty = Type::I64;
+    contract.functions[0].return_type = Some(Type::I64);
+    let negated = Expression::Unary {
+        op: UnaryOp::Neg,
+        expr: Box::new(Expression::Number(5)),
+    };
+    contract.functions[0].body = vec![Statement::Return { value: Some(negated.clone()) }];
+    
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    // `-5` is a valid i64
+    assert_eq!(contract.functions[0].body[0], Statement::Return { value: Some(negated) });
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+}
+
+#[test]
+fn test_optimizer_folds_bool_and_string_comparisons() {
+    let input = r#"
+        contract FoldTest {
+            public fn test(x: u64) -> u64 {
+                if ("admin" == "admin") {
+                    return x;
+                } else {
+                    return 0;
+                }
+                let a = true == false;
+                let b = "a" != "b";
+                let c = true && false;
+                return x;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let config = OptimizerConfig::builder()
+        .level(OptimizationLevel::O1)
+        .enable_simplification(false)
+        .build();
+    let report = Optimizer::with_config(config).optimize(&mut contract);
+    
+    let body = &contract.functions[0].body;
+    assert!(!body.iter().any(|s| matches!(s, Statement::If { .. })));
+    assert!(!body.contains(&Statement::Return { value: Some(Expression::Number(0)) }));
+    assert!(body.contains(&Statement::Let {
+        name: "a".to_string(), ty: None, value: Expression::Bool(false), is_mutable: false,
+    }));
+    assert!(body.contains(&Statement::Let {
+        name: "b".to_string(), ty: None, value: Expression::Bool(true), is_mutable: false,
+    }));
+    assert!(body.contains(&Statement::Let {
+        name: "c".to_string(), ty: None, value: Expression::Bool(false), is_mutable: false,
+    }));
+    assert!(report.constants_folded >= 4);
+}
+
+#[test]
+fn test_optimizer_folds_pow_shift_and_bitwise() {
+    let input = r#"
+        contract FoldTest {
+            public fn test(x: u64) -> u64 {
+                return x;
+            }
+        }
+    "#;
+    
+    let binary = |op, left, right| Expression::Binary {
+        op,
+        left: Box::new(Expression::Number(left)),
+        right: Box::new(Expression::Number(right)),
+    };
+    let cases = vec![
+        (binary(BinaryOp::Pow, 10, 18), Some(1_000_000_000_000_000_000)),
+        (binary(BinaryOp::Shl, 1, 63), Some(1 << 63)),
+        (binary(BinaryOp::Shr, 256, 4), Some(16)),
+        (binary(BinaryOp::BitAnd, 0b1100, 0b1010), Some(0b1000)),
+        (binary(BinaryOp::BitOr, 0b1100, 0b1010), Some(0b1110)),
+        (binary(BinaryOp::BitXor, 0b1100, 0b1010), Some(0b0110)),
+        (binary(BinaryOp::Pow, 10, 20), None),
+        (binary(BinaryOp::Shl, 3, 63), None),
+        (binary(BinaryOp::Shr, 1, 64), None),
+    ];
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    contract.functions[0].body = cases.iter()
+        .enumerate()
+        .map(|(i, (expr, _))| Statement::Let {
+            name: format!("v{}", i), ty: None, value: expr.clone(), is_mutable: false,
+        })
+        .collect();
+    
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    assert_eq!(contract.functions[0].body.len(), cases.len());
+    for ((original, expected), stmt) in cases.iter().zip(&contract.functions[0].body) {
+        let expected = match expected {
+            Some(n) => Expression::Number(*n),
+            None => original.clone(),
+        };
+        assert!(matches!(stmt, Statement::Let { value, .. } if *value == expected));
+    }
+    assert_eq!(report.warnings.len(), 3);
+    assert!(report.warnings[0].contains("10 ** 20"));
+    assert!(report.warnings[1].contains("3 << 63"));
+    assert!(report.warnings[2].contains("1 >> 64"));
+}
+
+#[test]
+fn test_optimizer_keeps_side_effects_of_discarded_operands() {
+    let input = r#"
+        contract EffectTest {
+            state {
+                checks: u64;
+            }
+            
+            fn check_limits(x: u64) -> bool {
+                checks = checks + 1;
+                return x < 100;
+            }
+            
+            public fn test(x: u64) -> bool {
+                check_limits(x) && false;
+                let ok = check_limits(x) && false;
+                let zero = check_limits(x) * 0;
+                let skipped = false && check_limits(x);
+                return x > 0 && false;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    Optimizer::new().optimize(&mut contract);
+    
+    let call = Expression::Call {
+        func: Box::new(Expression::Identifier("check_limits".to_string())),
+        args: vec![Expression::Identifier("x".to_string())],
+    };
+    let func = contract.functions.iter().find(|f| f.name == "test").unwrap();
+    let body = &func.body;
+    
+    // As a statement only the call is left; as a value the call must stay
+    assert_eq!(body[0], Statement::Expression(call.clone()));
+    assert!(matches!(&body[1], Statement::Let { value: Expression::Binary { left, .. }, .. } if **left == call));
+    assert!(matches!(&body[2], Statement::Let { value: Expression::Binary { left, .. }, .. } if **left == call));
+    // Short-circuiting never runs the right operand, and pure operands can go
+    assert!(matches!(&body[3], Statement::Let { value: Expression::Bool(false), .. }));
+    assert_eq!(body[4], Statement::Return { value: Some(Expression::Bool(false)) });
+}
+
+#[test]
+fn test_optimizer_inlines_contract_constants() {
+    let input = r#"
+        contract ConstTest {
+            const FEE_BPS: u64 = 30;
+            const SCALE: u64 = 100 * 100;
+            
+            state {
+                owner: address = msg_sender();
+                max_fee: u64 = FEE_BPS * 2 + 1;
+            }
+            
+            public fn fee(amount: u64) -> u64 {
+                return amount * FEE_BPS / SCALE;
+            }
+            
+            public fn shadowed(FEE_BPS: u64) -> u64 {
+                return FEE_BPS;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let config = OptimizerConfig::builder()
+        .level(OptimizationLevel::O1)
+        .enable_strength_reduction(false)
+        .build();
+    Optimizer::with_config(config).optimize(&mut contract);
+    
+    assert_eq!(contract.constants[1].value, Expression::Number(10000));
+    // State initializers fold with the constants; the deployer is only known at runtime
+    assert_eq!(contract.state[0].initial_value, Some(Expression::MsgSender));
+    assert_eq!(contract.state[1].initial_value, Some(Expression::Number(61)));
+    
+    let scaled = Expression::Binary {
+        op: BinaryOp::Div,
+        left: Box::new(Expression::Binary {
+            op: BinaryOp::Mul,
+            left: Box::new(Expression::Identifier("amount".to_string())),
+            right: Box::new(Expression::Number(30)),
+        }),
+        right: Box::new(Expression::Number(10000)),
+    };
+    assert_eq!(contract.functions[0].body[0], Statement::Return { value: Some(scaled) });
+    assert_eq!(contract.functions[1].body[0], Statement::Return {
+        value: Some(Expression::Identifier("FEE_BPS".to_string())),
+    });
+}
+
+#[test]
+fn test_optimizer_removes_repeated_requires() {
+    let input = r#"
+        contract Vault {
+            state {
+                owner: address;
+                balance: u64;
+                paused: bool;
+            }
+            
+            public fn withdraw(amount: u64) {
+                require(msg_sender() == owner, "Only owner");
+                require(msg_sender() == owner, "Only owner");
+                balance = balance - amount;
+                require(msg_sender() == owner, "Only owner");
+                owner = msg_sender();
+                require(msg_sender() == owner, "Only owner");
+            }
+            
+            public fn unpause(flag: bool) {
+                require(paused);
+                require(flag);
+                paused = false;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let report = Optimizer::new().optimize(&mut contract);
+    
+    let owner_check = Statement::Require {
+        condition: Expression::Binary {
+            op: BinaryOp::Eq,
+            left: Box::new(Expression::MsgSender),
+            right: Box::new(Expression::Identifier("owner".to_string())),
+        },
+        message: Some("Only owner".to_string()),
+    };
+    
+    // The three checks before `owner` is written collapse into one; the one after stays
+    let withdraw = &contract.functions[0].body;
+    assert_eq!(withdraw.len(), 4);
+    assert_eq!(withdraw[0], owner_check);
+    assert!(matches!(withdraw[1], Statement::Assign { .. }));
+    assert!(matches!(withdraw[2], Statement::Assign { .. }));
+    assert_eq!(withdraw[3], owner_check);
+    
+    // Adjacent message-less requires merge into one
+    let unpause = &contract.functions[1].body;
+    assert_eq!(unpause.len(), 2);
+    assert_eq!(unpause[0], Statement::Require {
+        condition: Expression::Binary {
+            op: BinaryOp::And,
+            left: Box::new(Expression::Identifier("paused".to_string())),
+            right: Box::new(Expression::Identifier("flag".to_string())),
+        },
+        message: None,
+    });
+    
+    assert_eq!(report.requires_removed, 3);
+    assert_eq!(report.passes[0].requires_removed, 3);
+}
+
+#[test]
+fn test_optimizer_forgets_state_values_across_calls() {
+    let input = r#"
+        contract Counter {
+            state {
+                x: u64;
+                total: u64;
+            }
+            
+            fn bump() {
+                x = 10;
+            }
+            
+            public fn run() -> u64 {
+                x = 5;
+                let local = 3;
+                bump();
+                return x + local;
+            }
+            
+            public fn sync() -> u64 {
+                require(total > 0, "Empty");
+                total = 5;
+                Oracle.refresh();
+                require(total > 0, "Empty");
+                return total;
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2)).optimize(&mut contract);
+    
+    // bump() writes x, so only the local is still known after the call
+    let run = &contract.functions.iter().find(|f| f.name == "run").unwrap().body;
+    assert_eq!(run.last(), Some(&Statement::Return {
+        value: Some(Expression::Binary {
+            op: BinaryOp::Add,
+            left: Box::new(Expression::Identifier("x".to_string())),
+            right: Box::new(Expression::Number(3)),
+        }),
+    }));
+    
+    // Neither is anything known past a call of another contract
+    let sync = &contract.functions.iter().find(|f| f.name == "sync").unwrap().body;
+    assert_eq!(sync.iter().filter(|s| matches!(s, Statement::Require { .. })).count(), 2);
+    assert_eq!(sync.last(), Some(&Statement::Return { value: Some(Expression::Identifier("total".to_string())) }));
+}
+
+#[test]
+fn test_optimizer_does_not_inline_into_shadowing_caller() {
+    let input = r#"
+        contract Fees {
+            state {
+                rate: u64;
+            }
+            
+            private fn fee(x: u64) -> u64 {
+                return x * rate;
+            }
+            
+            public fn charge(a: u64) -> u64 {
+                let rate = 5;
+                return fee(a);
+            }
+            
+            public fn quote(a: u64) -> u64 {
+                return fee(a);
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let report = Optimizer::with_config(OptimizerConfig::for_level(OptimizationLevel::O2)).optimize(&mut contract);
+    
+    // The local `rate` would capture the state variable fee() reads
+    let charge = &contract.functions.iter().find(|f| f.name == "charge").unwrap().body;
+    assert!(matches!(charge.last(), Some(Statement::Return { value: Some(Expression::Call { .. }) })));
+    
+    let quote = &contract.functions.iter().find(|f| f.name == "quote").unwrap().body;
+    assert_eq!(quote.last(), Some(&Statement::Return {
+        value: Some(Expression::Binary {
+            op: BinaryOp::Mul,
+            left: Box::new(Expression::Identifier("a".to_string())),
+            right: Box::new(Expression::Identifier("rate".to_string())),
+        }),
+    }));
+    assert_eq!(report.functions_inlined, 1);
+    assert!(contract.functions.iter().any(|f| f.name == "fee"));
+}
+
+/// Every emitted event, in source order, across all functions
+fn emitted_events(contract: &Contract) -> Vec<String> {
+    fn walk(statements: &[Statement], events: &mut Vec<String>) {
+        for stmt in statements {
+            match stmt {
+                Statement::Emit { event, .. } => events.push(event.clone()),
+                Statement::If { then_block, else_block, .. } => {
+                    walk(then_block, events);
+                    walk(else_block.as_deref().unwrap_or_default(), events);
+                }
+                Statement::While { body, .. } | Statement::ForEach { body, .. }
+                | Statement::For { body, .. } | Statement::Block(body) => walk(body, events),
+                Statement::Match { arms, .. } => arms.iter().for_each(|arm| walk(&arm.body, events)),
+                _ => {}
+            }
+        }
+    }
+    
+    let mut events = Vec::new();
+    for func in &contract.functions {
+        events.push(format!("fn {}", func.name));
+        walk(&func.body, &mut events);
+    }
+    events
+}
+
+#[test]
+fn test_optimizer_preserves_emit_order_at_every_level() {
+    let samples = [
+        include_str!("../examples/token.ccdsl"),
+        r#"
+        contract Barriers {
+            state {
+                total: u64;
+                paused: bool;
+            }
+            
+            fn helper(x: u64) -> u64 {
+                return x + 1;
+            }
+            
+            public fn run(x: u64) {
+                let a = 2 * 3;
+                emit Logged(a + 1);
+                require(x > 0, "Zero");
+                emit Checked(helper(x));
+                if 1 < 2 {
+                    emit Logged(a);
+                }
+                helper(x) * 0;
+                emit Logged(a * 0);
+                require(x > 0, "Zero");
+                emit Checked(x);
+            }
+            
+            public fn guarded(x: u64) {
+                if paused {
+                    emit Logged(x);
+                }
+                total = total + x;
+                emit Checked(total);
+                for i in 0..3 {
+                    total = total + i;
+                }
+                emit Logged(total);
+            }
+        }
+        "#,
+    ];
+    
+    for source in samples {
+        let original = Contract::parse(source).expect("Failed to parse");
+        let expected = emitted_events(&original);
+        
+        for level in [OptimizationLevel::O0, OptimizationLevel::O1, OptimizationLevel::O2] {
+            let mut contract = original.clone();
+            Optimizer::with_config(OptimizerConfig::for_level(level)).optimize(&mut contract);
+            assert_eq!(emitted_events(&contract), expected, "{} at {}", original.name, level);
+        }
+    }
+}
+
+#[test]
+fn test_optimizer_folds_branches_decided_by_requires() {
+    let input = r#"
+        contract Guarded {
+            state {
+                paused: bool;
+                total: u64;
+            }
+            
+            public fn deposit(amount: u64) {
+                require(amount > 0, "Zero amount");
+                if amount == 0 {
+                    total = 0;
+                }
+                total = total + amount;
+            }
+            
+            public fn unpause(flag: bool) {
+                require(!paused, "Paused");
+                if paused {
+                    total = 0;
+                } else {
+                    total = total + 1;
+                }
+                paused = flag;
+                if paused {
+                    total = 2;
+                }
+            }
+        }
+    "#;
+    
+    let mut contract = Contract::parse(input).expect("Failed to parse");
+    let config = OptimizerConfig::builder()
+        .level(OptimizationLevel::O1)
+        .enable_strength_reduction(false)
+        .build();
+    Optimizer::with_config(config).optimize(&mut contract);
+    
+    // `amount > 0` rules out `amount == 0`
+    let deposit = &contract.functions[0].body;
+    assert_eq!(deposit.len(), 2);
+    assert!(matches!(deposit[0], Statement::Require { .. }));
+    assert!(matches!(deposit[1], Statement::Assign { .. }));
+    
+    // `!paused` decides the first `if`, but not the one after `paused` is reassigned
+    let unpause = &contract.functions[1].body;
+    assert_eq!(unpause.len(), 4);
+    assert!(matches!(unpause[0], Statement::Require { .. }));
+    assert_eq!(unpause[1], Statement::Assign {
+        target: LValue::Identifier("total".to_string()),
+        value: Expression::Binary {
+            op: BinaryOp::Add,
+            left: Box::new(Expression::Identifier("total".to_string())),
+            right: Box::new(Expression::Number(1)),
+        },
+    });
+    assert!(matches!(unpause[3], Statement::If { .. }));
+}
@@ -1,4 +1,4 @@
-use cross_chain_dsl::{Contract, Type, Visibility};
+use cross_chain_dsl::{Contract, Program, Type, Visibility, Statement, Expression, LValue, Pattern, BinaryOp, UnaryOp};
 
 #[test]
 fn test_parse_simple_contract() {
@@ -70,6 +70,25 @@ fn test_parse_complex_types() {
     }
 }
 
+#[test]
+fn test_parse_bounded_string_types() {
+    let input = r#"
+        contract Profile {
+            state {
+                name: string<32>;
+                bio: string;
+                avatar: bytes<64>;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse contract");
+    
+    assert_eq!(contract.state[0].ty, Type::String(Some(32)));
+    assert_eq!(contract.state[1].ty, Type::String(None));
+    assert_eq!(contract.state[2].ty, Type::Bytes(Some(64)));
+}
+
 #[test]
 fn test_parse_expressions() {
     let input = r#"
@@ -117,6 +136,39 @@ fn test_parse_control_flow() {
     assert_eq!(func.params.len(), 2);
 }
 
+#[test]
+fn test_parse_match_statement() {
+    let input = r#"
+        contract MatchTest {
+            public fn classify(result: u64) -> u64 {
+                match result {
+                    0 => return 1,
+                    1..10 => return 2,
+                    10..=20 => {
+                        return 3;
+                    }
+                    _ => return result
+                }
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse contract");
+    
+    match &contract.functions[0].body[0] {
+        Statement::Match { value, arms } => {
+            assert_eq!(value, &Expression::Identifier("result".to_string()));
+            assert_eq!(arms.len(), 4);
+            assert_eq!(arms[0].pattern, Pattern::Literal(Expression::Number(0)));
+            assert_eq!(arms[1].pattern, Pattern::Range { start: 1, end: 10, inclusive: false });
+            assert_eq!(arms[2].pattern, Pattern::Range { start: 10, end: 20, inclusive: true });
+            assert_eq!(arms[3].pattern, Pattern::Wildcard);
+            assert!(matches!(arms[2].body[0], Statement::Return { .. }));
+        }
+        other => panic!("Expected match statement, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_parse_error_invalid_syntax() {
     let input = r#"
@@ -131,6 +183,115 @@ fn test_parse_error_invalid_syntax() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_parse_empty_lists_and_trailing_commas() {
+    let input = r#"
+        contract Commas {
+            event Moved(from: address, amount: u64,);
+            event Ping();
+            
+            modifier capped(limit: u64,) {
+                _;
+            }
+            
+            public fn ping() {
+                emit Ping();
+            }
+            
+            public fn move_to(to: address, amount: u64,) capped {
+                emit Moved(to, amount,);
+                let fee = quote(amount,);
+            }
+            
+            fn quote(amount: u64,) -> u64 {
+                return amount;
+            }
+            
+            view fn total() -> u64 {
+                return 1;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    assert_eq!(contract.events[0].params.len(), 2);
+    assert!(contract.events[1].params.is_empty());
+    assert_eq!(contract.modifiers[0].params.len(), 1);
+    assert!(contract.functions[0].params.is_empty());
+    // The return type follows an empty parameter list
+    assert!(matches!(contract.functions[3].return_type, Some(Type::U64)));
+    let params: Vec<&str> = contract.functions[1].params.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(params, vec!["to", "amount"]);
+    match &contract.functions[1].body[..] {
+        [Statement::Emit { event, args }, Statement::Let { value: Expression::Call { args: call_args, .. }, .. }] => {
+            assert_eq!(event, "Moved");
+            assert_eq!(args.len(), 2);
+            assert_eq!(call_args.len(), 1);
+        }
+        other => panic!("Unexpected body {:?}", other),
+    }
+    assert!(matches!(contract.functions[2].return_type, Some(Type::U64)));
+}
+
+#[test]
+fn test_parse_error_names_a_stray_comma() {
+    let error = |body: &str| Contract::parse(&format!("contract C {{\n    event E(a: u64);\n    {}\n}}", body))
+        .expect_err("Expected a syntax error")
+        .to_string();
+    
+    let message = error("public fn f(,) {}");
+    assert!(message.contains("3:17"), "{}", message);
+    assert!(message.contains("= unexpected `,`; expected a name"), "{}", message);
+    let message = error("public fn f(a: u64,,) {}");
+    assert!(message.contains("3:24"), "{}", message);
+    assert!(message.contains("= unexpected `,`; expected a name"), "{}", message);
+    let message = error("public fn f(x: u64) { emit E(,); }");
+    assert!(message.contains("= unexpected `,`; expected an expression"), "{}", message);
+    let message = error("public fn f(x: u64) { emit E(x,,); }");
+    assert!(message.contains("= unexpected `,`; expected an expression"), "{}", message);
+    
+    // Other errors say what was expected in words rather than with grammar rule names
+    let message = error("public fn f(a u64) {}");
+    assert!(message.contains("= expected a parameter `name: type`"), "{}", message);
+    let message = error("public fn f(x: u64) { let y = x + ; }");
+    assert!(message.contains("= expected an expression"), "{}", message);
+    
+    // Recovery reports the same message
+    let (_, diagnostics) = Contract::parse_recovering("contract C { event E(a: u64,,); }");
+    assert_eq!(diagnostics[0].message, "unexpected `,`; expected an event parameter `name: type`");
+}
+
+#[test]
+fn test_parse_constructor_attribute() {
+    let input = r#"
+        contract Vault {
+            state {
+                limit: u64;
+            }
+            
+            #[constructor]
+            public fn setup(initial_limit: u64) {
+                limit = initial_limit;
+            }
+            
+            public fn initialize() {}
+        }
+    "#;
+    let contract = Contract::parse(input).expect("Failed to parse");
+    assert!(contract.functions[0].is_constructor);
+    assert!(!contract.functions[1].is_constructor);
+    // The marked function is the constructor, even next to one named initialize
+    assert_eq!(contract.constructor().map(|f| f.name.as_str()), Some("setup"));
+    
+    let unmarked = Contract::parse("contract C { public fn initialize() {} public fn run() {} }").unwrap();
+    assert_eq!(unmarked.constructor().map(|f| f.name.as_str()), Some("initialize"));
+    assert!(Contract::parse("contract C { public fn run() {} }").unwrap().constructor().is_none());
+    
+    let printed = assert_round_trip(input);
+    assert!(printed.contains("    #[constructor]\n    public fn setup(initial_limit: u64) {"), "{}", printed);
+    assert!(Contract::parse("contract C { public #[constructor] fn setup() {} }").is_err());
+}
+
 #[test]
 fn test_parse_full_token_contract() {
     let input = std::fs::read_to_string("examples/token.ccdsl")
@@ -151,4 +312,762 @@ fn test_parse_full_token_contract() {
     assert!(function_names.contains(&"transfer"));
     assert!(function_names.contains(&"mint"));
     assert!(function_names.contains(&"burn"));
-}
\ No newline at end of file
+}
+#[test]
+fn test_parse_multiple_contracts() {
+    let input = r#"
+        contract Oracle {
+            public fn price(asset: address) -> u64 {
+                return 100;
+            }
+        }
+        
+        contract Vault {
+            public fn value(asset: address) -> u64 {
+                return Oracle.price(asset);
+            }
+        }
+    "#;
+    
+    let program = Program::parse(input).expect("Failed to parse");
+    
+    let names: Vec<&str> = program.contracts.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Oracle", "Vault"]);
+    assert_eq!(program.contracts[1].functions[0].body[0], Statement::Return {
+        value: Some(Expression::MethodCall {
+            object: Box::new(Expression::Identifier("Oracle".to_string())),
+            method: "price".to_string(),
+            args: vec![Expression::Identifier("asset".to_string())],
+        }),
+    });
+    
+    // A single-contract parse must not drop the rest of the file
+    let err = Contract::parse(input).unwrap_err();
+    assert!(err.to_string().contains("found 2 (Oracle, Vault)"));
+    
+    let duplicate = "contract A { } contract A { }";
+    assert!(Program::parse(duplicate).unwrap_err().to_string().contains("Duplicate contract 'A'"));
+}
+
+#[test]
+fn test_parse_external_contracts() {
+    let input = r#"
+        external contract Oracle at Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS {
+            fn get_price(pair: string) -> u64;
+            fn ping();
+        }
+        
+        external contract Registry at "0x42" { }
+        
+        contract Vault {
+            public fn value(pair: string) -> u64 {
+                return Oracle.get_price(pair);
+            }
+        }
+    "#;
+    
+    let program = Program::parse(input).expect("Failed to parse");
+    
+    assert_eq!(program.contracts.len(), 1);
+    assert_eq!(program.externals.len(), 2);
+    let oracle = &program.externals[0];
+    assert_eq!(oracle.name, "Oracle");
+    assert_eq!(oracle.address, "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+    assert_eq!(oracle.functions[0].name, "get_price");
+    assert_eq!(oracle.functions[0].params[0].ty, Type::String(None));
+    assert_eq!(oracle.functions[0].return_type, Some(Type::U64));
+    assert_eq!(oracle.functions[1].return_type, None);
+    assert_eq!(program.externals[1].address, "0x42");
+    
+    assert!(Contract::parse(input).unwrap_err().to_string().contains("External contracts"));
+    let duplicate = "external contract A at \"0x1\" { } contract A { }";
+    assert!(Program::parse(duplicate).unwrap_err().to_string().contains("Duplicate contract 'A'"));
+}
+
+#[test]
+fn test_parse_recovering_reports_every_error() {
+    let input = r#"
+        contract Broken {
+            state {
+                total: u64;
+            }
+            
+            public fn a(x: u64) {
+                let y = ;
+                total = x;
+            }
+            
+            public fn b(x: u64 {
+                total = x;
+            }
+            
+            public fn c() {
+                total = total + ;
+                }
+            }
+        }
+    "#;
+    
+    let (contract, diagnostics) = Contract::parse_recovering(input);
+    assert!(contract.is_none());
+    let lines: Vec<usize> = diagnostics.iter().map(|d| d.line).collect();
+    // The extra `}` in `c` closes the contract early, leaving the last one stray
+    assert_eq!(lines, vec![8, 12, 17, 20], "{:#?}", diagnostics);
+    assert_eq!(&input[diagnostics[0].span.clone()].trim(), &"let y = ;");
+    assert!(input[diagnostics[1].span.clone()].contains("public fn b(x: u64 {"));
+    
+    // A file without errors parses as usual
+    let valid = input.replace("let y = ;", "").replace("x: u64 {", "x: u64) {").replace("total + ;\n                }", "total + 1;");
+    let (contract, diagnostics) = Contract::parse_recovering(&valid);
+    assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+    assert_eq!(contract.expect("Failed to parse").functions.len(), 3);
+    
+    // A missing closing brace can't be skipped; parsing stops there
+    let (contract, diagnostics) = Contract::parse_recovering("contract A { fn f() { let x = 1; }");
+    assert!(contract.is_none());
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].span.is_empty());
+}
+
+#[test]
+fn test_parse_recovering_never_panics() {
+    let input = r#"
+        contract Token {
+            state {
+                balances: map<address, u64>; // 余额
+            }
+            
+            public fn transfer(to: address, amount: u64) {
+                require(balances[msg_sender()] >= amount, "Insufficient balance");
+                balances[to] = balances[to] + amount;
+            }
+        }
+    "#;
+    
+    // Every prefix, and the input with each character removed
+    for (i, _) in input.char_indices() {
+        Contract::parse_recovering(&input[..i]);
+        let mut removed = input.to_string();
+        removed.remove(i);
+        Contract::parse_recovering(&removed);
+    }
+}
+
+#[test]
+fn test_parse_view_functions() {
+    let input = r#"
+        contract Bank {
+            view fn total() -> u64 {
+                return 0;
+            }
+            
+            private view fn helper() -> u64 {
+                return 0;
+            }
+            
+            public fn deposit() { }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    assert!(contract.functions[0].is_view);
+    assert!(matches!(contract.functions[0].visibility, Visibility::Public));
+    assert!(contract.functions[1].is_view);
+    assert!(matches!(contract.functions[1].visibility, Visibility::Private));
+    assert!(!contract.functions[2].is_view);
+}
+
+#[test]
+fn test_parse_payable_functions() {
+    let input = r#"
+        contract Vault {
+            payable fn deposit() { }
+            
+            public payable fn top_up() { }
+            
+            public fn payable_amount() { }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    assert!(contract.functions[0].is_payable);
+    assert!(matches!(contract.functions[0].visibility, Visibility::Public));
+    assert!(contract.functions[1].is_payable);
+    // `payable` is only a keyword on its own
+    assert!(!contract.functions[2].is_payable);
+    assert!(Contract::parse("contract C { view payable fn f() -> u64 { return 0; } }").is_err());
+}
+
+#[test]
+fn test_parse_constants() {
+    let input = r#"
+        contract Fees {
+            const FEE_BPS: u64 = 30;
+            const MAX_FEE: u64 = FEE_BPS * 10;
+            
+            state {
+                collected: u64;
+            }
+            
+            const NAME: string = "fees";
+            
+            public fn fee(amount: u64) -> u64 {
+                return amount * FEE_BPS / 10000;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let names: Vec<&str> = contract.constants.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["FEE_BPS", "MAX_FEE", "NAME"]);
+    assert_eq!(contract.constants[0].ty, Type::U64);
+    assert_eq!(contract.constants[0].value, Expression::Number(30));
+    assert!(matches!(contract.constants[1].value, Expression::Binary { .. }));
+    assert_eq!(contract.constants[2].ty, Type::String(None));
+    assert_eq!(contract.state.len(), 1);
+    assert_eq!(contract.functions.len(), 1);
+}
+
+#[test]
+fn test_parse_structs() {
+    let input = r#"
+        contract Dex {
+            state {
+                pools: map<address, Pool>;
+            }
+            
+            struct Pool {
+                token_a: address;
+                reserve_a: u64;
+            }
+            
+            public fn lock(pool: address) {
+                pools[pool].reserve_a = 0;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    assert_eq!(contract.structs.len(), 1);
+    let pool = &contract.structs[0];
+    assert_eq!(pool.name, "Pool");
+    let fields: Vec<_> = pool.fields.iter().map(|f| (f.name.as_str(), f.ty.clone())).collect();
+    assert_eq!(fields, vec![("token_a", Type::Address), ("reserve_a", Type::U64)]);
+    assert_eq!(contract.state[0].ty, Type::Map(Box::new(Type::Address), Box::new(Type::Struct("Pool".to_string()))));
+    
+    // Fields of map values can be assigned directly
+    match &contract.functions[0].body[0] {
+        Statement::Assign { target: LValue::Field { object, field }, .. } => {
+            assert_eq!(field, "reserve_a");
+            assert!(matches!(object.as_ref(), LValue::Index { .. }));
+        }
+        other => panic!("Expected a field assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_events() {
+    let input = r#"
+        contract Events {
+            event Transfer(indexed from: address, to: address, amount: u64);
+            event Paused();
+            
+            public fn get_amount() -> u64 {
+                return 1;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    assert_eq!(contract.events.len(), 2);
+    
+    let transfer = &contract.events[0];
+    assert_eq!(transfer.name, "Transfer");
+    let params: Vec<_> = transfer.params.iter().map(|p| (p.name.as_str(), p.indexed)).collect();
+    assert_eq!(params, vec![("from", true), ("to", false), ("amount", false)]);
+    assert!(matches!(transfer.params[2].ty, Type::U64));
+    assert!(contract.events[1].params.is_empty());
+}
+
+#[test]
+fn test_parse_modifiers() {
+    let input = r#"
+        contract Secure {
+            state {
+                owner: address;
+                paused: bool;
+            }
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Only owner");
+                _;
+            }
+            
+            public fn withdraw(amount: u64) onlyOwner whenNotPaused -> u64 {
+                return amount;
+            }
+            
+            modifier whenNotPaused() {
+                require(!paused, "Paused");
+                _;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let names: Vec<&str> = contract.modifiers.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["onlyOwner", "whenNotPaused"]);
+    assert_eq!(contract.modifiers[0].body[1], Statement::Placeholder);
+    
+    let func = &contract.functions[0];
+    let applied: Vec<&str> = func.modifiers.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(applied, vec!["onlyOwner", "whenNotPaused"]);
+    assert!(func.modifiers.iter().all(|m| m.args.is_empty()));
+    assert_eq!(func.return_type, Some(Type::U64));
+}
+
+#[test]
+fn test_parse_modifier_arguments() {
+    let input = r#"
+        contract Capped {
+            modifier below(limit: u64) {
+                require(limit > 0, "No limit");
+                _;
+            }
+            
+            public fn deposit(amount: u64) below(amount * 2) below() {
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let modifiers = &contract.functions[0].modifiers;
+    assert_eq!(modifiers[0].name, "below");
+    assert!(matches!(
+        modifiers[0].args.as_slice(),
+        [Expression::Binary { op: BinaryOp::Mul, left, .. }] if **left == Expression::Identifier("amount".to_string())
+    ));
+    assert!(modifiers[1].args.is_empty());
+    assert!(contract.to_source().contains("fn deposit(amount: u64) below(amount * 2) below {"), "{}", contract.to_source());
+}
+
+#[test]
+fn test_parse_integer_suffixes() {
+    let input = r#"
+        contract Amm {
+            const MAX_RESERVE: u128 = 340_000_000_000_000_000_000u128;
+            
+            public fn scale(amount: u64) -> u64 {
+                let fee = 3u8;
+                let big = 1_000_000u128;
+                let plain = 1_000;
+                return amount;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    assert_eq!(contract.constants[0].value, Expression::TypedNumber {
+        value: 340_000_000_000_000_000_000,
+        ty: Type::U128,
+    });
+    
+    let values: Vec<&Expression> = contract.functions[0].body.iter()
+        .filter_map(|s| match s {
+            Statement::Let { value, .. } => Some(value),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(values, vec![
+        &Expression::TypedNumber { value: 3, ty: Type::U8 },
+        &Expression::TypedNumber { value: 1_000_000, ty: Type::U128 },
+        &Expression::Number(1000),
+    ]);
+    
+    let too_big = "contract A { public fn f(x: u64) { let y = 256u8; } }";
+    assert!(Contract::parse(too_big).is_err());
+    let too_big = "contract A { public fn f(x: u64) { let y = 18446744073709551616; } }";
+    assert!(Contract::parse(too_big).is_err());
+}
+
+#[test]
+fn test_parse_tuple_returns() {
+    let input = r#"
+        contract Amm {
+            public fn quote(amount: u64) -> (u64, u128) {
+                return (amount, 0u128);
+            }
+            
+            public fn swap(amount: u64) {
+                let (out, fee) = quote(amount);
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    assert_eq!(contract.functions[0].return_type, Some(Type::Tuple(vec![Type::U64, Type::U128])));
+    match &contract.functions[1].body[0] {
+        Statement::LetTuple { names, value: Expression::Call { .. } } => assert_eq!(names, &["out", "fee"]),
+        other => panic!("Expected a destructuring let, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_casts() {
+    let input = r#"
+        contract Amm {
+            public fn quote(reserve: u64, amount: u128) -> u64 {
+                return -reserve as u128 * amount as u64 as u128;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    let cast = |expr: Expression, ty: Type| Expression::Cast { expr: Box::new(expr), ty };
+    let id = |name: &str| Expression::Identifier(name.to_string());
+    // Casts bind tighter than `*` and looser than unary operators
+    let expected = Expression::Binary {
+        op: BinaryOp::Mul,
+        left: Box::new(cast(Expression::Unary { op: UnaryOp::Neg, expr: Box::new(id("reserve")) }, Type::U128)),
+        right: Box::new(cast(cast(id("amount"), Type::U64), Type::U128)),
+    };
+    assert_eq!(contract.functions[0].body[0], Statement::Return { value: Some(expected) });
+}
+
+#[test]
+fn test_parse_pow_precedence() {
+    let input = r#"
+        contract Math {
+            public fn f(x: u64) -> u64 {
+                return 2 * x ** 3 ** 2;
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    
+    // `**` binds tighter than `*` and groups to the right
+    let power = Expression::Binary {
+        op: BinaryOp::Pow,
+        left: Box::new(Expression::Identifier("x".to_string())),
+        right: Box::new(Expression::Binary {
+            op: BinaryOp::Pow,
+            left: Box::new(Expression::Number(3)),
+            right: Box::new(Expression::Number(2)),
+        }),
+    };
+    assert_eq!(contract.functions[0].body[0], Statement::Return {
+        value: Some(Expression::Binary {
+            op: BinaryOp::Mul,
+            left: Box::new(Expression::Number(2)),
+            right: Box::new(power),
+        }),
+    });
+}
+
+#[test]
+fn test_program_json_round_trip() {
+    let input = r#"
+        import { fee } from "./fees.ccdsl";
+        
+        contract Vault {
+            state {
+                balances: map<address, u64>;
+            }
+            
+            event Deposit(indexed who: address, amount: u64);
+            
+            public fn deposit(amount: u64) {
+                balances[msg_sender()] = balances[msg_sender()] + amount;
+                emit Deposit(msg_sender(), amount);
+            }
+        }
+    "#;
+    
+    let program = Program::parse(input).expect("Failed to parse");
+    let json = serde_json::to_string(&program).unwrap();
+    assert_eq!(Program::from_json(&json).unwrap(), program);
+    
+    let err = Program::from_json(r#"{"contracts": []}"#).unwrap_err();
+    assert!(err.to_string().contains("Invalid program JSON"), "{}", err);
+}
+
+#[test]
+fn test_parse_bridge_block() {
+    let input = r#"
+        contract Bridged {
+            bridge {
+                vault;
+                relayer: address;
+            }
+            
+            public fn unlock(to: address, amount: u64, source_chain: u64) {
+                bridge_receive(to, amount, source_chain);
+            }
+        }
+    "#;
+    
+    let contract = Contract::parse(input).expect("Failed to parse");
+    assert_eq!(contract.bridge.unwrap().authority, "relayer");
+    assert_eq!(Contract::parse("contract Plain { }").unwrap().bridge, None);
+    
+    let error = |body: &str| Contract::parse(&format!("contract C {{ bridge {{ {} }} }}", body)).unwrap_err().to_string();
+    assert!(error("authority: address;").contains("needs a `vault;`"));
+    assert!(error("vault;").contains("needs an authority"));
+    assert!(error("vault; authority: u64;").contains("must be an address"));
+    assert!(error("vault; vault; authority: address;").contains("more than once"));
+    assert!(error("vault; a: address; b: address;").contains("more than one authority"));
+    assert!(error("vault; authority: address = msg_sender();").contains("set by init_bridge"));
+}
+
+#[test]
+fn test_parse_bitwise_operators() {
+    let contract = Contract::parse(r#"
+        contract Flags {
+            public fn test(flags: u64, mask: u64) -> bool {
+                return flags & mask << 2 | 1 ^ 3 == 0;
+            }
+        }
+    "#).expect("Failed to parse");
+    let Statement::Return { value: Some(value) } = &contract.functions[0].body[0] else { panic!("expected a return") };
+    
+    // ((flags & (mask << 2)) | (1 ^ 3)) == 0: shifts bind tightest, then &, ^, |, and comparisons last
+    let binary = |expr: &Expression| match expr {
+        Expression::Binary { op, left, right } => (op.clone(), (**left).clone(), (**right).clone()),
+        other => panic!("expected a binary expression, got {:?}", other),
+    };
+    let (op, or, _) = binary(value);
+    assert_eq!(op, BinaryOp::Eq);
+    let (op, and, xor) = binary(&or);
+    assert_eq!(op, BinaryOp::BitOr);
+    assert_eq!(binary(&xor).0, BinaryOp::BitXor);
+    let (op, _, shift) = binary(&and);
+    assert_eq!(op, BinaryOp::BitAnd);
+    assert_eq!(binary(&shift).0, BinaryOp::Shl);
+    
+    // `||` and `&&` are still the logical operators
+    let contract = Contract::parse("contract C { public fn t(a: bool, b: bool) -> bool { return a || b && a; } }").unwrap();
+    let Statement::Return { value: Some(value) } = &contract.functions[0].body[0] else { panic!("expected a return") };
+    assert_eq!(binary(value).0, BinaryOp::Or);
+}
+
+#[test]
+fn test_parse_options() {
+    let contract = Contract::parse(r#"
+        contract Registry {
+            state {
+                owners: map<u64, address>;
+                fallback: option<u64>;
+            }
+            
+            view fn known(id: u64) -> bool {
+                fallback = Some(id);
+                fallback = None;
+                return owners.get(id).is_some();
+            }
+        }
+    "#).expect("Failed to parse");
+    assert_eq!(contract.state[1].ty, Type::Option(Box::new(Type::U64)));
+    
+    let body = &contract.functions[0].body;
+    let Statement::Assign { value, .. } = &body[0] else { panic!("expected an assignment") };
+    assert_eq!(*value, Expression::OptionSome(Box::new(Expression::Identifier("id".to_string()))));
+    let Statement::Assign { value, .. } = &body[1] else { panic!("expected an assignment") };
+    assert_eq!(*value, Expression::OptionNone);
+    
+    // Method calls chain: `owners.get(id)` is the receiver of `is_some()`
+    let Statement::Return { value: Some(Expression::MethodCall { object, method, args }) } = &body[2] else { panic!("expected a method call") };
+    assert_eq!(method, "is_some");
+    assert!(args.is_empty());
+    assert!(matches!(object.as_ref(), Expression::MethodCall { method, .. } if method == "get"));
+    
+    assert!(Contract::parse("contract C { public fn t() { let x = Some(1, 2); } }").is_err());
+    
+    let printed = assert_round_trip(&contract.to_source());
+    assert!(printed.contains("return owners.get(id).is_some();"), "{}", printed);
+}
+
+/// Parses `source`, prints it and parses the result again, checking both parses agree.
+/// Returns the printed source. Function lines are positions, not structure, so they're ignored.
+fn assert_round_trip(source: &str) -> String {
+    let without_lines = |mut contract: Contract| {
+        contract.functions.iter_mut().for_each(|f| f.line = 0);
+        contract
+    };
+    let contract = without_lines(Contract::parse(source).expect("Failed to parse"));
+    let printed = contract.to_source();
+    let reparsed = Contract::parse(&printed).unwrap_or_else(|e| panic!("Printed source doesn't parse: {}\n{}", e, printed));
+    assert_eq!(without_lines(reparsed), contract, "Round trip changed the contract:\n{}", printed);
+    printed
+}
+
+#[test]
+fn test_to_source_round_trips() {
+    let printed = assert_round_trip(r#"
+        contract Everything {
+            const MAX: u64 = 1_000u64;
+            const LIMIT: u128 = MAX as u128 * 2;
+            
+            state {
+                owner: address = msg_sender();
+                paused: bool;
+                fee: u64 = MAX * 3;
+                balances: map<address, u64>;
+                allowances: map<address, map<address, u64>>;
+                holders: vec<address>;
+                name: string<32>;
+                data: bytes;
+                pair: (u64, bool);
+                config: Config;
+            }
+            
+            bridge {
+                vault;
+                relayer: address;
+            }
+            
+            struct Config {
+                fee: u8;
+                label: string;
+            }
+            
+            event Moved(indexed from: address, amount: u64);
+            event Paused();
+            
+            modifier onlyOwner() {
+                require(msg_sender() == owner, "Not owner");
+                _;
+            }
+            
+            modifier bounded(limit: u64) {
+                _;
+                require(limit > 0);
+            }
+            
+            public fn transfer(to: address, amount: u64) onlyOwner {
+                let from = msg_sender();
+                let mut left = balances[from] - amount;
+                let (a, b) = (1, true);
+                let one = (left,);
+                balances[to] = balances[to] + amount * (2 - 1) / 3 % 4;
+                config.fee = 2u8;
+                emit Moved(from, amount);
+            }
+            
+            fn math(x: u64, y: u64) -> u64 {
+                let p = x ** y ** 2;
+                let q = (x ** y) ** 2;
+                let r = -(x + y) as u128 as u64;
+                let s = !(x > y) || x < y && x != y;
+                let t = x & y << 2 | x ^ y >> 1;
+                let u = (x | y) & 3;
+                let v = x > y ? x : y > 0 ? y : 0;
+                let w = (x > y ? x : y) + 1;
+                let z = x - (y - 1) - balances[owner];
+                let arr = [1, 2, x];
+                return block_timestamp() + block_number();
+            }
+            
+            view fn peek(who: address) -> u64 {
+                return balances[who];
+            }
+            
+            private view fn hidden() -> bool {
+                return paused;
+            }
+            
+            payable fn deposit() {
+                balances[msg_sender()] = balances[msg_sender()] + msg_value();
+            }
+            
+            public fn flow(n: u64) onlyOwner bounded {
+                if n > 10 {
+                    return;
+                } else if n > 5 {
+                    holders.push(msg_sender());
+                } else {
+                    n.check();
+                }
+                while n > 0 {
+                    if paused { break; }
+                    continue;
+                }
+                for i in 0..n { emit Paused(); }
+                for j in 1..=n + 1 { log(j, "tab\there\nnext \\ done"); }
+                match n {
+                    0 => return,
+                    1..5 => log(n),
+                    5..=9 => { log(n); log(n); }
+                    true => return n,
+                    _ => {}
+                }
+                bridge_receive(to, n, 2);
+            }
+        }
+    "#);
+    
+    // Canonical: printing the printed source again changes nothing
+    assert_eq!(Contract::parse(&printed).unwrap().to_source(), printed);
+    assert!(printed.contains("        owner: address = msg_sender();\n        paused: bool;\n        fee: u64 = MAX * 3;\n"), "{}", printed);
+    assert!(printed.contains("    public fn transfer(to: address, amount: u64) onlyOwner {\n"), "{}", printed);
+    assert!(printed.contains("let p = x ** y ** 2;"), "{}", printed);
+    assert!(printed.contains("let q = (x ** y) ** 2;"), "{}", printed);
+    assert!(printed.contains("let z = x - (y - 1) - balances[owner];"), "{}", printed);
+    assert!(printed.contains("} else if n > 5 {"), "{}", printed);
+    assert!(printed.contains("for j in 1..=n + 1 {"), "{}", printed);
+    assert!(printed.contains("    view fn peek(who: address) -> u64 {\n"), "{}", printed);
+    assert!(printed.contains("    private view fn hidden() -> bool {\n"), "{}", printed);
+}
+
+#[test]
+fn test_to_source_round_trips_examples() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let source = std::fs::read_to_string(dir.join("token.ccdsl")).unwrap();
+    let printed = assert_round_trip(&source);
+    assert!(!printed.contains("//"), "Comments are not part of the AST:\n{}", printed);
+}
+
+#[test]
+fn test_program_to_source() {
+    let input = r#"
+        import { fee, Fees } from "./fees.ccdsl";
+        
+        external contract Oracle at 0xcafe {
+            fn get_price(pair: string) -> u64;
+            fn ping();
+        }
+        
+        contract Vault {
+            view fn price() -> u64 {
+                return Oracle.get_price("SOL/USD");
+            }
+        }
+    "#;
+    let program = Program::parse(input).expect("Failed to parse");
+    let printed = program.to_source();
+    assert_eq!(printed, "\
+import { fee, Fees } from \"./fees.ccdsl\";
+
+external contract Oracle at 0xcafe {
+    fn get_price(pair: string) -> u64;
+    fn ping();
+}
+
+contract Vault {
+    view fn price() -> u64 {
+        return Oracle.get_price(\"SOL/USD\");
+    }
+}
+");
+    let mut reparsed = Program::parse(&printed).unwrap();
+    reparsed.contracts[0].functions[0].line = program.contracts[0].functions[0].line;
+    assert_eq!(reparsed, program);
+}
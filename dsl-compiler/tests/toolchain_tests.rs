@@ -0,0 +1,115 @@
+//! Builds the generated code with the real toolchains: `anchor build` (or
+//! `cargo check` without the Anchor CLI) for Solana and `aptos move compile` for Move,
+//! along with the generated Move unit tests.
+//!
+//! These need the toolchains installed and network access for their dependencies,
+//! so they only run with `CCDSL_TOOLCHAIN_TESTS=1`; a missing toolchain skips its target.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Examples the compiler doesn't accept yet, with the reason
+const UNSUPPORTED: &[(&str, &str)] = &[
+    ("amm_dex.ccdsl", "struct literals"),
+    ("lending_protocol.ccdsl", "struct literals"),
+];
+
+/// Whether `program` can be run
+fn installed(program: &str) -> bool {
+    Command::new(program).arg("--version").output().is_ok_and(|o| o.status.success())
+}
+
+/// Runs `program` in `dir`, failing the test with its output if it exits unsuccessfully
+fn run(dir: &Path, program: &str, args: &[&str]) {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to run {}: {}", program, e));
+    assert!(output.status.success(), "`{} {}` failed in {}:\n{}\n{}",
+        program, args.join(" "), dir.display(),
+        String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+}
+
+fn examples() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut examples: Vec<PathBuf> = fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ccdsl"))
+        .collect();
+    examples.sort();
+    examples
+}
+
+/// Compiles every supported example for `target` into a fresh directory, with the
+/// extra compiler `args`, returning each example's name with its output directory
+fn compile_examples(target: &str, args: &[&str]) -> Vec<(String, PathBuf)> {
+    let mut outputs = Vec::new();
+    for example in examples() {
+        let file = example.file_name().unwrap().to_str().unwrap().to_string();
+        if let Some((_, reason)) = UNSUPPORTED.iter().find(|(name, _)| *name == file) {
+            eprintln!("skipping {}: {} aren't supported yet", file, reason);
+            continue;
+        }
+        let out = std::env::temp_dir().join(format!("ccdsl-toolchain-{}-{}-{}", target, file, std::process::id()));
+        let _ = fs::remove_dir_all(&out);
+        let result = Command::new(env!("CARGO_BIN_EXE_cross-chain-dsl"))
+            .args(["compile", "-i", example.to_str().unwrap(), "-t", target, "-o", out.to_str().unwrap()])
+            .args(args)
+            .output()
+            .expect("Failed to run the compiler");
+        assert!(result.status.success(), "Compiling {} for {} failed:\n{}", file, target, String::from_utf8_lossy(&result.stderr));
+        outputs.push((file, out));
+    }
+    outputs
+}
+
+/// Whether the toolchain tests were asked for, noting the skip otherwise
+fn enabled(test: &str) -> bool {
+    let enabled = std::env::var_os("CCDSL_TOOLCHAIN_TESTS").is_some();
+    if !enabled {
+        eprintln!("skipping {}: set CCDSL_TOOLCHAIN_TESTS=1 to build the generated code", test);
+    }
+    enabled
+}
+
+#[test]
+fn test_generated_solana_programs_build() {
+    if !enabled("the Solana build") {
+        return;
+    }
+    let anchor = installed("anchor");
+    if !anchor && !installed("cargo") {
+        eprintln!("skipping the Solana build: neither anchor nor cargo is installed");
+        return;
+    }
+    for (_, out) in compile_examples("solana", &[]) {
+        // The output is an Anchor workspace; cargo check covers the Rust without the BPF toolchain
+        let workspace = out.join("solana");
+        if anchor {
+            run(&workspace, "anchor", &["build"]);
+        } else {
+            run(&workspace, "cargo", &["check", "--quiet"]);
+        }
+    }
+}
+
+#[test]
+fn test_generated_move_modules_compile() {
+    if !enabled("the Move build") {
+        return;
+    }
+    if !installed("aptos") {
+        eprintln!("skipping the Move build: the aptos CLI is not installed");
+        return;
+    }
+    for (_, out) in compile_examples("aptos", &["--with-tests"]) {
+        // The output is a Move package; its named address is left for publishing to fill in
+        let package = out.join("aptos");
+        run(&package, "aptos", &["move", "compile", "--named-addresses", "cross_chain=0xcafe"]);
+        // The tests build against the dev address; whether they pass depends on the default
+        // arguments they call with, so the filter only builds them
+        run(&package, "aptos", &["move", "test", "--filter", "no_such_test"]);
+    }
+}
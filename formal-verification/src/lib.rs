@@ -9,3 +9,5 @@ pub use invariants::InvariantExtractor;
 pub use symbolic_execution::SymbolicExecutor;
 pub use property_checker::PropertyChecker;
 pub use proof_generator::ProofGenerator;
+// The verifier borrows a Z3 context its caller creates
+pub use z3::{Config, Context};
@@ -1,6 +1,7 @@
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
+use crate::verifier::{Function, Statement};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolicValue {
@@ -24,6 +25,42 @@ pub struct StateChange {
     pub new_value: SymbolicValue,
 }
 
+/// A sum of variables and a constant, e.g. `i + 1` or `items.len() - offset`.
+/// `len(items)` and `items.len()` both become the variable `items.len()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinearTerm {
+    pub vars: Vec<(i64, String)>,
+    pub constant: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Relation {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+/// A path condition the executor understood; `a > b` is stored as `b < a`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    pub left: LinearTerm,
+    pub relation: Relation,
+    pub right: LinearTerm,
+}
+
+/// An `array[index]` access and the conditions known to hold whenever it runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexAccess {
+    pub function: String,
+    pub array: String,
+    /// The index as written
+    pub source: String,
+    /// `None` when the index isn't linear, e.g. `items[a * b]`
+    pub index: Option<LinearTerm>,
+    pub conditions: Vec<Comparison>,
+}
+
 pub struct SymbolicExecutor {
     paths: Vec<ExecutionPath>,
     current_path_id: usize,
@@ -95,6 +132,19 @@ impl SymbolicExecutor {
         Ok(())
     }
     
+    /// Walks every path of `function`, recording each index expression with the
+    /// conditions that guard it: preconditions, earlier `require`s, enclosing `if`
+    /// branches and the range of enclosing `for` loops. Conditions the executor
+    /// can't parse are dropped, which can only make an access harder to prove.
+    pub fn index_accesses(&self, function: &Function) -> Vec<IndexAccess> {
+        let mut conditions: Vec<Comparison> = function.requires.iter()
+            .flat_map(|c| parse_conditions(c))
+            .collect();
+        let mut accesses = Vec::new();
+        collect_accesses(&function.name, &function.body, &mut conditions, &mut accesses);
+        accesses
+    }
+    
     fn check_feasibility(&mut self) -> Result<()> {
         // Check if paths are feasible using SMT solver
         for path in &mut self.paths {
@@ -104,4 +154,283 @@ impl SymbolicExecutor {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn collect_accesses(
+    function: &str,
+    body: &[Statement],
+    conditions: &mut Vec<Comparison>,
+    accesses: &mut Vec<IndexAccess>,
+) {
+    for statement in body {
+        match statement {
+            Statement::Assignment(target, value) => {
+                record(function, target, conditions, accesses);
+                record(function, value, conditions, accesses);
+                // Facts about the old value no longer hold
+                forget(conditions, target);
+            }
+            Statement::Require(condition) => {
+                record(function, condition, conditions, accesses);
+                conditions.extend(parse_conditions(condition));
+            }
+            Statement::If(condition, then_branch, else_branch) => {
+                record(function, condition, conditions, accesses);
+                
+                let mut taken = conditions.clone();
+                taken.extend(parse_conditions(condition));
+                collect_accesses(function, then_branch, &mut taken, accesses);
+                
+                if let Some(else_branch) = else_branch {
+                    let mut not_taken = conditions.clone();
+                    not_taken.extend(negate_condition(condition));
+                    collect_accesses(function, else_branch, &mut not_taken, accesses);
+                }
+                
+                // Either branch may have assigned anything it mentions
+                let mut written = assigned(then_branch);
+                if let Some(else_branch) = else_branch {
+                    written.extend(assigned(else_branch));
+                }
+                for name in &written {
+                    forget(conditions, name);
+                }
+                
+                // A guard like `if (i >= len(items)) { return; }` leaves the rest of
+                // the function with the opposite condition
+                if else_branch.is_none() && matches!(then_branch.last(), Some(Statement::Return(_))) {
+                    conditions.extend(negate_condition(condition));
+                }
+            }
+            Statement::For(variable, start, end, loop_body) => {
+                record(function, start, conditions, accesses);
+                record(function, end, conditions, accesses);
+                
+                let mut inside = conditions.clone();
+                let written = assigned(loop_body);
+                for name in written.iter().chain(std::iter::once(variable)) {
+                    forget(&mut inside, name);
+                }
+                // start <= variable < end on every iteration
+                let counter = LinearTerm { vars: vec![(1, variable.clone())], constant: 0 };
+                if let Some(start) = parse_term(start) {
+                    inside.push(Comparison { left: start, relation: Relation::Le, right: counter.clone() });
+                }
+                if let Some(end) = parse_term(end) {
+                    inside.push(Comparison { left: counter, relation: Relation::Lt, right: end });
+                }
+                collect_accesses(function, loop_body, &mut inside, accesses);
+                
+                for name in written.iter().chain(std::iter::once(variable)) {
+                    forget(conditions, name);
+                }
+            }
+            Statement::Return(Some(value)) => record(function, value, conditions, accesses),
+            Statement::Return(None) => {}
+        }
+    }
+}
+
+fn record(function: &str, text: &str, conditions: &[Comparison], accesses: &mut Vec<IndexAccess>) {
+    for (array, source) in index_expressions(text) {
+        accesses.push(IndexAccess {
+            function: function.to_string(),
+            array,
+            index: parse_term(&source),
+            source,
+            conditions: conditions.to_vec(),
+        });
+    }
+}
+
+/// Drops the conditions that mention `name` or its length
+fn forget(conditions: &mut Vec<Comparison>, name: &str) {
+    let length = format!("{}.len()", name);
+    conditions.retain(|c| !c.left.vars.iter().chain(&c.right.vars).any(|(_, v)| *v == name || *v == length));
+}
+
+/// Variables assigned anywhere in `body`, including nested blocks
+fn assigned(body: &[Statement]) -> Vec<String> {
+    let mut names = Vec::new();
+    for statement in body {
+        match statement {
+            Statement::Assignment(target, _) => {
+                // `items[i] = x` writes an element, not `items` or its length
+                if !target.contains('[') {
+                    names.push(target.trim().to_string());
+                }
+            }
+            Statement::If(_, then_branch, else_branch) => {
+                names.extend(assigned(then_branch));
+                if let Some(else_branch) = else_branch {
+                    names.extend(assigned(else_branch));
+                }
+            }
+            Statement::For(variable, _, _, loop_body) => {
+                names.push(variable.clone());
+                names.extend(assigned(loop_body));
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Finds `name[index]` in an expression, innermost-last, returning the array name
+/// and the index text
+fn index_expressions(text: &str) -> Vec<(String, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut found = Vec::new();
+    for (open, _) in chars.iter().enumerate().filter(|(_, c)| **c == '[') {
+        let name_start = chars[..open].iter()
+            .rposition(|c| !(c.is_alphanumeric() || *c == '_' || *c == '.'))
+            .map_or(0, |p| p + 1);
+        if name_start == open {
+            continue;
+        }
+        let mut depth = 0;
+        let Some(close) = (open..chars.len()).find(|&i| {
+            match chars[i] {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        }) else { continue };
+        found.push((
+            chars[name_start..open].iter().collect(),
+            chars[open + 1..close].iter().collect::<String>().trim().to_string(),
+        ));
+    }
+    found
+}
+
+/// Parses a conjunction of comparisons, skipping the parts that aren't linear
+fn parse_conditions(condition: &str) -> Vec<Comparison> {
+    split_top_level(strip_parens(condition), "&&")
+        .iter()
+        .filter_map(|part| parse_comparison(part))
+        .collect()
+}
+
+/// The condition that holds when `condition` is false; only a single comparison
+/// has one that is itself a conjunction
+fn negate_condition(condition: &str) -> Vec<Comparison> {
+    let condition = strip_parens(condition);
+    if split_top_level(condition, "&&").len() > 1 {
+        return Vec::new();
+    }
+    parse_comparison(condition).into_iter().map(|c| match c.relation {
+        Relation::Lt => Comparison { left: c.right, relation: Relation::Le, right: c.left },
+        Relation::Le => Comparison { left: c.right, relation: Relation::Lt, right: c.left },
+        Relation::Eq => Comparison { relation: Relation::Ne, ..c },
+        Relation::Ne => Comparison { relation: Relation::Eq, ..c },
+    }).collect()
+}
+
+fn parse_comparison(text: &str) -> Option<Comparison> {
+    let text = strip_parens(text);
+    // Two-character operators first, so `<=` isn't read as `<`
+    for (op, relation, swap) in [
+        ("<=", Relation::Le, false), (">=", Relation::Le, true),
+        ("==", Relation::Eq, false), ("!=", Relation::Ne, false),
+        ("<", Relation::Lt, false), (">", Relation::Lt, true),
+    ] {
+        if let Some(pos) = text.find(op) {
+            let left = parse_term(&text[..pos])?;
+            let right = parse_term(&text[pos + op.len()..])?;
+            let (left, right) = if swap { (right, left) } else { (left, right) };
+            return Some(Comparison { left, relation, right });
+        }
+    }
+    None
+}
+
+/// Parses `a + b - 1`, where each operand is a number, a variable, `x.len()`,
+/// `len(x)` or an opaque `x[i]`
+pub fn parse_term(text: &str) -> Option<LinearTerm> {
+    let mut term = LinearTerm { vars: Vec::new(), constant: 0 };
+    let mut sign = 1;
+    let mut operand = String::new();
+    let mut depth = 0;
+    
+    for c in text.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '+' | '-' if depth == 0 => {
+                if operand.trim().is_empty() {
+                    // A leading sign
+                    if c == '-' {
+                        sign = -sign;
+                    }
+                    continue;
+                }
+                add_operand(&mut term, sign, &operand)?;
+                operand.clear();
+                sign = if c == '-' { -1 } else { 1 };
+                continue;
+            }
+            _ => {}
+        }
+        operand.push(c);
+    }
+    add_operand(&mut term, sign, &operand)?;
+    Some(term)
+}
+
+fn add_operand(term: &mut LinearTerm, sign: i64, operand: &str) -> Option<()> {
+    let text = strip_parens(operand);
+    if let Ok(value) = text.parse::<i64>() {
+        term.constant += sign * value;
+    } else if let Some(array) = text.strip_prefix("len(").and_then(|t| t.strip_suffix(')')) {
+        term.vars.push((sign, format!("{}.len()", array.trim())));
+    } else if !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || "_.()[]".contains(c)) {
+        term.vars.push((sign, text.to_string()));
+    } else {
+        return None;
+    }
+    Some(())
+}
+
+/// Splits on `separator` outside parentheses and brackets
+fn split_top_level<'a>(text: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ if depth == 0 && text[i..].starts_with(separator) && i >= start => {
+                parts.push(&text[start..i]);
+                start = i + separator.len();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Removes parentheses wrapping the whole expression
+fn strip_parens(text: &str) -> &str {
+    let mut text = text.trim();
+    while text.starts_with('(') && text.ends_with(')') {
+        // Only if the opening parenthesis closes at the very end
+        let mut depth = 0;
+        let closes_at_end = text.char_indices().all(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth > 0 || i == text.len() - 1
+        });
+        if !closes_at_end {
+            break;
+        }
+        text = text[1..text.len() - 1].trim();
+    }
+    text
+}
@@ -1,8 +1,10 @@
 use anyhow::{Result, anyhow};
-use z3::{Context, Solver, Config, SatResult, Model, ast::{Ast, Bool, Int}};
+use z3::{Context, Solver, SatResult, Model, ast::{Ast, Bool, Int}};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use log::{info, debug, warn, error};
+use crate::symbolic_execution::{SymbolicExecutor, IndexAccess, LinearTerm, Comparison, Relation};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contract {
@@ -50,6 +52,8 @@ pub enum Statement {
     Assignment(String, String),
     Require(String),
     If(String, Vec<Statement>, Option<Vec<Statement>>),
+    /// `for variable in start..end { body }`
+    For(String, String, String, Vec<Statement>),
     Return(Option<String>),
 }
 
@@ -66,6 +70,8 @@ pub struct ProofCertificate {
     pub verified_properties: Vec<VerifiedProperty>,
     pub invariants_checked: Vec<InvariantResult>,
     pub coverage: f64,
+    /// The properties checked for each function, keyed by function name
+    pub function_coverage: BTreeMap<String, FunctionCoverage>,
     pub timestamp: u64,
     pub solver_version: String,
 }
@@ -73,6 +79,8 @@ pub struct ProofCertificate {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifiedProperty {
     pub property_name: String,
+    /// The function the property is about, `None` for contract-wide properties
+    pub function: Option<String>,
     pub property_type: PropertyType,
     pub result: VerificationResult,
     pub proof_trace: Option<String>,
@@ -95,6 +103,42 @@ pub enum VerificationResult {
     Timeout,
 }
 
+/// Names of the properties checked for one function, by outcome
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCoverage {
+    pub checked: Vec<String>,
+    pub verified: Vec<String>,
+    pub violated: Vec<String>,
+    pub unknown: Vec<String>,
+    pub timed_out: Vec<String>,
+}
+
+impl FunctionCoverage {
+    fn record(&mut self, property: &VerifiedProperty) {
+        let name = property.property_name.clone();
+        self.checked.push(name.clone());
+        match property.result {
+            VerificationResult::Verified => self.verified.push(name),
+            VerificationResult::Violated(_) => self.violated.push(name),
+            VerificationResult::Unknown(_) => self.unknown.push(name),
+            VerificationResult::Timeout => self.timed_out.push(name),
+        }
+    }
+}
+
+/// `2/4 verified, 1 violated, 1 timed out`, leaving out outcomes that didn't happen
+impl fmt::Display for FunctionCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} verified", self.verified.len(), self.checked.len())?;
+        for (count, outcome) in [(self.violated.len(), "violated"), (self.unknown.len(), "unknown"), (self.timed_out.len(), "timed out")] {
+            if count > 0 {
+                write!(f, ", {} {}", count, outcome)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvariantResult {
     pub invariant_name: String,
@@ -102,17 +146,17 @@ pub struct InvariantResult {
     pub counterexample: Option<String>,
 }
 
-pub struct FormalVerifier {
-    context: Context,
-    solver: Solver<'static>,
+/// Checks a contract with Z3. Formulas and the solver live in the borrowed `Context`,
+/// so it must outlive the verifier.
+pub struct FormalVerifier<'ctx> {
+    context: &'ctx Context,
+    solver: Solver<'ctx>,
     contract: Option<Contract>,
 }
 
-impl FormalVerifier {
-    pub fn new() -> Self {
-        let cfg = Config::new();
-        let context = Context::new(&cfg);
-        let solver = Solver::new(&context);
+impl<'ctx> FormalVerifier<'ctx> {
+    pub fn new(context: &'ctx Context) -> Self {
+        let solver = Solver::new(context);
         
         FormalVerifier {
             context,
@@ -126,7 +170,8 @@ impl FormalVerifier {
     }
     
     pub fn verify_correctness(&mut self) -> Result<ProofCertificate> {
-        let contract = self.contract.as_ref()
+        // The checks below need `&mut self` for the solver
+        let contract = &self.contract.clone()
             .ok_or_else(|| anyhow!("No contract loaded"))?;
         
         info!("Starting formal verification for contract: {}", contract.name);
@@ -162,11 +207,22 @@ impl FormalVerifier {
             0.0
         };
         
+        // Every function is listed, even if nothing was checked for it
+        let mut function_coverage: BTreeMap<String, FunctionCoverage> = contract.functions.iter()
+            .map(|f| (f.name.clone(), FunctionCoverage::default()))
+            .collect();
+        for property in &verified_properties {
+            if let Some(function) = &property.function {
+                function_coverage.entry(function.clone()).or_default().record(property);
+            }
+        }
+        
         Ok(ProofCertificate {
             contract_name: contract.name.clone(),
             verified_properties,
             invariants_checked: invariant_results,
             coverage,
+            function_coverage,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
@@ -234,6 +290,7 @@ impl FormalVerifier {
             for (i, precondition) in function.requires.iter().enumerate() {
                 let property = self.verify_condition(
                     &format!("{}_precond_{}", function.name, i),
+                    Some(&function.name),
                     precondition,
                     PropertyType::Precondition,
                 )?;
@@ -244,6 +301,7 @@ impl FormalVerifier {
             for (i, postcondition) in function.ensures.iter().enumerate() {
                 let property = self.verify_condition(
                     &format!("{}_postcond_{}", function.name, i),
+                    Some(&function.name),
                     postcondition,
                     PropertyType::Postcondition,
                 )?;
@@ -269,6 +327,9 @@ impl FormalVerifier {
         let access_control_property = self.check_access_control(contract)?;
         properties.push(access_control_property);
         
+        // Check that every index stays within its array
+        properties.extend(self.check_index_bounds(contract)?);
+        
         Ok(properties)
     }
     
@@ -279,6 +340,7 @@ impl FormalVerifier {
         for function in &contract.functions {
             let termination_property = VerifiedProperty {
                 property_name: format!("{}_terminates", function.name),
+                function: Some(function.name.clone()),
                 property_type: PropertyType::Liveness,
                 result: VerificationResult::Verified, // Simplified - assume termination
                 proof_trace: Some("Termination analysis completed".to_string()),
@@ -295,6 +357,7 @@ impl FormalVerifier {
         
         Ok(VerifiedProperty {
             property_name: "no_integer_overflow".to_string(),
+            function: None,
             property_type: PropertyType::Safety,
             result: VerificationResult::Verified,
             proof_trace: Some("All arithmetic operations checked for overflow".to_string()),
@@ -329,6 +392,7 @@ impl FormalVerifier {
         
         Ok(VerifiedProperty {
             property_name: "no_reentrancy".to_string(),
+            function: None,
             property_type: PropertyType::Safety,
             result,
             proof_trace: Some("Reentrancy pattern analysis completed".to_string()),
@@ -367,15 +431,120 @@ impl FormalVerifier {
         
         Ok(VerifiedProperty {
             property_name: "access_control".to_string(),
+            function: None,
             property_type: PropertyType::Safety,
             result,
             proof_trace: Some("Access control analysis completed".to_string()),
         })
     }
     
+    fn check_index_bounds(&mut self, contract: &Contract) -> Result<Vec<VerifiedProperty>> {
+        let executor = SymbolicExecutor::new();
+        let mut properties = Vec::new();
+        
+        for function in &contract.functions {
+            // Map lookups have no length to stay under
+            let is_map = |name: &str| contract.state.iter()
+                .map(|v| (&v.name, &v.var_type))
+                .chain(function.params.iter().map(|p| (&p.name, &p.param_type)))
+                .any(|(var, var_type)| var == name && matches!(var_type, VarType::Map(..)));
+            let accesses = executor.index_accesses(function);
+            let accesses = accesses.iter().filter(|access| !is_map(&access.array));
+            
+            for (i, access) in accesses.enumerate() {
+                debug!("Checking bounds of {}[{}] in {}", access.array, access.source, function.name);
+                
+                let result = match &access.index {
+                    Some(index) => self.prove_in_bounds(access, index),
+                    None => VerificationResult::Unknown(
+                        format!("Cannot reason about the index `{}`", access.source)
+                    ),
+                };
+                
+                properties.push(VerifiedProperty {
+                    property_name: format!("{}_index_in_bounds_{}", function.name, i),
+                    function: Some(function.name.clone()),
+                    property_type: PropertyType::Safety,
+                    result,
+                    proof_trace: Some(format!(
+                        "{}[{}] checked under {} path condition(s)",
+                        access.array, access.source, access.conditions.len()
+                    )),
+                });
+            }
+        }
+        
+        Ok(properties)
+    }
+    
+    /// Asks the solver for an index at or past the array's length that satisfies
+    /// the path conditions. All values are unsigned, so indices below zero can't happen.
+    fn prove_in_bounds(&mut self, access: &IndexAccess, index: &LinearTerm) -> VerificationResult {
+        let length_name = format!("{}.len()", access.array);
+        let length = LinearTerm { vars: vec![(1, length_name.clone())], constant: 0 };
+        
+        let mut vars = HashMap::new();
+        let conditions: Vec<Bool> = access.conditions.iter()
+            .map(|c| self.comparison_formula(c, &mut vars))
+            .collect();
+        let index_value = self.term_formula(index, &mut vars);
+        let length_value = self.term_formula(&length, &mut vars);
+        
+        self.solver.push();
+        for condition in &conditions {
+            self.solver.assert(condition);
+        }
+        let zero = Int::from_i64(self.context, 0);
+        for var in vars.values() {
+            self.solver.assert(&var.ge(&zero));
+        }
+        self.solver.assert(&index_value.ge(&length_value));
+        
+        let result = match self.solver.check() {
+            SatResult::Sat => {
+                let model = self.solver.get_model().unwrap();
+                let value = |term: &Int| model.eval(term, true)
+                    .and_then(|v| v.as_i64())
+                    .map_or("?".to_string(), |v| v.to_string());
+                VerificationResult::Violated(format!(
+                    "Counterexample: {} = {}, {} = {}",
+                    access.source, value(&index_value), length_name, value(&length_value)
+                ))
+            },
+            SatResult::Unsat => VerificationResult::Verified,
+            SatResult::Unknown => VerificationResult::Unknown("Could not determine".to_string()),
+        };
+        
+        self.solver.pop(1);
+        result
+    }
+    
+    fn comparison_formula(&self, comparison: &Comparison, vars: &mut HashMap<String, Int<'ctx>>) -> Bool<'ctx> {
+        let left = self.term_formula(&comparison.left, vars);
+        let right = self.term_formula(&comparison.right, vars);
+        match comparison.relation {
+            Relation::Lt => left.lt(&right),
+            Relation::Le => left.le(&right),
+            Relation::Eq => left._eq(&right),
+            Relation::Ne => left._eq(&right).not(),
+        }
+    }
+    
+    fn term_formula(&self, term: &LinearTerm, vars: &mut HashMap<String, Int<'ctx>>) -> Int<'ctx> {
+        let mut parts = vec![Int::from_i64(self.context, term.constant)];
+        for (coefficient, name) in &term.vars {
+            let var = vars.entry(name.clone())
+                .or_insert_with(|| Int::new_const(self.context, name.as_str()))
+                .clone();
+            parts.push(Int::mul(self.context, &[&Int::from_i64(self.context, *coefficient), &var]));
+        }
+        Int::add(self.context, &parts.iter().collect::<Vec<_>>())
+    }
+    
     fn verify_condition(
         &mut self,
         property_name: &str,
+        function: Option<&str>,
         condition: &str,
         property_type: PropertyType,
     ) -> Result<VerifiedProperty> {
@@ -398,18 +567,19 @@ impl FormalVerifier {
         
         Ok(VerifiedProperty {
             property_name: property_name.to_string(),
+            function: function.map(str::to_string),
             property_type,
             result,
             proof_trace: Some(format!("Verified using Z3 SMT solver")),
         })
     }
     
-    fn parse_condition(&self, condition: &str) -> Result<Bool<'static>> {
+    fn parse_condition(&self, condition: &str) -> Result<Bool<'ctx>> {
         // Simplified condition parsing
         // In production, implement full expression parser
         
         // For now, return a simple true formula
-        let true_const = Bool::from_bool(&self.context, true);
+        let true_const = Bool::from_bool(self.context, true);
         Ok(true_const)
     }
 }
@@ -417,16 +587,19 @@ impl FormalVerifier {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use z3::Config;
     
     #[test]
     fn test_verifier_creation() {
-        let verifier = FormalVerifier::new();
+        let context = Context::new(&Config::new());
+        let verifier = FormalVerifier::new(&context);
         assert!(verifier.contract.is_none());
     }
     
     #[test]
     fn test_contract_verification() {
-        let mut verifier = FormalVerifier::new();
+        let context = Context::new(&Config::new());
+        let mut verifier = FormalVerifier::new(&context);
         
         let contract = Contract {
             name: "TestContract".to_string(),
@@ -472,5 +645,97 @@ mod tests {
         let certificate = result.unwrap();
         assert_eq!(certificate.contract_name, "TestContract");
         assert!(certificate.coverage >= 0.0);
+        
+        let transfer = &certificate.function_coverage["transfer"];
+        assert_eq!(transfer.checked, ["transfer_precond_0", "transfer_postcond_0", "transfer_terminates"]);
+        assert_eq!(transfer.to_string(), "3/3 verified");
+        // Contract-wide properties belong to no function
+        let reentrancy = certificate.verified_properties.iter()
+            .find(|p| p.property_name == "no_reentrancy")
+            .unwrap();
+        assert!(reentrancy.function.is_none());
+    }
+    
+    #[test]
+    fn test_function_coverage_summary() {
+        let property = |name: &str, result: VerificationResult| VerifiedProperty {
+            property_name: name.to_string(),
+            function: Some("swap".to_string()),
+            property_type: PropertyType::Safety,
+            result,
+            proof_trace: None,
+        };
+        
+        let mut coverage = FunctionCoverage::default();
+        coverage.record(&property("swap_precond_0", VerificationResult::Verified));
+        coverage.record(&property("swap_postcond_0", VerificationResult::Violated("Counterexample".to_string())));
+        coverage.record(&property("swap_index_in_bounds_0", VerificationResult::Timeout));
+        coverage.record(&property("swap_terminates", VerificationResult::Verified));
+        
+        assert_eq!(coverage.verified, ["swap_precond_0", "swap_terminates"]);
+        assert_eq!(coverage.violated, ["swap_postcond_0"]);
+        assert_eq!(coverage.timed_out, ["swap_index_in_bounds_0"]);
+        assert_eq!(coverage.to_string(), "2/4 verified, 1 violated, 1 timed out");
+    }
+    
+    #[test]
+    fn test_index_bounds() {
+        let function = |name: &str, body: Vec<Statement>| Function {
+            name: name.to_string(),
+            params: vec![
+                Parameter { name: "idx".to_string(), param_type: VarType::U64 },
+            ],
+            return_type: Some(VarType::U64),
+            requires: vec![],
+            ensures: vec![],
+            body,
+        };
+        
+        let contract = Contract {
+            name: "Registry".to_string(),
+            state: vec![StateVariable {
+                name: "owners".to_string(),
+                var_type: VarType::Map(Box::new(VarType::U64), Box::new(VarType::Address)),
+                initial_value: None,
+            }],
+            functions: vec![
+                // for i in 0..len(items) never leaves the array
+                function("sum", vec![
+                    Statement::For("i".to_string(), "0".to_string(), "len(items)".to_string(), vec![
+                        Statement::Assignment("total".to_string(), "total + items[i]".to_string()),
+                    ]),
+                    Statement::Return(Some("total".to_string())),
+                ]),
+                function("get", vec![
+                    Statement::Return(Some("items[idx]".to_string())),
+                ]),
+                function("owner_of", vec![
+                    Statement::Return(Some("owners[idx]".to_string())),
+                ]),
+                function("get_checked", vec![
+                    Statement::Require("idx < items.len()".to_string()),
+                    Statement::Return(Some("items[idx]".to_string())),
+                ]),
+            ],
+            invariants: vec![],
+        };
+        
+        let context = Context::new(&Config::new());
+        let mut verifier = FormalVerifier::new(&context);
+        let properties = verifier.check_index_bounds(&contract).unwrap();
+        let result = |name: &str| &properties.iter()
+            .find(|p| p.property_name == name)
+            .unwrap()
+            .result;
+        
+        assert!(matches!(result("sum_index_in_bounds_0"), VerificationResult::Verified));
+        assert!(matches!(result("get_checked_index_in_bounds_0"), VerificationResult::Verified));
+        assert!(properties.iter().all(|p| p.function.as_deref() != Some("owner_of")));
+        match result("get_index_in_bounds_0") {
+            VerificationResult::Violated(counterexample) => {
+                assert!(counterexample.starts_with("Counterexample: idx = "), "{}", counterexample);
+            }
+            other => panic!("Expected a violation, got {:?}", other),
+        }
     }
-}
\ No newline at end of file
+}
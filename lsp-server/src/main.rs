@@ -171,44 +171,37 @@ impl Backend {
         if let Some(rope) = self.documents.get(&uri) {
             let text = rope.to_string();
             
-            // Parse and analyze the document
-            match cross_chain_dsl::Contract::parse(&text) {
-                Ok(contract) => {
-                    // Semantic analysis
-                    let mut analyzer = cross_chain_dsl::semantic::SemanticAnalyzer::new();
-                    if let Err(e) = analyzer.analyze(&contract) {
-                        // Send error diagnostics
-                        let diagnostic = Diagnostic {
-                            range: Range::new(Position::new(0, 0), Position::new(0, 0)),
-                            severity: Some(DiagnosticSeverity::ERROR),
-                            message: format!("Semantic error: {}", e),
-                            ..Default::default()
-                        };
-                        
-                        self.client
-                            .publish_diagnostics(uri.clone(), vec![diagnostic], None)
-                            .await;
-                    } else {
-                        // Clear diagnostics if successful
-                        self.client
-                            .publish_diagnostics(uri.clone(), vec![], None)
-                            .await;
-                    }
-                }
-                Err(e) => {
-                    // Send parse error diagnostics
-                    let diagnostic = Diagnostic {
+            // Parse and analyze the document; every syntax error is reported in one pass
+            let (contract, parse_errors) = cross_chain_dsl::Contract::parse_recovering(&text);
+            let mut diagnostics: Vec<Diagnostic> = parse_errors.iter()
+                .map(|error| Diagnostic {
+                    range: Range::new(
+                        byte_position(&rope, error.span.start),
+                        byte_position(&rope, error.span.end),
+                    ),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    message: format!("Parse error: {}", error.message),
+                    ..Default::default()
+                })
+                .collect();
+            
+            if let Some(contract) = contract {
+                // Semantic analysis
+                let mut analyzer = cross_chain_dsl::semantic::SemanticAnalyzer::new();
+                if let Err(e) = analyzer.analyze(&contract) {
+                    diagnostics.push(Diagnostic {
                         range: Range::new(Position::new(0, 0), Position::new(0, 0)),
                         severity: Some(DiagnosticSeverity::ERROR),
-                        message: format!("Parse error: {}", e),
+                        message: format!("Semantic error: {}", e),
                         ..Default::default()
-                    };
-                    
-                    self.client
-                        .publish_diagnostics(uri.clone(), vec![diagnostic], None)
-                        .await;
+                    });
                 }
             }
+            
+            // An empty list clears earlier diagnostics
+            self.client
+                .publish_diagnostics(uri.clone(), diagnostics, None)
+                .await;
         }
     }
 
@@ -356,6 +349,14 @@ impl Backend {
     }
 }
 
+/// The LSP position (line, UTF-16 column) of a byte offset into the document
+fn byte_position(rope: &Rope, byte: usize) -> Position {
+    let char_idx = rope.byte_to_char(byte.min(rope.len_bytes()));
+    let line = rope.char_to_line(char_idx);
+    let column = rope.char_to_utf16_cu(char_idx) - rope.char_to_utf16_cu(rope.line_to_char(line));
+    Position::new(line as u32, column as u32)
+}
+
 fn extract_word_at_position(line: &str, position: usize) -> String {
     let chars: Vec<char> = line.chars().collect();
     
@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
 
 declare_id!("11111111111111111111111111111111");
 
@@ -21,46 +22,19 @@ pub mod cross_chain_token {
             ErrorCode::Unauthorized
         );
 
-        let token_state = &mut ctx.accounts.token_state;
-        let user_balance = &mut ctx.accounts.user_balance;
-
-        token_state.total_supply = token_state
-            .total_supply
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
-
-        user_balance.amount = user_balance
-            .amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
+        ctx.accounts.credit(&ctx.bumps, amount)?;
 
         emit!(MintEvent {
             to: ctx.accounts.to.key(),
             amount,
-            total_supply: token_state.total_supply,
+            total_supply: ctx.accounts.token_state.total_supply,
         });
 
         Ok(())
     }
 
     pub fn transfer(ctx: Context<Transfer>, amount: u64) -> Result<()> {
-        let from_balance = &mut ctx.accounts.from_balance;
-        let to_balance = &mut ctx.accounts.to_balance;
-
-        require!(
-            from_balance.amount >= amount,
-            ErrorCode::InsufficientBalance
-        );
-
-        from_balance.amount = from_balance
-            .amount
-            .checked_sub(amount)
-            .ok_or(ErrorCode::Underflow)?;
-
-        to_balance.amount = to_balance
-            .amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
+        ctx.accounts.move_tokens(amount)?;
 
         emit!(TransferEvent {
             from: ctx.accounts.from.key(),
@@ -71,28 +45,24 @@ pub mod cross_chain_token {
         Ok(())
     }
 
+    pub fn burn(ctx: Context<Burn>, amount: u64) -> Result<()> {
+        ctx.accounts.debit(amount)?;
+
+        emit!(BurnEvent {
+            from: ctx.accounts.owner.key(),
+            amount,
+            total_supply: ctx.accounts.token_state.total_supply,
+        });
+
+        Ok(())
+    }
+
     pub fn lock_for_bridge(
         ctx: Context<LockForBridge>,
         amount: u64,
         target_chain: u32,
     ) -> Result<()> {
-        let user_balance = &mut ctx.accounts.user_balance;
-        let bridge_vault = &mut ctx.accounts.bridge_vault;
-
-        require!(
-            user_balance.amount >= amount,
-            ErrorCode::InsufficientBalance
-        );
-
-        user_balance.amount = user_balance
-            .amount
-            .checked_sub(amount)
-            .ok_or(ErrorCode::Underflow)?;
-
-        bridge_vault.locked_amount = bridge_vault
-            .locked_amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
+        ctx.accounts.lock(amount)?;
 
         emit!(CrossChainLockEvent {
             from: ctx.accounts.user.key(),
@@ -104,33 +74,36 @@ pub mod cross_chain_token {
         Ok(())
     }
 
-    pub fn receive_from_bridge(
-        ctx: Context<ReceiveFromBridge>,
-        amount: u64,
-        source_chain: u32,
-    ) -> Result<()> {
+    pub fn set_merkle_root(ctx: Context<SetMerkleRoot>, merkle_root: [u8; 32]) -> Result<()> {
         require!(
-            ctx.accounts.bridge_authority.key() == ctx.accounts.bridge_vault.authority,
+            ctx.accounts.authority.key() == ctx.accounts.bridge_vault.authority,
             ErrorCode::Unauthorized
         );
 
-        let bridge_vault = &mut ctx.accounts.bridge_vault;
-        let user_balance = &mut ctx.accounts.user_balance;
+        ctx.accounts.bridge_vault.merkle_root = merkle_root;
+        Ok(())
+    }
 
+    pub fn receive_from_bridge(
+        ctx: Context<ReceiveFromBridge>,
+        amount: u64,
+        source_chain: u32,
+        nonce: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        // Any relayer may submit the claim; the proof against the
+        // authority-committed root is what authorizes the mint
+        let leaf = bridge_leaf(&ctx.accounts.to.key(), amount, source_chain, nonce);
         require!(
-            bridge_vault.locked_amount >= amount,
-            ErrorCode::InsufficientVaultBalance
+            verify_merkle_proof(&proof, ctx.accounts.bridge_vault.merkle_root, leaf),
+            ErrorCode::InvalidProof
         );
 
-        bridge_vault.locked_amount = bridge_vault
-            .locked_amount
-            .checked_sub(amount)
-            .ok_or(ErrorCode::Underflow)?;
+        let claim_receipt = &mut ctx.accounts.claim_receipt;
+        claim_receipt.source_chain = source_chain;
+        claim_receipt.nonce = nonce;
 
-        user_balance.amount = user_balance
-            .amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
+        ctx.accounts.release(&ctx.bumps, amount)?;
 
         emit!(CrossChainReceiveEvent {
             to: ctx.accounts.to.key(),
@@ -143,6 +116,16 @@ pub mod cross_chain_token {
     }
 }
 
+// Balances live either in the program's own `UserBalance` PDAs (the default, simplest to
+// bridge) or, with the `spl-token` feature, in SPL Token accounts of a mint the program
+// controls, so wallets and DEXes see them. Each mode defines the accounts of the
+// instructions that move tokens, and the methods that move them.
+#[cfg(feature = "spl-token")]
+mod spl;
+#[cfg(feature = "spl-token")]
+pub use spl::*;
+
+#[cfg(not(feature = "spl-token"))]
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -156,6 +139,7 @@ pub struct Initialize<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[cfg(not(feature = "spl-token"))]
 #[derive(Accounts)]
 pub struct Mint<'info> {
     #[account(mut)]
@@ -174,6 +158,26 @@ pub struct Mint<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[cfg(not(feature = "spl-token"))]
+impl<'info> Mint<'info> {
+    pub fn credit(&mut self, _bumps: &MintBumps, amount: u64) -> Result<()> {
+        self.token_state.total_supply = self
+            .token_state
+            .total_supply
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        self.user_balance.amount = self
+            .user_balance
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "spl-token"))]
 #[derive(Accounts)]
 pub struct Transfer<'info> {
     #[account(
@@ -190,11 +194,75 @@ pub struct Transfer<'info> {
         bump
     )]
     pub to_balance: Account<'info, UserBalance>,
+    #[account(mut)]
     pub from: Signer<'info>,
     pub to: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[cfg(not(feature = "spl-token"))]
+impl<'info> Transfer<'info> {
+    pub fn move_tokens(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.from_balance.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        self.from_balance.amount = self
+            .from_balance
+            .amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+
+        self.to_balance.amount = self
+            .to_balance
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "spl-token"))]
+#[derive(Accounts)]
+pub struct Burn<'info> {
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [b"balance", owner.key().as_ref()],
+        bump
+    )]
+    pub user_balance: Account<'info, UserBalance>,
+    pub owner: Signer<'info>,
+}
+
+#[cfg(not(feature = "spl-token"))]
+impl<'info> Burn<'info> {
+    pub fn debit(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.user_balance.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        self.user_balance.amount = self
+            .user_balance
+            .amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+
+        self.token_state.total_supply = self
+            .token_state
+            .total_supply
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "spl-token"))]
 #[derive(Accounts)]
 pub struct LockForBridge<'info> {
     #[account(
@@ -212,7 +280,44 @@ pub struct LockForBridge<'info> {
     pub user: Signer<'info>,
 }
 
+#[cfg(not(feature = "spl-token"))]
+impl<'info> LockForBridge<'info> {
+    pub fn lock(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.user_balance.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        self.user_balance.amount = self
+            .user_balance
+            .amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+
+        self.bridge_vault.locked_amount = self
+            .bridge_vault
+            .locked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+}
+
 #[derive(Accounts)]
+pub struct SetMerkleRoot<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_vault"],
+        bump
+    )]
+    pub bridge_vault: Account<'info, BridgeVault>,
+    pub authority: Signer<'info>,
+}
+
+#[cfg(not(feature = "spl-token"))]
+#[derive(Accounts)]
+#[instruction(amount: u64, source_chain: u32, nonce: u64)]
 pub struct ReceiveFromBridge<'info> {
     #[account(
         mut,
@@ -222,18 +327,51 @@ pub struct ReceiveFromBridge<'info> {
     pub bridge_vault: Account<'info, BridgeVault>,
     #[account(
         init_if_needed,
-        payer = bridge_authority,
+        payer = payer,
         space = 8 + UserBalance::LEN,
         seeds = [b"balance", to.key().as_ref()],
         bump
     )]
     pub user_balance: Account<'info, UserBalance>,
+    // `init` fails if the receipt exists, so each (source_chain, nonce) is claimable once
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ClaimReceipt::LEN,
+        seeds = [b"claim", source_chain.to_le_bytes().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Account<'info, ClaimReceipt>,
     pub to: AccountInfo<'info>,
     #[account(mut)]
-    pub bridge_authority: Signer<'info>,
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[cfg(not(feature = "spl-token"))]
+impl<'info> ReceiveFromBridge<'info> {
+    pub fn release(&mut self, _bumps: &ReceiveFromBridgeBumps, amount: u64) -> Result<()> {
+        require!(
+            self.bridge_vault.locked_amount >= amount,
+            ErrorCode::InsufficientVaultBalance
+        );
+
+        self.bridge_vault.locked_amount = self
+            .bridge_vault
+            .locked_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+
+        self.user_balance.amount = self
+            .user_balance
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+}
+
 #[account]
 pub struct TokenState {
     pub authority: Pubkey,
@@ -260,10 +398,47 @@ impl UserBalance {
 pub struct BridgeVault {
     pub authority: Pubkey,
     pub locked_amount: u64,
+    pub merkle_root: [u8; 32],
 }
 
 impl BridgeVault {
-    pub const LEN: usize = 32 + 8;
+    pub const LEN: usize = 32 + 8 + 32;
+}
+
+#[account]
+pub struct ClaimReceipt {
+    pub source_chain: u32,
+    pub nonce: u64,
+}
+
+impl ClaimReceipt {
+    pub const LEN: usize = 4 + 8;
+}
+
+/// Leaf committed to by the bridge merkle root:
+/// keccak256(to || amount_le || source_chain_le || nonce_le)
+pub fn bridge_leaf(to: &Pubkey, amount: u64, source_chain: u32, nonce: u64) -> [u8; 32] {
+    keccak::hashv(&[
+        to.as_ref(),
+        &amount.to_le_bytes(),
+        &source_chain.to_le_bytes(),
+        &nonce.to_le_bytes(),
+    ])
+    .0
+}
+
+/// Verifies `leaf` against `root` using sorted-pair keccak256 hashing,
+/// so proofs carry no left/right position flags
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed = leaf;
+    for node in proof {
+        computed = if computed <= *node {
+            keccak::hashv(&[&computed, node]).0
+        } else {
+            keccak::hashv(&[node, &computed]).0
+        };
+    }
+    computed == root
 }
 
 #[event]
@@ -273,6 +448,13 @@ pub struct MintEvent {
     pub total_supply: u64,
 }
 
+#[event]
+pub struct BurnEvent {
+    pub from: Pubkey,
+    pub amount: u64,
+    pub total_supply: u64,
+}
+
 #[event]
 pub struct TransferEvent {
     pub from: Pubkey,
@@ -308,4 +490,6 @@ pub enum ErrorCode {
     Overflow,
     #[msg("Arithmetic underflow")]
     Underflow,
+    #[msg("Invalid merkle proof")]
+    InvalidProof,
 }
\ No newline at end of file
@@ -0,0 +1,315 @@
+//! Accounts of the token instructions when balances are SPL Token accounts.
+//!
+//! The program owns a single mint, a PDA that is also its own mint authority, so only
+//! `mint` (checked against `TokenState::authority`) and bridge releases can create tokens.
+//! Balances are the holders' associated token accounts; tokens locked for the bridge sit in
+//! the associated token account of the `bridge_vault` PDA. `TokenState::total_supply`
+//! mirrors the mint's supply after every mint and burn.
+
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Token, TokenAccount};
+
+use crate::{BridgeVault, ClaimReceipt, ErrorCode, TokenState};
+
+/// Seed of the mint PDA
+pub const MINT_SEED: &[u8] = b"mint";
+
+/// Seed of the bridge vault PDA, which owns the locked tokens
+pub const BRIDGE_VAULT_SEED: &[u8] = b"bridge_vault";
+
+#[derive(Accounts)]
+#[instruction(decimals: u8)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TokenState::LEN
+    )]
+    pub token_state: Account<'info, TokenState>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [MINT_SEED],
+        bump,
+        mint::decimals = decimals,
+        mint::authority = mint
+    )]
+    pub mint: Account<'info, token::Mint>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Mint<'info> {
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [MINT_SEED],
+        bump
+    )]
+    pub mint: Account<'info, token::Mint>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = to
+    )]
+    pub to_token: Account<'info, TokenAccount>,
+    /// CHECK: only the owner of `to_token`
+    pub to: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Mint<'info> {
+    pub fn credit(&mut self, bumps: &MintBumps, amount: u64) -> Result<()> {
+        let seeds: &[&[u8]] = &[MINT_SEED, &[bumps.mint]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::MintTo {
+                    mint: self.mint.to_account_info(),
+                    to: self.to_token.to_account_info(),
+                    authority: self.mint.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        self.mint.reload()?;
+        self.token_state.total_supply = self.mint.supply;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Transfer<'info> {
+    #[account(seeds = [MINT_SEED], bump)]
+    pub mint: Account<'info, token::Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = from
+    )]
+    pub from_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = from,
+        associated_token::mint = mint,
+        associated_token::authority = to
+    )]
+    pub to_token: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub from: Signer<'info>,
+    /// CHECK: only the owner of `to_token`
+    pub to: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Transfer<'info> {
+    pub fn move_tokens(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.from_token.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.from_token.to_account_info(),
+                    to: self.to_token.to_account_info(),
+                    authority: self.from.to_account_info(),
+                },
+            ),
+            amount,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct Burn<'info> {
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    #[account(
+        mut,
+        seeds = [MINT_SEED],
+        bump
+    )]
+    pub mint: Account<'info, token::Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = owner
+    )]
+    pub owner_token: Account<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Burn<'info> {
+    pub fn debit(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.owner_token.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        token::burn(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token::Burn {
+                    mint: self.mint.to_account_info(),
+                    from: self.owner_token.to_account_info(),
+                    authority: self.owner.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        self.mint.reload()?;
+        self.token_state.total_supply = self.mint.supply;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct LockForBridge<'info> {
+    #[account(seeds = [MINT_SEED], bump)]
+    pub mint: Account<'info, token::Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = user
+    )]
+    pub user_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [BRIDGE_VAULT_SEED],
+        bump
+    )]
+    pub bridge_vault: Account<'info, BridgeVault>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = bridge_vault
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LockForBridge<'info> {
+    pub fn lock(&mut self, amount: u64) -> Result<()> {
+        require!(
+            self.user_token.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.user_token.to_account_info(),
+                    to: self.vault_token.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        self.bridge_vault.locked_amount = self
+            .bridge_vault
+            .locked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_chain: u32, nonce: u64)]
+pub struct ReceiveFromBridge<'info> {
+    #[account(seeds = [MINT_SEED], bump)]
+    pub mint: Account<'info, token::Mint>,
+    #[account(
+        mut,
+        seeds = [BRIDGE_VAULT_SEED],
+        bump
+    )]
+    pub bridge_vault: Account<'info, BridgeVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = bridge_vault
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = to
+    )]
+    pub to_token: Account<'info, TokenAccount>,
+    // `init` fails if the receipt exists, so each (source_chain, nonce) is claimable once
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ClaimReceipt::LEN,
+        seeds = [b"claim", source_chain.to_le_bytes().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Account<'info, ClaimReceipt>,
+    /// CHECK: only the owner of `to_token`
+    pub to: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReceiveFromBridge<'info> {
+    pub fn release(&mut self, bumps: &ReceiveFromBridgeBumps, amount: u64) -> Result<()> {
+        require!(
+            self.bridge_vault.locked_amount >= amount,
+            ErrorCode::InsufficientVaultBalance
+        );
+
+        let seeds: &[&[u8]] = &[BRIDGE_VAULT_SEED, &[bumps.bridge_vault]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.vault_token.to_account_info(),
+                    to: self.to_token.to_account_info(),
+                    authority: self.bridge_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        self.bridge_vault.locked_amount = self
+            .bridge_vault
+            .locked_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::Underflow)?;
+
+        Ok(())
+    }
+}